@@ -0,0 +1,15 @@
+-- Quarantine for rows that failed validation (OHLC invariants, timestamp sanity,
+-- decimal parsing). The raw payload is kept verbatim so rows can be reviewed and
+-- reprocessed once the parser or the upstream data is fixed.
+CREATE TABLE quarantine (
+    id BIGSERIAL PRIMARY KEY,
+    source VARCHAR(32) NOT NULL,
+    symbol VARCHAR(20),
+    interval VARCHAR(10),
+    raw_payload TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    reprocessed_at TIMESTAMPTZ
+);
+
+CREATE INDEX quarantine_pending_idx ON quarantine (created_at) WHERE reprocessed_at IS NULL;
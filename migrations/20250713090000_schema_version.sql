@@ -0,0 +1,9 @@
+-- Records which schema version the database is at. Every migration that changes
+-- the schema inserts its own version here, and components compare the latest row
+-- against the version compiled into opentrade-core at startup.
+CREATE TABLE schema_version (
+    version BIGINT PRIMARY KEY,
+    applied_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO schema_version (version) VALUES (20250713090000);
@@ -0,0 +1,12 @@
+-- Dataset labels let a single database host multiple isolated datasets
+-- (e.g., prod vs research). Existing rows belong to the 'default' dataset.
+ALTER TABLE kline_data ADD COLUMN dataset VARCHAR(32) NOT NULL DEFAULT 'default';
+
+ALTER TABLE kline_data DROP CONSTRAINT unique_kline_data;
+ALTER TABLE kline_data DROP CONSTRAINT kline_data_pkey;
+ALTER TABLE kline_data ADD CONSTRAINT unique_kline_data UNIQUE (start_time, symbol, interval, dataset);
+ALTER TABLE kline_data ADD PRIMARY KEY (start_time, symbol, interval, dataset);
+
+ALTER TABLE quarantine ADD COLUMN dataset VARCHAR(32) NOT NULL DEFAULT 'default';
+
+INSERT INTO schema_version (version) VALUES (20250714090000);
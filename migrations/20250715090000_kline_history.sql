@@ -0,0 +1,51 @@
+-- Revision history for kline_data. Whenever a candle is updated or deleted, the
+-- previous version is copied here together with the wall-clock interval during
+-- which it was the stored value, so queries can ask what was known at a given time.
+CREATE TABLE kline_data_history (
+    start_time TIMESTAMPTZ NOT NULL,
+    end_time TIMESTAMPTZ NOT NULL,
+    symbol VARCHAR(20) NOT NULL,
+    interval VARCHAR(10) NOT NULL,
+    first_trade_id INTEGER NOT NULL,
+    last_trade_id INTEGER NOT NULL,
+    open DECIMAL(20,8) NOT NULL,
+    high DECIMAL(20,8) NOT NULL,
+    low DECIMAL(20,8) NOT NULL,
+    close DECIMAL(20,8) NOT NULL,
+    volume DECIMAL(20,8) NOT NULL,
+    trade_count INTEGER,
+    quote_volume DECIMAL(20,8),
+    created_at TIMESTAMPTZ,
+    dataset VARCHAR(32) NOT NULL,
+    valid_from TIMESTAMPTZ NOT NULL,
+    valid_to TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX kline_data_history_lookup_idx
+    ON kline_data_history (symbol, interval, dataset, start_time, valid_from);
+
+CREATE FUNCTION record_kline_revision() RETURNS trigger AS $$
+BEGIN
+    INSERT INTO kline_data_history (
+        start_time, end_time, symbol, interval, first_trade_id, last_trade_id,
+        open, high, low, close, volume, trade_count, quote_volume, created_at, dataset,
+        valid_from, valid_to
+    )
+    VALUES (
+        OLD.start_time, OLD.end_time, OLD.symbol, OLD.interval, OLD.first_trade_id,
+        OLD.last_trade_id, OLD.open, OLD.high, OLD.low, OLD.close, OLD.volume,
+        OLD.trade_count, OLD.quote_volume, OLD.created_at, OLD.dataset,
+        COALESCE(OLD.update_at, OLD.created_at, '-infinity'), NOW()
+    );
+    IF TG_OP = 'DELETE' THEN
+        RETURN OLD;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER kline_data_revision
+    BEFORE UPDATE OR DELETE ON kline_data
+    FOR EACH ROW EXECUTE FUNCTION record_kline_revision();
+
+INSERT INTO schema_version (version) VALUES (20250715090000);
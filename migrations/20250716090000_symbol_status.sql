@@ -0,0 +1,13 @@
+-- Trading status history per symbol, as reported by the exchangeInfo endpoint.
+-- A row is only written when the status of a symbol changes; symbols that are
+-- no longer listed at all are recorded with the status 'DELISTED'.
+CREATE TABLE symbol_status_history (
+    id BIGSERIAL PRIMARY KEY,
+    symbol VARCHAR(20) NOT NULL,
+    status VARCHAR(20) NOT NULL,
+    observed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX symbol_status_history_symbol_idx ON symbol_status_history (symbol, observed_at);
+
+INSERT INTO schema_version (version) VALUES (20250716090000);
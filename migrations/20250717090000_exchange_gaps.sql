@@ -0,0 +1,14 @@
+-- Ranges for which the exchange returned no candles (pre-listing periods,
+-- maintenance windows). Recording them lets audits tell exchange-side gaps
+-- apart from data lost during ingestion.
+CREATE TABLE exchange_gaps (
+    symbol VARCHAR(20) NOT NULL,
+    interval VARCHAR(10) NOT NULL,
+    dataset VARCHAR(32) NOT NULL DEFAULT 'default',
+    start_time TIMESTAMPTZ NOT NULL,
+    end_time TIMESTAMPTZ NOT NULL,
+    recorded_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (symbol, interval, dataset, start_time)
+);
+
+INSERT INTO schema_version (version) VALUES (20250717090000);
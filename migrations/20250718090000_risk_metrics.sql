@@ -0,0 +1,29 @@
+-- Rolling historical volatility per symbol, annualized from the log returns of
+-- the `window_size` candles ending at `as_of`.
+CREATE TABLE volatility_metrics (
+    symbol VARCHAR(20) NOT NULL,
+    interval VARCHAR(10) NOT NULL,
+    dataset VARCHAR(32) NOT NULL DEFAULT 'default',
+    window_size INTEGER NOT NULL,
+    as_of TIMESTAMPTZ NOT NULL,
+    volatility DOUBLE PRECISION NOT NULL,
+    computed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (symbol, interval, dataset, window_size, as_of)
+);
+
+-- Pairwise correlation of the log returns of two symbols over the same window.
+-- Each pair is stored once, with `symbol_a` sorting before `symbol_b`.
+CREATE TABLE correlation_metrics (
+    symbol_a VARCHAR(20) NOT NULL,
+    symbol_b VARCHAR(20) NOT NULL,
+    interval VARCHAR(10) NOT NULL,
+    dataset VARCHAR(32) NOT NULL DEFAULT 'default',
+    window_size INTEGER NOT NULL,
+    as_of TIMESTAMPTZ NOT NULL,
+    correlation DOUBLE PRECISION NOT NULL,
+    samples INTEGER NOT NULL,
+    computed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (symbol_a, symbol_b, interval, dataset, window_size, as_of)
+);
+
+INSERT INTO schema_version (version) VALUES (20250718090000);
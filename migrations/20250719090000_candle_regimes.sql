@@ -0,0 +1,19 @@
+-- Market regime labels of stored candles, keyed like kline_data so research
+-- queries can join on (symbol, interval, dataset, start_time) and filter by
+-- regime directly in SQL.
+CREATE TABLE candle_regimes (
+    symbol VARCHAR(20) NOT NULL,
+    interval VARCHAR(10) NOT NULL,
+    dataset VARCHAR(32) NOT NULL DEFAULT 'default',
+    start_time TIMESTAMPTZ NOT NULL,
+    trend VARCHAR(10) NOT NULL,
+    volatility VARCHAR(10) NOT NULL,
+    adx DOUBLE PRECISION NOT NULL,
+    realized_volatility DOUBLE PRECISION NOT NULL,
+    computed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (symbol, interval, dataset, start_time)
+);
+
+CREATE INDEX candle_regimes_label_idx ON candle_regimes (trend, volatility);
+
+INSERT INTO schema_version (version) VALUES (20250719090000);
@@ -0,0 +1,12 @@
+-- Funding rates settled on perpetual futures. A positive rate is paid by long
+-- positions to short positions at `funding_time`.
+CREATE TABLE funding_rates (
+    symbol VARCHAR(20) NOT NULL,
+    dataset VARCHAR(32) NOT NULL DEFAULT 'default',
+    funding_time TIMESTAMPTZ NOT NULL,
+    funding_rate DECIMAL(20,10) NOT NULL,
+    mark_price DECIMAL(20,8),
+    PRIMARY KEY (symbol, dataset, funding_time)
+);
+
+INSERT INTO schema_version (version) VALUES (20250720090000);
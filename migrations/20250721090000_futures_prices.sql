@@ -0,0 +1,18 @@
+-- Mark and index prices of futures contracts, as streamed by the exchange. They
+-- diverge from last-trade klines and drive liquidations and funding.
+-- `kind` is 'mark' or 'index'; mark price rows also carry the index price,
+-- estimated settle price and funding rate reported alongside it.
+CREATE TABLE futures_prices (
+    symbol VARCHAR(20) NOT NULL,
+    dataset VARCHAR(32) NOT NULL DEFAULT 'default',
+    kind VARCHAR(10) NOT NULL,
+    event_time TIMESTAMPTZ NOT NULL,
+    price DECIMAL(20,8) NOT NULL,
+    index_price DECIMAL(20,8),
+    estimated_settle_price DECIMAL(20,8),
+    funding_rate DECIMAL(20,10),
+    next_funding_time TIMESTAMPTZ,
+    PRIMARY KEY (symbol, dataset, kind, event_time)
+);
+
+INSERT INTO schema_version (version) VALUES (20250721090000);
@@ -0,0 +1,25 @@
+-- Snapshots of the mark prices, implied volatilities and greeks of options
+-- contracts. Every poll stores all contracts of an underlying at the same
+-- `observed_at`, so one snapshot is a volatility surface over (expiry, strike).
+CREATE TABLE option_marks (
+    symbol VARCHAR(40) NOT NULL,
+    dataset VARCHAR(32) NOT NULL DEFAULT 'default',
+    observed_at TIMESTAMPTZ NOT NULL,
+    underlying VARCHAR(20) NOT NULL,
+    expiry TIMESTAMPTZ NOT NULL,
+    strike DECIMAL(20,8) NOT NULL,
+    option_type CHAR(1) NOT NULL,
+    mark_price DECIMAL(20,8) NOT NULL,
+    bid_iv DOUBLE PRECISION,
+    ask_iv DOUBLE PRECISION,
+    mark_iv DOUBLE PRECISION,
+    delta DOUBLE PRECISION,
+    gamma DOUBLE PRECISION,
+    theta DOUBLE PRECISION,
+    vega DOUBLE PRECISION,
+    PRIMARY KEY (symbol, dataset, observed_at)
+);
+
+CREATE INDEX option_marks_surface_idx ON option_marks (underlying, dataset, observed_at);
+
+INSERT INTO schema_version (version) VALUES (20250722090000);
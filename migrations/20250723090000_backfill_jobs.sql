@@ -0,0 +1,21 @@
+-- Progress of tracked kline backfills. Every stored batch advances
+-- `last_end_time`, so a backfill interrupted by a crash or restart resumes from
+-- its last batch instead of from `start_time`.
+CREATE TABLE backfill_jobs (
+    id BIGSERIAL PRIMARY KEY,
+    symbol VARCHAR(20) NOT NULL,
+    interval VARCHAR(10) NOT NULL,
+    dataset VARCHAR(32) NOT NULL DEFAULT 'default',
+    start_time TIMESTAMPTZ NOT NULL,
+    end_time TIMESTAMPTZ,
+    last_end_time TIMESTAMPTZ,
+    row_count BIGINT NOT NULL DEFAULT 0,
+    status VARCHAR(16) NOT NULL DEFAULT 'running',
+    error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX backfill_jobs_status_idx ON backfill_jobs (status, updated_at);
+
+INSERT INTO schema_version (version) VALUES (20250723090000);
@@ -0,0 +1,28 @@
+-- Provenance of stored candles: the ingestion path that last wrote each row,
+-- 'stream' for live WebSocket pipelines or 'backfill' for REST backfills, so
+-- discrepancies between live-captured and backfilled values can be traced.
+-- Rows stored before provenance was recorded are marked 'unknown'.
+ALTER TABLE kline_data ADD COLUMN source_kind VARCHAR(16) NOT NULL DEFAULT 'unknown';
+ALTER TABLE kline_data_history ADD COLUMN source_kind VARCHAR(16) NOT NULL DEFAULT 'unknown';
+
+CREATE OR REPLACE FUNCTION record_kline_revision() RETURNS trigger AS $$
+BEGIN
+    INSERT INTO kline_data_history (
+        start_time, end_time, symbol, interval, first_trade_id, last_trade_id,
+        open, high, low, close, volume, trade_count, quote_volume, created_at, dataset,
+        source_kind, valid_from, valid_to
+    )
+    VALUES (
+        OLD.start_time, OLD.end_time, OLD.symbol, OLD.interval, OLD.first_trade_id,
+        OLD.last_trade_id, OLD.open, OLD.high, OLD.low, OLD.close, OLD.volume,
+        OLD.trade_count, OLD.quote_volume, OLD.created_at, OLD.dataset,
+        OLD.source_kind, COALESCE(OLD.update_at, OLD.created_at, '-infinity'), NOW()
+    );
+    IF TG_OP = 'DELETE' THEN
+        RETURN OLD;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+INSERT INTO schema_version (version) VALUES (20250724090000);
@@ -0,0 +1,29 @@
+-- Corrections of streamed candles whose final update was missed, e.g. during a
+-- disconnect. Once such a candle closes, its final values are re-fetched over
+-- REST; when they differ from the stored ones, the candle is overwritten and the
+-- values before and after are recorded here.
+CREATE TABLE kline_corrections (
+    id BIGSERIAL PRIMARY KEY,
+    symbol VARCHAR(20) NOT NULL,
+    interval VARCHAR(10) NOT NULL,
+    dataset VARCHAR(32) NOT NULL DEFAULT 'default',
+    start_time TIMESTAMPTZ NOT NULL,
+    streamed_open DECIMAL(20,8),
+    streamed_high DECIMAL(20,8),
+    streamed_low DECIMAL(20,8),
+    streamed_close DECIMAL(20,8),
+    streamed_volume DECIMAL(20,8),
+    streamed_last_trade_id INTEGER,
+    open DECIMAL(20,8) NOT NULL,
+    high DECIMAL(20,8) NOT NULL,
+    low DECIMAL(20,8) NOT NULL,
+    close DECIMAL(20,8) NOT NULL,
+    volume DECIMAL(20,8) NOT NULL,
+    last_trade_id INTEGER NOT NULL,
+    corrected_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX kline_corrections_lookup_idx
+    ON kline_corrections (symbol, interval, dataset, start_time);
+
+INSERT INTO schema_version (version) VALUES (20250725090000);
@@ -0,0 +1,17 @@
+-- Periodic snapshots of the top levels of local order books, maintained from the
+-- depth diff streams. Each side is stored as parallel arrays of prices and
+-- quantities, best level first. `last_update_id` is the id of the last diff
+-- applied to the book, so a snapshot can be matched against the exchange's.
+CREATE TABLE order_book_snapshots (
+    symbol VARCHAR(20) NOT NULL,
+    dataset VARCHAR(32) NOT NULL DEFAULT 'default',
+    snapshot_time TIMESTAMPTZ NOT NULL,
+    last_update_id BIGINT NOT NULL,
+    bid_prices DECIMAL(20,8)[] NOT NULL,
+    bid_quantities DECIMAL(20,8)[] NOT NULL,
+    ask_prices DECIMAL(20,8)[] NOT NULL,
+    ask_quantities DECIMAL(20,8)[] NOT NULL,
+    PRIMARY KEY (symbol, dataset, snapshot_time)
+);
+
+INSERT INTO schema_version (version) VALUES (20250726090000);
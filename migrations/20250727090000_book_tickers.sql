@@ -0,0 +1,18 @@
+-- Best bid and ask quotes of symbols, as streamed by `<symbol>@bookTicker`, for
+-- spread analytics. The stream carries no event time, so `received_at` is the
+-- time the quote was received; `update_id` orders the quotes of a symbol.
+CREATE TABLE book_tickers (
+    symbol VARCHAR(20) NOT NULL,
+    dataset VARCHAR(32) NOT NULL DEFAULT 'default',
+    update_id BIGINT NOT NULL,
+    received_at TIMESTAMPTZ NOT NULL,
+    bid_price DECIMAL(20,8) NOT NULL,
+    bid_quantity DECIMAL(20,8) NOT NULL,
+    ask_price DECIMAL(20,8) NOT NULL,
+    ask_quantity DECIMAL(20,8) NOT NULL,
+    PRIMARY KEY (symbol, dataset, update_id)
+);
+
+CREATE INDEX book_tickers_received_at_idx ON book_tickers (symbol, dataset, received_at);
+
+INSERT INTO schema_version (version) VALUES (20250727090000);
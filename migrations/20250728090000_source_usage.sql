@@ -0,0 +1,14 @@
+-- Daily request and row counts per data source and API key, shared by all
+-- processes using the same key so that quotas hold across them. `api_key` is a
+-- label of the key (e.g., "default", "ops"), never the key itself.
+CREATE TABLE source_usage (
+    source VARCHAR(32) NOT NULL,
+    api_key VARCHAR(64) NOT NULL,
+    day DATE NOT NULL,
+    requests BIGINT NOT NULL DEFAULT 0,
+    rows BIGINT NOT NULL DEFAULT 0,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (source, api_key, day)
+);
+
+INSERT INTO schema_version (version) VALUES (20250728090000);
@@ -0,0 +1,13 @@
+-- Open interest of futures contracts: the total size of the open positions at
+-- `timestamp`, in contracts or base asset. `open_interest_value` is its notional
+-- in the quote asset, which only the open interest history reports.
+CREATE TABLE open_interest (
+    symbol VARCHAR(20) NOT NULL,
+    dataset VARCHAR(32) NOT NULL DEFAULT 'default',
+    timestamp TIMESTAMPTZ NOT NULL,
+    open_interest DECIMAL(30,8) NOT NULL,
+    open_interest_value DECIMAL(30,8),
+    PRIMARY KEY (symbol, dataset, timestamp)
+);
+
+INSERT INTO schema_version (version) VALUES (20250729090000);
@@ -0,0 +1,10 @@
+-- Named symbol sets, e.g. "majors", that commands accept with `--watchlist`
+-- instead of a long list of `--symbol` arguments.
+CREATE TABLE watchlists (
+    name VARCHAR(64) NOT NULL,
+    symbol VARCHAR(20) NOT NULL,
+    added_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (name, symbol)
+);
+
+INSERT INTO schema_version (version) VALUES (20250730090000);
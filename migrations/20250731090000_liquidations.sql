@@ -0,0 +1,23 @@
+-- Liquidation orders of futures contracts, as streamed by `<symbol>@forceOrder`
+-- or `!forceOrder@arr`. The exchange assigns them no id, so an order is
+-- identified by its symbol, trade time, side, price and filled quantity.
+CREATE TABLE liquidations (
+    symbol VARCHAR(20) NOT NULL,
+    dataset VARCHAR(32) NOT NULL DEFAULT 'default',
+    trade_time TIMESTAMPTZ NOT NULL,
+    side VARCHAR(4) NOT NULL,
+    order_type VARCHAR(16) NOT NULL,
+    time_in_force VARCHAR(8) NOT NULL,
+    status VARCHAR(16) NOT NULL,
+    price DECIMAL(20,8) NOT NULL,
+    average_price DECIMAL(20,8) NOT NULL,
+    quantity DECIMAL(30,8) NOT NULL,
+    last_filled_quantity DECIMAL(30,8) NOT NULL,
+    filled_quantity DECIMAL(30,8) NOT NULL,
+    event_time TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (symbol, dataset, trade_time, side, price, filled_quantity)
+);
+
+CREATE INDEX liquidations_trade_time_idx ON liquidations (dataset, trade_time);
+
+INSERT INTO schema_version (version) VALUES (20250731090000);
@@ -0,0 +1,8 @@
+-- Newest-first backfill jobs walk their range from `end_time` back to
+-- `start_time`. Every stored batch moves `first_start_time` back, so an
+-- interrupted job resumes below its earliest batch.
+ALTER TABLE backfill_jobs
+    ADD COLUMN newest_first BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN first_start_time TIMESTAMPTZ;
+
+INSERT INTO schema_version (version) VALUES (20250801090000);
@@ -0,0 +1,28 @@
+-- The exchange each candle was ingested from (e.g., 'binance', 'bybit'), so
+-- candles of several exchanges can share `kline_data`. Rows stored before the
+-- exchange was recorded all came from Binance.
+ALTER TABLE kline_data ADD COLUMN exchange VARCHAR(32) NOT NULL DEFAULT 'binance';
+ALTER TABLE kline_data_history ADD COLUMN exchange VARCHAR(32) NOT NULL DEFAULT 'binance';
+
+CREATE OR REPLACE FUNCTION record_kline_revision() RETURNS trigger AS $$
+BEGIN
+    INSERT INTO kline_data_history (
+        start_time, end_time, symbol, interval, first_trade_id, last_trade_id,
+        open, high, low, close, volume, trade_count, quote_volume, created_at, dataset,
+        source_kind, exchange, valid_from, valid_to
+    )
+    VALUES (
+        OLD.start_time, OLD.end_time, OLD.symbol, OLD.interval, OLD.first_trade_id,
+        OLD.last_trade_id, OLD.open, OLD.high, OLD.low, OLD.close, OLD.volume,
+        OLD.trade_count, OLD.quote_volume, OLD.created_at, OLD.dataset,
+        OLD.source_kind, OLD.exchange,
+        COALESCE(OLD.update_at, OLD.created_at, '-infinity'), NOW()
+    );
+    IF TG_OP = 'DELETE' THEN
+        RETURN OLD;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+INSERT INTO schema_version (version) VALUES (20250802090000);
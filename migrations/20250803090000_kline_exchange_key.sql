@@ -0,0 +1,10 @@
+-- Key candles by their exchange too, so a dataset can hold the same symbol of
+-- several exchanges (e.g., BTCUSDT of Binance and of Bybit) without one
+-- overwriting the other. The dataset stays part of the key.
+ALTER TABLE kline_data DROP CONSTRAINT unique_kline_data;
+ALTER TABLE kline_data DROP CONSTRAINT kline_data_pkey;
+ALTER TABLE kline_data ADD CONSTRAINT unique_kline_data
+    UNIQUE (start_time, symbol, interval, dataset, exchange);
+ALTER TABLE kline_data ADD PRIMARY KEY (start_time, symbol, interval, dataset, exchange);
+
+INSERT INTO schema_version (version) VALUES (20250803090000);
@@ -0,0 +1,20 @@
+-- Trade ids no longer fit in INTEGER (Binance spot trade ids are past 5 billion),
+-- so they are widened to BIGINT. Widening keeps every stored value, but rewrites
+-- the tables while holding an exclusive lock; run it when ingestion is paused.
+--
+-- Ids that already overflowed were stored truncated. Backfilling the affected
+-- ranges again overwrites them with the exchange's ids, and replayed messages
+-- overwrite them too, as truncated ids compare lower than the real ones.
+ALTER TABLE kline_data
+    ALTER COLUMN first_trade_id TYPE BIGINT,
+    ALTER COLUMN last_trade_id TYPE BIGINT;
+
+ALTER TABLE kline_data_history
+    ALTER COLUMN first_trade_id TYPE BIGINT,
+    ALTER COLUMN last_trade_id TYPE BIGINT;
+
+ALTER TABLE kline_corrections
+    ALTER COLUMN streamed_last_trade_id TYPE BIGINT,
+    ALTER COLUMN last_trade_id TYPE BIGINT;
+
+INSERT INTO schema_version (version) VALUES (20250804090000);
@@ -0,0 +1,41 @@
+-- Record only real revisions of candles. Upserts rewrite every column on conflict,
+-- so every streamed update and every backfill of an unchanged range used to copy
+-- the stored candle into kline_data_history, even when none of its values changed.
+--
+-- Updates that leave the values unchanged keep the previous update time as well,
+-- so the stored version stays valid from when its values were first written and
+-- `KlineData::as_of` finds a version for every point in time.
+DROP TRIGGER kline_data_revision ON kline_data;
+
+CREATE TRIGGER kline_data_revision
+    BEFORE UPDATE ON kline_data
+    FOR EACH ROW
+    WHEN ((OLD.end_time, OLD.first_trade_id, OLD.last_trade_id, OLD.open, OLD.high,
+           OLD.low, OLD.close, OLD.volume, OLD.trade_count, OLD.quote_volume)
+          IS DISTINCT FROM
+          (NEW.end_time, NEW.first_trade_id, NEW.last_trade_id, NEW.open, NEW.high,
+           NEW.low, NEW.close, NEW.volume, NEW.trade_count, NEW.quote_volume))
+    EXECUTE FUNCTION record_kline_revision();
+
+CREATE TRIGGER kline_data_deletion
+    BEFORE DELETE ON kline_data
+    FOR EACH ROW EXECUTE FUNCTION record_kline_revision();
+
+CREATE FUNCTION keep_kline_update_time() RETURNS trigger AS $$
+BEGIN
+    NEW.update_at := OLD.update_at;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER kline_data_unchanged
+    BEFORE UPDATE ON kline_data
+    FOR EACH ROW
+    WHEN ((OLD.end_time, OLD.first_trade_id, OLD.last_trade_id, OLD.open, OLD.high,
+           OLD.low, OLD.close, OLD.volume, OLD.trade_count, OLD.quote_volume)
+          IS NOT DISTINCT FROM
+          (NEW.end_time, NEW.first_trade_id, NEW.last_trade_id, NEW.open, NEW.high,
+           NEW.low, NEW.close, NEW.volume, NEW.trade_count, NEW.quote_volume))
+    EXECUTE FUNCTION keep_kline_update_time();
+
+INSERT INTO schema_version (version) VALUES (20250805090000);
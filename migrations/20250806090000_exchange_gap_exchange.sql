@@ -0,0 +1,9 @@
+-- Record exchange-side gaps per exchange, so a range one exchange returned no
+-- candles for is not skipped when backfilling the same symbol of another exchange
+-- into the dataset. Gaps recorded before the exchange was kept all came from
+-- Binance.
+ALTER TABLE exchange_gaps ADD COLUMN exchange VARCHAR(32) NOT NULL DEFAULT 'binance';
+ALTER TABLE exchange_gaps DROP CONSTRAINT exchange_gaps_pkey;
+ALTER TABLE exchange_gaps ADD PRIMARY KEY (symbol, interval, dataset, exchange, start_time);
+
+INSERT INTO schema_version (version) VALUES (20250806090000);
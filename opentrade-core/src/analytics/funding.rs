@@ -0,0 +1,223 @@
+//! # Funding-Adjusted Perpetual Prices
+//!
+//! Holding a perpetual futures position pays or earns funding on top of the price
+//! move, so raw perp candles are not directly comparable with spot. This module
+//! combines the stored candles of a perpetual with its stored funding rates into
+//! the return series of a long position that paid the funding.
+//!
+//! The position held from the close of one candle to the close of the next pays
+//! every funding settled in between, i.e. with a funding time in
+//! `(previous end_time, end_time]`. A funding rate `r` scales the position by
+//! `1 - r`, so the adjusted log return of a candle is
+//! `ln(close / previous close) + Σ ln(1 - r)`. Short positions earn the opposite.
+//!
+//! Funding rates are read from the `funding_rates` table; this module does not
+//! fetch them from the exchange.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use opentrade_core::analytics::funding::load_funding_adjusted;
+//! use opentrade_core::models::DEFAULT_DATASET;
+//! use chrono::{Duration, Utc};
+//! # use anyhow::Result;
+//!
+//! # async fn example(pool: sqlx::PgPool) -> Result<()> {
+//! let end = Utc::now();
+//! let start = end - Duration::days(30);
+//! let prices =
+//!     load_funding_adjusted(&pool, "BTCUSDT", "8h", DEFAULT_DATASET, "binance", start, end)
+//!         .await?;
+//! if let Some(last) = prices.last() {
+//!     println!("raw close {} adjusted close {}", last.close, last.adjusted_close);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::analytics::risk::Return;
+use crate::analytics::to_f64;
+use crate::models::KlineData;
+use crate::models::funding::FundingRate;
+
+/// A candle close of a perpetual with the funding paid up to it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct AdjustedPrice {
+    /// The start time of the candle.
+    pub time: DateTime<Utc>,
+    /// The raw close price.
+    pub close: f64,
+    /// The sum of the funding rates paid since the previous candle's close.
+    pub funding: f64,
+    /// The close price with all funding since the first candle applied, so that
+    /// the ratio of two adjusted closes is the return of a long position.
+    pub adjusted_close: f64,
+}
+
+/// Applies funding rates to candles sorted by start time.
+///
+/// The first candle starts the series with its raw close. Funding settled before
+/// its close is not applied. Candles with a non-positive close are skipped.
+///
+/// # Arguments
+///
+/// * `klines` - The candles of the perpetual, sorted by start time.
+/// * `rates` - The funding rates of the perpetual, sorted by funding time.
+pub fn funding_adjusted_prices(klines: &[KlineData], rates: &[FundingRate]) -> Vec<AdjustedPrice> {
+    let mut prices: Vec<AdjustedPrice> = Vec::with_capacity(klines.len());
+    let mut next_rate = 0;
+
+    for kline in klines {
+        let close = to_f64(&kline.close);
+        if close <= 0.0 || close.is_nan() {
+            continue;
+        }
+        // Funding settled up to the first close is not part of the series.
+        let mut funding = 0.0;
+        let mut growth = 1.0;
+        while next_rate < rates.len() && rates[next_rate].funding_time <= kline.end_time {
+            if !prices.is_empty() {
+                let rate = to_f64(&rates[next_rate].funding_rate);
+                funding += rate;
+                growth *= 1.0 - rate;
+            }
+            next_rate += 1;
+        }
+
+        let adjusted_close = match prices.last() {
+            Some(previous) => previous.adjusted_close * close / previous.close * growth,
+            None => close,
+        };
+        prices.push(AdjustedPrice {
+            time: kline.start_time,
+            close,
+            funding,
+            adjusted_close,
+        });
+    }
+    prices
+}
+
+/// Computes the funding-adjusted log returns of a long position, keyed by the
+/// start time of the candle each return ends on.
+pub fn funding_adjusted_returns(prices: &[AdjustedPrice]) -> Vec<Return> {
+    prices
+        .windows(2)
+        .map(|pair| Return {
+            time: pair[1].time,
+            value: (pair[1].adjusted_close / pair[0].adjusted_close).ln(),
+        })
+        .collect()
+}
+
+/// Loads the candles and funding rates of a perpetual for candles starting in
+/// `[start_time, end_time)` and applies the funding.
+///
+/// # Arguments
+///
+/// * `pool` - The database connection pool.
+/// * `symbol` - The perpetual contract symbol.
+/// * `interval` - The Kline interval.
+/// * `dataset` - The dataset label of both the candles and the funding rates.
+/// * `exchange` - The exchange the candles were captured from (e.g., "binance").
+/// * `start_time` - The inclusive start of the range.
+/// * `end_time` - The exclusive end of the range.
+pub async fn load_funding_adjusted(
+    pool: &sqlx::PgPool,
+    symbol: &str,
+    interval: &str,
+    dataset: &str,
+    exchange: &str,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+) -> Result<Vec<AdjustedPrice>> {
+    let klines = KlineData::list_range(
+        pool, symbol, interval, start_time, end_time, dataset, exchange,
+    )
+    .await
+    .with_context(|| format!("Failed to load candles of {}", symbol))?;
+    let (Some(first), Some(last)) = (klines.first(), klines.last()) else {
+        return Ok(Vec::new());
+    };
+    // Funding up to and including the last close; `list_range` excludes its end.
+    let rates = FundingRate::list_range(
+        pool,
+        symbol,
+        dataset,
+        first.end_time,
+        last.end_time + chrono::Duration::milliseconds(1),
+    )
+    .await
+    .with_context(|| format!("Failed to load funding rates of {}", symbol))?;
+    Ok(funding_adjusted_prices(&klines, &rates))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::DEFAULT_DATASET;
+    use sqlx::types::BigDecimal as Decimal;
+
+    fn kline(hour: u64, close: &str) -> KlineData {
+        let start = hour * 3_600_000;
+        let close: Decimal = close.parse().unwrap();
+        KlineData::new(
+            &start,
+            &(start + 3_599_999),
+            "BTCUSDT",
+            "1h",
+            1,
+            2,
+            close.clone(),
+            close.clone(),
+            close.clone(),
+            close.clone(),
+            close.clone(),
+            Some(1),
+            Some(close),
+        )
+    }
+
+    fn rate(hour: i64, rate: &str) -> FundingRate {
+        FundingRate {
+            symbol: "BTCUSDT".to_string(),
+            dataset: DEFAULT_DATASET.to_string(),
+            funding_time: DateTime::from_timestamp(hour * 3_600, 0).unwrap(),
+            funding_rate: rate.parse().unwrap(),
+            mark_price: None,
+        }
+    }
+
+    #[test]
+    fn test_funding_is_applied_between_closes() {
+        let klines = vec![kline(0, "100"), kline(1, "100"), kline(2, "110")];
+        // Settled before the first close, within the third candle, and after the last close.
+        let rates = vec![rate(0, "0.5"), rate(2, "0.01"), rate(3, "0.02")];
+
+        let prices = funding_adjusted_prices(&klines, &rates);
+
+        assert_eq!(prices.len(), 3);
+        assert_eq!(prices[0].adjusted_close, 100.0);
+        assert_eq!(prices[1].funding, 0.0);
+        assert_eq!(prices[1].adjusted_close, 100.0);
+        // The funding at 02:00 is paid when holding from the 01:59 close to the 02:59 close,
+        // but the one at 03:00 is after the last close.
+        assert_eq!(prices[2].funding, 0.01);
+        assert!((prices[2].adjusted_close - 110.0 * 0.99).abs() < 1e-9);
+
+        let returns = funding_adjusted_returns(&prices);
+        assert_eq!(returns.len(), 2);
+        assert!((returns[1].value - (1.1f64 * 0.99).ln()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_negative_funding_adds_to_returns() {
+        let klines = vec![kline(0, "100"), kline(8, "100")];
+        let prices = funding_adjusted_prices(&klines, &[rate(8, "-0.001")]);
+        assert!((prices[1].adjusted_close - 100.1).abs() < 1e-9);
+    }
+}
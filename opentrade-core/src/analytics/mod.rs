@@ -0,0 +1,23 @@
+//! # Analytics Module
+//!
+//! This module derives research and risk metrics from stored market data. The
+//! computations are plain functions over candles, so they can be used on data
+//! loaded from any source, with helpers that load the candles from the database
+//! and persist the results next to them.
+//!
+//! ## Submodules
+//!
+//! - [`funding`] - Funding-adjusted return series of perpetual futures
+//! - [`regime`] - Trend and volatility regime labels of stored candles
+//! - [`risk`] - Rolling historical volatility and pairwise return correlations
+
+pub mod funding;
+pub mod regime;
+pub mod risk;
+
+use sqlx::types::BigDecimal as Decimal;
+
+/// Converts a stored decimal to a floating-point value for numeric analysis.
+pub(crate) fn to_f64(value: &Decimal) -> f64 {
+    value.to_string().parse().unwrap_or(f64::NAN)
+}
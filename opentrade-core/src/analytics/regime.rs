@@ -0,0 +1,462 @@
+//! # Market Regime Tagging
+//!
+//! This module labels candles with the market regime they were formed in, and
+//! stores the labels in the `candle_regimes` companion table so strategy research
+//! can filter by regime directly in SQL:
+//!
+//! ```sql
+//! SELECT k.* FROM kline_data k
+//! JOIN candle_regimes r USING (symbol, interval, dataset, start_time)
+//! WHERE k.symbol = 'BTCUSDT' AND r.trend = 'trend' AND r.volatility = 'high';
+//! ```
+//!
+//! Each candle gets two labels:
+//! - A [`Trend`] label from the Average Directional Index (Wilder's ADX): candles
+//!   with an ADX at or above the threshold are trending, others are ranging.
+//! - A [`VolatilityBucket`] from the annualized realized volatility of the log
+//!   returns over the preceding candles (see [`risk`](super::risk)).
+//!
+//! Both indicators need history before the first labeled candle, so the candles
+//! before the range are loaded as a warm-up. Candles without enough contiguous
+//! history (at the start of the data or after a gap) are not labeled.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use opentrade_core::analytics::regime::{RegimeConfig, tag_range};
+//! use opentrade_core::models::{DEFAULT_DATASET, DEFAULT_EXCHANGE};
+//! use opentrade_core::models::regime::CandleRegime;
+//! use chrono::{Duration, Utc};
+//! # use anyhow::Result;
+//!
+//! # async fn example(pool: sqlx::PgPool) -> Result<()> {
+//! let end = Utc::now();
+//! let start = end - Duration::days(30);
+//! let config = RegimeConfig::default();
+//! let regimes = tag_range(
+//!     &pool,
+//!     "BTCUSDT",
+//!     "1h",
+//!     DEFAULT_DATASET,
+//!     DEFAULT_EXCHANGE,
+//!     start,
+//!     end,
+//!     &config,
+//! )
+//! .await?;
+//! CandleRegime::upsert_batch(&pool, &regimes).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Duration, Utc};
+
+use crate::analytics::risk::{periods_per_year, volatility};
+use crate::analytics::to_f64;
+use crate::ingest::audit::interval_duration;
+use crate::models::KlineData;
+use crate::models::regime::CandleRegime;
+
+/// Whether the market was trending or ranging at a candle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trend {
+    /// The ADX is at or above the trend threshold.
+    Trend,
+    /// The ADX is below the trend threshold.
+    Range,
+}
+
+impl Trend {
+    /// Returns the label stored in `candle_regimes.trend`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Trend::Trend => "trend",
+            Trend::Range => "range",
+        }
+    }
+}
+
+impl fmt::Display for Trend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The realized-volatility bucket of a candle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VolatilityBucket {
+    /// The realized volatility is below the low threshold.
+    Low,
+    /// The realized volatility is between the thresholds.
+    Normal,
+    /// The realized volatility is at or above the high threshold.
+    High,
+}
+
+impl VolatilityBucket {
+    /// Returns the label stored in `candle_regimes.volatility`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VolatilityBucket::Low => "low",
+            VolatilityBucket::Normal => "normal",
+            VolatilityBucket::High => "high",
+        }
+    }
+}
+
+impl fmt::Display for VolatilityBucket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Parameters of the regime indicators.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegimeConfig {
+    /// The smoothing period of the ADX, in candles.
+    pub adx_period: usize,
+    /// The ADX at or above which a candle is trending.
+    pub trend_threshold: f64,
+    /// The number of returns the realized volatility is computed over.
+    pub volatility_window: usize,
+    /// The annualized volatility below which a candle is in the low bucket.
+    pub low_volatility: f64,
+    /// The annualized volatility at or above which a candle is in the high bucket.
+    pub high_volatility: f64,
+}
+
+impl Default for RegimeConfig {
+    fn default() -> Self {
+        Self {
+            adx_period: 14,
+            trend_threshold: 25.0,
+            volatility_window: 20,
+            low_volatility: 0.4,
+            high_volatility: 0.8,
+        }
+    }
+}
+
+impl RegimeConfig {
+    /// Returns the trend label of an ADX value.
+    pub fn trend(&self, adx: f64) -> Trend {
+        if adx >= self.trend_threshold {
+            Trend::Trend
+        } else {
+            Trend::Range
+        }
+    }
+
+    /// Returns the bucket of an annualized realized volatility.
+    pub fn volatility_bucket(&self, volatility: f64) -> VolatilityBucket {
+        if volatility < self.low_volatility {
+            VolatilityBucket::Low
+        } else if volatility >= self.high_volatility {
+            VolatilityBucket::High
+        } else {
+            VolatilityBucket::Normal
+        }
+    }
+
+    /// Returns the number of candles of history needed before the first label.
+    pub fn warm_up(&self) -> usize {
+        (2 * self.adx_period).max(self.volatility_window + 1)
+    }
+}
+
+/// Computes Wilder's Average Directional Index for candles sorted by start time.
+///
+/// The result is aligned with `klines`; it is `None` until `2 * period` contiguous
+/// candles are available, and the computation restarts after a missing candle.
+pub fn adx(klines: &[KlineData], step: Duration, period: usize) -> Vec<Option<f64>> {
+    let mut values = vec![None; klines.len()];
+    if period == 0 {
+        return values;
+    }
+    let n = period as f64;
+    // Smoothed true range, +DM and -DM, the DX values of the first period, and the ADX.
+    let mut smoothed: Option<(f64, f64, f64)> = None;
+    let mut sums = (0.0, 0.0, 0.0);
+    let mut moves = 0;
+    let mut dx_sum = 0.0;
+    let mut dx_count = 0;
+    let mut average: Option<f64> = None;
+
+    for i in 1..klines.len() {
+        let (previous, current) = (&klines[i - 1], &klines[i]);
+        if current.start_time - previous.start_time != step {
+            smoothed = None;
+            sums = (0.0, 0.0, 0.0);
+            moves = 0;
+            dx_sum = 0.0;
+            dx_count = 0;
+            average = None;
+            continue;
+        }
+        let (high, low) = (to_f64(&current.high), to_f64(&current.low));
+        let (previous_high, previous_low, previous_close) = (
+            to_f64(&previous.high),
+            to_f64(&previous.low),
+            to_f64(&previous.close),
+        );
+        let true_range = (high - low)
+            .max((high - previous_close).abs())
+            .max((low - previous_close).abs());
+        let up = high - previous_high;
+        let down = previous_low - low;
+        let plus_dm = if up > down && up > 0.0 { up } else { 0.0 };
+        let minus_dm = if down > up && down > 0.0 { down } else { 0.0 };
+
+        let (tr, plus, minus) = match smoothed {
+            Some((tr, plus, minus)) => (
+                tr - tr / n + true_range,
+                plus - plus / n + plus_dm,
+                minus - minus / n + minus_dm,
+            ),
+            None => {
+                sums = (sums.0 + true_range, sums.1 + plus_dm, sums.2 + minus_dm);
+                moves += 1;
+                if moves < period {
+                    continue;
+                }
+                sums
+            }
+        };
+        smoothed = Some((tr, plus, minus));
+
+        let (plus_di, minus_di) = if tr > 0.0 {
+            (100.0 * plus / tr, 100.0 * minus / tr)
+        } else {
+            (0.0, 0.0)
+        };
+        let di_sum = plus_di + minus_di;
+        let dx = if di_sum > 0.0 {
+            100.0 * (plus_di - minus_di).abs() / di_sum
+        } else {
+            0.0
+        };
+
+        average = match average {
+            Some(previous) => Some((previous * (n - 1.0) + dx) / n),
+            None => {
+                dx_sum += dx;
+                dx_count += 1;
+                (dx_count == period).then(|| dx_sum / n)
+            }
+        };
+        values[i] = average;
+    }
+    values
+}
+
+/// Computes the annualized realized volatility of the `window` log returns ending
+/// at each candle, for candles sorted by start time.
+///
+/// The result is aligned with `klines`; it is `None` unless the `window + 1`
+/// candles ending at a candle are contiguous.
+pub fn realized_volatility(
+    klines: &[KlineData],
+    step: Duration,
+    window: usize,
+) -> Vec<Option<f64>> {
+    let returns: Vec<Option<f64>> = std::iter::once(None)
+        .chain(klines.windows(2).map(|pair| {
+            let previous = to_f64(&pair[0].close);
+            let current = to_f64(&pair[1].close);
+            (pair[1].start_time - pair[0].start_time == step && previous > 0.0 && current > 0.0)
+                .then(|| (current / previous).ln())
+        }))
+        .collect();
+    let annualization = periods_per_year(step);
+    (0..klines.len())
+        .map(|i| {
+            if window == 0 || i < window {
+                return None;
+            }
+            let values: Option<Vec<f64>> = returns[i + 1 - window..=i].iter().copied().collect();
+            volatility(&values?, annualization)
+        })
+        .collect()
+}
+
+/// The labels of a candle and the indicator values they were derived from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Regime {
+    /// The trend label.
+    pub trend: Trend,
+    /// The realized-volatility bucket.
+    pub volatility: VolatilityBucket,
+    /// The Average Directional Index.
+    pub adx: f64,
+    /// The annualized realized volatility.
+    pub realized_volatility: f64,
+}
+
+/// Labels candles sorted by start time.
+///
+/// The result is aligned with `klines`; it is `None` for candles without enough
+/// history for both indicators.
+pub fn tag(klines: &[KlineData], step: Duration, config: &RegimeConfig) -> Vec<Option<Regime>> {
+    let adx = adx(klines, step, config.adx_period);
+    let volatility = realized_volatility(klines, step, config.volatility_window);
+    adx.into_iter()
+        .zip(volatility)
+        .map(|values| match values {
+            (Some(adx), Some(volatility)) => Some(Regime {
+                trend: config.trend(adx),
+                volatility: config.volatility_bucket(volatility),
+                adx,
+                realized_volatility: volatility,
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Loads the candles of `[start_time, end_time)` with their warm-up history and
+/// labels them.
+///
+/// # Arguments
+///
+/// * `pool` - The database connection pool.
+/// * `symbol` - The trading symbol.
+/// * `interval` - The Kline interval; calendar intervals are not supported.
+/// * `dataset` - The dataset label.
+/// * `exchange` - The exchange the candles were captured from (e.g., "binance").
+/// * `start_time` - The inclusive start of the candles to label.
+/// * `end_time` - The exclusive end of the candles to label.
+/// * `config` - The indicator parameters.
+#[allow(clippy::too_many_arguments)]
+pub async fn tag_range(
+    pool: &sqlx::PgPool,
+    symbol: &str,
+    interval: &str,
+    dataset: &str,
+    exchange: &str,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    config: &RegimeConfig,
+) -> Result<Vec<CandleRegime>> {
+    let step = interval_duration(interval)
+        .with_context(|| format!("Unsupported interval for regime tagging: {}", interval))?;
+    if config.adx_period == 0 || config.volatility_window < 2 {
+        bail!("The ADX period must be positive and the volatility window at least 2 returns");
+    }
+    let warm_up = i32::try_from(config.warm_up()).context("The warm-up period is too large")?;
+    let klines = KlineData::list_range(
+        pool,
+        symbol,
+        interval,
+        start_time - step * warm_up,
+        end_time,
+        dataset,
+        exchange,
+    )
+    .await
+    .with_context(|| format!("Failed to load candles of {}", symbol))?;
+
+    let regimes = tag(&klines, step, config);
+    Ok(klines
+        .iter()
+        .zip(regimes)
+        .filter(|(kline, _)| kline.start_time >= start_time)
+        .filter_map(|(kline, regime)| {
+            let regime = regime?;
+            Some(CandleRegime {
+                symbol: symbol.to_string(),
+                interval: interval.to_string(),
+                dataset: dataset.to_string(),
+                start_time: kline.start_time,
+                trend: regime.trend.to_string(),
+                volatility: regime.volatility.to_string(),
+                adx: regime.adx,
+                realized_volatility: regime.realized_volatility,
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::types::BigDecimal as Decimal;
+
+    fn kline(minute: i64, high: f64, low: f64, close: f64) -> KlineData {
+        let start = (minute * 60_000) as u64;
+        let price = |value: f64| value.to_string().parse::<Decimal>().unwrap();
+        KlineData::new(
+            &start,
+            &(start + 59_999),
+            "BTCUSDT",
+            "1m",
+            1,
+            2,
+            price(close),
+            price(high),
+            price(low),
+            price(close),
+            price(1.0),
+            Some(1),
+            Some(price(close)),
+        )
+    }
+
+    #[test]
+    fn test_adx_of_steady_trend_is_high() {
+        let klines: Vec<KlineData> = (0..40)
+            .map(|i| {
+                let base = 100.0 + i as f64;
+                kline(i, base + 0.5, base - 0.5, base + 0.4)
+            })
+            .collect();
+        let values = adx(&klines, Duration::minutes(1), 14);
+
+        assert!(values[..27].iter().all(Option::is_none));
+        assert!(values[27..].iter().all(Option::is_some));
+        assert!(values[39].unwrap() > 90.0);
+    }
+
+    #[test]
+    fn test_adx_of_oscillation_is_low() {
+        let klines: Vec<KlineData> = (0..60)
+            .map(|i| {
+                let base = if i % 2 == 0 { 100.0 } else { 101.0 };
+                kline(i, base + 1.0, base - 1.0, base)
+            })
+            .collect();
+        let values = adx(&klines, Duration::minutes(1), 14);
+        assert!(values[59].unwrap() < 25.0);
+    }
+
+    #[test]
+    fn test_gap_restarts_warm_up() {
+        let mut klines: Vec<KlineData> = (0..10).map(|i| kline(i, 101.0, 99.0, 100.0)).collect();
+        klines.extend((11..16).map(|i| kline(i, 101.0, 99.0, 100.0 + i as f64)));
+        let volatility = realized_volatility(&klines, Duration::minutes(1), 3);
+
+        assert_eq!(volatility[3], Some(0.0));
+        // Candles 11..=13 (indexes 10..=12) have fewer than 3 returns since the gap.
+        assert!(volatility[10..13].iter().all(Option::is_none));
+        assert!(volatility[13].unwrap() > 0.0);
+        assert!(
+            adx(&klines, Duration::minutes(1), 3)[10..]
+                .iter()
+                .all(Option::is_none)
+        );
+    }
+
+    #[test]
+    fn test_labels_follow_thresholds() {
+        let config = RegimeConfig::default();
+        assert_eq!(config.trend(30.0), Trend::Trend);
+        assert_eq!(config.trend(10.0), Trend::Range);
+        assert_eq!(config.volatility_bucket(0.2), VolatilityBucket::Low);
+        assert_eq!(config.volatility_bucket(0.5), VolatilityBucket::Normal);
+        assert_eq!(config.volatility_bucket(0.8), VolatilityBucket::High);
+        assert_eq!(config.warm_up(), 28);
+        assert_eq!(Trend::Range.to_string(), "range");
+    }
+}
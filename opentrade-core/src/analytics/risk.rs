@@ -0,0 +1,409 @@
+//! # Volatility and Correlation
+//!
+//! This module computes the rolling historical volatility of each symbol and the
+//! pairwise correlation of their returns over a window of closed candles, for risk
+//! dashboards.
+//!
+//! Both metrics are based on the log returns of consecutive close prices. A return
+//! is keyed by the start time of the candle it ends on, and returns across a
+//! missing candle are skipped rather than spanning the gap. Volatility is the
+//! sample standard deviation of the returns, annualized by the square root of the
+//! number of candles in a 365-day year. Correlations are the Pearson correlation of
+//! the returns both symbols have at the same times.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use opentrade_core::analytics::risk::RiskReport;
+//! use opentrade_core::models::DEFAULT_DATASET;
+//! use chrono::Utc;
+//! # use anyhow::Result;
+//!
+//! # async fn example(pool: sqlx::PgPool) -> Result<()> {
+//! let symbols = ["BTCUSDT".to_string(), "ETHUSDT".to_string()];
+//! let report =
+//!     RiskReport::compute(&pool, &symbols, "1h", DEFAULT_DATASET, "binance", Utc::now(), 720)
+//!         .await?;
+//! println!("{:?}", report.correlations.get("BTCUSDT", "ETHUSDT"));
+//! report.persist(&pool).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+use crate::analytics::to_f64;
+use crate::ingest::audit::interval_duration;
+use crate::models::KlineData;
+use crate::models::risk::{CorrelationMetric, VolatilityMetric};
+
+/// The minimum number of returns needed for a volatility or correlation.
+const MIN_SAMPLES: usize = 2;
+
+/// A log return, keyed by the start time of the candle it ends on.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Return {
+    /// The start time of the candle whose close ends the return.
+    pub time: DateTime<Utc>,
+    /// The natural logarithm of the close price ratio.
+    pub value: f64,
+}
+
+/// Computes the log returns of candles sorted by start time.
+///
+/// Returns spanning a missing candle, and returns involving a non-positive close
+/// price, are skipped.
+///
+/// # Arguments
+///
+/// * `klines` - The candles, sorted by start time.
+/// * `step` - The length of the candle interval.
+pub fn log_returns(klines: &[KlineData], step: Duration) -> Vec<Return> {
+    klines
+        .windows(2)
+        .filter(|pair| pair[1].start_time - pair[0].start_time == step)
+        .filter_map(|pair| {
+            let previous = to_f64(&pair[0].close);
+            let current = to_f64(&pair[1].close);
+            (previous > 0.0 && current > 0.0).then(|| Return {
+                time: pair[1].start_time,
+                value: (current / previous).ln(),
+            })
+        })
+        .collect()
+}
+
+/// Returns the number of candles of length `step` in a 365-day year.
+pub fn periods_per_year(step: Duration) -> f64 {
+    Duration::days(365).num_milliseconds() as f64 / step.num_milliseconds() as f64
+}
+
+/// Returns the annualized sample standard deviation of the returns, or `None`
+/// with fewer than two returns.
+pub fn volatility(returns: &[f64], periods_per_year: f64) -> Option<f64> {
+    if returns.len() < MIN_SAMPLES {
+        return None;
+    }
+    let n = returns.len() as f64;
+    let mean = returns.iter().sum::<f64>() / n;
+    let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    Some((variance * periods_per_year).sqrt())
+}
+
+/// Computes the rolling volatility over every `window` consecutive returns,
+/// keyed by the time of the last return in each window.
+pub fn rolling_volatility(
+    returns: &[Return],
+    window: usize,
+    periods_per_year: f64,
+) -> Vec<(DateTime<Utc>, f64)> {
+    if window < MIN_SAMPLES {
+        return Vec::new();
+    }
+    returns
+        .windows(window)
+        .filter_map(|slice| {
+            let values: Vec<f64> = slice.iter().map(|r| r.value).collect();
+            volatility(&values, periods_per_year).map(|v| (slice[window - 1].time, v))
+        })
+        .collect()
+}
+
+/// Computes the Pearson correlation of the returns two series have at the same
+/// times, with the number of such returns.
+///
+/// Returns `None` with fewer than two common returns or if either series is
+/// constant over them.
+pub fn correlation(a: &[Return], b: &[Return]) -> Option<(f64, usize)> {
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        match a[i].time.cmp(&b[j].time) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                pairs.push((a[i].value, b[j].value));
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    if pairs.len() < MIN_SAMPLES {
+        return None;
+    }
+    let n = pairs.len() as f64;
+    let mean_a = pairs.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_b = pairs.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let (mut covariance, mut variance_a, mut variance_b) = (0.0, 0.0, 0.0);
+    for (x, y) in &pairs {
+        covariance += (x - mean_a) * (y - mean_b);
+        variance_a += (x - mean_a).powi(2);
+        variance_b += (y - mean_b).powi(2);
+    }
+    if variance_a == 0.0 || variance_b == 0.0 {
+        return None;
+    }
+    Some((covariance / (variance_a * variance_b).sqrt(), pairs.len()))
+}
+
+/// A symmetric matrix of pairwise return correlations.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CorrelationMatrix {
+    /// The symbols, in the order of the rows and columns.
+    pub symbols: Vec<String>,
+    /// The correlations; `None` where too few common returns were available.
+    pub values: Vec<Vec<Option<f64>>>,
+}
+
+impl CorrelationMatrix {
+    /// Builds the matrix from the returns of each symbol.
+    pub fn from_returns(series: &[(String, Vec<Return>)]) -> Self {
+        let symbols = series.iter().map(|(symbol, _)| symbol.clone()).collect();
+        let values = series
+            .iter()
+            .map(|(_, a)| {
+                series
+                    .iter()
+                    .map(|(_, b)| correlation(a, b).map(|(value, _)| value))
+                    .collect()
+            })
+            .collect();
+        Self { symbols, values }
+    }
+
+    /// Returns the correlation of two symbols, if both are in the matrix and it
+    /// could be computed.
+    pub fn get(&self, a: &str, b: &str) -> Option<f64> {
+        let i = self.symbols.iter().position(|symbol| symbol == a)?;
+        let j = self.symbols.iter().position(|symbol| symbol == b)?;
+        self.values[i][j]
+    }
+}
+
+/// The volatility and correlations of a set of symbols over one window.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RiskReport {
+    /// The volatility of each symbol with enough returns in the window.
+    pub volatility: Vec<VolatilityMetric>,
+    /// The correlation of each pair of symbols with enough common returns.
+    pub pairs: Vec<CorrelationMetric>,
+    /// The correlations as a matrix over all symbols.
+    pub correlations: CorrelationMatrix,
+}
+
+impl RiskReport {
+    /// Loads the `window` closed candles before `as_of` for each symbol and
+    /// computes their volatility and pairwise correlations.
+    ///
+    /// `as_of` is truncated to the start of the candle open at that time, so the
+    /// window only holds closed candles.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `symbols` - The symbols to compare.
+    /// * `interval` - The Kline interval; calendar intervals are not supported.
+    /// * `dataset` - The dataset label.
+    /// * `exchange` - The exchange the candles were captured from (e.g., "binance").
+    /// * `as_of` - The end of the window.
+    /// * `window` - The number of returns in the window.
+    pub async fn compute(
+        pool: &sqlx::PgPool,
+        symbols: &[String],
+        interval: &str,
+        dataset: &str,
+        exchange: &str,
+        as_of: DateTime<Utc>,
+        window: usize,
+    ) -> Result<Self> {
+        let step = interval_duration(interval)
+            .with_context(|| format!("Unsupported interval for risk metrics: {}", interval))?;
+        if window < MIN_SAMPLES {
+            bail!("The window must hold at least {} returns", MIN_SAMPLES);
+        }
+        let window_size = i32::try_from(window).context("The window is too large")?;
+        let as_of = truncate(as_of, step);
+        let start = as_of - step * (window_size + 1);
+
+        let mut series = Vec::with_capacity(symbols.len());
+        for symbol in symbols {
+            let klines =
+                KlineData::list_range(pool, symbol, interval, start, as_of, dataset, exchange)
+                    .await
+                    .with_context(|| format!("Failed to load candles of {}", symbol))?;
+            series.push((symbol.clone(), log_returns(&klines, step)));
+        }
+        Ok(Self::from_returns(
+            &series,
+            interval,
+            dataset,
+            as_of,
+            window_size,
+            step,
+        ))
+    }
+
+    /// Builds the report from the returns of each symbol over the window.
+    fn from_returns(
+        series: &[(String, Vec<Return>)],
+        interval: &str,
+        dataset: &str,
+        as_of: DateTime<Utc>,
+        window_size: i32,
+        step: Duration,
+    ) -> Self {
+        let annualization = periods_per_year(step);
+        let volatility = series
+            .iter()
+            .filter_map(|(symbol, returns)| {
+                let values: Vec<f64> = returns.iter().map(|r| r.value).collect();
+                Some(VolatilityMetric {
+                    symbol: symbol.clone(),
+                    interval: interval.to_string(),
+                    dataset: dataset.to_string(),
+                    window_size,
+                    as_of,
+                    volatility: volatility(&values, annualization)?,
+                })
+            })
+            .collect();
+
+        let mut pairs = Vec::new();
+        for (i, (symbol_a, a)) in series.iter().enumerate() {
+            for (symbol_b, b) in &series[i + 1..] {
+                let Some((correlation, samples)) = correlation(a, b) else {
+                    continue;
+                };
+                let (symbol_a, symbol_b) = if symbol_a <= symbol_b {
+                    (symbol_a, symbol_b)
+                } else {
+                    (symbol_b, symbol_a)
+                };
+                pairs.push(CorrelationMetric {
+                    symbol_a: symbol_a.clone(),
+                    symbol_b: symbol_b.clone(),
+                    interval: interval.to_string(),
+                    dataset: dataset.to_string(),
+                    window_size,
+                    as_of,
+                    correlation,
+                    samples: samples as i32,
+                });
+            }
+        }
+
+        Self {
+            volatility,
+            pairs,
+            correlations: CorrelationMatrix::from_returns(series),
+        }
+    }
+
+    /// Stores the volatility and pair correlations, replacing previous values
+    /// for the same window.
+    pub async fn persist(&self, pool: &sqlx::PgPool) -> Result<()> {
+        for metric in &self.volatility {
+            metric.upsert(pool).await?;
+        }
+        for metric in &self.pairs {
+            metric.upsert(pool).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Truncates a time to the start of the candle of length `step` open at it.
+fn truncate(time: DateTime<Utc>, step: Duration) -> DateTime<Utc> {
+    crate::time::align_down(time, step)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn returns(values: &[f64]) -> Vec<Return> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| Return {
+                time: DateTime::from_timestamp(60 * i as i64, 0).unwrap(),
+                value: *value,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_volatility_is_annualized_sample_deviation() {
+        // Sample standard deviation of [0.01, -0.01, 0.01, -0.01] is sqrt(0.0004 / 3).
+        let expected = (0.0004f64 / 3.0 * 365.0).sqrt();
+        let vol = volatility(&[0.01, -0.01, 0.01, -0.01], 365.0).unwrap();
+        assert!((vol - expected).abs() < 1e-12);
+        assert_eq!(volatility(&[0.01], 365.0), None);
+        assert_eq!(periods_per_year(Duration::days(1)), 365.0);
+    }
+
+    #[test]
+    fn test_rolling_volatility_windows() {
+        let series = returns(&[0.01, -0.01, 0.02, 0.0]);
+        let rolling = rolling_volatility(&series, 3, 1.0);
+        assert_eq!(rolling.len(), 2);
+        assert_eq!(rolling[0].0, series[2].time);
+        assert_eq!(rolling[1].0, series[3].time);
+    }
+
+    #[test]
+    fn test_correlation_aligns_on_time() {
+        let a = returns(&[0.01, 0.02, -0.01, 0.03]);
+        let b = returns(&[0.02, 0.04, -0.02, 0.06]);
+        let (value, samples) = correlation(&a, &b).unwrap();
+        assert!((value - 1.0).abs() < 1e-12);
+        assert_eq!(samples, 4);
+
+        let inverse: Vec<Return> = a
+            .iter()
+            .map(|r| Return {
+                value: -r.value,
+                ..*r
+            })
+            .collect();
+        assert!((correlation(&a, &inverse).unwrap().0 + 1.0).abs() < 1e-12);
+
+        // Only the last two returns of `a` overlap with `late`.
+        let late = vec![a[2], a[3]];
+        assert_eq!(correlation(&a, &late).unwrap().1, 2);
+        assert_eq!(correlation(&a, &returns(&[0.0, 0.0, 0.0])), None);
+    }
+
+    #[test]
+    fn test_report_orders_pairs_and_fills_matrix() {
+        let series = vec![
+            ("ETHUSDT".to_string(), returns(&[0.01, 0.02, -0.01])),
+            ("BTCUSDT".to_string(), returns(&[0.02, 0.03, -0.02])),
+        ];
+        let as_of = DateTime::from_timestamp(180, 0).unwrap();
+        let report =
+            RiskReport::from_returns(&series, "1m", "default", as_of, 3, Duration::minutes(1));
+
+        assert_eq!(report.volatility.len(), 2);
+        assert_eq!(report.pairs.len(), 1);
+        assert_eq!(report.pairs[0].symbol_a, "BTCUSDT");
+        assert_eq!(report.pairs[0].symbol_b, "ETHUSDT");
+        assert_eq!(report.pairs[0].samples, 3);
+        assert_eq!(
+            report.correlations.get("BTCUSDT", "ETHUSDT"),
+            report.correlations.get("ETHUSDT", "BTCUSDT")
+        );
+        assert!((report.correlations.get("BTCUSDT", "BTCUSDT").unwrap() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_truncate_to_candle_start() {
+        let time = DateTime::from_timestamp(3_700, 0).unwrap();
+        assert_eq!(
+            truncate(time, Duration::hours(1)),
+            DateTime::from_timestamp(3_600, 0).unwrap()
+        );
+    }
+}
@@ -0,0 +1,266 @@
+//! # Property-Based Test Data
+//!
+//! This module provides [`proptest`] strategies and [`Arbitrary`] implementations
+//! for the Kline types of this crate, so downstream applications can fuzz their
+//! own handlers, sinks and serializers with realistic market data. It requires
+//! the `proptest` feature.
+//!
+//! Generated candles are consistent the way exchange data is:
+//! - start times are aligned to the candle's interval (see [`Interval::start_of`])
+//!   and end one millisecond before the next candle opens
+//! - the open and close lie between the low and the high, and volumes are
+//!   non-negative decimal strings with up to 8 decimal places
+//! - the last trade id is the first trade id plus the trade count
+//!
+//! Every generated [`KlineData`] therefore passes [`KlineData::validate`].
+//!
+//! ## Example
+//!
+//! ```rust,ignore
+//! use opentrade_core::models::SerdableKlineData;
+//! use proptest::prelude::*;
+//!
+//! proptest! {
+//!     #[test]
+//!     fn handler_accepts_any_kline(kline in any::<SerdableKlineData>()) {
+//!         let json = serde_json::to_string(&kline).unwrap();
+//!         prop_assert!(my_handler(&json).is_ok());
+//!     }
+//! }
+//! ```
+
+use proptest::arbitrary::Arbitrary;
+use proptest::prelude::*;
+use proptest::sample::select;
+use proptest::strategy::BoxedStrategy;
+
+use crate::data_source::websocket::{IngestContext, KlineDetails, KlinePayloadData, Payload};
+use crate::models::event::NormalizedEvent;
+use crate::models::{Interval, KlineData, SerdableKlineData};
+
+/// The earliest generated candle start, 2017-07-14 UTC (milliseconds since the epoch).
+const EARLIEST_START: i64 = 1_500_000_000_000;
+
+/// The latest generated candle start, 2025-01-01 UTC (milliseconds since the epoch).
+const LATEST_START: i64 = 1_735_689_600_000;
+
+/// The exchanges generated candles are labeled with.
+const EXCHANGES: [&str; 5] = ["binance", "bybit", "okx", "coinbase", "kraken"];
+
+/// Formats `units` in steps of 10^-`scale` as a decimal string (e.g., 12345 and 2
+/// as "123.45").
+fn decimal_string(units: u64, scale: u32) -> String {
+    if scale == 0 {
+        return units.to_string();
+    }
+    let divisor = 10u64.pow(scale);
+    format!(
+        "{}.{:0width$}",
+        units / divisor,
+        units % divisor,
+        width = scale as usize
+    )
+}
+
+/// Returns a strategy for trading symbols like "BTCUSDT".
+pub fn symbol() -> impl Strategy<Value = String> {
+    ("[A-Z]{2,6}", select(vec!["USDT", "USDC", "BTC", "ETH"]))
+        .prop_map(|(base, quote)| format!("{}{}", base, quote))
+}
+
+/// Returns a strategy for Kline intervals.
+pub fn interval() -> impl Strategy<Value = Interval> {
+    select(Interval::ALL.to_vec())
+}
+
+/// Returns a strategy for candles of the given interval, see the
+/// [module documentation](self) for their guarantees.
+pub fn serdable_kline_with_interval(
+    interval: Interval,
+) -> impl Strategy<Value = SerdableKlineData> {
+    (
+        (symbol(), EARLIEST_START..LATEST_START, 0u32..=8),
+        (
+            1u64..1_000_000_000_000,
+            0u64..=1000,
+            0u64..=1000,
+            0u64..=1000,
+        ),
+        (
+            0u64..1_000_000_000_000,
+            0u64..1_000_000_000_000,
+            0u64..100_000,
+        ),
+        (0i64..1_000_000_000_000, any::<bool>()),
+        (
+            proptest::option::of(0i64..=60_000),
+            proptest::option::of(select(EXCHANGES.to_vec())),
+        ),
+    )
+        .prop_map(
+            move |(
+                (symbol, start, scale),
+                (low, spread, open, close),
+                (volume, quote_volume, trade_count),
+                (first_trade_id, is_final),
+                (event_delay, exchange),
+            )| {
+                let start_time =
+                    interval.start_of(crate::time::from_millis(start).unwrap_or_default());
+                let end_time = interval.end_of(start_time);
+                // Spreads are in thousandths of the low, so prices stay plausible.
+                let high = low + low / 1000 * spread;
+                let price = |offset: u64| low + (high - low) / 1000 * offset;
+                SerdableKlineData {
+                    start_time: start_time.timestamp_millis() as u64,
+                    end_time: end_time.timestamp_millis() as u64,
+                    symbol,
+                    interval: interval.to_string(),
+                    first_trade_id,
+                    last_trade_id: first_trade_id + trade_count as i64,
+                    open: decimal_string(price(open), scale),
+                    close: decimal_string(price(close), scale),
+                    high: decimal_string(high, scale),
+                    low: decimal_string(low, scale),
+                    volume: decimal_string(volume, scale),
+                    trade_count,
+                    quote_volume: decimal_string(quote_volume, scale),
+                    event_time: event_delay
+                        .map(|delay| (start_time.timestamp_millis() + delay) as u64),
+                    is_final,
+                    exchange: exchange.map(str::to_string),
+                }
+            },
+        )
+}
+
+/// Returns a strategy for candles of any interval.
+pub fn serdable_kline() -> impl Strategy<Value = SerdableKlineData> {
+    interval().prop_flat_map(serdable_kline_with_interval)
+}
+
+/// Returns a strategy for stored candles of any interval.
+pub fn kline_data() -> impl Strategy<Value = KlineData> {
+    serdable_kline().prop_map(KlineData::from)
+}
+
+/// Returns a strategy for Binance WebSocket Kline messages, as parsed by
+/// [`KlineStreaming`](crate::data_source::websocket::KlineStreaming).
+pub fn payload() -> impl Strategy<Value = Payload> {
+    (serdable_kline(), 0u64..=1000, 0u64..=1000).prop_map(|(kline, taker_base, taker_quote)| {
+        let stream = format!("{}@kline_{}", kline.symbol.to_lowercase(), kline.interval);
+        Payload {
+            stream,
+            data: KlinePayloadData {
+                event_type: "kline".to_string(),
+                event_time: kline.event_time.unwrap_or(kline.start_time),
+                symbol: kline.symbol.clone(),
+                kline: KlineDetails {
+                    start_time: kline.start_time,
+                    end_time: kline.end_time,
+                    symbol: kline.symbol,
+                    interval: kline.interval,
+                    first_trade_id: kline.first_trade_id as u64,
+                    last_trade_id: kline.last_trade_id as u64,
+                    open: kline.open,
+                    close: kline.close,
+                    high: kline.high,
+                    low: kline.low,
+                    taker_buy_base_volume: decimal_string(taker_base, 3),
+                    taker_buy_quote_volume: decimal_string(taker_quote, 3),
+                    volume: kline.volume,
+                    trade_count: kline.trade_count,
+                    is_final: kline.is_final,
+                    quote_volume: kline.quote_volume,
+                    ignore: "0".to_string(),
+                },
+            },
+        }
+    })
+}
+
+/// Returns a strategy for normalized Kline events.
+pub fn normalized_event() -> impl Strategy<Value = NormalizedEvent> {
+    (serdable_kline(), 0i64..=5_000).prop_map(|(kline, delay)| {
+        let exchange = kline.exchange.as_deref().unwrap_or("binance");
+        let stream = format!("{}@kline_{}", kline.symbol.to_lowercase(), kline.interval);
+        let mut event = NormalizedEvent::from_kline(&kline, &IngestContext::new(exchange, &stream));
+        event.received_at = kline.end_time as i64 + delay;
+        event
+    })
+}
+
+impl Arbitrary for Interval {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        interval().boxed()
+    }
+}
+
+impl Arbitrary for SerdableKlineData {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        serdable_kline().boxed()
+    }
+}
+
+impl Arbitrary for KlineData {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        kline_data().boxed()
+    }
+}
+
+impl Arbitrary for Payload {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        payload().boxed()
+    }
+}
+
+impl Arbitrary for NormalizedEvent {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        normalized_event().boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decimal_string() {
+        assert_eq!(decimal_string(12345, 2), "123.45");
+        assert_eq!(decimal_string(5, 3), "0.005");
+        assert_eq!(decimal_string(7, 0), "7");
+    }
+
+    proptest! {
+        #[test]
+        fn test_generated_klines_validate(kline in any::<KlineData>()) {
+            prop_assert!(kline.validate().is_ok(), "{:?}", kline);
+            let interval: Interval = kline.interval.parse().unwrap();
+            prop_assert_eq!(interval.start_of(kline.start_time), kline.start_time);
+            prop_assert_eq!(interval.end_of(kline.start_time), kline.end_time);
+        }
+
+        #[test]
+        fn test_generated_payloads_roundtrip(payload in any::<Payload>()) {
+            let json = serde_json::to_string(&payload).unwrap();
+            let parsed: Payload = serde_json::from_str(&json).unwrap();
+            prop_assert_eq!(parsed.data.kline.open, payload.data.kline.open);
+            prop_assert_eq!(parsed.stream, payload.stream);
+        }
+    }
+}
@@ -0,0 +1,428 @@
+//! # API Key Rotation
+//!
+//! Public market data endpoints only count request weight per IP, but
+//! authenticated endpoints are also limited per API key, e.g. the order count
+//! limits reported with error code -1015. Spreading requests over several keys
+//! raises those limits. [`ApiKeyPool`] holds the configured keys and picks one per
+//! request:
+//!
+//! - [`Rotation::RoundRobin`] cycles through the keys in their configured order.
+//! - [`Rotation::LeastUsed`] picks the key that has sent the fewest requests.
+//!
+//! The pool tracks the health of every key. A key rate limited on its own is
+//! skipped for the `Retry-After` duration, and a key the exchange rejects (e.g.,
+//! revoked or without the needed permissions) is skipped for
+//! [`REJECTED_KEY_COOLDOWN`]. The health of the keys is available from
+//! [`ApiKeyPool::health`] for exporting as metrics.
+//!
+//! All Binance REST requests of the process share [`BINANCE_API_KEYS`], which the
+//! [`rest`](super::rest) client sends in the [`API_KEY_HEADER`] header. Without
+//! configured keys, requests are sent unauthenticated.
+//!
+//! ## Configuration
+//!
+//! Keys are read from the `BINANCE_API_KEYS` environment variable as a comma
+//! separated list of keys, optionally labeled with `label=` so logs and metrics do
+//! not show them. The rotation is read from `BINANCE_API_KEY_ROTATION`, either
+//! "round-robin" (the default) or "least-used":
+//!
+//! ```bash
+//! BINANCE_API_KEYS="ops=vmPUZE6mv9SD5V,research=NhqPtmdSJYdKjVHjA" \
+//! BINANCE_API_KEY_ROTATION=least-used cargo run --bin backfill_klines -- ...
+//! ```
+//!
+//! ## Example
+//!
+//! ```rust
+//! use opentrade_core::data_source::api_keys::{ApiKeyPool, KeyOutcome, Rotation, parse_api_keys};
+//!
+//! let keys = parse_api_keys("ops=first,research=second").unwrap();
+//! let pool = ApiKeyPool::new(keys, Rotation::RoundRobin);
+//! let key = pool.select().unwrap();
+//! assert_eq!(key.label(), "ops");
+//! key.report(KeyOutcome::Rejected);
+//! assert_eq!(pool.select().unwrap().label(), "research");
+//! assert_eq!(pool.select().unwrap().label(), "research");
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+use anyhow::{Result, bail};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// The environment variable API keys are read from.
+pub const API_KEYS_ENV: &str = "BINANCE_API_KEYS";
+
+/// The environment variable the [`Rotation`] is read from.
+pub const API_KEY_ROTATION_ENV: &str = "BINANCE_API_KEY_ROTATION";
+
+/// The header Binance expects the API key in.
+pub const API_KEY_HEADER: &str = "X-MBX-APIKEY";
+
+/// How long a key the exchange rejected is skipped.
+pub const REJECTED_KEY_COOLDOWN: Duration = Duration::from_secs(3600);
+
+/// The API keys shared by all Binance REST requests of the process, read from
+/// the environment on first use. `None` if no keys are configured, or if the
+/// configuration is invalid, which is logged.
+pub static BINANCE_API_KEYS: LazyLock<Option<ApiKeyPool>> =
+    LazyLock::new(|| match ApiKeyPool::from_env() {
+        Ok(pool) => pool,
+        Err(e) => {
+            log::error!("Ignoring the configured API keys: {:#}", e);
+            None
+        }
+    });
+
+/// How an [`ApiKeyPool`] picks the key of the next request.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Rotation {
+    /// Cycles through the keys in their configured order.
+    #[default]
+    RoundRobin,
+    /// Picks the key that has sent the fewest requests, the first one on ties.
+    LeastUsed,
+}
+
+impl fmt::Display for Rotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rotation::RoundRobin => write!(f, "round-robin"),
+            Rotation::LeastUsed => write!(f, "least-used"),
+        }
+    }
+}
+
+impl FromStr for Rotation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "round-robin" => Ok(Rotation::RoundRobin),
+            "least-used" => Ok(Rotation::LeastUsed),
+            _ => Err(format!(
+                "unknown key rotation '{}', expected 'round-robin' or 'least-used'",
+                s
+            )),
+        }
+    }
+}
+
+/// A labeled API key. The key itself is never printed.
+#[derive(Clone, PartialEq, Eq)]
+pub struct ApiKey {
+    /// The label shown in logs and metrics.
+    pub label: String,
+    key: String,
+}
+
+impl ApiKey {
+    /// Creates a labeled API key.
+    pub fn new(label: &str, key: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            key: key.to_string(),
+        }
+    }
+
+    /// Returns the key sent to the exchange.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+}
+
+impl fmt::Debug for ApiKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ApiKey")
+            .field("label", &self.label)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Parses a comma separated list of API keys, each optionally labeled as
+/// `label=key`. Unlabeled keys are labeled by their position, e.g. "key-2".
+///
+/// # Errors
+///
+/// Returns an error if a key or label is empty, or a label is used twice.
+pub fn parse_api_keys(value: &str) -> Result<Vec<ApiKey>> {
+    let mut keys: Vec<ApiKey> = Vec::new();
+    for (index, entry) in value.split(',').map(str::trim).enumerate() {
+        let (label, key) = match entry.split_once('=') {
+            Some((label, key)) => (label.trim().to_string(), key.trim()),
+            None => (format!("key-{}", index + 1), entry),
+        };
+        if label.is_empty() || key.is_empty() {
+            bail!("Invalid API key entry {} in {}", index + 1, API_KEYS_ENV);
+        }
+        if keys.iter().any(|existing| existing.label == label) {
+            bail!("Duplicate API key label '{}' in {}", label, API_KEYS_ENV);
+        }
+        keys.push(ApiKey::new(&label, key));
+    }
+    Ok(keys)
+}
+
+/// The outcome of a request sent with a key, reported to its pool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyOutcome {
+    /// The exchange accepted the key.
+    Success,
+    /// The key exceeded a limit of its own and may be used again after the
+    /// given duration.
+    RateLimited(Duration),
+    /// The exchange rejected the key, e.g. because it is invalid or lacks a
+    /// permission.
+    Rejected,
+}
+
+/// The health of a key of an [`ApiKeyPool`], for exporting as metrics.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct KeyHealth {
+    /// The label of the key.
+    pub label: String,
+    /// The number of requests sent with the key.
+    pub requests: u64,
+    /// The number of times the key was rate limited.
+    pub rate_limited: u64,
+    /// The number of times the exchange rejected the key.
+    pub rejected: u64,
+    /// The time until which the key is skipped, if it is.
+    pub disabled_until: Option<DateTime<Utc>>,
+}
+
+impl KeyHealth {
+    /// Returns true if the key is used for requests at `now`.
+    pub fn is_healthy(&self, now: DateTime<Utc>) -> bool {
+        self.disabled_until.is_none_or(|until| until <= now)
+    }
+}
+
+impl fmt::Display for KeyHealth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} requests, {} rate limited, {} rejected",
+            self.label, self.requests, self.rate_limited, self.rejected
+        )?;
+        if let Some(until) = self.disabled_until.filter(|_| !self.is_healthy(Utc::now())) {
+            write!(f, ", disabled until {}", until.format("%Y-%m-%d %H:%M:%S"))?;
+        }
+        Ok(())
+    }
+}
+
+/// A set of API keys that requests rotate through.
+#[derive(Debug)]
+pub struct ApiKeyPool {
+    keys: Vec<ApiKey>,
+    rotation: Rotation,
+    state: Mutex<PoolState>,
+}
+
+/// The position of a round robin rotation and the health of every key.
+#[derive(Debug)]
+struct PoolState {
+    /// The index of the key a round robin rotation tries next.
+    next: usize,
+    /// The health of every key, by index.
+    health: Vec<KeyHealth>,
+}
+
+impl ApiKeyPool {
+    /// Creates a pool of keys.
+    ///
+    /// # Arguments
+    ///
+    /// * `keys` - The keys, in the order a round robin rotation uses them.
+    /// * `rotation` - How the key of the next request is picked.
+    pub fn new(keys: Vec<ApiKey>, rotation: Rotation) -> Self {
+        let health = keys
+            .iter()
+            .map(|key| KeyHealth {
+                label: key.label.clone(),
+                requests: 0,
+                rate_limited: 0,
+                rejected: 0,
+                disabled_until: None,
+            })
+            .collect();
+        Self {
+            keys,
+            rotation,
+            state: Mutex::new(PoolState { next: 0, health }),
+        }
+    }
+
+    /// Creates a pool from the [`API_KEYS_ENV`] and [`API_KEY_ROTATION_ENV`]
+    /// environment variables.
+    ///
+    /// # Returns
+    ///
+    /// `None` if no keys are configured.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the keys or the rotation are invalid.
+    pub fn from_env() -> Result<Option<Self>> {
+        let keys = match std::env::var(API_KEYS_ENV) {
+            Ok(value) if !value.trim().is_empty() => parse_api_keys(&value)?,
+            _ => return Ok(None),
+        };
+        let rotation = match std::env::var(API_KEY_ROTATION_ENV) {
+            Ok(value) => value.parse().map_err(anyhow::Error::msg)?,
+            Err(_) => Rotation::default(),
+        };
+        Ok(Some(Self::new(keys, rotation)))
+    }
+
+    /// Returns the rotation of the pool.
+    pub fn rotation(&self) -> Rotation {
+        self.rotation
+    }
+
+    /// Picks the key of the next request and counts the request.
+    ///
+    /// # Returns
+    ///
+    /// The key, or `None` if every key is disabled.
+    pub fn select(&self) -> Option<SelectedKey<'_>> {
+        let index = self.select_at(Utc::now())?;
+        Some(SelectedKey { pool: self, index })
+    }
+
+    /// Reports the outcome of a request sent with the key at `index`.
+    fn report(&self, index: usize, outcome: KeyOutcome) {
+        self.report_at(index, outcome, Utc::now());
+    }
+
+    /// Returns the health of every key, in their configured order.
+    pub fn health(&self) -> Vec<KeyHealth> {
+        self.state.lock().unwrap().health.clone()
+    }
+
+    fn select_at(&self, now: DateTime<Utc>) -> Option<usize> {
+        let mut state = self.state.lock().unwrap();
+        let count = state.health.len();
+        let healthy = |health: &KeyHealth| health.is_healthy(now);
+        let index = match self.rotation {
+            Rotation::RoundRobin => (0..count)
+                .map(|offset| (state.next + offset) % count)
+                .find(|&index| healthy(&state.health[index]))?,
+            Rotation::LeastUsed => (0..count)
+                .filter(|&index| healthy(&state.health[index]))
+                .min_by_key(|&index| state.health[index].requests)?,
+        };
+        state.next = (index + 1) % count;
+        state.health[index].requests += 1;
+        Some(index)
+    }
+
+    fn report_at(&self, index: usize, outcome: KeyOutcome, now: DateTime<Utc>) {
+        let mut state = self.state.lock().unwrap();
+        let health = &mut state.health[index];
+        let cooldown = match outcome {
+            KeyOutcome::Success => return,
+            KeyOutcome::RateLimited(retry_after) => {
+                health.rate_limited += 1;
+                retry_after
+            }
+            KeyOutcome::Rejected => {
+                health.rejected += 1;
+                REJECTED_KEY_COOLDOWN
+            }
+        };
+        let until = chrono::Duration::from_std(cooldown)
+            .ok()
+            .and_then(|cooldown| now.checked_add_signed(cooldown))
+            .unwrap_or(DateTime::<Utc>::MAX_UTC);
+        health.disabled_until = health.disabled_until.max(Some(until));
+        log::warn!(
+            "API key {} disabled until {} ({:?})",
+            health.label,
+            until.format("%Y-%m-%d %H:%M:%S"),
+            outcome
+        );
+    }
+}
+
+/// A key picked by [`ApiKeyPool::select`] for one request.
+#[derive(Debug, Clone, Copy)]
+pub struct SelectedKey<'a> {
+    pool: &'a ApiKeyPool,
+    index: usize,
+}
+
+impl SelectedKey<'_> {
+    /// Returns the label of the key.
+    pub fn label(&self) -> &str {
+        &self.pool.keys[self.index].label
+    }
+
+    /// Returns the key sent to the exchange.
+    pub fn key(&self) -> &str {
+        self.pool.keys[self.index].key()
+    }
+
+    /// Reports the outcome of the request to the pool.
+    pub fn report(&self, outcome: KeyOutcome) {
+        self.pool.report(self.index, outcome);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(rotation: Rotation) -> ApiKeyPool {
+        ApiKeyPool::new(parse_api_keys("a=1,b=2,c=3").unwrap(), rotation)
+    }
+
+    #[test]
+    fn test_parse_api_keys() {
+        let keys = parse_api_keys("ops=abc, def").unwrap();
+        assert_eq!(keys[0].label, "ops");
+        assert_eq!(keys[0].key(), "abc");
+        assert_eq!(keys[1].label, "key-2");
+        assert!(!format!("{:?}", keys[1]).contains("def"));
+        assert!(parse_api_keys("ops=abc,ops=def").is_err());
+        assert!(parse_api_keys("ops=").is_err());
+        assert_eq!("least-used".parse(), Ok(Rotation::LeastUsed));
+    }
+
+    #[test]
+    fn test_round_robin_skips_disabled_keys() {
+        let pool = pool(Rotation::RoundRobin);
+        let now = Utc::now();
+        assert_eq!(pool.select_at(now), Some(0));
+        pool.report_at(1, KeyOutcome::RateLimited(Duration::from_secs(60)), now);
+        assert_eq!(pool.select_at(now), Some(2));
+        assert_eq!(pool.select_at(now), Some(0));
+        let later = now + chrono::Duration::seconds(61);
+        assert_eq!(pool.select_at(later), Some(1));
+
+        let health = pool.health();
+        assert_eq!(health[0].requests, 2);
+        assert_eq!(health[1].rate_limited, 1);
+        assert!(!health[1].is_healthy(now));
+        assert!(health[1].is_healthy(later));
+    }
+
+    #[test]
+    fn test_least_used_balances_requests() {
+        let pool = pool(Rotation::LeastUsed);
+        let now = Utc::now();
+        pool.report_at(0, KeyOutcome::Rejected, now);
+        let picked: Vec<_> = (0..4).filter_map(|_| pool.select_at(now)).collect();
+        assert_eq!(picked, vec![1, 2, 1, 2]);
+
+        pool.report_at(1, KeyOutcome::Rejected, now);
+        pool.report_at(2, KeyOutcome::Rejected, now);
+        assert_eq!(pool.select_at(now), None);
+        assert_eq!(pool.health()[0].rejected, 1);
+    }
+}
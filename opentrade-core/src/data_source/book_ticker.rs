@@ -0,0 +1,260 @@
+//! # Book Ticker Streams
+//!
+//! The `<symbol>@bookTicker` streams push the best bid and ask price and quantity
+//! of a symbol whenever they change. Unlike klines, which only carry traded prices,
+//! these quotes show the spread a market order would pay.
+//!
+//! [`BookTickerStreaming`] implements [`StreamingClient<SerdableBookTickerData>`], so
+//! it is managed like the kline streams, and [`UpsertBookTickerSink`] stores the
+//! quotes in the `book_tickers` table.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use opentrade_core::data_source::book_ticker::{BookTickerStreaming, UpsertBookTickerSink};
+//! use opentrade_core::data_source::websocket::StreamingClient;
+//! # use anyhow::Result;
+//!
+//! # async fn example(pool: sqlx::PgPool) -> Result<()> {
+//! let mut stream = BookTickerStreaming::new("BTCUSDT").await?;
+//! stream.add_callback(Box::new(UpsertBookTickerSink::new(pool)));
+//! stream.subscribe().await?;
+//! stream.listen().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+use crate::data_source::depth::SPOT_STREAM_URL;
+use crate::data_source::websocket::{
+    BINANCE_EXCHANGE, Borrowed, IngestContext, MessageHandler, SharedMessageHandler,
+    StreamingClient,
+};
+use crate::ingest::stats::StreamStats;
+use crate::models::DEFAULT_DATASET;
+use crate::models::book_ticker::SerdableBookTickerData;
+
+/// A message of a combined stream, wrapping the event with its stream name.
+#[derive(Deserialize, Debug)]
+struct BookTickerPayload {
+    stream: String,
+    data: SerdableBookTickerData,
+}
+
+/// A WebSocket client streaming the best bid and ask of one symbol.
+pub struct BookTickerStreaming {
+    url: String,
+    stream: String,
+    socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    callbacks: Vec<Box<dyn SharedMessageHandler<SerdableBookTickerData> + Send>>,
+    stats: Option<Arc<StreamStats>>,
+    generation: u64,
+    context: Option<IngestContext>,
+}
+
+impl BookTickerStreaming {
+    /// Connects to the book ticker stream of a symbol.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbol` - The trading symbol (e.g., "BTCUSDT").
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the WebSocket connection cannot be established.
+    pub async fn new(symbol: &str) -> Result<Self> {
+        let (socket, _) = tokio_tungstenite::connect_async(SPOT_STREAM_URL)
+            .await
+            .with_context(|| format!("Failed to connect to {}", SPOT_STREAM_URL))?;
+        Ok(Self {
+            url: SPOT_STREAM_URL.to_string(),
+            stream: format!("{}@bookTicker", symbol.to_lowercase()),
+            socket,
+            callbacks: Vec::new(),
+            stats: None,
+            generation: 0,
+            context: None,
+        })
+    }
+
+    /// Returns the stream name.
+    pub fn stream(&self) -> &str {
+        &self.stream
+    }
+
+    /// Attaches shared [`StreamStats`] that record parse errors, handler errors and
+    /// the latency of the handler chain while [`listen`](StreamingClient::listen) is
+    /// running.
+    pub fn attach_stats(&mut self, stats: Arc<StreamStats>) {
+        self.stats = Some(stats);
+    }
+}
+
+#[async_trait]
+impl StreamingClient<SerdableBookTickerData> for BookTickerStreaming {
+    async fn connect(&mut self) -> Result<()> {
+        let (socket, _) = tokio_tungstenite::connect_async(self.url.as_str())
+            .await
+            .with_context(|| format!("Failed to connect to {}", self.url))?;
+        self.socket = socket;
+        self.generation += 1;
+        Ok(())
+    }
+
+    async fn subscribe(&mut self) -> Result<()> {
+        let request = serde_json::json!({
+            "method": "SUBSCRIBE",
+            "params": [self.stream],
+            "id": 1,
+        });
+        self.socket
+            .send(Message::Text(request.to_string()))
+            .await
+            .with_context(|| format!("Failed to subscribe to {}", self.stream))?;
+        Ok(())
+    }
+
+    async fn next(&mut self) -> Result<Option<Result<SerdableBookTickerData>>> {
+        while let Some(message) = self.socket.next().await {
+            let text = match message {
+                Ok(Message::Text(text)) => text,
+                Ok(Message::Close(_)) => return Ok(None),
+                // Pings are answered by the socket itself.
+                Ok(_) => continue,
+                Err(e) => return Ok(Some(Err(anyhow::Error::msg(e.to_string())))),
+            };
+            match serde_json::from_str::<BookTickerPayload>(&text) {
+                Ok(payload) => {
+                    self.context = Some(
+                        IngestContext::new(BINANCE_EXCHANGE, &payload.stream)
+                            .with_generation(self.generation),
+                    );
+                    return Ok(Some(Ok(payload.data)));
+                }
+                // Replies to requests, e.g. `{"result":null,"id":1}`, are not events.
+                Err(_) if text.contains("\"id\"") && !text.contains("\"stream\"") => continue,
+                Err(e) => {
+                    return Ok(Some(Err(anyhow::anyhow!(
+                        "Failed to parse book ticker: {}: {}",
+                        e,
+                        text
+                    ))));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn context(&self) -> Option<IngestContext> {
+        self.context.clone()
+    }
+
+    fn add_callback(&mut self, handler: Box<dyn MessageHandler<SerdableBookTickerData> + Send>) {
+        self.callbacks.push(Box::new(Borrowed(handler)));
+    }
+
+    fn add_shared_callback(
+        &mut self,
+        handler: Box<dyn SharedMessageHandler<SerdableBookTickerData> + Send>,
+    ) {
+        self.callbacks.push(handler);
+    }
+
+    async fn listen(&mut self) -> Result<()> {
+        while let Some(result) = StreamingClient::next(self).await? {
+            match result {
+                Ok(quote) => {
+                    let quote = Arc::new(quote);
+                    let context = self
+                        .context
+                        .clone()
+                        .unwrap_or_else(|| IngestContext::new(BINANCE_EXCHANGE, &self.stream));
+                    let started_at = Instant::now();
+                    for callback in &mut self.callbacks {
+                        if let Err(e) = callback.handle_shared_with_context(&quote, &context).await
+                        {
+                            if let Some(stats) = &self.stats {
+                                stats.record_error();
+                            }
+                            return Err(e);
+                        }
+                    }
+                    if let Some(stats) = &self.stats {
+                        stats.record_latency(started_at.elapsed());
+                    }
+                }
+                Err(e) => {
+                    if let Some(stats) = &self.stats {
+                        stats.record_error();
+                    }
+                    log::warn!("Error processing book ticker: {}", e);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A [`MessageHandler`] that stores book ticker quotes in the `book_tickers` table,
+/// timestamped with the time they were handled.
+pub struct UpsertBookTickerSink {
+    pool: sqlx::PgPool,
+    dataset: String,
+}
+
+impl UpsertBookTickerSink {
+    /// Creates a sink storing quotes under the [`DEFAULT_DATASET`].
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    pub fn new(pool: sqlx::PgPool) -> Self {
+        Self {
+            pool,
+            dataset: DEFAULT_DATASET.to_string(),
+        }
+    }
+
+    /// Sets the dataset the quotes are stored under.
+    pub fn with_dataset(mut self, dataset: &str) -> Self {
+        self.dataset = dataset.to_string();
+        self
+    }
+}
+
+#[async_trait]
+impl MessageHandler<SerdableBookTickerData> for UpsertBookTickerSink {
+    async fn handle_message(&mut self, message: &SerdableBookTickerData) -> Result<()> {
+        let quote = message
+            .to_book_ticker_data(Utc::now())?
+            .with_dataset(&self.dataset);
+        quote
+            .upsert(&self.pool)
+            .await
+            .with_context(|| format!("Failed to store the book ticker of {}", quote.symbol))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_book_ticker_payload() {
+        let json = r#"{"stream":"bnbusdt@bookTicker","data":{"u":400900217,"s":"BNBUSDT","b":"25.35190000","B":"31.21000000","a":"25.36520000","A":"40.66000000"}}"#;
+        let payload: BookTickerPayload = serde_json::from_str(json).unwrap();
+        assert_eq!(payload.stream, "bnbusdt@bookTicker");
+        assert_eq!(payload.data.ask_price, "25.36520000");
+    }
+}
@@ -0,0 +1,696 @@
+//! # Bybit
+//!
+//! This module implements [`MarketDataSource`] for the spot and USDT perpetual
+//! (linear) markets of Bybit, so its symbols go through the same backfills and
+//! pipelines as Binance symbols:
+//!
+//! - Historical klines are fetched from the `kline` endpoint of the v5 REST API.
+//! - Live klines are streamed from the `kline` topic of the v5 public WebSocket API
+//!   by [`BybitKlineStreaming`].
+//!
+//! A [`Bybit`] source serves one [`BybitCategory`]. Bybit names symbols like
+//! Binance (e.g., "BTCUSDT"), and a symbol can be listed as both a spot pair and a
+//! perpetual contract, so the categories report themselves as different exchanges,
+//! [`BYBIT_EXCHANGE`] and [`BYBIT_LINEAR_EXCHANGE`]. Stored candles record it in
+//! their `exchange` column; store them under their own dataset, as the same symbols
+//! exist on Binance.
+//!
+//! ## Mapping
+//!
+//! - Candles have no trade ids or trade counts, so their trade ids are 0 and their
+//!   trade counts are empty (0 for streamed candles). The turnover is stored as the
+//!   quote volume.
+//! - The REST API returns candles newest first; they are reordered oldest first.
+//! - Streamed candles are final once Bybit confirms them.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use opentrade_core::data_source::bybit::Bybit;
+//! use opentrade_core::data_source::exchange::MarketDataSource;
+//! # use anyhow::Result;
+//!
+//! # async fn example() -> Result<()> {
+//! let source = Bybit::linear();
+//! let symbol = source.normalize_symbol("btc/usdt");
+//! let klines = source.fetch_klines(&symbol, "1h", 1_700_000_000_000, None, Some(24)).await?;
+//! println!("Fetched {} klines from {}", klines.len(), source.name());
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, bail};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use sqlx::types::BigDecimal as Decimal;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+use crate::data_source::exchange::MarketDataSource;
+use crate::data_source::websocket::{
+    Borrowed, IngestContext, MessageHandler, SharedMessageHandler, StreamingClient,
+};
+use crate::ingest::audit::interval_duration;
+use crate::ingest::stats::StreamStats;
+use crate::models::{KlineData, SerdableKlineData};
+
+/// The exchange name of Bybit spot sources.
+pub const BYBIT_EXCHANGE: &str = "bybit";
+
+/// The exchange name of Bybit USDT perpetual sources.
+pub const BYBIT_LINEAR_EXCHANGE: &str = "bybit-linear";
+
+/// The base URL of the market endpoints of the v5 REST API.
+pub const BYBIT_REST_URL: &str = "https://api.bybit.com/v5/market";
+
+/// The base URL of the v5 public WebSocket API, followed by the category.
+pub const BYBIT_STREAM_URL: &str = "wss://stream.bybit.com/v5/public";
+
+/// The maximum number of candles returned by one REST request.
+pub const MAX_KLINE_LIMIT: u32 = 1000;
+
+/// The number of candles returned by one REST request without a limit.
+pub const DEFAULT_KLINE_LIMIT: u32 = 200;
+
+/// How often streams send a ping; Bybit closes connections that stay silent.
+pub const PING_INTERVAL: Duration = Duration::from_secs(20);
+
+/// A market category of Bybit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BybitCategory {
+    /// Spot pairs.
+    Spot,
+    /// USDT and USDC perpetual and futures contracts.
+    Linear,
+}
+
+impl BybitCategory {
+    /// Returns the category as the APIs expect it ("spot" or "linear").
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BybitCategory::Spot => "spot",
+            BybitCategory::Linear => "linear",
+        }
+    }
+
+    /// Returns the exchange name the category reports itself as.
+    pub fn exchange(&self) -> &'static str {
+        match self {
+            BybitCategory::Spot => BYBIT_EXCHANGE,
+            BybitCategory::Linear => BYBIT_LINEAR_EXCHANGE,
+        }
+    }
+}
+
+/// Returns the interval as the Bybit APIs expect it (e.g., "60" for "1h"), or
+/// `None` if Bybit has no candles of that length.
+pub fn kline_interval(interval: &str) -> Option<&'static str> {
+    let interval = match interval {
+        "1m" => "1",
+        "3m" => "3",
+        "5m" => "5",
+        "15m" => "15",
+        "30m" => "30",
+        "1h" => "60",
+        "2h" => "120",
+        "4h" => "240",
+        "6h" => "360",
+        "12h" => "720",
+        "1d" => "D",
+        _ => return None,
+    };
+    Some(interval)
+}
+
+#[derive(Deserialize)]
+struct BybitResponse<T> {
+    #[serde(rename = "retCode")]
+    ret_code: i64,
+    #[serde(rename = "retMsg")]
+    ret_msg: String,
+    result: Option<T>,
+}
+
+impl<T> BybitResponse<T> {
+    fn into_result(self) -> Result<T> {
+        if self.ret_code != 0 {
+            bail!("Bybit returned error {}: {}", self.ret_code, self.ret_msg);
+        }
+        self.result.context("Bybit returned no result")
+    }
+}
+
+#[derive(Deserialize)]
+struct KlineResult {
+    list: Vec<BybitKlineRow>,
+}
+
+#[derive(Deserialize)]
+struct TimeResult {
+    #[serde(rename = "timeNano")]
+    time_nano: String,
+}
+
+/// A candle of the REST API: start time (milliseconds since the epoch), open,
+/// high, low, close, volume and turnover.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BybitKlineRow(
+    pub String,
+    pub String,
+    pub String,
+    pub String,
+    pub String,
+    pub String,
+    pub String,
+);
+
+/// Converts REST candles into klines, oldest first.
+///
+/// # Arguments
+///
+/// * `rows` - The candles, in any order.
+/// * `symbol` - The symbol the klines are labeled with.
+/// * `interval` - The interval the klines are labeled with.
+/// * `step` - The length of `interval`.
+/// * `category` - The category of the market, which names the exchange.
+///
+/// # Errors
+///
+/// Returns an error if a candle has an unparseable field.
+pub fn klines_from_rows(
+    rows: &[BybitKlineRow],
+    symbol: &str,
+    interval: &str,
+    step: chrono::Duration,
+    category: BybitCategory,
+) -> Result<Vec<KlineData>> {
+    fn decimal(value: &str) -> Result<Decimal> {
+        value
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid kline value: {}", value))
+    }
+
+    let step_ms = step.num_milliseconds() as u64;
+    let mut klines = rows
+        .iter()
+        .map(|row| {
+            let BybitKlineRow(start, open, high, low, close, volume, turnover) = row;
+            let start: u64 = start
+                .parse()
+                .with_context(|| format!("Invalid kline start time: {}", start))?;
+            Ok(KlineData::new(
+                &start,
+                &(start + step_ms - 1),
+                symbol,
+                interval,
+                0,
+                0,
+                decimal(open)?,
+                decimal(high)?,
+                decimal(low)?,
+                decimal(close)?,
+                decimal(volume)?,
+                None,
+                Some(decimal(turnover)?),
+            )
+            .with_exchange(category.exchange()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    klines.sort_by_key(|kline| kline.start_time);
+    Ok(klines)
+}
+
+/// The Bybit exchange, for the markets of one category.
+#[derive(Debug, Clone)]
+pub struct Bybit {
+    category: BybitCategory,
+    client: reqwest::Client,
+}
+
+impl Bybit {
+    /// Creates a source for the markets of a category.
+    pub fn new(category: BybitCategory) -> Self {
+        Self {
+            category,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Creates a source for spot pairs.
+    pub fn spot() -> Self {
+        Self::new(BybitCategory::Spot)
+    }
+
+    /// Creates a source for USDT perpetual contracts.
+    pub fn linear() -> Self {
+        Self::new(BybitCategory::Linear)
+    }
+
+    /// Returns the category of the source.
+    pub fn category(&self) -> BybitCategory {
+        self.category
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str, query: &[(&str, String)]) -> Result<T> {
+        let response: BybitResponse<T> = self
+            .client
+            .get(format!("{}/{}", BYBIT_REST_URL, path))
+            .query(query)
+            .send()
+            .await
+            .with_context(|| format!("Failed to request Bybit {}", path))?
+            .error_for_status()
+            .with_context(|| format!("Bybit rejected the {} request", path))?
+            .json()
+            .await
+            .with_context(|| format!("Failed to parse the Bybit {} response", path))?;
+        response.into_result()
+    }
+}
+
+#[async_trait]
+impl MarketDataSource for Bybit {
+    fn name(&self) -> &str {
+        self.category.exchange()
+    }
+
+    fn normalize_symbol(&self, symbol: &str) -> String {
+        symbol
+            .chars()
+            .filter(char::is_ascii_alphanumeric)
+            .collect::<String>()
+            .to_uppercase()
+    }
+
+    fn max_kline_limit(&self) -> u32 {
+        MAX_KLINE_LIMIT
+    }
+
+    fn default_kline_limit(&self) -> u32 {
+        DEFAULT_KLINE_LIMIT
+    }
+
+    async fn fetch_klines(
+        &self,
+        symbol: &str,
+        interval: &str,
+        start_time: u64,
+        end_time: Option<u64>,
+        limit: Option<u32>,
+    ) -> Result<Vec<KlineData>> {
+        let bybit_interval = kline_interval(interval)
+            .with_context(|| format!("Unsupported interval for Bybit: {}", interval))?;
+        let step = interval_duration(interval)
+            .with_context(|| format!("Unsupported interval for Bybit: {}", interval))?;
+        let step_ms = step.num_milliseconds() as u64;
+        let limit = limit
+            .unwrap_or(self.default_kline_limit())
+            .min(self.max_kline_limit());
+
+        // Candles starting in [start_time, window_end), like the Binance API.
+        let first_start = start_time.div_ceil(step_ms) * step_ms;
+        let mut window_end = first_start + u64::from(limit) * step_ms;
+        if let Some(end_time) = end_time {
+            window_end = window_end.min(end_time + 1);
+        }
+        if first_start >= window_end {
+            return Ok(Vec::new());
+        }
+
+        let result: KlineResult = self
+            .get(
+                "kline",
+                &[
+                    ("category", self.category.as_str().to_string()),
+                    ("symbol", symbol.to_string()),
+                    ("interval", bybit_interval.to_string()),
+                    ("start", first_start.to_string()),
+                    ("end", (window_end - 1).to_string()),
+                    ("limit", limit.to_string()),
+                ],
+            )
+            .await?;
+        let mut klines = klines_from_rows(&result.list, symbol, interval, step, self.category)?;
+        klines.retain(|kline| {
+            let start = kline.start_time.timestamp_millis() as u64;
+            start >= first_start && start < window_end
+        });
+        Ok(klines)
+    }
+
+    async fn stream_klines(
+        &self,
+        symbol: &str,
+        interval: &str,
+    ) -> Result<Box<dyn StreamingClient<SerdableKlineData>>> {
+        Ok(Box::new(
+            BybitKlineStreaming::new(self.category, symbol, interval).await?,
+        ))
+    }
+
+    async fn server_time(&self) -> Result<DateTime<Utc>> {
+        let result: TimeResult = self.get("time", &[]).await?;
+        let nanos: i64 = result
+            .time_nano
+            .parse()
+            .with_context(|| format!("Invalid Bybit server time: {}", result.time_nano))?;
+        Ok(DateTime::from_timestamp_nanos(nanos))
+    }
+}
+
+/// A candle of the `kline` topic of the WebSocket API.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BybitKline {
+    /// The start of the candle in milliseconds since the epoch.
+    pub start: u64,
+    /// The end of the candle in milliseconds since the epoch, inclusive.
+    pub end: u64,
+    /// The opening price.
+    pub open: String,
+    /// The closing price.
+    pub close: String,
+    /// The highest price.
+    pub high: String,
+    /// The lowest price.
+    pub low: String,
+    /// The traded volume of the base asset.
+    pub volume: String,
+    /// The traded volume of the quote asset.
+    pub turnover: String,
+    /// Whether the candle is closed.
+    pub confirm: bool,
+    /// The time of the update in milliseconds since the epoch.
+    pub timestamp: u64,
+}
+
+impl BybitKline {
+    /// Converts the candle into a kline update.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbol` - The symbol the kline is labeled with.
+    /// * `interval` - The interval the kline is labeled with.
+    /// * `category` - The category of the market, which names the exchange.
+    pub fn to_serdable_kline(
+        &self,
+        symbol: &str,
+        interval: &str,
+        category: BybitCategory,
+    ) -> SerdableKlineData {
+        SerdableKlineData {
+            start_time: self.start,
+            end_time: self.end,
+            symbol: symbol.to_string(),
+            interval: interval.to_string(),
+            first_trade_id: 0,
+            last_trade_id: 0,
+            open: self.open.clone(),
+            close: self.close.clone(),
+            high: self.high.clone(),
+            low: self.low.clone(),
+            volume: self.volume.clone(),
+            trade_count: 0,
+            quote_volume: self.turnover.clone(),
+            event_time: Some(self.timestamp),
+            is_final: self.confirm,
+            exchange: Some(category.exchange().to_string()),
+        }
+    }
+}
+
+/// A message of the WebSocket API: topic data, or the reply to a request.
+#[derive(Deserialize, Debug)]
+struct BybitMessage {
+    topic: Option<String>,
+    #[serde(default)]
+    data: Vec<BybitKline>,
+    op: Option<String>,
+    success: Option<bool>,
+    ret_msg: Option<String>,
+}
+
+/// A WebSocket client streaming the klines of a Bybit symbol from the `kline`
+/// topic of the v5 public WebSocket API.
+pub struct BybitKlineStreaming {
+    url: String,
+    category: BybitCategory,
+    symbol: String,
+    interval: String,
+    topic: String,
+    socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    next_ping: tokio::time::Instant,
+    pending: VecDeque<SerdableKlineData>,
+    callbacks: Vec<Box<dyn SharedMessageHandler<SerdableKlineData> + Send>>,
+    stats: Option<Arc<StreamStats>>,
+    generation: u64,
+    context: Option<IngestContext>,
+}
+
+impl BybitKlineStreaming {
+    /// Connects to the WebSocket API of a category for the klines of a symbol.
+    ///
+    /// # Arguments
+    ///
+    /// * `category` - The category the symbol is listed in.
+    /// * `symbol` - The symbol (e.g., "BTCUSDT").
+    /// * `interval` - The kline interval (e.g., "1m").
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if Bybit has no candles of the interval or the WebSocket
+    /// connection cannot be established.
+    pub async fn new(category: BybitCategory, symbol: &str, interval: &str) -> Result<Self> {
+        let bybit_interval = kline_interval(interval)
+            .with_context(|| format!("Unsupported interval for Bybit: {}", interval))?;
+        let url = format!("{}/{}", BYBIT_STREAM_URL, category.as_str());
+        let (socket, _) = tokio_tungstenite::connect_async(url.as_str())
+            .await
+            .with_context(|| format!("Failed to connect to {}", url))?;
+        Ok(Self {
+            url,
+            category,
+            symbol: symbol.to_string(),
+            interval: interval.to_string(),
+            topic: format!("kline.{}.{}", bybit_interval, symbol),
+            socket,
+            next_ping: tokio::time::Instant::now() + PING_INTERVAL,
+            pending: VecDeque::new(),
+            callbacks: Vec::new(),
+            stats: None,
+            generation: 0,
+            context: None,
+        })
+    }
+
+    /// Returns the topic of the stream (e.g., "kline.1.BTCUSDT").
+    pub fn topic(&self) -> &str {
+        &self.topic
+    }
+
+    /// Attaches shared [`StreamStats`] that record parse errors, handler errors and
+    /// the latency of the handler chain while [`listen`](StreamingClient::listen) is
+    /// running.
+    pub fn attach_stats(&mut self, stats: Arc<StreamStats>) {
+        self.stats = Some(stats);
+    }
+}
+
+#[async_trait]
+impl StreamingClient<SerdableKlineData> for BybitKlineStreaming {
+    async fn connect(&mut self) -> Result<()> {
+        let (socket, _) = tokio_tungstenite::connect_async(self.url.as_str())
+            .await
+            .with_context(|| format!("Failed to connect to {}", self.url))?;
+        self.socket = socket;
+        self.next_ping = tokio::time::Instant::now() + PING_INTERVAL;
+        self.pending.clear();
+        self.generation += 1;
+        Ok(())
+    }
+
+    async fn subscribe(&mut self) -> Result<()> {
+        let request = serde_json::json!({
+            "op": "subscribe",
+            "args": [self.topic],
+        });
+        self.socket
+            .send(Message::Text(request.to_string()))
+            .await
+            .with_context(|| format!("Failed to subscribe to {}", self.topic))?;
+        Ok(())
+    }
+
+    async fn next(&mut self) -> Result<Option<Result<SerdableKlineData>>> {
+        loop {
+            if let Some(kline) = self.pending.pop_front() {
+                return Ok(Some(Ok(kline)));
+            }
+            let message = match tokio::time::timeout_at(self.next_ping, self.socket.next()).await {
+                Ok(message) => message,
+                Err(_) => {
+                    let ping = serde_json::json!({ "op": "ping" });
+                    self.socket
+                        .send(Message::Text(ping.to_string()))
+                        .await
+                        .context("Failed to ping Bybit")?;
+                    self.next_ping = tokio::time::Instant::now() + PING_INTERVAL;
+                    continue;
+                }
+            };
+            let text = match message {
+                Some(Ok(Message::Text(text))) => text,
+                Some(Ok(Message::Close(_))) | None => return Ok(None),
+                // Pings are answered by the socket itself.
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => return Ok(Some(Err(anyhow::Error::msg(e.to_string())))),
+            };
+            let message = match serde_json::from_str::<BybitMessage>(&text) {
+                Ok(message) => message,
+                Err(e) => {
+                    return Ok(Some(Err(anyhow::anyhow!(
+                        "Failed to parse Bybit message: {}: {}",
+                        e,
+                        text
+                    ))));
+                }
+            };
+            if message.op.as_deref() == Some("subscribe") && message.success == Some(false) {
+                bail!(
+                    "Bybit rejected {}: {}",
+                    self.topic,
+                    message.ret_msg.unwrap_or_default()
+                );
+            }
+            // Replies to pings and subscriptions carry no candles.
+            if message.topic.as_deref() != Some(self.topic.as_str()) {
+                continue;
+            }
+            self.context = Some(
+                IngestContext::new(self.category.exchange(), &self.topic)
+                    .with_generation(self.generation),
+            );
+            self.pending.extend(
+                message.data.iter().map(|kline| {
+                    kline.to_serdable_kline(&self.symbol, &self.interval, self.category)
+                }),
+            );
+        }
+    }
+
+    fn context(&self) -> Option<IngestContext> {
+        self.context.clone()
+    }
+
+    fn add_callback(&mut self, handler: Box<dyn MessageHandler<SerdableKlineData> + Send>) {
+        self.callbacks.push(Box::new(Borrowed(handler)));
+    }
+
+    fn add_shared_callback(
+        &mut self,
+        handler: Box<dyn SharedMessageHandler<SerdableKlineData> + Send>,
+    ) {
+        self.callbacks.push(handler);
+    }
+
+    async fn listen(&mut self) -> Result<()> {
+        while let Some(result) = StreamingClient::next(self).await? {
+            match result {
+                Ok(kline) => {
+                    let kline = Arc::new(kline);
+                    let context = self.context.clone().unwrap_or_else(|| {
+                        IngestContext::new(self.category.exchange(), &self.topic)
+                    });
+                    let started_at = Instant::now();
+                    for callback in &mut self.callbacks {
+                        if let Err(e) = callback.handle_shared_with_context(&kline, &context).await
+                        {
+                            if let Some(stats) = &self.stats {
+                                stats.record_error();
+                            }
+                            return Err(e);
+                        }
+                    }
+                    if let Some(stats) = &self.stats {
+                        stats.record_latency(started_at.elapsed());
+                    }
+                }
+                Err(e) => {
+                    if let Some(stats) = &self.stats {
+                        stats.record_error();
+                    }
+                    log::warn!("Error processing Bybit kline: {}", e);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_categories_and_intervals() {
+        let source = Bybit::linear();
+        assert_eq!(source.name(), BYBIT_LINEAR_EXCHANGE);
+        assert_eq!(Bybit::spot().name(), BYBIT_EXCHANGE);
+        assert_eq!(source.normalize_symbol("btc/usdt"), "BTCUSDT");
+        assert_eq!(kline_interval("1h"), Some("60"));
+        assert_eq!(kline_interval("1d"), Some("D"));
+        assert_eq!(kline_interval("8h"), None);
+    }
+
+    #[test]
+    fn test_parse_rest_klines_oldest_first() {
+        let json = r#"{"retCode":0,"retMsg":"OK","result":{"category":"spot","symbol":"BTCUSDT","list":[
+            ["1700003600000","37050","37200","37000","37100","1.5","55650"],
+            ["1700000000000","37000","37100","36900","37050","2","74050"]
+        ]},"retExtInfo":{},"time":1700007200000}"#;
+        let response: BybitResponse<KlineResult> = serde_json::from_str(json).unwrap();
+        let rows = response.into_result().unwrap().list;
+        let klines = klines_from_rows(
+            &rows,
+            "BTCUSDT",
+            "1h",
+            chrono::Duration::hours(1),
+            BybitCategory::Spot,
+        )
+        .unwrap();
+        assert_eq!(klines[0].start_time.timestamp_millis(), 1_700_000_000_000);
+        assert_eq!(klines[0].end_time.timestamp_millis(), 1_700_003_599_999);
+        assert_eq!(klines[1].close, "37100".parse().unwrap());
+        assert_eq!(klines[0].quote_volume, Some("74050".parse().unwrap()));
+        assert!(klines.iter().all(|kline| kline.validate().is_ok()));
+
+        let json = r#"{"retCode":10001,"retMsg":"Not supported symbols","result":{}}"#;
+        let response: BybitResponse<serde_json::Value> = serde_json::from_str(json).unwrap();
+        assert!(response.into_result().is_err());
+    }
+
+    #[test]
+    fn test_parse_stream_kline() {
+        let json = r#"{"topic":"kline.5.BTCUSDT","type":"snapshot","ts":1672324988882,"data":[
+            {"start":1672324800000,"end":1672325099999,"interval":"5","open":"16649.5","close":"16677","high":"16677","low":"16608","volume":"2.081","turnover":"34666.4005","confirm":true,"timestamp":1672324988882}
+        ]}"#;
+        let message: BybitMessage = serde_json::from_str(json).unwrap();
+        assert_eq!(message.topic.as_deref(), Some("kline.5.BTCUSDT"));
+        let kline = message.data[0].to_serdable_kline("BTCUSDT", "5m", BybitCategory::Linear);
+        assert!(kline.is_final);
+        assert_eq!(kline.end_time - kline.start_time, 299_999);
+        assert_eq!(kline.quote_volume, "34666.4005");
+        assert!(kline.to_validated_kline_data().is_ok());
+
+        let json = r#"{"success":true,"ret_msg":"pong","conn_id":"abc","op":"ping"}"#;
+        let message: BybitMessage = serde_json::from_str(json).unwrap();
+        assert!(message.topic.is_none() && message.data.is_empty());
+    }
+}
@@ -0,0 +1,138 @@
+//! # Exchange Clocks
+//!
+//! Candles open and close on the clock of the exchange, not on the local one. On
+//! a host whose clock drifted, batch boundaries and the expected latest candle
+//! computed from the local time are off by a candle around every boundary. This
+//! module keeps the offset of every exchange clock from the local clock, measured
+//! with [`MarketDataSource::server_time`] and re-synchronized every
+//! [`SYNC_INTERVAL`], and provides the exchange time as [`server_now`].
+//!
+//! If the exchange time cannot be fetched, the last known offset is kept, or the
+//! local clock is used before the first successful sync, so a failing time
+//! endpoint never stops ingestion.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use opentrade_core::data_source::clock::server_now;
+//! use opentrade_core::data_source::exchange::Binance;
+//!
+//! # async fn example() {
+//! let now = server_now(&Binance).await;
+//! println!("Binance time: {}", now);
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+
+use crate::data_source::exchange::MarketDataSource;
+
+/// How often the offset of an exchange clock is re-synchronized.
+pub const SYNC_INTERVAL: Duration = Duration::from_secs(600);
+
+/// Offsets from the local clock at which a warning is logged.
+const DRIFT_WARNING: chrono::Duration = chrono::Duration::seconds(1);
+
+/// The synchronized offsets, keyed by exchange name.
+static OFFSETS: Mutex<BTreeMap<String, SyncedOffset>> = Mutex::new(BTreeMap::new());
+
+/// The offset of an exchange clock and when it was measured.
+#[derive(Debug, Clone, Copy)]
+struct SyncedOffset {
+    offset: chrono::Duration,
+    synced_at: Instant,
+}
+
+/// Returns the current time of an exchange.
+///
+/// # Arguments
+///
+/// * `source` - The exchange.
+pub async fn server_now(source: &dyn MarketDataSource) -> DateTime<Utc> {
+    Utc::now() + clock_offset(source).await
+}
+
+/// Returns the offset of an exchange clock from the local clock, synchronizing
+/// it first if it was never measured or is older than [`SYNC_INTERVAL`].
+///
+/// # Arguments
+///
+/// * `source` - The exchange.
+pub async fn clock_offset(source: &dyn MarketDataSource) -> chrono::Duration {
+    let cached = OFFSETS.lock().unwrap().get(source.name()).copied();
+    if let Some(cached) = cached
+        && cached.synced_at.elapsed() < SYNC_INTERVAL
+    {
+        return cached.offset;
+    }
+
+    let sent_at = Utc::now();
+    let offset = match source.server_time().await {
+        Ok(server_time) => {
+            let offset = measure_offset(sent_at, Utc::now(), server_time);
+            if offset.abs() >= DRIFT_WARNING {
+                log::warn!(
+                    "The {} clock is {} ms ahead of the local clock",
+                    source.name(),
+                    offset.num_milliseconds()
+                );
+            }
+            offset
+        }
+        Err(e) => {
+            log::warn!(
+                "Failed to synchronize with the {} clock, keeping the last offset: {:#}",
+                source.name(),
+                e
+            );
+            cached.map_or_else(chrono::Duration::zero, |cached| cached.offset)
+        }
+    };
+    OFFSETS.lock().unwrap().insert(
+        source.name().to_string(),
+        SyncedOffset {
+            offset,
+            synced_at: Instant::now(),
+        },
+    );
+    offset
+}
+
+/// Computes the offset of a server time read during a request, assuming the
+/// server read its clock halfway through the round trip.
+fn measure_offset(
+    sent_at: DateTime<Utc>,
+    received_at: DateTime<Utc>,
+    server_time: DateTime<Utc>,
+) -> chrono::Duration {
+    server_time - (sent_at + (received_at - sent_at) / 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_measure_offset_uses_round_trip_midpoint() {
+        let sent_at = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let received_at = sent_at + chrono::Duration::milliseconds(200);
+
+        let server_time = sent_at + chrono::Duration::milliseconds(100);
+        assert_eq!(
+            measure_offset(sent_at, received_at, server_time),
+            chrono::Duration::zero()
+        );
+
+        // A local clock running 2 seconds slow.
+        let server_time = sent_at + chrono::Duration::milliseconds(2_100);
+        assert_eq!(
+            measure_offset(sent_at, received_at, server_time),
+            chrono::Duration::seconds(2)
+        );
+    }
+}
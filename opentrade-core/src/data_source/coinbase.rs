@@ -0,0 +1,971 @@
+//! # Coinbase Advanced Trade
+//!
+//! This module implements [`MarketDataSource`] for the spot markets of Coinbase
+//! Advanced Trade, so its products go through the same backfills and pipelines as
+//! Binance symbols:
+//!
+//! - Historical klines are fetched from the public candles endpoint of the REST API.
+//! - Live klines are built from the `market_trades` channel of the WebSocket API,
+//!   whose trades are mapped to [`TradeData`] by [`CoinbaseTradeStreaming`] and
+//!   aggregated into candles by [`KlineBuilder`]. The `ticker` channel only carries
+//!   prices and 24 hour volumes, so candles cannot be built from it.
+//!
+//! ## Mapping
+//!
+//! - Coinbase names products by their base and quote currencies (e.g., "BTC-USD").
+//!   Candles are stored under the symbol without the dash (e.g., "BTCUSD"), like
+//!   Binance symbols; the product of a symbol is derived from its quote currency,
+//!   or configured with [`Coinbase::with_product`]. The source reports itself as
+//!   [`COINBASE_EXCHANGE`]; store its candles under their own dataset, as symbols
+//!   like "BTCUSDT" exist on both exchanges.
+//! - REST candles have no trade ids, trade counts or quote volumes, so their trade
+//!   ids are 0 and the counts and quote volumes are empty. Streamed candles carry
+//!   all of them; trade ids that do not fit the `kline_data` columns are stored as 0.
+//! - Candles are aligned to multiples of the interval since the Unix epoch, and
+//!   intervals without trades produce no candle. The REST API supports the
+//!   intervals of [`granularity`]; streams support every interval of a fixed length.
+//! - Streams only aggregate the trades received after subscribing. Open candles
+//!   are emitted at most every [`KLINE_UPDATE_INTERVAL`], and a candle is emitted
+//!   as final once a later trade arrives or its end has passed.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use opentrade_core::data_source::coinbase::Coinbase;
+//! use opentrade_core::data_source::exchange::MarketDataSource;
+//! # use anyhow::Result;
+//!
+//! # async fn example() -> Result<()> {
+//! let source = Coinbase::new();
+//! let symbol = source.normalize_symbol("btc-usd");
+//! let klines = source.fetch_klines(&symbol, "1h", 1_700_000_000_000, None, Some(24)).await?;
+//! println!("Fetched {} klines from {}", klines.len(), source.name());
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, bail};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use sqlx::types::BigDecimal as Decimal;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+use crate::data_source::exchange::MarketDataSource;
+use crate::data_source::websocket::{
+    Borrowed, IngestContext, MessageHandler, SharedMessageHandler, StreamingClient,
+};
+use crate::ingest::audit::interval_duration;
+use crate::ingest::stats::StreamStats;
+use crate::models::trade::TradeData;
+use crate::models::{KlineData, SerdableKlineData};
+
+/// The exchange name of Coinbase sources.
+pub const COINBASE_EXCHANGE: &str = "coinbase";
+
+/// The base URL of the Advanced Trade REST API.
+pub const COINBASE_REST_URL: &str = "https://api.coinbase.com/api/v3/brokerage";
+
+/// The URL of the Advanced Trade market data WebSocket API.
+pub const COINBASE_STREAM_URL: &str = "wss://advanced-trade-ws.coinbase.com";
+
+/// The channel streaming the trades of products.
+pub const MARKET_TRADES_CHANNEL: &str = "market_trades";
+
+/// The maximum number of candles the REST API returns per request.
+pub const MAX_CANDLE_LIMIT: u32 = 350;
+
+/// The minimum time between two updates of an open streamed candle.
+pub const KLINE_UPDATE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How long after its end a streamed candle is closed without a later trade, to
+/// let trades executed just before the end arrive.
+const CLOSE_GRACE: Duration = Duration::from_secs(2);
+
+/// Quote currencies, tried in order to split a symbol into a product id.
+const QUOTE_CURRENCIES: [&str; 8] = ["USDC", "USDT", "USD", "EUR", "GBP", "DAI", "BTC", "ETH"];
+
+/// Returns the candle granularity of the REST API for an interval, or `None` if
+/// the API has no candles of that length.
+pub fn granularity(interval: &str) -> Option<&'static str> {
+    let granularity = match interval {
+        "1m" => "ONE_MINUTE",
+        "5m" => "FIVE_MINUTE",
+        "15m" => "FIFTEEN_MINUTE",
+        "30m" => "THIRTY_MINUTE",
+        "1h" => "ONE_HOUR",
+        "2h" => "TWO_HOUR",
+        "6h" => "SIX_HOUR",
+        "1d" => "ONE_DAY",
+        _ => return None,
+    };
+    Some(granularity)
+}
+
+/// Derives the product id of a symbol from its quote currency (e.g., "BTCUSD" to
+/// "BTC-USD"). Symbols that already contain a dash are product ids.
+pub fn product_id(symbol: &str) -> Option<String> {
+    let symbol = symbol.to_uppercase();
+    if symbol.contains('-') {
+        return Some(symbol);
+    }
+    QUOTE_CURRENCIES.iter().find_map(|quote| {
+        symbol
+            .strip_suffix(quote)
+            .filter(|base| !base.is_empty())
+            .map(|base| format!("{}-{}", base, quote))
+    })
+}
+
+/// A candle of the REST API. Times are in seconds since the epoch.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CoinbaseCandle {
+    /// The start of the candle.
+    pub start: String,
+    /// The lowest price.
+    pub low: String,
+    /// The highest price.
+    pub high: String,
+    /// The opening price.
+    pub open: String,
+    /// The closing price.
+    pub close: String,
+    /// The traded quantity of the base currency.
+    pub volume: String,
+}
+
+#[derive(Deserialize)]
+struct CandlesResponse {
+    #[serde(default)]
+    candles: Vec<CoinbaseCandle>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TimeResponse {
+    epoch_millis: String,
+}
+
+/// Converts REST candles, which are returned newest first, into klines sorted by
+/// start time.
+///
+/// # Arguments
+///
+/// * `candles` - The candles, in any order.
+/// * `symbol` - The symbol the klines are labeled with.
+/// * `interval` - The interval the klines are labeled with.
+/// * `step` - The length of `interval`.
+///
+/// # Errors
+///
+/// Returns an error if a candle has an unparseable field.
+pub fn klines_from_candles(
+    candles: &[CoinbaseCandle],
+    symbol: &str,
+    interval: &str,
+    step: chrono::Duration,
+) -> Result<Vec<KlineData>> {
+    fn decimal(value: &str) -> Result<Decimal> {
+        value
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid candle value: {}", value))
+    }
+
+    let step_ms = step.num_milliseconds() as u64;
+    let mut klines = candles
+        .iter()
+        .map(|candle| {
+            let seconds: u64 = candle
+                .start
+                .parse()
+                .with_context(|| format!("Invalid candle start: {}", candle.start))?;
+            let start = seconds * 1000;
+            Ok(KlineData::new(
+                &start,
+                &(start + step_ms - 1),
+                symbol,
+                interval,
+                0,
+                0,
+                decimal(&candle.open)?,
+                decimal(&candle.high)?,
+                decimal(&candle.low)?,
+                decimal(&candle.close)?,
+                decimal(&candle.volume)?,
+                None,
+                None,
+            )
+            .with_exchange(COINBASE_EXCHANGE))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    klines.sort_by_key(|kline| kline.start_time);
+    Ok(klines)
+}
+
+/// A trade of the `market_trades` channel.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CoinbaseTrade {
+    /// The trade id, a decimal number.
+    pub trade_id: String,
+    /// The product id (e.g., "BTC-USD").
+    pub product_id: String,
+    /// The execution price.
+    pub price: String,
+    /// The traded quantity of the base currency.
+    pub size: String,
+    /// The taker side, "BUY" or "SELL".
+    pub side: String,
+    /// The execution time, in RFC 3339 format.
+    pub time: String,
+}
+
+impl CoinbaseTrade {
+    /// Converts the trade into a [`TradeData`] labeled with `symbol`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the trade id, a decimal or the time cannot be parsed.
+    pub fn to_trade_data(&self, symbol: &str) -> Result<TradeData> {
+        fn decimal(value: &str) -> Result<Decimal> {
+            value
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid decimal: {}", value))
+        }
+
+        Ok(TradeData {
+            symbol: symbol.to_string(),
+            trade_id: self
+                .trade_id
+                .parse()
+                .with_context(|| format!("Invalid trade id: {}", self.trade_id))?,
+            price: decimal(&self.price)?,
+            quantity: decimal(&self.size)?,
+            side: self.side.to_uppercase(),
+            trade_time: DateTime::parse_from_rfc3339(&self.time)
+                .with_context(|| format!("Invalid trade time: {}", self.time))?
+                .with_timezone(&Utc),
+        })
+    }
+}
+
+/// A message of the WebSocket API.
+#[derive(Deserialize, Debug)]
+struct CoinbaseMessage {
+    #[serde(default)]
+    channel: String,
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    message: Option<String>,
+    #[serde(default)]
+    events: Vec<CoinbaseEvent>,
+}
+
+/// An event of a [`CoinbaseMessage`]. `snapshot` events carry the latest trades
+/// before the subscription, `update` events new ones.
+#[derive(Deserialize, Debug)]
+struct CoinbaseEvent {
+    #[serde(rename = "type", default)]
+    kind: String,
+    #[serde(default)]
+    trades: Vec<CoinbaseTrade>,
+}
+
+/// The Coinbase Advanced Trade spot exchange.
+#[derive(Debug, Clone, Default)]
+pub struct Coinbase {
+    products: HashMap<String, String>,
+    client: reqwest::Client,
+}
+
+impl Coinbase {
+    /// Creates a source deriving product ids from the quote currencies of symbols.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Maps a symbol to a product whose id cannot be derived from it.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbol` - The symbol the product's candles are stored under.
+    /// * `product_id` - The Coinbase product id (e.g., "BTC-USD").
+    pub fn with_product(mut self, symbol: &str, product_id: &str) -> Self {
+        self.products
+            .insert(self.normalize_symbol(symbol), product_id.to_uppercase());
+        self
+    }
+
+    /// Returns the product id of a symbol.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the symbol has no configured product and no known
+    /// quote currency.
+    pub fn product_id(&self, symbol: &str) -> Result<String> {
+        match self.products.get(&self.normalize_symbol(symbol)) {
+            Some(product_id) => Ok(product_id.clone()),
+            None => product_id(symbol)
+                .with_context(|| format!("No Coinbase product known for {}", symbol)),
+        }
+    }
+
+    /// Opens a stream of the trades of a symbol. The stream still has to be
+    /// subscribed before trades are received.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the symbol has no product or the WebSocket connection
+    /// cannot be established.
+    pub async fn stream_trades(&self, symbol: &str) -> Result<CoinbaseTradeStreaming> {
+        CoinbaseTradeStreaming::new(symbol, &self.product_id(symbol)?).await
+    }
+}
+
+#[async_trait]
+impl MarketDataSource for Coinbase {
+    fn name(&self) -> &str {
+        COINBASE_EXCHANGE
+    }
+
+    fn normalize_symbol(&self, symbol: &str) -> String {
+        symbol
+            .chars()
+            .filter(char::is_ascii_alphanumeric)
+            .collect::<String>()
+            .to_uppercase()
+    }
+
+    fn max_kline_limit(&self) -> u32 {
+        MAX_CANDLE_LIMIT
+    }
+
+    fn default_kline_limit(&self) -> u32 {
+        MAX_CANDLE_LIMIT
+    }
+
+    async fn fetch_klines(
+        &self,
+        symbol: &str,
+        interval: &str,
+        start_time: u64,
+        end_time: Option<u64>,
+        limit: Option<u32>,
+    ) -> Result<Vec<KlineData>> {
+        let granularity = granularity(interval)
+            .with_context(|| format!("Unsupported interval for Coinbase: {}", interval))?;
+        let step = interval_duration(interval)
+            .with_context(|| format!("Unsupported interval for Coinbase: {}", interval))?;
+        let product_id = self.product_id(symbol)?;
+        let step_ms = step.num_milliseconds() as u64;
+        let limit = limit
+            .unwrap_or(self.default_kline_limit())
+            .min(self.max_kline_limit());
+
+        // Candles starting in [start_time, window_end), like the Binance API.
+        let first_start = start_time.div_ceil(step_ms) * step_ms;
+        let mut window_end = first_start + u64::from(limit) * step_ms;
+        if let Some(end_time) = end_time {
+            window_end = window_end.min(end_time + 1);
+        }
+        if first_start >= window_end {
+            return Ok(Vec::new());
+        }
+
+        let url = format!(
+            "{}/market/products/{}/candles",
+            COINBASE_REST_URL, product_id
+        );
+        let response: CandlesResponse = self
+            .client
+            .get(&url)
+            .query(&[
+                ("start", (first_start / 1000).to_string()),
+                ("end", ((window_end - 1) / 1000).to_string()),
+                ("granularity", granularity.to_string()),
+                ("limit", limit.to_string()),
+            ])
+            .send()
+            .await
+            .with_context(|| format!("Failed to request the candles of {}", product_id))?
+            .error_for_status()
+            .with_context(|| format!("Coinbase rejected the candles request of {}", product_id))?
+            .json()
+            .await
+            .context("Failed to parse the Coinbase candles response")?;
+        let mut klines = klines_from_candles(&response.candles, symbol, interval, step)?;
+        klines.retain(|kline| {
+            let start = kline.start_time.timestamp_millis() as u64;
+            start >= first_start && start < window_end
+        });
+        Ok(klines)
+    }
+
+    async fn stream_klines(
+        &self,
+        symbol: &str,
+        interval: &str,
+    ) -> Result<Box<dyn StreamingClient<SerdableKlineData>>> {
+        let builder = KlineBuilder::new(symbol, interval)?;
+        let trades = self.stream_trades(symbol).await?;
+        Ok(Box::new(CoinbaseKlineStreaming::new(trades, builder)))
+    }
+
+    async fn server_time(&self) -> Result<DateTime<Utc>> {
+        let response: TimeResponse = self
+            .client
+            .get(format!("{}/time", COINBASE_REST_URL))
+            .send()
+            .await
+            .context("Failed to request the Coinbase server time")?
+            .error_for_status()
+            .context("Coinbase rejected the server time request")?
+            .json()
+            .await
+            .context("Failed to parse the Coinbase server time")?;
+        response
+            .epoch_millis
+            .parse()
+            .ok()
+            .and_then(DateTime::from_timestamp_millis)
+            .with_context(|| format!("Invalid Coinbase server time: {}", response.epoch_millis))
+    }
+}
+
+/// A WebSocket client streaming the trades of a Coinbase product from the
+/// `market_trades` channel.
+///
+/// Only trades executed after subscribing are returned; the snapshot of earlier
+/// trades sent with the subscription is skipped.
+pub struct CoinbaseTradeStreaming {
+    url: String,
+    symbol: String,
+    product_id: String,
+    socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    pending: VecDeque<TradeData>,
+    callbacks: Vec<Box<dyn SharedMessageHandler<TradeData> + Send>>,
+    stats: Option<Arc<StreamStats>>,
+    generation: u64,
+    context: Option<IngestContext>,
+}
+
+impl CoinbaseTradeStreaming {
+    /// Connects to the WebSocket API for the trades of a product.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbol` - The symbol the trades are labeled with (e.g., "BTCUSD").
+    /// * `product_id` - The Coinbase product id (e.g., "BTC-USD").
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the WebSocket connection cannot be established.
+    pub async fn new(symbol: &str, product_id: &str) -> Result<Self> {
+        let (socket, _) = tokio_tungstenite::connect_async(COINBASE_STREAM_URL)
+            .await
+            .with_context(|| format!("Failed to connect to {}", COINBASE_STREAM_URL))?;
+        Ok(Self {
+            url: COINBASE_STREAM_URL.to_string(),
+            symbol: symbol.to_string(),
+            product_id: product_id.to_string(),
+            socket,
+            pending: VecDeque::new(),
+            callbacks: Vec::new(),
+            stats: None,
+            generation: 0,
+            context: None,
+        })
+    }
+
+    /// Returns the stream name (e.g., "BTC-USD@market_trades").
+    pub fn stream(&self) -> String {
+        format!("{}@{}", self.product_id, MARKET_TRADES_CHANNEL)
+    }
+
+    /// Attaches shared [`StreamStats`] that record parse errors, handler errors and
+    /// the latency of the handler chain while [`listen`](StreamingClient::listen) is
+    /// running.
+    pub fn attach_stats(&mut self, stats: Arc<StreamStats>) {
+        self.stats = Some(stats);
+    }
+
+    /// Queues the trades of an update event, oldest first.
+    fn queue_trades(&mut self, trades: &[CoinbaseTrade]) -> Result<()> {
+        let mut batch = trades
+            .iter()
+            .filter(|trade| trade.product_id == self.product_id)
+            .map(|trade| trade.to_trade_data(&self.symbol))
+            .collect::<Result<Vec<_>>>()?;
+        batch.sort_by_key(|trade| trade.trade_id);
+        self.pending.extend(batch);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl StreamingClient<TradeData> for CoinbaseTradeStreaming {
+    async fn connect(&mut self) -> Result<()> {
+        let (socket, _) = tokio_tungstenite::connect_async(self.url.as_str())
+            .await
+            .with_context(|| format!("Failed to connect to {}", self.url))?;
+        self.socket = socket;
+        self.pending.clear();
+        self.generation += 1;
+        Ok(())
+    }
+
+    async fn subscribe(&mut self) -> Result<()> {
+        // Heartbeats keep the connection open while the product does not trade.
+        for channel in [MARKET_TRADES_CHANNEL, "heartbeats"] {
+            let request = serde_json::json!({
+                "type": "subscribe",
+                "product_ids": [self.product_id],
+                "channel": channel,
+            });
+            self.socket
+                .send(Message::Text(request.to_string()))
+                .await
+                .with_context(|| format!("Failed to subscribe to {}", self.stream()))?;
+        }
+        Ok(())
+    }
+
+    async fn next(&mut self) -> Result<Option<Result<TradeData>>> {
+        loop {
+            if let Some(trade) = self.pending.pop_front() {
+                return Ok(Some(Ok(trade)));
+            }
+            let text = match self.socket.next().await {
+                Some(Ok(Message::Text(text))) => text,
+                Some(Ok(Message::Close(_))) | None => return Ok(None),
+                // Pings are answered by the socket itself.
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => return Ok(Some(Err(anyhow::Error::msg(e.to_string())))),
+            };
+            let message = match serde_json::from_str::<CoinbaseMessage>(&text) {
+                Ok(message) => message,
+                Err(e) => {
+                    return Ok(Some(Err(anyhow::anyhow!(
+                        "Failed to parse Coinbase message: {}: {}",
+                        e,
+                        text
+                    ))));
+                }
+            };
+            if message.kind.as_deref() == Some("error") {
+                bail!(
+                    "Coinbase rejected {}: {}",
+                    self.stream(),
+                    message.message.unwrap_or_default()
+                );
+            }
+            if message.channel != MARKET_TRADES_CHANNEL {
+                continue;
+            }
+            self.context = Some(
+                IngestContext::new(COINBASE_EXCHANGE, &self.stream())
+                    .with_generation(self.generation),
+            );
+            for event in message.events.iter().filter(|event| event.kind == "update") {
+                if let Err(e) = self.queue_trades(&event.trades) {
+                    return Ok(Some(Err(e)));
+                }
+            }
+        }
+    }
+
+    fn context(&self) -> Option<IngestContext> {
+        self.context.clone()
+    }
+
+    fn add_callback(&mut self, handler: Box<dyn MessageHandler<TradeData> + Send>) {
+        self.callbacks.push(Box::new(Borrowed(handler)));
+    }
+
+    fn add_shared_callback(&mut self, handler: Box<dyn SharedMessageHandler<TradeData> + Send>) {
+        self.callbacks.push(handler);
+    }
+
+    async fn listen(&mut self) -> Result<()> {
+        while let Some(result) = StreamingClient::next(self).await? {
+            match result {
+                Ok(trade) => {
+                    let trade = Arc::new(trade);
+                    let context = self
+                        .context
+                        .clone()
+                        .unwrap_or_else(|| IngestContext::new(COINBASE_EXCHANGE, &self.stream()));
+                    let started_at = Instant::now();
+                    for callback in &mut self.callbacks {
+                        if let Err(e) = callback.handle_shared_with_context(&trade, &context).await
+                        {
+                            if let Some(stats) = &self.stats {
+                                stats.record_error();
+                            }
+                            return Err(e);
+                        }
+                    }
+                    if let Some(stats) = &self.stats {
+                        stats.record_latency(started_at.elapsed());
+                    }
+                }
+                Err(e) => {
+                    if let Some(stats) = &self.stats {
+                        stats.record_error();
+                    }
+                    log::warn!("Error processing Coinbase trade: {}", e);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Aggregates trades sorted by time into the candles of one symbol and interval.
+#[derive(Debug, Clone)]
+pub struct KlineBuilder {
+    symbol: String,
+    interval: String,
+    step_ms: u64,
+    current: Option<KlineData>,
+}
+
+impl KlineBuilder {
+    /// Creates a builder of the candles of a symbol.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the interval has no fixed length.
+    pub fn new(symbol: &str, interval: &str) -> Result<Self> {
+        let step = interval_duration(interval)
+            .with_context(|| format!("Unsupported interval for trade candles: {}", interval))?;
+        Ok(Self {
+            symbol: symbol.to_string(),
+            interval: interval.to_string(),
+            step_ms: step.num_milliseconds() as u64,
+            current: None,
+        })
+    }
+
+    /// Adds a trade to its candle.
+    ///
+    /// # Returns
+    ///
+    /// The previous candle as final if the trade opened a new one. Trades before
+    /// the open candle are ignored.
+    pub fn push(&mut self, trade: &TradeData) -> Option<SerdableKlineData> {
+        let time = trade.trade_time.timestamp_millis() as u64;
+        let start = time / self.step_ms * self.step_ms;
+        let trade_id = trade.trade_id;
+        match &mut self.current {
+            Some(kline) if kline.start_time.timestamp_millis() as u64 == start => {
+                if trade.price > kline.high {
+                    kline.high = trade.price.clone();
+                }
+                if trade.price < kline.low {
+                    kline.low = trade.price.clone();
+                }
+                kline.close = trade.price.clone();
+                kline.volume += &trade.quantity;
+                kline.quote_volume = kline
+                    .quote_volume
+                    .take()
+                    .map(|volume| volume + trade.quote_quantity());
+                kline.trade_count = kline.trade_count.map(|count| count + 1);
+                kline.last_trade_id = trade_id;
+                None
+            }
+            Some(kline) if kline.start_time.timestamp_millis() as u64 > start => None,
+            _ => {
+                let kline = KlineData::new(
+                    &start,
+                    &(start + self.step_ms - 1),
+                    &self.symbol,
+                    &self.interval,
+                    trade_id,
+                    trade_id,
+                    trade.price.clone(),
+                    trade.price.clone(),
+                    trade.price.clone(),
+                    trade.price.clone(),
+                    trade.quantity.clone(),
+                    Some(1),
+                    Some(trade.quote_quantity()),
+                )
+                .with_exchange(COINBASE_EXCHANGE);
+                self.current
+                    .replace(kline)
+                    .map(|closed| Self::serdable(closed, true))
+            }
+        }
+    }
+
+    /// Returns the open candle, or `None` before the first trade.
+    pub fn current(&self) -> Option<SerdableKlineData> {
+        self.current
+            .clone()
+            .map(|kline| Self::serdable(kline, false))
+    }
+
+    /// Returns the end time of the open candle in milliseconds since the epoch.
+    pub fn current_end(&self) -> Option<u64> {
+        self.current
+            .as_ref()
+            .map(|kline| kline.end_time.timestamp_millis() as u64)
+    }
+
+    /// Closes the open candle if it ended before `now`.
+    ///
+    /// # Returns
+    ///
+    /// The closed candle as final.
+    pub fn close_before(&mut self, now: u64) -> Option<SerdableKlineData> {
+        if self.current_end()? >= now {
+            return None;
+        }
+        self.current.take().map(|kline| Self::serdable(kline, true))
+    }
+
+    fn serdable(kline: KlineData, is_final: bool) -> SerdableKlineData {
+        SerdableKlineData {
+            event_time: Some(Utc::now().timestamp_millis() as u64),
+            is_final,
+            ..SerdableKlineData::from(kline)
+        }
+    }
+}
+
+/// A kline stream of a Coinbase product, built from its trades.
+///
+/// See the [module documentation](self) for when candles are emitted.
+pub struct CoinbaseKlineStreaming {
+    trades: CoinbaseTradeStreaming,
+    builder: KlineBuilder,
+    pending: VecDeque<SerdableKlineData>,
+    last_update: Option<Instant>,
+    callbacks: Vec<Box<dyn SharedMessageHandler<SerdableKlineData> + Send>>,
+    stats: Option<Arc<StreamStats>>,
+}
+
+impl CoinbaseKlineStreaming {
+    /// Creates a kline stream aggregating the trades of a trade stream.
+    pub fn new(trades: CoinbaseTradeStreaming, builder: KlineBuilder) -> Self {
+        Self {
+            trades,
+            builder,
+            pending: VecDeque::new(),
+            last_update: None,
+            callbacks: Vec::new(),
+            stats: None,
+        }
+    }
+
+    /// Attaches shared [`StreamStats`] that record parse errors, handler errors and
+    /// the latency of the handler chain while [`listen`](StreamingClient::listen) is
+    /// running.
+    pub fn attach_stats(&mut self, stats: Arc<StreamStats>) {
+        self.stats = Some(stats);
+    }
+
+    /// Adds a trade, queueing the closed candle and, at most every
+    /// [`KLINE_UPDATE_INTERVAL`], the open one.
+    fn add_trade(&mut self, trade: &TradeData) {
+        if let Some(closed) = self.builder.push(trade) {
+            self.pending.push_back(closed);
+        }
+        if self
+            .last_update
+            .is_none_or(|last_update| last_update.elapsed() >= KLINE_UPDATE_INTERVAL)
+        {
+            self.pending.extend(self.builder.current());
+            self.last_update = Some(Instant::now());
+        }
+    }
+}
+
+#[async_trait]
+impl StreamingClient<SerdableKlineData> for CoinbaseKlineStreaming {
+    async fn connect(&mut self) -> Result<()> {
+        self.trades.connect().await
+    }
+
+    async fn subscribe(&mut self) -> Result<()> {
+        self.trades.subscribe().await
+    }
+
+    async fn next(&mut self) -> Result<Option<Result<SerdableKlineData>>> {
+        loop {
+            if let Some(kline) = self.pending.pop_front() {
+                return Ok(Some(Ok(kline)));
+            }
+            let next = match self.builder.current_end() {
+                Some(end) => {
+                    let now = Utc::now().timestamp_millis() as u64;
+                    let wait = Duration::from_millis((end + 1).saturating_sub(now)) + CLOSE_GRACE;
+                    match tokio::time::timeout(wait, self.trades.next()).await {
+                        Ok(next) => next?,
+                        Err(_) => {
+                            let now = Utc::now().timestamp_millis() as u64;
+                            self.pending.extend(self.builder.close_before(now));
+                            continue;
+                        }
+                    }
+                }
+                None => self.trades.next().await?,
+            };
+            match next {
+                Some(Ok(trade)) => self.add_trade(&trade),
+                Some(Err(e)) => return Ok(Some(Err(e))),
+                None => return Ok(None),
+            }
+        }
+    }
+
+    fn context(&self) -> Option<IngestContext> {
+        self.trades.context()
+    }
+
+    fn add_callback(&mut self, handler: Box<dyn MessageHandler<SerdableKlineData> + Send>) {
+        self.callbacks.push(Box::new(Borrowed(handler)));
+    }
+
+    fn add_shared_callback(
+        &mut self,
+        handler: Box<dyn SharedMessageHandler<SerdableKlineData> + Send>,
+    ) {
+        self.callbacks.push(handler);
+    }
+
+    async fn listen(&mut self) -> Result<()> {
+        while let Some(result) = StreamingClient::next(self).await? {
+            match result {
+                Ok(kline) => {
+                    let kline = Arc::new(kline);
+                    let context = self.trades.context().unwrap_or_else(|| {
+                        IngestContext::new(COINBASE_EXCHANGE, &self.trades.stream())
+                    });
+                    let started_at = Instant::now();
+                    for callback in &mut self.callbacks {
+                        if let Err(e) = callback.handle_shared_with_context(&kline, &context).await
+                        {
+                            if let Some(stats) = &self.stats {
+                                stats.record_error();
+                            }
+                            return Err(e);
+                        }
+                    }
+                    if let Some(stats) = &self.stats {
+                        stats.record_latency(started_at.elapsed());
+                    }
+                }
+                Err(e) => {
+                    if let Some(stats) = &self.stats {
+                        stats.record_error();
+                    }
+                    log::warn!("Error processing Coinbase kline: {}", e);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decimal(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    fn trade(trade_id: i64, millis: i64, price: &str, quantity: &str) -> TradeData {
+        TradeData {
+            symbol: "BTCUSD".to_string(),
+            trade_id,
+            price: decimal(price),
+            quantity: decimal(quantity),
+            side: "BUY".to_string(),
+            trade_time: DateTime::from_timestamp_millis(millis).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_product_ids_and_granularities() {
+        assert_eq!(product_id("BTCUSD").as_deref(), Some("BTC-USD"));
+        assert_eq!(product_id("ethusdc").as_deref(), Some("ETH-USDC"));
+        assert_eq!(product_id("ETH-BTC").as_deref(), Some("ETH-BTC"));
+        assert_eq!(product_id("USD"), None);
+        let source = Coinbase::new().with_product("wbtc/usd", "wbtc-usd");
+        assert_eq!(source.normalize_symbol("btc-usd"), "BTCUSD");
+        assert_eq!(source.product_id("WBTCUSD").unwrap(), "WBTC-USD");
+        assert!(source.product_id("XYZ").is_err());
+        assert_eq!(granularity("6h"), Some("SIX_HOUR"));
+        assert_eq!(granularity("4h"), None);
+    }
+
+    #[test]
+    fn test_parse_candles_and_trades() {
+        let json = r#"{"candles":[
+            {"start":"1700003600","low":"36900","high":"37100","open":"37000","close":"37050","volume":"12.5"},
+            {"start":"1700000000","low":"36800","high":"37010","open":"36850","close":"37000","volume":"8"}
+        ]}"#;
+        let response: CandlesResponse = serde_json::from_str(json).unwrap();
+        let klines = klines_from_candles(
+            &response.candles,
+            "BTCUSD",
+            "1h",
+            chrono::Duration::hours(1),
+        )
+        .unwrap();
+        assert_eq!(klines[0].start_time.timestamp(), 1_700_000_000);
+        assert_eq!(klines[0].end_time.timestamp_millis(), 1_700_003_599_999);
+        assert_eq!(klines[1].close, decimal("37050"));
+        assert_eq!(klines[1].trade_count, None);
+        assert!(klines[1].validate().is_ok());
+
+        let json = r#"{"channel":"market_trades","client_id":"","timestamp":"2023-02-09T20:19:35.39625135Z","sequence_num":3,"events":[{"type":"update","trades":[{"trade_id":"12","product_id":"BTC-USD","price":"21000.5","size":"0.01","side":"SELL","time":"2023-02-09T20:19:35.367Z"}]}]}"#;
+        let message: CoinbaseMessage = serde_json::from_str(json).unwrap();
+        assert_eq!(message.channel, MARKET_TRADES_CHANNEL);
+        let trade = message.events[0].trades[0].to_trade_data("BTCUSD").unwrap();
+        assert_eq!(trade.trade_id, 12);
+        assert!(!trade.is_buy());
+        assert_eq!(trade.quote_quantity(), decimal("210.005"));
+        assert_eq!(trade.trade_time.timestamp_millis(), 1_675_973_975_367);
+    }
+
+    #[test]
+    fn test_kline_builder_closes_candles() {
+        let mut builder = KlineBuilder::new("BTCUSD", "1m").unwrap();
+        assert!(builder.push(&trade(1, 60_000, "100", "1")).is_none());
+        assert!(builder.push(&trade(2, 90_000, "104", "2")).is_none());
+        assert!(builder.push(&trade(3, 119_999, "98", "1")).is_none());
+        // Late trades of earlier candles are ignored.
+        assert!(builder.push(&trade(0, 59_000, "1", "1")).is_none());
+
+        let open = builder.current().unwrap();
+        assert!(!open.is_final);
+        assert_eq!(open.trade_count, 3);
+
+        let closed = builder.push(&trade(4, 180_500, "99", "1")).unwrap();
+        assert!(closed.is_final);
+        assert_eq!((closed.start_time, closed.end_time), (60_000, 119_999));
+        assert_eq!((closed.first_trade_id, closed.last_trade_id), (1, 3));
+        assert_eq!(decimal(&closed.high), decimal("104"));
+        assert_eq!(decimal(&closed.low), decimal("98"));
+        assert_eq!(decimal(&closed.close), decimal("98"));
+        assert_eq!(decimal(&closed.volume), decimal("4"));
+        assert_eq!(decimal(&closed.quote_volume), decimal("406"));
+
+        assert_eq!(builder.current_end(), Some(239_999));
+        assert!(builder.close_before(239_999).is_none());
+        let closed = builder.close_before(240_000).unwrap();
+        assert_eq!(closed.start_time, 180_000);
+        assert!(builder.current().is_none());
+    }
+}
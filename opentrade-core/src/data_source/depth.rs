@@ -0,0 +1,436 @@
+//! # Order Book Depth Streams
+//!
+//! This module maintains local order books from the Binance depth diff streams
+//! (`<symbol>@depth@100ms`). A diff only carries the levels that changed, so the
+//! book is built from a REST snapshot and kept in sync by applying the diffs that
+//! follow it, as described by Binance:
+//!
+//! 1. Buffer the diffs of the stream and fetch a snapshot from `/api/v3/depth`.
+//! 2. Fetch the snapshot again while it is older than the first buffered diff.
+//! 3. Drop the diffs contained in the snapshot; the first applied diff must contain
+//!    the update following it.
+//! 4. Apply every later diff, which must start right after the previous one.
+//!    Otherwise diffs were missed, and the book is rebuilt from a new snapshot.
+//!
+//! [`DepthStreaming`] implements [`StreamingClient<DepthUpdate>`] and performs the
+//! synchronization, so its callbacks receive the diffs applied to the book and
+//! [`DepthStreaming::book`] is always consistent with them. [`OrderBookSnapshotSink`]
+//! periodically stores the top levels of the book in `order_book_snapshots`.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use opentrade_core::data_source::depth::{DepthStreaming, OrderBookSnapshotSink};
+//! use opentrade_core::data_source::websocket::StreamingClient;
+//! # use anyhow::Result;
+//!
+//! # async fn example(pool: sqlx::PgPool) -> Result<()> {
+//! let mut stream = DepthStreaming::new("BTCUSDT").await?;
+//! stream.add_callback(Box::new(OrderBookSnapshotSink::new(pool, stream.book())));
+//! stream.subscribe().await?;
+//! stream.listen().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::DateTime;
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+use crate::data_source::rest::get_depth_snapshot;
+use crate::data_source::websocket::{
+    BINANCE_EXCHANGE, Borrowed, IngestContext, MessageHandler, SharedMessageHandler,
+    StreamingClient,
+};
+use crate::ingest::stats::StreamStats;
+use crate::models::DEFAULT_DATASET;
+use crate::models::order_book::{DepthSyncError, DepthUpdate, OrderBook};
+
+/// The combined-stream endpoint of Binance spot.
+pub const SPOT_STREAM_URL: &str = "wss://stream.binance.com:9443/stream";
+
+/// The default number of levels per side of the REST snapshots books are built from.
+pub const DEFAULT_SNAPSHOT_LIMIT: u32 = 1000;
+
+/// The number of diffs buffered while a book is synchronized, after which the
+/// oldest diffs are dropped.
+const MAX_PENDING_UPDATES: usize = 1000;
+
+/// A local order book shared between a [`DepthStreaming`] and its readers. It is
+/// `None` while the book is being synchronized.
+pub type SharedOrderBook = Arc<RwLock<Option<OrderBook>>>;
+
+/// A message of a combined stream, wrapping the event with its stream name.
+#[derive(Deserialize, Debug)]
+struct DepthPayload {
+    stream: String,
+    data: DepthUpdate,
+}
+
+/// A WebSocket client maintaining the local order book of one symbol from its
+/// depth diff stream.
+pub struct DepthStreaming {
+    url: String,
+    symbol: String,
+    stream: String,
+    snapshot_limit: u32,
+    socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    callbacks: Vec<Box<dyn SharedMessageHandler<DepthUpdate> + Send>>,
+    stats: Option<Arc<StreamStats>>,
+    generation: u64,
+    context: Option<IngestContext>,
+    book: SharedOrderBook,
+    /// The diffs received while the book is not synchronized.
+    pending: Vec<DepthUpdate>,
+}
+
+impl DepthStreaming {
+    /// Connects to the depth diff stream of a symbol, updated every 100ms.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbol` - The trading symbol (e.g., "BTCUSDT").
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the WebSocket connection cannot be established.
+    pub async fn new(symbol: &str) -> Result<Self> {
+        let (socket, _) = tokio_tungstenite::connect_async(SPOT_STREAM_URL)
+            .await
+            .with_context(|| format!("Failed to connect to {}", SPOT_STREAM_URL))?;
+        Ok(Self {
+            url: SPOT_STREAM_URL.to_string(),
+            symbol: symbol.to_uppercase(),
+            stream: format!("{}@depth@100ms", symbol.to_lowercase()),
+            snapshot_limit: DEFAULT_SNAPSHOT_LIMIT,
+            socket,
+            callbacks: Vec::new(),
+            stats: None,
+            generation: 0,
+            context: None,
+            book: Arc::new(RwLock::new(None)),
+            pending: Vec::new(),
+        })
+    }
+
+    /// Sets the number of levels per side of the REST snapshots (defaults to
+    /// [`DEFAULT_SNAPSHOT_LIMIT`]). Levels beyond it are only known once a diff
+    /// changes them.
+    pub fn with_snapshot_limit(mut self, limit: u32) -> Self {
+        self.snapshot_limit = limit;
+        self
+    }
+
+    /// Returns the stream name.
+    pub fn stream(&self) -> &str {
+        &self.stream
+    }
+
+    /// Returns the local order book, shared with the stream. It is updated before
+    /// the callbacks of a diff are called.
+    pub fn book(&self) -> SharedOrderBook {
+        self.book.clone()
+    }
+
+    /// Attaches shared [`StreamStats`] that record parse errors, handler errors and
+    /// the latency of the handler chain while [`listen`](StreamingClient::listen) is
+    /// running.
+    pub fn attach_stats(&mut self, stats: Arc<StreamStats>) {
+        self.stats = Some(stats);
+    }
+
+    /// Drops the book so it is rebuilt from a new snapshot.
+    fn reset(&mut self) {
+        *self.book.write().unwrap() = None;
+        self.pending.clear();
+    }
+
+    /// Builds the book from a REST snapshot and the buffered diffs.
+    ///
+    /// # Returns
+    ///
+    /// The last diff applied on top of the snapshot, or `None` if the book is not
+    /// synchronized yet or no buffered diff was newer than the snapshot.
+    async fn synchronize(&mut self) -> Result<Option<DepthUpdate>> {
+        let Some(first_update_id) = self.pending.first().map(|update| update.first_update_id)
+        else {
+            return Ok(None);
+        };
+        let snapshot = get_depth_snapshot(&self.symbol, self.snapshot_limit)
+            .await
+            .with_context(|| format!("Failed to fetch the order book of {}", self.symbol))?;
+        if snapshot.last_update_id + 1 < first_update_id {
+            log::debug!(
+                "Order book snapshot of {} at {} is older than the first diff at {}",
+                self.symbol,
+                snapshot.last_update_id,
+                first_update_id
+            );
+            return Ok(None);
+        }
+
+        let mut book = OrderBook::from_snapshot(&self.symbol, &snapshot)?;
+        let mut last_applied = None;
+        for update in std::mem::take(&mut self.pending) {
+            if book.apply(&update)? {
+                last_applied = Some(update);
+            }
+        }
+        log::info!(
+            "Synchronized the order book of {} at update {}",
+            self.symbol,
+            book.last_update_id()
+        );
+        *self.book.write().unwrap() = Some(book);
+        Ok(last_applied)
+    }
+
+    /// Buffers a diff received while the book is not synchronized and tries to
+    /// synchronize it. If the buffered diffs have a gap, they are dropped and the
+    /// following diffs are buffered for a new snapshot.
+    async fn buffer(&mut self, update: DepthUpdate) -> Result<Option<DepthUpdate>> {
+        if self.pending.len() >= MAX_PENDING_UPDATES {
+            self.pending.remove(0);
+        }
+        self.pending.push(update);
+        self.synchronize().await
+    }
+}
+
+#[async_trait]
+impl StreamingClient<DepthUpdate> for DepthStreaming {
+    async fn connect(&mut self) -> Result<()> {
+        let (socket, _) = tokio_tungstenite::connect_async(self.url.as_str())
+            .await
+            .with_context(|| format!("Failed to connect to {}", self.url))?;
+        self.socket = socket;
+        self.generation += 1;
+        // Diffs sent while disconnected are lost.
+        self.reset();
+        Ok(())
+    }
+
+    async fn subscribe(&mut self) -> Result<()> {
+        let request = serde_json::json!({
+            "method": "SUBSCRIBE",
+            "params": [self.stream],
+            "id": 1,
+        });
+        self.socket
+            .send(Message::Text(request.to_string()))
+            .await
+            .with_context(|| format!("Failed to subscribe to {}", self.stream))?;
+        Ok(())
+    }
+
+    async fn next(&mut self) -> Result<Option<Result<DepthUpdate>>> {
+        while let Some(message) = self.socket.next().await {
+            let text = match message {
+                Ok(Message::Text(text)) => text,
+                Ok(Message::Close(_)) => return Ok(None),
+                // Pings are answered by the socket itself.
+                Ok(_) => continue,
+                Err(e) => return Ok(Some(Err(anyhow::Error::msg(e.to_string())))),
+            };
+            let update = match serde_json::from_str::<DepthPayload>(&text) {
+                Ok(payload) => {
+                    self.context = Some(
+                        IngestContext::new(BINANCE_EXCHANGE, &payload.stream)
+                            .with_generation(self.generation),
+                    );
+                    payload.data
+                }
+                // Replies to requests, e.g. `{"result":null,"id":1}`, are not events.
+                Err(_) if text.contains("\"id\"") && !text.contains("\"stream\"") => continue,
+                Err(e) => {
+                    return Ok(Some(Err(anyhow::anyhow!(
+                        "Failed to parse depth update: {}: {}",
+                        e,
+                        text
+                    ))));
+                }
+            };
+
+            let applied = self
+                .book
+                .write()
+                .unwrap()
+                .as_mut()
+                .map(|book| book.apply(&update));
+            match applied {
+                Some(Ok(true)) => return Ok(Some(Ok(update))),
+                Some(Ok(false)) => continue,
+                Some(Err(e @ DepthSyncError::InvalidLevel(_))) => {
+                    return Ok(Some(Err(e.into())));
+                }
+                Some(Err(e)) => {
+                    log::warn!(
+                        "Order book of {} is out of sync, rebuilding it: {}",
+                        self.symbol,
+                        e
+                    );
+                    self.reset();
+                }
+                None => {}
+            }
+            match self.buffer(update).await {
+                Ok(Some(update)) => return Ok(Some(Ok(update))),
+                Ok(None) => continue,
+                Err(e) => return Ok(Some(Err(e))),
+            }
+        }
+        Ok(None)
+    }
+
+    fn context(&self) -> Option<IngestContext> {
+        self.context.clone()
+    }
+
+    fn add_callback(&mut self, handler: Box<dyn MessageHandler<DepthUpdate> + Send>) {
+        self.callbacks.push(Box::new(Borrowed(handler)));
+    }
+
+    fn add_shared_callback(&mut self, handler: Box<dyn SharedMessageHandler<DepthUpdate> + Send>) {
+        self.callbacks.push(handler);
+    }
+
+    async fn listen(&mut self) -> Result<()> {
+        while let Some(result) = StreamingClient::next(self).await? {
+            match result {
+                Ok(update) => {
+                    let update = Arc::new(update);
+                    let context = self
+                        .context
+                        .clone()
+                        .unwrap_or_else(|| IngestContext::new(BINANCE_EXCHANGE, &self.stream));
+                    let started_at = Instant::now();
+                    for callback in &mut self.callbacks {
+                        if let Err(e) = callback.handle_shared_with_context(&update, &context).await
+                        {
+                            if let Some(stats) = &self.stats {
+                                stats.record_error();
+                            }
+                            return Err(e);
+                        }
+                    }
+                    if let Some(stats) = &self.stats {
+                        stats.record_latency(started_at.elapsed());
+                    }
+                }
+                Err(e) => {
+                    if let Some(stats) = &self.stats {
+                        stats.record_error();
+                    }
+                    log::warn!("Error processing depth update: {:#}", e);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The default number of levels per side stored by [`OrderBookSnapshotSink`].
+pub const DEFAULT_PERSIST_DEPTH: usize = 20;
+
+/// The default time between two snapshots stored by [`OrderBookSnapshotSink`].
+pub const DEFAULT_PERSIST_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A [`MessageHandler`] that periodically stores the top levels of a local order
+/// book in the `order_book_snapshots` table.
+///
+/// Snapshots are taken when a diff arrives after the interval elapsed, and are
+/// recorded at the event time of that diff.
+pub struct OrderBookSnapshotSink {
+    pool: sqlx::PgPool,
+    book: SharedOrderBook,
+    dataset: String,
+    depth: usize,
+    interval: Duration,
+    last_persisted: Option<Instant>,
+}
+
+impl OrderBookSnapshotSink {
+    /// Creates a sink storing snapshots under the [`DEFAULT_DATASET`].
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `book` - The book to take snapshots of, see [`DepthStreaming::book`].
+    pub fn new(pool: sqlx::PgPool, book: SharedOrderBook) -> Self {
+        Self {
+            pool,
+            book,
+            dataset: DEFAULT_DATASET.to_string(),
+            depth: DEFAULT_PERSIST_DEPTH,
+            interval: DEFAULT_PERSIST_INTERVAL,
+            last_persisted: None,
+        }
+    }
+
+    /// Sets the dataset the snapshots are stored under.
+    pub fn with_dataset(mut self, dataset: &str) -> Self {
+        self.dataset = dataset.to_string();
+        self
+    }
+
+    /// Sets the number of levels per side to store (defaults to [`DEFAULT_PERSIST_DEPTH`]).
+    pub fn with_depth(mut self, depth: usize) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Sets the time between two snapshots (defaults to [`DEFAULT_PERSIST_INTERVAL`]).
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+}
+
+#[async_trait]
+impl MessageHandler<DepthUpdate> for OrderBookSnapshotSink {
+    async fn handle_message(&mut self, message: &DepthUpdate) -> Result<()> {
+        if self
+            .last_persisted
+            .is_some_and(|persisted| persisted.elapsed() < self.interval)
+        {
+            return Ok(());
+        }
+        let snapshot_time = i64::try_from(message.event_time)
+            .ok()
+            .and_then(DateTime::from_timestamp_millis)
+            .with_context(|| format!("Invalid event time: {}", message.event_time))?;
+        let snapshot = match self.book.read().unwrap().as_ref() {
+            Some(book) => book
+                .to_snapshot(self.depth, snapshot_time)
+                .with_dataset(&self.dataset),
+            None => return Ok(()),
+        };
+        snapshot
+            .upsert(&self.pool)
+            .await
+            .with_context(|| format!("Failed to store the order book of {}", snapshot.symbol))?;
+        self.last_persisted = Some(Instant::now());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_depth_payload() {
+        let json = r#"{"stream":"bnbbtc@depth@100ms","data":{"e":"depthUpdate","E":1672515782136,"s":"BNBBTC","U":157,"u":160,"b":[["0.0024","10"]],"a":[["0.0026","100"]]}}"#;
+        let payload: DepthPayload = serde_json::from_str(json).unwrap();
+        assert_eq!(payload.stream, "bnbbtc@depth@100ms");
+        assert_eq!(payload.data.symbol, "BNBBTC");
+        assert_eq!(payload.data.final_update_id, 160);
+    }
+}
@@ -0,0 +1,295 @@
+//! # Market Data Sources
+//!
+//! This module defines [`MarketDataSource`], the interface the ingestion layer uses
+//! to talk to an exchange: fetching historical klines, opening live kline streams,
+//! and normalizing symbols to the exchange's format. Backfills and hand-offs to
+//! live streams are written against this trait, so another exchange can be plugged
+//! in by implementing it without changing the ingestion code.
+//!
+//! [`Binance`] implements the trait with the REST and WebSocket clients of the
+//! [`rest`](super::rest) and [`websocket`](super::websocket) modules, and
+//! [`BinanceFutures`] with their variants for USDⓈ-M futures contracts, so futures
+//! klines go through the same backfills and pipelines as spot ones.
+//! [`Coinbase`](super::coinbase::Coinbase) implements it for the spot markets of
+//! Coinbase Advanced Trade, building live klines from its trade stream,
+//! [`Kraken`](super::kraken::Kraken) for those of Kraken,
+//! [`Bybit`](super::bybit::Bybit) for the spot and perpetual markets of Bybit, and
+//! [`Okx`](super::okx::Okx) for those of OKX.
+//!
+//! Intervals are passed as the labels used throughout the crate and stored in the
+//! database (e.g., "1m", "4h", "1d"); sources map them to their own format.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use opentrade_core::data_source::exchange::{Binance, MarketDataSource};
+//! # use anyhow::Result;
+//!
+//! # async fn example() -> Result<()> {
+//! let source = Binance;
+//! let symbol = source.normalize_symbol("btc/usdt");
+//! let klines = source.fetch_klines(&symbol, "1h", 1_700_000_000_000, None, Some(24)).await?;
+//! println!("Fetched {} klines from {}", klines.len(), source.name());
+//! # Ok(())
+//! # }
+//! ```
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+use crate::data_source::rest::{
+    DEFAULT_KLINE_LIMIT, MAX_FUTURES_KLINE_LIMIT, MAX_KLINE_LIMIT, extract_klines_from_string,
+    get_futures_kline_data, get_futures_server_time, get_kline_data, get_server_time,
+};
+use crate::data_source::websocket::{
+    BINANCE_EXCHANGE, BINANCE_FUTURES_EXCHANGE, KlineStreaming, StreamingClient,
+};
+use crate::ingest::symbols::parse_interval;
+use crate::models::{KlineData, SerdableKlineData};
+use crate::time::from_unsigned_millis;
+
+/// An exchange that provides historical and live kline data.
+#[async_trait]
+pub trait MarketDataSource: Send + Sync {
+    /// Returns the exchange name, as recorded in the
+    /// [`IngestContext`](crate::data_source::websocket::IngestContext) of its messages
+    /// and in the `exchange` column of the candles backfilled from it.
+    fn name(&self) -> &str;
+
+    /// Converts a symbol from a common spelling (e.g., "btc-usdt", "BTC/USDT") to
+    /// the format the exchange expects.
+    fn normalize_symbol(&self, symbol: &str) -> String;
+
+    /// Returns the maximum number of klines returned by one
+    /// [`fetch_klines`](Self::fetch_klines) call.
+    fn max_kline_limit(&self) -> u32;
+
+    /// Returns the number of klines returned by one
+    /// [`fetch_klines`](Self::fetch_klines) call without a limit.
+    fn default_kline_limit(&self) -> u32;
+
+    /// Fetches the klines of a symbol starting at or after `start_time`, oldest
+    /// first.
+    ///
+    /// The returned klines are labeled with the requested `symbol` and `interval`.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbol` - The trading symbol, in the exchange's format.
+    /// * `interval` - The kline interval (e.g., "1m").
+    /// * `start_time` - The start time in milliseconds since the UNIX epoch.
+    /// * `end_time` - An optional end time in milliseconds since the UNIX epoch.
+    /// * `limit` - An optional limit on the number of klines, at most
+    ///   [`max_kline_limit`](Self::max_kline_limit).
+    async fn fetch_klines(
+        &self,
+        symbol: &str,
+        interval: &str,
+        start_time: u64,
+        end_time: Option<u64>,
+        limit: Option<u32>,
+    ) -> Result<Vec<KlineData>>;
+
+    /// Opens a live kline stream of a symbol. The stream still has to be
+    /// subscribed before messages are received.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbol` - The trading symbol, in the exchange's format.
+    /// * `interval` - The kline interval (e.g., "1m").
+    async fn stream_klines(
+        &self,
+        symbol: &str,
+        interval: &str,
+    ) -> Result<Box<dyn StreamingClient<SerdableKlineData>>>;
+
+    /// Returns the current time of the exchange, which decides when its candles
+    /// open and close. Defaults to the local clock for sources without a time
+    /// endpoint.
+    ///
+    /// Prefer [`clock::server_now`](crate::data_source::clock::server_now), which
+    /// caches the offset to the local clock instead of requesting the time on
+    /// every call.
+    async fn server_time(&self) -> Result<DateTime<Utc>> {
+        Ok(Utc::now())
+    }
+}
+
+/// The Binance spot exchange.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Binance;
+
+#[async_trait]
+impl MarketDataSource for Binance {
+    fn name(&self) -> &str {
+        BINANCE_EXCHANGE
+    }
+
+    fn normalize_symbol(&self, symbol: &str) -> String {
+        symbol
+            .chars()
+            .filter(char::is_ascii_alphanumeric)
+            .collect::<String>()
+            .to_uppercase()
+    }
+
+    fn max_kline_limit(&self) -> u32 {
+        MAX_KLINE_LIMIT
+    }
+
+    fn default_kline_limit(&self) -> u32 {
+        DEFAULT_KLINE_LIMIT
+    }
+
+    async fn fetch_klines(
+        &self,
+        symbol: &str,
+        interval: &str,
+        start_time: u64,
+        end_time: Option<u64>,
+        limit: Option<u32>,
+    ) -> Result<Vec<KlineData>> {
+        let kline_interval = parse_interval(interval)
+            .with_context(|| format!("Unsupported interval for Binance: {}", interval))?;
+        let raw_data = get_kline_data(symbol, kline_interval, start_time, end_time, limit).await?;
+        let mut klines = extract_klines_from_string(&raw_data, symbol)
+            .context("Failed to extract klines from the Binance response")?;
+        for kline in &mut klines {
+            kline.interval = interval.to_string();
+            kline.exchange = self.name().to_string();
+        }
+        Ok(klines)
+    }
+
+    async fn stream_klines(
+        &self,
+        symbol: &str,
+        interval: &str,
+    ) -> Result<Box<dyn StreamingClient<SerdableKlineData>>> {
+        let kline_interval = parse_interval(interval)
+            .with_context(|| format!("Unsupported interval for Binance: {}", interval))?;
+        Ok(Box::new(KlineStreaming::new(symbol, kline_interval).await?))
+    }
+
+    async fn server_time(&self) -> Result<DateTime<Utc>> {
+        let millis = get_server_time().await?;
+        from_unsigned_millis(millis).context("Invalid Binance server time")
+    }
+}
+
+/// The Binance USDⓈ-M futures exchange.
+///
+/// Klines are those of trades by default, or of the mark price with
+/// [`with_mark_price`](Self::with_mark_price). Mark price klines are only
+/// available over REST; their volumes and trade counts are zero.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BinanceFutures {
+    mark_price: bool,
+}
+
+impl BinanceFutures {
+    /// Fetches the klines of the mark price instead of trades.
+    pub fn with_mark_price(mut self) -> Self {
+        self.mark_price = true;
+        self
+    }
+}
+
+#[async_trait]
+impl MarketDataSource for BinanceFutures {
+    fn name(&self) -> &str {
+        BINANCE_FUTURES_EXCHANGE
+    }
+
+    fn normalize_symbol(&self, symbol: &str) -> String {
+        Binance.normalize_symbol(symbol)
+    }
+
+    fn max_kline_limit(&self) -> u32 {
+        MAX_FUTURES_KLINE_LIMIT
+    }
+
+    fn default_kline_limit(&self) -> u32 {
+        DEFAULT_KLINE_LIMIT
+    }
+
+    async fn fetch_klines(
+        &self,
+        symbol: &str,
+        interval: &str,
+        start_time: u64,
+        end_time: Option<u64>,
+        limit: Option<u32>,
+    ) -> Result<Vec<KlineData>> {
+        let kline_interval = parse_interval(interval)
+            .with_context(|| format!("Unsupported interval for Binance futures: {}", interval))?;
+        let raw_data = get_futures_kline_data(
+            symbol,
+            kline_interval,
+            start_time,
+            end_time,
+            limit,
+            self.mark_price,
+        )
+        .await?;
+        let mut klines = extract_klines_from_string(&raw_data, symbol)
+            .context("Failed to extract klines from the Binance futures response")?;
+        for kline in &mut klines {
+            kline.interval = interval.to_string();
+            kline.exchange = self.name().to_string();
+        }
+        Ok(klines)
+    }
+
+    async fn stream_klines(
+        &self,
+        symbol: &str,
+        interval: &str,
+    ) -> Result<Box<dyn StreamingClient<SerdableKlineData>>> {
+        if self.mark_price {
+            anyhow::bail!("Mark price klines of {} cannot be streamed", symbol);
+        }
+        let kline_interval = parse_interval(interval)
+            .with_context(|| format!("Unsupported interval for Binance futures: {}", interval))?;
+        Ok(Box::new(
+            KlineStreaming::usdm_futures(symbol, kline_interval).await?,
+        ))
+    }
+
+    async fn server_time(&self) -> Result<DateTime<Utc>> {
+        let millis = get_futures_server_time().await?;
+        from_unsigned_millis(millis).context("Invalid Binance futures server time")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binance_normalizes_symbols() {
+        assert_eq!(Binance.normalize_symbol("btc-usdt"), "BTCUSDT");
+        assert_eq!(Binance.normalize_symbol("BTC/USDT"), "BTCUSDT");
+        assert_eq!(Binance.normalize_symbol("ethbtc"), "ETHBTC");
+        assert_eq!(Binance.name(), "binance");
+    }
+
+    #[test]
+    fn test_binance_futures_is_a_separate_exchange() {
+        let source = BinanceFutures::default();
+        assert_eq!(source.normalize_symbol("btc-usdt"), "BTCUSDT");
+        assert_eq!(source.name(), "binance-futures");
+        assert_eq!(source.max_kline_limit(), 1500);
+        assert_ne!(source, source.with_mark_price());
+    }
+
+    #[tokio::test]
+    async fn test_binance_rejects_unknown_interval() {
+        let error = Binance
+            .fetch_klines("BTCUSDT", "7m", 0, None, None)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("7m"));
+    }
+}
@@ -0,0 +1,299 @@
+//! # Futures Price Streams
+//!
+//! Mark and index prices of futures contracts diverge from the last-trade prices
+//! of klines: liquidations and unrealized PnL are computed from the mark price,
+//! which in turn follows the index price aggregated over spot exchanges. This
+//! module streams both from the Binance futures WebSocket APIs:
+//!
+//! - Mark prices of USDⓈ-M contracts (`<symbol>@markPrice@1s` on `fstream`), which
+//!   also carry the index price, estimated settle price and current funding rate
+//! - Index prices of COIN-M pairs (`<pair>@indexPrice@1s` on `dstream`)
+//!
+//! [`FuturesPriceStreaming`] implements [`StreamingClient<PriceUpdate>`], so it is
+//! managed like the kline streams, and [`UpsertPriceSink`] stores the updates in
+//! the `futures_prices` table. Klines of USDⓈ-M contracts are fetched and streamed
+//! by [`BinanceFutures`](super::exchange::BinanceFutures) instead.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use opentrade_core::data_source::futures::{FuturesPriceStreaming, UpsertPriceSink};
+//! use opentrade_core::data_source::websocket::StreamingClient;
+//! # use anyhow::Result;
+//!
+//! # async fn example(pool: sqlx::PgPool) -> Result<()> {
+//! let mut stream = FuturesPriceStreaming::mark_price("BTCUSDT").await?;
+//! stream.add_callback(Box::new(UpsertPriceSink::new(pool)));
+//! stream.subscribe().await?;
+//! stream.listen().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+use crate::data_source::websocket::{
+    BINANCE_EXCHANGE, Borrowed, IngestContext, MessageHandler, SharedMessageHandler,
+    StreamingClient,
+};
+use crate::ingest::stats::StreamStats;
+use crate::models::DEFAULT_DATASET;
+use crate::models::futures_price::PriceUpdate;
+
+/// The combined-stream endpoint of Binance USDⓈ-M futures.
+pub const USDM_FUTURES_STREAM_URL: &str = "wss://fstream.binance.com/stream";
+
+/// The combined-stream endpoint of Binance COIN-M futures.
+pub const COINM_FUTURES_STREAM_URL: &str = "wss://dstream.binance.com/stream";
+
+/// A message of a combined stream, wrapping the event with its stream name.
+#[derive(Deserialize, Debug)]
+struct PricePayload {
+    stream: String,
+    data: PriceUpdate,
+}
+
+/// A WebSocket client streaming the mark or index price of one futures contract.
+pub struct FuturesPriceStreaming {
+    url: String,
+    stream: String,
+    socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    callbacks: Vec<Box<dyn SharedMessageHandler<PriceUpdate> + Send>>,
+    stats: Option<Arc<StreamStats>>,
+    generation: u64,
+    context: Option<IngestContext>,
+}
+
+impl FuturesPriceStreaming {
+    /// Connects to the mark price stream of a USDⓈ-M contract, updated every second.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbol` - The contract symbol (e.g., "BTCUSDT").
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the WebSocket connection cannot be established.
+    pub async fn mark_price(symbol: &str) -> Result<Self> {
+        let stream = format!("{}@markPrice@1s", symbol.to_lowercase());
+        Self::new(USDM_FUTURES_STREAM_URL, &stream).await
+    }
+
+    /// Connects to the index price stream of a COIN-M pair, updated every second.
+    ///
+    /// # Arguments
+    ///
+    /// * `pair` - The index pair (e.g., "BTCUSD").
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the WebSocket connection cannot be established.
+    pub async fn index_price(pair: &str) -> Result<Self> {
+        let stream = format!("{}@indexPrice@1s", pair.to_lowercase());
+        Self::new(COINM_FUTURES_STREAM_URL, &stream).await
+    }
+
+    /// Connects to a stream of a combined-stream endpoint.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The combined-stream endpoint.
+    /// * `stream` - The stream name (e.g., "btcusdt@markPrice@1s").
+    pub async fn new(url: &str, stream: &str) -> Result<Self> {
+        let (socket, _) = tokio_tungstenite::connect_async(url)
+            .await
+            .with_context(|| format!("Failed to connect to {}", url))?;
+        Ok(Self {
+            url: url.to_string(),
+            stream: stream.to_string(),
+            socket,
+            callbacks: Vec::new(),
+            stats: None,
+            generation: 0,
+            context: None,
+        })
+    }
+
+    /// Returns the stream name.
+    pub fn stream(&self) -> &str {
+        &self.stream
+    }
+
+    /// Attaches shared [`StreamStats`] that record parse errors, handler errors and
+    /// the latency of the handler chain while [`listen`](StreamingClient::listen) is
+    /// running.
+    pub fn attach_stats(&mut self, stats: Arc<StreamStats>) {
+        self.stats = Some(stats);
+    }
+}
+
+#[async_trait]
+impl StreamingClient<PriceUpdate> for FuturesPriceStreaming {
+    async fn connect(&mut self) -> Result<()> {
+        let (socket, _) = tokio_tungstenite::connect_async(self.url.as_str())
+            .await
+            .with_context(|| format!("Failed to connect to {}", self.url))?;
+        self.socket = socket;
+        self.generation += 1;
+        Ok(())
+    }
+
+    async fn subscribe(&mut self) -> Result<()> {
+        let request = serde_json::json!({
+            "method": "SUBSCRIBE",
+            "params": [self.stream],
+            "id": 1,
+        });
+        self.socket
+            .send(Message::Text(request.to_string()))
+            .await
+            .with_context(|| format!("Failed to subscribe to {}", self.stream))?;
+        Ok(())
+    }
+
+    async fn next(&mut self) -> Result<Option<Result<PriceUpdate>>> {
+        while let Some(message) = self.socket.next().await {
+            let text = match message {
+                Ok(Message::Text(text)) => text,
+                Ok(Message::Close(_)) => return Ok(None),
+                // Pings are answered by the socket itself.
+                Ok(_) => continue,
+                Err(e) => return Ok(Some(Err(anyhow::Error::msg(e.to_string())))),
+            };
+            match serde_json::from_str::<PricePayload>(&text) {
+                Ok(payload) => {
+                    self.context = Some(
+                        IngestContext::new(BINANCE_EXCHANGE, &payload.stream)
+                            .with_generation(self.generation),
+                    );
+                    return Ok(Some(Ok(payload.data)));
+                }
+                // Replies to requests, e.g. `{"result":null,"id":1}`, are not events.
+                Err(_) if text.contains("\"id\"") && !text.contains("\"stream\"") => continue,
+                Err(e) => {
+                    return Ok(Some(Err(anyhow::anyhow!(
+                        "Failed to parse price update: {}: {}",
+                        e,
+                        text
+                    ))));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn context(&self) -> Option<IngestContext> {
+        self.context.clone()
+    }
+
+    fn add_callback(&mut self, handler: Box<dyn MessageHandler<PriceUpdate> + Send>) {
+        self.callbacks.push(Box::new(Borrowed(handler)));
+    }
+
+    fn add_shared_callback(&mut self, handler: Box<dyn SharedMessageHandler<PriceUpdate> + Send>) {
+        self.callbacks.push(handler);
+    }
+
+    async fn listen(&mut self) -> Result<()> {
+        while let Some(result) = StreamingClient::next(self).await? {
+            match result {
+                Ok(update) => {
+                    let update = Arc::new(update);
+                    let context = self
+                        .context
+                        .clone()
+                        .unwrap_or_else(|| IngestContext::new(BINANCE_EXCHANGE, &self.stream));
+                    let started_at = Instant::now();
+                    for callback in &mut self.callbacks {
+                        if let Err(e) = callback.handle_shared_with_context(&update, &context).await
+                        {
+                            if let Some(stats) = &self.stats {
+                                stats.record_error();
+                            }
+                            return Err(e);
+                        }
+                    }
+                    if let Some(stats) = &self.stats {
+                        stats.record_latency(started_at.elapsed());
+                    }
+                }
+                Err(e) => {
+                    if let Some(stats) = &self.stats {
+                        stats.record_error();
+                    }
+                    log::warn!("Error processing price update: {}", e);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A [`MessageHandler`] that stores price updates in the `futures_prices` table.
+pub struct UpsertPriceSink {
+    pool: sqlx::PgPool,
+    dataset: String,
+}
+
+impl UpsertPriceSink {
+    /// Creates a sink storing updates under the [`DEFAULT_DATASET`].
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    pub fn new(pool: sqlx::PgPool) -> Self {
+        Self {
+            pool,
+            dataset: DEFAULT_DATASET.to_string(),
+        }
+    }
+
+    /// Sets the dataset the updates are stored under.
+    pub fn with_dataset(mut self, dataset: &str) -> Self {
+        self.dataset = dataset.to_string();
+        self
+    }
+}
+
+#[async_trait]
+impl MessageHandler<PriceUpdate> for UpsertPriceSink {
+    async fn handle_message(&mut self, message: &PriceUpdate) -> Result<()> {
+        let price = message.to_futures_price()?.with_dataset(&self.dataset);
+        price
+            .upsert(&self.pool)
+            .await
+            .with_context(|| format!("Failed to store the price of {}", price.symbol))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mark_price_payload() {
+        let json = r#"{"stream":"btcusdt@markPrice@1s","data":{"e":"markPriceUpdate","E":1562305380000,"s":"BTCUSDT","p":"11794.15000000","i":"11784.62659091","P":"11784.25641265","r":"0.00038167","T":1562306400000}}"#;
+        let payload: PricePayload = serde_json::from_str(json).unwrap();
+        assert_eq!(payload.stream, "btcusdt@markPrice@1s");
+        match payload.data {
+            PriceUpdate::MarkPrice(update) => assert_eq!(update.mark_price, "11794.15000000"),
+            other => panic!("Unexpected update: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_index_price_payload() {
+        let json = r#"{"stream":"btcusd@indexPrice@1s","data":{"e":"indexPriceUpdate","E":1591261236000,"i":"BTCUSD","p":"9636.57860000"}}"#;
+        let payload: PricePayload = serde_json::from_str(json).unwrap();
+        assert_eq!(payload.data.symbol(), "BTCUSD");
+    }
+}
@@ -0,0 +1,813 @@
+//! # Kraken
+//!
+//! This module implements [`MarketDataSource`] for the spot markets of Kraken, so
+//! its pairs go through the same backfills and pipelines as Binance symbols:
+//!
+//! - Historical klines are fetched from the public `OHLC` endpoint of the REST API.
+//! - Live klines are streamed from the `ohlc` channel of the WebSocket v2 API by
+//!   [`KrakenKlineStreaming`].
+//!
+//! ## Symbols
+//!
+//! Kraken names assets differently depending on the API: the REST API calls bitcoin
+//! "XBT" and Dogecoin "XDG", and answers with legacy pair names such as
+//! "XXBTZUSD", while the WebSocket v2 API uses "BTC/USD". Candles are stored under
+//! the normalized symbol scheme of the other sources, base and quote asset without
+//! a separator and with the common asset codes (e.g., "BTCUSD"), and
+//! [`Kraken::normalize_symbol`] accepts any of these spellings. A pair is split
+//! into its assets by its quote asset (see [`split_symbol`]). The source reports
+//! itself as [`KRAKEN_EXCHANGE`]; store its candles under their own dataset, as
+//! symbols like "BTCUSDT" exist on several exchanges.
+//!
+//! ## Mapping
+//!
+//! - Candles have no trade ids, so their first and last trade ids are 0. The quote
+//!   volume is the volume weighted average price times the volume.
+//! - The REST API only serves the latest [`MAX_OHLC_LIMIT`] candles of an interval;
+//!   requests for older candles fail instead of silently skipping them.
+//! - The WebSocket API does not mark candles as final. A candle is emitted as final
+//!   once an update of a later candle arrives; use the finalization timer of
+//!   [`CloseRepairSink`](crate::ingest::close_repair::CloseRepairSink) for pairs
+//!   that trade rarely.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use opentrade_core::data_source::exchange::MarketDataSource;
+//! use opentrade_core::data_source::kraken::Kraken;
+//! # use anyhow::Result;
+//!
+//! # async fn example() -> Result<()> {
+//! let source = Kraken::new();
+//! let symbol = source.normalize_symbol("XBT/USD");
+//! assert_eq!(symbol, "BTCUSD");
+//! let klines = source.fetch_klines(&symbol, "1h", 1_700_000_000_000, None, Some(24)).await?;
+//! println!("Fetched {} klines from {}", klines.len(), source.name());
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Instant;
+
+use anyhow::{Context, Result, bail};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use sqlx::types::BigDecimal as Decimal;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+use crate::data_source::exchange::MarketDataSource;
+use crate::data_source::websocket::{
+    Borrowed, IngestContext, MessageHandler, SharedMessageHandler, StreamingClient,
+};
+use crate::ingest::audit::interval_duration;
+use crate::ingest::stats::StreamStats;
+use crate::models::{KlineData, SerdableKlineData};
+
+/// The exchange name of Kraken sources.
+pub const KRAKEN_EXCHANGE: &str = "kraken";
+
+/// The base URL of the public REST API.
+pub const KRAKEN_REST_URL: &str = "https://api.kraken.com/0/public";
+
+/// The URL of the WebSocket v2 API.
+pub const KRAKEN_STREAM_URL: &str = "wss://ws.kraken.com/v2";
+
+/// The number of the latest candles of an interval the REST API serves.
+pub const MAX_OHLC_LIMIT: u32 = 720;
+
+/// The number of decimal places quote volumes are rounded to, matching `kline_data`.
+const QUOTE_VOLUME_SCALE: i64 = 8;
+
+/// Quote assets, tried in order to split a symbol into its assets.
+const QUOTE_ASSETS: [&str; 13] = [
+    "USDT", "USDC", "USD", "EUR", "GBP", "CAD", "AUD", "CHF", "JPY", "DAI", "BTC", "XBT", "ETH",
+];
+
+/// Kraken asset codes that differ from the common ones, as (Kraken, common).
+const ASSET_ALIASES: [(&str, &str); 2] = [("XBT", "BTC"), ("XDG", "DOGE")];
+
+/// Returns the length of an interval in minutes, as the Kraken APIs expect it, or
+/// `None` if Kraken has no candles of that length.
+pub fn ohlc_interval(interval: &str) -> Option<u32> {
+    let minutes = match interval {
+        "1m" => 1,
+        "5m" => 5,
+        "15m" => 15,
+        "30m" => 30,
+        "1h" => 60,
+        "4h" => 240,
+        "1d" => 1440,
+        _ => return None,
+    };
+    Some(minutes)
+}
+
+/// Returns the common code of a Kraken asset (e.g., "XBT" to "BTC").
+fn common_asset(asset: &str) -> &str {
+    ASSET_ALIASES
+        .iter()
+        .find(|(kraken, _)| *kraken == asset)
+        .map_or(asset, |(_, common)| common)
+}
+
+/// Returns the Kraken REST code of a common asset (e.g., "BTC" to "XBT").
+fn kraken_asset(asset: &str) -> &str {
+    ASSET_ALIASES
+        .iter()
+        .find(|(_, common)| *common == asset)
+        .map_or(asset, |(kraken, _)| kraken)
+}
+
+/// Splits a symbol in any Kraken spelling into its base and quote asset, with the
+/// common asset codes.
+///
+/// # Example
+///
+/// ```rust
+/// use opentrade_core::data_source::kraken::split_symbol;
+///
+/// let btc_usd = Some(("BTC".to_string(), "USD".to_string()));
+/// assert_eq!(split_symbol("XBT/USD"), btc_usd);
+/// assert_eq!(split_symbol("XXBTZUSD"), btc_usd);
+/// assert_eq!(split_symbol("btcusd"), btc_usd);
+/// ```
+pub fn split_symbol(symbol: &str) -> Option<(String, String)> {
+    let symbol = symbol.to_uppercase();
+    let pair = |base: &str, quote: &str| {
+        Some((
+            common_asset(base).to_string(),
+            common_asset(quote).to_string(),
+        ))
+    };
+    if let Some((base, quote)) = symbol.split_once(['/', '-', '_']) {
+        return pair(base, quote);
+    }
+    // Legacy names prefix crypto assets with X and fiat ones with Z.
+    let bytes = symbol.as_bytes();
+    if symbol.len() == 8 && bytes[0] == b'X' && matches!(bytes[4], b'X' | b'Z') {
+        return pair(&symbol[1..4], &symbol[5..]);
+    }
+    QUOTE_ASSETS.iter().find_map(|quote| {
+        symbol
+            .strip_suffix(quote)
+            .filter(|base| !base.is_empty())
+            .and_then(|base| pair(base, quote))
+    })
+}
+
+/// A candle of the REST API: time (seconds since the epoch), open, high, low,
+/// close, volume weighted average price, volume and trade count.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct KrakenOhlc(
+    pub i64,
+    pub String,
+    pub String,
+    pub String,
+    pub String,
+    pub String,
+    pub String,
+    pub i64,
+);
+
+#[derive(Deserialize)]
+struct KrakenResponse<T> {
+    #[serde(default)]
+    error: Vec<String>,
+    result: Option<T>,
+}
+
+impl<T> KrakenResponse<T> {
+    fn into_result(self) -> Result<T> {
+        if let Some(error) = self.error.first() {
+            bail!("Kraken returned an error: {}", error);
+        }
+        self.result.context("Kraken returned no result")
+    }
+}
+
+#[derive(Deserialize)]
+struct TimeResult {
+    unixtime: i64,
+}
+
+/// Returns the product of two decimal strings, rounded to the scale of
+/// `kline_data`.
+fn product(left: &str, right: &str) -> Result<Decimal> {
+    let left: Decimal = left
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid decimal: {}", left))?;
+    let right: Decimal = right
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid decimal: {}", right))?;
+    Ok((left * right).round(QUOTE_VOLUME_SCALE))
+}
+
+/// Converts REST candles into klines.
+///
+/// # Arguments
+///
+/// * `rows` - The candles, oldest first.
+/// * `symbol` - The symbol the klines are labeled with.
+/// * `interval` - The interval the klines are labeled with.
+/// * `step` - The length of `interval`.
+///
+/// # Errors
+///
+/// Returns an error if a candle has an unparseable field.
+pub fn klines_from_ohlc(
+    rows: &[KrakenOhlc],
+    symbol: &str,
+    interval: &str,
+    step: chrono::Duration,
+) -> Result<Vec<KlineData>> {
+    fn decimal(value: &str) -> Result<Decimal> {
+        value
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid OHLC value: {}", value))
+    }
+
+    let step_ms = step.num_milliseconds() as u64;
+    rows.iter()
+        .map(|row| {
+            let KrakenOhlc(time, open, high, low, close, vwap, volume, count) = row;
+            let start = u64::try_from(*time)
+                .with_context(|| format!("Invalid OHLC time: {}", time))?
+                * 1000;
+            Ok(KlineData::new(
+                &start,
+                &(start + step_ms - 1),
+                symbol,
+                interval,
+                0,
+                0,
+                decimal(open)?,
+                decimal(high)?,
+                decimal(low)?,
+                decimal(close)?,
+                decimal(volume)?,
+                Some(i32::try_from(*count).unwrap_or(i32::MAX)),
+                Some(product(vwap, volume)?),
+            )
+            .with_exchange(KRAKEN_EXCHANGE))
+        })
+        .collect()
+}
+
+/// The Kraken spot exchange.
+#[derive(Debug, Clone, Default)]
+pub struct Kraken {
+    client: reqwest::Client,
+}
+
+impl Kraken {
+    /// Creates a Kraken source.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the pair name of a symbol in the REST API (e.g., "XBTUSD").
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the symbol has no known quote asset.
+    pub fn rest_pair(&self, symbol: &str) -> Result<String> {
+        let (base, quote) =
+            split_symbol(symbol).with_context(|| format!("No Kraken pair known for {}", symbol))?;
+        Ok(format!("{}{}", kraken_asset(&base), kraken_asset(&quote)))
+    }
+
+    /// Returns the symbol of a pair in the WebSocket v2 API (e.g., "BTC/USD").
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the symbol has no known quote asset.
+    pub fn stream_symbol(&self, symbol: &str) -> Result<String> {
+        let (base, quote) =
+            split_symbol(symbol).with_context(|| format!("No Kraken pair known for {}", symbol))?;
+        Ok(format!("{}/{}", base, quote))
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str, query: &[(&str, String)]) -> Result<T> {
+        let response: KrakenResponse<T> = self
+            .client
+            .get(format!("{}/{}", KRAKEN_REST_URL, path))
+            .query(query)
+            .send()
+            .await
+            .with_context(|| format!("Failed to request Kraken {}", path))?
+            .error_for_status()
+            .with_context(|| format!("Kraken rejected the {} request", path))?
+            .json()
+            .await
+            .with_context(|| format!("Failed to parse the Kraken {} response", path))?;
+        response.into_result()
+    }
+}
+
+#[async_trait]
+impl MarketDataSource for Kraken {
+    fn name(&self) -> &str {
+        KRAKEN_EXCHANGE
+    }
+
+    fn normalize_symbol(&self, symbol: &str) -> String {
+        match split_symbol(symbol) {
+            Some((base, quote)) => format!("{}{}", base, quote),
+            None => symbol
+                .chars()
+                .filter(char::is_ascii_alphanumeric)
+                .collect::<String>()
+                .to_uppercase(),
+        }
+    }
+
+    fn max_kline_limit(&self) -> u32 {
+        MAX_OHLC_LIMIT
+    }
+
+    fn default_kline_limit(&self) -> u32 {
+        MAX_OHLC_LIMIT
+    }
+
+    async fn fetch_klines(
+        &self,
+        symbol: &str,
+        interval: &str,
+        start_time: u64,
+        end_time: Option<u64>,
+        limit: Option<u32>,
+    ) -> Result<Vec<KlineData>> {
+        let minutes = ohlc_interval(interval)
+            .with_context(|| format!("Unsupported interval for Kraken: {}", interval))?;
+        let step = interval_duration(interval)
+            .with_context(|| format!("Unsupported interval for Kraken: {}", interval))?;
+        let pair = self.rest_pair(symbol)?;
+        let step_ms = step.num_milliseconds() as u64;
+        let limit = limit
+            .unwrap_or(self.default_kline_limit())
+            .min(self.max_kline_limit());
+
+        // Candles starting in [start_time, window_end), like the Binance API.
+        let first_start = start_time.div_ceil(step_ms) * step_ms;
+        let mut window_end = first_start + u64::from(limit) * step_ms;
+        if let Some(end_time) = end_time {
+            window_end = window_end.min(end_time + 1);
+        }
+        if first_start >= window_end {
+            return Ok(Vec::new());
+        }
+
+        // `since` is exclusive, so ask for the candles after the second before.
+        let since = (first_start / 1000).saturating_sub(1);
+        let result: HashMap<String, serde_json::Value> = self
+            .get(
+                "OHLC",
+                &[
+                    ("pair", pair.clone()),
+                    ("interval", minutes.to_string()),
+                    ("since", since.to_string()),
+                ],
+            )
+            .await?;
+        // The candles are keyed by the legacy pair name, next to `last`.
+        let rows = match result.into_iter().find(|(key, _)| key != "last") {
+            Some((_, rows)) => serde_json::from_value::<Vec<KrakenOhlc>>(rows)
+                .context("Failed to parse the Kraken OHLC response")?,
+            None => Vec::new(),
+        };
+        if let Some(earliest) = rows.first().map(|row| row.0.max(0) as u64 * 1000)
+            && earliest > first_start
+            && earliest >= window_end
+        {
+            bail!(
+                "Kraken only serves the latest {} {} candles of {}, starting at {}",
+                MAX_OHLC_LIMIT,
+                interval,
+                pair,
+                DateTime::from_timestamp_millis(earliest as i64).unwrap_or_default()
+            );
+        }
+
+        let mut klines = klines_from_ohlc(&rows, symbol, interval, step)?;
+        klines.retain(|kline| {
+            let start = kline.start_time.timestamp_millis() as u64;
+            start >= first_start && start < window_end
+        });
+        Ok(klines)
+    }
+
+    async fn stream_klines(
+        &self,
+        symbol: &str,
+        interval: &str,
+    ) -> Result<Box<dyn StreamingClient<SerdableKlineData>>> {
+        let stream_symbol = self.stream_symbol(symbol)?;
+        Ok(Box::new(
+            KrakenKlineStreaming::new(symbol, &stream_symbol, interval).await?,
+        ))
+    }
+
+    async fn server_time(&self) -> Result<DateTime<Utc>> {
+        let result: TimeResult = self.get("Time", &[]).await?;
+        DateTime::from_timestamp(result.unixtime, 0)
+            .with_context(|| format!("Invalid Kraken server time: {}", result.unixtime))
+    }
+}
+
+/// A candle of the `ohlc` channel of the WebSocket v2 API. Prices and volumes are
+/// JSON numbers.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct KrakenCandle {
+    /// The pair (e.g., "BTC/USD").
+    pub symbol: String,
+    /// The opening price.
+    pub open: serde_json::Number,
+    /// The highest price.
+    pub high: serde_json::Number,
+    /// The lowest price.
+    pub low: serde_json::Number,
+    /// The closing price.
+    pub close: serde_json::Number,
+    /// The number of trades.
+    pub trades: u64,
+    /// The traded volume of the base asset.
+    pub volume: serde_json::Number,
+    /// The volume weighted average price.
+    pub vwap: serde_json::Number,
+    /// The start of the candle, in RFC 3339 format.
+    pub interval_begin: String,
+    /// The length of the candle in minutes.
+    pub interval: u32,
+    /// The time of the update, in RFC 3339 format.
+    pub timestamp: String,
+}
+
+impl KrakenCandle {
+    /// Converts the candle into an update of an open kline.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbol` - The normalized symbol the kline is labeled with.
+    /// * `interval` - The interval the kline is labeled with.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a time or volume cannot be parsed.
+    pub fn to_serdable_kline(&self, symbol: &str, interval: &str) -> Result<SerdableKlineData> {
+        fn millis(value: &str) -> Result<u64> {
+            let time = DateTime::parse_from_rfc3339(value)
+                .with_context(|| format!("Invalid candle time: {}", value))?;
+            u64::try_from(time.timestamp_millis())
+                .with_context(|| format!("Invalid candle time: {}", value))
+        }
+
+        let start_time = millis(&self.interval_begin)?;
+        Ok(SerdableKlineData {
+            start_time,
+            end_time: start_time + u64::from(self.interval) * 60_000 - 1,
+            symbol: symbol.to_string(),
+            interval: interval.to_string(),
+            first_trade_id: 0,
+            last_trade_id: 0,
+            open: self.open.to_string(),
+            close: self.close.to_string(),
+            high: self.high.to_string(),
+            low: self.low.to_string(),
+            volume: self.volume.to_string(),
+            trade_count: self.trades,
+            quote_volume: product(&self.vwap.to_string(), &self.volume.to_string())?.to_string(),
+            event_time: Some(millis(&self.timestamp)?),
+            is_final: false,
+            exchange: Some(KRAKEN_EXCHANGE.to_string()),
+        })
+    }
+}
+
+/// A message of the WebSocket v2 API: channel data, or the reply to a request.
+#[derive(Deserialize, Debug)]
+struct KrakenMessage {
+    channel: Option<String>,
+    #[serde(default)]
+    data: Vec<KrakenCandle>,
+    method: Option<String>,
+    success: Option<bool>,
+    error: Option<String>,
+}
+
+/// A WebSocket client streaming the klines of a Kraken pair from the `ohlc`
+/// channel of the WebSocket v2 API.
+pub struct KrakenKlineStreaming {
+    url: String,
+    symbol: String,
+    stream_symbol: String,
+    interval: String,
+    minutes: u32,
+    socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    candles: CandleSequence,
+    pending: VecDeque<SerdableKlineData>,
+    callbacks: Vec<Box<dyn SharedMessageHandler<SerdableKlineData> + Send>>,
+    stats: Option<Arc<StreamStats>>,
+    generation: u64,
+    context: Option<IngestContext>,
+}
+
+impl KrakenKlineStreaming {
+    /// Connects to the WebSocket v2 API for the klines of a pair.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbol` - The normalized symbol the klines are labeled with (e.g., "BTCUSD").
+    /// * `stream_symbol` - The pair in the WebSocket API (e.g., "BTC/USD").
+    /// * `interval` - The kline interval (e.g., "1m").
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if Kraken has no candles of the interval or the WebSocket
+    /// connection cannot be established.
+    pub async fn new(symbol: &str, stream_symbol: &str, interval: &str) -> Result<Self> {
+        let minutes = ohlc_interval(interval)
+            .with_context(|| format!("Unsupported interval for Kraken: {}", interval))?;
+        let (socket, _) = tokio_tungstenite::connect_async(KRAKEN_STREAM_URL)
+            .await
+            .with_context(|| format!("Failed to connect to {}", KRAKEN_STREAM_URL))?;
+        Ok(Self {
+            url: KRAKEN_STREAM_URL.to_string(),
+            symbol: symbol.to_string(),
+            stream_symbol: stream_symbol.to_string(),
+            interval: interval.to_string(),
+            minutes,
+            socket,
+            candles: CandleSequence::default(),
+            pending: VecDeque::new(),
+            callbacks: Vec::new(),
+            stats: None,
+            generation: 0,
+            context: None,
+        })
+    }
+
+    /// Returns the stream name (e.g., "BTC/USD@ohlc_1m").
+    pub fn stream(&self) -> String {
+        format!("{}@ohlc_{}", self.stream_symbol, self.interval)
+    }
+
+    /// Attaches shared [`StreamStats`] that record parse errors, handler errors and
+    /// the latency of the handler chain while [`listen`](StreamingClient::listen) is
+    /// running.
+    pub fn attach_stats(&mut self, stats: Arc<StreamStats>) {
+        self.stats = Some(stats);
+    }
+
+    /// Queues the candles of a message.
+    fn queue_candles(&mut self, candles: &[KrakenCandle]) -> Result<()> {
+        let klines = candles
+            .iter()
+            .filter(|candle| candle.symbol == self.stream_symbol)
+            .map(|candle| candle.to_serdable_kline(&self.symbol, &self.interval))
+            .collect::<Result<Vec<_>>>()?;
+        self.pending.extend(self.candles.push(klines));
+        Ok(())
+    }
+}
+
+/// The latest update of the open candle of a stream, which is emitted as final
+/// once a later candle starts.
+#[derive(Debug, Default)]
+struct CandleSequence {
+    latest: Option<SerdableKlineData>,
+}
+
+impl CandleSequence {
+    /// Tracks the updates of a message, oldest first, and returns the updates to
+    /// emit: the candles they closed as final, then the latest update as open.
+    /// Only the latest update of a message is emitted as open, as snapshots carry
+    /// several past candles. Late updates of an earlier candle are ignored.
+    fn push(&mut self, klines: Vec<SerdableKlineData>) -> Vec<SerdableKlineData> {
+        let mut emitted = Vec::new();
+        let mut open = None;
+        for kline in klines {
+            match &self.latest {
+                Some(latest) if latest.start_time > kline.start_time => continue,
+                Some(latest) if latest.start_time < kline.start_time => {
+                    emitted.push(SerdableKlineData {
+                        is_final: true,
+                        ..latest.clone()
+                    });
+                }
+                _ => {}
+            }
+            self.latest = Some(kline.clone());
+            open = Some(kline);
+        }
+        emitted.extend(open);
+        emitted
+    }
+}
+
+#[async_trait]
+impl StreamingClient<SerdableKlineData> for KrakenKlineStreaming {
+    async fn connect(&mut self) -> Result<()> {
+        let (socket, _) = tokio_tungstenite::connect_async(self.url.as_str())
+            .await
+            .with_context(|| format!("Failed to connect to {}", self.url))?;
+        self.socket = socket;
+        self.pending.clear();
+        self.generation += 1;
+        Ok(())
+    }
+
+    async fn subscribe(&mut self) -> Result<()> {
+        let request = serde_json::json!({
+            "method": "subscribe",
+            "params": {
+                "channel": "ohlc",
+                "symbol": [self.stream_symbol],
+                "interval": self.minutes,
+            },
+        });
+        self.socket
+            .send(Message::Text(request.to_string()))
+            .await
+            .with_context(|| format!("Failed to subscribe to {}", self.stream()))?;
+        Ok(())
+    }
+
+    async fn next(&mut self) -> Result<Option<Result<SerdableKlineData>>> {
+        loop {
+            if let Some(kline) = self.pending.pop_front() {
+                return Ok(Some(Ok(kline)));
+            }
+            let text = match self.socket.next().await {
+                Some(Ok(Message::Text(text))) => text,
+                Some(Ok(Message::Close(_))) | None => return Ok(None),
+                // Pings are answered by the socket itself.
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => return Ok(Some(Err(anyhow::Error::msg(e.to_string())))),
+            };
+            let message = match serde_json::from_str::<KrakenMessage>(&text) {
+                Ok(message) => message,
+                Err(e) => {
+                    return Ok(Some(Err(anyhow::anyhow!(
+                        "Failed to parse Kraken message: {}: {}",
+                        e,
+                        text
+                    ))));
+                }
+            };
+            if message.method.as_deref() == Some("subscribe") && message.success == Some(false) {
+                bail!(
+                    "Kraken rejected {}: {}",
+                    self.stream(),
+                    message.error.unwrap_or_default()
+                );
+            }
+            // Heartbeats and status messages carry no candles.
+            if message.channel.as_deref() != Some("ohlc") {
+                continue;
+            }
+            self.context = Some(
+                IngestContext::new(KRAKEN_EXCHANGE, &self.stream())
+                    .with_generation(self.generation),
+            );
+            if let Err(e) = self.queue_candles(&message.data) {
+                return Ok(Some(Err(e)));
+            }
+        }
+    }
+
+    fn context(&self) -> Option<IngestContext> {
+        self.context.clone()
+    }
+
+    fn add_callback(&mut self, handler: Box<dyn MessageHandler<SerdableKlineData> + Send>) {
+        self.callbacks.push(Box::new(Borrowed(handler)));
+    }
+
+    fn add_shared_callback(
+        &mut self,
+        handler: Box<dyn SharedMessageHandler<SerdableKlineData> + Send>,
+    ) {
+        self.callbacks.push(handler);
+    }
+
+    async fn listen(&mut self) -> Result<()> {
+        while let Some(result) = StreamingClient::next(self).await? {
+            match result {
+                Ok(kline) => {
+                    let kline = Arc::new(kline);
+                    let context = self
+                        .context
+                        .clone()
+                        .unwrap_or_else(|| IngestContext::new(KRAKEN_EXCHANGE, &self.stream()));
+                    let started_at = Instant::now();
+                    for callback in &mut self.callbacks {
+                        if let Err(e) = callback.handle_shared_with_context(&kline, &context).await
+                        {
+                            if let Some(stats) = &self.stats {
+                                stats.record_error();
+                            }
+                            return Err(e);
+                        }
+                    }
+                    if let Some(stats) = &self.stats {
+                        stats.record_latency(started_at.elapsed());
+                    }
+                }
+                Err(e) => {
+                    if let Some(stats) = &self.stats {
+                        stats.record_error();
+                    }
+                    log::warn!("Error processing Kraken kline: {}", e);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_symbol_mapping() {
+        let source = Kraken::new();
+        for symbol in ["XBT/USD", "XXBTZUSD", "xbtusd", "BTC-USD", "BTCUSD"] {
+            assert_eq!(source.normalize_symbol(symbol), "BTCUSD", "{}", symbol);
+        }
+        assert_eq!(source.normalize_symbol("XETHXXBT"), "ETHBTC");
+        assert_eq!(source.normalize_symbol("XDGUSDT"), "DOGEUSDT");
+        assert_eq!(source.rest_pair("BTCUSD").unwrap(), "XBTUSD");
+        assert_eq!(source.rest_pair("DOGEUSDT").unwrap(), "XDGUSDT");
+        assert_eq!(source.stream_symbol("BTCUSD").unwrap(), "BTC/USD");
+        assert!(source.rest_pair("BTC").is_err());
+        assert_eq!(ohlc_interval("4h"), Some(240));
+        assert_eq!(ohlc_interval("2h"), None);
+    }
+
+    #[test]
+    fn test_parse_rest_ohlc() {
+        let json = r#"{"error":[],"result":{"XXBTZUSD":[
+            [1700000000,"37000.0","37100.0","36900.0","37050.0","37010.5","2.00000000",42]
+        ],"last":1700000000}}"#;
+        let response: KrakenResponse<HashMap<String, serde_json::Value>> =
+            serde_json::from_str(json).unwrap();
+        let mut result = response.into_result().unwrap();
+        let rows: Vec<KrakenOhlc> =
+            serde_json::from_value(result.remove("XXBTZUSD").unwrap()).unwrap();
+        let klines = klines_from_ohlc(&rows, "BTCUSD", "1h", chrono::Duration::hours(1)).unwrap();
+        assert_eq!(klines[0].start_time.timestamp(), 1_700_000_000);
+        assert_eq!(klines[0].trade_count, Some(42));
+        assert_eq!(klines[0].quote_volume, Some("74021".parse().unwrap()));
+        assert!(klines[0].validate().is_ok());
+
+        let json = r#"{"error":["EQuery:Unknown asset pair"]}"#;
+        let response: KrakenResponse<HashMap<String, serde_json::Value>> =
+            serde_json::from_str(json).unwrap();
+        assert!(response.into_result().is_err());
+    }
+
+    #[test]
+    fn test_stream_emits_previous_candle_as_final() {
+        let json = r#"{"channel":"ohlc","type":"snapshot","timestamp":"2024-01-01T00:01:30Z","data":[
+            {"symbol":"BTC/USD","open":100.0,"high":110.5,"low":95.0,"close":101.0,"trades":3,"volume":2.0,"vwap":101.25,"interval_begin":"2024-01-01T00:00:00.000000000Z","interval":1,"timestamp":"2024-01-01T00:01:00.000000Z"},
+            {"symbol":"BTC/USD","open":101.0,"high":102.0,"low":100.0,"close":102.0,"trades":1,"volume":0.5,"vwap":102.0,"interval_begin":"2024-01-01T00:01:00.000000000Z","interval":1,"timestamp":"2024-01-01T00:01:30.000000Z"}
+        ]}"#;
+        let message: KrakenMessage = serde_json::from_str(json).unwrap();
+        assert_eq!(message.channel.as_deref(), Some("ohlc"));
+        let klines: Vec<_> = message
+            .data
+            .iter()
+            .map(|candle| candle.to_serdable_kline("BTCUSD", "1m").unwrap())
+            .collect();
+        assert_eq!(klines[0].end_time - klines[0].start_time, 59_999);
+        assert_eq!(klines[0].close, "101.0");
+        let quote_volume: Decimal = klines[0].quote_volume.parse().unwrap();
+        assert_eq!(quote_volume, "202.5".parse::<Decimal>().unwrap());
+
+        let mut candles = CandleSequence::default();
+        let emitted = candles.push(klines.clone());
+        assert_eq!(emitted.len(), 2);
+        assert!(emitted[0].is_final);
+        assert_eq!(emitted[0].start_time, 1_704_067_200_000);
+        assert!(!emitted[1].is_final);
+
+        // Late updates are ignored, and a later candle closes the open one.
+        assert!(candles.push(vec![klines[0].clone()]).is_empty());
+        let next = SerdableKlineData {
+            start_time: klines[1].start_time + 60_000,
+            end_time: klines[1].end_time + 60_000,
+            ..klines[1].clone()
+        };
+        let emitted = candles.push(vec![next]);
+        assert!(emitted[0].is_final);
+        assert_eq!(emitted[0].start_time, klines[1].start_time);
+    }
+}
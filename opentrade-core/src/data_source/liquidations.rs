@@ -0,0 +1,282 @@
+//! # Liquidation Streams
+//!
+//! When the margin of a futures position runs out, the exchange closes it with a
+//! liquidation order. Liquidations push the price further against the remaining
+//! positions, which may be liquidated in turn; tracking them shows such cascades
+//! as they unfold. The Binance USDⓈ-M futures WebSocket API streams them as
+//! `forceOrder` events:
+//!
+//! - `<symbol>@forceOrder` streams the liquidations of one contract
+//! - `!forceOrder@arr` streams the liquidations of all contracts
+//!
+//! Both streams push at most the latest liquidation of a contract per second, so
+//! they undercount bursts within a second.
+//!
+//! [`LiquidationStreaming`] implements [`StreamingClient<SerdableLiquidationEvent>`],
+//! so it is managed like the kline streams, and [`InsertLiquidationSink`] stores
+//! the events in the `liquidations` table.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use opentrade_core::data_source::liquidations::{InsertLiquidationSink, LiquidationStreaming};
+//! use opentrade_core::data_source::websocket::StreamingClient;
+//! # use anyhow::Result;
+//!
+//! # async fn example(pool: sqlx::PgPool) -> Result<()> {
+//! let mut stream = LiquidationStreaming::all_market().await?;
+//! stream.add_callback(Box::new(InsertLiquidationSink::new(pool)));
+//! stream.subscribe().await?;
+//! stream.listen().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+use crate::data_source::futures::USDM_FUTURES_STREAM_URL;
+use crate::data_source::websocket::{
+    BINANCE_EXCHANGE, Borrowed, IngestContext, MessageHandler, SharedMessageHandler,
+    StreamingClient,
+};
+use crate::ingest::stats::StreamStats;
+use crate::models::DEFAULT_DATASET;
+use crate::models::liquidation::SerdableLiquidationEvent;
+
+/// The stream of the liquidations of all USDⓈ-M contracts.
+pub const ALL_MARKET_LIQUIDATIONS_STREAM: &str = "!forceOrder@arr";
+
+/// A message of a combined stream, wrapping the event with its stream name.
+#[derive(Deserialize, Debug)]
+struct LiquidationPayload {
+    stream: String,
+    data: SerdableLiquidationEvent,
+}
+
+/// A WebSocket client streaming the liquidations of one or all futures contracts.
+pub struct LiquidationStreaming {
+    url: String,
+    stream: String,
+    socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    callbacks: Vec<Box<dyn SharedMessageHandler<SerdableLiquidationEvent> + Send>>,
+    stats: Option<Arc<StreamStats>>,
+    generation: u64,
+    context: Option<IngestContext>,
+}
+
+impl LiquidationStreaming {
+    /// Connects to the liquidation stream of a USDⓈ-M contract.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbol` - The contract symbol (e.g., "BTCUSDT").
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the WebSocket connection cannot be established.
+    pub async fn new(symbol: &str) -> Result<Self> {
+        let stream = format!("{}@forceOrder", symbol.to_lowercase());
+        Self::connect_to(&stream).await
+    }
+
+    /// Connects to the liquidation stream of all USDⓈ-M contracts.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the WebSocket connection cannot be established.
+    pub async fn all_market() -> Result<Self> {
+        Self::connect_to(ALL_MARKET_LIQUIDATIONS_STREAM).await
+    }
+
+    async fn connect_to(stream: &str) -> Result<Self> {
+        let (socket, _) = tokio_tungstenite::connect_async(USDM_FUTURES_STREAM_URL)
+            .await
+            .with_context(|| format!("Failed to connect to {}", USDM_FUTURES_STREAM_URL))?;
+        Ok(Self {
+            url: USDM_FUTURES_STREAM_URL.to_string(),
+            stream: stream.to_string(),
+            socket,
+            callbacks: Vec::new(),
+            stats: None,
+            generation: 0,
+            context: None,
+        })
+    }
+
+    /// Returns the stream name.
+    pub fn stream(&self) -> &str {
+        &self.stream
+    }
+
+    /// Attaches shared [`StreamStats`] that record parse errors, handler errors and
+    /// the latency of the handler chain while [`listen`](StreamingClient::listen) is
+    /// running.
+    pub fn attach_stats(&mut self, stats: Arc<StreamStats>) {
+        self.stats = Some(stats);
+    }
+}
+
+#[async_trait]
+impl StreamingClient<SerdableLiquidationEvent> for LiquidationStreaming {
+    async fn connect(&mut self) -> Result<()> {
+        let (socket, _) = tokio_tungstenite::connect_async(self.url.as_str())
+            .await
+            .with_context(|| format!("Failed to connect to {}", self.url))?;
+        self.socket = socket;
+        self.generation += 1;
+        Ok(())
+    }
+
+    async fn subscribe(&mut self) -> Result<()> {
+        let request = serde_json::json!({
+            "method": "SUBSCRIBE",
+            "params": [self.stream],
+            "id": 1,
+        });
+        self.socket
+            .send(Message::Text(request.to_string()))
+            .await
+            .with_context(|| format!("Failed to subscribe to {}", self.stream))?;
+        Ok(())
+    }
+
+    async fn next(&mut self) -> Result<Option<Result<SerdableLiquidationEvent>>> {
+        while let Some(message) = self.socket.next().await {
+            let text = match message {
+                Ok(Message::Text(text)) => text,
+                Ok(Message::Close(_)) => return Ok(None),
+                // Pings are answered by the socket itself.
+                Ok(_) => continue,
+                Err(e) => return Ok(Some(Err(anyhow::Error::msg(e.to_string())))),
+            };
+            match serde_json::from_str::<LiquidationPayload>(&text) {
+                Ok(payload) => {
+                    self.context = Some(
+                        IngestContext::new(BINANCE_EXCHANGE, &payload.stream)
+                            .with_generation(self.generation),
+                    );
+                    return Ok(Some(Ok(payload.data)));
+                }
+                // Replies to requests, e.g. `{"result":null,"id":1}`, are not events.
+                Err(_) if text.contains("\"id\"") && !text.contains("\"stream\"") => continue,
+                Err(e) => {
+                    return Ok(Some(Err(anyhow::anyhow!(
+                        "Failed to parse liquidation: {}: {}",
+                        e,
+                        text
+                    ))));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn context(&self) -> Option<IngestContext> {
+        self.context.clone()
+    }
+
+    fn add_callback(&mut self, handler: Box<dyn MessageHandler<SerdableLiquidationEvent> + Send>) {
+        self.callbacks.push(Box::new(Borrowed(handler)));
+    }
+
+    fn add_shared_callback(
+        &mut self,
+        handler: Box<dyn SharedMessageHandler<SerdableLiquidationEvent> + Send>,
+    ) {
+        self.callbacks.push(handler);
+    }
+
+    async fn listen(&mut self) -> Result<()> {
+        while let Some(result) = StreamingClient::next(self).await? {
+            match result {
+                Ok(event) => {
+                    let event = Arc::new(event);
+                    let context = self
+                        .context
+                        .clone()
+                        .unwrap_or_else(|| IngestContext::new(BINANCE_EXCHANGE, &self.stream));
+                    let started_at = Instant::now();
+                    for callback in &mut self.callbacks {
+                        if let Err(e) = callback.handle_shared_with_context(&event, &context).await
+                        {
+                            if let Some(stats) = &self.stats {
+                                stats.record_error();
+                            }
+                            return Err(e);
+                        }
+                    }
+                    if let Some(stats) = &self.stats {
+                        stats.record_latency(started_at.elapsed());
+                    }
+                }
+                Err(e) => {
+                    if let Some(stats) = &self.stats {
+                        stats.record_error();
+                    }
+                    log::warn!("Error processing liquidation: {}", e);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A [`MessageHandler`] that stores liquidations in the `liquidations` table.
+pub struct InsertLiquidationSink {
+    pool: sqlx::PgPool,
+    dataset: String,
+}
+
+impl InsertLiquidationSink {
+    /// Creates a sink storing liquidations under the [`DEFAULT_DATASET`].
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    pub fn new(pool: sqlx::PgPool) -> Self {
+        Self {
+            pool,
+            dataset: DEFAULT_DATASET.to_string(),
+        }
+    }
+
+    /// Sets the dataset the liquidations are stored under.
+    pub fn with_dataset(mut self, dataset: &str) -> Self {
+        self.dataset = dataset.to_string();
+        self
+    }
+}
+
+#[async_trait]
+impl MessageHandler<SerdableLiquidationEvent> for InsertLiquidationSink {
+    async fn handle_message(&mut self, message: &SerdableLiquidationEvent) -> Result<()> {
+        let liquidation = message.to_liquidation_event()?.with_dataset(&self.dataset);
+        liquidation
+            .insert(&self.pool)
+            .await
+            .with_context(|| format!("Failed to store a liquidation of {}", liquidation.symbol))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_all_market_payload() {
+        let json = r#"{"stream":"!forceOrder@arr","data":{"e":"forceOrder","E":1568014460893,"o":{"s":"ETHUSDT","S":"BUY","o":"LIMIT","f":"IOC","q":"1.5","p":"180.2","ap":"180.1","X":"FILLED","l":"1.5","z":"1.5","T":1568014460890}}}"#;
+        let payload: LiquidationPayload = serde_json::from_str(json).unwrap();
+        assert_eq!(payload.stream, ALL_MARKET_LIQUIDATIONS_STREAM);
+        assert_eq!(payload.data.order.symbol, "ETHUSDT");
+        assert_eq!(payload.data.order.side, "BUY");
+    }
+}
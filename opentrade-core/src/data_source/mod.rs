@@ -6,7 +6,23 @@
 //!
 //! ## Submodules
 //!
+//! - [`api_keys`] - Rotation of REST requests over several API keys, with per-key health
+//! - [`book_ticker`] - Best bid and ask quote streams for spread analytics
+//! - [`bybit`] - Klines and kline streams of the spot and linear markets of Bybit, implementing [`exchange::MarketDataSource`]
+//! - [`clock`] - Exchange server time, synchronized periodically, for candle boundaries
+//! - [`coinbase`] - Candles and trade streams of Coinbase Advanced Trade, implementing [`exchange::MarketDataSource`]
+//! - [`depth`] - Local order books maintained from depth diff streams and REST snapshots
+//! - [`exchange`] - The [`exchange::MarketDataSource`] trait abstracting exchanges, and its Binance spot and futures implementations
+//! - [`futures`] - Mark and index price streams of futures contracts
+//! - [`kraken`] - OHLC candles and kline streams of Kraken, with its asset codes mapped to common ones
+//! - [`liquidations`] - Liquidation order streams of futures contracts
+//! - [`okx`] - Candles and candle streams of the spot and perpetual swap markets of OKX, parsed from its string-array payloads
+//! - [`options`] - Mark prices, implied volatilities and greeks of options contracts
+//! - [`quota`] - Daily request and row quotas per data source and API key
+//! - [`rate_limit`] - Request weight limiting driven by the weight headers of Binance
 //! - [`rest`] - RESTful HTTP API client implementations for fetching historical data
+//! - [`sharded`] - Kline streams of many symbols sharded over connections within the per-connection stream limit
+//! - [`uniswap`] - Candles of Uniswap v3 pools built from swaps read from a subgraph
 //! - [`websocket`] - Real-time WebSocket streaming implementations for live market data
 //!
 //! ## Usage Patterns
@@ -29,7 +45,27 @@
 //!
 //! The data source module follows a modular design where each connection type
 //! (REST/WebSocket) is implemented in its own submodule with standardized
-//! interfaces for data retrieval and processing.
+//! interfaces for data retrieval and processing. All WebSocket stream clients
+//! implement the [`websocket::StreamingClient`] trait so they can be managed
+//! uniformly by orchestration code. The ingestion layer reaches an exchange through
+//! the [`exchange::MarketDataSource`] trait rather than a specific client, so other
+//! exchanges can be added by implementing it.
 
+pub mod api_keys;
+pub mod book_ticker;
+pub mod bybit;
+pub mod clock;
+pub mod coinbase;
+pub mod depth;
+pub mod exchange;
+pub mod futures;
+pub mod kraken;
+pub mod liquidations;
+pub mod okx;
+pub mod options;
+pub mod quota;
+pub mod rate_limit;
 pub mod rest;
+pub mod sharded;
+pub mod uniswap;
 pub mod websocket;
\ No newline at end of file
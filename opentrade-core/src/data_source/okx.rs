@@ -0,0 +1,809 @@
+//! # OKX
+//!
+//! This module implements [`MarketDataSource`] for the spot and perpetual swap
+//! markets of OKX, so its instruments go through the same backfills and pipelines
+//! as Binance symbols:
+//!
+//! - Historical klines are fetched from the `history-candles` endpoint of the v5
+//!   REST API.
+//! - Live klines are streamed from the `candle{bar}` channels (e.g., `candle1m`)
+//!   of the v5 business WebSocket API by [`OkxKlineStreaming`].
+//!
+//! Both APIs send candles as arrays of strings rather than objects, parsed by
+//! [`OkxCandle::from_fields`].
+//!
+//! ## Instruments
+//!
+//! OKX names instruments by their assets (e.g., "BTC-USDT", and "BTC-USDT-SWAP"
+//! for the perpetual swap). Candles are stored under the symbol without dashes
+//! (e.g., "BTCUSDT"); the instrument of a symbol is derived from its quote asset
+//! (see [`Okx::inst_id`]). An [`Okx`] source serves one [`OkxInstType`], and the
+//! types report themselves as different exchanges, [`OKX_EXCHANGE`] and
+//! [`OKX_SWAP_EXCHANGE`]; store their candles under their own dataset, as the same
+//! symbols exist on Binance.
+//!
+//! ## Mapping
+//!
+//! - Candles have no trade ids or trade counts, so their trade ids are 0 and their
+//!   trade counts are empty (0 for streamed candles).
+//! - Swap volumes are counted in contracts; the volume in the base asset is stored
+//!   instead. The quote volume is the volume in the quote asset.
+//! - Candles of 6 hours and longer are requested aligned to UTC, as OKX aligns them
+//!   to Hong Kong time by default.
+//! - The REST API returns candles newest first; they are reordered oldest first.
+//! - Streamed candles are final once OKX confirms them.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use opentrade_core::data_source::exchange::MarketDataSource;
+//! use opentrade_core::data_source::okx::Okx;
+//! # use anyhow::Result;
+//!
+//! # async fn example() -> Result<()> {
+//! let source = Okx::spot();
+//! let symbol = source.normalize_symbol("BTC-USDT");
+//! assert_eq!(source.inst_id(&symbol)?, "BTC-USDT");
+//! let klines = source.fetch_klines(&symbol, "1h", 1_700_000_000_000, None, Some(24)).await?;
+//! println!("Fetched {} klines from {}", klines.len(), source.name());
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, bail};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use sqlx::types::BigDecimal as Decimal;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+use crate::data_source::exchange::MarketDataSource;
+use crate::data_source::websocket::{
+    Borrowed, IngestContext, MessageHandler, SharedMessageHandler, StreamingClient,
+};
+use crate::ingest::audit::interval_duration;
+use crate::ingest::stats::StreamStats;
+use crate::models::{KlineData, SerdableKlineData};
+use crate::time::from_millis;
+
+/// The exchange name of OKX spot sources.
+pub const OKX_EXCHANGE: &str = "okx";
+
+/// The exchange name of OKX perpetual swap sources.
+pub const OKX_SWAP_EXCHANGE: &str = "okx-swap";
+
+/// The base URL of the v5 REST API.
+pub const OKX_REST_URL: &str = "https://www.okx.com/api/v5";
+
+/// The URL of the v5 business WebSocket API, which serves the candle channels.
+pub const OKX_STREAM_URL: &str = "wss://ws.okx.com:8443/ws/v5/business";
+
+/// The maximum number of candles returned by one `history-candles` request.
+pub const MAX_CANDLE_LIMIT: u32 = 100;
+
+/// How often streams send a ping; OKX closes connections silent for 30 seconds.
+pub const PING_INTERVAL: Duration = Duration::from_secs(20);
+
+/// Quote assets, tried in order to split a symbol into its assets.
+const QUOTE_ASSETS: [&str; 8] = ["USDT", "USDC", "USD", "EUR", "BTC", "ETH", "OKB", "DAI"];
+
+/// The type of instruments an [`Okx`] source serves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OkxInstType {
+    /// Spot pairs (e.g., "BTC-USDT").
+    Spot,
+    /// Perpetual swaps (e.g., "BTC-USDT-SWAP").
+    Swap,
+}
+
+impl OkxInstType {
+    /// Returns the exchange name the type reports itself as.
+    pub fn exchange(&self) -> &'static str {
+        match self {
+            OkxInstType::Spot => OKX_EXCHANGE,
+            OkxInstType::Swap => OKX_SWAP_EXCHANGE,
+        }
+    }
+}
+
+/// Returns the bar of an interval as the OKX APIs expect it (e.g., "1H" for "1h"),
+/// or `None` if OKX has no candles of that length.
+pub fn candle_bar(interval: &str) -> Option<&'static str> {
+    let bar = match interval {
+        "1m" => "1m",
+        "3m" => "3m",
+        "5m" => "5m",
+        "15m" => "15m",
+        "30m" => "30m",
+        "1h" => "1H",
+        "2h" => "2H",
+        "4h" => "4H",
+        "6h" => "6Hutc",
+        "12h" => "12Hutc",
+        "1d" => "1Dutc",
+        _ => return None,
+    };
+    Some(bar)
+}
+
+/// A candle of the REST or WebSocket API.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OkxCandle {
+    /// The start of the candle in milliseconds since the epoch.
+    pub start_time: u64,
+    /// The opening price.
+    pub open: String,
+    /// The highest price.
+    pub high: String,
+    /// The lowest price.
+    pub low: String,
+    /// The closing price.
+    pub close: String,
+    /// The volume, in contracts for swaps and in the base asset otherwise.
+    pub volume: String,
+    /// The volume in the base asset for swaps, and in the quote asset otherwise.
+    pub volume_currency: String,
+    /// The volume in the quote asset.
+    pub volume_quote: String,
+    /// Whether the candle is closed.
+    pub confirmed: bool,
+}
+
+impl OkxCandle {
+    /// Parses a candle from its fields: start time, open, high, low, close, volume,
+    /// currency volume, quote volume and confirmation ("0" or "1").
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if fields are missing or the start time is not a number.
+    pub fn from_fields(fields: &[String]) -> Result<Self> {
+        let [
+            start_time,
+            open,
+            high,
+            low,
+            close,
+            volume,
+            volume_currency,
+            volume_quote,
+            confirm,
+        ] = fields
+        else {
+            bail!(
+                "Expected 9 candle fields, got {}: {:?}",
+                fields.len(),
+                fields
+            );
+        };
+        Ok(Self {
+            start_time: start_time
+                .parse()
+                .with_context(|| format!("Invalid candle start time: {}", start_time))?,
+            open: open.clone(),
+            high: high.clone(),
+            low: low.clone(),
+            close: close.clone(),
+            volume: volume.clone(),
+            volume_currency: volume_currency.clone(),
+            volume_quote: volume_quote.clone(),
+            confirmed: confirm == "1",
+        })
+    }
+
+    /// Returns the volume in the base asset.
+    fn base_volume(&self, inst_type: OkxInstType) -> &str {
+        match inst_type {
+            OkxInstType::Spot => &self.volume,
+            OkxInstType::Swap => &self.volume_currency,
+        }
+    }
+
+    /// Converts the candle into a kline.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbol` - The symbol the kline is labeled with.
+    /// * `interval` - The interval the kline is labeled with.
+    /// * `step` - The length of `interval`.
+    /// * `inst_type` - The type of the instrument, which determines the volume unit
+    ///   and the exchange.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a price or volume cannot be parsed.
+    pub fn to_kline_data(
+        &self,
+        symbol: &str,
+        interval: &str,
+        step: chrono::Duration,
+        inst_type: OkxInstType,
+    ) -> Result<KlineData> {
+        fn decimal(value: &str) -> Result<Decimal> {
+            value
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid candle value: {}", value))
+        }
+
+        Ok(KlineData::new(
+            &self.start_time,
+            &(self.start_time + step.num_milliseconds() as u64 - 1),
+            symbol,
+            interval,
+            0,
+            0,
+            decimal(&self.open)?,
+            decimal(&self.high)?,
+            decimal(&self.low)?,
+            decimal(&self.close)?,
+            decimal(self.base_volume(inst_type))?,
+            None,
+            Some(decimal(&self.volume_quote)?),
+        )
+        .with_exchange(inst_type.exchange()))
+    }
+
+    /// Converts the candle into a kline update.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbol` - The symbol the kline is labeled with.
+    /// * `interval` - The interval the kline is labeled with.
+    /// * `step` - The length of `interval`.
+    /// * `inst_type` - The type of the instrument, which determines the volume unit
+    ///   and the exchange.
+    pub fn to_serdable_kline(
+        &self,
+        symbol: &str,
+        interval: &str,
+        step: chrono::Duration,
+        inst_type: OkxInstType,
+    ) -> SerdableKlineData {
+        SerdableKlineData {
+            start_time: self.start_time,
+            end_time: self.start_time + step.num_milliseconds() as u64 - 1,
+            symbol: symbol.to_string(),
+            interval: interval.to_string(),
+            first_trade_id: 0,
+            last_trade_id: 0,
+            open: self.open.clone(),
+            close: self.close.clone(),
+            high: self.high.clone(),
+            low: self.low.clone(),
+            volume: self.base_volume(inst_type).to_string(),
+            trade_count: 0,
+            quote_volume: self.volume_quote.clone(),
+            event_time: None,
+            is_final: self.confirmed,
+            exchange: Some(inst_type.exchange().to_string()),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct OkxResponse<T> {
+    code: String,
+    msg: String,
+    data: Vec<T>,
+}
+
+impl<T> OkxResponse<T> {
+    fn into_data(self) -> Result<Vec<T>> {
+        if self.code != "0" {
+            bail!("OKX returned error {}: {}", self.code, self.msg);
+        }
+        Ok(self.data)
+    }
+}
+
+#[derive(Deserialize)]
+struct TimeData {
+    ts: String,
+}
+
+/// The OKX exchange, for the instruments of one type.
+#[derive(Debug, Clone)]
+pub struct Okx {
+    inst_type: OkxInstType,
+    client: reqwest::Client,
+}
+
+impl Okx {
+    /// Creates a source for the instruments of a type.
+    pub fn new(inst_type: OkxInstType) -> Self {
+        Self {
+            inst_type,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Creates a source for spot pairs.
+    pub fn spot() -> Self {
+        Self::new(OkxInstType::Spot)
+    }
+
+    /// Creates a source for perpetual swaps.
+    pub fn swap() -> Self {
+        Self::new(OkxInstType::Swap)
+    }
+
+    /// Returns the type of the instruments of the source.
+    pub fn inst_type(&self) -> OkxInstType {
+        self.inst_type
+    }
+
+    /// Returns the instrument id of a symbol (e.g., "BTC-USDT", or
+    /// "BTC-USDT-SWAP" for swaps).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the symbol has no known quote asset.
+    pub fn inst_id(&self, symbol: &str) -> Result<String> {
+        let symbol = self.normalize_symbol(symbol);
+        let (base, quote) = QUOTE_ASSETS
+            .iter()
+            .find_map(|quote| {
+                symbol
+                    .strip_suffix(quote)
+                    .filter(|base| !base.is_empty())
+                    .map(|base| (base, quote))
+            })
+            .with_context(|| format!("No OKX instrument known for {}", symbol))?;
+        Ok(match self.inst_type {
+            OkxInstType::Spot => format!("{}-{}", base, quote),
+            OkxInstType::Swap => format!("{}-{}-SWAP", base, quote),
+        })
+    }
+
+    async fn get<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> Result<Vec<T>> {
+        let response: OkxResponse<T> = self
+            .client
+            .get(format!("{}/{}", OKX_REST_URL, path))
+            .query(query)
+            .send()
+            .await
+            .with_context(|| format!("Failed to request OKX {}", path))?
+            .error_for_status()
+            .with_context(|| format!("OKX rejected the {} request", path))?
+            .json()
+            .await
+            .with_context(|| format!("Failed to parse the OKX {} response", path))?;
+        response.into_data()
+    }
+}
+
+#[async_trait]
+impl MarketDataSource for Okx {
+    fn name(&self) -> &str {
+        self.inst_type.exchange()
+    }
+
+    fn normalize_symbol(&self, symbol: &str) -> String {
+        let symbol = symbol.to_uppercase();
+        symbol
+            .strip_suffix("-SWAP")
+            .unwrap_or(&symbol)
+            .chars()
+            .filter(char::is_ascii_alphanumeric)
+            .collect()
+    }
+
+    fn max_kline_limit(&self) -> u32 {
+        MAX_CANDLE_LIMIT
+    }
+
+    fn default_kline_limit(&self) -> u32 {
+        MAX_CANDLE_LIMIT
+    }
+
+    async fn fetch_klines(
+        &self,
+        symbol: &str,
+        interval: &str,
+        start_time: u64,
+        end_time: Option<u64>,
+        limit: Option<u32>,
+    ) -> Result<Vec<KlineData>> {
+        let bar = candle_bar(interval)
+            .with_context(|| format!("Unsupported interval for OKX: {}", interval))?;
+        let step = interval_duration(interval)
+            .with_context(|| format!("Unsupported interval for OKX: {}", interval))?;
+        let inst_id = self.inst_id(symbol)?;
+        let step_ms = step.num_milliseconds() as u64;
+        let limit = limit
+            .unwrap_or(self.default_kline_limit())
+            .min(self.max_kline_limit());
+
+        // Candles starting in [start_time, window_end), like the Binance API.
+        let first_start = start_time.div_ceil(step_ms) * step_ms;
+        let mut window_end = first_start + u64::from(limit) * step_ms;
+        if let Some(end_time) = end_time {
+            window_end = window_end.min(end_time + 1);
+        }
+        if first_start >= window_end {
+            return Ok(Vec::new());
+        }
+
+        // `after` and `before` are exclusive bounds on the start time.
+        let rows: Vec<Vec<String>> = self
+            .get(
+                "market/history-candles",
+                &[
+                    ("instId", inst_id),
+                    ("bar", bar.to_string()),
+                    ("after", window_end.to_string()),
+                    ("before", (first_start.saturating_sub(1)).to_string()),
+                    ("limit", limit.to_string()),
+                ],
+            )
+            .await?;
+        let mut klines = rows
+            .iter()
+            .map(|fields| {
+                OkxCandle::from_fields(fields)?.to_kline_data(
+                    symbol,
+                    interval,
+                    step,
+                    self.inst_type,
+                )
+            })
+            .collect::<Result<Vec<_>>>()?;
+        klines.sort_by_key(|kline| kline.start_time);
+        klines.retain(|kline| {
+            let start = kline.start_time.timestamp_millis() as u64;
+            start >= first_start && start < window_end
+        });
+        Ok(klines)
+    }
+
+    async fn stream_klines(
+        &self,
+        symbol: &str,
+        interval: &str,
+    ) -> Result<Box<dyn StreamingClient<SerdableKlineData>>> {
+        let inst_id = self.inst_id(symbol)?;
+        Ok(Box::new(
+            OkxKlineStreaming::new(self.inst_type, symbol, &inst_id, interval).await?,
+        ))
+    }
+
+    async fn server_time(&self) -> Result<DateTime<Utc>> {
+        let data: Vec<TimeData> = self.get("public/time", &[]).await?;
+        let ts = data.first().context("OKX returned no server time")?;
+        let millis: i64 = ts
+            .ts
+            .parse()
+            .with_context(|| format!("Invalid OKX server time: {}", ts.ts))?;
+        from_millis(millis).context("Invalid OKX server time")
+    }
+}
+
+/// The channel and instrument of a WebSocket message.
+#[derive(Deserialize, Debug)]
+struct OkxArg {
+    channel: String,
+    #[serde(rename = "instId")]
+    inst_id: String,
+}
+
+/// A message of the WebSocket API: channel data, or an event answering a request.
+#[derive(Deserialize, Debug)]
+struct OkxMessage {
+    arg: Option<OkxArg>,
+    #[serde(default)]
+    data: Vec<Vec<String>>,
+    event: Option<String>,
+    msg: Option<String>,
+}
+
+/// A WebSocket client streaming the klines of an OKX instrument from its
+/// `candle{bar}` channel.
+pub struct OkxKlineStreaming {
+    url: String,
+    inst_type: OkxInstType,
+    symbol: String,
+    inst_id: String,
+    interval: String,
+    channel: String,
+    step: chrono::Duration,
+    socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    next_ping: tokio::time::Instant,
+    pending: VecDeque<SerdableKlineData>,
+    callbacks: Vec<Box<dyn SharedMessageHandler<SerdableKlineData> + Send>>,
+    stats: Option<Arc<StreamStats>>,
+    generation: u64,
+    context: Option<IngestContext>,
+}
+
+impl OkxKlineStreaming {
+    /// Connects to the business WebSocket API for the klines of an instrument.
+    ///
+    /// # Arguments
+    ///
+    /// * `inst_type` - The type of the instrument.
+    /// * `symbol` - The normalized symbol the klines are labeled with (e.g., "BTCUSDT").
+    /// * `inst_id` - The instrument id (e.g., "BTC-USDT").
+    /// * `interval` - The kline interval (e.g., "1m").
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if OKX has no candles of the interval or the WebSocket
+    /// connection cannot be established.
+    pub async fn new(
+        inst_type: OkxInstType,
+        symbol: &str,
+        inst_id: &str,
+        interval: &str,
+    ) -> Result<Self> {
+        let bar = candle_bar(interval)
+            .with_context(|| format!("Unsupported interval for OKX: {}", interval))?;
+        let step = interval_duration(interval)
+            .with_context(|| format!("Unsupported interval for OKX: {}", interval))?;
+        let (socket, _) = tokio_tungstenite::connect_async(OKX_STREAM_URL)
+            .await
+            .with_context(|| format!("Failed to connect to {}", OKX_STREAM_URL))?;
+        Ok(Self {
+            url: OKX_STREAM_URL.to_string(),
+            inst_type,
+            symbol: symbol.to_string(),
+            inst_id: inst_id.to_string(),
+            interval: interval.to_string(),
+            channel: format!("candle{}", bar),
+            step,
+            socket,
+            next_ping: tokio::time::Instant::now() + PING_INTERVAL,
+            pending: VecDeque::new(),
+            callbacks: Vec::new(),
+            stats: None,
+            generation: 0,
+            context: None,
+        })
+    }
+
+    /// Returns the stream name (e.g., "BTC-USDT@candle1m").
+    pub fn stream(&self) -> String {
+        format!("{}@{}", self.inst_id, self.channel)
+    }
+
+    /// Attaches shared [`StreamStats`] that record parse errors, handler errors and
+    /// the latency of the handler chain while [`listen`](StreamingClient::listen) is
+    /// running.
+    pub fn attach_stats(&mut self, stats: Arc<StreamStats>) {
+        self.stats = Some(stats);
+    }
+
+    /// Queues the candles of a message.
+    fn queue_candles(&mut self, rows: &[Vec<String>]) -> Result<()> {
+        for fields in rows {
+            let candle = OkxCandle::from_fields(fields)?;
+            self.pending.push_back(candle.to_serdable_kline(
+                &self.symbol,
+                &self.interval,
+                self.step,
+                self.inst_type,
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl StreamingClient<SerdableKlineData> for OkxKlineStreaming {
+    async fn connect(&mut self) -> Result<()> {
+        let (socket, _) = tokio_tungstenite::connect_async(self.url.as_str())
+            .await
+            .with_context(|| format!("Failed to connect to {}", self.url))?;
+        self.socket = socket;
+        self.next_ping = tokio::time::Instant::now() + PING_INTERVAL;
+        self.pending.clear();
+        self.generation += 1;
+        Ok(())
+    }
+
+    async fn subscribe(&mut self) -> Result<()> {
+        let request = serde_json::json!({
+            "op": "subscribe",
+            "args": [{ "channel": self.channel, "instId": self.inst_id }],
+        });
+        self.socket
+            .send(Message::Text(request.to_string()))
+            .await
+            .with_context(|| format!("Failed to subscribe to {}", self.stream()))?;
+        Ok(())
+    }
+
+    async fn next(&mut self) -> Result<Option<Result<SerdableKlineData>>> {
+        loop {
+            if let Some(kline) = self.pending.pop_front() {
+                return Ok(Some(Ok(kline)));
+            }
+            let message = match tokio::time::timeout_at(self.next_ping, self.socket.next()).await {
+                Ok(message) => message,
+                Err(_) => {
+                    // OKX expects a plain "ping" text frame, answered by "pong".
+                    self.socket
+                        .send(Message::Text("ping".to_string()))
+                        .await
+                        .context("Failed to ping OKX")?;
+                    self.next_ping = tokio::time::Instant::now() + PING_INTERVAL;
+                    continue;
+                }
+            };
+            let text = match message {
+                Some(Ok(Message::Text(text))) => text,
+                Some(Ok(Message::Close(_))) | None => return Ok(None),
+                // Pings are answered by the socket itself.
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => return Ok(Some(Err(anyhow::Error::msg(e.to_string())))),
+            };
+            if text == "pong" {
+                continue;
+            }
+            let message = match serde_json::from_str::<OkxMessage>(&text) {
+                Ok(message) => message,
+                Err(e) => {
+                    return Ok(Some(Err(anyhow::anyhow!(
+                        "Failed to parse OKX message: {}: {}",
+                        e,
+                        text
+                    ))));
+                }
+            };
+            if message.event.as_deref() == Some("error") {
+                bail!(
+                    "OKX rejected {}: {}",
+                    self.stream(),
+                    message.msg.unwrap_or_default()
+                );
+            }
+            // Events answering subscriptions carry no candles.
+            let is_candles = message.event.is_none()
+                && message
+                    .arg
+                    .as_ref()
+                    .is_some_and(|arg| arg.channel == self.channel && arg.inst_id == self.inst_id);
+            if !is_candles {
+                continue;
+            }
+            self.context = Some(
+                IngestContext::new(self.inst_type.exchange(), &self.stream())
+                    .with_generation(self.generation),
+            );
+            if let Err(e) = self.queue_candles(&message.data) {
+                return Ok(Some(Err(e)));
+            }
+        }
+    }
+
+    fn context(&self) -> Option<IngestContext> {
+        self.context.clone()
+    }
+
+    fn add_callback(&mut self, handler: Box<dyn MessageHandler<SerdableKlineData> + Send>) {
+        self.callbacks.push(Box::new(Borrowed(handler)));
+    }
+
+    fn add_shared_callback(
+        &mut self,
+        handler: Box<dyn SharedMessageHandler<SerdableKlineData> + Send>,
+    ) {
+        self.callbacks.push(handler);
+    }
+
+    async fn listen(&mut self) -> Result<()> {
+        while let Some(result) = StreamingClient::next(self).await? {
+            match result {
+                Ok(kline) => {
+                    let kline = Arc::new(kline);
+                    let context = self.context.clone().unwrap_or_else(|| {
+                        IngestContext::new(self.inst_type.exchange(), &self.stream())
+                    });
+                    let started_at = Instant::now();
+                    for callback in &mut self.callbacks {
+                        if let Err(e) = callback.handle_shared_with_context(&kline, &context).await
+                        {
+                            if let Some(stats) = &self.stats {
+                                stats.record_error();
+                            }
+                            return Err(e);
+                        }
+                    }
+                    if let Some(stats) = &self.stats {
+                        stats.record_latency(started_at.elapsed());
+                    }
+                }
+                Err(e) => {
+                    if let Some(stats) = &self.stats {
+                        stats.record_error();
+                    }
+                    log::warn!("Error processing OKX kline: {}", e);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instruments_and_bars() {
+        let spot = Okx::spot();
+        assert_eq!(spot.normalize_symbol("btc-usdt"), "BTCUSDT");
+        assert_eq!(spot.normalize_symbol("BTC-USDT-SWAP"), "BTCUSDT");
+        assert_eq!(spot.inst_id("BTCUSDT").unwrap(), "BTC-USDT");
+        assert_eq!(Okx::swap().inst_id("ETHUSD").unwrap(), "ETH-USD-SWAP");
+        assert!(spot.inst_id("USDT").is_err());
+        assert_eq!(Okx::swap().name(), OKX_SWAP_EXCHANGE);
+        assert_eq!(candle_bar("1h"), Some("1H"));
+        assert_eq!(candle_bar("1d"), Some("1Dutc"));
+        assert_eq!(candle_bar("8h"), None);
+    }
+
+    #[test]
+    fn test_parse_rest_candles() {
+        let json = r#"{"code":"0","msg":"","data":[
+            ["1700003600000","37050","37200","37000","37100","1.5","55650","55650","1"],
+            ["1700000000000","37000","37100","36900","37050","2","74050","74050","1"]
+        ]}"#;
+        let response: OkxResponse<Vec<String>> = serde_json::from_str(json).unwrap();
+        let rows = response.into_data().unwrap();
+        let step = chrono::Duration::hours(1);
+        let klines: Vec<KlineData> = rows
+            .iter()
+            .map(|fields| {
+                OkxCandle::from_fields(fields)
+                    .unwrap()
+                    .to_kline_data("BTCUSDT", "1h", step, OkxInstType::Spot)
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(klines[1].start_time.timestamp_millis(), 1_700_000_000_000);
+        assert_eq!(klines[1].end_time.timestamp_millis(), 1_700_003_599_999);
+        assert_eq!(klines[1].quote_volume, Some("74050".parse().unwrap()));
+        assert!(klines.iter().all(|kline| kline.validate().is_ok()));
+
+        assert!(OkxCandle::from_fields(&rows[0][..5]).is_err());
+        let json = r#"{"code":"51001","msg":"Instrument ID does not exist","data":[]}"#;
+        let response: OkxResponse<Vec<String>> = serde_json::from_str(json).unwrap();
+        assert!(response.into_data().is_err());
+    }
+
+    #[test]
+    fn test_parse_stream_candles() {
+        let json = r#"{"arg":{"channel":"candle1m","instId":"BTC-USDT-SWAP"},"data":[
+            ["1700000040000","37000","37010","36990","37005","120","1.2","44406","0"]
+        ]}"#;
+        let message: OkxMessage = serde_json::from_str(json).unwrap();
+        assert_eq!(message.arg.unwrap().channel, "candle1m");
+        let candle = OkxCandle::from_fields(&message.data[0]).unwrap();
+        let kline = candle.to_serdable_kline(
+            "BTCUSDT",
+            "1m",
+            chrono::Duration::minutes(1),
+            OkxInstType::Swap,
+        );
+        assert!(!kline.is_final);
+        assert_eq!(kline.volume, "1.2");
+        assert_eq!(kline.end_time - kline.start_time, 59_999);
+        assert!(kline.to_validated_kline_data().is_ok());
+
+        let json = r#"{"event":"subscribe","arg":{"channel":"candle1m","instId":"BTC-USDT"},"connId":"a4d3ae55"}"#;
+        let message: OkxMessage = serde_json::from_str(json).unwrap();
+        assert_eq!(message.event.as_deref(), Some("subscribe"));
+        assert!(message.data.is_empty());
+    }
+}
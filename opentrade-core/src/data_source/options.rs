@@ -0,0 +1,202 @@
+//! # Options Market Data
+//!
+//! Client for the mark prices, implied volatilities and greeks of Binance European
+//! options. The `/eapi/v1/mark` endpoint returns every listed contract in one
+//! response, so polling it yields consistent snapshots of the volatility surface.
+//!
+//! Contract symbols encode the underlying, expiry, strike and type, e.g.
+//! `BTC-250926-100000-C` is a BTC call expiring on 2025-09-26 with a strike of
+//! 100000. [`parse_option_symbol`] decodes them; contracts expire at 08:00 UTC.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use opentrade_core::data_source::options::fetch_option_marks;
+//! use chrono::Utc;
+//! # use anyhow::Result;
+//!
+//! # async fn example() -> Result<()> {
+//! let observed_at = Utc::now();
+//! for payload in fetch_option_marks().await? {
+//!     let mark = payload.to_option_mark(observed_at)?;
+//!     println!("{} mark IV {:?} delta {:?}", mark.symbol, mark.mark_iv, mark.delta);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Deserialize;
+use sqlx::types::BigDecimal as Decimal;
+
+use crate::models::DEFAULT_DATASET;
+use crate::models::option::{OptionKind, OptionMark};
+
+/// The base URL of the Binance options REST API.
+pub const OPTIONS_API_URL: &str = "https://eapi.binance.com";
+
+/// The hour of the day (UTC) options contracts expire at.
+const EXPIRY_HOUR: u32 = 8;
+
+/// The terms of an options contract, decoded from its symbol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptionContract {
+    /// The underlying asset (e.g., "BTC").
+    pub underlying: String,
+    /// The expiry of the contract.
+    pub expiry: DateTime<Utc>,
+    /// The strike price.
+    pub strike: Decimal,
+    /// Whether the contract is a call or a put.
+    pub kind: OptionKind,
+}
+
+/// Decodes a contract symbol of the form `<underlying>-<YYMMDD>-<strike>-<C|P>`.
+///
+/// Returns `None` if the symbol is not of that form.
+pub fn parse_option_symbol(symbol: &str) -> Option<OptionContract> {
+    let mut parts = symbol.split('-');
+    let (underlying, expiry, strike, kind) =
+        (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some() || underlying.is_empty() {
+        return None;
+    }
+    let expiry = NaiveDate::parse_from_str(expiry, "%y%m%d")
+        .ok()?
+        .and_hms_opt(EXPIRY_HOUR, 0, 0)?
+        .and_utc();
+    Some(OptionContract {
+        underlying: underlying.to_string(),
+        expiry,
+        strike: strike.parse().ok()?,
+        kind: OptionKind::parse(kind)?,
+    })
+}
+
+/// The mark of one contract, as returned by `/eapi/v1/mark`.
+///
+/// Numeric fields are strings to preserve precision.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct OptionMarkPayload {
+    pub symbol: String,
+    pub mark_price: String,
+    #[serde(rename = "bidIV", default)]
+    pub bid_iv: Option<String>,
+    #[serde(rename = "askIV", default)]
+    pub ask_iv: Option<String>,
+    #[serde(rename = "markIV", default)]
+    pub mark_iv: Option<String>,
+    #[serde(default)]
+    pub delta: Option<String>,
+    #[serde(default)]
+    pub gamma: Option<String>,
+    #[serde(default)]
+    pub theta: Option<String>,
+    #[serde(default)]
+    pub vega: Option<String>,
+}
+
+impl OptionMarkPayload {
+    /// Converts the payload into a row of `option_marks` under the [`DEFAULT_DATASET`].
+    ///
+    /// Missing or unparseable implied volatilities and greeks are stored as `None`.
+    ///
+    /// # Arguments
+    ///
+    /// * `observed_at` - The time of the snapshot the payload was fetched in.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the symbol cannot be decoded or the mark price cannot
+    /// be parsed.
+    pub fn to_option_mark(&self, observed_at: DateTime<Utc>) -> Result<OptionMark> {
+        fn number(value: &Option<String>) -> Option<f64> {
+            value.as_deref()?.parse().ok()
+        }
+
+        let contract = parse_option_symbol(&self.symbol)
+            .with_context(|| format!("Invalid option symbol: {}", self.symbol))?;
+        let mark_price = self
+            .mark_price
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid mark price: {}", self.mark_price))?;
+        Ok(OptionMark {
+            symbol: self.symbol.clone(),
+            dataset: DEFAULT_DATASET.to_string(),
+            observed_at,
+            underlying: contract.underlying,
+            expiry: contract.expiry,
+            strike: contract.strike,
+            option_type: contract.kind.as_str().to_string(),
+            mark_price,
+            bid_iv: number(&self.bid_iv),
+            ask_iv: number(&self.ask_iv),
+            mark_iv: number(&self.mark_iv),
+            delta: number(&self.delta),
+            gamma: number(&self.gamma),
+            theta: number(&self.theta),
+            vega: number(&self.vega),
+        })
+    }
+}
+
+/// Fetches the marks of all listed options contracts.
+///
+/// # Errors
+///
+/// Returns an error if the request fails or the response cannot be parsed.
+pub async fn fetch_option_marks() -> Result<Vec<OptionMarkPayload>> {
+    let url = format!("{}/eapi/v1/mark", OPTIONS_API_URL);
+    let response = reqwest::get(&url)
+        .await
+        .with_context(|| format!("Failed to request {}", url))?
+        .error_for_status()
+        .context("Binance options API returned an error")?;
+    response
+        .json()
+        .await
+        .context("Failed to parse option marks")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_option_symbol() {
+        let contract = parse_option_symbol("BTC-250926-100000-C").unwrap();
+        assert_eq!(contract.underlying, "BTC");
+        assert_eq!(
+            contract.expiry,
+            DateTime::parse_from_rfc3339("2025-09-26T08:00:00Z").unwrap()
+        );
+        assert_eq!(contract.strike, "100000".parse::<Decimal>().unwrap());
+        assert_eq!(contract.kind, OptionKind::Call);
+
+        let contract = parse_option_symbol("DOGE-250801-0.2-P").unwrap();
+        assert_eq!(contract.strike, "0.2".parse::<Decimal>().unwrap());
+        assert_eq!(contract.kind, OptionKind::Put);
+
+        assert_eq!(parse_option_symbol("BTCUSDT"), None);
+        assert_eq!(parse_option_symbol("BTC-251399-100000-C"), None);
+        assert_eq!(parse_option_symbol("BTC-250926-100000-X"), None);
+        assert_eq!(parse_option_symbol("BTC-250926-100000-C-1"), None);
+    }
+
+    #[test]
+    fn test_payload_to_option_mark() {
+        let json = r#"[{"symbol":"BTC-200730-9000-C","markPrice":"1343.2883","bidIV":"1.40000077","askIV":"","markIV":"1.45000000","delta":"0.55937056","theta":"-3739.82509871","gamma":"0.00010969","vega":"978.58874732","highPriceLimit":"1618.241","lowPriceLimit":"1068.3356"}]"#;
+        let payloads: Vec<OptionMarkPayload> = serde_json::from_str(json).unwrap();
+        let observed_at = DateTime::from_timestamp(1_596_000_000, 0).unwrap();
+
+        let mark = payloads[0].to_option_mark(observed_at).unwrap();
+        assert_eq!(mark.underlying, "BTC");
+        assert_eq!(mark.option_type, "C");
+        assert_eq!(mark.observed_at, observed_at);
+        assert_eq!(mark.mark_iv, Some(1.45));
+        assert_eq!(mark.ask_iv, None);
+        assert_eq!(mark.theta, Some(-3739.82509871));
+    }
+}
@@ -0,0 +1,478 @@
+//! # Data Source Quotas
+//!
+//! Shared API keys often come with daily allowances on top of the per-minute
+//! weight limits of [`rate_limit`](super::rate_limit). This module counts the
+//! requests sent to and the rows downloaded from each data source per API key and
+//! day in the `source_usage` table, so the counts hold across all processes using
+//! the same key, and enforces configurable [`Quota`]s:
+//!
+//! - Past the soft limit (80% of a quota by default), a warning is logged once per
+//!   day.
+//! - Past the hard limit, requests fail with [`QuotaExceeded`] until the next day
+//!   (UTC).
+//!
+//! [`QuotaSource`] wraps a [`MarketDataSource`] like
+//! [`ScaledSource`](crate::ingest::precision::ScaledSource) does, so backfills
+//! account for their requests without changes. The remaining budget is available
+//! from [`QuotaTracker::status`] for exporting as metrics, and from the `quota`
+//! binary.
+//!
+//! ## Configuration
+//!
+//! Quotas are read from the `SOURCE_QUOTAS` environment variable by
+//! [`quotas_from_env`], as a JSON object keyed by source, or by source and API key
+//! label separated by a slash:
+//!
+//! ```json
+//! {
+//!     "binance": { "max_requests": 100000, "max_rows": 50000000 },
+//!     "binance-futures/ops": { "max_requests": 20000, "soft_percent": 50 }
+//! }
+//! ```
+//!
+//! Sources without a quota are still counted.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use std::sync::Arc;
+//! use opentrade_core::data_source::exchange::Binance;
+//! use opentrade_core::data_source::quota::{QuotaSource, QuotaTracker, quota_for, quotas_from_env};
+//! # use anyhow::Result;
+//!
+//! # async fn example(pool: sqlx::PgPool) -> Result<()> {
+//! let quota = quota_for(&quotas_from_env()?, "binance", "default");
+//! let tracker = Arc::new(QuotaTracker::new(pool, "binance", "default", quota));
+//! tracker.refresh().await?;
+//! let source = QuotaSource::new(Binance, tracker.clone());
+//! println!("{}", tracker.status());
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Deserialize;
+
+use crate::data_source::exchange::MarketDataSource;
+use crate::data_source::websocket::StreamingClient;
+use crate::models::source_usage::SourceUsage;
+use crate::models::{KlineData, SerdableKlineData};
+
+/// The environment variable quotas are read from.
+pub const SOURCE_QUOTAS_ENV: &str = "SOURCE_QUOTAS";
+
+/// The API key label used when none is given.
+pub const DEFAULT_API_KEY: &str = "default";
+
+/// The share of a quota, in percent, after which a warning is logged.
+pub const DEFAULT_SOFT_PERCENT: u32 = 80;
+
+/// The daily allowance of a data source and API key. Limits that are `None` are
+/// not enforced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct Quota {
+    /// The number of requests allowed per day.
+    #[serde(default)]
+    pub max_requests: Option<u64>,
+    /// The number of rows allowed to be downloaded per day.
+    #[serde(default)]
+    pub max_rows: Option<u64>,
+    /// The share of a limit, in percent, after which a warning is logged.
+    #[serde(default = "default_soft_percent")]
+    pub soft_percent: u32,
+}
+
+fn default_soft_percent() -> u32 {
+    DEFAULT_SOFT_PERCENT
+}
+
+impl Default for Quota {
+    fn default() -> Self {
+        Self {
+            max_requests: None,
+            max_rows: None,
+            soft_percent: DEFAULT_SOFT_PERCENT,
+        }
+    }
+}
+
+impl Quota {
+    /// Returns true if the usage reached a hard limit.
+    pub fn is_exhausted(&self, requests: u64, rows: u64) -> bool {
+        self.max_requests.is_some_and(|max| requests >= max)
+            || self.max_rows.is_some_and(|max| rows >= max)
+    }
+
+    /// Returns true if the usage reached the soft limit of a quota.
+    pub fn is_soft_exceeded(&self, requests: u64, rows: u64) -> bool {
+        let soft = |max: u64| max * u64::from(self.soft_percent) / 100;
+        self.max_requests.is_some_and(|max| requests >= soft(max))
+            || self.max_rows.is_some_and(|max| rows >= soft(max))
+    }
+}
+
+/// Reads the quotas from the [`SOURCE_QUOTAS_ENV`] environment variable, keyed by
+/// `source` or `source/api_key`. Returns no quotas if the variable is not set.
+///
+/// # Errors
+///
+/// Returns an error if the variable is not a valid quota object.
+pub fn quotas_from_env() -> Result<HashMap<String, Quota>> {
+    match std::env::var(SOURCE_QUOTAS_ENV) {
+        Ok(value) => serde_json::from_str(&value)
+            .with_context(|| format!("Invalid {}: {}", SOURCE_QUOTAS_ENV, value)),
+        Err(_) => Ok(HashMap::new()),
+    }
+}
+
+/// Returns the quota of a source and API key: the one configured for both, else
+/// the one configured for the source, else no limits.
+pub fn quota_for(quotas: &HashMap<String, Quota>, source: &str, api_key: &str) -> Quota {
+    quotas
+        .get(&format!("{}/{}", source, api_key))
+        .or_else(|| quotas.get(source))
+        .copied()
+        .unwrap_or_default()
+}
+
+/// The error returned for requests past the hard limit of a quota.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuotaExceeded {
+    /// The usage when the request was rejected.
+    pub status: QuotaStatus,
+}
+
+impl fmt::Display for QuotaExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "daily quota exceeded: {}", self.status)
+    }
+}
+
+impl std::error::Error for QuotaExceeded {}
+
+/// The usage and remaining budget of a data source and API key on a day.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuotaStatus {
+    /// The data source.
+    pub source: String,
+    /// The label of the API key.
+    pub api_key: String,
+    /// The day the usage was counted on.
+    pub day: NaiveDate,
+    /// The number of requests sent.
+    pub requests: u64,
+    /// The number of rows downloaded.
+    pub rows: u64,
+    /// The number of requests left, if requests are limited.
+    pub remaining_requests: Option<u64>,
+    /// The number of rows left, if rows are limited.
+    pub remaining_rows: Option<u64>,
+}
+
+impl QuotaStatus {
+    /// Creates the status of a usage under `quota`.
+    pub fn new(
+        source: &str,
+        api_key: &str,
+        day: NaiveDate,
+        requests: u64,
+        rows: u64,
+        quota: &Quota,
+    ) -> Self {
+        Self {
+            source: source.to_string(),
+            api_key: api_key.to_string(),
+            day,
+            requests,
+            rows,
+            remaining_requests: quota.max_requests.map(|max| max.saturating_sub(requests)),
+            remaining_rows: quota.max_rows.map(|max| max.saturating_sub(rows)),
+        }
+    }
+}
+
+impl fmt::Display for QuotaStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let remaining = |value: Option<u64>| match value {
+            Some(value) => value.to_string(),
+            None => "unlimited".to_string(),
+        };
+        write!(
+            f,
+            "{}/{} on {}: {} requests ({} left), {} rows ({} left)",
+            self.source,
+            self.api_key,
+            self.day,
+            self.requests,
+            remaining(self.remaining_requests),
+            self.rows,
+            remaining(self.remaining_rows)
+        )
+    }
+}
+
+/// The last known usage of a tracker.
+#[derive(Debug)]
+struct Usage {
+    day: NaiveDate,
+    requests: u64,
+    rows: u64,
+    warned: bool,
+}
+
+impl Usage {
+    fn new(day: NaiveDate) -> Self {
+        Self {
+            day,
+            requests: 0,
+            rows: 0,
+            warned: false,
+        }
+    }
+}
+
+/// Counts and limits the usage of one data source and API key.
+///
+/// The counts are kept in `source_usage`; the tracker caches the totals returned
+/// by the last [`record`](Self::record), which include the usage of other
+/// processes, to check requests without a round trip.
+pub struct QuotaTracker {
+    pool: sqlx::PgPool,
+    source: String,
+    api_key: String,
+    quota: Quota,
+    usage: Mutex<Usage>,
+}
+
+impl QuotaTracker {
+    /// Creates a tracker.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `source` - The data source.
+    /// * `api_key` - The label of the API key (e.g., [`DEFAULT_API_KEY`]).
+    /// * `quota` - The daily allowance.
+    pub fn new(pool: sqlx::PgPool, source: &str, api_key: &str, quota: Quota) -> Self {
+        Self {
+            pool,
+            source: source.to_string(),
+            api_key: api_key.to_string(),
+            quota,
+            usage: Mutex::new(Usage::new(Utc::now().date_naive())),
+        }
+    }
+
+    /// Returns the quota.
+    pub fn quota(&self) -> &Quota {
+        &self.quota
+    }
+
+    /// Loads the usage of today recorded so far, e.g. by other processes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the usage cannot be read.
+    pub async fn refresh(&self) -> Result<QuotaStatus, sqlx::Error> {
+        self.record(0, 0).await
+    }
+
+    /// Checks that a request fits into the quota.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`QuotaExceeded`] if the usage of today reached a hard limit.
+    pub fn check(&self) -> Result<(), QuotaExceeded> {
+        let today = Utc::now().date_naive();
+        let mut usage = self.usage.lock().unwrap();
+        if usage.day != today {
+            *usage = Usage::new(today);
+        }
+        if self.quota.is_exhausted(usage.requests, usage.rows) {
+            return Err(QuotaExceeded {
+                status: self.status_of(&usage),
+            });
+        }
+        Ok(())
+    }
+
+    /// Adds requests and rows to the usage of today, logging a warning the first
+    /// time the soft limit is reached.
+    ///
+    /// # Arguments
+    ///
+    /// * `requests` - The number of requests sent.
+    /// * `rows` - The number of rows downloaded.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the usage cannot be stored.
+    pub async fn record(&self, requests: u64, rows: u64) -> Result<QuotaStatus, sqlx::Error> {
+        let recorded = SourceUsage::record(
+            &self.pool,
+            &self.source,
+            &self.api_key,
+            Utc::now().date_naive(),
+            requests as i64,
+            rows as i64,
+        )
+        .await?;
+        let mut usage = self.usage.lock().unwrap();
+        if usage.day != recorded.day {
+            *usage = Usage::new(recorded.day);
+        }
+        usage.requests = recorded.requests.max(0) as u64;
+        usage.rows = recorded.rows.max(0) as u64;
+        let status = self.status_of(&usage);
+        if !usage.warned && self.quota.is_soft_exceeded(usage.requests, usage.rows) {
+            usage.warned = true;
+            log::warn!("Approaching the daily quota of {}", status);
+        }
+        Ok(status)
+    }
+
+    /// Returns the last known usage and remaining budget of today.
+    pub fn status(&self) -> QuotaStatus {
+        self.status_of(&self.usage.lock().unwrap())
+    }
+
+    fn status_of(&self, usage: &Usage) -> QuotaStatus {
+        QuotaStatus::new(
+            &self.source,
+            &self.api_key,
+            usage.day,
+            usage.requests,
+            usage.rows,
+            &self.quota,
+        )
+    }
+}
+
+/// A [`MarketDataSource`] that counts the requests and rows of another source
+/// with a [`QuotaTracker`] and rejects requests once its quota is exhausted.
+///
+/// Only REST requests are counted; live streams are passed through.
+pub struct QuotaSource<S> {
+    inner: S,
+    tracker: Arc<QuotaTracker>,
+}
+
+impl<S: MarketDataSource> QuotaSource<S> {
+    /// Wraps `inner`, accounting its requests with `tracker`.
+    pub fn new(inner: S, tracker: Arc<QuotaTracker>) -> Self {
+        Self { inner, tracker }
+    }
+
+    /// Returns the tracker.
+    pub fn tracker(&self) -> &Arc<QuotaTracker> {
+        &self.tracker
+    }
+}
+
+#[async_trait]
+impl<S: MarketDataSource> MarketDataSource for QuotaSource<S> {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn normalize_symbol(&self, symbol: &str) -> String {
+        self.inner.normalize_symbol(symbol)
+    }
+
+    fn max_kline_limit(&self) -> u32 {
+        self.inner.max_kline_limit()
+    }
+
+    fn default_kline_limit(&self) -> u32 {
+        self.inner.default_kline_limit()
+    }
+
+    async fn fetch_klines(
+        &self,
+        symbol: &str,
+        interval: &str,
+        start_time: u64,
+        end_time: Option<u64>,
+        limit: Option<u32>,
+    ) -> Result<Vec<KlineData>> {
+        self.tracker.check()?;
+        let result = self
+            .inner
+            .fetch_klines(symbol, interval, start_time, end_time, limit)
+            .await;
+        let rows = result.as_ref().map_or(0, Vec::len);
+        self.tracker
+            .record(1, rows as u64)
+            .await
+            .context("Failed to record the source usage")?;
+        result
+    }
+
+    async fn stream_klines(
+        &self,
+        symbol: &str,
+        interval: &str,
+    ) -> Result<Box<dyn StreamingClient<SerdableKlineData>>> {
+        self.inner.stream_klines(symbol, interval).await
+    }
+
+    async fn server_time(&self) -> Result<DateTime<Utc>> {
+        self.inner.server_time().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quota_limits() {
+        let quota = Quota {
+            max_requests: Some(100),
+            max_rows: None,
+            soft_percent: 80,
+        };
+        assert!(!quota.is_soft_exceeded(79, u64::MAX));
+        assert!(quota.is_soft_exceeded(80, 0));
+        assert!(!quota.is_exhausted(99, u64::MAX));
+        assert!(quota.is_exhausted(100, 0));
+        assert!(!Quota::default().is_exhausted(u64::MAX, u64::MAX));
+
+        let day = NaiveDate::from_ymd_opt(2025, 7, 28).unwrap();
+        let status = QuotaStatus::new("binance", "default", day, 120, 5, &quota);
+        assert_eq!(status.remaining_requests, Some(0));
+        assert_eq!(status.remaining_rows, None);
+        assert_eq!(
+            status.to_string(),
+            "binance/default on 2025-07-28: 120 requests (0 left), 5 rows (unlimited left)"
+        );
+    }
+
+    #[test]
+    fn test_quota_for_prefers_the_api_key() {
+        let quotas: HashMap<String, Quota> = serde_json::from_str(
+            r#"{"binance": {"max_requests": 100}, "binance/ops": {"max_rows": 10, "soft_percent": 50}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            quota_for(&quotas, "binance", "default").max_requests,
+            Some(100)
+        );
+        let ops = quota_for(&quotas, "binance", "ops");
+        assert_eq!(
+            (ops.max_requests, ops.max_rows, ops.soft_percent),
+            (None, Some(10), 50)
+        );
+        assert_eq!(quota_for(&quotas, "binance", "default").soft_percent, 80);
+        assert_eq!(
+            quota_for(&quotas, "uniswap-v3", "default"),
+            Quota::default()
+        );
+    }
+}
@@ -0,0 +1,257 @@
+//! # Request Weight Limiting
+//!
+//! Binance does not count requests but request weight: every endpoint costs a
+//! fixed weight, and the weight an IP spends within a minute is limited. Each
+//! response reports the weight used in the current minute in the
+//! `x-mbx-used-weight-1m` header. Clients that exceed the limit receive HTTP 429
+//! with a `Retry-After` header, and clients that keep sending requests after that
+//! are banned with HTTP 418.
+//!
+//! [`WeightLimiter`] tracks the weight used in the current minute, from its own
+//! reservations and from the reported header, and makes requests wait for the next
+//! minute once [`WEIGHT_THRESHOLD_PERCENT`] of the budget is spent. After a 429 or
+//! 418 it pauses all requests for the `Retry-After` duration. All Binance REST
+//! requests of the process share [`BINANCE_LIMITER`], so concurrent backfills
+//! stay within one budget.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use opentrade_core::data_source::rate_limit::BINANCE_LIMITER;
+//!
+//! # async fn example() {
+//! // Waits until the request fits into the budget of the current minute.
+//! BINANCE_LIMITER.acquire(2).await;
+//! println!("Used weight: {}", BINANCE_LIMITER.used_weight());
+//! # }
+//! ```
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+/// The request weight Binance allows per IP and minute.
+pub const BINANCE_WEIGHT_LIMIT: u32 = 6000;
+
+/// The request weight Binance USDⓈ-M futures allow per IP and minute, counted
+/// separately from the spot budget.
+pub const BINANCE_FUTURES_WEIGHT_LIMIT: u32 = 2400;
+
+/// The share of the weight budget, in percent, after which requests wait for the
+/// next minute. The rest is left for requests the limiter does not see, e.g. of
+/// other processes on the same IP.
+pub const WEIGHT_THRESHOLD_PERCENT: u32 = 90;
+
+/// The header in which Binance reports the weight used in the current minute.
+pub const USED_WEIGHT_HEADER: &str = "x-mbx-used-weight-1m";
+
+/// The pause applied after a 429 or 418 response without a `Retry-After` header.
+pub const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// The weight limiter shared by all Binance REST requests of the process.
+pub static BINANCE_LIMITER: WeightLimiter = WeightLimiter::new(BINANCE_WEIGHT_LIMIT);
+
+/// The weight limiter shared by all Binance USDⓈ-M futures REST requests of the
+/// process.
+pub static BINANCE_FUTURES_LIMITER: WeightLimiter =
+    WeightLimiter::new(BINANCE_FUTURES_WEIGHT_LIMIT);
+
+/// A limiter of the request weight spent per minute.
+#[derive(Debug)]
+pub struct WeightLimiter {
+    limit: u32,
+    state: Mutex<WeightWindow>,
+}
+
+/// The weight used in the current minute and the end of the current pause.
+#[derive(Debug, Default)]
+struct WeightWindow {
+    /// The current minute, in minutes since the UNIX epoch.
+    minute: i64,
+    /// The weight used in the current minute.
+    used: u32,
+    /// The time until which requests are paused after a 429 or 418 response.
+    paused_until: Option<DateTime<Utc>>,
+}
+
+impl WeightLimiter {
+    /// Creates a limiter.
+    ///
+    /// # Arguments
+    ///
+    /// * `limit` - The request weight allowed per minute.
+    pub const fn new(limit: u32) -> Self {
+        Self {
+            limit,
+            state: Mutex::new(WeightWindow {
+                minute: 0,
+                used: 0,
+                paused_until: None,
+            }),
+        }
+    }
+
+    /// Waits until a request of `weight` fits into the budget, then reserves it.
+    ///
+    /// # Arguments
+    ///
+    /// * `weight` - The weight of the request.
+    pub async fn acquire(&self, weight: u32) {
+        loop {
+            let wait = self
+                .state
+                .lock()
+                .unwrap()
+                .reserve(self.threshold(), weight, Utc::now());
+            match wait {
+                Some(wait) => {
+                    log::debug!("Request weight budget spent, waiting {:?}", wait);
+                    tokio::time::sleep(wait).await;
+                }
+                None => return,
+            }
+        }
+    }
+
+    /// Records the weight the exchange reported for the current minute.
+    ///
+    /// # Arguments
+    ///
+    /// * `used` - The value of the [`USED_WEIGHT_HEADER`] header.
+    pub fn record_used_weight(&self, used: u32) {
+        self.state.lock().unwrap().record(used, Utc::now());
+    }
+
+    /// Pauses all requests, e.g. for the `Retry-After` duration of a 429 response.
+    ///
+    /// # Arguments
+    ///
+    /// * `duration` - How long to pause.
+    pub fn pause(&self, duration: Duration) {
+        let until = chrono::Duration::from_std(duration)
+            .ok()
+            .and_then(|duration| Utc::now().checked_add_signed(duration))
+            .unwrap_or(DateTime::<Utc>::MAX_UTC);
+        let mut state = self.state.lock().unwrap();
+        state.paused_until = state.paused_until.max(Some(until));
+    }
+
+    /// Returns the weight used in the current minute, as far as it is known.
+    pub fn used_weight(&self) -> u32 {
+        let mut state = self.state.lock().unwrap();
+        state.roll(Utc::now());
+        state.used
+    }
+
+    /// Returns the weight per minute after which requests wait.
+    fn threshold(&self) -> u32 {
+        self.limit * WEIGHT_THRESHOLD_PERCENT / 100
+    }
+}
+
+impl WeightWindow {
+    /// Starts a new window if `now` is in a later minute.
+    fn roll(&mut self, now: DateTime<Utc>) {
+        let minute = now.timestamp().div_euclid(60);
+        if minute > self.minute {
+            self.minute = minute;
+            self.used = 0;
+        }
+    }
+
+    /// Reserves `weight` if it fits below `threshold` and no pause is running.
+    ///
+    /// # Returns
+    ///
+    /// `None` if the weight was reserved, or how long to wait before trying again.
+    fn reserve(&mut self, threshold: u32, weight: u32, now: DateTime<Utc>) -> Option<Duration> {
+        if let Some(paused_until) = self.paused_until {
+            if paused_until > now {
+                return (paused_until - now).to_std().ok();
+            }
+            self.paused_until = None;
+        }
+        self.roll(now);
+        // A request heavier than the whole budget is let through in a fresh minute.
+        if self.used > 0 && self.used.saturating_add(weight) > threshold {
+            let next_minute = DateTime::from_timestamp((self.minute + 1) * 60, 0)?;
+            return (next_minute - now).to_std().ok();
+        }
+        self.used = self.used.saturating_add(weight);
+        None
+    }
+
+    /// Records the weight reported by the exchange. The reported weight includes
+    /// requests of other clients on the same IP, while the local count includes
+    /// reservations whose responses are still pending, so the larger one is kept.
+    fn record(&mut self, used: u32, now: DateTime<Utc>) {
+        self.roll(now);
+        self.used = self.used.max(used);
+    }
+}
+
+/// Parses a `Retry-After` header given in seconds.
+///
+/// # Returns
+///
+/// The duration, or [`DEFAULT_RETRY_AFTER`] if the header is missing or invalid.
+pub fn parse_retry_after(value: Option<&str>) -> Duration {
+    value
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .map_or(DEFAULT_RETRY_AFTER, Duration::from_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_reserve_waits_for_next_minute_at_threshold() {
+        let now = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 45).unwrap();
+        let mut window = WeightWindow::default();
+        assert_eq!(window.reserve(10, 4, now), None);
+        assert_eq!(window.reserve(10, 4, now), None);
+        assert_eq!(window.reserve(10, 4, now), Some(Duration::from_secs(15)));
+
+        // The budget is renewed in the next minute.
+        let next_minute = now + chrono::Duration::seconds(15);
+        assert_eq!(window.reserve(10, 4, next_minute), None);
+        assert_eq!(window.used, 4);
+    }
+
+    #[test]
+    fn test_record_keeps_larger_weight() {
+        let now = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let mut window = WeightWindow::default();
+        assert_eq!(window.reserve(100, 2, now), None);
+        window.record(50, now);
+        assert_eq!(window.used, 50);
+        window.record(10, now);
+        assert_eq!(window.used, 50);
+        assert_eq!(window.reserve(55, 10, now), Some(Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn test_reserve_honors_pause() {
+        let now = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let mut window = WeightWindow {
+            paused_until: Some(now + chrono::Duration::seconds(30)),
+            ..Default::default()
+        };
+        assert_eq!(window.reserve(100, 2, now), Some(Duration::from_secs(30)));
+        assert_eq!(
+            window.reserve(100, 2, now + chrono::Duration::seconds(30)),
+            None
+        );
+        assert_eq!(window.paused_until, None);
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after(Some("12")), Duration::from_secs(12));
+        assert_eq!(parse_retry_after(Some("soon")), DEFAULT_RETRY_AFTER);
+        assert_eq!(parse_retry_after(None), DEFAULT_RETRY_AFTER);
+    }
+}
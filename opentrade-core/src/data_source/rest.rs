@@ -1,12 +1,314 @@
-use binance_spot_connector_rust::{
-    hyper::{BinanceHttpClient, Error},
-    market::{self, klines::KlineInterval},
-};
+use std::fmt;
+use std::time::Duration;
+
+use binance_spot_connector_rust::market::klines::KlineInterval;
 use serde::de::Error as SerdeDeError;
 use serde_json::Value;
 use sqlx::types::BigDecimal;
 
-use crate::models::KlineData;
+use crate::data_source::api_keys::{API_KEY_HEADER, ApiKeyPool, BINANCE_API_KEYS, KeyOutcome};
+use crate::data_source::rate_limit::{
+    BINANCE_FUTURES_LIMITER, BINANCE_LIMITER, USED_WEIGHT_HEADER, WeightLimiter, parse_retry_after,
+};
+use crate::error::Error;
+use crate::models::funding::SerdableFundingRate;
+use crate::models::open_interest::{SerdableOpenInterest, SerdableOpenInterestHist};
+use crate::models::order_book::DepthSnapshot;
+use crate::models::{DecimalScale, KlineData};
+
+/// The base URL of the Binance spot REST API.
+const BASE_URL: &str = "https://api.binance.com";
+
+/// The base URL of the Binance USDⓈ-M futures REST API.
+const FUTURES_BASE_URL: &str = "https://fapi.binance.com";
+
+/// The maximum number of k-lines Binance returns per request.
+pub const MAX_KLINE_LIMIT: u32 = 1000;
+
+/// The number of k-lines Binance returns per request when no limit is given.
+pub const DEFAULT_KLINE_LIMIT: u32 = 500;
+
+/// The Binance error code for requests that exceeded the rate limit or come from a banned IP.
+const TOO_MANY_REQUESTS_CODE: i64 = -1003;
+/// The Binance error code for an API key that exceeded its own order count limit.
+const TOO_MANY_ORDERS_CODE: i64 = -1015;
+/// The Binance error codes for an invalid API key or one lacking a permission.
+const REJECTED_KEY_CODES: [i64; 2] = [-2014, -2015];
+/// The HTTP status Binance returns for requests with a rejected API key.
+const UNAUTHORIZED_STATUS: u16 = 401;
+/// The Binance error code for an invalid interval.
+const INVALID_INTERVAL_CODE: i64 = -1120;
+/// The Binance error code for an invalid symbol.
+const INVALID_SYMBOL_CODE: i64 = -1121;
+/// The HTTP status Binance returns once an IP has been banned.
+const BANNED_STATUS: u16 = 418;
+/// The HTTP status Binance returns for requests that exceeded the rate limit.
+const TOO_MANY_REQUESTS_STATUS: u16 = 429;
+/// How often a request rejected with HTTP 429 is retried after its `Retry-After` pause.
+const MAX_RATE_LIMIT_RETRIES: u32 = 3;
+
+/// The request weight of a klines request.
+pub const KLINES_WEIGHT: u32 = 2;
+/// The request weight of a server time request.
+pub const SERVER_TIME_WEIGHT: u32 = 1;
+/// The request weight of an exchange information request for all symbols.
+pub const EXCHANGE_INFO_WEIGHT: u32 = 20;
+/// The largest number of levels per side a depth snapshot can contain.
+pub const MAX_DEPTH_LIMIT: u32 = 5000;
+
+/// The maximum number of k-lines Binance futures return per request.
+pub const MAX_FUTURES_KLINE_LIMIT: u32 = 1500;
+/// The maximum number of funding rates Binance futures return per request.
+pub const MAX_FUNDING_RATE_LIMIT: u32 = 1000;
+/// The request weight of a funding rate history request.
+pub const FUNDING_RATE_WEIGHT: u32 = 1;
+/// The request weight of a current open interest request.
+pub const OPEN_INTEREST_WEIGHT: u32 = 1;
+/// The request weight of an open interest history request.
+pub const OPEN_INTEREST_HIST_WEIGHT: u32 = 1;
+/// The maximum number of open interest values Binance futures return per request.
+pub const MAX_OPEN_INTEREST_HIST_LIMIT: u32 = 500;
+/// The periods of the open interest history Binance futures support.
+pub const OPEN_INTEREST_PERIODS: [&str; 9] =
+    ["5m", "15m", "30m", "1h", "2h", "4h", "6h", "12h", "1d"];
+
+/// Errors reported by the REST API client functions.
+#[derive(Debug)]
+pub enum RestError {
+    /// The requested limit is outside the range the exchange accepts.
+    InvalidLimit { limit: u32, max: u32 },
+    /// The exchange does not know the requested symbol.
+    InvalidSymbol { message: String },
+    /// The exchange does not support the requested interval.
+    InvalidInterval { message: String },
+    /// The client was banned for exceeding the rate limits.
+    Banned { message: String },
+    /// The request kept exceeding the rate limit after being retried.
+    RateLimited { retry_after: Duration },
+    /// The exchange rejected the request with another error payload.
+    Api { status: u16, code: i64, message: String },
+    /// The exchange rejected the request without an error payload. `body` is the response body as returned
+    /// by the exchange, e.g. `{"code":-1121,"msg":"Invalid symbol."}`.
+    Rejected { status: u16, body: String },
+    /// The request could not be sent or its response could not be read.
+    Http(reqwest::Error),
+}
+
+impl fmt::Display for RestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RestError::InvalidLimit { limit, max } => {
+                write!(f, "invalid limit {}: must be between 1 and {}", limit, max)
+            }
+            RestError::InvalidSymbol { message } => write!(f, "invalid symbol: {}", message),
+            RestError::InvalidInterval { message } => write!(f, "invalid interval: {}", message),
+            RestError::Banned { message } => write!(f, "banned by the exchange: {}", message),
+            RestError::RateLimited { retry_after } => {
+                write!(f, "rate limited by the exchange, retry after {}s", retry_after.as_secs())
+            }
+            RestError::Api { status, code, message } => {
+                write!(f, "request rejected with status {} (code {}): {}", status, code, message)
+            }
+            RestError::Rejected { status, body } => {
+                write!(f, "request rejected with status {}: {}", status, body)
+            }
+            RestError::Http(e) => write!(f, "request failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for RestError {}
+
+impl RestError {
+    /// Classifies an error payload returned by the exchange.
+    ///
+    /// # Arguments
+    ///
+    /// * `status` - The HTTP status code of the response.
+    /// * `code` - The `code` field of the payload.
+    /// * `message` - The `msg` field of the payload.
+    pub fn from_api_error(status: u16, code: i64, message: String) -> Self {
+        match code {
+            INVALID_SYMBOL_CODE => RestError::InvalidSymbol { message },
+            INVALID_INTERVAL_CODE => RestError::InvalidInterval { message },
+            _ if status == BANNED_STATUS => RestError::Banned { message },
+            TOO_MANY_REQUESTS_CODE if message.to_lowercase().contains("banned") => {
+                RestError::Banned { message }
+            }
+            _ => RestError::Api { status, code, message },
+        }
+    }
+
+    /// Parses a response body, classifying it if it is an error payload such as
+    /// `{"code":-1121,"msg":"Invalid symbol."}`.
+    pub fn from_response(status: u16, body: String) -> Self {
+        match parse_api_error(&body) {
+            Some((code, message)) => RestError::from_api_error(status, code, message),
+            None if status == BANNED_STATUS => RestError::Banned { message: body },
+            None => RestError::Rejected { status, body },
+        }
+    }
+}
+
+impl From<reqwest::Error> for RestError {
+    fn from(e: reqwest::Error) -> Self {
+        RestError::Http(e)
+    }
+}
+
+/// Sends a GET request to the Binance spot REST API within the shared request
+/// weight budget of [`BINANCE_LIMITER`], see [`send_weighted_to`].
+async fn send_weighted(
+    path: &str,
+    query: &[(&str, String)],
+    weight: u32,
+) -> Result<String, RestError> {
+    send_weighted_to(BASE_URL, &BINANCE_LIMITER, path, query, weight).await
+}
+
+/// Sends a GET request to a Binance REST API within the request weight budget of
+/// `limiter`.
+///
+/// The weight reported in the response headers is recorded in the limiter. A
+/// request rejected with HTTP 429 pauses all requests for its `Retry-After`
+/// duration and is retried up to [`MAX_RATE_LIMIT_RETRIES`] times; one rejected
+/// with HTTP 418 pauses all requests as well and fails with [`RestError::Banned`].
+///
+/// If API keys are configured in [`BINANCE_API_KEYS`], each request is sent with
+/// the next key of the pool and its outcome is reported to the pool. A request
+/// rejected because its key exceeded a limit of its own is retried with another
+/// key instead of pausing all requests.
+///
+/// # Arguments
+///
+/// * `base_url` - The base URL of the API (e.g., "https://api.binance.com").
+/// * `limiter` - The weight budget of the API.
+/// * `path` - The path of the endpoint (e.g., "/api/v3/klines").
+/// * `query` - The query parameters.
+/// * `weight` - The request weight of the endpoint.
+///
+/// # Returns
+///
+/// The response body on success, or a [`RestError`] carrying the response body if
+/// the exchange rejected the request.
+async fn send_weighted_to(
+    base_url: &str,
+    limiter: &WeightLimiter,
+    path: &str,
+    query: &[(&str, String)],
+    weight: u32,
+) -> Result<String, RestError> {
+    let client = reqwest::Client::new();
+    let url = format!("{}{}", base_url, path);
+    let mut retries = 0;
+    loop {
+        limiter.acquire(weight).await;
+        let api_key = BINANCE_API_KEYS.as_ref().and_then(ApiKeyPool::select);
+        let mut request = client.get(&url).query(query);
+        if let Some(api_key) = &api_key {
+            request = request.header(API_KEY_HEADER, api_key.key());
+        }
+        let response = request.send().await?;
+        let headers = response.headers();
+        if let Some(used) = headers
+            .get(USED_WEIGHT_HEADER)
+            .and_then(|value| value.to_str().ok()?.parse().ok())
+        {
+            limiter.record_used_weight(used);
+        }
+
+        let status = response.status().as_u16();
+        let retry_after = parse_retry_after(
+            headers
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|value| value.to_str().ok()),
+        );
+        let body = response.text().await?;
+        let code = parse_api_error(&body).map(|(code, _)| code);
+        if let Some(api_key) = &api_key {
+            let outcome = key_outcome(status, code, retry_after);
+            api_key.report(outcome);
+            if let KeyOutcome::RateLimited(_) = outcome {
+                if retries < MAX_RATE_LIMIT_RETRIES {
+                    retries += 1;
+                    log::warn!(
+                        "API key {} rate limited on {}, rotating",
+                        api_key.label(),
+                        path
+                    );
+                    continue;
+                }
+                return Err(RestError::RateLimited { retry_after });
+            }
+        }
+
+        if status == TOO_MANY_REQUESTS_STATUS || status == BANNED_STATUS {
+            limiter.pause(retry_after);
+            if status == TOO_MANY_REQUESTS_STATUS {
+                if retries < MAX_RATE_LIMIT_RETRIES {
+                    retries += 1;
+                    log::warn!("Rate limited on {}, retrying in {}s", path, retry_after.as_secs());
+                    continue;
+                }
+                return Err(RestError::RateLimited { retry_after });
+            }
+            log::error!("Banned on {} for {}s", path, retry_after.as_secs());
+        }
+
+        if !(200..300).contains(&status) {
+            return Err(RestError::from_response(status, body));
+        }
+        return Ok(body);
+    }
+}
+
+/// Classifies the response to a request sent with an API key for the key's pool.
+///
+/// # Arguments
+///
+/// * `status` - The HTTP status code of the response.
+/// * `code` - The `code` field of the error payload, if the response is one.
+/// * `retry_after` - The `Retry-After` duration of the response.
+fn key_outcome(status: u16, code: Option<i64>, retry_after: Duration) -> KeyOutcome {
+    match (status, code) {
+        (TOO_MANY_REQUESTS_STATUS, Some(TOO_MANY_ORDERS_CODE)) => {
+            KeyOutcome::RateLimited(retry_after)
+        }
+        (UNAUTHORIZED_STATUS, _) => KeyOutcome::Rejected,
+        (_, Some(code)) if REJECTED_KEY_CODES.contains(&code) => KeyOutcome::Rejected,
+        _ => KeyOutcome::Success,
+    }
+}
+
+/// Extracts the `code` and `msg` fields of a Binance error payload, if `body` is one.
+pub fn parse_api_error(body: &str) -> Option<(i64, String)> {
+    let value: Value = serde_json::from_str(body).ok()?;
+    let code = value.get("code")?.as_i64()?;
+    let message = value.get("msg")?.as_str()?;
+    Some((code, message.to_string()))
+}
+
+/// Returns the request-weight-optimal limit for fetching k-lines of an interval.
+///
+/// Binance currently weighs every klines request the same, regardless of its
+/// interval and limit, so fetching the maximum number of k-lines per request
+/// minimizes the weight spent on a range for every interval (e.g., 1000 for `1m`).
+pub fn optimal_kline_limit(_interval: KlineInterval) -> u32 {
+    MAX_KLINE_LIMIT
+}
+
+/// Checks that a user-provided k-line limit is accepted by the exchange.
+///
+/// # Returns
+///
+/// The limit on success, or [`RestError::InvalidLimit`] if it is zero or above
+/// [`MAX_KLINE_LIMIT`].
+pub fn validate_kline_limit(limit: u32) -> Result<u32, RestError> {
+    if limit == 0 || limit > MAX_KLINE_LIMIT {
+        return Err(RestError::InvalidLimit { limit, max: MAX_KLINE_LIMIT });
+    }
+    Ok(limit)
+}
 
 /// Fetches k-line (candlestick) data from the Binance API.
 ///
@@ -16,12 +318,16 @@ use crate::models::KlineData;
 /// * `interval` - The k-line interval (e.g., `KlineInterval::Minutes1`).
 /// * `start_time` - The start time in milliseconds since the UNIX epoch.
 /// * `end_time` - An optional end time in milliseconds since the UNIX epoch.
-/// * `limit` - An optional limit on the number of k-lines to retrieve.
+/// * `limit` - An optional limit on the number of k-lines to retrieve, at most
+///   [`MAX_KLINE_LIMIT`].
 ///
 /// # Returns
 ///
 /// A `Result` containing the raw JSON string response from the API on success,
-/// or a `binance_spot_connector_rust::hyper::Error` on failure.
+/// or an [`Error::Api`] on failure. If the exchange rejects the request, the error
+/// carries the response body.
+///
+/// The request waits for the shared request weight budget, see [`send_weighted`].
 pub async fn get_kline_data(
     symbol: &str,
     interval: KlineInterval,
@@ -29,18 +335,256 @@ pub async fn get_kline_data(
     end_time: Option<u64>,
     limit: Option<u32>,
 ) -> Result<String, Error> {
-    let client = BinanceHttpClient::default();
-    let mut request = market::klines(symbol, interval)
-        .start_time(start_time);
+    let mut query = vec![
+        ("symbol", symbol.to_string()),
+        ("interval", interval.to_string()),
+        ("startTime", start_time.to_string()),
+    ];
     if let Some(end_time) = end_time {
-        request = request.end_time(end_time);
+        query.push(("endTime", end_time.to_string()));
     }
     if let Some(limit) = limit {
-        request = request.limit(limit);
+        query.push(("limit", validate_kline_limit(limit)?.to_string()));
+    }
+    Ok(send_weighted("/api/v3/klines", &query, KLINES_WEIGHT).await?)
+}
+
+/// Fetches the current time of the Binance server.
+///
+/// # Returns
+///
+/// A `Result` containing the server time in milliseconds since the UNIX epoch, or
+/// an [`Error`] if the request fails or the response has no server time.
+pub async fn get_server_time() -> Result<u64, Error> {
+    let body = send_weighted("/api/v3/time", &[], SERVER_TIME_WEIGHT).await?;
+    serde_json::from_str::<Value>(&body)?
+        .get("serverTime")
+        .and_then(|value| value.as_u64())
+        .ok_or_else(|| Error::parse("server time", format!("no serverTime in {}", body)))
+}
+
+/// Returns the request weight of a futures klines request with `limit` k-lines.
+pub fn futures_klines_weight(limit: u32) -> u32 {
+    match limit {
+        0..=99 => 1,
+        100..=499 => 2,
+        500..=1000 => 5,
+        _ => 10,
     }
-    let response = client.send(request).await?;
-    let data = response.into_body_str().await?;
-    Ok(data)
+}
+
+/// Fetches k-line data of a Binance USDⓈ-M futures contract.
+///
+/// The response has the same shape as that of [`get_kline_data`], so it can be
+/// parsed with [`extract_klines_from_string`]. Mark price k-lines carry zero
+/// volumes and trade counts.
+///
+/// # Arguments
+///
+/// * `symbol` - The contract symbol (e.g., "BTCUSDT").
+/// * `interval` - The k-line interval (e.g., `KlineInterval::Minutes1`).
+/// * `start_time` - The start time in milliseconds since the UNIX epoch.
+/// * `end_time` - An optional end time in milliseconds since the UNIX epoch.
+/// * `limit` - An optional limit on the number of k-lines to retrieve, at most
+///   [`MAX_FUTURES_KLINE_LIMIT`].
+/// * `mark_price` - Fetch the k-lines of the mark price instead of trades.
+///
+/// # Returns
+///
+/// A `Result` containing the raw JSON string response from the API on success,
+/// or an [`Error::Api`] on failure.
+///
+/// The request waits for the futures request weight budget of
+/// [`BINANCE_FUTURES_LIMITER`].
+pub async fn get_futures_kline_data(
+    symbol: &str,
+    interval: KlineInterval,
+    start_time: u64,
+    end_time: Option<u64>,
+    limit: Option<u32>,
+    mark_price: bool,
+) -> Result<String, Error> {
+    let mut query = vec![
+        ("symbol", symbol.to_string()),
+        ("interval", interval.to_string()),
+        ("startTime", start_time.to_string()),
+    ];
+    if let Some(end_time) = end_time {
+        query.push(("endTime", end_time.to_string()));
+    }
+    let limit = limit.unwrap_or(DEFAULT_KLINE_LIMIT);
+    if limit == 0 || limit > MAX_FUTURES_KLINE_LIMIT {
+        return Err(RestError::InvalidLimit { limit, max: MAX_FUTURES_KLINE_LIMIT }.into());
+    }
+    query.push(("limit", limit.to_string()));
+    let path = if mark_price {
+        "/fapi/v1/markPriceKlines"
+    } else {
+        "/fapi/v1/klines"
+    };
+    let weight = futures_klines_weight(limit);
+    let body =
+        send_weighted_to(FUTURES_BASE_URL, &BINANCE_FUTURES_LIMITER, path, &query, weight).await?;
+    Ok(body)
+}
+
+/// Fetches the current time of the Binance USDⓈ-M futures server.
+///
+/// # Returns
+///
+/// A `Result` containing the server time in milliseconds since the UNIX epoch, or
+/// an [`Error`] if the request fails or the response has no server time.
+pub async fn get_futures_server_time() -> Result<u64, Error> {
+    let body = send_weighted_to(
+        FUTURES_BASE_URL,
+        &BINANCE_FUTURES_LIMITER,
+        "/fapi/v1/time",
+        &[],
+        SERVER_TIME_WEIGHT,
+    )
+    .await?;
+    serde_json::from_str::<Value>(&body)?
+        .get("serverTime")
+        .and_then(|value| value.as_u64())
+        .ok_or_else(|| Error::parse("server time", format!("no serverTime in {}", body)))
+}
+
+/// Fetches the funding rate history of a Binance USDⓈ-M perpetual contract,
+/// oldest first.
+///
+/// # Arguments
+///
+/// * `symbol` - The contract symbol (e.g., "BTCUSDT").
+/// * `start_time` - The start time in milliseconds since the UNIX epoch.
+/// * `end_time` - An optional end time in milliseconds since the UNIX epoch.
+/// * `limit` - The number of funding rates, at most [`MAX_FUNDING_RATE_LIMIT`].
+///
+/// # Returns
+///
+/// A `Result` containing the parsed funding rates, or an [`Error`] if the request
+/// fails or the response cannot be parsed.
+pub async fn get_funding_rates(
+    symbol: &str,
+    start_time: u64,
+    end_time: Option<u64>,
+    limit: u32,
+) -> Result<Vec<SerdableFundingRate>, Error> {
+    let mut query = vec![
+        ("symbol", symbol.to_string()),
+        ("startTime", start_time.to_string()),
+        ("limit", limit.clamp(1, MAX_FUNDING_RATE_LIMIT).to_string()),
+    ];
+    if let Some(end_time) = end_time {
+        query.push(("endTime", end_time.to_string()));
+    }
+    let body = send_weighted_to(
+        FUTURES_BASE_URL,
+        &BINANCE_FUTURES_LIMITER,
+        "/fapi/v1/fundingRate",
+        &query,
+        FUNDING_RATE_WEIGHT,
+    )
+    .await?;
+    serde_json::from_str(&body).map_err(|e| Error::parse("funding rates", e))
+}
+
+/// Fetches the current open interest of a Binance USDⓈ-M futures contract.
+///
+/// # Arguments
+///
+/// * `symbol` - The contract symbol (e.g., "BTCUSDT").
+///
+/// # Returns
+///
+/// A `Result` containing the parsed open interest, or an [`Error`] if the request
+/// fails or the response cannot be parsed.
+pub async fn get_open_interest(symbol: &str) -> Result<SerdableOpenInterest, Error> {
+    let body = send_weighted_to(
+        FUTURES_BASE_URL,
+        &BINANCE_FUTURES_LIMITER,
+        "/fapi/v1/openInterest",
+        &[("symbol", symbol.to_string())],
+        OPEN_INTEREST_WEIGHT,
+    )
+    .await?;
+    serde_json::from_str(&body).map_err(|e| Error::parse("open interest", e))
+}
+
+/// Fetches the open interest history of a Binance USDⓈ-M futures contract,
+/// oldest first. Binance only keeps the history of the last 30 days.
+///
+/// # Arguments
+///
+/// * `symbol` - The contract symbol (e.g., "BTCUSDT").
+/// * `period` - The period of the values, one of [`OPEN_INTEREST_PERIODS`].
+/// * `start_time` - The start time in milliseconds since the UNIX epoch.
+/// * `end_time` - An optional end time in milliseconds since the UNIX epoch.
+/// * `limit` - The number of values, at most [`MAX_OPEN_INTEREST_HIST_LIMIT`].
+///
+/// # Returns
+///
+/// A `Result` containing the parsed values, or an [`Error`] if the period is not
+/// supported, the request fails or the response cannot be parsed.
+pub async fn get_open_interest_hist(
+    symbol: &str,
+    period: &str,
+    start_time: u64,
+    end_time: Option<u64>,
+    limit: u32,
+) -> Result<Vec<SerdableOpenInterestHist>, Error> {
+    if !OPEN_INTEREST_PERIODS.contains(&period) {
+        return Err(RestError::InvalidInterval {
+            message: format!("unsupported open interest period {}", period),
+        }
+        .into());
+    }
+    let limit = limit.clamp(1, MAX_OPEN_INTEREST_HIST_LIMIT);
+    let mut query = vec![
+        ("symbol", symbol.to_string()),
+        ("period", period.to_string()),
+        ("startTime", start_time.to_string()),
+        ("limit", limit.to_string()),
+    ];
+    if let Some(end_time) = end_time {
+        query.push(("endTime", end_time.to_string()));
+    }
+    let body = send_weighted_to(
+        FUTURES_BASE_URL,
+        &BINANCE_FUTURES_LIMITER,
+        "/futures/data/openInterestHist",
+        &query,
+        OPEN_INTEREST_HIST_WEIGHT,
+    )
+    .await?;
+    serde_json::from_str(&body).map_err(|e| Error::parse("open interest history", e))
+}
+
+/// Returns the request weight of a depth snapshot with `limit` levels per side.
+pub fn depth_weight(limit: u32) -> u32 {
+    match limit {
+        0..=100 => 5,
+        101..=500 => 25,
+        501..=1000 => 50,
+        _ => 250,
+    }
+}
+
+/// Fetches a snapshot of the order book of a symbol.
+///
+/// # Arguments
+///
+/// * `symbol` - The trading symbol (e.g., "BTCUSDT").
+/// * `limit` - The number of levels per side, at most [`MAX_DEPTH_LIMIT`].
+///
+/// # Returns
+///
+/// A `Result` containing the parsed [`DepthSnapshot`], or an [`Error`] if the
+/// request fails or the response cannot be parsed.
+pub async fn get_depth_snapshot(symbol: &str, limit: u32) -> Result<DepthSnapshot, Error> {
+    let limit = limit.clamp(1, MAX_DEPTH_LIMIT);
+    let query = [("symbol", symbol.to_string()), ("limit", limit.to_string())];
+    let body = send_weighted("/api/v3/depth", &query, depth_weight(limit)).await?;
+    serde_json::from_str(&body).map_err(|e| Error::parse("depth snapshot", e))
 }
 
 /// Parses a `serde_json::Value` containing a string representation of a decimal
@@ -81,9 +625,8 @@ pub fn parse_kline_data(
     kline: Value,
     symbol: &str,
 ) -> Result<KlineData, serde_json::Error> {
-    match kline.is_array() {
-        true => {
-            let array = kline.as_array().unwrap();
+    match kline.as_array() {
+        Some(array) => {
             let open_time = array.first()
                 .and_then(|v| v.as_u64())
                 .ok_or_else(|| serde_json::Error::custom("Missing or invalid open time"))?;
@@ -141,7 +684,7 @@ pub fn parse_kline_data(
                 Some(quote_volume),
             ))
             }
-        false => {
+        None => {
             Err(serde_json::Error::custom("Expected kline data to be an array"))
         }
     }
@@ -164,23 +707,118 @@ pub fn extract_klines_from_string(
     symbol: &str,
 ) -> Result<Vec<KlineData>, serde_json::Error> {
     let data: Value = serde_json::from_str(klines_data)?;
+    if let Some((code, message)) = parse_api_error(klines_data) {
+        return Err(serde_json::Error::custom(format!("Binance API error {}: {}", code, message)));
+    }
 
-    match data.is_array() {
-        true => {
+    match data.as_array() {
+        Some(items) => {
             // Process the array
             let mut klines = Vec::new();
-            for item in data.as_array().unwrap() {
+            for item in items {
                 let kline = parse_kline_data(item.clone(), symbol)?;
                 klines.push(kline);
             }
             Ok(klines)
         },
-        false => {
+        None => {
             Err(serde_json::Error::custom("Expected klines data is an array"))
         }
     }
 }
 
+/// Fetches the exchange information, including the trading status of every listed symbol.
+///
+/// # Returns
+///
+/// A `Result` containing the raw JSON string response from the API on success,
+/// or an [`Error::Api`] on failure.
+pub async fn get_exchange_info() -> Result<String, Error> {
+    Ok(send_weighted("/api/v3/exchangeInfo", &[], EXCHANGE_INFO_WEIGHT).await?)
+}
+
+/// Extracts the `(symbol, status)` pairs from an exchange information response.
+///
+/// # Arguments
+///
+/// * `exchange_info` - A string slice containing the JSON response from the exchange info API.
+///
+/// # Returns
+///
+/// A `Result` containing the symbol and status pairs on success, or a `serde_json::Error`
+/// if the string is not valid JSON or has no `symbols` array.
+pub fn extract_symbol_statuses(
+    exchange_info: &str,
+) -> Result<Vec<(String, String)>, serde_json::Error> {
+    let data: Value = serde_json::from_str(exchange_info)?;
+    let symbols = data.get("symbols")
+        .and_then(|v| v.as_array())
+        .ok_or_else(|| serde_json::Error::custom("Expected exchange info to contain a symbols array"))?;
+    symbols.iter()
+        .map(|item| {
+            let symbol = item.get("symbol")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| serde_json::Error::custom("Missing or invalid symbol"))?;
+            let status = item.get("status")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| serde_json::Error::custom("Missing or invalid status"))?;
+            Ok((symbol.to_string(), status.to_string()))
+        })
+        .collect()
+}
+
+/// Extracts the [`DecimalScale`] of every symbol from an exchange information response.
+///
+/// The price scale is the number of decimal places of the `tickSize` of the
+/// `PRICE_FILTER`, the quantity scale that of the `stepSize` of the `LOT_SIZE`
+/// filter (e.g., 2 for "0.01000000"), and the quote scale the `quoteAssetPrecision`.
+///
+/// # Arguments
+///
+/// * `exchange_info` - A string slice containing the JSON response from the exchange info API.
+///
+/// # Returns
+///
+/// A `Result` containing the symbol and scale pairs on success, or a `serde_json::Error`
+/// if the string is not valid JSON or a symbol lacks one of the fields.
+pub fn extract_symbol_scales(
+    exchange_info: &str,
+) -> Result<Vec<(String, DecimalScale)>, serde_json::Error> {
+    let data: Value = serde_json::from_str(exchange_info)?;
+    let symbols = data.get("symbols")
+        .and_then(|v| v.as_array())
+        .ok_or_else(|| serde_json::Error::custom("Expected exchange info to contain a symbols array"))?;
+    symbols.iter()
+        .map(|item| {
+            let symbol = item.get("symbol")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| serde_json::Error::custom("Missing or invalid symbol"))?;
+            let quote = item.get("quoteAssetPrecision")
+                .and_then(|v| v.as_i64())
+                .ok_or_else(|| serde_json::Error::custom(format!("Missing quoteAssetPrecision of {}", symbol)))?;
+            let scale = DecimalScale {
+                price: filter_scale(item, symbol, "PRICE_FILTER", "tickSize")?,
+                quantity: filter_scale(item, symbol, "LOT_SIZE", "stepSize")?,
+                quote,
+            };
+            Ok((symbol.to_string(), scale))
+        })
+        .collect()
+}
+
+/// Returns the number of decimal places of a size field of a symbol filter.
+fn filter_scale(item: &Value, symbol: &str, filter_type: &str, field: &str) -> Result<i64, serde_json::Error> {
+    let size = item.get("filters")
+        .and_then(|v| v.as_array())
+        .and_then(|filters| {
+            filters.iter().find(|filter| filter.get("filterType").and_then(|v| v.as_str()) == Some(filter_type))
+        })
+        .and_then(|filter| filter.get(field))
+        .ok_or_else(|| serde_json::Error::custom(format!("Missing {} {} of {}", filter_type, field, symbol)))?;
+    let size = parse_decimal_string(size)?;
+    Ok(size.normalized().as_bigint_and_exponent().1.max(0))
+}
+
 #[cfg(test)]
 /// This module contains tests for the API client functions.
 mod tests {
@@ -289,6 +927,118 @@ mod tests {
         assert_eq!(result.unwrap_err().to_string(), "Expected klines data is an array");
     }
 
+    #[test]
+    fn test_validate_kline_limit() {
+        assert_eq!(validate_kline_limit(1).unwrap(), 1);
+        assert_eq!(validate_kline_limit(MAX_KLINE_LIMIT).unwrap(), MAX_KLINE_LIMIT);
+        assert!(matches!(validate_kline_limit(0), Err(RestError::InvalidLimit { limit: 0, .. })));
+        assert!(matches!(
+            validate_kline_limit(MAX_KLINE_LIMIT + 1),
+            Err(RestError::InvalidLimit { max: MAX_KLINE_LIMIT, .. })
+        ));
+    }
+
+    #[test]
+    fn test_rest_error_surfaces_rejected_body() {
+        let error = RestError::Rejected {
+            status: 400,
+            body: r#"{"code":-1121,"msg":"Invalid symbol."}"#.to_string(),
+        };
+        assert!(error.to_string().contains("Invalid symbol."));
+    }
+
+    #[test]
+    fn test_rest_error_from_response_classifies_payloads() {
+        let error = RestError::from_response(400, r#"{"code":-1121,"msg":"Invalid symbol."}"#.to_string());
+        assert!(matches!(error, RestError::InvalidSymbol { message } if message == "Invalid symbol."));
+        let error = RestError::from_response(400, r#"{"code":-1120,"msg":"Invalid interval."}"#.to_string());
+        assert!(matches!(error, RestError::InvalidInterval { .. }));
+        let error = RestError::from_response(418, r#"{"code":-1003,"msg":"Way too many requests."}"#.to_string());
+        assert!(matches!(error, RestError::Banned { .. }));
+        let error = RestError::from_response(400, r#"{"code":-1100,"msg":"Illegal characters."}"#.to_string());
+        assert!(matches!(error, RestError::Api { code: -1100, .. }));
+        let error = RestError::from_response(502, "Bad Gateway".to_string());
+        assert!(matches!(error, RestError::Rejected { status: 502, .. }));
+    }
+
+    #[test]
+    fn test_extract_klines_from_string_error_payload() {
+        let result = extract_klines_from_string(r#"{"code":-1121,"msg":"Invalid symbol."}"#, "BTCUSDT");
+        assert!(result.unwrap_err().to_string().contains("Invalid symbol."));
+    }
+
+    #[test]
+    fn test_key_outcome_classifies_key_scoped_errors() {
+        let retry_after = Duration::from_secs(10);
+        assert_eq!(
+            key_outcome(429, Some(TOO_MANY_ORDERS_CODE), retry_after),
+            KeyOutcome::RateLimited(retry_after)
+        );
+        assert_eq!(
+            key_outcome(429, Some(TOO_MANY_REQUESTS_CODE), retry_after),
+            KeyOutcome::Success
+        );
+        assert_eq!(key_outcome(401, None, retry_after), KeyOutcome::Rejected);
+        assert_eq!(
+            key_outcome(400, Some(-2015), retry_after),
+            KeyOutcome::Rejected
+        );
+        assert_eq!(key_outcome(200, None, retry_after), KeyOutcome::Success);
+    }
+
+    #[test]
+    fn test_extract_symbol_statuses_success() {
+        let exchange_info = r#"{
+            "timezone": "UTC",
+            "symbols": [
+                {"symbol": "BTCUSDT", "status": "TRADING", "baseAsset": "BTC"},
+                {"symbol": "LUNAUSDT", "status": "BREAK", "baseAsset": "LUNA"}
+            ]
+        }"#;
+        let statuses = extract_symbol_statuses(exchange_info).unwrap();
+        assert_eq!(statuses, vec![
+            ("BTCUSDT".to_string(), "TRADING".to_string()),
+            ("LUNAUSDT".to_string(), "BREAK".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_extract_symbol_scales_from_filters() {
+        let exchange_info = r#"{
+            "symbols": [
+                {
+                    "symbol": "BTCUSDT",
+                    "quoteAssetPrecision": 8,
+                    "filters": [
+                        {"filterType": "PRICE_FILTER", "minPrice": "0.01000000", "tickSize": "0.01000000"},
+                        {"filterType": "LOT_SIZE", "minQty": "0.00001000", "stepSize": "0.00001000"}
+                    ]
+                },
+                {
+                    "symbol": "SHIBUSDT",
+                    "quoteAssetPrecision": 8,
+                    "filters": [
+                        {"filterType": "PRICE_FILTER", "tickSize": "0.00000001"},
+                        {"filterType": "LOT_SIZE", "stepSize": "1.00"}
+                    ]
+                }
+            ]
+        }"#;
+        let scales = extract_symbol_scales(exchange_info).unwrap();
+        assert_eq!(scales, vec![
+            ("BTCUSDT".to_string(), DecimalScale { price: 2, quantity: 5, quote: 8 }),
+            ("SHIBUSDT".to_string(), DecimalScale { price: 8, quantity: 0, quote: 8 }),
+        ]);
+        let missing = r#"{"symbols": [{"symbol": "BTCUSDT", "quoteAssetPrecision": 8, "filters": []}]}"#;
+        assert!(extract_symbol_scales(missing).is_err());
+    }
+
+    #[test]
+    fn test_extract_symbol_statuses_missing_symbols() {
+        let result = extract_symbol_statuses(r#"{"timezone": "UTC"}"#);
+        assert!(result.is_err());
+    }
+
     #[tokio::test]
     async fn test_get_data_e2e() {
         let result = get_kline_data("BTCUSDT", KlineInterval::Minutes1, 1751073120000, None, Some(100)).await.unwrap();
@@ -0,0 +1,582 @@
+//! # Sharded Kline Streams
+//!
+//! Binance accepts at most [`MAX_STREAMS_PER_CONNECTION`] streams per WebSocket
+//! connection, so a single [`KlineStreaming`](super::websocket::KlineStreaming)
+//! cannot cover a large symbol universe. [`ShardedKlineStreaming`] streams the
+//! klines of any number of symbols over as many connections as they need and
+//! merges their messages, so it is managed like a single kline stream:
+//!
+//! - A new symbol is assigned to the connection with the fewest streams that has
+//!   room for it; a connection is opened when all of them are full.
+//! - When removing symbols lets the remaining ones fit in fewer connections, the
+//!   streams of the least loaded connection are moved to the others and it is
+//!   closed. Moved streams are subscribed on their new connection before the old
+//!   one closes, so no update is missed, but a few may be received twice.
+//!
+//! Symbols can be added and removed while streaming. Connections are opened when
+//! the client subscribes.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use opentrade_core::data_source::sharded::ShardedKlineStreaming;
+//! use opentrade_core::data_source::websocket::StreamingClient;
+//! use opentrade_core::models::Interval;
+//! # use anyhow::Result;
+//!
+//! # async fn example(symbols: Vec<String>) -> Result<()> {
+//! let mut stream = ShardedKlineStreaming::new(&symbols, Interval::Minutes1);
+//! stream.subscribe().await?;
+//! println!("Streaming over {} connections", stream.connection_count());
+//!
+//! stream.add_symbols(&["ETHUSDT".to_string()]).await?;
+//! while let Some(result) = stream.next().await? {
+//!     println!("{:?}", result?);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::task::Poll;
+use std::time::Instant;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use futures_util::{SinkExt, StreamExt};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+use crate::data_source::depth::SPOT_STREAM_URL;
+use crate::data_source::websocket::{
+    BINANCE_EXCHANGE, Borrowed, CLOSE_TIMEOUT, IngestContext, MessageHandler, Payload,
+    SharedMessageHandler, StreamingClient,
+};
+use crate::ingest::stats::StreamStats;
+use crate::models::{Interval, SerdableKlineData};
+
+/// The maximum number of streams Binance accepts per WebSocket connection.
+pub const MAX_STREAMS_PER_CONNECTION: usize = 1024;
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// A connection and the streams assigned to it.
+struct Shard {
+    streams: BTreeSet<String>,
+    socket: Option<Socket>,
+}
+
+/// A WebSocket client streaming the klines of many symbols over as many
+/// connections as the per-connection stream limit requires.
+pub struct ShardedKlineStreaming {
+    url: String,
+    interval: Interval,
+    max_streams: usize,
+    shards: Vec<Shard>,
+    subscribed: bool,
+    next_shard: usize,
+    request_id: u64,
+    callbacks: Vec<Box<dyn SharedMessageHandler<SerdableKlineData> + Send>>,
+    stats: Option<Arc<StreamStats>>,
+    generation: u64,
+    context: Option<IngestContext>,
+}
+
+impl ShardedKlineStreaming {
+    /// Creates a client for the klines of `symbols` on the Binance spot market.
+    ///
+    /// No connection is opened until [`subscribe`](StreamingClient::subscribe) is
+    /// called.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbols` - The trading symbols (e.g., "BTCUSDT"); duplicates are ignored.
+    /// * `interval` - The Kline interval of every stream.
+    pub fn new(symbols: &[String], interval: Interval) -> Self {
+        let mut client = Self {
+            url: SPOT_STREAM_URL.to_string(),
+            interval,
+            max_streams: MAX_STREAMS_PER_CONNECTION,
+            shards: Vec::new(),
+            subscribed: false,
+            next_shard: 0,
+            request_id: 0,
+            callbacks: Vec::new(),
+            stats: None,
+            generation: 0,
+            context: None,
+        };
+        for symbol in symbols {
+            client.place(client.stream_name(symbol));
+        }
+        client
+    }
+
+    /// Sets the maximum number of streams per connection (defaults to
+    /// [`MAX_STREAMS_PER_CONNECTION`]) and reassigns the streams accordingly. Only
+    /// takes effect before subscribing.
+    pub fn with_max_streams_per_connection(mut self, max_streams: usize) -> Self {
+        if self.subscribed {
+            return self;
+        }
+        let streams: Vec<String> = self
+            .shards
+            .drain(..)
+            .flat_map(|shard| shard.streams)
+            .collect();
+        self.max_streams = max_streams.max(1);
+        for stream in streams {
+            self.place(stream);
+        }
+        self
+    }
+
+    /// Attaches shared [`StreamStats`] that record parse errors, handler errors and
+    /// the latency of the handler chain while [`listen`](StreamingClient::listen) is
+    /// running.
+    pub fn attach_stats(&mut self, stats: Arc<StreamStats>) {
+        self.stats = Some(stats);
+    }
+
+    /// Returns the number of connections the streams are sharded over.
+    pub fn connection_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Returns the number of streams of each connection.
+    pub fn streams_per_connection(&self) -> Vec<usize> {
+        self.shards
+            .iter()
+            .map(|shard| shard.streams.len())
+            .collect()
+    }
+
+    /// Returns the names of all streams (e.g., "btcusdt@kline_1m"), sorted per
+    /// connection.
+    pub fn streams(&self) -> impl Iterator<Item = &str> {
+        self.shards
+            .iter()
+            .flat_map(|shard| shard.streams.iter().map(String::as_str))
+    }
+
+    /// Adds symbols to the streamed universe, subscribing them right away if the
+    /// client is subscribed. Symbols that are already streamed are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a connection cannot be opened or a subscription request
+    /// cannot be sent.
+    pub async fn add_symbols(&mut self, symbols: &[String]) -> Result<()> {
+        let mut added: Vec<Vec<String>> = Vec::new();
+        for symbol in symbols {
+            let stream = self.stream_name(symbol);
+            if let Some(index) = self.place(stream.clone()) {
+                added.resize_with(self.shards.len(), Vec::new);
+                added[index].push(stream);
+            }
+        }
+        if self.subscribed {
+            for (index, streams) in added.into_iter().enumerate() {
+                if !streams.is_empty() {
+                    self.request(index, "SUBSCRIBE", streams).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes symbols from the streamed universe, unsubscribing them right away if
+    /// the client is subscribed, and closes the connections no longer needed (see
+    /// the [module documentation](self)). Symbols that are not streamed are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a request cannot be sent or a connection cannot be
+    /// opened while rebalancing.
+    pub async fn remove_symbols(&mut self, symbols: &[String]) -> Result<()> {
+        let mut removed: Vec<Vec<String>> = vec![Vec::new(); self.shards.len()];
+        for symbol in symbols {
+            let stream = self.stream_name(symbol);
+            if let Some(index) = self.shards.iter().position(|s| s.streams.contains(&stream)) {
+                self.shards[index].streams.remove(&stream);
+                removed[index].push(stream);
+            }
+        }
+        if self.subscribed {
+            for (index, streams) in removed.into_iter().enumerate() {
+                if !streams.is_empty() && !self.shards[index].streams.is_empty() {
+                    self.request(index, "UNSUBSCRIBE", streams).await?;
+                }
+            }
+        }
+        self.rebalance().await
+    }
+
+    /// Returns the combined stream name of a symbol.
+    fn stream_name(&self, symbol: &str) -> String {
+        format!("{}@kline_{}", symbol.to_lowercase(), self.interval)
+    }
+
+    /// Assigns a stream to the connection with the fewest streams that has room for
+    /// it, adding a connection if all are full.
+    ///
+    /// # Returns
+    ///
+    /// The index of the connection, or `None` if the stream is already assigned.
+    fn place(&mut self, stream: String) -> Option<usize> {
+        if self
+            .shards
+            .iter()
+            .any(|shard| shard.streams.contains(&stream))
+        {
+            return None;
+        }
+        let index = least_loaded(&self.streams_per_connection(), self.max_streams, None)
+            .unwrap_or_else(|| {
+                self.shards.push(Shard {
+                    streams: BTreeSet::new(),
+                    socket: None,
+                });
+                self.shards.len() - 1
+            });
+        self.shards[index].streams.insert(stream);
+        Some(index)
+    }
+
+    /// Moves the streams of the least loaded connections to the others while all
+    /// streams fit in fewer connections, closing the emptied ones.
+    async fn rebalance(&mut self) -> Result<()> {
+        while let Some(victim) = surplus_shard(&self.streams_per_connection(), self.max_streams) {
+            let mut moved: Vec<Vec<String>> = vec![Vec::new(); self.shards.len()];
+            let streams = std::mem::take(&mut self.shards[victim].streams);
+            for stream in streams {
+                let index = least_loaded(
+                    &self.streams_per_connection(),
+                    self.max_streams,
+                    Some(victim),
+                )
+                .context("No connection has room for a moved stream")?;
+                self.shards[index].streams.insert(stream.clone());
+                moved[index].push(stream);
+            }
+            if self.subscribed {
+                for (index, streams) in moved.into_iter().enumerate() {
+                    if !streams.is_empty() {
+                        self.request(index, "SUBSCRIBE", streams).await?;
+                    }
+                }
+            }
+            let shard = self.shards.remove(victim);
+            self.next_shard = 0;
+            if let Some(socket) = shard.socket {
+                close_socket(socket).await;
+            }
+            log::info!(
+                "Rebalanced kline streams over {} connections",
+                self.shards.len()
+            );
+        }
+        Ok(())
+    }
+
+    /// Sends a subscription request for `streams` over a connection, opening it
+    /// first if needed.
+    async fn request(&mut self, index: usize, method: &str, streams: Vec<String>) -> Result<()> {
+        self.request_id += 1;
+        let request = serde_json::json!({
+            "method": method,
+            "params": streams,
+            "id": self.request_id,
+        });
+        let shard = &mut self.shards[index];
+        if shard.socket.is_none() {
+            shard.socket = Some(connect_socket(&self.url).await?);
+        }
+        if let Some(socket) = shard.socket.as_mut() {
+            socket
+                .send(Message::Text(request.to_string()))
+                .await
+                .with_context(|| format!("Failed to send {} to connection {}", method, index))?;
+        }
+        Ok(())
+    }
+
+    /// Waits for the next message of any open connection, polling them in turn so
+    /// that a busy connection cannot starve the others.
+    ///
+    /// # Returns
+    ///
+    /// The index of the connection and its message, which is `None` if the
+    /// connection closed, or `None` if no connection is open.
+    async fn next_message(
+        &mut self,
+    ) -> Option<(
+        usize,
+        Option<Result<Message, tokio_tungstenite::tungstenite::Error>>,
+    )> {
+        let count = self.shards.len();
+        if self.shards.iter().all(|shard| shard.socket.is_none()) {
+            return None;
+        }
+        let start = self.next_shard;
+        let shards = &mut self.shards;
+        let (index, message) = futures_util::future::poll_fn(|cx| {
+            for offset in 0..count {
+                let index = (start + offset) % count;
+                if let Some(socket) = shards[index].socket.as_mut()
+                    && let Poll::Ready(message) = socket.poll_next_unpin(cx)
+                {
+                    return Poll::Ready((index, message));
+                }
+            }
+            Poll::Pending
+        })
+        .await;
+        self.next_shard = (index + 1) % count;
+        Some((index, message))
+    }
+}
+
+/// Returns the index of the connection with the fewest streams that has room for
+/// another one, skipping `exclude`, or `None` if all are full.
+fn least_loaded(loads: &[usize], max_streams: usize, exclude: Option<usize>) -> Option<usize> {
+    loads
+        .iter()
+        .enumerate()
+        .filter(|&(index, &load)| Some(index) != exclude && load < max_streams)
+        .min_by_key(|&(_, &load)| load)
+        .map(|(index, _)| index)
+}
+
+/// Returns the least loaded connection if all streams fit in the other ones.
+fn surplus_shard(loads: &[usize], max_streams: usize) -> Option<usize> {
+    let total: usize = loads.iter().sum();
+    let needed = total.div_ceil(max_streams);
+    if loads.len() <= needed {
+        return None;
+    }
+    loads
+        .iter()
+        .enumerate()
+        .min_by_key(|&(_, &load)| load)
+        .map(|(index, _)| index)
+}
+
+/// Opens a connection to a combined-stream endpoint.
+async fn connect_socket(url: &str) -> Result<Socket> {
+    let (socket, _) = tokio_tungstenite::connect_async(url)
+        .await
+        .with_context(|| format!("Failed to connect to {}", url))?;
+    Ok(socket)
+}
+
+/// Sends a close frame and waits up to [`CLOSE_TIMEOUT`] for the exchange to
+/// close its side of the connection.
+async fn close_socket(mut socket: Socket) {
+    if socket.close(None).await.is_err() {
+        return;
+    }
+    let acknowledged = tokio::time::timeout(CLOSE_TIMEOUT, async {
+        while let Some(Ok(_)) = socket.next().await {}
+    })
+    .await;
+    if acknowledged.is_err() {
+        log::warn!(
+            "Binance did not acknowledge the WebSocket close within {:?}",
+            CLOSE_TIMEOUT
+        );
+    }
+}
+
+#[async_trait]
+impl StreamingClient<SerdableKlineData> for ShardedKlineStreaming {
+    /// Closes the open connections and re-establishes every one. The streams must
+    /// be subscribed again.
+    async fn connect(&mut self) -> Result<()> {
+        let replaced = self
+            .shards
+            .iter_mut()
+            .filter_map(|shard| shard.socket.take());
+        futures_util::future::join_all(replaced.map(close_socket)).await;
+        for shard in &mut self.shards {
+            shard.socket = Some(connect_socket(&self.url).await?);
+        }
+        self.subscribed = false;
+        self.generation += 1;
+        Ok(())
+    }
+
+    /// Opens the connections that are not open yet and subscribes every connection
+    /// to its streams.
+    async fn subscribe(&mut self) -> Result<()> {
+        for index in 0..self.shards.len() {
+            let streams: Vec<String> = self.shards[index].streams.iter().cloned().collect();
+            self.request(index, "SUBSCRIBE", streams).await?;
+        }
+        self.subscribed = true;
+        Ok(())
+    }
+
+    /// Waits for the next message of any connection.
+    ///
+    /// Returns `Ok(None)` as soon as one of the connections closes, or if none is
+    /// open; [`connect`](StreamingClient::connect) re-establishes all of them.
+    async fn next(&mut self) -> Result<Option<Result<SerdableKlineData>>> {
+        while let Some((index, message)) = self.next_message().await {
+            let text = match message {
+                Some(Ok(Message::Text(text))) => text,
+                Some(Ok(Message::Close(_))) | None => {
+                    log::warn!("Connection {} of the sharded kline stream closed", index);
+                    return Ok(None);
+                }
+                // Pings are answered by the socket itself.
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => return Ok(Some(Err(anyhow::Error::msg(e.to_string())))),
+            };
+            match serde_json::from_str::<Payload>(&text) {
+                Ok(payload) => {
+                    self.context = Some(
+                        IngestContext::new(BINANCE_EXCHANGE, &payload.stream)
+                            .with_generation(self.generation),
+                    );
+                    let kline = payload.to_serializable_kline_data().map(|mut kline| {
+                        kline.exchange = Some(BINANCE_EXCHANGE.to_string());
+                        kline
+                    });
+                    return Ok(Some(kline));
+                }
+                // Replies to requests, e.g. `{"result":null,"id":1}`, are not events.
+                Err(_) if text.contains("\"id\"") && !text.contains("\"stream\"") => {
+                    if text.contains("\"error\"") {
+                        log::warn!("Binance rejected a subscription request: {}", text);
+                    }
+                    continue;
+                }
+                Err(e) => {
+                    return Ok(Some(Err(anyhow::anyhow!(
+                        "Failed to parse Kline data: {}: {}",
+                        e,
+                        text
+                    ))));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn context(&self) -> Option<IngestContext> {
+        self.context.clone()
+    }
+
+    fn add_callback(&mut self, handler: Box<dyn MessageHandler<SerdableKlineData> + Send>) {
+        self.callbacks.push(Box::new(Borrowed(handler)));
+    }
+
+    fn add_shared_callback(
+        &mut self,
+        handler: Box<dyn SharedMessageHandler<SerdableKlineData> + Send>,
+    ) {
+        self.callbacks.push(handler);
+    }
+
+    async fn listen(&mut self) -> Result<()> {
+        while let Some(result) = StreamingClient::next(self).await? {
+            match result {
+                Ok(kline) => {
+                    let kline = Arc::new(kline);
+                    let context = self.context.clone().unwrap_or_else(|| {
+                        IngestContext::new(BINANCE_EXCHANGE, &self.stream_name(&kline.symbol))
+                    });
+                    let started_at = Instant::now();
+                    for callback in &mut self.callbacks {
+                        if let Err(e) = callback.handle_shared_with_context(&kline, &context).await
+                        {
+                            if let Some(stats) = &self.stats {
+                                stats.record_error();
+                            }
+                            return Err(e);
+                        }
+                    }
+                    if let Some(stats) = &self.stats {
+                        stats.record_latency(started_at.elapsed());
+                    }
+                }
+                Err(e) => {
+                    if let Some(stats) = &self.stats {
+                        stats.record_error();
+                    }
+                    log::warn!("Error processing Kline data: {}", e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Closes every connection, waiting for each to be acknowledged.
+    async fn close(&mut self) -> Result<()> {
+        let sockets = self
+            .shards
+            .iter_mut()
+            .filter_map(|shard| shard.socket.take());
+        futures_util::future::join_all(sockets.map(close_socket)).await;
+        self.subscribed = false;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbols(count: usize) -> Vec<String> {
+        (0..count).map(|i| format!("SYM{}USDT", i)).collect()
+    }
+
+    #[test]
+    fn test_streams_are_sharded_within_the_limit() {
+        let stream = ShardedKlineStreaming::new(&symbols(5), Interval::Minutes1)
+            .with_max_streams_per_connection(2);
+        assert_eq!(stream.streams_per_connection(), vec![2, 2, 1]);
+        assert!(stream.streams().any(|name| name == "sym0usdt@kline_1m"));
+
+        let stream = ShardedKlineStreaming::new(&symbols(5), Interval::Minutes1);
+        assert_eq!(stream.connection_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_adding_and_removing_symbols_rebalances() {
+        let mut stream = ShardedKlineStreaming::new(&symbols(5), Interval::Hours1)
+            .with_max_streams_per_connection(2);
+        stream.add_symbols(&symbols(6)).await.unwrap();
+        assert_eq!(stream.streams_per_connection(), vec![2, 2, 2]);
+
+        let removed: Vec<String> = symbols(6).into_iter().take(3).collect();
+        stream.remove_symbols(&removed).await.unwrap();
+        assert_eq!(stream.connection_count(), 2);
+        let mut remaining: Vec<&str> = stream.streams().collect();
+        remaining.sort();
+        assert_eq!(
+            remaining,
+            vec![
+                "sym3usdt@kline_1h",
+                "sym4usdt@kline_1h",
+                "sym5usdt@kline_1h"
+            ]
+        );
+
+        stream.remove_symbols(&symbols(6)).await.unwrap();
+        assert_eq!(stream.connection_count(), 0);
+    }
+
+    #[test]
+    fn test_shard_selection() {
+        assert_eq!(least_loaded(&[3, 1, 2], 3, None), Some(1));
+        assert_eq!(least_loaded(&[3, 1, 2], 3, Some(1)), Some(2));
+        assert_eq!(least_loaded(&[3, 3], 3, None), None);
+        assert_eq!(surplus_shard(&[3, 1, 1], 3), Some(1));
+        assert_eq!(surplus_shard(&[3, 2, 2], 3), None);
+        assert_eq!(surplus_shard(&[0], 3), Some(0));
+    }
+}
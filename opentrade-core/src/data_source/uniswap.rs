@@ -0,0 +1,506 @@
+//! # Uniswap v3 Candles
+//!
+//! Uniswap v3 pools have no candle API: prices are only observable through the
+//! swaps executed against them. This module implements [`MarketDataSource`] for
+//! Uniswap v3 by fetching the swaps of a pool from a Uniswap v3 subgraph (The
+//! Graph) and aggregating them into [`KlineData`], so pools can be backfilled and
+//! analysed like the instruments of a centralized exchange.
+//!
+//! ## Mapping
+//!
+//! - Every configured pool is labeled with a symbol (e.g., "WETHUSDC"), which is the
+//!   symbol its candles are stored under. The source reports itself as
+//!   [`UNISWAP_V3_EXCHANGE`]; store pools under their own dataset if their symbols
+//!   could collide with exchange symbols.
+//! - The price of a swap is its execution price `|amount1| / |amount0|`, i.e. token1
+//!   per token0, or the reciprocal for pools configured as inverted.
+//! - Volume is the traded amount of the base token and quote volume that of the
+//!   quote token; the trade count is the number of swaps.
+//! - Swaps have no sequential trade ids, so the first and last trade ids of a candle
+//!   are the block numbers of its first and last swap.
+//! - Candles are aligned to multiples of the interval since the Unix epoch, and
+//!   intervals without swaps produce no candle. Calendar intervals (`1w`, `1M`) are
+//!   not supported.
+//!
+//! There is no live stream; keep pools up to date with a tailing backfill.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use opentrade_core::data_source::exchange::MarketDataSource;
+//! use opentrade_core::data_source::uniswap::UniswapV3;
+//! # use anyhow::Result;
+//!
+//! # async fn example() -> Result<()> {
+//! let source = UniswapV3::new("https://gateway.thegraph.com/api/<key>/subgraphs/id/<id>")
+//!     .with_pool("WETHUSDC", "0x88e6a0c2ddd26feeb64f039a2c41296fcb3f5640", true);
+//! let klines = source.fetch_klines("WETHUSDC", "1h", 1_700_000_000_000, None, Some(24)).await?;
+//! println!("Built {} candles", klines.len());
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+
+use anyhow::{Context, Result, bail};
+use async_trait::async_trait;
+use chrono::Utc;
+use serde::Deserialize;
+use sqlx::types::BigDecimal as Decimal;
+
+use crate::data_source::exchange::MarketDataSource;
+use crate::data_source::websocket::StreamingClient;
+use crate::ingest::audit::interval_duration;
+use crate::models::{KlineData, SerdableKlineData};
+
+/// The exchange name of Uniswap v3 sources.
+pub const UNISWAP_V3_EXCHANGE: &str = "uniswap_v3";
+
+/// The maximum number of swaps a subgraph returns per query.
+const SWAPS_PER_QUERY: usize = 1000;
+
+/// The number of decimal places prices are rounded to, matching `kline_data`.
+const PRICE_SCALE: i64 = 8;
+
+const SWAPS_QUERY: &str = r#"
+query Swaps($pool: String!, $from: BigInt!, $to: BigInt!, $first: Int!, $skip: Int!) {
+  swaps(
+    first: $first
+    skip: $skip
+    orderBy: timestamp
+    orderDirection: asc
+    where: { pool: $pool, timestamp_gte: $from, timestamp_lt: $to }
+  ) {
+    timestamp
+    amount0
+    amount1
+    transaction { blockNumber }
+  }
+}
+"#;
+
+/// A Uniswap v3 pool whose swaps are aggregated into candles.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UniswapPool {
+    /// The pool contract address.
+    pub address: String,
+    /// Whether prices are token0 per token1 instead of token1 per token0.
+    pub invert: bool,
+}
+
+/// A swap of a pool, as returned by the subgraph. Amounts are signed and already
+/// scaled by the token decimals.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Swap {
+    /// The block time (seconds since the epoch).
+    pub timestamp: String,
+    /// The amount of token0 sent to (positive) or received from the pool.
+    pub amount0: String,
+    /// The amount of token1 sent to (positive) or received from the pool.
+    pub amount1: String,
+    /// The transaction the swap was executed in.
+    pub transaction: SwapTransaction,
+}
+
+/// The transaction of a [`Swap`].
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SwapTransaction {
+    /// The block number.
+    pub block_number: String,
+}
+
+#[derive(Deserialize)]
+struct GraphResponse {
+    data: Option<SwapsData>,
+    #[serde(default)]
+    errors: Vec<GraphError>,
+}
+
+#[derive(Deserialize)]
+struct SwapsData {
+    swaps: Vec<Swap>,
+}
+
+#[derive(Deserialize)]
+struct GraphError {
+    message: String,
+}
+
+/// Uniswap v3 pools, read from a subgraph.
+#[derive(Debug, Clone)]
+pub struct UniswapV3 {
+    subgraph_url: String,
+    pools: HashMap<String, UniswapPool>,
+    client: reqwest::Client,
+}
+
+impl UniswapV3 {
+    /// Creates a source reading from a Uniswap v3 subgraph.
+    ///
+    /// # Arguments
+    ///
+    /// * `subgraph_url` - The GraphQL endpoint of the subgraph, including any API key.
+    pub fn new(subgraph_url: &str) -> Self {
+        Self {
+            subgraph_url: subgraph_url.to_string(),
+            pools: HashMap::new(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Adds a pool under a symbol.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbol` - The symbol the pool's candles are stored under (e.g., "WETHUSDC").
+    /// * `address` - The pool contract address.
+    /// * `invert` - Whether prices are token0 per token1, e.g. for pools whose
+    ///   token0 is the quote currency.
+    pub fn with_pool(mut self, symbol: &str, address: &str, invert: bool) -> Self {
+        self.pools.insert(
+            self.normalize_symbol(symbol),
+            UniswapPool {
+                address: address.to_lowercase(),
+                invert,
+            },
+        );
+        self
+    }
+
+    /// Fetches the swaps of a pool executed in `[from, to)`, in seconds since the
+    /// epoch, oldest first.
+    pub async fn fetch_swaps(&self, pool: &str, from: i64, to: i64) -> Result<Vec<Swap>> {
+        let mut swaps: Vec<Swap> = Vec::new();
+        let mut page_from = from;
+        // Swaps at `page_from` that were already returned by the previous page.
+        let mut skip = 0;
+        loop {
+            let body = serde_json::json!({
+                "query": SWAPS_QUERY,
+                "variables": {
+                    "pool": pool,
+                    "from": page_from.to_string(),
+                    "to": to.to_string(),
+                    "first": SWAPS_PER_QUERY,
+                    "skip": skip,
+                },
+            });
+            let response: GraphResponse = self
+                .client
+                .post(&self.subgraph_url)
+                .json(&body)
+                .send()
+                .await
+                .context("Failed to query the Uniswap subgraph")?
+                .error_for_status()
+                .context("Uniswap subgraph returned an error")?
+                .json()
+                .await
+                .context("Failed to parse the Uniswap subgraph response")?;
+            if let Some(error) = response.errors.first() {
+                bail!("Uniswap subgraph query failed: {}", error.message);
+            }
+            let page = response.data.map(|data| data.swaps).unwrap_or_default();
+            let full = page.len() == SWAPS_PER_QUERY;
+            let Some(last) = page.last() else {
+                break;
+            };
+            let last_timestamp: i64 = last
+                .timestamp
+                .parse()
+                .with_context(|| format!("Invalid swap timestamp: {}", last.timestamp))?;
+            let at_last = page
+                .iter()
+                .filter(|s| s.timestamp == last.timestamp)
+                .count();
+            skip = if last_timestamp == page_from {
+                skip + at_last
+            } else {
+                at_last
+            };
+            page_from = last_timestamp;
+            swaps.extend(page);
+            if !full {
+                break;
+            }
+        }
+        Ok(swaps)
+    }
+}
+
+/// Aggregates swaps sorted by time into candles.
+///
+/// # Arguments
+///
+/// * `swaps` - The swaps, sorted by timestamp.
+/// * `symbol` - The symbol the candles are labeled with.
+/// * `interval` - The interval the candles are labeled with.
+/// * `step` - The length of `interval`.
+/// * `invert` - Whether prices are token0 per token1.
+///
+/// # Errors
+///
+/// Returns an error if a swap has an unparseable field. Swaps without a price,
+/// i.e. with a zero amount, are skipped.
+pub fn candles_from_swaps(
+    swaps: &[Swap],
+    symbol: &str,
+    interval: &str,
+    step: chrono::Duration,
+    invert: bool,
+) -> Result<Vec<KlineData>> {
+    struct Candle {
+        start: u64,
+        first_block: i64,
+        last_block: i64,
+        open: Decimal,
+        high: Decimal,
+        low: Decimal,
+        close: Decimal,
+        volume: Decimal,
+        quote_volume: Decimal,
+        swaps: i32,
+    }
+
+    fn amount(value: &str) -> Result<Decimal> {
+        let amount: Decimal = value
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid swap amount: {}", value))?;
+        Ok(amount.abs())
+    }
+
+    let step_ms = step.num_milliseconds() as u64;
+    let zero = Decimal::from(0);
+    let mut candles: Vec<Candle> = Vec::new();
+    for swap in swaps {
+        let seconds: u64 = swap
+            .timestamp
+            .parse()
+            .with_context(|| format!("Invalid swap timestamp: {}", swap.timestamp))?;
+        let block: i64 =
+            swap.transaction.block_number.parse().with_context(|| {
+                format!("Invalid block number: {}", swap.transaction.block_number)
+            })?;
+        let (amount0, amount1) = (amount(&swap.amount0)?, amount(&swap.amount1)?);
+        let (base, quote) = if invert {
+            (amount1, amount0)
+        } else {
+            (amount0, amount1)
+        };
+        if base == zero || quote == zero {
+            continue;
+        }
+        let price = (&quote / &base).round(PRICE_SCALE);
+
+        let start = seconds * 1000 / step_ms * step_ms;
+        match candles.last_mut() {
+            Some(candle) if candle.start == start => {
+                if price > candle.high {
+                    candle.high = price.clone();
+                }
+                if price < candle.low {
+                    candle.low = price.clone();
+                }
+                candle.close = price;
+                candle.last_block = block;
+                candle.volume += base;
+                candle.quote_volume += quote;
+                candle.swaps += 1;
+            }
+            _ => candles.push(Candle {
+                start,
+                first_block: block,
+                last_block: block,
+                open: price.clone(),
+                high: price.clone(),
+                low: price.clone(),
+                close: price,
+                volume: base,
+                quote_volume: quote,
+                swaps: 1,
+            }),
+        }
+    }
+
+    Ok(candles
+        .into_iter()
+        .map(|candle| {
+            KlineData::new(
+                &candle.start,
+                &(candle.start + step_ms - 1),
+                symbol,
+                interval,
+                candle.first_block,
+                candle.last_block,
+                candle.open,
+                candle.high,
+                candle.low,
+                candle.close,
+                candle.volume.round(PRICE_SCALE),
+                Some(candle.swaps),
+                Some(candle.quote_volume.round(PRICE_SCALE)),
+            )
+            .with_exchange(UNISWAP_V3_EXCHANGE)
+        })
+        .collect())
+}
+
+#[async_trait]
+impl MarketDataSource for UniswapV3 {
+    fn name(&self) -> &str {
+        UNISWAP_V3_EXCHANGE
+    }
+
+    fn normalize_symbol(&self, symbol: &str) -> String {
+        symbol
+            .chars()
+            .filter(char::is_ascii_alphanumeric)
+            .collect::<String>()
+            .to_uppercase()
+    }
+
+    fn max_kline_limit(&self) -> u32 {
+        1000
+    }
+
+    fn default_kline_limit(&self) -> u32 {
+        500
+    }
+
+    async fn fetch_klines(
+        &self,
+        symbol: &str,
+        interval: &str,
+        start_time: u64,
+        end_time: Option<u64>,
+        limit: Option<u32>,
+    ) -> Result<Vec<KlineData>> {
+        let step = interval_duration(interval)
+            .with_context(|| format!("Unsupported interval for Uniswap v3: {}", interval))?;
+        let pool = self
+            .pools
+            .get(&self.normalize_symbol(symbol))
+            .with_context(|| format!("No Uniswap v3 pool configured for {}", symbol))?;
+        let step_ms = step.num_milliseconds() as u64;
+        let limit = limit
+            .unwrap_or(self.default_kline_limit())
+            .min(self.max_kline_limit());
+
+        // Candles starting in [start_time, window_end), like the exchange APIs.
+        let first_start = start_time.div_ceil(step_ms) * step_ms;
+        let mut window_end = first_start + u64::from(limit) * step_ms;
+        if let Some(end_time) = end_time {
+            window_end = window_end.min(end_time + 1);
+        }
+        let now = Utc::now().timestamp_millis() as u64;
+        let swaps_end = window_end.min(now + 1);
+        if first_start >= swaps_end {
+            return Ok(Vec::new());
+        }
+
+        let swaps = self
+            .fetch_swaps(
+                &pool.address,
+                (first_start / 1000) as i64,
+                swaps_end.div_ceil(1000) as i64,
+            )
+            .await?;
+        let mut klines = candles_from_swaps(&swaps, symbol, interval, step, pool.invert)?;
+        klines.retain(|kline| (kline.start_time.timestamp_millis() as u64) < window_end);
+        Ok(klines)
+    }
+
+    async fn stream_klines(
+        &self,
+        symbol: &str,
+        _interval: &str,
+    ) -> Result<Box<dyn StreamingClient<SerdableKlineData>>> {
+        bail!(
+            "Uniswap v3 has no live kline stream for {}; tail it with a backfill instead",
+            symbol
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn swap(timestamp: u64, amount0: &str, amount1: &str, block: u64) -> Swap {
+        Swap {
+            timestamp: timestamp.to_string(),
+            amount0: amount0.to_string(),
+            amount1: amount1.to_string(),
+            transaction: SwapTransaction {
+                block_number: block.to_string(),
+            },
+        }
+    }
+
+    fn decimal(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn test_candles_from_swaps() {
+        // An hour boundary in November 2023, so that the candles pass validation.
+        let base = 1_699_999_200;
+        // token0 is USDC and token1 is WETH, so the ETH price is the inverted one.
+        let swaps = vec![
+            swap(base + 3_600, "-3000", "1", 100),
+            swap(base + 3_700, "6200", "-2", 101),
+            swap(base + 3_800, "-1450", "0.5", 102),
+            swap(base + 7_300, "3100", "-1", 130),
+            swap(base + 7_400, "0", "-1", 131),
+        ];
+        let candles =
+            candles_from_swaps(&swaps, "WETHUSDC", "1h", chrono::Duration::hours(1), true).unwrap();
+
+        assert_eq!(candles.len(), 2);
+        let first = &candles[0];
+        assert_eq!(first.start_time.timestamp(), base as i64 + 3_600);
+        assert_eq!(
+            first.end_time.timestamp_millis(),
+            (base as i64 + 7_200) * 1_000 - 1
+        );
+        assert_eq!(first.open, decimal("3000"));
+        assert_eq!(first.high, decimal("3100"));
+        assert_eq!(first.low, decimal("2900"));
+        assert_eq!(first.close, decimal("2900"));
+        assert_eq!(first.volume, decimal("3.5"));
+        assert_eq!(first.quote_volume, Some(decimal("10650")));
+        assert_eq!(first.trade_count, Some(3));
+        assert_eq!((first.first_trade_id, first.last_trade_id), (100, 102));
+        assert!(first.validate().is_ok());
+
+        // The swap without a token0 amount has no price.
+        assert_eq!(candles[1].trade_count, Some(1));
+        assert_eq!(candles[1].close, decimal("3100"));
+    }
+
+    #[test]
+    fn test_prices_are_rounded() {
+        let swaps = vec![swap(0, "3", "1", 1)];
+        let candles =
+            candles_from_swaps(&swaps, "X", "1m", chrono::Duration::minutes(1), false).unwrap();
+        assert_eq!(candles[0].open, decimal("0.33333333"));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_pool_and_interval_are_rejected() {
+        let source = UniswapV3::new("http://localhost:0").with_pool("weth/usdc", "0xABC", true);
+        assert_eq!(source.pools["WETHUSDC"].address, "0xabc");
+
+        let error = source
+            .fetch_klines("WBTCUSDC", "1h", 0, None, None)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("WBTCUSDC"));
+        let error = source
+            .fetch_klines("WETHUSDC", "1w", 0, None, None)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("1w"));
+    }
+}
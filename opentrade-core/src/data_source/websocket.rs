@@ -1,4 +1,7 @@
 
+use crate::data_source::futures::USDM_FUTURES_STREAM_URL;
+use crate::error::Error;
+use crate::ingest::stats::StreamStats;
 use crate::models::{KlineData, SerdableKlineData};
 use anyhow::{Context, Result};
 use async_trait::async_trait;
@@ -7,12 +10,29 @@ use binance_spot_connector_rust::{
     market_stream::kline::KlineStream,
     tokio_tungstenite::{BinanceWebSocketClient, WebSocketState},
 };
-use futures_util::{StreamExt};
+use chrono::{DateTime, Utc};
+use futures_util::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
 use serde_json;
 use sqlx::types::BigDecimal;
+use std::borrow::Cow;
+use std::sync::Arc;
+use std::time::Instant;
 use tokio::net::TcpStream;
+use tokio::sync::mpsc;
 use tokio_tungstenite::MaybeTlsStream;
+use tokio_tungstenite::tungstenite::Message;
+
+/// The exchange name recorded in the [`IngestContext`] of Binance streams.
+pub const BINANCE_EXCHANGE: &str = "binance";
+
+/// The exchange name recorded in the [`IngestContext`] of Binance USDⓈ-M futures
+/// streams.
+pub const BINANCE_FUTURES_EXCHANGE: &str = "binance-futures";
+
+/// How long [`KlineStreaming::close`] waits for the exchange to answer the close
+/// frame before giving up on a clean close.
+pub const CLOSE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
 
 /// WebSocket message payload containing Kline stream data.
 ///
@@ -243,8 +263,8 @@ impl Payload {
             &kline.end_time,
             &kline.symbol,
             &kline.interval,
-            kline.first_trade_id as i32,
-            kline.last_trade_id as i32,
+            kline.first_trade_id as i64,
+            kline.last_trade_id as i64,
             parse_decimal_string(&kline.open)?,
             parse_decimal_string(&kline.high)?,
             parse_decimal_string(&kline.low)?,
@@ -261,6 +281,8 @@ impl Payload {
     /// the string-based representation suitable for JSON serialization and API responses.
     /// Unlike `to_kline_data()`, this method preserves the original string format without
     /// decimal conversion, making it faster and suitable for pass-through scenarios.
+    /// The exchange is left unset, as the payload does not name it; [`KlineStreaming`]
+    /// fills it in.
     ///
     /// # Returns
     ///
@@ -288,8 +310,8 @@ impl Payload {
             end_time: kline.end_time,
             symbol: kline.symbol.clone(),
             interval: kline.interval.clone(),
-            first_trade_id: kline.first_trade_id as i32,
-            last_trade_id: kline.last_trade_id as i32,
+            first_trade_id: kline.first_trade_id as i64,
+            last_trade_id: kline.last_trade_id as i64,
             open: kline.open.clone(),
             high: kline.high.clone(),
             low: kline.low.clone(),
@@ -297,10 +319,116 @@ impl Payload {
             volume: kline.volume.clone(),
             trade_count: kline.trade_count,
             quote_volume: kline.quote_volume.clone(),
+            event_time: Some(self.data.event_time),
+            is_final: kline.is_final,
+            exchange: None,
         })
     }
 }
 
+/// A borrowed view of a Kline WebSocket message, see [`parse_kline_data`].
+///
+/// Only the fields stored in [`KlineData`] are read; the others are skipped.
+#[derive(Deserialize)]
+struct PayloadView<'a> {
+    #[serde(borrow)]
+    stream: Cow<'a, str>,
+    #[serde(borrow)]
+    data: KlinePayloadView<'a>,
+}
+
+#[derive(Deserialize)]
+struct KlinePayloadView<'a> {
+    #[serde(rename = "k", borrow)]
+    kline: KlineDetailsView<'a>,
+}
+
+#[derive(Deserialize)]
+struct KlineDetailsView<'a> {
+    #[serde(rename = "t")]
+    start_time: u64,
+    #[serde(rename = "T")]
+    end_time: u64,
+    #[serde(rename = "s", borrow)]
+    symbol: Cow<'a, str>,
+    #[serde(rename = "i", borrow)]
+    interval: Cow<'a, str>,
+    #[serde(rename = "f")]
+    first_trade_id: u64,
+    #[serde(rename = "L")]
+    last_trade_id: u64,
+    #[serde(rename = "o", borrow)]
+    open: Cow<'a, str>,
+    #[serde(rename = "c", borrow)]
+    close: Cow<'a, str>,
+    #[serde(rename = "h", borrow)]
+    high: Cow<'a, str>,
+    #[serde(rename = "l", borrow)]
+    low: Cow<'a, str>,
+    #[serde(rename = "v", borrow)]
+    volume: Cow<'a, str>,
+    #[serde(rename = "n")]
+    trade_count: u64,
+    #[serde(rename = "q", borrow)]
+    quote_volume: Cow<'a, str>,
+}
+
+impl PayloadView<'_> {
+    fn to_kline_data(&self) -> std::result::Result<KlineData, Error> {
+        let kline = &self.data.kline;
+        let decimal = |field: &str, value: &str| {
+            value.parse::<BigDecimal>().map_err(|e| {
+                Error::parse(
+                    "Kline message",
+                    format!("invalid {} {}: {}", field, value, e),
+                )
+            })
+        };
+        Ok(KlineData::new(
+            &kline.start_time,
+            &kline.end_time,
+            &kline.symbol,
+            &kline.interval,
+            kline.first_trade_id as i64,
+            kline.last_trade_id as i64,
+            decimal("open", &kline.open)?,
+            decimal("high", &kline.high)?,
+            decimal("low", &kline.low)?,
+            decimal("close", &kline.close)?,
+            decimal("volume", &kline.volume)?,
+            Some(kline.trade_count as i32),
+            Some(decimal("quote_volume", &kline.quote_volume)?),
+        ))
+    }
+}
+
+/// Parses a Kline WebSocket message straight into a [`KlineData`].
+///
+/// This is the fast path of deployments that only store candles: the strings of
+/// the message are borrowed instead of being copied into a [`Payload`] and a
+/// [`SerdableKlineData`], and each price and quantity is parsed once. Like
+/// [`Payload::to_kline_data`], the dataset, source kind and exchange are left at
+/// their defaults.
+///
+/// # Errors
+///
+/// Returns [`Error::Parse`] if the message is not a Kline message or a price or
+/// quantity is not a decimal.
+///
+/// # Example
+///
+/// ```rust
+/// use opentrade_core::data_source::websocket::parse_kline_data;
+///
+/// let json = r#"{"stream":"btcusdt@kline_1m","data":{"e":"kline","E":1751897378015,"s":"BTCUSDT","k":{"t":1751897340000,"T":1751897399999,"s":"BTCUSDT","i":"1m","f":1,"L":2,"o":"1.5","c":"1.6","h":"1.7","l":"1.4","v":"10","n":2,"x":false,"q":"15.5","V":"5","Q":"7.7","B":"0"}}}"#;
+/// let kline = parse_kline_data(json)?;
+/// assert_eq!(kline.symbol, "BTCUSDT");
+/// # Ok::<(), opentrade_core::error::Error>(())
+/// ```
+pub fn parse_kline_data(message: &str) -> std::result::Result<KlineData, Error> {
+    serde_json::from_str::<PayloadView>(message)?.to_kline_data()
+}
+
 pub struct KlineSubscription {
     pub symbol: String,
     pub interval: market::klines::KlineInterval,
@@ -326,7 +454,16 @@ pub struct KlineSubscription {
 /// - `symbol`: The trading pair symbol (e.g., "BTCUSDT")
 /// - `interval`: The Kline interval for data aggregation
 /// - `state`: Internal WebSocket connection state
-/// - `callbacks`: Collection of message handlers for processing incoming data
+/// - `callbacks`: Collection of message handlers for processing incoming data; each
+///   message is allocated once and shared by all of them
+/// - `stats`: Optional shared statistics recording errors and handler latency
+/// - `generation`: How many times the connection has been re-established
+/// - `context`: The [`IngestContext`] of the most recently received message
+/// - `url`: The combined-stream endpoint, or `None` for the spot endpoint
+/// - `exchange`: The exchange name recorded in the [`IngestContext`] of messages
+/// - `subscribed`: Whether the stream is subscribed over the current connection
+/// - `closed`: Whether the current connection was closed with
+///   [`close`](KlineStreaming::close)
 ///
 /// # Example
 ///
@@ -362,7 +499,14 @@ pub struct KlineStreaming {
     pub symbol: String,
     pub interval: market::klines::KlineInterval,
     pub state: WebSocketState<MaybeTlsStream<TcpStream>>,
-    pub callbacks: Vec<Box<dyn MessageHandler<SerdableKlineData>>>,
+    pub callbacks: Vec<Box<dyn SharedMessageHandler<SerdableKlineData> + Send>>,
+    pub stats: Option<Arc<StreamStats>>,
+    generation: u64,
+    context: Option<IngestContext>,
+    url: Option<String>,
+    exchange: &'static str,
+    subscribed: bool,
+    closed: bool,
 }
 
 impl KlineStreaming {
@@ -410,9 +554,82 @@ impl KlineStreaming {
             interval,
             state,
             callbacks: Vec::new(),
+            stats: None,
+            generation: 0,
+            context: None,
+            url: None,
+            exchange: BINANCE_EXCHANGE,
+            subscribed: false,
+            closed: false,
         })
     }
 
+    /// Creates a new [`KlineStreaming`] instance for a Binance USDⓈ-M futures
+    /// contract.
+    ///
+    /// Futures kline events have the same shape as spot ones, so they are parsed
+    /// and handled alike. Their [`IngestContext`] records the
+    /// [`BINANCE_FUTURES_EXCHANGE`].
+    ///
+    /// # Arguments
+    ///
+    /// * `symbol` - The contract symbol (e.g., "BTCUSDT")
+    /// * `interval` - The Kline interval for data aggregation (e.g., Minutes1, Hours1)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the WebSocket connection cannot be established.
+    pub async fn usdm_futures(symbol: &str, interval: market::klines::KlineInterval) -> Result<Self> {
+        let (state, _) = BinanceWebSocketClient::connect_async(USDM_FUTURES_STREAM_URL).await?;
+
+        Ok(Self {
+            symbol: symbol.to_string(),
+            interval,
+            state,
+            callbacks: Vec::new(),
+            stats: None,
+            generation: 0,
+            context: None,
+            url: Some(USDM_FUTURES_STREAM_URL.to_string()),
+            exchange: BINANCE_FUTURES_EXCHANGE,
+            subscribed: false,
+            closed: false,
+        })
+    }
+
+    /// Re-establishes the WebSocket connection, replacing the current one.
+    ///
+    /// Existing callbacks are kept, but the stream must be subscribed again
+    /// with [`subscribe`](Self::subscribe) before messages are received. Messages
+    /// received over the new connection carry the next reconnect generation in
+    /// their [`IngestContext`].
+    ///
+    /// Unless it was closed with [`close`](Self::close), the replaced connection
+    /// is closed the same way in a background task.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the WebSocket connection to Binance cannot be established.
+    pub async fn connect(&mut self) -> Result<()> {
+        let (state, _) = match &self.url {
+            Some(url) => BinanceWebSocketClient::connect_async(url).await?,
+            None => BinanceWebSocketClient::connect_async_default().await?,
+        };
+        let mut replaced = std::mem::replace(&mut self.state, state);
+        if !self.closed {
+            let stream = self.subscribed.then(|| self.stream_name());
+            tokio::spawn(async move {
+                if let Err(e) = close_connection(&mut replaced, stream).await {
+                    log::debug!("Failed to close a replaced Binance connection: {:#}", e);
+                }
+            });
+        }
+        self.subscribed = false;
+        self.closed = false;
+        self.generation += 1;
+        Ok(())
+    }
+
     /// Adds a message handler callback for processing incoming Kline data.
     ///
     /// Message handlers implement the [`MessageHandler`] trait and are called
@@ -450,51 +667,254 @@ impl KlineStreaming {
     ///     Ok(())
     /// }
     /// ```
-    pub fn add_callback<H: MessageHandler<SerdableKlineData> + 'static>(&mut self, handler: H) {
+    pub fn add_callback<H: MessageHandler<SerdableKlineData> + Send + 'static>(&mut self, handler: H) {
+        self.callbacks.push(Box::new(Borrowed(Box::new(handler))));
+    }
+
+    /// Adds a handler that receives each message as a shared [`Arc`].
+    ///
+    /// Use this instead of [`add_callback`](Self::add_callback) for handlers that
+    /// keep or forward messages, e.g. a [`ChannelHandler`]; they can hold on to the
+    /// message with a cheap [`Arc::clone`] instead of cloning the Kline data.
+    /// Shared and borrowing handlers are called together in registration order.
+    ///
+    /// # Arguments
+    ///
+    /// * `handler` - A type implementing [`SharedMessageHandler<SerdableKlineData>`]
+    pub fn add_shared_callback<H: SharedMessageHandler<SerdableKlineData> + Send + 'static>(
+        &mut self,
+        handler: H,
+    ) {
         self.callbacks.push(Box::new(handler));
     }
 
+    /// Attaches shared [`StreamStats`] that record parse errors, handler errors and
+    /// the latency of the handler chain while [`listen`](Self::listen) is running.
+    ///
+    /// This is typically paired with a [`StatsHandler`](crate::ingest::stats::StatsHandler),
+    /// which records the messages themselves and periodically logs a summary.
+    ///
+    /// # Arguments
+    ///
+    /// * `stats` - The statistics accumulator to record into
+    pub fn attach_stats(&mut self, stats: Arc<StreamStats>) {
+        self.stats = Some(stats);
+    }
+
     pub async fn subscribe(&mut self) -> Result<()> {
         self.state
             .subscribe(vec![&KlineStream::new(&self.symbol, self.interval).into()])
             .await;
+        self.subscribed = true;
         Ok(())
     }
 
+    /// Closes the connection cleanly: unsubscribes from the stream, sends a close
+    /// frame and waits up to [`CLOSE_TIMEOUT`] for the exchange to close its side.
+    ///
+    /// Afterwards [`next`](Self::next) returns `Ok(None)`; call
+    /// [`connect`](Self::connect) to stream again. Closing a closed connection
+    /// does nothing. Dropping the client without closing it only sends the
+    /// unsubscribe request and close frame if that is possible without waiting.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the close frame cannot be sent.
+    pub async fn close(&mut self) -> Result<()> {
+        if self.closed {
+            return Ok(());
+        }
+        let stream = self.subscribed.then(|| self.stream_name());
+        self.closed = true;
+        self.subscribed = false;
+        close_connection(&mut self.state, stream).await
+    }
+
+    /// Returns the name of the Binance stream (e.g., "btcusdt@kline_1m").
+    fn stream_name(&self) -> String {
+        format!("{}@kline_{}", self.symbol.to_lowercase(), self.interval)
+    }
+
+    /// Forgets the subscription once the connection failed or ended, so that
+    /// closing or dropping the client does not write to a dead socket.
+    fn track_connection(
+        &mut self,
+        message: &Option<std::result::Result<Message, tokio_tungstenite::tungstenite::Error>>,
+    ) {
+        match message {
+            Some(Ok(_)) => {}
+            Some(Err(_)) => self.subscribed = false,
+            None => {
+                self.subscribed = false;
+                self.closed = true;
+            }
+        }
+    }
+
     pub async fn next(&mut self) -> Result<Option<Result<SerdableKlineData>>> {
-        match self.state.as_mut().next().await {
-            Some(Ok(message)) => {
-                let binary_data = message.into_data();
-                let data = std::str::from_utf8(&binary_data)
-                    .expect("Failed to convert binary data to string");
-                println!("Received Kline message: {}", data);
-                let payload = serde_json::from_str::<Payload>(data);
-                match payload {
-                    Ok(payload) => {
-                        let kline_data = payload.to_serializable_kline_data()?;
-                        Ok(Some(Ok(kline_data)))
+        let message = self.state.as_mut().next().await;
+        self.track_connection(&message);
+        match message {
+            Some(message) => self.parse_message(message).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Parses a message received over the connection and records its
+    /// [`IngestContext`].
+    ///
+    /// The outer error is a failure to convert a parsed message, which ends
+    /// [`next`](Self::next); the inner one is a connection or parse error of a
+    /// single message.
+    fn parse_message(
+        &mut self,
+        message: std::result::Result<
+            tokio_tungstenite::tungstenite::Message,
+            tokio_tungstenite::tungstenite::Error,
+        >,
+    ) -> Result<Result<SerdableKlineData>> {
+        let message = match message {
+            Ok(message) => message,
+            Err(e) => return Ok(Err(anyhow::Error::msg(e.to_string()))),
+        };
+        let binary_data = message.into_data();
+        let data = match std::str::from_utf8(&binary_data) {
+            Ok(data) => data,
+            Err(e) => {
+                return Ok(Err(Error::parse("WebSocket message", e).into()));
+            }
+        };
+        log::debug!("Received Kline message: {}", data);
+        let payload = serde_json::from_str::<Payload>(data);
+        match payload {
+            Ok(payload) => {
+                self.context = Some(
+                    IngestContext::new(self.exchange, &payload.stream)
+                        .with_generation(self.generation),
+                );
+                let mut kline_data = payload.to_serializable_kline_data()?;
+                kline_data.exchange = Some(self.exchange.to_string());
+                Ok(Ok(kline_data))
+            }
+            Err(e) => {
+                log::warn!("Failed to parse Kline data: {}", data);
+                Ok(Err(Error::parse("Kline message", e).into()))
+            }
+        }
+    }
+
+    /// Returns the [`IngestContext`] of the message most recently returned by
+    /// [`next`](Self::next), or `None` before the first message.
+    pub fn context(&self) -> Option<&IngestContext> {
+        self.context.as_ref()
+    }
+
+    /// Returns the next message parsed straight into a [`KlineData`] (see
+    /// [`parse_kline_data`]), the fast path of deployments that only store candles.
+    ///
+    /// The candle is labeled with the exchange of the stream and the
+    /// [`SOURCE_KIND_STREAM`](crate::models::SOURCE_KIND_STREAM) source kind. Like
+    /// [`next`](Self::next), connection and parse errors of a single message are
+    /// returned as the inner error, and `Ok(None)` means the connection closed.
+    pub async fn next_kline_data(&mut self) -> Result<Option<Result<KlineData>>> {
+        let message = self.state.as_mut().next().await;
+        self.track_connection(&message);
+        let message = match message {
+            Some(Ok(message)) => message,
+            Some(Err(e)) => return Ok(Some(Err(anyhow::Error::msg(e.to_string())))),
+            None => return Ok(None),
+        };
+        let binary_data = message.into_data();
+        let data = match std::str::from_utf8(&binary_data) {
+            Ok(data) => data,
+            Err(e) => return Ok(Some(Err(Error::parse("WebSocket message", e).into()))),
+        };
+        let view = match serde_json::from_str::<PayloadView>(data) {
+            Ok(view) => view,
+            Err(e) => return Ok(Some(Err(Error::parse("Kline message", e).into()))),
+        };
+        self.context =
+            Some(IngestContext::new(self.exchange, &view.stream).with_generation(self.generation));
+        Ok(Some(
+            view.to_kline_data()
+                .map(|kline| {
+                    kline
+                        .with_exchange(self.exchange)
+                        .with_source_kind(crate::models::SOURCE_KIND_STREAM)
+                })
+                .map_err(Into::into),
+        ))
+    }
+
+    /// Like [`listen`](Self::listen), but reads messages with
+    /// [`next_kline_data`](Self::next_kline_data) and passes them to a single
+    /// handler of [`KlineData`], skipping the [`SerdableKlineData`] conversion.
+    /// Registered callbacks are not called.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error of the handler, or an error if the connection fails.
+    pub async fn listen_kline_data<H>(&mut self, handler: &mut H) -> Result<()>
+    where
+        H: MessageHandler<KlineData> + Send,
+    {
+        while let Some(result) = self.next_kline_data().await? {
+            match result {
+                Ok(kline) => {
+                    let context = self.context.clone().unwrap_or_else(|| {
+                        IngestContext::new(self.exchange, &self.symbol.to_lowercase())
+                    });
+                    let started_at = Instant::now();
+                    if let Err(e) = handler.handle_message_with_context(&kline, &context).await {
+                        if let Some(stats) = &self.stats {
+                            stats.record_error();
+                        }
+                        return Err(e);
                     }
-                    _ => {
-                        println!("Failed to parse Kline data: {}", data);
-                        Ok(Some(Err(anyhow::Error::msg("Failed to parse Kline data"))))
+                    if let Some(stats) = &self.stats {
+                        stats.record_latency(started_at.elapsed());
                     }
                 }
+                Err(e) => {
+                    if let Some(stats) = &self.stats {
+                        stats.record_error();
+                    }
+                    log::warn!("Error processing Kline data: {}", e);
+                }
             }
-            Some(Err(e)) => Ok(Some(Err(anyhow::Error::msg(e.to_string())))),
-            None => Ok(None),
         }
+        Ok(())
     }
 
     pub async fn listen(&mut self) -> Result<()> {
         while let Some(result) = self.next().await? {
             match result {
                 Ok(kline_data) => {
+                    let kline_data = Arc::new(kline_data);
+                    let context = self.context.clone().unwrap_or_else(|| {
+                        IngestContext::new(self.exchange, &self.symbol.to_lowercase())
+                    });
+                    let started_at = Instant::now();
                     for callback in &mut self.callbacks {
-                        callback.handle_message(&kline_data).await?;
+                        if let Err(e) = callback
+                            .handle_shared_with_context(&kline_data, &context)
+                            .await
+                        {
+                            if let Some(stats) = &self.stats {
+                                stats.record_error();
+                            }
+                            return Err(e);
+                        }
+                    }
+                    if let Some(stats) = &self.stats {
+                        stats.record_latency(started_at.elapsed());
                     }
                 }
                 Err(e) => {
-                    eprintln!("Error processing Kline data: {}", e);
+                    if let Some(stats) = &self.stats {
+                        stats.record_error();
+                    }
+                    log::warn!("Error processing Kline data: {}", e);
                 }
             }
         }
@@ -502,6 +922,175 @@ impl KlineStreaming {
     }
 }
 
+/// Yields the Kline data of every message, so streams can be consumed with
+/// [`StreamExt`] combinators instead of a [`next`](KlineStreaming::next) loop.
+///
+/// Connection, parse and conversion errors are yielded as items; the stream ends
+/// when the connection closes. Registered callbacks are not called, and the
+/// inherent [`next`](KlineStreaming::next) shadows [`StreamExt::next`], so call
+/// the latter as `StreamExt::next(&mut stream)`.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use opentrade_core::data_source::websocket::KlineStreaming;
+/// use binance_spot_connector_rust::market::klines::KlineInterval;
+/// use futures_util::StreamExt;
+/// # use anyhow::Result;
+///
+/// # async fn example() -> Result<()> {
+/// let mut stream = KlineStreaming::new("BTCUSDT", KlineInterval::Minutes1).await?;
+/// stream.subscribe().await?;
+///
+/// // Print the next ten closed candles.
+/// let mut closed = stream
+///     .filter_map(|result| async move { result.ok().filter(|kline| kline.is_final) })
+///     .take(10)
+///     .boxed();
+/// while let Some(kline) = closed.next().await {
+///     println!("{} closed at {}", kline.symbol, kline.close);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+impl futures_util::Stream for KlineStreaming {
+    type Item = Result<SerdableKlineData>;
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        this.state.as_mut().poll_next_unpin(cx).map(|message| {
+            this.track_connection(&message);
+            message.map(|message| this.parse_message(message).and_then(|kline| kline))
+        })
+    }
+}
+
+impl Drop for KlineStreaming {
+    fn drop(&mut self) {
+        use futures_util::FutureExt;
+
+        // Dropping cannot wait for the exchange, so only the requests that can be
+        // written without blocking are sent before the socket is dropped. Failures
+        // are ignored: the socket may already be closed by the exchange.
+        if self.closed || tokio::runtime::Handle::try_current().is_err() {
+            return;
+        }
+        let request = self
+            .subscribed
+            .then(|| unsubscribe_request(&self.stream_name()));
+        let socket = self.state.as_mut();
+        if let Some(request) = request {
+            let _ = socket.send(request).now_or_never();
+        }
+        let _ = socket.close(None).now_or_never();
+    }
+}
+
+/// Returns the request unsubscribing from a stream.
+///
+/// The connector's `unsubscribe` panics when the socket is closed, so the request
+/// is sent directly and its failure treated as best-effort.
+fn unsubscribe_request(stream: &str) -> Message {
+    let request = serde_json::json!({
+        "method": "UNSUBSCRIBE",
+        "params": [stream],
+        "id": 1,
+    });
+    Message::Text(request.to_string())
+}
+
+/// Unsubscribes from `stream`, if any, sends a close frame and waits up to
+/// [`CLOSE_TIMEOUT`] for the exchange to close its side of the connection.
+async fn close_connection(
+    state: &mut WebSocketState<MaybeTlsStream<TcpStream>>,
+    stream: Option<String>,
+) -> Result<()> {
+    use tokio_tungstenite::tungstenite::Error as WsError;
+
+    let socket = state.as_mut();
+    if let Some(stream) = stream
+        && let Err(e) = socket.send(unsubscribe_request(&stream)).await
+    {
+        log::debug!("Failed to unsubscribe from {}: {}", stream, e);
+    }
+    match socket.close(None).await {
+        Ok(()) => {}
+        Err(WsError::ConnectionClosed | WsError::AlreadyClosed) => return Ok(()),
+        Err(e) => return Err(e).context("Failed to send the WebSocket close frame"),
+    }
+    // Reads the remaining messages, including the unsubscribe response, until the
+    // close frame of the exchange ends the stream.
+    let acknowledged = tokio::time::timeout(CLOSE_TIMEOUT, async {
+        while let Some(Ok(_)) = socket.next().await {}
+    })
+    .await;
+    if acknowledged.is_err() {
+        log::warn!(
+            "Binance did not acknowledge the WebSocket close within {:?}",
+            CLOSE_TIMEOUT
+        );
+    }
+    Ok(())
+}
+
+/// Metadata describing where and when a message was received.
+///
+/// Streams pass an `IngestContext` to handlers alongside every message (see
+/// [`MessageHandler::handle_message_with_context`]), so handlers can tag stored
+/// rows and metrics with the stream they came from without re-parsing it.
+///
+/// # Fields
+///
+/// - `exchange`: The exchange the message came from (e.g., "binance")
+/// - `stream`: The stream name (e.g., "btcusdt@kline_1m"); pipelines whose source
+///   has no stream name use the pipeline name
+/// - `received_at`: When the message was received
+/// - `generation`: The reconnect generation of the connection, starting at 0 and
+///   incremented every time the stream reconnects
+///
+/// # Example
+///
+/// ```rust
+/// use opentrade_core::data_source::websocket::IngestContext;
+///
+/// let context = IngestContext::new("binance", "btcusdt@kline_1m").with_generation(2);
+/// assert_eq!(context.stream, "btcusdt@kline_1m");
+/// assert_eq!(context.generation, 2);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IngestContext {
+    pub exchange: String,
+    pub stream: String,
+    pub received_at: DateTime<Utc>,
+    pub generation: u64,
+}
+
+impl IngestContext {
+    /// Creates a context for a message received now in the first connection generation.
+    ///
+    /// # Arguments
+    ///
+    /// * `exchange` - The exchange the message came from
+    /// * `stream` - The name of the stream the message came from
+    pub fn new(exchange: &str, stream: &str) -> Self {
+        Self {
+            exchange: exchange.to_string(),
+            stream: stream.to_string(),
+            received_at: Utc::now(),
+            generation: 0,
+        }
+    }
+
+    /// Sets the reconnect generation.
+    pub fn with_generation(mut self, generation: u64) -> Self {
+        self.generation = generation;
+        self
+    }
+}
+
 /// Trait for handling incoming WebSocket messages with custom processing logic.
 ///
 /// The `MessageHandler` trait defines a contract for processing incoming messages
@@ -510,7 +1099,10 @@ impl KlineStreaming {
 ///
 /// # Type Parameters
 ///
-/// * `T` - The message type that must be sendable, thread-safe, cloneable, and serializable
+/// * `T` - The message type. Only `Sync` is required, so handlers can receive wire
+///   types such as [`SerdableKlineData`], internal types such as
+///   [`KlineData`](crate::models::KlineData) or enriched events, and unsized
+///   borrowed data such as `str` or `[u8]`
 ///
 /// # Async Support
 ///
@@ -550,6 +1142,29 @@ impl KlineStreaming {
 /// }
 /// ```
 ///
+/// # Handlers Over Other Types
+///
+/// Handlers are not limited to serializable wire types:
+///
+/// ```rust
+/// use opentrade_core::data_source::websocket::MessageHandler;
+/// use async_trait::async_trait;
+/// use anyhow::Result;
+///
+/// /// Counts the bytes of raw text frames.
+/// struct FrameSizeHandler {
+///     bytes: usize,
+/// }
+///
+/// #[async_trait]
+/// impl MessageHandler<str> for FrameSizeHandler {
+///     async fn handle_message(&mut self, message: &str) -> Result<()> {
+///         self.bytes += message.len();
+///         Ok(())
+///     }
+/// }
+/// ```
+///
 /// # Multiple Handlers
 ///
 /// Multiple handlers can be registered with a single stream to perform different
@@ -570,7 +1185,7 @@ impl KlineStreaming {
 /// # }
 /// ```
 #[async_trait]
-pub trait MessageHandler<T: Send + Sync + Clone + Serialize + for<'de> Deserialize<'de>> {
+pub trait MessageHandler<T: ?Sized + Sync> {
     /// Processes an incoming message asynchronously.
     ///
     /// This method is called for each message received from the WebSocket stream.
@@ -604,37 +1219,351 @@ pub trait MessageHandler<T: Send + Sync + Clone + Serialize + for<'de> Deseriali
     /// }
     /// ```
     async fn handle_message(&mut self, message: &T) -> Result<()>;
+
+    /// Processes an incoming message together with the [`IngestContext`] it was
+    /// received in.
+    ///
+    /// Streams and pipelines always call this method. The default implementation
+    /// ignores the context and calls [`handle_message`](Self::handle_message), so
+    /// only handlers that use the context need to override it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use opentrade_core::data_source::websocket::{IngestContext, MessageHandler};
+    /// # use opentrade_core::models::SerdableKlineData;
+    /// # use async_trait::async_trait;
+    /// # use anyhow::Result;
+    /// struct StreamLogger;
+    ///
+    /// #[async_trait]
+    /// impl MessageHandler<SerdableKlineData> for StreamLogger {
+    ///     async fn handle_message(&mut self, message: &SerdableKlineData) -> Result<()> {
+    ///         println!("Received Kline for {}", message.symbol);
+    ///         Ok(())
+    ///     }
+    ///
+    ///     async fn handle_message_with_context(
+    ///         &mut self,
+    ///         message: &SerdableKlineData,
+    ///         context: &IngestContext,
+    ///     ) -> Result<()> {
+    ///         println!(
+    ///             "Received Kline for {} on {} (generation {})",
+    ///             message.symbol, context.stream, context.generation
+    ///         );
+    ///         Ok(())
+    ///     }
+    /// }
+    /// ```
+    async fn handle_message_with_context(
+        &mut self,
+        message: &T,
+        _context: &IngestContext,
+    ) -> Result<()> {
+        self.handle_message(message).await
+    }
 }
 
-struct PrintKlineHandler {
-    count: usize,
+/// Trait for handlers that receive messages as a shared [`Arc`].
+///
+/// A [`MessageHandler`] only borrows the message for the duration of the call, so a
+/// handler that needs to keep it (buffering, forwarding to a channel or another
+/// task) has to clone it. A `SharedMessageHandler` instead receives the [`Arc`] the
+/// stream allocated once for the message, and keeping it costs a reference count
+/// increment regardless of how many handlers and channels hold it.
+///
+/// Streams and pipelines call shared handlers alongside borrowing ones, see
+/// [`KlineStreaming::add_shared_callback`] and
+/// [`PipelineBuilder::shared_sink`](crate::ingest::pipeline::PipelineBuilder::shared_sink).
+///
+/// # Example Implementation
+///
+/// ```rust
+/// use opentrade_core::data_source::websocket::SharedMessageHandler;
+/// use opentrade_core::models::SerdableKlineData;
+/// use async_trait::async_trait;
+/// use anyhow::Result;
+/// use std::sync::Arc;
+///
+/// /// Keeps the most recent closes without copying the Kline data.
+/// struct RecentKlines {
+///     recent: Vec<Arc<SerdableKlineData>>,
+/// }
+///
+/// #[async_trait]
+/// impl SharedMessageHandler<SerdableKlineData> for RecentKlines {
+///     async fn handle_shared(&mut self, message: &Arc<SerdableKlineData>) -> Result<()> {
+///         self.recent.push(Arc::clone(message));
+///         Ok(())
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait SharedMessageHandler<T: Send + Sync> {
+    /// Processes an incoming message asynchronously.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The shared incoming message, which may be cloned to keep it
+    async fn handle_shared(&mut self, message: &Arc<T>) -> Result<()>;
+
+    /// Processes an incoming message together with the [`IngestContext`] it was
+    /// received in. The default implementation ignores the context and calls
+    /// [`handle_shared`](Self::handle_shared).
+    async fn handle_shared_with_context(
+        &mut self,
+        message: &Arc<T>,
+        _context: &IngestContext,
+    ) -> Result<()> {
+        self.handle_shared(message).await
+    }
+}
+
+/// Adapts a borrowing [`MessageHandler`] so it can be called with shared messages.
+pub(crate) struct Borrowed<T: ?Sized>(pub(crate) Box<dyn MessageHandler<T> + Send>);
+
+#[async_trait]
+impl<T: Send + Sync> SharedMessageHandler<T> for Borrowed<T> {
+    async fn handle_shared(&mut self, message: &Arc<T>) -> Result<()> {
+        self.0.handle_message(message).await
+    }
+
+    async fn handle_shared_with_context(
+        &mut self,
+        message: &Arc<T>,
+        context: &IngestContext,
+    ) -> Result<()> {
+        self.0.handle_message_with_context(message, context).await
+    }
+}
+
+/// A [`SharedMessageHandler`] that forwards every message to a Tokio channel.
+///
+/// Messages are sent as [`Arc`]s, so forwarding does not copy them. Sending waits
+/// while the channel is full, applying backpressure to the stream.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use opentrade_core::data_source::websocket::{ChannelHandler, KlineStreaming};
+/// use binance_spot_connector_rust::market::klines::KlineInterval;
+/// # use anyhow::Result;
+///
+/// # async fn example() -> Result<()> {
+/// let (sender, mut receiver) = tokio::sync::mpsc::channel(1024);
+/// let mut stream = KlineStreaming::new("BTCUSDT", KlineInterval::Minutes1).await?;
+/// stream.add_shared_callback(ChannelHandler::new(sender));
+///
+/// tokio::spawn(async move {
+///     while let Some(kline) = receiver.recv().await {
+///         println!("Close: {}", kline.close);
+///     }
+/// });
+/// # Ok(())
+/// # }
+/// ```
+pub struct ChannelHandler<T> {
+    sender: mpsc::Sender<Arc<T>>,
 }
 
-impl PrintKlineHandler {
-    pub fn new() -> Self {
-        Self { count: 0 }
+impl<T> ChannelHandler<T> {
+    /// Creates a handler forwarding messages to `sender`.
+    pub fn new(sender: mpsc::Sender<Arc<T>>) -> Self {
+        Self { sender }
     }
 }
 
 #[async_trait]
-impl MessageHandler<SerdableKlineData> for PrintKlineHandler {
-    async fn handle_message(&mut self, message: &SerdableKlineData) -> Result<()> {
-        println!("Received Kline data: {:?}", message);
-        self.count += 1;
-        if self.count >= 10 {
-            println!("Processed 10 Kline messages, stopping further processing.");
-            return Err(anyhow::Error::msg(
-                "Processed 10 Kline messages, stopping further processing.",
-            ));
-        }
+impl<T: Send + Sync> SharedMessageHandler<T> for ChannelHandler<T> {
+    async fn handle_shared(&mut self, message: &Arc<T>) -> Result<()> {
+        self.sender
+            .send(Arc::clone(message))
+            .await
+            .map_err(|_| anyhow::anyhow!("Channel receiver was dropped"))
+    }
+}
+
+/// Common interface implemented by every WebSocket stream client.
+///
+/// `StreamingClient` captures the lifecycle shared by all stream types (klines,
+/// trades, depth, ...), so orchestration code can connect, subscribe and run
+/// heterogeneous streams uniformly, e.g. as `Box<dyn StreamingClient<T>>`.
+///
+/// # Type Parameters
+///
+/// * `T` - The message type emitted by the stream and passed to its handlers
+///
+/// # Lifecycle
+///
+/// 1. [`connect`](Self::connect) - (Re-)establish the underlying connection
+/// 2. [`subscribe`](Self::subscribe) - Subscribe to the configured streams
+/// 3. [`add_callback`](Self::add_callback) or
+///    [`add_shared_callback`](Self::add_shared_callback) - Register message handlers
+/// 4. [`listen`](Self::listen) or [`next`](Self::next) - Consume messages
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use opentrade_core::data_source::websocket::{KlineStreaming, StreamingClient};
+/// use opentrade_core::models::SerdableKlineData;
+/// use binance_spot_connector_rust::market::klines::KlineInterval;
+/// # use anyhow::Result;
+///
+/// async fn run(client: &mut dyn StreamingClient<SerdableKlineData>) -> Result<()> {
+///     client.subscribe().await?;
+///     client.listen().await
+/// }
+///
+/// # async fn example() -> Result<()> {
+/// let mut stream = KlineStreaming::new("BTCUSDT", KlineInterval::Minutes1).await?;
+/// run(&mut stream).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[async_trait]
+pub trait StreamingClient<T: Send + Sync>: Send {
+    /// Establishes (or re-establishes) the underlying WebSocket connection.
+    async fn connect(&mut self) -> Result<()>;
+
+    /// Subscribes to the streams this client is configured for.
+    async fn subscribe(&mut self) -> Result<()>;
+
+    /// Waits for the next message.
+    ///
+    /// Returns `Ok(None)` when the connection is closed, and `Ok(Some(Err(_)))`
+    /// for individual messages that could not be parsed.
+    async fn next(&mut self) -> Result<Option<Result<T>>>;
+
+    /// Returns the [`IngestContext`] of the message most recently returned by
+    /// [`next`](Self::next), or `None` before the first message.
+    fn context(&self) -> Option<IngestContext>;
+
+    /// Registers a message handler that is called for every received message.
+    fn add_callback(&mut self, handler: Box<dyn MessageHandler<T> + Send>);
+
+    /// Registers a handler that receives every message as a shared [`Arc`].
+    fn add_shared_callback(&mut self, handler: Box<dyn SharedMessageHandler<T> + Send>);
+
+    /// Consumes messages and dispatches them to the registered handlers until
+    /// the connection is closed or a handler fails.
+    async fn listen(&mut self) -> Result<()>;
+
+    /// Closes the connection cleanly, unsubscribing from the streams first where
+    /// the exchange supports it.
+    ///
+    /// The default implementation does nothing, leaving the connection to be
+    /// closed when the client is dropped.
+    async fn close(&mut self) -> Result<()> {
         Ok(())
     }
 }
 
+#[async_trait]
+impl StreamingClient<SerdableKlineData> for KlineStreaming {
+    async fn connect(&mut self) -> Result<()> {
+        KlineStreaming::connect(self).await
+    }
+
+    async fn subscribe(&mut self) -> Result<()> {
+        KlineStreaming::subscribe(self).await
+    }
+
+    async fn next(&mut self) -> Result<Option<Result<SerdableKlineData>>> {
+        KlineStreaming::next(self).await
+    }
+
+    fn context(&self) -> Option<IngestContext> {
+        self.context.clone()
+    }
+
+    fn add_callback(&mut self, handler: Box<dyn MessageHandler<SerdableKlineData> + Send>) {
+        self.callbacks.push(Box::new(Borrowed(handler)));
+    }
+
+    fn add_shared_callback(
+        &mut self,
+        handler: Box<dyn SharedMessageHandler<SerdableKlineData> + Send>,
+    ) {
+        self.callbacks.push(handler);
+    }
+
+    async fn listen(&mut self) -> Result<()> {
+        KlineStreaming::listen(self).await
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        KlineStreaming::close(self).await
+    }
+}
+
+/// Boxed clients, e.g. those returned by a
+/// [`MarketDataSource`](crate::data_source::exchange::MarketDataSource), are
+/// clients too.
+#[async_trait]
+impl<T: Send + Sync, C: StreamingClient<T> + ?Sized> StreamingClient<T> for Box<C> {
+    async fn connect(&mut self) -> Result<()> {
+        (**self).connect().await
+    }
+
+    async fn subscribe(&mut self) -> Result<()> {
+        (**self).subscribe().await
+    }
+
+    async fn next(&mut self) -> Result<Option<Result<T>>> {
+        (**self).next().await
+    }
+
+    fn context(&self) -> Option<IngestContext> {
+        (**self).context()
+    }
+
+    fn add_callback(&mut self, handler: Box<dyn MessageHandler<T> + Send>) {
+        (**self).add_callback(handler)
+    }
+
+    fn add_shared_callback(&mut self, handler: Box<dyn SharedMessageHandler<T> + Send>) {
+        (**self).add_shared_callback(handler)
+    }
+
+    async fn listen(&mut self) -> Result<()> {
+        (**self).listen().await
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        (**self).close().await
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    struct PrintKlineHandler {
+        count: usize,
+    }
+
+    impl PrintKlineHandler {
+        pub fn new() -> Self {
+            Self { count: 0 }
+        }
+    }
+
+    #[async_trait]
+    impl MessageHandler<SerdableKlineData> for PrintKlineHandler {
+        async fn handle_message(&mut self, message: &SerdableKlineData) -> Result<()> {
+            println!("Received Kline data: {:?}", message);
+            self.count += 1;
+            if self.count >= 10 {
+                println!("Processed 10 Kline messages, stopping further processing.");
+                return Err(anyhow::Error::msg(
+                    "Processed 10 Kline messages, stopping further processing.",
+                ));
+            }
+            Ok(())
+        }
+    }
+
     #[test]
     fn test_parse_payload() {
         let json = r#"{"stream":"btcusdt@kline_1m","data":{"e":"kline","E":1751897378015,"s":"BTCUSDT","k":{"t":1751897340000,"T":1751897399999,"s":"BTCUSDT","i":"1m","f":5067431062,"L":5067432892,"o":"108521.04000000","c":"108473.03000000","h":"108521.04000000","l":"108473.02000000","v":"5.21006000","n":1831,"x":false,"q":"565334.99194810","V":"3.03940000","Q":"329823.87289940","B":"0"}}}"#;
@@ -648,6 +1577,29 @@ mod tests {
         assert_eq!(payload.data.kline.low, "108473.02000000");
         assert_eq!(payload.data.kline.volume, "5.21006000");
         assert_eq!(payload.data.kline.quote_volume, "565334.99194810");
+
+        let kline = payload.to_serializable_kline_data().unwrap();
+        assert_eq!(kline.event_time, Some(1751897378015));
+        assert!(!kline.is_final);
+    }
+
+    #[test]
+    fn test_parse_kline_data_matches_payload_conversion() {
+        let json = r#"{"stream":"btcusdt@kline_1m","data":{"e":"kline","E":1751897378015,"s":"BTCUSDT","k":{"t":1751897340000,"T":1751897399999,"s":"BTCUSDT","i":"1m","f":5067431062,"L":5067432892,"o":"108521.04000000","c":"108473.03000000","h":"108521.04000000","l":"108473.02000000","v":"5.21006000","n":1831,"x":false,"q":"565334.99194810","V":"3.03940000","Q":"329823.87289940","B":"0"}}}"#;
+        let direct = parse_kline_data(json).unwrap();
+        let payload: Payload = serde_json::from_str(json).unwrap();
+        let converted = KlineData::from(payload.to_serializable_kline_data().unwrap());
+        assert_eq!(direct.start_time, converted.start_time);
+        assert_eq!(direct.symbol, converted.symbol);
+        assert_eq!(direct.last_trade_id, 5067432892);
+        assert_eq!(direct.close, converted.close);
+        assert_eq!(direct.quote_volume, converted.quote_volume);
+
+        let invalid = json.replace("\"108473.03000000\"", "\"abc\"");
+        assert!(matches!(
+            parse_kline_data(&invalid),
+            Err(Error::Parse { .. })
+        ));
     }
 
     #[tokio::test]
@@ -689,4 +1641,74 @@ mod tests {
             .await
             .expect("Failed to listen to KlineStreaming");
     }
+
+    /// Serves `connections` WebSocket connections that each close right after
+    /// receiving their first request, returning the URL of the server.
+    async fn closing_server(connections: usize) -> (String, tokio::task::JoinHandle<()>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            for _ in 0..connections {
+                let (tcp, _) = listener.accept().await.unwrap();
+                let mut socket = tokio_tungstenite::accept_async(tcp).await.unwrap();
+                socket.next().await;
+                let _ = socket.close(None).await;
+            }
+        });
+        (url, server)
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_and_drop_after_server_close() {
+        let (url, server) = closing_server(2).await;
+        let (state, _) = BinanceWebSocketClient::connect_async(&url).await.unwrap();
+        let mut stream = KlineStreaming {
+            symbol: "BTCUSDT".to_string(),
+            interval: market::klines::KlineInterval::Minutes1,
+            state,
+            callbacks: Vec::new(),
+            stats: None,
+            generation: 0,
+            context: None,
+            url: Some(url),
+            exchange: BINANCE_EXCHANGE,
+            subscribed: false,
+            closed: false,
+        };
+
+        // The server closes the connection; reconnecting must not try to
+        // unsubscribe over it.
+        stream.subscribe().await.unwrap();
+        while let Ok(Some(_)) = stream.next().await {}
+        assert!(!stream.subscribed);
+        stream.close().await.unwrap();
+        stream.connect().await.unwrap();
+        assert_eq!(stream.generation, 1);
+
+        // Dropping a client whose connection the server closed must not panic.
+        stream.subscribe().await.unwrap();
+        while let Ok(Some(_)) = stream.next().await {}
+        drop(stream);
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_kline_streaming_close() {
+        let mut kline_streaming =
+            KlineStreaming::new("BTCUSDT", market::klines::KlineInterval::Minutes1)
+                .await
+                .expect("Failed to create KlineStreaming instance");
+        kline_streaming
+            .subscribe()
+            .await
+            .expect("Failed to subscribe to KlineStreaming");
+        assert!(matches!(kline_streaming.next().await, Ok(Some(_))));
+
+        kline_streaming
+            .close()
+            .await
+            .expect("Failed to close KlineStreaming");
+        assert!(matches!(kline_streaming.next().await, Ok(None)));
+        assert!(kline_streaming.close().await.is_ok());
+    }
 }
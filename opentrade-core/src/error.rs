@@ -0,0 +1,113 @@
+//! # Errors
+//!
+//! This module defines [`Error`], the error type of the exchange requests,
+//! backfills and WebSocket streams of this crate. Failures of the network, of the
+//! exchange, of parsing and of the database are returned as its variants instead
+//! of panicking, so a single bad response or lost connection fails one request or
+//! backfill rather than taking down the whole process.
+//!
+//! Errors of [`MarketDataSource`](crate::data_source::exchange::MarketDataSource)
+//! implementations, which use `anyhow`, are converted back into their typed
+//! variant when they wrap an [`Error`] or a [`RestError`].
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use opentrade_core::data_source::rest::{RestError, get_server_time};
+//! use opentrade_core::error::Error;
+//!
+//! # async fn example() {
+//! match get_server_time().await {
+//!     Ok(millis) => println!("Server time: {}", millis),
+//!     Err(Error::Api(RestError::Banned { message })) => eprintln!("Banned: {}", message),
+//!     Err(e) => eprintln!("Request failed: {}", e),
+//! }
+//! # }
+//! ```
+
+use std::fmt::Display;
+
+use crate::data_source::rest::RestError;
+
+/// A `Result` with the crate's [`Error`].
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Errors of exchange requests, backfills and WebSocket streams.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The exchange rejected a request or could not be reached.
+    #[error(transparent)]
+    Api(#[from] RestError),
+    /// An exchange response or message could not be parsed.
+    #[error("failed to parse {context}: {message}")]
+    Parse { context: String, message: String },
+    /// A database statement failed.
+    #[error("database error: {0}")]
+    Db(#[from] sqlx::Error),
+    /// A timestamp in milliseconds since the epoch is outside the supported range.
+    #[error("timestamp out of range: {0} ms")]
+    InvalidTimestamp(i64),
+    /// A tracked backfill job does not exist.
+    #[error("backfill job {0} does not exist")]
+    JobNotFound(i64),
+    /// Another error, e.g. of a market data source or a pipeline.
+    #[error("{0:#}")]
+    Other(anyhow::Error),
+}
+
+impl Error {
+    /// Creates a [`Error::Parse`].
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - What was parsed (e.g., "WebSocket message").
+    /// * `message` - Why parsing failed.
+    pub fn parse(context: &str, message: impl Display) -> Self {
+        Error::Parse {
+            context: context.to_string(),
+            message: message.to_string(),
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::parse("JSON", e)
+    }
+}
+
+impl From<anyhow::Error> for Error {
+    fn from(e: anyhow::Error) -> Self {
+        let e = match e.downcast::<Error>() {
+            Ok(e) => return e,
+            Err(e) => e,
+        };
+        match e.downcast::<RestError>() {
+            Ok(e) => Error::Api(e),
+            Err(e) => Error::Other(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_anyhow_errors_keep_their_type() {
+        let rest = anyhow::Error::from(RestError::Banned {
+            message: "Way too many requests".to_string(),
+        })
+        .context("Failed to fetch klines");
+        assert!(matches!(
+            Error::from(rest),
+            Error::Api(RestError::Banned { .. })
+        ));
+
+        let typed = anyhow::Error::from(Error::JobNotFound(42));
+        assert!(matches!(Error::from(typed), Error::JobNotFound(42)));
+
+        let other = Error::from(anyhow::anyhow!("unsupported interval"));
+        assert_eq!(other.to_string(), "unsupported interval");
+    }
+}
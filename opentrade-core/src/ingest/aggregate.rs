@@ -0,0 +1,562 @@
+//! # Kline Aggregation
+//!
+//! This module derives higher-timeframe candles from stored lower-timeframe data.
+//!
+//! ## Rolling Up 1m Candles
+//!
+//! [`aggregate_klines`] groups stored [`BASE_INTERVAL`] candles into the buckets of
+//! a longer [`Interval`] (e.g., `5m`, `1h`, `1d`) and returns the rolled-up candles
+//! without storing them. [`generate_candles`] stores them under the longer
+//! interval, and an [`Aggregator`] does so periodically for a set of targets,
+//! recomputing a trailing window so that candles still in progress are refreshed.
+//! Derived candles are stored under the dataset and exchange they were read from,
+//! with the [`SOURCE_KIND_AGGREGATE`] source kind.
+//!
+//! A bucket missing some of its 1m candles is rolled up from the candles that are
+//! stored; audit the 1m data (see [`crate::ingest::audit`]) before relying on the
+//! aggregated candles of a range.
+//!
+//! ## Calendar Candles
+//!
+//! Weekly and monthly candles cannot be produced by fixed-size bucketing because
+//! months differ in length and weeks are anchored to a weekday. They are aligned
+//! to calendar boundaries in UTC (see [`Interval::start_of`]), matching the
+//! exchange conventions:
+//! - `1w` candles cover ISO weeks, starting Monday 00:00 UTC
+//! - `1M` candles cover calendar months, starting on the 1st at 00:00 UTC
+//!
+//! [`generate_calendar_candles`] builds them from daily (`1d`) data with the
+//! usual OHLCV rules: the open is the first open, the close is the last close,
+//! the high and low are the extremes, and volumes and trade counts are summed.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use opentrade_core::ingest::aggregate::generate_calendar_candles;
+//! use opentrade_core::models::Interval;
+//! use chrono::{TimeZone, Utc};
+//! use sqlx::PgPool;
+//!
+//! # async fn example(pool: &PgPool) -> anyhow::Result<()> {
+//! let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+//! let end = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+//! let written = generate_calendar_candles(
+//!     pool,
+//!     "BTCUSDT",
+//!     Interval::Months1,
+//!     start,
+//!     end,
+//!     "default",
+//!     "binance",
+//! )
+//! .await?;
+//! println!("Wrote {} monthly candles", written);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! ```rust,no_run
+//! use opentrade_core::ingest::aggregate::Aggregator;
+//! use opentrade_core::models::Interval;
+//! use sqlx::PgPool;
+//!
+//! # async fn example(pool: &PgPool) -> anyhow::Result<()> {
+//! // Keep the hourly and daily candles of BTCUSDT up to date from its 1m candles.
+//! let aggregator = Aggregator::new(chrono::Duration::days(2))
+//!     .with_target("BTCUSDT", Interval::Hours1, "default", "binance")
+//!     .with_target("BTCUSDT", Interval::Days1, "default", "binance");
+//! let written = aggregator.run(pool).await?;
+//! println!("Wrote {} candles", written);
+//! # Ok(())
+//! # }
+//! ```
+
+use anyhow::{Result, bail};
+use chrono::{DateTime, Duration, Utc};
+
+use crate::models::{Interval, KlineData, SOURCE_KIND_AGGREGATE};
+
+/// The interval of the stored candles that [`aggregate_klines`] rolls up.
+pub const BASE_INTERVAL: Interval = Interval::Minutes1;
+
+/// Combines consecutive candles into a single candle with the given interval label.
+///
+/// The resulting candle spans from the start of the first candle to the end of the
+/// last one. Returns `None` if `klines` is empty.
+pub fn merge_klines(klines: &[KlineData], interval: &str) -> Option<KlineData> {
+    let first = klines.first()?;
+    let last = klines.last()?;
+
+    let mut merged = first.clone();
+    merged.interval = interval.to_string();
+    merged.end_time = last.end_time;
+    merged.last_trade_id = last.last_trade_id;
+    merged.close = last.close.clone();
+    merged.created_at = None;
+    merged.update_at = None;
+
+    for kline in &klines[1..] {
+        if kline.high > merged.high {
+            merged.high = kline.high.clone();
+        }
+        if kline.low < merged.low {
+            merged.low = kline.low.clone();
+        }
+        merged.volume = &merged.volume + &kline.volume;
+        merged.trade_count = match (merged.trade_count, kline.trade_count) {
+            (Some(a), Some(b)) => Some(a + b),
+            (a, b) => a.or(b),
+        };
+        merged.quote_volume = match (merged.quote_volume.take(), &kline.quote_volume) {
+            (Some(a), Some(b)) => Some(&a + b),
+            (a, b) => a.or_else(|| b.clone()),
+        };
+    }
+    Some(merged)
+}
+
+/// Generates weekly or monthly candles for a symbol from stored daily candles and
+/// upserts them under the interval's label.
+///
+/// The range is widened to whole weeks or months, so partially covered periods at
+/// either end are recomputed completely.
+///
+/// # Arguments
+///
+/// * `pool` - The database connection pool.
+/// * `symbol` - The trading symbol (e.g., "BTCUSDT").
+/// * `interval` - The calendar interval to generate, [`Interval::Weeks1`] or
+///   [`Interval::Months1`].
+/// * `start_time` - The start of the range to generate.
+/// * `end_time` - The end of the range to generate.
+/// * `dataset` - The dataset to read daily candles from and write aggregated candles to.
+/// * `exchange` - The exchange whose daily candles are aggregated (e.g., "binance").
+///
+/// # Errors
+///
+/// Returns an error if `interval` is not a calendar interval (see
+/// [`Interval::is_calendar`]), or if reading or writing candles fails.
+///
+/// # Returns
+///
+/// The number of aggregated candles written.
+pub async fn generate_calendar_candles(
+    pool: &sqlx::PgPool,
+    symbol: &str,
+    interval: Interval,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    dataset: &str,
+    exchange: &str,
+) -> Result<usize> {
+    if !interval.is_calendar() {
+        bail!("{} is not a calendar interval", interval);
+    }
+    let (range_start, range_end) = bucket_range(interval, start_time, end_time);
+    let daily = KlineData::list_range(
+        pool,
+        symbol,
+        Interval::Days1.as_str(),
+        range_start,
+        range_end,
+        dataset,
+        exchange,
+    )
+    .await?;
+
+    let candles = derive_candles(&daily, interval, dataset, exchange);
+    for candle in &candles {
+        candle.upsert(pool).await?;
+    }
+    log::info!(
+        "Generated {} {} candles for symbol {} from {} to {}",
+        candles.len(),
+        interval,
+        symbol,
+        range_start,
+        range_end
+    );
+    Ok(candles.len())
+}
+
+/// Rolls up candles sorted by start time into candles of a longer interval.
+///
+/// Each output candle starts at the start of its bucket (see [`Interval::start_of`])
+/// and ends with the last input candle of the bucket, which is one millisecond
+/// before the next bucket starts once the bucket is complete.
+pub fn roll_up(klines: &[KlineData], interval: Interval) -> Vec<KlineData> {
+    let mut candles = Vec::new();
+    let mut group_start = 0;
+
+    while group_start < klines.len() {
+        let bucket = interval.start_of(klines[group_start].start_time);
+        let next_bucket = interval.next_start(bucket);
+        let group_end = klines[group_start..]
+            .iter()
+            .position(|kline| kline.start_time >= next_bucket)
+            .map_or(klines.len(), |offset| group_start + offset);
+
+        let group = &klines[group_start..group_end];
+        if let Some(mut candle) = merge_klines(group, interval.as_str()) {
+            candle.start_time = bucket;
+            candles.push(candle);
+        }
+        group_start = group_end;
+    }
+    candles
+}
+
+/// Rolls up `klines` into candles of `interval` stored under `dataset` and
+/// `exchange` with the [`SOURCE_KIND_AGGREGATE`] source kind, instead of the labels
+/// of the first candle of each bucket.
+fn derive_candles(
+    klines: &[KlineData],
+    interval: Interval,
+    dataset: &str,
+    exchange: &str,
+) -> Vec<KlineData> {
+    roll_up(klines, interval)
+        .into_iter()
+        .map(|candle| {
+            candle
+                .with_dataset(dataset)
+                .with_exchange(exchange)
+                .with_source_kind(SOURCE_KIND_AGGREGATE)
+        })
+        .collect()
+}
+
+/// Returns the range of whole buckets of `interval` covering `[start_time, end_time)`.
+fn bucket_range(
+    interval: Interval,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+) -> (DateTime<Utc>, DateTime<Utc>) {
+    let range_start = interval.start_of(start_time);
+    let last_bucket = interval.start_of(end_time);
+    let range_end = if last_bucket == end_time {
+        end_time
+    } else {
+        interval.next_start(last_bucket)
+    };
+    (range_start, range_end)
+}
+
+/// Derives the candles of a longer interval from the stored [`BASE_INTERVAL`]
+/// candles of a symbol, without storing them.
+///
+/// The range is widened to whole buckets, so the candles at either end cover
+/// their whole bucket.
+///
+/// # Arguments
+///
+/// * `pool` - The database connection pool.
+/// * `symbol` - The trading symbol (e.g., "BTCUSDT").
+/// * `interval` - The interval to derive, longer than [`BASE_INTERVAL`].
+/// * `start_time` - The start of the range.
+/// * `end_time` - The end of the range.
+/// * `dataset` - The dataset to read 1m candles from.
+/// * `exchange` - The exchange whose 1m candles are rolled up (e.g., "binance").
+pub async fn aggregate_klines(
+    pool: &sqlx::PgPool,
+    symbol: &str,
+    interval: Interval,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    dataset: &str,
+    exchange: &str,
+) -> Result<Vec<KlineData>, sqlx::Error> {
+    let (range_start, range_end) = bucket_range(interval, start_time, end_time);
+    let base = KlineData::list_range(
+        pool,
+        symbol,
+        BASE_INTERVAL.as_str(),
+        range_start,
+        range_end,
+        dataset,
+        exchange,
+    )
+    .await?;
+    Ok(derive_candles(&base, interval, dataset, exchange))
+}
+
+/// Derives the candles of a longer interval from the stored [`BASE_INTERVAL`]
+/// candles of a symbol and upserts them under the interval's label.
+///
+/// # Returns
+///
+/// The number of aggregated candles written.
+pub async fn generate_candles(
+    pool: &sqlx::PgPool,
+    symbol: &str,
+    interval: Interval,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    dataset: &str,
+    exchange: &str,
+) -> Result<usize, sqlx::Error> {
+    let candles = aggregate_klines(
+        pool, symbol, interval, start_time, end_time, dataset, exchange,
+    )
+    .await?;
+    for candle in &candles {
+        candle.upsert(pool).await?;
+    }
+    log::info!(
+        "Generated {} {} candles for symbol {} from {} to {}",
+        candles.len(),
+        interval,
+        symbol,
+        start_time,
+        end_time
+    );
+    Ok(candles.len())
+}
+
+/// A symbol, interval, dataset and exchange kept up to date by an [`Aggregator`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AggregationTarget {
+    /// The trading symbol.
+    pub symbol: String,
+    /// The interval derived from the 1m candles.
+    pub interval: Interval,
+    /// The dataset to read 1m candles from and write aggregated candles to.
+    pub dataset: String,
+    /// The exchange whose 1m candles are rolled up.
+    pub exchange: String,
+}
+
+/// Periodically rolls up the latest 1m candles of its targets into stored
+/// higher-timeframe candles. See the [module documentation](self).
+pub struct Aggregator {
+    targets: Vec<AggregationTarget>,
+    lookback: Duration,
+}
+
+impl Aggregator {
+    /// Creates an aggregator without targets.
+    ///
+    /// # Arguments
+    ///
+    /// * `lookback` - How far back each run recomputes candles, which should cover
+    ///   the time since the previous run and any late 1m candles
+    pub fn new(lookback: Duration) -> Self {
+        Self {
+            targets: Vec::new(),
+            lookback,
+        }
+    }
+
+    /// Adds a target to aggregate.
+    pub fn with_target(
+        mut self,
+        symbol: &str,
+        interval: Interval,
+        dataset: &str,
+        exchange: &str,
+    ) -> Self {
+        self.targets.push(AggregationTarget {
+            symbol: symbol.to_string(),
+            interval,
+            dataset: dataset.to_string(),
+            exchange: exchange.to_string(),
+        });
+        self
+    }
+
+    /// Recomputes the candles of every target over the lookback window ending now.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a target's interval is not longer than
+    /// [`BASE_INTERVAL`], or if reading or writing candles fails.
+    ///
+    /// # Returns
+    ///
+    /// The number of aggregated candles written.
+    pub async fn run(&self, pool: &sqlx::PgPool) -> Result<usize> {
+        let end_time = Utc::now();
+        self.run_range(pool, end_time - self.lookback, end_time)
+            .await
+    }
+
+    /// Recomputes the candles of every target over `[start_time, end_time)`, e.g.
+    /// to derive the history of a newly added target.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a target's interval is not longer than
+    /// [`BASE_INTERVAL`], or if reading or writing candles fails.
+    pub async fn run_range(
+        &self,
+        pool: &sqlx::PgPool,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<usize> {
+        let mut written = 0;
+        for target in &self.targets {
+            if target.interval <= BASE_INTERVAL {
+                bail!(
+                    "Cannot derive {} candles from {} candles",
+                    target.interval,
+                    BASE_INTERVAL
+                );
+            }
+            written += generate_candles(
+                pool,
+                &target.symbol,
+                target.interval,
+                start_time,
+                end_time,
+                &target.dataset,
+                &target.exchange,
+            )
+            .await?;
+        }
+        Ok(written)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use sqlx::types::BigDecimal;
+    use std::str::FromStr;
+
+    fn day(year: i32, month: u32, day: u32, open: &str, close: &str) -> KlineData {
+        let start = Utc.with_ymd_and_hms(year, month, day, 0, 0, 0).unwrap();
+        let end = start + Duration::days(1) - Duration::milliseconds(1);
+        KlineData::new(
+            &(start.timestamp_millis() as u64),
+            &(end.timestamp_millis() as u64),
+            "BTCUSDT",
+            "1d",
+            1,
+            2,
+            BigDecimal::from_str(open).unwrap(),
+            BigDecimal::from_str(open)
+                .unwrap()
+                .max(BigDecimal::from_str(close).unwrap()),
+            BigDecimal::from_str(open)
+                .unwrap()
+                .min(BigDecimal::from_str(close).unwrap()),
+            BigDecimal::from_str(close).unwrap(),
+            BigDecimal::from_str("1").unwrap(),
+            Some(10),
+            Some(BigDecimal::from_str("100").unwrap()),
+        )
+    }
+
+    #[test]
+    fn test_aggregate_monthly_splits_on_month_boundary() {
+        let klines = vec![
+            day(2024, 1, 30, "10", "12"),
+            day(2024, 1, 31, "12", "8"),
+            day(2024, 2, 1, "8", "20"),
+        ];
+        let candles = roll_up(&klines, Interval::Months1);
+        assert_eq!(candles.len(), 2);
+
+        let january = &candles[0];
+        assert_eq!(january.interval, "1M");
+        assert_eq!(
+            january.start_time,
+            Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap()
+        );
+        assert_eq!(
+            january.end_time,
+            Utc.with_ymd_and_hms(2024, 2, 1, 0, 0, 0).unwrap() - Duration::milliseconds(1)
+        );
+        assert_eq!(january.open, BigDecimal::from_str("10").unwrap());
+        assert_eq!(january.close, BigDecimal::from_str("8").unwrap());
+        assert_eq!(january.high, BigDecimal::from_str("12").unwrap());
+        assert_eq!(january.low, BigDecimal::from_str("8").unwrap());
+        assert_eq!(january.volume, BigDecimal::from_str("2").unwrap());
+        assert_eq!(january.trade_count, Some(20));
+
+        // February is still in progress, so it ends with its last daily candle.
+        let february = &candles[1];
+        assert_eq!(february.end_time, klines[2].end_time);
+    }
+
+    fn minute(minute: i64, open: &str, close: &str) -> KlineData {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap() + Duration::minutes(minute);
+        let mut kline = day(2024, 1, 1, open, close);
+        kline.interval = "1m".to_string();
+        kline.start_time = start;
+        kline.end_time = start + Duration::minutes(1) - Duration::milliseconds(1);
+        kline
+    }
+
+    #[test]
+    fn test_roll_up_minutes_into_buckets() {
+        let klines = vec![
+            minute(0, "10", "11"),
+            minute(3, "11", "15"),
+            minute(4, "15", "9"),
+            minute(5, "9", "10"),
+        ];
+        let candles = roll_up(&klines, Interval::Minutes5);
+        assert_eq!(candles.len(), 2);
+
+        let first = &candles[0];
+        assert_eq!(first.interval, "5m");
+        assert_eq!(first.start_time, klines[0].start_time);
+        assert_eq!(first.end_time, klines[2].end_time);
+        assert_eq!(first.open, BigDecimal::from_str("10").unwrap());
+        assert_eq!(first.high, BigDecimal::from_str("15").unwrap());
+        assert_eq!(first.low, BigDecimal::from_str("9").unwrap());
+        assert_eq!(first.close, BigDecimal::from_str("9").unwrap());
+        assert_eq!(first.volume, BigDecimal::from_str("3").unwrap());
+        assert_eq!(first.trade_count, Some(30));
+        assert_eq!(candles[1].start_time, klines[3].start_time);
+    }
+
+    #[test]
+    fn test_bucket_range_widens_to_whole_buckets() {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        assert_eq!(
+            bucket_range(
+                Interval::Hours1,
+                start + Duration::minutes(10),
+                start + Duration::minutes(70)
+            ),
+            (start, start + Duration::hours(2))
+        );
+        assert_eq!(
+            bucket_range(Interval::Hours1, start, start + Duration::hours(1)),
+            (start, start + Duration::hours(1))
+        );
+    }
+
+    #[test]
+    fn test_aggregate_weekly() {
+        // 2024-01-07 is a Sunday, 2024-01-08 a Monday.
+        let klines = vec![day(2024, 1, 7, "1", "2"), day(2024, 1, 8, "2", "3")];
+        let candles = roll_up(&klines, Interval::Weeks1);
+        assert_eq!(candles.len(), 2);
+        assert_eq!(
+            candles[0].start_time,
+            Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap()
+        );
+        assert_eq!(
+            candles[1].start_time,
+            Utc.with_ymd_and_hms(2024, 1, 8, 0, 0, 0).unwrap()
+        );
+        assert_eq!(candles[1].interval, "1w");
+    }
+
+    #[test]
+    fn test_derived_candles_are_labeled_as_aggregates() {
+        let klines = vec![
+            minute(0, "10", "11").with_exchange("okx"),
+            minute(1, "11", "12"),
+        ];
+        let candles = derive_candles(&klines, Interval::Minutes5, "research", "bybit");
+        assert_eq!(candles.len(), 1);
+        assert_eq!(candles[0].dataset, "research");
+        assert_eq!(candles[0].exchange, "bybit");
+        assert_eq!(candles[0].source_kind, SOURCE_KIND_AGGREGATE);
+    }
+}
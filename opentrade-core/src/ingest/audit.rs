@@ -0,0 +1,537 @@
+//! # Data Completeness Audit
+//!
+//! This module checks stored Kline data for missing candles and produces
+//! [`GapReport`]s that can be logged or exported as structured artifacts
+//! (JSON or CSV) for data-quality dashboards. It also computes
+//! [`RangeChecksum`]s, deterministic fingerprints of stored candles that let two
+//! databases (e.g., prod and DR) be compared cheaply for divergence.
+//!
+//! ## Gap Semantics
+//!
+//! For a `[start, end)` range and an interval, every interval-aligned start time
+//! in the range is expected to have a stored candle. Consecutive missing start
+//! times are reported as a single [`Gap`] covering `[start, end)`.
+//!
+//! [`gap_report`] reports every missing candle. [`missing_gaps`] additionally
+//! leaves out the ranges the exchange reported as empty (see
+//! [`ExchangeGap`]), so what remains are the candles that backfill or streaming
+//! dropped and that re-fetching can repair.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use opentrade_core::ingest::audit::{gap_report, write_json_artifact};
+//! use chrono::{TimeZone, Utc};
+//! use sqlx::PgPool;
+//!
+//! # async fn example(pool: &PgPool) -> anyhow::Result<()> {
+//! let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+//! let end = Utc.with_ymd_and_hms(2024, 2, 1, 0, 0, 0).unwrap();
+//! let report = gap_report(pool, "BTCUSDT", "1m", start, end, "default", "binance").await?;
+//! println!("{} candles missing", report.missing);
+//!
+//! write_json_artifact("gaps.json", &[report])?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt::Write as _;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+use crate::models::exchange_gap::ExchangeGap;
+use crate::models::{Interval, KlineData};
+use crate::time;
+
+/// A run of consecutive missing candles.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Gap {
+    /// Start time of the first missing candle.
+    pub start: DateTime<Utc>,
+    /// Start time of the first candle after the gap (exclusive end).
+    pub end: DateTime<Utc>,
+    /// Number of missing candles in the gap.
+    pub missing: i64,
+}
+
+/// Completeness findings for one symbol and interval over a time range.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GapReport {
+    /// The trading symbol.
+    pub symbol: String,
+    /// The Kline interval.
+    pub interval: String,
+    /// The inclusive start of the audited range.
+    pub range_start: DateTime<Utc>,
+    /// The exclusive end of the audited range.
+    pub range_end: DateTime<Utc>,
+    /// Number of candles expected in the range.
+    pub expected: i64,
+    /// Number of candles found in the range.
+    pub found: i64,
+    /// Number of candles missing from the range.
+    pub missing: i64,
+    /// The missing ranges, in chronological order.
+    pub gaps: Vec<Gap>,
+}
+
+impl GapReport {
+    /// Returns the report without the candles starting within any of the
+    /// `excluded` ranges, e.g. exchange maintenance windows (see
+    /// [`MaintenanceCalendar::ranges`](super::maintenance::MaintenanceCalendar::ranges)).
+    /// Excluded candles are neither expected nor missing.
+    ///
+    /// Reports of calendar intervals are returned unchanged.
+    pub fn excluding(&self, excluded: &[(DateTime<Utc>, DateTime<Utc>)]) -> GapReport {
+        let Some(step) = interval_duration(&self.interval) else {
+            return self.clone();
+        };
+        let gaps = exclude_ranges(&self.gaps, excluded, step);
+        let missing: i64 = gaps.iter().map(|gap| gap.missing).sum();
+        GapReport {
+            expected: self.expected - (self.missing - missing),
+            missing,
+            gaps,
+            ..self.clone()
+        }
+    }
+}
+
+/// Returns the length of a fixed-size Kline interval, or `None` for calendar
+/// intervals (`1w`, `1M`) and unknown labels.
+pub fn interval_duration(interval: &str) -> Option<Duration> {
+    let interval = interval.parse::<Interval>().ok()?;
+    if interval.is_calendar() {
+        return None;
+    }
+    interval.duration()
+}
+
+/// Finds the gaps in a sorted list of candle start times.
+///
+/// Expected start times are aligned to multiples of `step` since the Unix epoch,
+/// beginning with the first aligned time at or after `range_start`. Start times
+/// outside the range or not aligned to `step` are ignored.
+pub fn find_gaps(
+    start_times: &[DateTime<Utc>],
+    step: Duration,
+    range_start: DateTime<Utc>,
+    range_end: DateTime<Utc>,
+) -> Vec<Gap> {
+    let mut gaps: Vec<Gap> = Vec::new();
+    let mut present = start_times.iter().peekable();
+    for expected in time::buckets(range_start, range_end, step) {
+        while present.next_if(|time| **time < expected).is_some() {}
+        if present.next_if(|time| **time == expected).is_none() {
+            match gaps.last_mut() {
+                Some(gap) if gap.end == expected => {
+                    gap.end = expected + step;
+                    gap.missing += 1;
+                }
+                _ => gaps.push(Gap {
+                    start: expected,
+                    end: expected + step,
+                    missing: 1,
+                }),
+            }
+        }
+    }
+    gaps
+}
+
+/// Audits stored candles of one exchange for a symbol and interval in a dataset
+/// over `[range_start, range_end)`.
+///
+/// # Errors
+///
+/// Returns an error if the interval is not a fixed-size interval or the query fails.
+pub async fn gap_report(
+    pool: &sqlx::PgPool,
+    symbol: &str,
+    interval: &str,
+    range_start: DateTime<Utc>,
+    range_end: DateTime<Utc>,
+    dataset: &str,
+    exchange: &str,
+) -> Result<GapReport> {
+    let step = interval_duration(interval)
+        .with_context(|| format!("Unsupported interval for gap detection: {}", interval))?;
+    let start_times = KlineData::list_start_times(
+        pool,
+        symbol,
+        interval,
+        range_start,
+        range_end,
+        dataset,
+        exchange,
+    )
+    .await?;
+
+    let gaps = find_gaps(&start_times, step, range_start, range_end);
+    let missing: i64 = gaps.iter().map(|gap| gap.missing).sum();
+    let found = start_times.len() as i64;
+    Ok(GapReport {
+        symbol: symbol.to_string(),
+        interval: interval.to_string(),
+        range_start,
+        range_end,
+        expected: found + missing,
+        found,
+        missing,
+        gaps,
+    })
+}
+
+/// Removes the candles starting within any of the `excluded` ranges from `gaps`,
+/// splitting gaps where needed.
+///
+/// # Arguments
+///
+/// * `gaps` - Gaps found by [`find_gaps`] with the same `step`.
+/// * `excluded` - `[start, end)` ranges whose candles are not considered missing.
+/// * `step` - The length of the interval.
+pub fn exclude_ranges(
+    gaps: &[Gap],
+    excluded: &[(DateTime<Utc>, DateTime<Utc>)],
+    step: Duration,
+) -> Vec<Gap> {
+    let step_ms = step.num_milliseconds();
+    let mut remaining = gaps.to_vec();
+    for &(start, end) in excluded {
+        let (start, end) = (time::align_up(start, step), time::align_up(end, step));
+        if start >= end {
+            continue;
+        }
+        remaining = remaining
+            .into_iter()
+            .flat_map(|gap| {
+                [
+                    (gap.start, gap.end.min(start)),
+                    (gap.start.max(end), gap.end),
+                ]
+                .into_iter()
+                .filter(|(piece_start, piece_end)| piece_start < piece_end)
+                .map(|(piece_start, piece_end)| Gap {
+                    start: piece_start,
+                    end: piece_end,
+                    missing: (piece_end - piece_start).num_milliseconds() / step_ms,
+                })
+                .collect::<Vec<_>>()
+            })
+            .collect();
+    }
+    remaining
+}
+
+/// Finds the candles of a symbol and interval in a dataset that are missing
+/// from `[range_start, range_end)` although the exchange did not report their
+/// range as empty.
+///
+/// These are the gaps left by failed backfills or dropped stream messages.
+///
+/// # Arguments
+///
+/// * `pool` - The database connection pool.
+/// * `symbol` - The trading symbol.
+/// * `interval` - The Kline interval.
+/// * `range_start` - The inclusive start of the range.
+/// * `range_end` - The exclusive end of the range.
+/// * `dataset` - The dataset label.
+/// * `exchange` - The exchange the candles were captured from (e.g., "binance").
+///
+/// # Errors
+///
+/// Returns an error if the interval is not a fixed-size interval or a query fails.
+pub async fn missing_gaps(
+    pool: &sqlx::PgPool,
+    symbol: &str,
+    interval: &str,
+    range_start: DateTime<Utc>,
+    range_end: DateTime<Utc>,
+    dataset: &str,
+    exchange: &str,
+) -> Result<Vec<Gap>> {
+    let step = interval_duration(interval)
+        .with_context(|| format!("Unsupported interval for gap detection: {}", interval))?;
+    let start_times = KlineData::list_start_times(
+        pool,
+        symbol,
+        interval,
+        range_start,
+        range_end,
+        dataset,
+        exchange,
+    )
+    .await?;
+    let gaps = find_gaps(&start_times, step, range_start, range_end);
+    if gaps.is_empty() {
+        return Ok(gaps);
+    }
+    let exchange_gaps = ExchangeGap::list_range(
+        pool,
+        symbol,
+        interval,
+        dataset,
+        exchange,
+        range_start,
+        range_end,
+    )
+    .await?;
+    let excluded: Vec<(DateTime<Utc>, DateTime<Utc>)> = exchange_gaps
+        .iter()
+        .map(|gap| (gap.start_time, gap.end_time))
+        .collect();
+    Ok(exclude_ranges(&gaps, &excluded, step))
+}
+
+/// A deterministic fingerprint of the candles stored for a symbol, interval and range.
+///
+/// Two databases holding identical candles produce identical checksums, regardless
+/// of when or in which order the rows were written.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RangeChecksum {
+    /// Number of candles in the range.
+    pub rows: i64,
+    /// MD5 digest (hex) over the candles in start-time order.
+    pub checksum: String,
+}
+
+/// Computes the [`RangeChecksum`] of stored candles over `[range_start, range_end)`.
+///
+/// The digest covers the candle times, trade ids, prices, volumes and trade count,
+/// but not the `created_at`/`update_at` bookkeeping columns, which naturally
+/// differ between environments.
+///
+/// # Arguments
+///
+/// * `pool` - The database connection pool.
+/// * `symbol` - The trading symbol.
+/// * `interval` - The Kline interval.
+/// * `range_start` - The inclusive start of the range.
+/// * `range_end` - The exclusive end of the range.
+/// * `dataset` - The dataset label.
+/// * `exchange` - The exchange the candles were captured from (e.g., "binance").
+pub async fn range_checksum(
+    pool: &sqlx::PgPool,
+    symbol: &str,
+    interval: &str,
+    range_start: DateTime<Utc>,
+    range_end: DateTime<Utc>,
+    dataset: &str,
+    exchange: &str,
+) -> Result<RangeChecksum, sqlx::Error> {
+    let checksum = sqlx::query_as!(
+        RangeChecksum,
+        r#"
+        SELECT
+            COUNT(*) AS "rows!",
+            md5(COALESCE(string_agg(
+                concat_ws(',',
+                    (EXTRACT(EPOCH FROM start_time) * 1000)::BIGINT,
+                    (EXTRACT(EPOCH FROM end_time) * 1000)::BIGINT,
+                    first_trade_id, last_trade_id, open, high, low, close, volume,
+                    trade_count, quote_volume
+                ),
+                ';' ORDER BY start_time
+            ), '')) AS "checksum!"
+        FROM kline_data
+        WHERE symbol = $1 AND interval = $2 AND start_time >= $3 AND start_time < $4
+          AND dataset = $5 AND exchange = $6
+        "#,
+        symbol,
+        interval,
+        range_start,
+        range_end,
+        dataset,
+        exchange
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(checksum)
+}
+
+/// The top-level structure of a JSON gap artifact.
+#[derive(Serialize)]
+struct GapArtifact<'a> {
+    generated_at: DateTime<Utc>,
+    reports: &'a [GapReport],
+}
+
+/// Writes gap reports as a JSON artifact with a generation timestamp.
+pub fn write_json_artifact(path: impl AsRef<Path>, reports: &[GapReport]) -> Result<()> {
+    let artifact = GapArtifact {
+        generated_at: Utc::now(),
+        reports,
+    };
+    let json = serde_json::to_string_pretty(&artifact)?;
+    std::fs::write(path.as_ref(), json)
+        .with_context(|| format!("Failed to write {}", path.as_ref().display()))
+}
+
+/// Renders gap reports as CSV with one row per gap.
+///
+/// Symbols without gaps get a single row with empty gap columns so that every
+/// audited symbol appears in the artifact.
+pub fn to_csv(reports: &[GapReport]) -> String {
+    let mut csv = String::from(
+        "symbol,interval,range_start,range_end,expected,found,missing,gap_start,gap_end,gap_missing\n",
+    );
+    for report in reports {
+        let prefix = format!(
+            "{},{},{},{},{},{},{}",
+            report.symbol,
+            report.interval,
+            report.range_start.to_rfc3339(),
+            report.range_end.to_rfc3339(),
+            report.expected,
+            report.found,
+            report.missing
+        );
+        if report.gaps.is_empty() {
+            let _ = writeln!(csv, "{},,,", prefix);
+        }
+        for gap in &report.gaps {
+            let _ = writeln!(
+                csv,
+                "{},{},{},{}",
+                prefix,
+                gap.start.to_rfc3339(),
+                gap.end.to_rfc3339(),
+                gap.missing
+            );
+        }
+    }
+    csv
+}
+
+/// Writes gap reports as a CSV artifact. See [`to_csv`] for the layout.
+pub fn write_csv_artifact(path: impl AsRef<Path>, reports: &[GapReport]) -> Result<()> {
+    std::fs::write(path.as_ref(), to_csv(reports))
+        .with_context(|| format!("Failed to write {}", path.as_ref().display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn minute(m: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 1, 0, m, 0).unwrap()
+    }
+
+    #[test]
+    fn test_find_gaps_merges_consecutive_missing_candles() {
+        let present = vec![minute(0), minute(1), minute(4), minute(6)];
+        let gaps = find_gaps(&present, Duration::minutes(1), minute(0), minute(8));
+        assert_eq!(
+            gaps,
+            vec![
+                Gap {
+                    start: minute(2),
+                    end: minute(4),
+                    missing: 2
+                },
+                Gap {
+                    start: minute(5),
+                    end: minute(6),
+                    missing: 1
+                },
+                Gap {
+                    start: minute(7),
+                    end: minute(8),
+                    missing: 1
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_find_gaps_aligns_range_start() {
+        let start = minute(0) + Duration::seconds(30);
+        let gaps = find_gaps(&[minute(1)], Duration::minutes(1), start, minute(2));
+        assert!(gaps.is_empty());
+    }
+
+    #[test]
+    fn test_exclude_ranges_splits_gaps() {
+        let gaps = vec![
+            Gap {
+                start: minute(2),
+                end: minute(8),
+                missing: 6,
+            },
+            Gap {
+                start: minute(10),
+                end: minute(11),
+                missing: 1,
+            },
+        ];
+        // Unaligned bounds exclude the candles starting at 4 and 5 only.
+        let excluded = vec![
+            (
+                minute(3) + Duration::seconds(1),
+                minute(5) + Duration::seconds(1),
+            ),
+            (minute(9), minute(12)),
+        ];
+        assert_eq!(
+            exclude_ranges(&gaps, &excluded, Duration::minutes(1)),
+            vec![
+                Gap {
+                    start: minute(2),
+                    end: minute(4),
+                    missing: 2
+                },
+                Gap {
+                    start: minute(6),
+                    end: minute(8),
+                    missing: 2
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_report_excluding_ranges_reduces_expectations() {
+        let report = GapReport {
+            symbol: "BTCUSDT".to_string(),
+            interval: "1m".to_string(),
+            range_start: minute(0),
+            range_end: minute(10),
+            expected: 10,
+            found: 4,
+            missing: 6,
+            gaps: vec![Gap {
+                start: minute(4),
+                end: minute(10),
+                missing: 6,
+            }],
+        };
+        let excluded = report.excluding(&[(minute(3), minute(8))]);
+        assert_eq!(excluded.expected, 6);
+        assert_eq!(excluded.missing, 2);
+        assert_eq!(excluded.gaps[0].start, minute(8));
+    }
+
+    #[test]
+    fn test_to_csv_includes_symbols_without_gaps() {
+        let report = GapReport {
+            symbol: "BTCUSDT".to_string(),
+            interval: "1m".to_string(),
+            range_start: minute(0),
+            range_end: minute(2),
+            expected: 2,
+            found: 2,
+            missing: 0,
+            gaps: Vec::new(),
+        };
+        let csv = to_csv(&[report]);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].starts_with("BTCUSDT,1m,"));
+        assert!(lines[1].ends_with(",2,2,0,,,"));
+    }
+}
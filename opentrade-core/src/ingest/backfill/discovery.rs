@@ -0,0 +1,125 @@
+use std::future::Future;
+
+use anyhow::{Context, Result};
+
+use crate::data_source::exchange::MarketDataSource;
+
+/// The precision of the binary search in milliseconds (one day).
+const SEARCH_RESOLUTION_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// Binary-searches `[lower, upper]` for the earliest time at which `probe` holds.
+///
+/// `probe(t)` must be monotonic: once it returns true for some `t`, it returns
+/// true for every later time. The search stops once the candidate range is
+/// narrower than `resolution`, so the result may be up to `resolution` after the
+/// exact boundary.
+///
+/// # Returns
+///
+/// The earliest time found, or `None` if `probe(upper)` is false.
+pub async fn find_earliest<F, Fut>(
+    lower: u64,
+    upper: u64,
+    resolution: u64,
+    mut probe: F,
+) -> Result<Option<u64>>
+where
+    F: FnMut(u64) -> Fut,
+    Fut: Future<Output = Result<bool>>,
+{
+    if lower > upper || !probe(upper).await? {
+        return Ok(None);
+    }
+    let (mut low, mut high) = (lower, upper);
+    while high - low > resolution.max(1) {
+        let mid = low + (high - low) / 2;
+        if probe(mid).await? {
+            high = mid;
+        } else {
+            low = mid;
+        }
+    }
+    if probe(low).await? {
+        return Ok(Some(low));
+    }
+    Ok(Some(high))
+}
+
+/// Discovers the open time of the first candle the exchange has for a symbol.
+///
+/// Probes the klines endpoint with binary search over `[lower, upper]` so that
+/// full-history backfills can start at the listing date instead of issuing
+/// thousands of empty requests before it.
+///
+/// # Arguments
+///
+/// * `source` - The exchange to probe.
+/// * `symbol` - The trading symbol (e.g., "BTCUSDT").
+/// * `interval` - The kline interval (e.g., "1d").
+/// * `lower` - The earliest time to consider, in milliseconds since the epoch.
+/// * `upper` - The latest time to consider, in milliseconds since the epoch.
+///
+/// # Returns
+///
+/// The open time of the first available candle in milliseconds since the epoch,
+/// or `None` if the exchange has no candles in the range.
+pub async fn discover_earliest_kline_time(
+    source: &dyn MarketDataSource,
+    symbol: &str,
+    interval: &str,
+    lower: u64,
+    upper: u64,
+) -> Result<Option<u64>> {
+    let first_candle = |end_time: u64| async move {
+        let klines = source
+            .fetch_klines(symbol, interval, lower, Some(end_time), Some(1))
+            .await
+            .context("Failed to get kline data")?;
+        Ok::<_, anyhow::Error>(
+            klines
+                .first()
+                .map(|kline| kline.start_time.timestamp_millis() as u64),
+        )
+    };
+
+    let Some(end_time) = find_earliest(lower, upper, SEARCH_RESOLUTION_MS, |time| async move {
+        Ok(first_candle(time).await?.is_some())
+    })
+    .await?
+    else {
+        return Ok(None);
+    };
+    let earliest = first_candle(end_time).await?;
+    if let Some(earliest) = earliest {
+        log::info!("Earliest available {} kline: {}", symbol, earliest);
+    }
+    Ok(earliest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_find_earliest_converges_within_resolution() {
+        let listing = 1_500_000_123;
+        let mut probes = 0;
+        let found = find_earliest(0, 2_000_000_000, 1000, |time| {
+            probes += 1;
+            async move { Ok(time >= listing) }
+        })
+        .await
+        .unwrap()
+        .unwrap();
+        assert!(found >= listing && found - listing <= 1000);
+        assert!(probes < 40);
+    }
+
+    #[tokio::test]
+    async fn test_find_earliest_returns_none_without_data() {
+        let found = find_earliest(0, 1000, 1, |_| async { Ok(false) })
+            .await
+            .unwrap();
+        assert_eq!(found, None);
+    }
+}
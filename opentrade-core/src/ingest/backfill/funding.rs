@@ -0,0 +1,110 @@
+use crate::data_source::rest::{MAX_FUNDING_RATE_LIMIT, get_funding_rates};
+use crate::error::{Error, Result};
+use crate::models::funding::FundingRate;
+
+/// Backfills the funding rates of a Binance USDⓈ-M perpetual contract into the
+/// `funding_rates` table.
+///
+/// The history is fetched in pages of [`MAX_FUNDING_RATE_LIMIT`] rates, oldest
+/// first, until `end_time` or the latest settlement is reached. Rates that are
+/// already stored are replaced. Each page is written in one transaction.
+///
+/// # Arguments
+///
+/// * `pool` - The database connection pool.
+/// * `symbol` - The contract symbol (e.g., "BTCUSDT").
+/// * `start_time` - The start time in milliseconds since the epoch.
+/// * `end_time` - An optional end time in milliseconds since the epoch.
+/// * `dataset` - The dataset label the rates are stored under.
+///
+/// # Returns
+///
+/// The number of stored funding rates.
+pub async fn backfill_funding_rates(
+    pool: &sqlx::PgPool,
+    symbol: &str,
+    start_time: u64,
+    end_time: Option<u64>,
+    dataset: &str,
+) -> Result<usize> {
+    let mut current_time = start_time;
+    let mut stored = 0;
+    loop {
+        let page =
+            get_funding_rates(symbol, current_time, end_time, MAX_FUNDING_RATE_LIMIT).await?;
+        let Some(last) = page.last() else {
+            break;
+        };
+        let next_time = u64::try_from(last.funding_time)
+            .map_err(|_| Error::InvalidTimestamp(last.funding_time))?
+            + 1;
+        let rates = page
+            .iter()
+            .map(|rate| Ok(rate.to_funding_rate()?.with_dataset(dataset)))
+            .collect::<Result<Vec<_>>>()?;
+        stored += FundingRate::upsert_batch(pool, &rates).await?;
+        log::info!(
+            "Backfilled {} funding rates for symbol {} up to {}",
+            page.len(),
+            symbol,
+            last.funding_time
+        );
+        if page.len() < MAX_FUNDING_RATE_LIMIT as usize || next_time <= current_time {
+            break;
+        }
+        current_time = next_time;
+    }
+    Ok(stored)
+}
+
+/// Backfills the funding rates of a contract settled after the latest stored one.
+///
+/// Settled rates do not change, so resuming from the stored history avoids
+/// fetching it again. Without stored rates, the backfill starts at `start_time`.
+///
+/// # Arguments
+///
+/// * `pool` - The database connection pool.
+/// * `symbol` - The contract symbol (e.g., "BTCUSDT").
+/// * `start_time` - The start time in milliseconds since the epoch.
+/// * `end_time` - An optional end time in milliseconds since the epoch.
+/// * `dataset` - The dataset label the rates are stored under.
+///
+/// # Returns
+///
+/// The number of stored funding rates.
+pub async fn resume_funding_rates(
+    pool: &sqlx::PgPool,
+    symbol: &str,
+    start_time: u64,
+    end_time: Option<u64>,
+    dataset: &str,
+) -> Result<usize> {
+    let latest = FundingRate::latest_funding_time(pool, symbol, dataset).await?;
+    let start_time = resume_time(start_time, latest.map(|time| time.timestamp_millis()));
+    if end_time.is_some_and(|end_time| start_time > end_time) {
+        return Ok(0);
+    }
+    backfill_funding_rates(pool, symbol, start_time, end_time, dataset).await
+}
+
+/// Returns the time to resume a funding backfill from, just after the latest
+/// stored funding time unless that is before `start_time`.
+fn resume_time(start_time: u64, latest: Option<i64>) -> u64 {
+    latest
+        .and_then(|latest| u64::try_from(latest).ok())
+        .map_or(start_time, |latest| start_time.max(latest + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resume_time() {
+        assert_eq!(resume_time(1_000, None), 1_000);
+        assert_eq!(resume_time(1_000, Some(500)), 1_000);
+        assert_eq!(resume_time(1_000, Some(28_800_000)), 28_800_001);
+        assert_eq!(resume_time(1_000, Some(-1)), 1_000);
+    }
+}
@@ -1,99 +1,1310 @@
-use binance_spot_connector_rust::market::klines::KlineInterval;
+use std::ops::Range;
+
 use chrono::{DateTime, Utc};
+use futures_util::stream::{self, StreamExt};
 
-use crate::data_source::rest::{extract_klines_from_string, get_kline_data};
-use anyhow::Result;
+use crate::data_source::clock::server_now;
+use crate::data_source::exchange::MarketDataSource;
+use crate::data_source::rate_limit::{BINANCE_WEIGHT_LIMIT, WEIGHT_THRESHOLD_PERCENT};
+use crate::data_source::rest::{DEFAULT_KLINE_LIMIT, KLINES_WEIGHT};
+use crate::error::{Error, Result};
+use crate::ingest::audit::{Gap, exclude_ranges, interval_duration, missing_gaps};
+use crate::ingest::maintenance::MaintenanceCalendar;
+use crate::ingest::pipeline::{Pipeline, StreamSource, UpsertSink};
+use crate::models::backfill_job::{BackfillJob, COMPLETED, FAILED};
+use crate::models::exchange_gap::ExchangeGap;
+use crate::models::quarantine::QuarantinedRow;
+use crate::models::trace::{self, JobId};
+use crate::models::{DEFAULT_DATASET, Interval, KlineData, SOURCE_KIND_BACKFILL};
+use crate::time::from_unsigned_millis;
 
-/// Backfills kline data for a single symbol and time range.
+/// Backfills kline data for a single symbol and time range from a [`MarketDataSource`].
+///
+/// Klines that fail [`KlineData::validate`](crate::models::KlineData::validate) are
+/// written to the quarantine table instead of `kline_data`; the others are written
+/// with a single [`KlineData::upsert_many`] statement.
+///
+/// If the exchange returns no klines (e.g., before the listing date or during a
+/// maintenance window), the requested window is recorded as an [`ExchangeGap`]
+/// and the returned end time points at the end of the window, so callers advance
+/// past it instead of requesting it again.
+///
+/// Every call is a batch with its own [`JobId`], which is logged and tags the
+/// transaction the klines are written in (see [`trace`]).
 ///
 /// # Arguments
 ///
+/// * `source` - The exchange to fetch the klines from.
 /// * `pool` - The database connection pool.
 /// * `symbol` - The trading symbol (e.g., "BTCUSDT").
-/// * `interval` - The kline interval (e.g., `KlineInterval::Minutes1`).
+/// * `interval` - The kline interval (e.g., "1m").
 /// * `start_time` - The start time for the backfill in milliseconds since the epoch.
 /// * `end_time` - An optional end time for the backfill in milliseconds since the epoch.
 /// * `limit` - An optional limit on the number of klines to fetch.
+/// * `dataset` - The dataset label the klines are stored under.
 ///
 /// # Returns
 ///
-/// A `Result` containing a tuple with the number of klines backfilled and the end time of the last kline,
-/// or an error if the backfill fails.
+/// A `Result` containing a tuple with the number of klines backfilled and the end time of the last kline
+/// in milliseconds since the epoch, or an error if the backfill fails.
+#[allow(clippy::too_many_arguments)]
 pub async fn kline_backfill(
+    source: &dyn MarketDataSource,
     pool: &sqlx::PgPool,
     symbol: &str,
-    interval: KlineInterval,
+    interval: &str,
     start_time: u64,
     end_time: Option<u64>,
     limit: Option<u32>,
-) -> Result<(usize, usize), Box<dyn std::error::Error>> {
-    let raw_data = get_kline_data(symbol, interval, start_time, end_time, limit)
-        .await
-        .expect("Failed to get kline data");
-    let klines = extract_klines_from_string(&raw_data, symbol)
-        .expect("Failed to extract klines from string");
+    dataset: &str,
+) -> Result<(usize, u64)> {
+    let job = JobId::new("backfill");
+    trace::scope(
+        job,
+        backfill_batch(
+            source, pool, symbol, interval, start_time, end_time, limit, dataset,
+        ),
+    )
+    .await
+}
+
+/// Fetches and stores one batch of [`kline_backfill`] under the current job.
+#[allow(clippy::too_many_arguments)]
+async fn backfill_batch(
+    source: &dyn MarketDataSource,
+    pool: &sqlx::PgPool,
+    symbol: &str,
+    interval: &str,
+    start_time: u64,
+    end_time: Option<u64>,
+    limit: Option<u32>,
+    dataset: &str,
+) -> Result<(usize, u64)> {
+    let klines = source
+        .fetch_klines(symbol, interval, start_time, end_time, limit)
+        .await?;
     let data_size = klines.len();
-    let last_data = klines.last().expect("No kline data found");
+    let Some(last_data) = klines.last() else {
+        let window_end = empty_window_end(
+            interval,
+            start_time,
+            end_time,
+            limit.unwrap_or(source.default_kline_limit()),
+            server_now(source).await.timestamp_millis() as u64,
+        );
+        let (gap_start, gap_end) = (
+            from_unsigned_millis(start_time)?,
+            from_unsigned_millis(window_end)?,
+        );
+        log::warn!(
+            "No klines returned for symbol {} from {} to {}, recording exchange-side gap",
+            symbol,
+            gap_start,
+            gap_end
+        );
+        ExchangeGap::record(
+            pool,
+            symbol,
+            interval,
+            dataset,
+            source.name(),
+            gap_start,
+            gap_end,
+        )
+        .await?;
+        return Ok((0, window_end.saturating_sub(1)));
+    };
     log::info!(
-        "Backfilled {} klines for symbol {} from {} to {}",
+        "Backfilled {} klines for symbol {} from {} to {} (job {})",
         data_size,
         symbol,
-        DateTime::from_timestamp_millis(start_time as i64)
-            .expect("Failed to convert start time to DateTime"),
-        last_data.end_time
+        from_unsigned_millis(start_time)?,
+        last_data.end_time,
+        trace::current()
+            .map(|job| job.to_string())
+            .unwrap_or_default()
     );
     let last_end_time = last_data.end_time;
 
+    let mut valid = Vec::with_capacity(klines.len());
     for kline in klines {
-        kline
-            .upsert(pool)
-            .await
-            .expect("Failed to insert kline data");
+        if let Err(reason) = kline.validate() {
+            log::warn!(
+                "Quarantining invalid kline for symbol {} at {}: {}",
+                kline.symbol,
+                kline.start_time,
+                reason
+            );
+            QuarantinedRow::add_kline(pool, "rest", &kline.into(), &reason.to_string(), dataset)
+                .await?;
+            continue;
+        }
+        valid.push(
+            kline
+                .with_dataset(dataset)
+                .with_source_kind(SOURCE_KIND_BACKFILL)
+                .with_exchange(source.name()),
+        );
+    }
+    let mut tx = trace::begin(pool).await?;
+    KlineData::upsert_many(&mut *tx, &valid).await?;
+    tx.commit().await?;
+    Ok((data_size, last_end_time.timestamp_millis() as u64))
+}
+
+/// Returns the exclusive end of a request window that came back empty.
+///
+/// With an explicit end time the window ends there; otherwise it spans `limit`
+/// intervals from `start_time`, capped at `now`. Calendar intervals without a fixed
+/// length are assumed to be at most 31 days long.
+fn empty_window_end(
+    interval: &str,
+    start_time: u64,
+    end_time: Option<u64>,
+    limit: u32,
+    now: u64,
+) -> u64 {
+    if let Some(end_time) = end_time {
+        return end_time + 1;
+    }
+    let step = interval_duration(interval)
+        .unwrap_or_else(|| chrono::Duration::days(31))
+        .num_milliseconds() as u64;
+    let span = step * u64::from(limit);
+    (start_time + span).min(now).max(start_time + 1)
+}
+
+/// Returns the start of the request window that ends at `end_time` (exclusive) in
+/// a newest-first backfill.
+///
+/// The window spans `limit` intervals, so the exchange returns all of its klines
+/// in one request, and is clamped to `start_time`. Calendar intervals without a
+/// fixed length are assumed to be at least 28 days long.
+fn reverse_window_start(interval: &str, start_time: u64, end_time: u64, limit: u32) -> u64 {
+    let step = interval_duration(interval)
+        .unwrap_or_else(|| chrono::Duration::days(28))
+        .num_milliseconds() as u64;
+    end_time
+        .saturating_sub(step * u64::from(limit.max(1)))
+        .max(start_time)
+}
+
+/// The order in which a backfill walks its range.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BackfillOrder {
+    /// From the start of the range to its end.
+    #[default]
+    OldestFirst,
+    /// From the end of the range back to its start, so the most recent klines are
+    /// stored first.
+    NewestFirst,
+}
+
+/// Limits that stop a backfill run early, e.g. to fit inside a cron window.
+///
+/// A run stops after the batch during which a limit is reached. The row budget
+/// additionally caps the size of the last batch so that no more than `max_rows`
+/// klines are fetched.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BackfillBudget {
+    /// Maximum wall-clock time to spend on the run.
+    pub max_duration: Option<std::time::Duration>,
+    /// Maximum number of klines to backfill in the run.
+    pub max_rows: Option<usize>,
+}
+
+impl BackfillBudget {
+    /// A budget without any limits.
+    pub fn unlimited() -> Self {
+        Self::default()
+    }
+}
+
+/// What a backfill does once it has caught up to the current time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CatchUpMode {
+    /// Stop at the current time.
+    #[default]
+    Stop,
+    /// Keep polling the REST API for new klines, waiting `poll_interval` between
+    /// polls. The latest (still open) kline is re-fetched on every poll so that its
+    /// final values are stored once it closes.
+    Tail {
+        /// The time to wait between polls once caught up.
+        poll_interval: std::time::Duration,
+    },
+    /// Hand off to a live WebSocket pipeline that upserts new klines as they arrive.
+    /// The backfill then runs until the stream ends.
+    HandOff,
+}
+
+/// The result of a budgeted backfill run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackfillProgress {
+    /// The number of klines backfilled during the run.
+    pub rows: usize,
+    /// The start time (milliseconds since the epoch) from which the next run should continue,
+    /// or for a [`kline_backfill_newest_first`] run, the exclusive end time below which it
+    /// should continue.
+    pub checkpoint: u64,
+    /// Whether the run stopped because the budget was exhausted rather than
+    /// because the requested range was completed.
+    pub budget_exhausted: bool,
+}
+
+/// A rough estimate of the size of a backfill, used to confirm large runs up front.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackfillEstimate {
+    /// The expected number of klines.
+    pub rows: u64,
+    /// The expected number of exchange requests.
+    pub requests: u64,
+    /// The expected wall-clock time spent waiting for the request weight budget of
+    /// the exchange, a lower bound for the duration of the run.
+    pub min_duration: std::time::Duration,
+}
+
+impl BackfillEstimate {
+    /// Estimates a backfill of `[start_time, end_time)` in milliseconds since the epoch.
+    ///
+    /// Calendar intervals without a fixed length are assumed to be 31 days long.
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - The kline interval.
+    /// * `start_time` - The start of the range.
+    /// * `end_time` - The end of the range.
+    /// * `limit` - The number of klines fetched per request, defaulting to [`DEFAULT_KLINE_LIMIT`].
+    pub fn new(interval: Interval, start_time: u64, end_time: u64, limit: Option<u32>) -> Self {
+        let step = interval
+            .duration()
+            .unwrap_or_else(|| chrono::Duration::days(31))
+            .num_milliseconds() as u64;
+        let rows = end_time.saturating_sub(start_time).div_ceil(step);
+        let limit = u64::from(limit.unwrap_or(DEFAULT_KLINE_LIMIT).max(1));
+        let requests = rows.div_ceil(limit);
+        let weight_per_minute = u64::from(BINANCE_WEIGHT_LIMIT * WEIGHT_THRESHOLD_PERCENT / 100);
+        Self {
+            rows,
+            requests,
+            min_duration: std::time::Duration::from_millis(
+                requests.saturating_mul(u64::from(KLINES_WEIGHT) * 60_000) / weight_per_minute,
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for BackfillEstimate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "~{} klines in ~{} requests, taking at least {:.1} hours",
+            self.rows,
+            self.requests,
+            self.min_duration.as_secs_f64() / 3600.0
+        )
     }
-    Ok((data_size, last_end_time.timestamp_millis() as usize))
 }
 
 /// Continuously backfills kline data for a given symbol until an optional end time is reached.
 ///
-/// This function repeatedly calls `kline_backfill` to fetch and store kline data in batches.
+/// This function repeatedly calls `kline_backfill` to fetch and store kline data in batches
+/// under the [`DEFAULT_DATASET`].
 ///
 /// # Arguments
 ///
+/// * `source` - The exchange to fetch the klines from.
 /// * `pool` - The database connection pool.
 /// * `symbols` - The trading symbol (e.g., "BTCUSDT").
-/// * `interval` - The kline interval (e.g., `KlineInterval::Minutes1`).
+/// * `interval` - The kline interval (e.g., "1m").
 /// * `start_time` - The start time for the backfill in milliseconds since the epoch.
 /// * `end_time` - An optional end time for the backfill in milliseconds since the epoch. If `None`, it will backfill indefinitely.
 /// * `limit` - An optional limit on the number of klines to fetch in each batch.
-/// * `delay` - An optional delay in milliseconds between backfill requests. This can be used to avoid hitting API rate limits.
+/// * `catch_up` - What to do once the backfill reaches the current time (see [`CatchUpMode`]).
 ///
 /// # Returns
 ///
 /// A `Result` containing the total number of klines backfilled, or an error if the backfill fails.
+#[allow(clippy::too_many_arguments)]
 pub async fn kline_backfill_all(
+    source: &dyn MarketDataSource,
     pool: &sqlx::PgPool,
     symbols: &str,
-    interval: KlineInterval,
+    interval: &str,
+    start_time: u64,
+    end_time: Option<u64>,
+    limit: Option<u32>,
+    catch_up: CatchUpMode,
+) -> Result<usize> {
+    let progress = kline_backfill_with_budget(
+        source,
+        pool,
+        symbols,
+        interval,
+        start_time,
+        end_time,
+        limit,
+        BackfillBudget::unlimited(),
+        DEFAULT_DATASET,
+        catch_up,
+    )
+    .await?;
+    Ok(progress.rows)
+}
+
+/// Backfills kline data like [`kline_backfill_all`], stopping early once `budget` is exhausted.
+///
+/// The returned [`BackfillProgress::checkpoint`] is the start time for the next run,
+/// so a run that stopped on its budget can be resumed from where it left off.
+///
+/// Whether the backfill caught up is decided on the exchange clock (see
+/// [`server_now`]) rather than the local one, so a drifted host neither stops a
+/// candle early nor waits for a candle that has not opened yet.
+///
+/// # Arguments
+///
+/// * `source` - The exchange to fetch the klines from, and to stream from after a hand-off.
+/// * `pool` - The database connection pool.
+/// * `symbol` - The trading symbol (e.g., "BTCUSDT").
+/// * `interval` - The kline interval (e.g., "1m").
+/// * `start_time` - The start time for the backfill in milliseconds since the epoch.
+/// * `end_time` - An optional end time for the backfill in milliseconds since the epoch.
+/// * `limit` - An optional limit on the number of klines to fetch in each batch.
+/// * `budget` - The time and row limits for this run.
+/// * `dataset` - The dataset label the klines are stored under.
+/// * `catch_up` - What to do once the backfill reaches the current time (see [`CatchUpMode`]).
+///   Catching up is only possible without an end time.
+///
+/// # Returns
+///
+/// A `Result` containing the [`BackfillProgress`] of the run, or an error if the backfill fails.
+#[allow(clippy::too_many_arguments)]
+pub async fn kline_backfill_with_budget(
+    source: &dyn MarketDataSource,
+    pool: &sqlx::PgPool,
+    symbol: &str,
+    interval: &str,
     start_time: u64,
     end_time: Option<u64>,
     limit: Option<u32>,
-    delay: Option<u64>,
-) -> Result<usize, Box<dyn std::error::Error>> {
+    budget: BackfillBudget,
+    dataset: &str,
+    catch_up: CatchUpMode,
+) -> Result<BackfillProgress> {
+    let started_at = std::time::Instant::now();
     let mut current_time = start_time;
     let mut total_data_size = 0;
+    let mut budget_exhausted = false;
+
+    let tail_poll_interval = match catch_up {
+        CatchUpMode::Tail { poll_interval } if end_time.is_none() => Some(poll_interval),
+        _ => None,
+    };
 
     while current_time < end_time.unwrap_or(u64::MAX)
-        && current_time <= Utc::now().timestamp_millis() as u64
+        && current_time <= server_now(source).await.timestamp_millis() as u64
     {
-        let (data_size, last_end_time) =
-            kline_backfill(pool, symbols, interval, current_time, None, limit).await?;
+        let out_of_time = budget
+            .max_duration
+            .is_some_and(|max| started_at.elapsed() >= max);
+        let remaining_rows = budget
+            .max_rows
+            .map(|max| max.saturating_sub(total_data_size));
+        if out_of_time || remaining_rows == Some(0) {
+            budget_exhausted = true;
+            break;
+        }
+
+        let batch_limit = match remaining_rows {
+            Some(remaining) => Some(
+                limit
+                    .unwrap_or(source.default_kline_limit())
+                    .min(u32::try_from(remaining).unwrap_or(u32::MAX)),
+            ),
+            None => limit,
+        };
+        let (data_size, last_end_time) = kline_backfill(
+            source,
+            pool,
+            symbol,
+            interval,
+            current_time,
+            None,
+            batch_limit,
+            dataset,
+        )
+        .await?;
 
         total_data_size += data_size;
-        current_time = last_end_time as u64 + 1;
-        if let Some(d) = delay {
-            tokio::time::sleep(tokio::time::Duration::from_millis(d)).await;
+        let caught_up = last_end_time >= server_now(source).await.timestamp_millis() as u64;
+        current_time = last_end_time + 1;
+        if caught_up && let Some(poll_interval) = tail_poll_interval {
+            if data_size > 0
+                && let Some(step) = interval_duration(interval)
+            {
+                // Re-fetch the open kline on the next poll.
+                current_time -= step.num_milliseconds() as u64;
+            }
+            tokio::time::sleep(poll_interval).await;
+            continue;
         }
     }
 
-    Ok(total_data_size)
+    if budget_exhausted {
+        log::info!(
+            "Backfill budget exhausted for symbol {} after {} klines, next run should start at {}",
+            symbol,
+            total_data_size,
+            current_time
+        );
+    }
+
+    if catch_up == CatchUpMode::HandOff && !budget_exhausted && end_time.is_none() {
+        log::info!(
+            "Backfill for symbol {} caught up after {} klines, handing off to live stream",
+            symbol,
+            total_data_size
+        );
+        let stream = source.stream_klines(symbol, interval).await?;
+        Pipeline::builder(&format!("{}-{}", symbol.to_lowercase(), interval))
+            .source(StreamSource::new(stream))
+            .sink(
+                UpsertSink::new(pool.clone(), "websocket")
+                    .with_dataset(dataset)
+                    .with_exchange(source.name()),
+            )
+            .build()?
+            .run()
+            .await?;
+    }
+
+    Ok(BackfillProgress {
+        rows: total_data_size,
+        checkpoint: current_time,
+        budget_exhausted,
+    })
+}
+
+/// Backfills kline data like [`kline_backfill_with_budget`], but walks the range
+/// from its end back to its start so the most recent klines are stored first.
+///
+/// Every batch requests the klines of the `limit` intervals before the current
+/// end. The returned [`BackfillProgress::checkpoint`] is the exclusive end time of
+/// the next run, which continues further back; a completed run returns
+/// `start_time`.
+///
+/// # Arguments
+///
+/// * `source` - The exchange to fetch the klines from.
+/// * `pool` - The database connection pool.
+/// * `symbol` - The trading symbol (e.g., "BTCUSDT").
+/// * `interval` - The kline interval (e.g., "1m").
+/// * `start_time` - The inclusive start of the range in milliseconds since the epoch.
+/// * `end_time` - The exclusive end of the range in milliseconds since the epoch.
+/// * `limit` - An optional limit on the number of klines to fetch in each batch.
+/// * `budget` - The time and row limits for this run.
+/// * `dataset` - The dataset label the klines are stored under.
+///
+/// # Returns
+///
+/// A `Result` containing the [`BackfillProgress`] of the run, or an error if the backfill fails.
+#[allow(clippy::too_many_arguments)]
+pub async fn kline_backfill_newest_first(
+    source: &dyn MarketDataSource,
+    pool: &sqlx::PgPool,
+    symbol: &str,
+    interval: &str,
+    start_time: u64,
+    end_time: u64,
+    limit: Option<u32>,
+    budget: BackfillBudget,
+    dataset: &str,
+) -> Result<BackfillProgress> {
+    let started_at = std::time::Instant::now();
+    let mut current_end = end_time;
+    let mut total_data_size = 0;
+    let mut budget_exhausted = false;
+
+    while current_end > start_time {
+        let out_of_time = budget
+            .max_duration
+            .is_some_and(|max| started_at.elapsed() >= max);
+        let remaining_rows = budget
+            .max_rows
+            .map(|max| max.saturating_sub(total_data_size));
+        if out_of_time || remaining_rows == Some(0) {
+            budget_exhausted = true;
+            break;
+        }
+
+        let mut batch_limit = limit.unwrap_or(source.default_kline_limit());
+        if let Some(remaining) = remaining_rows {
+            batch_limit = batch_limit.min(u32::try_from(remaining).unwrap_or(u32::MAX));
+        }
+        let window_start = reverse_window_start(interval, start_time, current_end, batch_limit);
+        let (data_size, _) = kline_backfill(
+            source,
+            pool,
+            symbol,
+            interval,
+            window_start,
+            Some(current_end - 1),
+            Some(batch_limit),
+            dataset,
+        )
+        .await?;
+        total_data_size += data_size;
+        current_end = window_start;
+    }
+
+    if budget_exhausted {
+        log::info!(
+            "Backfill budget exhausted for symbol {} after {} klines, next run should end at {}",
+            symbol,
+            total_data_size,
+            current_end
+        );
+    }
+    Ok(BackfillProgress {
+        rows: total_data_size,
+        checkpoint: current_end,
+        budget_exhausted,
+    })
+}
+
+/// Creates a tracked [`BackfillJob`] for a range and runs it.
+///
+/// Progress is stored after every batch, so if the process dies the job can be
+/// continued with [`resume_backfill`] instead of starting over.
+///
+/// # Arguments
+///
+/// * `source` - The exchange to fetch the klines from.
+/// * `pool` - The database connection pool.
+/// * `symbol` - The trading symbol (e.g., "BTCUSDT").
+/// * `interval` - The kline interval (e.g., "1m").
+/// * `start_time` - The inclusive start of the range.
+/// * `end_time` - The exclusive end of the range, or `None` to backfill up to now.
+/// * `limit` - An optional limit on the number of klines to fetch in each batch.
+/// * `dataset` - The dataset label the klines are stored under.
+/// * `order` - The order the range is walked in. A newest-first job without an end
+///   time ends at the current time, so that resuming it does not skip the klines
+///   that appeared in between.
+///
+/// # Returns
+///
+/// A `Result` containing the completed job, or an error if a batch fails, in which
+/// case the job is marked as [`FAILED`].
+#[allow(clippy::too_many_arguments)]
+pub async fn start_backfill_job(
+    source: &dyn MarketDataSource,
+    pool: &sqlx::PgPool,
+    symbol: &str,
+    interval: &str,
+    start_time: DateTime<Utc>,
+    end_time: Option<DateTime<Utc>>,
+    limit: Option<u32>,
+    dataset: &str,
+    order: BackfillOrder,
+) -> Result<BackfillJob> {
+    let newest_first = order == BackfillOrder::NewestFirst;
+    let end_time = match end_time {
+        None if newest_first => Some(server_now(source).await),
+        end_time => end_time,
+    };
+    let job = BackfillJob::create(
+        pool,
+        symbol,
+        interval,
+        dataset,
+        start_time,
+        end_time,
+        newest_first,
+    )
+    .await?;
+    log::info!(
+        "Created backfill job {} for symbol {} {}",
+        job.id,
+        symbol,
+        interval
+    );
+    run_backfill_job(source, pool, job, limit).await
+}
+
+/// Resumes a tracked backfill from the batch after the last stored one.
+///
+/// Jobs that are still marked as running (because their process died) and failed
+/// jobs are resumed alike; completed jobs are returned unchanged. Callers should
+/// hold the [`BackfillLock`](crate::ingest::backfill::lock::BackfillLock) of the
+/// job's symbol, interval, dataset and source so that a job is not resumed while
+/// it still runs.
+///
+/// # Arguments
+///
+/// * `source` - The exchange to fetch the klines from.
+/// * `pool` - The database connection pool.
+/// * `job_id` - The identifier of the job.
+/// * `limit` - An optional limit on the number of klines to fetch in each batch.
+///
+/// # Returns
+///
+/// A `Result` containing the completed job, or an error if the job does not exist
+/// or a batch fails.
+pub async fn resume_backfill(
+    source: &dyn MarketDataSource,
+    pool: &sqlx::PgPool,
+    job_id: i64,
+    limit: Option<u32>,
+) -> Result<BackfillJob> {
+    let job = BackfillJob::get(pool, job_id)
+        .await?
+        .ok_or(Error::JobNotFound(job_id))?;
+    if job.is_completed() {
+        log::info!("Backfill job {} is already completed", job_id);
+        return Ok(job);
+    }
+    log::info!(
+        "Resuming backfill job {} for symbol {} {} at {}",
+        job.id,
+        job.symbol,
+        job.interval,
+        job.resume_time()
+    );
+    run_backfill_job(source, pool, job, limit).await
+}
+
+/// Runs a job from its resume time until its end time (or now), storing the
+/// progress after every batch.
+async fn run_backfill_job(
+    source: &dyn MarketDataSource,
+    pool: &sqlx::PgPool,
+    mut job: BackfillJob,
+    limit: Option<u32>,
+) -> Result<BackfillJob> {
+    if job.newest_first {
+        return run_reverse_backfill_job(source, pool, job, limit).await;
+    }
+    let end_time = job
+        .end_time
+        .map(|end_time| end_time.timestamp_millis() as u64);
+    let mut current_time = job.resume_time().timestamp_millis() as u64;
+    let mut row_count = job.row_count;
+    while current_time < end_time.unwrap_or(u64::MAX)
+        && current_time <= server_now(source).await.timestamp_millis() as u64
+    {
+        let batch = kline_backfill(
+            source,
+            pool,
+            &job.symbol,
+            &job.interval,
+            current_time,
+            end_time.map(|end_time| end_time - 1),
+            limit,
+            &job.dataset,
+        )
+        .await;
+        let (data_size, last_end_time) = match batch {
+            Ok(batch) => batch,
+            Err(e) => {
+                job.set_status(pool, FAILED, Some(&e.to_string())).await?;
+                return Err(e);
+            }
+        };
+        row_count += data_size as i64;
+        let last_end = from_unsigned_millis(last_end_time)?;
+        job.record_progress(pool, last_end, row_count).await?;
+        current_time = last_end_time + 1;
+    }
+    job.set_status(pool, COMPLETED, None).await?;
+    log::info!(
+        "Backfill job {} completed after {} klines",
+        job.id,
+        job.row_count
+    );
+    Ok(job)
+}
+
+/// Runs a newest-first job from its resume time back to its start time, storing
+/// the progress after every batch.
+async fn run_reverse_backfill_job(
+    source: &dyn MarketDataSource,
+    pool: &sqlx::PgPool,
+    mut job: BackfillJob,
+    limit: Option<u32>,
+) -> Result<BackfillJob> {
+    let start_time = job.start_time.timestamp_millis() as u64;
+    let mut current_end = job.resume_time().timestamp_millis() as u64;
+    let mut row_count = job.row_count;
+    let window_limit = limit.unwrap_or(source.default_kline_limit());
+    while current_end > start_time {
+        let window_start =
+            reverse_window_start(&job.interval, start_time, current_end, window_limit);
+        let batch = kline_backfill(
+            source,
+            pool,
+            &job.symbol,
+            &job.interval,
+            window_start,
+            Some(current_end - 1),
+            limit,
+            &job.dataset,
+        )
+        .await;
+        let data_size = match batch {
+            Ok((data_size, _)) => data_size,
+            Err(e) => {
+                job.set_status(pool, FAILED, Some(&e.to_string())).await?;
+                return Err(e);
+            }
+        };
+        row_count += data_size as i64;
+        job.record_reverse_progress(pool, from_unsigned_millis(window_start)?, row_count)
+            .await?;
+        current_end = window_start;
+    }
+    job.set_status(pool, COMPLETED, None).await?;
+    log::info!(
+        "Backfill job {} completed after {} klines",
+        job.id,
+        job.row_count
+    );
+    Ok(job)
+}
+
+/// The result of a [`repair_gaps`] run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GapRepair {
+    /// The number of gaps found in the range.
+    pub gaps: usize,
+    /// The number of missing klines the gaps covered.
+    pub missing: i64,
+    /// The number of klines fetched to fill the gaps.
+    pub rows: usize,
+    /// The number of missing klines within maintenance windows, which were not
+    /// requested.
+    pub in_maintenance: i64,
+}
+
+/// Backfills only the candles missing from a stored range, instead of
+/// re-downloading the whole range.
+///
+/// The gaps are found with [`missing_gaps`], so ranges the exchange already
+/// reported as empty are not requested again. Each gap is fetched like a
+/// [`kline_backfill`] with an end time; gaps that still come back empty are
+/// recorded as [`ExchangeGap`]s and skipped by later runs. Candles starting within
+/// a maintenance window of the source are not requested.
+///
+/// Callers should hold the [`BackfillLock`](crate::ingest::backfill::lock::BackfillLock)
+/// of the symbol, interval, dataset and source, so that no other backfill writes
+/// the range while its gaps are repaired.
+///
+/// # Arguments
+///
+/// * `source` - The exchange to fetch the klines from.
+/// * `pool` - The database connection pool.
+/// * `symbol` - The trading symbol (e.g., "BTCUSDT").
+/// * `interval` - The kline interval (e.g., "1m"); must have a fixed length.
+/// * `range` - The range of candle start times to repair.
+/// * `dataset` - The dataset label the klines are stored under.
+/// * `maintenance` - The known maintenance windows of the exchanges.
+///
+/// # Returns
+///
+/// A `Result` containing the [`GapRepair`] summary, or an error if gap detection
+/// or a backfill request fails.
+pub async fn repair_gaps(
+    source: &dyn MarketDataSource,
+    pool: &sqlx::PgPool,
+    symbol: &str,
+    interval: &str,
+    range: Range<DateTime<Utc>>,
+    dataset: &str,
+    maintenance: &MaintenanceCalendar,
+) -> Result<GapRepair> {
+    let gaps = missing_gaps(
+        pool,
+        symbol,
+        interval,
+        range.start,
+        range.end,
+        dataset,
+        source.name(),
+    )
+    .await?;
+    let windows = maintenance.ranges(source.name(), range.start, range.end);
+    let mut repair = GapRepair::default();
+    let gaps = match interval_duration(interval) {
+        Some(step) if !windows.is_empty() => {
+            let outside = exclude_ranges(&gaps, &windows, step);
+            let missing = |gaps: &[Gap]| gaps.iter().map(|gap| gap.missing).sum::<i64>();
+            repair.in_maintenance = missing(&gaps) - missing(&outside);
+            outside
+        }
+        _ => gaps,
+    };
+    repair.gaps = gaps.len();
+    for gap in &gaps {
+        log::info!(
+            "Repairing {} missing klines for symbol {} from {} to {}",
+            gap.missing,
+            symbol,
+            gap.start,
+            gap.end
+        );
+        repair.missing += gap.missing;
+        let mut current_time = gap.start.timestamp_millis() as u64;
+        // The last millisecond of the gap, so that the candle at its end is not fetched.
+        let last_time = gap.end.timestamp_millis() as u64 - 1;
+        while current_time <= last_time {
+            let (data_size, last_end_time) = kline_backfill(
+                source,
+                pool,
+                symbol,
+                interval,
+                current_time,
+                Some(last_time),
+                None,
+                dataset,
+            )
+            .await?;
+            repair.rows += data_size;
+            current_time = last_end_time + 1;
+        }
+    }
+    Ok(repair)
+}
+
+/// The result of a [`rewrite_range`] run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RangeRewrite {
+    /// The number of stored klines deleted from the range.
+    pub deleted: u64,
+    /// The number of klines fetched and stored in their place.
+    pub rows: usize,
+    /// The number of fetched klines that failed validation and were quarantined.
+    pub quarantined: usize,
+}
+
+/// Replaces the stored klines of a range with freshly fetched ones, e.g. after
+/// the range was found to be corrupted.
+///
+/// The whole range is fetched first; the stored klines are then deleted with
+/// [`KlineData::delete_range`] and the fetched ones written in a single
+/// transaction, so readers see either the old or the new range and a failure
+/// leaves the stored range untouched. Fetched klines that fail validation are
+/// quarantined instead of stored. If the exchange returns no klines for the
+/// range, the stored range is kept. Only the klines stored from `source` are
+/// replaced; those of other exchanges in the same dataset are left untouched.
+///
+/// Callers should hold the [`BackfillLock`](crate::ingest::backfill::lock::BackfillLock)
+/// of the symbol, interval, dataset and source, so that no backfill writes the
+/// range between the fetch and the rewrite.
+///
+/// # Arguments
+///
+/// * `source` - The exchange to fetch the klines from.
+/// * `pool` - The database connection pool.
+/// * `symbol` - The trading symbol (e.g., "BTCUSDT").
+/// * `interval` - The kline interval (e.g., "1m").
+/// * `range` - The range of candle start times to rewrite.
+/// * `dataset` - The dataset label the klines are stored under.
+///
+/// # Returns
+///
+/// A `Result` containing the [`RangeRewrite`] summary, or an error if the range
+/// is empty, a request or the transaction fails.
+pub async fn rewrite_range(
+    source: &dyn MarketDataSource,
+    pool: &sqlx::PgPool,
+    symbol: &str,
+    interval: &str,
+    range: Range<DateTime<Utc>>,
+    dataset: &str,
+) -> Result<RangeRewrite> {
+    if range.start >= range.end {
+        return Err(Error::Other(anyhow::anyhow!(
+            "Cannot rewrite the empty range from {} to {}",
+            range.start,
+            range.end
+        )));
+    }
+
+    let mut fetched = Vec::new();
+    let mut current_time = range.start.timestamp_millis() as u64;
+    // The last millisecond of the range, so that the candle at its end is not fetched.
+    let last_time = (range.end.timestamp_millis() as u64).saturating_sub(1);
+    while current_time <= last_time {
+        let klines = source
+            .fetch_klines(symbol, interval, current_time, Some(last_time), None)
+            .await?;
+        let Some(last) = klines.last() else {
+            break;
+        };
+        let next_time = last.end_time.timestamp_millis() as u64 + 1;
+        fetched.extend(klines);
+        // Stop if the source keeps returning the same page instead of moving forward.
+        if next_time <= current_time {
+            log::warn!(
+                "Klines for symbol {} did not advance past {}, stopping the fetch",
+                symbol,
+                current_time
+            );
+            break;
+        }
+        current_time = next_time;
+    }
+    if fetched.is_empty() {
+        log::warn!(
+            "No klines returned for symbol {} from {} to {}, keeping the stored range",
+            symbol,
+            range.start,
+            range.end
+        );
+        return Ok(RangeRewrite::default());
+    }
+
+    let mut rewrite = RangeRewrite::default();
+    let mut valid = Vec::with_capacity(fetched.len());
+    for kline in fetched {
+        if let Err(reason) = kline.validate() {
+            log::warn!(
+                "Quarantining invalid kline for symbol {} at {}: {}",
+                kline.symbol,
+                kline.start_time,
+                reason
+            );
+            QuarantinedRow::add_kline(pool, "rest", &kline.into(), &reason.to_string(), dataset)
+                .await?;
+            rewrite.quarantined += 1;
+            continue;
+        }
+        valid.push(
+            kline
+                .with_dataset(dataset)
+                .with_source_kind(SOURCE_KIND_BACKFILL)
+                .with_exchange(source.name()),
+        );
+    }
+
+    let mut tx = trace::begin(pool).await?;
+    rewrite.deleted = KlineData::delete_range(
+        &mut *tx,
+        symbol,
+        interval,
+        range.clone(),
+        dataset,
+        source.name(),
+    )
+    .await?;
+    rewrite.rows = KlineData::upsert_many(&mut *tx, &valid).await?;
+    tx.commit().await?;
+    log::info!(
+        "Rewrote klines for symbol {} from {} to {}: deleted {}, stored {}",
+        symbol,
+        range.start,
+        range.end,
+        rewrite.deleted,
+        rewrite.rows
+    );
+    Ok(rewrite)
+}
+
+/// The result of one symbol of a [`kline_backfill_many`] run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolBackfill {
+    /// The trading symbol.
+    pub symbol: String,
+    /// The number of klines backfilled, including those stored before a failure.
+    pub rows: usize,
+    /// The error that stopped the backfill of the symbol, if it failed.
+    pub error: Option<String>,
+}
+
+/// Backfills kline data for many symbols concurrently.
+///
+/// Up to `workers` symbols are backfilled at a time. The requests of all workers
+/// draw on the request weight budget of the exchange shared by the process (see
+/// [`rate_limit`](crate::data_source::rate_limit)), so adding workers does not
+/// push the request rate of the run beyond the limit. A symbol that fails does
+/// not stop the others; its error is reported in its [`SymbolBackfill`].
+///
+/// # Arguments
+///
+/// * `source` - The exchange to fetch the klines from.
+/// * `pool` - The database connection pool.
+/// * `symbols` - The trading symbols (e.g., "BTCUSDT").
+/// * `interval` - The kline interval (e.g., "1m").
+/// * `start_time` - The start time for the backfill in milliseconds since the epoch.
+/// * `end_time` - An optional end time for the backfill in milliseconds since the epoch.
+/// * `limit` - An optional limit on the number of klines to fetch in each batch.
+/// * `workers` - The maximum number of symbols backfilled at a time.
+/// * `dataset` - The dataset label the klines are stored under.
+///
+/// # Returns
+///
+/// The [`SymbolBackfill`] of every symbol, in the order of `symbols`.
+#[allow(clippy::too_many_arguments)]
+pub async fn kline_backfill_many(
+    source: &dyn MarketDataSource,
+    pool: &sqlx::PgPool,
+    symbols: &[&str],
+    interval: &str,
+    start_time: u64,
+    end_time: Option<u64>,
+    limit: Option<u32>,
+    workers: usize,
+    dataset: &str,
+) -> Vec<SymbolBackfill> {
+    stream::iter(symbols)
+        .map(|symbol| {
+            backfill_symbol(
+                source, pool, symbol, interval, start_time, end_time, limit, dataset,
+            )
+        })
+        .buffered(workers.max(1))
+        .collect()
+        .await
+}
+
+/// Backfills one symbol of a [`kline_backfill_many`] run.
+#[allow(clippy::too_many_arguments)]
+async fn backfill_symbol(
+    source: &dyn MarketDataSource,
+    pool: &sqlx::PgPool,
+    symbol: &str,
+    interval: &str,
+    start_time: u64,
+    end_time: Option<u64>,
+    limit: Option<u32>,
+    dataset: &str,
+) -> SymbolBackfill {
+    let mut result = SymbolBackfill {
+        symbol: symbol.to_string(),
+        rows: 0,
+        error: None,
+    };
+    let mut current_time = start_time;
+    while current_time < end_time.unwrap_or(u64::MAX)
+        && current_time <= server_now(source).await.timestamp_millis() as u64
+    {
+        let batch = kline_backfill(
+            source,
+            pool,
+            symbol,
+            interval,
+            current_time,
+            None,
+            limit,
+            dataset,
+        )
+        .await
+        .map_err(|e| e.to_string());
+        match batch {
+            Ok((data_size, last_end_time)) => {
+                result.rows += data_size;
+                current_time = last_end_time + 1;
+            }
+            Err(e) => {
+                log::error!("Backfill of symbol {} failed: {}", symbol, e);
+                result.error = Some(e);
+                return result;
+            }
+        }
+    }
+    log::info!(
+        "Backfill of symbol {} completed with {} klines",
+        symbol,
+        result.rows
+    );
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use chrono::Duration;
+
+    use super::*;
+    use crate::data_source::websocket::StreamingClient;
+    use crate::ingest::synthetic::SyntheticKlines;
+    use crate::models::SerdableKlineData;
+
+    /// A source that serves a fixed set of klines under an exchange name.
+    struct FixedSource {
+        name: &'static str,
+        klines: Vec<KlineData>,
+    }
+
+    #[async_trait]
+    impl MarketDataSource for FixedSource {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn normalize_symbol(&self, symbol: &str) -> String {
+            symbol.to_string()
+        }
+
+        fn max_kline_limit(&self) -> u32 {
+            DEFAULT_KLINE_LIMIT
+        }
+
+        fn default_kline_limit(&self) -> u32 {
+            DEFAULT_KLINE_LIMIT
+        }
+
+        async fn fetch_klines(
+            &self,
+            _symbol: &str,
+            _interval: &str,
+            start_time: u64,
+            end_time: Option<u64>,
+            _limit: Option<u32>,
+        ) -> anyhow::Result<Vec<KlineData>> {
+            Ok(self
+                .klines
+                .iter()
+                .filter(|kline| {
+                    let open_time = kline.start_time.timestamp_millis() as u64;
+                    open_time >= start_time && end_time.is_none_or(|end| open_time <= end)
+                })
+                .cloned()
+                .collect())
+        }
+
+        async fn stream_klines(
+            &self,
+            symbol: &str,
+            _interval: &str,
+        ) -> anyhow::Result<Box<dyn StreamingClient<SerdableKlineData>>> {
+            anyhow::bail!("{} has no live kline stream for {}", self.name, symbol)
+        }
+    }
+
+    #[test]
+    fn test_backfill_estimate() {
+        let start = 1_600_000_000_000;
+        let estimate = BackfillEstimate::new(
+            Interval::Minutes1,
+            start,
+            start + 365 * 24 * 3_600_000,
+            Some(1000),
+        );
+        assert_eq!(estimate.rows, 525_600);
+        assert_eq!(estimate.requests, 526);
+        assert_eq!(
+            estimate.min_duration,
+            std::time::Duration::from_millis(11_688)
+        );
+
+        let empty = BackfillEstimate::new(Interval::Hours1, start, start, None);
+        assert_eq!((empty.rows, empty.requests), (0, 0));
+    }
+
+    #[test]
+    fn test_empty_window_end_spans_limit_intervals() {
+        let start = 1_600_000_000_000;
+        let now = start + 10_000 * 60_000;
+        assert_eq!(
+            empty_window_end("1m", start, None, 100, now),
+            start + 100 * 60_000
+        );
+        assert_eq!(
+            empty_window_end("1m", start, None, DEFAULT_KLINE_LIMIT, now),
+            start + 500 * 60_000
+        );
+    }
+
+    #[test]
+    fn test_reverse_window_start_spans_limit_intervals() {
+        let end = 1_600_000_000_000;
+        assert_eq!(reverse_window_start("1m", 0, end, 100), end - 100 * 60_000);
+        assert_eq!(reverse_window_start("1h", end - 5, end, 100), end - 5);
+        assert_eq!(reverse_window_start("1m", 0, 1_000, 100), 0);
+    }
+
+    #[test]
+    fn test_empty_window_end_respects_end_time_and_now() {
+        let start = 1_600_000_000_000;
+        assert_eq!(
+            empty_window_end("1h", start, Some(start + 5), DEFAULT_KLINE_LIMIT, u64::MAX),
+            start + 6
+        );
+        assert_eq!(
+            empty_window_end("1d", start, None, DEFAULT_KLINE_LIMIT, start + 1000),
+            start + 1000
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rewrite_range_rejects_empty_range() {
+        // The range is checked before the pool is used, so it never connects.
+        let pool = sqlx::PgPool::connect_lazy("postgres://localhost/unused").unwrap();
+        let source = FixedSource {
+            name: "binance",
+            klines: Vec::new(),
+        };
+        let start = Utc::now();
+        for range in [start..start, start..start - Duration::minutes(1)] {
+            let result = rewrite_range(&source, &pool, "SYNUSDT", "1m", range, "test").await;
+            assert!(result.is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rewrite_range_keeps_other_exchanges() {
+        let Ok(database_url) = std::env::var("DATABASE_URL") else {
+            eprintln!("DATABASE_URL is not set, skipping the rewrite_range test");
+            return;
+        };
+        let pool = sqlx::PgPool::connect(&database_url).await.unwrap();
+        let dataset = "test-rewrite-range";
+        let klines = SyntheticKlines::new(&["SYNUSDT"]).final_klines(10);
+        let (symbol, interval) = (klines[0].symbol.clone(), klines[0].interval.clone());
+        let range = klines[0].start_time..klines[9].start_time + Duration::minutes(1);
+        let stored = |exchange: &str| -> Vec<KlineData> {
+            klines
+                .iter()
+                .map(|kline| kline.clone().with_dataset(dataset).with_exchange(exchange))
+                .collect()
+        };
+        for exchange in ["binance", "okx"] {
+            KlineData::delete_range(&pool, &symbol, &interval, range.clone(), dataset, exchange)
+                .await
+                .unwrap();
+            KlineData::upsert_many(&pool, &stored(exchange))
+                .await
+                .unwrap();
+        }
+
+        let source = FixedSource {
+            name: "okx",
+            klines: klines[..5].to_vec(),
+        };
+        let rewrite = rewrite_range(&source, &pool, &symbol, &interval, range.clone(), dataset)
+            .await
+            .unwrap();
+        assert_eq!((rewrite.deleted, rewrite.rows), (10, 5));
+
+        for (exchange, expected) in [("okx", 5), ("binance", 10)] {
+            let rows = KlineData::list_range(
+                &pool,
+                &symbol,
+                &interval,
+                range.start,
+                range.end,
+                dataset,
+                exchange,
+            )
+            .await
+            .unwrap();
+            assert_eq!(rows.len(), expected, "{}", exchange);
+        }
+
+        for exchange in ["binance", "okx"] {
+            KlineData::delete_range(&pool, &symbol, &interval, range.clone(), dataset, exchange)
+                .await
+                .unwrap();
+        }
+    }
 }
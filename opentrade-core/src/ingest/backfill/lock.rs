@@ -0,0 +1,192 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use sqlx::pool::PoolConnection;
+use sqlx::{Connection, PgConnection, Postgres};
+
+/// How often [`BackfillLock::acquire`] retries while another backfill holds the lock.
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A session-level Postgres advisory lock held for the backfill of one symbol and
+/// interval of a dataset and exchange.
+///
+/// Two backfills of the same series would interleave their writes and share the
+/// same API rate limit, so only one may run at a time. Backfills of the same symbol
+/// into another dataset or from another exchange write other rows and do not
+/// conflict. The lock is tied
+/// to a dedicated database connection: it is released by [`release`](Self::release),
+/// or by closing that connection when the lock is dropped, which also covers
+/// crashed processes.
+pub struct BackfillLock {
+    /// The connection holding the lock.
+    conn: Option<PoolConnection<Postgres>>,
+    /// The name the lock key is derived from.
+    name: String,
+}
+
+impl BackfillLock {
+    /// Returns the name of the lock for a symbol and interval of a dataset and
+    /// exchange, e.g. `backfill:default:binance:BTCUSDT:1m`.
+    pub fn name(symbol: &str, interval: &str, dataset: &str, exchange: &str) -> String {
+        format!("backfill:{}:{}:{}:{}", dataset, exchange, symbol, interval)
+    }
+
+    /// Tries to take the lock for a symbol and interval without waiting.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `symbol` - The trading symbol.
+    /// * `interval` - The Kline interval.
+    /// * `dataset` - The dataset label the klines are stored under.
+    /// * `exchange` - The exchange the klines are fetched from (e.g., "binance").
+    ///
+    /// # Returns
+    ///
+    /// The lock, or `None` if another backfill holds it.
+    pub async fn try_acquire(
+        pool: &sqlx::PgPool,
+        symbol: &str,
+        interval: &str,
+        dataset: &str,
+        exchange: &str,
+    ) -> Result<Option<Self>> {
+        let name = Self::name(symbol, interval, dataset, exchange);
+        let mut conn = pool.acquire().await?;
+        let locked = sqlx::query_scalar!(
+            r#"SELECT pg_try_advisory_lock(hashtextextended($1, 0)) AS "locked!""#,
+            name
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+        if !locked {
+            return Ok(None);
+        }
+        log::debug!("Acquired lock {}", name);
+        Ok(Some(Self {
+            conn: Some(conn),
+            name,
+        }))
+    }
+
+    /// Takes the lock for a symbol and interval, waiting while another backfill
+    /// holds it.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `symbol` - The trading symbol.
+    /// * `interval` - The Kline interval.
+    /// * `dataset` - The dataset label the klines are stored under.
+    /// * `exchange` - The exchange the klines are fetched from (e.g., "binance").
+    pub async fn acquire(
+        pool: &sqlx::PgPool,
+        symbol: &str,
+        interval: &str,
+        dataset: &str,
+        exchange: &str,
+    ) -> Result<Self> {
+        loop {
+            if let Some(lock) = Self::try_acquire(pool, symbol, interval, dataset, exchange).await?
+            {
+                return Ok(lock);
+            }
+            log::info!(
+                "Waiting for the running backfill of {} {} to finish",
+                symbol,
+                interval
+            );
+            tokio::time::sleep(LOCK_RETRY_INTERVAL).await;
+        }
+    }
+
+    /// Releases the lock and returns its connection to the pool.
+    pub async fn release(mut self) -> Result<()> {
+        if let Some(mut conn) = self.conn.take() {
+            sqlx::query_scalar!(
+                r#"SELECT pg_advisory_unlock(hashtextextended($1, 0)) AS "unlocked!""#,
+                self.name
+            )
+            .fetch_one(&mut *conn)
+            .await?;
+            log::debug!("Released lock {}", self.name);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for BackfillLock {
+    fn drop(&mut self) {
+        // Closing the session releases the lock without blocking in `drop`.
+        if let Some(conn) = self.conn.as_mut() {
+            conn.close_on_drop();
+        }
+    }
+}
+
+/// The backfill locks of many symbols, held on one dedicated database connection.
+///
+/// Taking a [`BackfillLock`] per symbol ties up a pooled connection per symbol for
+/// the whole run; a multi-symbol backfill holds its locks here instead, so its pool
+/// only has to serve the workers. The locks are released together by
+/// [`release`](Self::release), or by the connection closing when they are dropped.
+pub struct BackfillLocks {
+    /// The connection holding the locks.
+    conn: PgConnection,
+    /// The names of the locks held.
+    names: Vec<String>,
+}
+
+impl BackfillLocks {
+    /// Opens the connection to hold the locks on, without taking any.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The database connection string.
+    pub async fn connect(url: &str) -> Result<Self> {
+        Ok(Self {
+            conn: PgConnection::connect(url).await?,
+            names: Vec::new(),
+        })
+    }
+
+    /// Tries to take the lock for a symbol and interval without waiting.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbol` - The trading symbol.
+    /// * `interval` - The Kline interval.
+    /// * `dataset` - The dataset label the klines are stored under.
+    /// * `exchange` - The exchange the klines are fetched from (e.g., "binance").
+    ///
+    /// # Returns
+    ///
+    /// `true` if the lock was taken, or `false` if another backfill holds it.
+    pub async fn try_acquire(
+        &mut self,
+        symbol: &str,
+        interval: &str,
+        dataset: &str,
+        exchange: &str,
+    ) -> Result<bool> {
+        let name = BackfillLock::name(symbol, interval, dataset, exchange);
+        let locked = sqlx::query_scalar!(
+            r#"SELECT pg_try_advisory_lock(hashtextextended($1, 0)) AS "locked!""#,
+            name
+        )
+        .fetch_one(&mut self.conn)
+        .await?;
+        if locked {
+            log::debug!("Acquired lock {}", name);
+            self.names.push(name);
+        }
+        Ok(locked)
+    }
+
+    /// Releases all locks by closing their connection.
+    pub async fn release(self) -> Result<()> {
+        self.conn.close().await?;
+        log::debug!("Released locks {}", self.names.join(", "));
+        Ok(())
+    }
+}
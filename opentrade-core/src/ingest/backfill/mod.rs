@@ -15,7 +15,10 @@
 //!
 //! ## Submodules
 //!
+//! - [`discovery`] - Discovery of the earliest data available on the exchange
+//! - [`funding`] - Funding rate history of perpetual futures contracts
 //! - [`klines`] - Kline (candlestick) data backfill operations and utilities
+//! - [`lock`] - Advisory locks preventing concurrent backfills of the same data
 //!
 //! ## Usage Patterns
 //!
@@ -30,7 +33,7 @@
 //!     symbol: &str,
 //!     start_time: DateTime<Utc>,
 //!     end_time: DateTime<Utc>
-//! ) -> Result<(), Box<dyn std::error::Error>> {
+//! ) -> opentrade_core::error::Result<()> {
 //!     // Implementation details available in klines submodule
 //!     Ok(())
 //! }
@@ -42,4 +45,7 @@
 //! (klines, trades, etc.) has its own specialized processor that can operate
 //! independently or in coordination with other processors.
 
-pub mod klines;
\ No newline at end of file
+pub mod discovery;
+pub mod funding;
+pub mod klines;
+pub mod lock;
\ No newline at end of file
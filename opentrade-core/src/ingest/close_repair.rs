@@ -0,0 +1,428 @@
+//! # Close Repair
+//!
+//! This module provides [`CloseRepairSink`], a pipeline sink that repairs streamed
+//! candles whose final update was missed.
+//!
+//! A stream sends several updates per candle and marks the last one as final
+//! (`x=true`). When that update is lost, e.g. because the connection dropped just
+//! before the candle closed, the stored candle keeps the values of an earlier,
+//! partial update. The sink remembers the latest update of every stream and, once
+//! an update of a later candle arrives while the previous candle was never
+//! finalized, re-fetches the previous candle over REST. If the final values differ
+//! from the stored ones, the stored candle is overwritten and the correction is
+//! recorded as a [`KlineCorrection`].
+//!
+//! The sink does not store streamed updates itself, so it runs next to a sink that
+//! does, such as [`UpsertSink`](crate::ingest::pipeline::UpsertSink).
+//!
+//! ## Finalization Timer
+//!
+//! Without a later update, e.g. on a long interval or when the stream stalls, the
+//! repair waits until the next candle starts trading. With
+//! [`with_finalizer`](CloseRepairSink::with_finalizer), a background task counts
+//! down to the end of every open candle on the exchange clock (see
+//! [`clock`](crate::data_source::clock)) and, if no final update arrived within the
+//! grace period after the end, marks the candle closed and repairs it. Updates of
+//! a candle closed by the timer are ignored afterwards.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use opentrade_core::data_source::exchange::{Binance, MarketDataSource};
+//! use opentrade_core::ingest::close_repair::CloseRepairSink;
+//! use opentrade_core::ingest::pipeline::{Pipeline, StreamSource, UpsertSink};
+//! use std::time::Duration;
+//! # use anyhow::Result;
+//!
+//! # async fn example(pool: sqlx::PgPool) -> Result<()> {
+//! let client = Binance.stream_klines("BTCUSDT", "1m").await?;
+//! Pipeline::builder("btcusdt-1m")
+//!     .source(StreamSource::new(client))
+//!     .sink(UpsertSink::new(pool.clone(), "websocket"))
+//!     .sink(CloseRepairSink::new(pool, Binance).with_finalizer(Duration::from_secs(10)))
+//!     .build()?
+//!     .run()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+
+use crate::data_source::clock::clock_offset;
+use crate::data_source::exchange::MarketDataSource;
+use crate::data_source::websocket::MessageHandler;
+use crate::models::kline_correction::KlineCorrection;
+use crate::models::{DEFAULT_DATASET, KlineData, SOURCE_KIND_BACKFILL, SerdableKlineData};
+use crate::time;
+
+/// A sink that re-fetches streamed candles that closed without a final update and
+/// overwrites them when their final values differ.
+pub struct CloseRepairSink {
+    repairer: Repairer,
+    open: Arc<Mutex<OpenCandles>>,
+    finalize_grace: Option<std::time::Duration>,
+    finalizer: Option<(JoinHandle<()>, Arc<Notify>)>,
+}
+
+impl CloseRepairSink {
+    /// Creates a close repair sink.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `source` - The exchange the final values are fetched from.
+    pub fn new(pool: sqlx::PgPool, source: impl MarketDataSource + 'static) -> Self {
+        Self {
+            repairer: Repairer {
+                pool,
+                source: Arc::new(source),
+                dataset: DEFAULT_DATASET.to_string(),
+            },
+            open: Arc::new(Mutex::new(OpenCandles::default())),
+            finalize_grace: None,
+            finalizer: None,
+        }
+    }
+
+    /// Sets the dataset the repaired candles are stored under (defaults to
+    /// [`DEFAULT_DATASET`]).
+    pub fn with_dataset(mut self, dataset: &str) -> Self {
+        self.repairer.dataset = dataset.to_string();
+        self
+    }
+
+    /// Repairs candles that received no final update within `grace` after their
+    /// end, without waiting for an update of the next candle. The timer task is
+    /// started with the first message and stopped when the sink is dropped.
+    pub fn with_finalizer(mut self, grace: std::time::Duration) -> Self {
+        self.finalize_grace = Some(grace);
+        self
+    }
+}
+
+impl Drop for CloseRepairSink {
+    fn drop(&mut self) {
+        if let Some((task, _)) = &self.finalizer {
+            task.abort();
+        }
+    }
+}
+
+/// Fetches final candle values and overwrites the stored ones, shared by the sink
+/// and its finalization timer.
+#[derive(Clone)]
+struct Repairer {
+    pool: sqlx::PgPool,
+    source: Arc<dyn MarketDataSource>,
+    dataset: String,
+}
+
+impl Repairer {
+    /// Fetches the final values of a candle and overwrites the stored candle if
+    /// they differ.
+    ///
+    /// # Returns
+    ///
+    /// The recorded correction, or `None` if the stored candle was already final
+    /// or the exchange did not return the candle.
+    async fn repair(&self, streamed: &SerdableKlineData) -> Result<Option<KlineCorrection>> {
+        let start_time = time::from_unsigned_millis(streamed.start_time)
+            .context("Invalid start time of a streamed candle")?;
+        let fetched = self
+            .source
+            .fetch_klines(
+                &streamed.symbol,
+                &streamed.interval,
+                streamed.start_time,
+                Some(streamed.end_time),
+                Some(1),
+            )
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to fetch the final {} {} candle at {}",
+                    streamed.symbol, streamed.interval, start_time
+                )
+            })?;
+        let Some(fetched) = fetched
+            .into_iter()
+            .find(|kline| kline.start_time == start_time)
+        else {
+            log::warn!(
+                "{} did not return the {} {} candle at {}",
+                self.source.name(),
+                streamed.symbol,
+                streamed.interval,
+                start_time
+            );
+            return Ok(None);
+        };
+        let fetched = fetched
+            .with_dataset(&self.dataset)
+            .with_source_kind(SOURCE_KIND_BACKFILL)
+            .with_exchange(self.source.name());
+
+        let stored = KlineData::list_range(
+            &self.pool,
+            &streamed.symbol,
+            &streamed.interval,
+            start_time,
+            start_time + Duration::milliseconds(1),
+            &self.dataset,
+            &fetched.exchange,
+        )
+        .await?
+        .into_iter()
+        .next();
+        if stored
+            .as_ref()
+            .is_some_and(|stored| !differs(stored, &fetched))
+        {
+            return Ok(None);
+        }
+
+        let mut tx = self.pool.begin().await?;
+        fetched.upsert(&mut *tx).await?;
+        let correction = KlineCorrection::record(&mut *tx, stored.as_ref(), &fetched).await?;
+        tx.commit().await?;
+        log::warn!(
+            "Corrected the {} {} candle at {}: close {} -> {}",
+            fetched.symbol,
+            fetched.interval,
+            start_time,
+            stored.map_or_else(|| "missing".to_string(), |stored| stored.close.to_string()),
+            fetched.close
+        );
+        Ok(Some(correction))
+    }
+}
+
+#[async_trait]
+impl MessageHandler<SerdableKlineData> for CloseRepairSink {
+    async fn handle_message(&mut self, message: &SerdableKlineData) -> Result<()> {
+        if let Some(grace) = self.finalize_grace
+            && self.finalizer.is_none()
+        {
+            let notify = Arc::new(Notify::new());
+            let task = tokio::spawn(run_finalizer(
+                self.repairer.clone(),
+                self.open.clone(),
+                notify.clone(),
+                grace,
+            ));
+            self.finalizer = Some((task, notify));
+        }
+
+        let closed = self.open.lock().unwrap().track(message);
+        if let Some((_, notify)) = &self.finalizer
+            && !message.is_final
+        {
+            // The open candle may have changed, so the countdown starts over.
+            notify.notify_one();
+        }
+        if let Some(closed) = closed {
+            self.repairer.repair(&closed).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Counts down to the end of the earliest open candle plus `grace` and repairs
+/// the candles that are still open then, until the task is aborted.
+async fn run_finalizer(
+    repairer: Repairer,
+    open: Arc<Mutex<OpenCandles>>,
+    notify: Arc<Notify>,
+    grace: std::time::Duration,
+) {
+    let grace_ms = grace.as_millis() as u64;
+    loop {
+        let deadline = open.lock().unwrap().next_deadline(grace_ms);
+        let Some(deadline) = deadline else {
+            notify.notified().await;
+            continue;
+        };
+        let now = (Utc::now() + clock_offset(repairer.source.as_ref()).await).timestamp_millis();
+        let now = now.max(0) as u64;
+        if deadline > now {
+            tokio::select! {
+                _ = tokio::time::sleep(std::time::Duration::from_millis(deadline - now)) => {}
+                _ = notify.notified() => {}
+            }
+            continue;
+        }
+
+        let expired = open.lock().unwrap().expire(now, grace_ms);
+        for closed in expired {
+            log::info!(
+                "No final update of the {} {} candle at {} within {:?}, repairing it",
+                closed.symbol,
+                closed.interval,
+                closed.start_time,
+                grace
+            );
+            if let Err(e) = repairer.repair(&closed).await {
+                log::warn!("{:#}", e);
+            }
+        }
+    }
+}
+
+/// The latest update of the candle that is still open, per symbol and interval.
+#[derive(Debug, Default)]
+struct OpenCandles {
+    open: HashMap<(String, String), SerdableKlineData>,
+    /// The start time of the latest candle closed by the finalization timer.
+    expired: HashMap<(String, String), u64>,
+}
+
+impl OpenCandles {
+    /// Tracks an update and returns the last update of the previous candle of its
+    /// stream if that candle closed without a final update.
+    ///
+    /// Late updates of an earlier candle, or of a candle closed by
+    /// [`expire`](Self::expire), are ignored.
+    fn track(&mut self, message: &SerdableKlineData) -> Option<SerdableKlineData> {
+        let key = (message.symbol.clone(), message.interval.clone());
+        if self
+            .expired
+            .get(&key)
+            .is_some_and(|start_time| message.start_time <= *start_time)
+        {
+            return None;
+        }
+        let closed = match self.open.get(&key) {
+            Some(open) if open.start_time < message.start_time => self.open.remove(&key),
+            Some(open) if open.start_time > message.start_time => return None,
+            _ => None,
+        };
+        if message.is_final {
+            self.open.remove(&key);
+        } else {
+            self.open.insert(key, message.clone());
+        }
+        closed
+    }
+
+    /// Returns the time in milliseconds since the epoch at which the earliest open
+    /// candle expires, `grace_ms` after its end.
+    fn next_deadline(&self, grace_ms: u64) -> Option<u64> {
+        self.open
+            .values()
+            .map(|open| open.end_time + 1 + grace_ms)
+            .min()
+    }
+
+    /// Closes and returns the open candles that ended more than `grace_ms` before
+    /// `now`.
+    fn expire(&mut self, now: u64, grace_ms: u64) -> Vec<SerdableKlineData> {
+        let keys: Vec<_> = self
+            .open
+            .iter()
+            .filter(|(_, open)| open.end_time + 1 + grace_ms <= now)
+            .map(|(key, _)| key.clone())
+            .collect();
+        keys.into_iter()
+            .filter_map(|key| {
+                let closed = self.open.remove(&key)?;
+                self.expired.insert(key, closed.start_time);
+                Some(closed)
+            })
+            .collect()
+    }
+}
+
+/// Returns true if the values of two versions of a candle differ.
+fn differs(stored: &KlineData, fetched: &KlineData) -> bool {
+    stored.end_time != fetched.end_time
+        || stored.last_trade_id != fetched.last_trade_id
+        || stored.open != fetched.open
+        || stored.high != fetched.high
+        || stored.low != fetched.low
+        || stored.close != fetched.close
+        || stored.volume != fetched.volume
+        || stored.trade_count != fetched.trade_count
+        || stored.quote_volume != fetched.quote_volume
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(start_time: u64, close: &str, is_final: bool) -> SerdableKlineData {
+        SerdableKlineData {
+            start_time,
+            end_time: start_time + 59_999,
+            symbol: "BTCUSDT".to_string(),
+            interval: "1m".to_string(),
+            first_trade_id: 1,
+            last_trade_id: 2,
+            open: "100".to_string(),
+            high: "110".to_string(),
+            low: "90".to_string(),
+            close: close.to_string(),
+            volume: "1".to_string(),
+            trade_count: 2,
+            quote_volume: "100".to_string(),
+            event_time: None,
+            is_final,
+            exchange: None,
+        }
+    }
+
+    #[test]
+    fn test_track_returns_candles_closed_without_final_update() {
+        let mut open = OpenCandles::default();
+        assert!(open.track(&update(0, "100", false)).is_none());
+        assert!(open.track(&update(0, "101", false)).is_none());
+        let closed = open.track(&update(60_000, "102", false)).unwrap();
+        assert_eq!(closed.start_time, 0);
+        assert_eq!(closed.close, "101");
+
+        // A final update closes the candle, so the next one needs no repair.
+        assert!(open.track(&update(60_000, "103", true)).is_none());
+        assert!(open.track(&update(120_000, "104", false)).is_none());
+
+        // Late updates of an earlier candle are ignored.
+        assert!(open.track(&update(60_000, "105", false)).is_none());
+        let closed = open.track(&update(180_000, "106", false)).unwrap();
+        assert_eq!(closed.start_time, 120_000);
+    }
+
+    #[test]
+    fn test_expire_closes_candles_after_grace() {
+        let mut open = OpenCandles::default();
+        assert_eq!(open.next_deadline(5_000), None);
+        open.track(&update(0, "100", false));
+        assert_eq!(open.next_deadline(5_000), Some(65_000));
+        assert!(open.expire(64_999, 5_000).is_empty());
+        let expired = open.expire(65_000, 5_000);
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].close, "100");
+        assert_eq!(open.next_deadline(5_000), None);
+
+        // Late updates of the expired candle neither reopen nor repair it again.
+        assert!(open.track(&update(0, "101", true)).is_none());
+        assert!(open.track(&update(0, "101", false)).is_none());
+        assert!(open.track(&update(60_000, "102", false)).is_none());
+        assert_eq!(open.next_deadline(5_000), Some(125_000));
+    }
+
+    #[test]
+    fn test_differs_compares_values() {
+        let stored = KlineData::from(update(0, "100", false));
+        assert!(!differs(&stored, &stored.clone()));
+        let mut fetched = stored.clone().with_source_kind(SOURCE_KIND_BACKFILL);
+        assert!(!differs(&stored, &fetched));
+        fetched.close = "101".parse().unwrap();
+        assert!(differs(&stored, &fetched));
+    }
+}
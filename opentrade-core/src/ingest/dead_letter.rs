@@ -0,0 +1,285 @@
+//! # Dead Letters
+//!
+//! This module provides destinations for messages that a [`Pipeline`] sink could
+//! not handle, so that a failing sink does not silently lose data.
+//!
+//! When a sink keeps failing after the attempts allowed by the pipeline's
+//! [`RetryPolicy`], the pipeline wraps the message and the error in a
+//! [`DeadLetter`] and writes it to the configured [`DeadLetterSink`] instead of
+//! stopping. The remaining sinks still receive the message.
+//!
+//! ## Destinations
+//!
+//! - [`QuarantineDeadLetters`] - The `quarantine` table, with the source
+//!   [`DEAD_LETTER_SOURCE`], so dead letters can be reprocessed like rows that
+//!   failed validation
+//! - [`FileDeadLetters`] - A newline-delimited JSON file, for when the database
+//!   itself is the failing sink
+//! - [`ChannelDeadLetters`] - A channel, e.g. to publish dead letters to a
+//!   message bus topic from another task
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use opentrade_core::data_source::websocket::KlineStreaming;
+//! use opentrade_core::ingest::dead_letter::FileDeadLetters;
+//! use opentrade_core::ingest::pipeline::{Pipeline, RetryPolicy, StreamSource, UpsertSink};
+//! use opentrade_core::models::SerdableKlineData;
+//! use binance_spot_connector_rust::market::klines::KlineInterval;
+//! # use anyhow::Result;
+//!
+//! # async fn example(pool: sqlx::PgPool) -> Result<()> {
+//! let stream = KlineStreaming::new("BTCUSDT", KlineInterval::Minutes1).await?;
+//!
+//! let pipeline = Pipeline::<SerdableKlineData>::builder("btcusdt-1m")
+//!     .source(StreamSource::new(stream))
+//!     .sink(UpsertSink::new(pool, "websocket"))
+//!     .retry(RetryPolicy::default())
+//!     .dead_letter(FileDeadLetters::new("dead-letters.jsonl"))
+//!     .build()?;
+//!
+//! let report = pipeline.run().await?;
+//! println!("{} messages dead-lettered", report.dead_lettered);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Pipeline`]: crate::ingest::pipeline::Pipeline
+//! [`RetryPolicy`]: crate::ingest::pipeline::RetryPolicy
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::fs::{File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+
+use crate::data_source::websocket::IngestContext;
+use crate::models::quarantine::QuarantinedRow;
+use crate::models::{DEFAULT_DATASET, SerdableKlineData};
+
+/// The source recorded for dead letters written to the quarantine table.
+pub const DEAD_LETTER_SOURCE: &str = "dead_letter";
+
+/// A message a sink failed to handle, with the details of the failure.
+#[derive(Debug)]
+pub struct DeadLetter<T> {
+    /// The name of the pipeline.
+    pub pipeline: String,
+    /// The position of the failing sink in the pipeline, starting at 0.
+    pub sink: usize,
+    /// How many times the sink was called before giving up.
+    pub attempts: u32,
+    /// The error of the last attempt, including its causes.
+    pub error: String,
+    /// The context of the message.
+    pub context: IngestContext,
+    /// When the message was given up on.
+    pub failed_at: DateTime<Utc>,
+    /// The message.
+    pub message: Arc<T>,
+}
+
+impl<T> DeadLetter<T> {
+    /// Returns a one-line description of the failure.
+    pub fn reason(&self) -> String {
+        format!(
+            "sink {} of pipeline '{}' failed after {} attempts: {}",
+            self.sink, self.pipeline, self.attempts, self.error
+        )
+    }
+}
+
+impl<T: Serialize> DeadLetter<T> {
+    /// Serializes the dead letter, including the message, as a JSON object.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the message cannot be serialized.
+    pub fn to_json(&self) -> Result<serde_json::Value> {
+        Ok(serde_json::json!({
+            "pipeline": self.pipeline,
+            "sink": self.sink,
+            "attempts": self.attempts,
+            "error": self.error,
+            "exchange": self.context.exchange,
+            "stream": self.context.stream,
+            "received_at": self.context.received_at,
+            "generation": self.context.generation,
+            "failed_at": self.failed_at,
+            "message": serde_json::to_value(self.message.as_ref())?,
+        }))
+    }
+}
+
+/// A destination for [`DeadLetter`]s.
+///
+/// A failing dead-letter destination stops the pipeline, as there is nowhere
+/// left to put the message.
+#[async_trait]
+pub trait DeadLetterSink<T>: Send {
+    /// Writes a dead letter.
+    async fn write(&mut self, letter: DeadLetter<T>) -> Result<()>;
+}
+
+/// Writes dead Kline messages to the `quarantine` table with the source
+/// [`DEAD_LETTER_SOURCE`] and the failure as the reason.
+///
+/// Once the failing sink has been fixed, the rows can be recovered with
+/// [`reprocess`](crate::ingest::reprocess::reprocess) by filtering on that source.
+pub struct QuarantineDeadLetters {
+    pool: sqlx::PgPool,
+    dataset: String,
+}
+
+impl QuarantineDeadLetters {
+    /// Creates a destination writing to the quarantine table of `pool`.
+    pub fn new(pool: sqlx::PgPool) -> Self {
+        Self {
+            pool,
+            dataset: DEFAULT_DATASET.to_string(),
+        }
+    }
+
+    /// Sets the dataset recorded for the rows (defaults to [`DEFAULT_DATASET`]).
+    pub fn with_dataset(mut self, dataset: &str) -> Self {
+        self.dataset = dataset.to_string();
+        self
+    }
+}
+
+#[async_trait]
+impl DeadLetterSink<SerdableKlineData> for QuarantineDeadLetters {
+    async fn write(&mut self, letter: DeadLetter<SerdableKlineData>) -> Result<()> {
+        QuarantinedRow::add_kline(
+            &self.pool,
+            DEAD_LETTER_SOURCE,
+            &letter.message,
+            &letter.reason(),
+            &self.dataset,
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+/// Appends dead letters to a file as newline-delimited JSON objects (see
+/// [`DeadLetter::to_json`]).
+///
+/// The file is created if it does not exist and opened on the first write.
+pub struct FileDeadLetters {
+    path: PathBuf,
+    file: Option<File>,
+}
+
+impl FileDeadLetters {
+    /// Creates a destination appending to `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            file: None,
+        }
+    }
+}
+
+#[async_trait]
+impl<T: Serialize + Send + Sync + 'static> DeadLetterSink<T> for FileDeadLetters {
+    async fn write(&mut self, letter: DeadLetter<T>) -> Result<()> {
+        if self.file.is_none() {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+                .await
+                .with_context(|| {
+                    format!("Failed to open dead-letter file {}", self.path.display())
+                })?;
+            self.file = Some(file);
+        }
+        let mut line = serde_json::to_string(&letter.to_json()?)?;
+        line.push('\n');
+        let file = self.file.as_mut().expect("file was opened above");
+        file.write_all(line.as_bytes()).await?;
+        file.flush().await?;
+        Ok(())
+    }
+}
+
+/// Forwards dead letters to a channel, e.g. to a task publishing them to a
+/// message bus topic.
+///
+/// Waits when the channel is full and fails when the receiver has been dropped.
+pub struct ChannelDeadLetters<T> {
+    sender: mpsc::Sender<DeadLetter<T>>,
+}
+
+impl<T> ChannelDeadLetters<T> {
+    /// Creates a destination forwarding to `sender`.
+    pub fn new(sender: mpsc::Sender<DeadLetter<T>>) -> Self {
+        Self { sender }
+    }
+}
+
+#[async_trait]
+impl<T: Send + Sync + 'static> DeadLetterSink<T> for ChannelDeadLetters<T> {
+    async fn write(&mut self, letter: DeadLetter<T>) -> Result<()> {
+        self.sender
+            .send(letter)
+            .await
+            .map_err(|_| anyhow::anyhow!("Dead-letter channel is closed"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn letter(message: u64) -> DeadLetter<u64> {
+        DeadLetter {
+            pipeline: "test".to_string(),
+            sink: 1,
+            attempts: 3,
+            error: "connection refused".to_string(),
+            context: IngestContext::new("binance", "btcusdt@kline_1m"),
+            failed_at: Utc::now(),
+            message: Arc::new(message),
+        }
+    }
+
+    #[test]
+    fn test_reason_names_sink_and_attempts() {
+        assert_eq!(
+            letter(7).reason(),
+            "sink 1 of pipeline 'test' failed after 3 attempts: connection refused"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_file_dead_letters_append_json_lines() {
+        let path = std::env::temp_dir().join(format!(
+            "opentrade-dead-letters-{}.jsonl",
+            std::process::id()
+        ));
+        let _ = tokio::fs::remove_file(&path).await;
+
+        let mut sink = FileDeadLetters::new(&path);
+        sink.write(letter(7)).await.unwrap();
+        sink.write(letter(8)).await.unwrap();
+
+        let contents = tokio::fs::read_to_string(&path).await.unwrap();
+        let lines: Vec<serde_json::Value> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["message"], 7);
+        assert_eq!(lines[1]["message"], 8);
+        assert_eq!(lines[0]["stream"], "btcusdt@kline_1m");
+        assert_eq!(lines[0]["attempts"], 3);
+        tokio::fs::remove_file(&path).await.unwrap();
+    }
+}
@@ -0,0 +1,152 @@
+//! # Event Logs
+//!
+//! This module provides an [`EventLogSink`] that emits Kline messages as
+//! [`NormalizedEvent`]s to a file, e.g. for downstream consumers tailing the file
+//! or a log shipper forwarding it to a message bus. Events are written as
+//! newline-delimited JSON by default, or as length-delimited protobuf messages
+//! with [`EventFormat::Protobuf`] (requires the `protobuf` feature). JSON events
+//! carry prices as exact decimal strings unless the sink is configured with
+//! [`DecimalFormat::Float`] for consumers that prefer numbers.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use opentrade_core::data_source::websocket::KlineStreaming;
+//! use opentrade_core::ingest::event_log::EventLogSink;
+//! use opentrade_core::ingest::pipeline::{Pipeline, StreamSource};
+//! use opentrade_core::models::SerdableKlineData;
+//! use binance_spot_connector_rust::market::klines::KlineInterval;
+//! # use anyhow::Result;
+//!
+//! # async fn example() -> Result<()> {
+//! let stream = KlineStreaming::new("BTCUSDT", KlineInterval::Minutes1).await?;
+//!
+//! Pipeline::<SerdableKlineData>::builder("btcusdt-1m")
+//!     .source(StreamSource::new(stream))
+//!     .sink(EventLogSink::new("events.jsonl"))
+//!     .build()?
+//!     .run()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use tokio::fs::{File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+
+use crate::data_source::websocket::{IngestContext, MessageHandler};
+use crate::ingest::pipeline::UNKNOWN_EXCHANGE;
+use crate::models::SerdableKlineData;
+use crate::models::event::{DecimalFormat, EventFormat, NormalizedEvent};
+
+/// A sink that appends every message to a file as a [`NormalizedEvent`] encoded
+/// in an [`EventFormat`] (JSON by default).
+///
+/// The file is created if it does not exist and opened on the first message.
+/// Messages handled without an [`IngestContext`] are attributed to the
+/// [`UNKNOWN_EXCHANGE`] and the file name.
+pub struct EventLogSink {
+    path: PathBuf,
+    format: EventFormat,
+    decimals: DecimalFormat,
+    file: Option<File>,
+}
+
+impl EventLogSink {
+    /// Creates a sink appending to `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            format: EventFormat::default(),
+            decimals: DecimalFormat::default(),
+            file: None,
+        }
+    }
+
+    /// Sets the format events are encoded in.
+    pub fn with_format(mut self, format: EventFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Sets how prices and quantities are represented in JSON events (strings by
+    /// default). See [`DecimalFormat`] for the precision tradeoffs.
+    pub fn with_decimal_format(mut self, decimals: DecimalFormat) -> Self {
+        self.decimals = decimals;
+        self
+    }
+
+    /// Appends an event to the file, opening it first if needed.
+    async fn write(&mut self, event: &NormalizedEvent) -> Result<()> {
+        if self.file.is_none() {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+                .await
+                .with_context(|| format!("Failed to open event log {}", self.path.display()))?;
+            self.file = Some(file);
+        }
+        let bytes = event.encode_with(self.format, self.decimals)?;
+        let file = self.file.as_mut().expect("file was opened above");
+        file.write_all(&bytes).await?;
+        file.flush().await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl MessageHandler<SerdableKlineData> for EventLogSink {
+    async fn handle_message(&mut self, message: &SerdableKlineData) -> Result<()> {
+        let context = IngestContext::new(UNKNOWN_EXCHANGE, &self.path.to_string_lossy());
+        self.handle_message_with_context(message, &context).await
+    }
+
+    async fn handle_message_with_context(
+        &mut self,
+        message: &SerdableKlineData,
+        context: &IngestContext,
+    ) -> Result<()> {
+        self.write(&NormalizedEvent::from_kline(message, context)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::event::EventPayload;
+
+    #[tokio::test]
+    async fn test_messages_are_appended_as_normalized_events() {
+        let path =
+            std::env::temp_dir().join(format!("opentrade-events-{}.jsonl", std::process::id()));
+        let _ = tokio::fs::remove_file(&path).await;
+        let message: SerdableKlineData = serde_json::from_str(
+            r#"{"t":0,"T":59999,"s":"BTCUSDT","i":"1m","f":1,"L":2,"o":"1","c":"1","h":"1","l":"1","v":"1","n":2,"q":"1","x":true}"#,
+        )
+        .unwrap();
+
+        let mut sink = EventLogSink::new(&path);
+        let context = IngestContext::new("binance", "btcusdt@kline_1m");
+        sink.handle_message_with_context(&message, &context)
+            .await
+            .unwrap();
+        sink.handle_message(&message).await.unwrap();
+
+        let contents = tokio::fs::read_to_string(&path).await.unwrap();
+        let events: Vec<NormalizedEvent> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].stream, "btcusdt@kline_1m");
+        assert_eq!(events[1].exchange, UNKNOWN_EXCHANGE);
+        let EventPayload::Kline(kline) = &events[0].payload;
+        assert!(kline.is_final);
+        tokio::fs::remove_file(&path).await.unwrap();
+    }
+}
@@ -0,0 +1,510 @@
+//! # Exports
+//!
+//! This module writes stored candles of a dataset and exchange to CSV or JSON
+//! lines files for downstream tools. An [`ExportSpec`] selects the candles and
+//! the columns to write, so exports match the schema a consumer expects without
+//! post-processing:
+//!
+//! - Symbols are matched by glob patterns, where `*` matches any run of characters
+//!   and `?` a single one (e.g., `*USDT` for every USDT pair).
+//! - Intervals, a start time range and final candles only (candles whose end has
+//!   passed) further restrict the rows.
+//! - Columns are written in the order given (see [`ExportColumn`]).
+//!
+//! Timestamps are written as milliseconds since the UNIX epoch and decimals as
+//! strings, like [`SerdableKlineData`](crate::models::SerdableKlineData). Unlike
+//! [`snapshot`](super::snapshot) archives, exports are not meant to be restored.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use opentrade_core::ingest::export::{ExportColumn, ExportFormat, ExportSpec, export};
+//! use sqlx::PgPool;
+//!
+//! # async fn example(pool: &PgPool) -> anyhow::Result<()> {
+//! let spec = ExportSpec::new()
+//!     .with_symbols(&["*USDT"])
+//!     .with_intervals(&["1h"])
+//!     .final_only()
+//!     .with_columns(&ExportColumn::parse_list("start_time,symbol,close,volume")?);
+//! let rows = export(
+//!     pool,
+//!     "default",
+//!     "binance",
+//!     &spec,
+//!     ExportFormat::Csv,
+//!     "usdt-1h.csv",
+//! )
+//! .await?;
+//! println!("Exported {} candles", rows);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Utc};
+use futures_util::TryStreamExt;
+use serde_json::Value;
+
+use crate::models::KlineData;
+
+/// A column of `kline_data` that can be exported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportColumn {
+    StartTime,
+    EndTime,
+    Symbol,
+    Interval,
+    FirstTradeId,
+    LastTradeId,
+    Open,
+    High,
+    Low,
+    Close,
+    Volume,
+    TradeCount,
+    QuoteVolume,
+    Dataset,
+    SourceKind,
+    Exchange,
+}
+
+impl ExportColumn {
+    /// Every exportable column, in table order.
+    pub const ALL: [ExportColumn; 16] = [
+        ExportColumn::StartTime,
+        ExportColumn::EndTime,
+        ExportColumn::Symbol,
+        ExportColumn::Interval,
+        ExportColumn::FirstTradeId,
+        ExportColumn::LastTradeId,
+        ExportColumn::Open,
+        ExportColumn::High,
+        ExportColumn::Low,
+        ExportColumn::Close,
+        ExportColumn::Volume,
+        ExportColumn::TradeCount,
+        ExportColumn::QuoteVolume,
+        ExportColumn::Dataset,
+        ExportColumn::SourceKind,
+        ExportColumn::Exchange,
+    ];
+
+    /// Returns the column name, as in `kline_data`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportColumn::StartTime => "start_time",
+            ExportColumn::EndTime => "end_time",
+            ExportColumn::Symbol => "symbol",
+            ExportColumn::Interval => "interval",
+            ExportColumn::FirstTradeId => "first_trade_id",
+            ExportColumn::LastTradeId => "last_trade_id",
+            ExportColumn::Open => "open",
+            ExportColumn::High => "high",
+            ExportColumn::Low => "low",
+            ExportColumn::Close => "close",
+            ExportColumn::Volume => "volume",
+            ExportColumn::TradeCount => "trade_count",
+            ExportColumn::QuoteVolume => "quote_volume",
+            ExportColumn::Dataset => "dataset",
+            ExportColumn::SourceKind => "source_kind",
+            ExportColumn::Exchange => "exchange",
+        }
+    }
+
+    /// Parses a comma-separated list of column names (e.g., "start_time,close").
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a name is not a column or the list is empty.
+    pub fn parse_list(list: &str) -> Result<Vec<ExportColumn>> {
+        let columns = list
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(str::parse)
+            .collect::<Result<Vec<_>>>()?;
+        if columns.is_empty() {
+            bail!("No columns to export");
+        }
+        Ok(columns)
+    }
+
+    /// Returns the value of the column in a candle. Timestamps are milliseconds
+    /// since the UNIX epoch and decimals are strings.
+    pub fn value(&self, kline: &KlineData) -> Value {
+        match self {
+            ExportColumn::StartTime => kline.start_time.timestamp_millis().into(),
+            ExportColumn::EndTime => kline.end_time.timestamp_millis().into(),
+            ExportColumn::Symbol => kline.symbol.clone().into(),
+            ExportColumn::Interval => kline.interval.clone().into(),
+            ExportColumn::FirstTradeId => kline.first_trade_id.into(),
+            ExportColumn::LastTradeId => kline.last_trade_id.into(),
+            ExportColumn::Open => kline.open.to_string().into(),
+            ExportColumn::High => kline.high.to_string().into(),
+            ExportColumn::Low => kline.low.to_string().into(),
+            ExportColumn::Close => kline.close.to_string().into(),
+            ExportColumn::Volume => kline.volume.to_string().into(),
+            ExportColumn::TradeCount => kline.trade_count.into(),
+            ExportColumn::QuoteVolume => {
+                kline.quote_volume.as_ref().map(ToString::to_string).into()
+            }
+            ExportColumn::Dataset => kline.dataset.clone().into(),
+            ExportColumn::SourceKind => kline.source_kind.clone().into(),
+            ExportColumn::Exchange => kline.exchange.clone().into(),
+        }
+    }
+}
+
+impl FromStr for ExportColumn {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        ExportColumn::ALL
+            .into_iter()
+            .find(|column| column.name() == name)
+            .with_context(|| format!("Unknown export column: {}", name))
+    }
+}
+
+/// The file format of an export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Comma-separated values with a header row.
+    Csv,
+    /// One JSON object per line, keyed by column name.
+    JsonLines,
+}
+
+impl FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(format: &str) -> Result<Self> {
+        match format {
+            "csv" => Ok(ExportFormat::Csv),
+            "ndjson" | "jsonl" => Ok(ExportFormat::JsonLines),
+            _ => bail!("Unknown export format: {} (expected csv or ndjson)", format),
+        }
+    }
+}
+
+/// Selects the candles of an export and the columns written for them.
+///
+/// An empty symbol or interval list matches every symbol or interval.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportSpec {
+    symbols: Vec<String>,
+    intervals: Vec<String>,
+    start_time: Option<DateTime<Utc>>,
+    end_time: Option<DateTime<Utc>>,
+    final_only: bool,
+    columns: Vec<ExportColumn>,
+}
+
+impl Default for ExportSpec {
+    fn default() -> Self {
+        Self {
+            symbols: Vec::new(),
+            intervals: Vec::new(),
+            start_time: None,
+            end_time: None,
+            final_only: false,
+            columns: ExportColumn::ALL.to_vec(),
+        }
+    }
+}
+
+impl ExportSpec {
+    /// Creates a spec exporting every column of every candle.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Restricts the export to the symbols matching any of the glob patterns
+    /// (e.g., "*USDT", "BTC*", "ETHUSDT").
+    pub fn with_symbols<S: AsRef<str>>(mut self, patterns: &[S]) -> Self {
+        self.symbols = patterns
+            .iter()
+            .map(|pattern| pattern.as_ref().to_uppercase())
+            .collect();
+        self
+    }
+
+    /// Restricts the export to the given intervals (e.g., "1m", "1h").
+    pub fn with_intervals<S: AsRef<str>>(mut self, intervals: &[S]) -> Self {
+        self.intervals = intervals
+            .iter()
+            .map(|interval| interval.as_ref().to_string())
+            .collect();
+        self
+    }
+
+    /// Restricts the export to candles starting in `[start_time, end_time)`;
+    /// either bound may be open.
+    pub fn with_range(
+        mut self,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+    ) -> Self {
+        self.start_time = start_time;
+        self.end_time = end_time;
+        self
+    }
+
+    /// Restricts the export to final candles, whose end time has passed.
+    pub fn final_only(mut self) -> Self {
+        self.final_only = true;
+        self
+    }
+
+    /// Sets the columns written, in order (defaults to [`ExportColumn::ALL`]).
+    pub fn with_columns(mut self, columns: &[ExportColumn]) -> Self {
+        self.columns = columns.to_vec();
+        self
+    }
+
+    /// Returns the columns written, in order.
+    pub fn columns(&self) -> &[ExportColumn] {
+        &self.columns
+    }
+
+    /// Renders the header line of an export, if the format has one.
+    pub fn header(&self, format: ExportFormat) -> Option<String> {
+        match format {
+            ExportFormat::Csv => Some(
+                self.columns
+                    .iter()
+                    .map(|column| column.name())
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+            ExportFormat::JsonLines => None,
+        }
+    }
+
+    /// Renders a candle as one line of an export, without the line break.
+    pub fn render(&self, kline: &KlineData, format: ExportFormat) -> String {
+        match format {
+            ExportFormat::Csv => {
+                let mut line = String::new();
+                for (i, column) in self.columns.iter().enumerate() {
+                    if i > 0 {
+                        line.push(',');
+                    }
+                    match column.value(kline) {
+                        Value::Null => {}
+                        Value::String(value) => line.push_str(&csv_field(&value)),
+                        value => {
+                            let _ = write!(line, "{}", value);
+                        }
+                    }
+                }
+                line
+            }
+            ExportFormat::JsonLines => {
+                let object: serde_json::Map<String, Value> = self
+                    .columns
+                    .iter()
+                    .map(|column| (column.name().to_string(), column.value(kline)))
+                    .collect();
+                Value::Object(object).to_string()
+            }
+        }
+    }
+}
+
+/// Converts a symbol glob pattern into a SQL `LIKE` pattern.
+///
+/// # Example
+///
+/// ```rust
+/// use opentrade_core::ingest::export::like_pattern;
+///
+/// assert_eq!(like_pattern("*USDT"), "%USDT");
+/// assert_eq!(like_pattern("BTC?"), "BTC_");
+/// ```
+pub fn like_pattern(glob: &str) -> String {
+    let mut pattern = String::with_capacity(glob.len());
+    for c in glob.chars() {
+        match c {
+            '*' => pattern.push('%'),
+            '?' => pattern.push('_'),
+            '%' | '_' | '\\' => {
+                pattern.push('\\');
+                pattern.push(c);
+            }
+            c => pattern.push(c),
+        }
+    }
+    pattern
+}
+
+/// Quotes a CSV field if it contains a separator, a quote or a line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Writes the candles of a dataset and exchange selected by a spec to a file,
+/// ordered by symbol, interval and start time.
+///
+/// # Arguments
+///
+/// * `pool` - The database connection pool.
+/// * `dataset` - The dataset to export.
+/// * `exchange` - The exchange whose candles are exported (e.g., "binance").
+/// * `spec` - The candles and columns to export.
+/// * `format` - The file format.
+/// * `path` - The path of the file to create.
+///
+/// # Returns
+///
+/// The number of exported candles.
+pub async fn export(
+    pool: &sqlx::PgPool,
+    dataset: &str,
+    exchange: &str,
+    spec: &ExportSpec,
+    format: ExportFormat,
+    path: impl AsRef<Path>,
+) -> Result<usize> {
+    let path = path.as_ref();
+    if spec.columns.is_empty() {
+        bail!("No columns to export");
+    }
+    let file =
+        File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut writer = BufWriter::new(file);
+    if let Some(header) = spec.header(format) {
+        writeln!(writer, "{}", header)?;
+    }
+
+    let symbols: Vec<String> = spec.symbols.iter().map(|glob| like_pattern(glob)).collect();
+    let mut klines = sqlx::query_as!(
+        KlineData,
+        r#"
+        SELECT * FROM kline_data
+        WHERE dataset = $1 AND exchange = $2
+          AND (cardinality($3::text[]) = 0 OR symbol LIKE ANY($3))
+          AND (cardinality($4::text[]) = 0 OR interval = ANY($4))
+          AND ($5::timestamptz IS NULL OR start_time >= $5)
+          AND ($6::timestamptz IS NULL OR start_time < $6)
+          AND (NOT $7 OR end_time < NOW())
+        ORDER BY symbol, interval, start_time
+        "#,
+        dataset,
+        exchange,
+        &symbols,
+        &spec.intervals,
+        spec.start_time,
+        spec.end_time,
+        spec.final_only
+    )
+    .fetch(pool);
+    let mut rows = 0;
+    while let Some(kline) = klines.try_next().await? {
+        writeln!(writer, "{}", spec.render(&kline, format))?;
+        rows += 1;
+    }
+    writer
+        .flush()
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    log::info!(
+        "Exported {} candles of dataset {} ({}) to {}",
+        rows,
+        dataset,
+        exchange,
+        path.display()
+    );
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::types::BigDecimal as Decimal;
+
+    fn kline() -> KlineData {
+        let price = |value: &str| Decimal::from_str(value).unwrap();
+        KlineData::new(
+            &1_751_897_340_000,
+            &1_751_897_399_999,
+            "BTCUSDT",
+            "1m",
+            1,
+            2,
+            price("108344.44"),
+            price("108352.04"),
+            price("108344.44"),
+            price("108352.04"),
+            price("1.51598"),
+            Some(281),
+            Some(price("164254.26458")),
+        )
+    }
+
+    #[test]
+    fn test_parse_columns() {
+        let columns = ExportColumn::parse_list("start_time, close,volume").unwrap();
+        assert_eq!(
+            columns,
+            vec![
+                ExportColumn::StartTime,
+                ExportColumn::Close,
+                ExportColumn::Volume
+            ]
+        );
+        assert!(ExportColumn::parse_list("start_time,vwap").is_err());
+        assert!(ExportColumn::parse_list("").is_err());
+        assert_eq!(
+            "ndjson".parse::<ExportFormat>().unwrap(),
+            ExportFormat::JsonLines
+        );
+    }
+
+    #[test]
+    fn test_render_projected_columns() {
+        let spec = ExportSpec::new().with_columns(&[
+            ExportColumn::StartTime,
+            ExportColumn::Symbol,
+            ExportColumn::Close,
+            ExportColumn::QuoteVolume,
+        ]);
+        assert_eq!(
+            spec.header(ExportFormat::Csv).as_deref(),
+            Some("start_time,symbol,close,quote_volume")
+        );
+        let mut kline = kline();
+        assert_eq!(
+            spec.render(&kline, ExportFormat::Csv),
+            "1751897340000,BTCUSDT,108352.04,164254.26458"
+        );
+        kline.quote_volume = None;
+        assert_eq!(
+            spec.render(&kline, ExportFormat::Csv),
+            "1751897340000,BTCUSDT,108352.04,"
+        );
+        let json: Value =
+            serde_json::from_str(&spec.render(&kline, ExportFormat::JsonLines)).unwrap();
+        assert_eq!(json["close"], "108352.04");
+        assert_eq!(json["quote_volume"], Value::Null);
+        assert_eq!(json.as_object().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_like_pattern_escapes_wildcards() {
+        assert_eq!(like_pattern("*USD?"), "%USD_");
+        assert_eq!(like_pattern("A_B%"), "A\\_B\\%");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+    }
+}
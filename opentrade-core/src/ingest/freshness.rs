@@ -0,0 +1,408 @@
+//! # Freshness Monitoring
+//!
+//! This module provides a [`FreshnessMonitor`] that periodically checks that the
+//! latest stored candle of every monitored symbol and interval is recent, and
+//! raises an alert when ingestion falls behind.
+//!
+//! ## SLA
+//!
+//! The SLA of a target is breached when more than `max_missing_intervals` closed
+//! candles are missing after the latest stored one, or when no candles are stored
+//! at all. The candle that is still open is never counted as missing. Calendar
+//! intervals (`1w`, `1M`) have no fixed length and are reported but never breach.
+//!
+//! Which candles are closed is decided on the local clock, or on the clock of an
+//! exchange set with [`FreshnessMonitor::with_exchange_clock`], so that a drifted
+//! host does not expect a candle that the exchange has not closed yet.
+//!
+//! Candles starting within a maintenance window of the exchange set with
+//! [`FreshnessMonitor::with_maintenance`] are not expected, so a maintenance does
+//! not breach the SLA (see [`maintenance`](super::maintenance)).
+//!
+//! ## Alerts and Metrics
+//!
+//! Transitions are logged and published as [`FreshnessEvent`]s on a broadcast
+//! channel obtained with [`FreshnessMonitor::subscribe`], so a breach is reported
+//! once rather than on every check. The result of the latest check of every target
+//! is available from [`FreshnessMonitor::statuses`] for exporting as metrics.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use opentrade_core::ingest::freshness::FreshnessMonitor;
+//! use opentrade_core::models::{DEFAULT_DATASET, DEFAULT_EXCHANGE};
+//! use std::time::Duration;
+//! # use anyhow::Result;
+//!
+//! # async fn example(pool: sqlx::PgPool) -> Result<()> {
+//! let monitor = FreshnessMonitor::new(3, Duration::from_secs(60))
+//!     .with_target("BTCUSDT", "1m", DEFAULT_DATASET, DEFAULT_EXCHANGE)
+//!     .with_target("ETHUSDT", "1h", DEFAULT_DATASET, DEFAULT_EXCHANGE);
+//!
+//! let mut events = monitor.subscribe();
+//! tokio::spawn(async move {
+//!     while let Ok(event) = events.recv().await {
+//!         println!("{:?}", event);
+//!     }
+//! });
+//! monitor.run(&pool).await
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use tokio::sync::broadcast;
+
+use crate::data_source::clock::server_now;
+use crate::data_source::exchange::MarketDataSource;
+use crate::ingest::audit::{Gap, exclude_ranges, interval_duration};
+use crate::ingest::maintenance::MaintenanceCalendar;
+use crate::models::KlineData;
+
+/// A symbol, interval, dataset and exchange whose freshness is monitored.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FreshnessTarget {
+    /// The trading symbol.
+    pub symbol: String,
+    /// The Kline interval.
+    pub interval: String,
+    /// The dataset label.
+    pub dataset: String,
+    /// The exchange the candles are ingested from.
+    pub exchange: String,
+}
+
+/// The result of checking the freshness of a single target.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FreshnessStatus {
+    /// The checked target.
+    pub target: FreshnessTarget,
+    /// The start time of the latest stored candle, if any.
+    pub latest_start: Option<DateTime<Utc>>,
+    /// The number of closed candles missing after the latest stored one, or `None`
+    /// if no candles are stored or the interval has no fixed length. Candles
+    /// starting within a maintenance window are not counted.
+    pub missing_intervals: Option<i64>,
+    /// Whether the exchange is in a maintenance window at the time of the check.
+    pub in_maintenance: bool,
+    /// Whether the SLA is breached.
+    pub breached: bool,
+    /// When the check was made.
+    pub checked_at: DateTime<Utc>,
+}
+
+/// A change of the SLA state of a target, emitted by the [`FreshnessMonitor`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FreshnessEvent {
+    /// The target fell behind its SLA.
+    Breached(FreshnessStatus),
+    /// The target caught up again after a breach.
+    Recovered(FreshnessStatus),
+}
+
+/// Returns how many closed candles of length `step` are missing after the candle
+/// starting at `latest_start`.
+///
+/// Candle start times are aligned to multiples of `step` since the Unix epoch. The
+/// candle containing `now` is still open and is not counted.
+pub fn missing_intervals(
+    latest_start: DateTime<Utc>,
+    step: chrono::Duration,
+    now: DateTime<Utc>,
+) -> i64 {
+    let step = step.num_milliseconds();
+    let open_start = now.timestamp_millis().div_euclid(step) * step;
+    let last_closed_start = open_start - step;
+    ((last_closed_start - latest_start.timestamp_millis()) / step).max(0)
+}
+
+/// Periodically checks the freshness of stored candles. See the [module documentation](self).
+pub struct FreshnessMonitor {
+    targets: Vec<FreshnessTarget>,
+    max_missing_intervals: i64,
+    period: Duration,
+    events: broadcast::Sender<FreshnessEvent>,
+    statuses: Mutex<HashMap<FreshnessTarget, FreshnessStatus>>,
+    clock: Option<Box<dyn MarketDataSource>>,
+    maintenance: Option<(MaintenanceCalendar, String)>,
+}
+
+impl FreshnessMonitor {
+    /// Creates a monitor without targets.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_missing_intervals` - The number of missing closed candles tolerated
+    ///   before the SLA is breached
+    /// * `period` - The time between checks
+    pub fn new(max_missing_intervals: u32, period: Duration) -> Self {
+        let (events, _) = broadcast::channel(256);
+        Self {
+            targets: Vec::new(),
+            max_missing_intervals: i64::from(max_missing_intervals),
+            period,
+            events,
+            statuses: Mutex::new(HashMap::new()),
+            clock: None,
+            maintenance: None,
+        }
+    }
+
+    /// Adds a target to monitor.
+    pub fn with_target(
+        mut self,
+        symbol: &str,
+        interval: &str,
+        dataset: &str,
+        exchange: &str,
+    ) -> Self {
+        self.targets.push(FreshnessTarget {
+            symbol: symbol.to_string(),
+            interval: interval.to_string(),
+            dataset: dataset.to_string(),
+            exchange: exchange.to_string(),
+        });
+        self
+    }
+
+    /// Decides which candles are closed on the clock of an exchange (see
+    /// [`server_now`]) instead of the local clock.
+    pub fn with_exchange_clock(mut self, source: impl MarketDataSource + 'static) -> Self {
+        self.clock = Some(Box::new(source));
+        self
+    }
+
+    /// Leaves the candles starting within the maintenance windows of an exchange
+    /// out of the expected candles.
+    ///
+    /// # Arguments
+    ///
+    /// * `calendar` - The known maintenance windows
+    /// * `exchange` - The exchange the targets are ingested from (e.g., "binance")
+    pub fn with_maintenance(mut self, calendar: MaintenanceCalendar, exchange: &str) -> Self {
+        self.maintenance = Some((calendar, exchange.to_string()));
+        self
+    }
+
+    /// Subscribes to SLA transitions. Only events emitted after subscribing are received.
+    pub fn subscribe(&self) -> broadcast::Receiver<FreshnessEvent> {
+        self.events.subscribe()
+    }
+
+    /// Returns the result of the latest check of every target checked so far.
+    pub fn statuses(&self) -> Vec<FreshnessStatus> {
+        let mut statuses: Vec<FreshnessStatus> =
+            self.statuses.lock().unwrap().values().cloned().collect();
+        statuses.sort_by(|a, b| a.target.cmp(&b.target));
+        statuses
+    }
+
+    /// Checks every target once, emitting an event for every target whose SLA
+    /// state changed since the previous check.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the latest candle of a target cannot be queried.
+    pub async fn check(&self, pool: &sqlx::PgPool) -> Result<Vec<FreshnessStatus>> {
+        let now = match &self.clock {
+            Some(source) => server_now(source.as_ref()).await,
+            None => Utc::now(),
+        };
+        let mut results = Vec::with_capacity(self.targets.len());
+        for target in &self.targets {
+            let latest_start = KlineData::latest_start_time(
+                pool,
+                &target.symbol,
+                &target.interval,
+                &target.dataset,
+                &target.exchange,
+            )
+            .await?;
+            let status = self.evaluate(target, latest_start, now);
+            self.record(status.clone());
+            results.push(status);
+        }
+        Ok(results)
+    }
+
+    /// Checks every target once per period until an error occurs.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error of [`check`](Self::check).
+    pub async fn run(&self, pool: &sqlx::PgPool) -> Result<()> {
+        let mut ticker = tokio::time::interval(self.period);
+        loop {
+            ticker.tick().await;
+            self.check(pool).await?;
+        }
+    }
+
+    /// Computes the status of a target from its latest stored candle.
+    fn evaluate(
+        &self,
+        target: &FreshnessTarget,
+        latest_start: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> FreshnessStatus {
+        let step = interval_duration(&target.interval);
+        let missing = latest_start.zip(step).map(|(latest_start, step)| {
+            let missing = missing_intervals(latest_start, step, now);
+            self.without_maintenance(latest_start, step, missing)
+        });
+        let breached = match (latest_start, missing) {
+            (None, _) => true,
+            (Some(_), Some(missing)) => missing > self.max_missing_intervals,
+            (Some(_), None) => false,
+        };
+        FreshnessStatus {
+            target: target.clone(),
+            latest_start,
+            missing_intervals: missing,
+            in_maintenance: self
+                .maintenance
+                .as_ref()
+                .is_some_and(|(calendar, exchange)| calendar.active(exchange, now).is_some()),
+            breached,
+            checked_at: now,
+        }
+    }
+
+    /// Returns how many of the `missing` candles after the one starting at
+    /// `latest_start` do not start within a maintenance window.
+    fn without_maintenance(
+        &self,
+        latest_start: DateTime<Utc>,
+        step: chrono::Duration,
+        missing: i64,
+    ) -> i64 {
+        let Some((calendar, exchange)) = &self.maintenance else {
+            return missing;
+        };
+        if missing == 0 {
+            return 0;
+        }
+        let start = latest_start + step;
+        let end = start + chrono::Duration::milliseconds(step.num_milliseconds() * missing);
+        let gap = Gap {
+            start,
+            end,
+            missing,
+        };
+        exclude_ranges(&[gap], &calendar.ranges(exchange, start, end), step)
+            .iter()
+            .map(|gap| gap.missing)
+            .sum()
+    }
+
+    /// Stores a status and emits an event if its SLA state changed.
+    fn record(&self, status: FreshnessStatus) {
+        let previous = self
+            .statuses
+            .lock()
+            .unwrap()
+            .insert(status.target.clone(), status.clone());
+        let was_breached = previous.is_some_and(|previous| previous.breached);
+        let event = match (was_breached, status.breached) {
+            (false, true) => {
+                log::warn!(
+                    "Freshness SLA breached for {} {} ({}): latest candle {:?}, {:?} intervals missing",
+                    status.target.symbol,
+                    status.target.interval,
+                    status.target.dataset,
+                    status.latest_start,
+                    status.missing_intervals
+                );
+                FreshnessEvent::Breached(status)
+            }
+            (true, false) => {
+                log::info!(
+                    "Freshness SLA recovered for {} {} ({})",
+                    status.target.symbol,
+                    status.target.interval,
+                    status.target.dataset
+                );
+                FreshnessEvent::Recovered(status)
+            }
+            _ => return,
+        };
+        // Sending only fails when there are no subscribers, which is fine.
+        let _ = self.events.send(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingest::maintenance::MaintenanceWindow;
+
+    fn at(minutes: i64, seconds: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_600_000_020 * 60 + minutes * 60 + seconds, 0).unwrap()
+    }
+
+    #[test]
+    fn test_missing_intervals_ignores_open_candle() {
+        let step = chrono::Duration::minutes(1);
+        // At 10:30 into minute 10, minute 9 is the latest closed candle.
+        assert_eq!(missing_intervals(at(9, 0), step, at(10, 30)), 0);
+        assert_eq!(missing_intervals(at(10, 0), step, at(10, 30)), 0);
+        assert_eq!(missing_intervals(at(5, 0), step, at(10, 30)), 4);
+    }
+
+    #[test]
+    fn test_breach_and_recovery_are_emitted_once() {
+        let monitor = FreshnessMonitor::new(2, Duration::from_secs(60))
+            .with_target("BTCUSDT", "1m", "default", "binance");
+        let target = monitor.targets[0].clone();
+        let mut events = monitor.subscribe();
+
+        monitor.record(monitor.evaluate(&target, Some(at(9, 0)), at(10, 30)));
+        monitor.record(monitor.evaluate(&target, Some(at(5, 0)), at(10, 30)));
+        monitor.record(monitor.evaluate(&target, Some(at(5, 0)), at(11, 30)));
+        monitor.record(monitor.evaluate(&target, Some(at(10, 0)), at(11, 30)));
+
+        assert!(matches!(
+            events.try_recv(),
+            Ok(FreshnessEvent::Breached(status)) if status.missing_intervals == Some(4)
+        ));
+        assert!(matches!(events.try_recv(), Ok(FreshnessEvent::Recovered(_))));
+        assert!(events.try_recv().is_err());
+        assert!(!monitor.statuses()[0].breached);
+    }
+
+    #[test]
+    fn test_maintenance_windows_are_not_expected() {
+        let window = MaintenanceWindow::new("binance", at(3, 0), at(8, 0));
+        let calendar = MaintenanceCalendar::default().with_window(window);
+        let monitor = FreshnessMonitor::new(2, Duration::from_secs(60))
+            .with_target("BTCUSDT", "1m", "default", "binance")
+            .with_maintenance(calendar, "binance");
+        let target = monitor.targets[0].clone();
+
+        // Minutes 3 to 7 fall into the window, leaving 8 and 9 missing.
+        let status = monitor.evaluate(&target, Some(at(2, 0)), at(10, 30));
+        assert_eq!(status.missing_intervals, Some(2));
+        assert!(!status.breached);
+        assert!(!status.in_maintenance);
+        let status = monitor.evaluate(&target, Some(at(2, 0)), at(5, 0));
+        assert!(status.in_maintenance);
+    }
+
+    #[test]
+    fn test_missing_data_breaches_and_calendar_intervals_do_not() {
+        let monitor = FreshnessMonitor::new(2, Duration::from_secs(60))
+            .with_target("BTCUSDT", "1m", "default", "binance")
+            .with_target("BTCUSDT", "1M", "default", "binance");
+        let minute = monitor.targets[0].clone();
+        let month = monitor.targets[1].clone();
+
+        assert!(monitor.evaluate(&minute, None, at(0, 0)).breached);
+        let status = monitor.evaluate(&month, Some(at(0, 0)), at(100_000, 0));
+        assert_eq!(status.missing_intervals, None);
+        assert!(!status.breached);
+    }
+}
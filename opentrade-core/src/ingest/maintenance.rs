@@ -0,0 +1,232 @@
+//! # Exchange Maintenance Windows
+//!
+//! Exchanges announce maintenance during which they stop trading and publish no
+//! candles. Without knowing about it, the freshness monitor reports a breach and
+//! gap audits report missing candles that no ingestion could have stored.
+//!
+//! A [`MaintenanceCalendar`] holds the known [`MaintenanceWindow`]s per exchange.
+//! Components that expect data take one with a `with_maintenance` builder or
+//! argument and leave the candles starting within a window out of their
+//! expectations:
+//!
+//! - [`FreshnessMonitor`](super::freshness::FreshnessMonitor) does not count them as
+//!   missing, so a maintenance does not breach the SLA.
+//! - [`repair_gaps`](super::backfill::klines::repair_gaps) does not request them.
+//! - [`GapReport::excluding`](super::audit::GapReport::excluding) and
+//!   [`QualityReport::excluding_maintenance`](super::quality::QualityReport::excluding_maintenance)
+//!   drop them from gap reports.
+//!
+//! ## Configuration
+//!
+//! [`MaintenanceCalendar::from_env`] reads the windows from the environment:
+//!
+//! - `MAINTENANCE_CALENDAR` - Path of a JSON file with a list of windows
+//! - `MAINTENANCE_WINDOWS` - JSON list of windows, added to those of the file
+//!
+//! Each window names the exchange as returned by
+//! [`MarketDataSource::name`](crate::data_source::exchange::MarketDataSource::name),
+//! or "*" for every exchange, and its `[start, end)` range in RFC 3339:
+//!
+//! ```json
+//! [
+//!     {
+//!         "exchange": "binance",
+//!         "start": "2023-03-24T13:00:00Z",
+//!         "end": "2023-03-24T14:30:00Z",
+//!         "reason": "Matching engine upgrade"
+//!     }
+//! ]
+//! ```
+//!
+//! ## Example
+//!
+//! ```rust
+//! use chrono::{TimeZone, Utc};
+//! use opentrade_core::ingest::maintenance::{MaintenanceCalendar, MaintenanceWindow};
+//!
+//! let start = Utc.with_ymd_and_hms(2023, 3, 24, 13, 0, 0).unwrap();
+//! let end = Utc.with_ymd_and_hms(2023, 3, 24, 14, 30, 0).unwrap();
+//! let calendar = MaintenanceCalendar::default()
+//!     .with_window(MaintenanceWindow::new("binance", start, end));
+//!
+//! assert!(calendar.active("binance", start).is_some());
+//! assert!(calendar.active("binance", end).is_none());
+//! assert!(calendar.active("binance-futures", start).is_none());
+//! ```
+
+use std::fmt;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// The exchange name of windows that apply to every exchange.
+pub const ALL_EXCHANGES: &str = "*";
+
+/// A range during which an exchange does not publish data.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaintenanceWindow {
+    /// The exchange, or [`ALL_EXCHANGES`].
+    pub exchange: String,
+    /// The inclusive start of the window.
+    pub start: DateTime<Utc>,
+    /// The exclusive end of the window.
+    pub end: DateTime<Utc>,
+    /// Why the exchange is down, e.g. the title of its announcement.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl MaintenanceWindow {
+    /// Creates a window without a reason.
+    pub fn new(exchange: &str, start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        Self {
+            exchange: exchange.to_string(),
+            start,
+            end,
+            reason: None,
+        }
+    }
+
+    /// Sets why the exchange is down.
+    pub fn with_reason(mut self, reason: &str) -> Self {
+        self.reason = Some(reason.to_string());
+        self
+    }
+
+    /// Returns true if the window applies to `exchange`.
+    pub fn applies_to(&self, exchange: &str) -> bool {
+        self.exchange == ALL_EXCHANGES || self.exchange == exchange
+    }
+
+    /// Returns true if `time` is within the window.
+    pub fn contains(&self, time: DateTime<Utc>) -> bool {
+        self.start <= time && time < self.end
+    }
+}
+
+impl fmt::Display for MaintenanceWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} maintenance from {} to {}",
+            self.exchange, self.start, self.end
+        )?;
+        if let Some(reason) = &self.reason {
+            write!(f, " ({})", reason)?;
+        }
+        Ok(())
+    }
+}
+
+/// The known maintenance windows of the exchanges. See the [module documentation](self).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MaintenanceCalendar {
+    windows: Vec<MaintenanceWindow>,
+}
+
+impl MaintenanceCalendar {
+    /// Creates a calendar of the given windows.
+    pub fn new(windows: Vec<MaintenanceWindow>) -> Self {
+        Self { windows }
+    }
+
+    /// Reads a calendar from a JSON file with a list of windows.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a valid list of windows.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read maintenance calendar {}", path.display()))?;
+        let windows = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse maintenance calendar {}", path.display()))?;
+        Ok(Self::new(windows))
+    }
+
+    /// Reads the calendar from the `MAINTENANCE_CALENDAR` file and the
+    /// `MAINTENANCE_WINDOWS` list. The calendar is empty if neither is set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read, or the file or
+    /// `MAINTENANCE_WINDOWS` is not a valid list of windows.
+    pub fn from_env() -> Result<Self> {
+        let mut calendar = match std::env::var("MAINTENANCE_CALENDAR") {
+            Ok(path) => Self::from_file(path)?,
+            Err(_) => Self::default(),
+        };
+        if let Ok(windows) = std::env::var("MAINTENANCE_WINDOWS") {
+            let windows: Vec<MaintenanceWindow> =
+                serde_json::from_str(&windows).context("Failed to parse MAINTENANCE_WINDOWS")?;
+            calendar.windows.extend(windows);
+        }
+        Ok(calendar)
+    }
+
+    /// Adds a window.
+    pub fn with_window(mut self, window: MaintenanceWindow) -> Self {
+        self.windows.push(window);
+        self
+    }
+
+    /// Returns true if the calendar has no windows.
+    pub fn is_empty(&self) -> bool {
+        self.windows.is_empty()
+    }
+
+    /// Returns the window of an exchange that contains `time`, if any.
+    pub fn active(&self, exchange: &str, time: DateTime<Utc>) -> Option<&MaintenanceWindow> {
+        self.windows
+            .iter()
+            .find(|window| window.applies_to(exchange) && window.contains(time))
+    }
+
+    /// Returns the `[start, end)` ranges of the windows of an exchange that
+    /// overlap `[start, end)`, ordered by start.
+    pub fn ranges(
+        &self,
+        exchange: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+        let mut ranges: Vec<(DateTime<Utc>, DateTime<Utc>)> = self
+            .windows
+            .iter()
+            .filter(|window| window.applies_to(exchange))
+            .filter(|window| window.start < end && start < window.end)
+            .map(|window| (window.start, window.end))
+            .collect();
+        ranges.sort();
+        ranges
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_windows_and_select_ranges() {
+        let json = r#"[
+            {"exchange": "binance", "start": "2023-03-24T13:00:00Z", "end": "2023-03-24T14:30:00Z"},
+            {"exchange": "*", "start": "2023-03-20T00:00:00Z", "end": "2023-03-20T01:00:00Z", "reason": "DNS"}
+        ]"#;
+        let calendar = MaintenanceCalendar::new(serde_json::from_str(json).unwrap());
+        let at = |value: &str| value.parse::<DateTime<Utc>>().unwrap();
+
+        let (march, april) = (at("2023-03-01T00:00:00Z"), at("2023-04-01T00:00:00Z"));
+
+        let ranges = calendar.ranges("binance", march, april);
+        assert_eq!(ranges.len(), 2);
+        assert_eq!(ranges[0].0, at("2023-03-20T00:00:00Z"));
+        assert_eq!(calendar.ranges("binance-futures", march, april).len(), 1);
+        let after = at("2023-03-24T14:30:00Z");
+        assert!(calendar.ranges("binance", after, april).is_empty());
+
+        let window = calendar.active("okx", at("2023-03-20T00:30:00Z")).unwrap();
+        assert_eq!(window.reason.as_deref(), Some("DNS"));
+    }
+}
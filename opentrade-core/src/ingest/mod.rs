@@ -15,7 +15,32 @@
 //!
 //! ## Submodules
 //!
+//! - [`aggregate`] - Derivation of higher-timeframe candles from stored data
+//! - [`audit`] - Gap detection and completeness reports for stored data
 //! - [`backfill`] - Historical data backfill operations and batch processing
+//! - [`close_repair`] - REST repair of streamed candles that closed without a final update
+//! - [`dead_letter`] - Destinations for messages that pipeline sinks failed to handle
+//! - [`event_log`] - Emission of messages as normalized events to JSON lines files
+//! - [`export`] - Filtered and projected exports of stored candles as CSV or JSON lines
+//! - [`freshness`] - Monitoring of the latest stored candle against a freshness SLA
+//! - [`maintenance`] - Calendar of exchange maintenance windows excluded from data expectations
+//! - [`open_interest`] - Polling and history backfill of the open interest of futures contracts
+//! - [`options`] - Snapshots of options marks and greeks for volatility surfaces
+//! - [`pipeline`] - Source → transforms → sinks pipeline builder
+//! - [`precision`] - Per-symbol decimal scales for normalizing prices and quantities on write
+//! - [`quality`] - Data-quality reports over a recent window as Markdown or HTML
+//! - [`recent`] - Columnar in-memory buffer of recent candles for indicator computations
+//! - [`replicate`] - Conflict-safe replication of stored data between databases
+//! - [`reprocess`] - Reprocessing of quarantined rows and archived raw messages
+//! - [`share`] - Down-sampled and noise-perturbed copies of stored data for sharing
+//! - [`snapshot`] - Compressed snapshot archives of a symbol's data and their restore
+//! - [`stats`] - Streaming statistics collection with periodic summaries
+//! - [`status`] - Symbol trading status tracking and delisting detection
+//! - [`symbols`] - Symbol and interval validation against the exchange listing
+//! - [`supervisor`] - Supervised task groups with automatic restart of failed components
+//! - [`synthetic`] - Deterministic synthetic Kline traffic for benchmarks and load tests
+//! - [`triggers`] - Configurable conditions over live candles that emit trigger events
+//! - [`watchlists`] - Named symbol sets that commands accept in place of symbol lists
 //!
 //! ## Usage Patterns
 //!
@@ -34,4 +59,29 @@
 //! various stages of validation, transformation, and storage. Each stage can be
 //! configured independently to meet specific requirements.
 
-pub mod backfill;
\ No newline at end of file
+pub mod aggregate;
+pub mod audit;
+pub mod backfill;
+pub mod close_repair;
+pub mod dead_letter;
+pub mod event_log;
+pub mod export;
+pub mod freshness;
+pub mod maintenance;
+pub mod open_interest;
+pub mod options;
+pub mod pipeline;
+pub mod precision;
+pub mod quality;
+pub mod recent;
+pub mod replicate;
+pub mod reprocess;
+pub mod share;
+pub mod snapshot;
+pub mod stats;
+pub mod status;
+pub mod supervisor;
+pub mod symbols;
+pub mod synthetic;
+pub mod triggers;
+pub mod watchlists;
\ No newline at end of file
@@ -0,0 +1,131 @@
+//! # Open Interest
+//!
+//! This module stores the open interest of Binance USDⓈ-M futures contracts in
+//! the `open_interest` table, keyed by symbol and time. Values come from two
+//! endpoints:
+//!
+//! - [`poll_open_interest`] stores the current open interest of each contract, to
+//!   be called at the poll interval of the caller.
+//! - [`backfill_open_interest`] stores the open interest history at a fixed
+//!   period (e.g., "5m"), which Binance keeps for the last 30 days only, together
+//!   with its notional.
+//!
+//! Both upsert, so polling and backfilling the same range is safe.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use opentrade_core::ingest::open_interest::poll_open_interest;
+//! use opentrade_core::models::DEFAULT_DATASET;
+//! use std::time::Duration;
+//! # use anyhow::Result;
+//!
+//! # async fn example(pool: sqlx::PgPool) -> Result<()> {
+//! let symbols = vec!["BTCUSDT".to_string(), "ETHUSDT".to_string()];
+//! loop {
+//!     let stored = poll_open_interest(&pool, &symbols, DEFAULT_DATASET).await?;
+//!     println!("Stored {} open interest values", stored);
+//!     tokio::time::sleep(Duration::from_secs(60)).await;
+//! }
+//! # }
+//! ```
+
+use anyhow::{Context, Result};
+
+use crate::data_source::rest::{
+    MAX_OPEN_INTEREST_HIST_LIMIT, get_open_interest, get_open_interest_hist,
+};
+use crate::models::open_interest::OpenInterestData;
+
+/// Fetches the current open interest of the given contracts and stores it.
+///
+/// # Arguments
+///
+/// * `pool` - The database connection pool.
+/// * `symbols` - The contract symbols (e.g., "BTCUSDT").
+/// * `dataset` - The dataset label.
+///
+/// # Returns
+///
+/// The number of stored values.
+pub async fn poll_open_interest(
+    pool: &sqlx::PgPool,
+    symbols: &[String],
+    dataset: &str,
+) -> Result<usize> {
+    let mut values = Vec::with_capacity(symbols.len());
+    for symbol in symbols {
+        let current = get_open_interest(symbol)
+            .await
+            .with_context(|| format!("Failed to fetch the open interest of {}", symbol))?;
+        values.push(current.to_open_interest()?.with_dataset(dataset));
+    }
+    OpenInterestData::upsert_batch(pool, &values)
+        .await
+        .context("Failed to store open interest")
+}
+
+/// Backfills the open interest history of a contract at a fixed period.
+///
+/// The history is fetched in pages of [`MAX_OPEN_INTEREST_HIST_LIMIT`] values,
+/// oldest first, until `end_time` or the latest value is reached.
+///
+/// # Arguments
+///
+/// * `pool` - The database connection pool.
+/// * `symbol` - The contract symbol (e.g., "BTCUSDT").
+/// * `period` - The period of the values (e.g., "5m"), see
+///   [`OPEN_INTEREST_PERIODS`](crate::data_source::rest::OPEN_INTEREST_PERIODS).
+/// * `start_time` - The start time in milliseconds since the epoch.
+/// * `end_time` - An optional end time in milliseconds since the epoch.
+/// * `dataset` - The dataset label.
+///
+/// # Returns
+///
+/// The number of stored values.
+pub async fn backfill_open_interest(
+    pool: &sqlx::PgPool,
+    symbol: &str,
+    period: &str,
+    start_time: u64,
+    end_time: Option<u64>,
+    dataset: &str,
+) -> Result<usize> {
+    let mut current_time = start_time;
+    let mut stored = 0;
+    loop {
+        let page = get_open_interest_hist(
+            symbol,
+            period,
+            current_time,
+            end_time,
+            MAX_OPEN_INTEREST_HIST_LIMIT,
+        )
+        .await
+        .with_context(|| format!("Failed to fetch the open interest history of {}", symbol))?;
+        let Some(last) = page.last() else {
+            break;
+        };
+        let next_time = u64::try_from(last.timestamp)
+            .with_context(|| format!("Invalid open interest time: {}", last.timestamp))?
+            + 1;
+        let values = page
+            .iter()
+            .map(|value| Ok(value.to_open_interest()?.with_dataset(dataset)))
+            .collect::<Result<Vec<_>>>()?;
+        stored += OpenInterestData::upsert_batch(pool, &values)
+            .await
+            .context("Failed to store open interest")?;
+        log::info!(
+            "Backfilled {} open interest values for symbol {} up to {}",
+            page.len(),
+            symbol,
+            last.timestamp
+        );
+        if page.len() < MAX_OPEN_INTEREST_HIST_LIMIT as usize || next_time <= current_time {
+            break;
+        }
+        current_time = next_time;
+    }
+    Ok(stored)
+}
@@ -0,0 +1,130 @@
+//! # Options Snapshots
+//!
+//! This module stores snapshots of the marks, implied volatilities and greeks of
+//! options contracts in the `option_marks` table. All contracts of a snapshot are
+//! stored with the same `observed_at`, so a snapshot of one underlying can be read
+//! back as a volatility surface with
+//! [`OptionMark::list_surface`](crate::models::option::OptionMark::list_surface).
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use opentrade_core::ingest::options::snapshot_option_marks;
+//! use opentrade_core::models::DEFAULT_DATASET;
+//! use chrono::Utc;
+//! # use anyhow::Result;
+//!
+//! # async fn example(pool: sqlx::PgPool) -> Result<()> {
+//! let underlyings = vec!["BTC".to_string(), "ETH".to_string()];
+//! let stored = snapshot_option_marks(&pool, &underlyings, DEFAULT_DATASET, Utc::now()).await?;
+//! println!("Stored {} option marks", stored);
+//! # Ok(())
+//! # }
+//! ```
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+
+use crate::data_source::options::{OptionMarkPayload, fetch_option_marks};
+use crate::models::option::OptionMark;
+
+/// Converts the payloads of the given underlyings into rows of one snapshot.
+///
+/// Payloads that cannot be converted are logged and skipped, so that one malformed
+/// contract does not drop the whole snapshot.
+///
+/// # Arguments
+///
+/// * `payloads` - The payloads returned by the exchange.
+/// * `underlyings` - The underlyings to keep (e.g., "BTC"); all if empty.
+/// * `dataset` - The dataset label.
+/// * `observed_at` - The time of the snapshot.
+pub fn snapshot_rows(
+    payloads: &[OptionMarkPayload],
+    underlyings: &[String],
+    dataset: &str,
+    observed_at: DateTime<Utc>,
+) -> Vec<OptionMark> {
+    payloads
+        .iter()
+        .filter_map(|payload| match payload.to_option_mark(observed_at) {
+            Ok(mark) => Some(mark),
+            Err(e) => {
+                log::warn!("Skipping option mark: {:#}", e);
+                None
+            }
+        })
+        .filter(|mark| underlyings.is_empty() || underlyings.contains(&mark.underlying))
+        .map(|mark| OptionMark {
+            dataset: dataset.to_string(),
+            ..mark
+        })
+        .collect()
+}
+
+/// Fetches the marks of all listed options contracts and stores those of the
+/// given underlyings as one snapshot.
+///
+/// # Arguments
+///
+/// * `pool` - The database connection pool.
+/// * `underlyings` - The underlyings to store (e.g., "BTC"); all if empty.
+/// * `dataset` - The dataset label.
+/// * `observed_at` - The time of the snapshot.
+///
+/// # Returns
+///
+/// The number of stored marks.
+pub async fn snapshot_option_marks(
+    pool: &sqlx::PgPool,
+    underlyings: &[String],
+    dataset: &str,
+    observed_at: DateTime<Utc>,
+) -> Result<usize> {
+    let payloads = fetch_option_marks().await?;
+    let marks = snapshot_rows(&payloads, underlyings, dataset, observed_at);
+    OptionMark::upsert_batch(pool, &marks)
+        .await
+        .context("Failed to store option marks")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payload(symbol: &str, mark_price: &str) -> OptionMarkPayload {
+        OptionMarkPayload {
+            symbol: symbol.to_string(),
+            mark_price: mark_price.to_string(),
+            bid_iv: None,
+            ask_iv: None,
+            mark_iv: Some("0.5".to_string()),
+            delta: None,
+            gamma: None,
+            theta: None,
+            vega: None,
+        }
+    }
+
+    #[test]
+    fn test_snapshot_rows_filters_underlyings_and_skips_invalid() {
+        let payloads = vec![
+            payload("BTC-250926-100000-C", "1200"),
+            payload("ETH-250926-4000-P", "80"),
+            payload("BTC-250926-90000-P", "n/a"),
+            payload("BTCUSDT", "1"),
+        ];
+        let observed_at = Utc::now();
+
+        let rows = snapshot_rows(&payloads, &["BTC".to_string()], "research", observed_at);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].symbol, "BTC-250926-100000-C");
+        assert_eq!(rows[0].dataset, "research");
+        assert_eq!(rows[0].observed_at, observed_at);
+
+        assert_eq!(
+            snapshot_rows(&payloads, &[], "research", observed_at).len(),
+            2
+        );
+    }
+}
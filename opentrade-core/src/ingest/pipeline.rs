@@ -0,0 +1,1085 @@
+//! # Ingestion Pipelines
+//!
+//! This module provides a [`Pipeline`] builder that wires a message source, an
+//! ordered chain of transforms, and one or more sinks into a single runnable unit.
+//! It formalizes the "create a stream, register handlers, listen" sequence that
+//! the pipeline binaries otherwise implement by hand.
+//!
+//! ## Building Blocks
+//!
+//! - **Sources** implement [`Source`]. Built-in sources are:
+//!   - [`StreamSource`] - Any WebSocket [`StreamingClient`] (live data)
+//!   - [`ReplaySource`] - Stored Kline data replayed from PostgreSQL
+//!   - [`FileSource`] - Newline-delimited JSON files
+//! - **Transforms** implement [`Transform`] and are applied in order between the
+//!   source and the sinks; a transform may drop a message by returning `None`.
+//!   Closures can be used directly, and [`RenameSymbols`] and [`FinalOnly`] are
+//!   built-in transforms for Kline messages.
+//! - **Sinks** are [`MessageHandler`] implementations, called in registration order.
+//!   [`UpsertSink`] is a built-in sink that stores Kline messages in PostgreSQL.
+//!   Sinks that keep or forward messages can implement [`SharedMessageHandler`]
+//!   instead and receive the message as an [`Arc`] shared by all sinks.
+//! - **Retries and dead letters**: a failing sink is retried according to the
+//!   pipeline's [`RetryPolicy`]. When it still fails, the message is written to the
+//!   pipeline's [`DeadLetterSink`], if one is configured, or the pipeline stops.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use opentrade_core::data_source::websocket::KlineStreaming;
+//! use opentrade_core::ingest::pipeline::{Pipeline, StreamSource};
+//! use opentrade_core::ingest::stats::StatsHandler;
+//! use opentrade_core::models::SerdableKlineData;
+//! use binance_spot_connector_rust::market::klines::KlineInterval;
+//! use std::time::Duration;
+//! # use anyhow::Result;
+//!
+//! # async fn example() -> Result<()> {
+//! let stream = KlineStreaming::new("BTCUSDT", KlineInterval::Minutes1).await?;
+//!
+//! let pipeline = Pipeline::<SerdableKlineData>::builder("btcusdt-1m")
+//!     .source(StreamSource::new(stream))
+//!     .filter(|kline| kline.trade_count > 0)
+//!     .sink(StatsHandler::new(Duration::from_secs(60)))
+//!     .build()?;
+//!
+//! let report = pipeline.run().await?;
+//! println!("Delivered {} messages", report.delivered);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::{HashMap, VecDeque};
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Instant;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde::de::DeserializeOwned;
+use tokio::fs::File;
+use tokio::io::{AsyncBufReadExt, BufReader, Lines};
+use tokio::task::JoinHandle;
+
+use crate::data_source::websocket::{
+    Borrowed, IngestContext, MessageHandler, SharedMessageHandler, StreamingClient,
+};
+use crate::ingest::dead_letter::{DeadLetter, DeadLetterSink};
+use crate::ingest::stats::StreamStats;
+use crate::models::cache::KlineCache;
+use crate::models::quarantine::QuarantinedRow;
+use crate::models::{
+    DEFAULT_DATASET, DEFAULT_EXCHANGE, DecimalScale, KlineData, SOURCE_KIND_STREAM,
+    SerdableKlineData,
+};
+
+/// The exchange recorded in the [`IngestContext`] of messages from sources
+/// without stream metadata.
+pub const UNKNOWN_EXCHANGE: &str = "unknown";
+
+/// A producer of messages for a [`Pipeline`].
+///
+/// The contract of [`next`](Source::next) mirrors [`StreamingClient::next`]:
+/// `Ok(None)` signals that the source is exhausted, `Ok(Some(Err(_)))` reports a
+/// single bad message, and `Err(_)` is a fatal source failure.
+#[async_trait]
+pub trait Source<T>: Send {
+    /// Prepares the source before the first message is read (connect, subscribe, open).
+    async fn start(&mut self) -> Result<()>;
+
+    /// Returns the next message from the source.
+    async fn next(&mut self) -> Result<Option<Result<T>>>;
+
+    /// Returns the [`IngestContext`] of the message most recently returned by
+    /// [`next`](Source::next).
+    ///
+    /// Sources without stream metadata keep the default, which returns `None`; the
+    /// pipeline then passes sinks a context named after the pipeline.
+    fn context(&self) -> Option<IngestContext> {
+        None
+    }
+}
+
+/// A [`Source`] backed by a live WebSocket [`StreamingClient`].
+///
+/// The client is subscribed when the pipeline starts.
+pub struct StreamSource<C> {
+    client: C,
+}
+
+impl<C> StreamSource<C> {
+    /// Wraps a streaming client as a pipeline source.
+    pub fn new(client: C) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl<T, C> Source<T> for StreamSource<C>
+where
+    T: Send + Sync + 'static,
+    C: StreamingClient<T>,
+{
+    async fn start(&mut self) -> Result<()> {
+        self.client.subscribe().await
+    }
+
+    async fn next(&mut self) -> Result<Option<Result<T>>> {
+        self.client.next().await
+    }
+
+    fn context(&self) -> Option<IngestContext> {
+        self.client.context()
+    }
+}
+
+/// A [`Source`] that replays stored Kline data from PostgreSQL in start-time order.
+///
+/// Rows are loaded in chunks of `chunk` duration so that long ranges can be
+/// replayed without loading them into memory at once.
+pub struct ReplaySource {
+    pool: sqlx::PgPool,
+    symbol: String,
+    interval: String,
+    dataset: String,
+    exchange: String,
+    cursor: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    chunk: Duration,
+    buffer: VecDeque<KlineData>,
+}
+
+impl ReplaySource {
+    /// Creates a replay source over `[start_time, end_time)` for a symbol and interval.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `symbol` - The trading symbol (e.g., "BTCUSDT").
+    /// * `interval` - The Kline interval (e.g., "1m").
+    /// * `start_time` - The inclusive start of the replay range.
+    /// * `end_time` - The exclusive end of the replay range.
+    pub fn new(
+        pool: sqlx::PgPool,
+        symbol: &str,
+        interval: &str,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            pool,
+            symbol: symbol.to_string(),
+            interval: interval.to_string(),
+            dataset: DEFAULT_DATASET.to_string(),
+            exchange: DEFAULT_EXCHANGE.to_string(),
+            cursor: start_time,
+            end_time,
+            chunk: Duration::days(1),
+            buffer: VecDeque::new(),
+        }
+    }
+
+    /// Sets the time span loaded from the database per query (defaults to one day).
+    pub fn with_chunk(mut self, chunk: Duration) -> Self {
+        self.chunk = chunk;
+        self
+    }
+
+    /// Sets the dataset to replay (defaults to [`DEFAULT_DATASET`]).
+    pub fn with_dataset(mut self, dataset: &str) -> Self {
+        self.dataset = dataset.to_string();
+        self
+    }
+
+    /// Sets the exchange whose candles are replayed (defaults to
+    /// [`DEFAULT_EXCHANGE`]).
+    pub fn with_exchange(mut self, exchange: &str) -> Self {
+        self.exchange = exchange.to_string();
+        self
+    }
+}
+
+#[async_trait]
+impl Source<SerdableKlineData> for ReplaySource {
+    async fn start(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn next(&mut self) -> Result<Option<Result<SerdableKlineData>>> {
+        while self.buffer.is_empty() && self.cursor < self.end_time {
+            let chunk_end = (self.cursor + self.chunk).min(self.end_time);
+            let klines = KlineData::list_range(
+                &self.pool,
+                &self.symbol,
+                &self.interval,
+                self.cursor,
+                chunk_end,
+                &self.dataset,
+                &self.exchange,
+            )
+            .await?;
+            self.buffer.extend(klines);
+            self.cursor = chunk_end;
+        }
+        Ok(self.buffer.pop_front().map(|kline| Ok(kline.into())))
+    }
+}
+
+/// A [`Source`] that reads newline-delimited JSON messages from a file.
+///
+/// Blank lines are skipped and lines that fail to deserialize are reported as
+/// per-message errors.
+pub struct FileSource<T> {
+    path: PathBuf,
+    lines: Option<Lines<BufReader<File>>>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> FileSource<T> {
+    /// Creates a file source reading from `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            lines: None,
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<T: DeserializeOwned + Send + 'static> Source<T> for FileSource<T> {
+    async fn start(&mut self) -> Result<()> {
+        let file = File::open(&self.path)
+            .await
+            .with_context(|| format!("Failed to open source file {}", self.path.display()))?;
+        self.lines = Some(BufReader::new(file).lines());
+        Ok(())
+    }
+
+    async fn next(&mut self) -> Result<Option<Result<T>>> {
+        let lines = self.lines.as_mut().context("File source was not started")?;
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let message = serde_json::from_str::<T>(&line)
+                .with_context(|| format!("Failed to parse line: {}", line));
+            return Ok(Some(message));
+        }
+        Ok(None)
+    }
+}
+
+/// A sink that validates Kline messages and upserts them into `kline_data`.
+///
+/// Messages that fail validation are written to the quarantine table instead.
+pub struct UpsertSink {
+    pool: sqlx::PgPool,
+    source: String,
+    dataset: String,
+    source_kind: String,
+    exchange: Option<String>,
+    decimal_scale: Option<DecimalScale>,
+    cache: Option<Arc<KlineCache>>,
+    replay: bool,
+}
+
+impl UpsertSink {
+    /// Creates an upsert sink.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `source` - The source label recorded for quarantined messages (e.g., "websocket").
+    pub fn new(pool: sqlx::PgPool, source: &str) -> Self {
+        Self {
+            pool,
+            source: source.to_string(),
+            dataset: DEFAULT_DATASET.to_string(),
+            source_kind: SOURCE_KIND_STREAM.to_string(),
+            exchange: None,
+            decimal_scale: None,
+            cache: None,
+            replay: false,
+        }
+    }
+
+    /// Sets the dataset the messages are stored under (defaults to [`DEFAULT_DATASET`]).
+    pub fn with_dataset(mut self, dataset: &str) -> Self {
+        self.dataset = dataset.to_string();
+        self
+    }
+
+    /// Sets the source kind recorded for stored candles (defaults to
+    /// [`SOURCE_KIND_STREAM`]).
+    pub fn with_source_kind(mut self, source_kind: &str) -> Self {
+        self.source_kind = source_kind.to_string();
+        self
+    }
+
+    /// Sets the exchange recorded for stored candles. By default it is the exchange
+    /// named by the message, or
+    /// [`DEFAULT_EXCHANGE`](crate::models::DEFAULT_EXCHANGE) if the message names
+    /// none.
+    pub fn with_exchange(mut self, exchange: &str) -> Self {
+        self.exchange = Some(exchange.to_string());
+        self
+    }
+
+    /// Normalizes the prices and quantities of stored candles to a fixed scale (see
+    /// [`KlineData::with_decimal_scale`]). By default they are stored as received.
+    pub fn with_decimal_scale(mut self, scale: DecimalScale) -> Self {
+        self.decimal_scale = Some(scale);
+        self
+    }
+
+    /// Invalidates the cached ranges containing the stored candles, so readers of
+    /// the same process sharing the cache see the writes.
+    pub fn with_cache(mut self, cache: Arc<KlineCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Stores messages with [`KlineData::upsert_replayed`], which skips messages
+    /// that are not newer than the stored candle, so that replaying messages that
+    /// were already stored is a no-op.
+    pub fn for_replay(mut self) -> Self {
+        self.replay = true;
+        self
+    }
+}
+
+impl UpsertSink {
+    /// Labels a validated candle with the settings of the sink and stores it.
+    async fn store(&self, kline: KlineData) -> Result<()> {
+        let mut kline = kline
+            .with_dataset(&self.dataset)
+            .with_source_kind(&self.source_kind);
+        if let Some(exchange) = &self.exchange {
+            kline = kline.with_exchange(exchange);
+        }
+        if let Some(scale) = &self.decimal_scale {
+            kline = kline.with_decimal_scale(scale);
+        }
+        if self.replay {
+            kline.upsert_replayed(&self.pool).await?;
+        } else {
+            kline.upsert(&self.pool).await?;
+        }
+        if let Some(cache) = &self.cache {
+            cache.invalidate_kline(&kline);
+        }
+        Ok(())
+    }
+
+    async fn quarantine(&self, message: &SerdableKlineData, reason: &str) -> Result<()> {
+        log::warn!("Quarantining invalid Kline data: {}", reason);
+        QuarantinedRow::add_kline(&self.pool, &self.source, message, reason, &self.dataset).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl MessageHandler<SerdableKlineData> for UpsertSink {
+    async fn handle_message(&mut self, message: &SerdableKlineData) -> Result<()> {
+        match message.to_validated_kline_data() {
+            Ok(kline) => self.store(kline).await,
+            Err(reason) => self.quarantine(message, &reason.to_string()).await,
+        }
+    }
+}
+
+/// Stores candles parsed directly from WebSocket messages, see
+/// [`KlineStreaming::listen_kline_data`](crate::data_source::websocket::KlineStreaming::listen_kline_data).
+#[async_trait]
+impl MessageHandler<KlineData> for UpsertSink {
+    async fn handle_message(&mut self, message: &KlineData) -> Result<()> {
+        match message.validate() {
+            Ok(()) => self.store(message.clone()).await,
+            Err(reason) => {
+                let message = SerdableKlineData::from(message.clone());
+                self.quarantine(&message, &reason.to_string()).await
+            }
+        }
+    }
+}
+
+/// Counters describing a finished pipeline run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PipelineReport {
+    /// Messages read from the source successfully.
+    pub received: u64,
+    /// Messages dropped by a transform.
+    pub dropped: u64,
+    /// Messages delivered to all sinks.
+    pub delivered: u64,
+    /// Messages written to the dead-letter destination because a sink failed.
+    pub dead_lettered: u64,
+    /// Messages the source failed to parse.
+    pub errors: u64,
+}
+
+/// Controls how often a failing sink is called again with the same message.
+///
+/// Sinks are retried with the message they failed on, so they should be
+/// idempotent (as upserts are).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The number of calls before giving up, including the first one.
+    pub max_attempts: u32,
+    /// Backoff before the first retry, doubled on every further retry.
+    pub initial_backoff: std::time::Duration,
+}
+
+impl RetryPolicy {
+    /// A policy that calls a sink once and never retries. This is the default
+    /// of a [`PipelineBuilder`].
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            initial_backoff: std::time::Duration::ZERO,
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: std::time::Duration::from_millis(500),
+        }
+    }
+}
+
+/// A step applied to every message between the source and the sinks.
+///
+/// Transforms can modify a message (add derived fields, rename symbols, ...) or
+/// drop it by returning `None`. They are applied in the order they were added to
+/// the [`PipelineBuilder`], each receiving the output of the previous one.
+///
+/// Closures of type `FnMut(T) -> Option<T>` implement this trait.
+///
+/// # Example
+///
+/// ```rust
+/// use opentrade_core::ingest::pipeline::Transform;
+/// use opentrade_core::models::SerdableKlineData;
+///
+/// /// Drops candles without trades.
+/// struct SkipEmpty;
+///
+/// impl Transform<SerdableKlineData> for SkipEmpty {
+///     fn apply(&mut self, message: SerdableKlineData) -> Option<SerdableKlineData> {
+///         (message.trade_count > 0).then_some(message)
+///     }
+/// }
+/// ```
+pub trait Transform<T>: Send {
+    /// Transforms a message, or drops it by returning `None`.
+    fn apply(&mut self, message: T) -> Option<T>;
+}
+
+impl<T, F> Transform<T> for F
+where
+    F: FnMut(T) -> Option<T> + Send,
+{
+    fn apply(&mut self, message: T) -> Option<T> {
+        self(message)
+    }
+}
+
+/// A [`Transform`] that renames symbols, e.g. to map exchange symbols to the
+/// names used in storage. Symbols without a mapping are left unchanged.
+#[derive(Debug, Clone, Default)]
+pub struct RenameSymbols {
+    renames: HashMap<String, String>,
+}
+
+impl RenameSymbols {
+    /// Creates a transform without any renames.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Renames `from` to `to`.
+    pub fn rename(mut self, from: &str, to: &str) -> Self {
+        self.renames.insert(from.to_string(), to.to_string());
+        self
+    }
+}
+
+impl Transform<SerdableKlineData> for RenameSymbols {
+    fn apply(&mut self, mut message: SerdableKlineData) -> Option<SerdableKlineData> {
+        if let Some(to) = self.renames.get(&message.symbol) {
+            message.symbol = to.clone();
+        }
+        Some(message)
+    }
+}
+
+/// A [`Transform`] that only lets through closed candles, dropping the updates of
+/// candles that are still open.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FinalOnly;
+
+impl Transform<SerdableKlineData> for FinalOnly {
+    fn apply(&mut self, message: SerdableKlineData) -> Option<SerdableKlineData> {
+        message.is_final.then_some(message)
+    }
+}
+
+/// Builder for [`Pipeline`], created with [`Pipeline::builder`].
+pub struct PipelineBuilder<T> {
+    name: String,
+    source: Option<Box<dyn Source<T>>>,
+    transforms: Vec<Box<dyn Transform<T>>>,
+    sinks: Vec<Box<dyn SharedMessageHandler<T> + Send>>,
+    retry: RetryPolicy,
+    dead_letter: Option<Box<dyn DeadLetterSink<T>>>,
+    stats: Option<Arc<StreamStats>>,
+}
+
+impl<T> PipelineBuilder<T>
+where
+    T: Send + Sync + 'static,
+{
+    /// Sets the message source. Only one source can be configured; setting a
+    /// new one replaces the previous source.
+    pub fn source<S: Source<T> + 'static>(mut self, source: S) -> Self {
+        self.source = Some(Box::new(source));
+        self
+    }
+
+    /// Appends a [`Transform`] to the chain.
+    pub fn chain<X: Transform<T> + 'static>(mut self, transform: X) -> Self {
+        self.transforms.push(Box::new(transform));
+        self
+    }
+
+    /// Appends a closure that can modify a message or drop it by returning `None`.
+    pub fn transform<F>(self, transform: F) -> Self
+    where
+        F: FnMut(T) -> Option<T> + Send + 'static,
+    {
+        self.chain(transform)
+    }
+
+    /// Appends a transform that only lets through messages matching `predicate`.
+    pub fn filter<F>(self, mut predicate: F) -> Self
+    where
+        F: FnMut(&T) -> bool + Send + 'static,
+    {
+        self.transform(move |message| predicate(&message).then_some(message))
+    }
+
+    /// Appends a transform that maps every message.
+    pub fn map<F>(self, mut map: F) -> Self
+    where
+        F: FnMut(T) -> T + Send + 'static,
+    {
+        self.transform(move |message| Some(map(message)))
+    }
+
+    /// Adds a sink. Sinks are called in the order they were added.
+    pub fn sink<H: MessageHandler<T> + Send + 'static>(mut self, sink: H) -> Self {
+        self.sinks.push(Box::new(Borrowed(Box::new(sink))));
+        self
+    }
+
+    /// Adds a sink that receives each message as a shared [`Arc`], e.g. a
+    /// [`ChannelHandler`](crate::data_source::websocket::ChannelHandler). Shared
+    /// and borrowing sinks are called together in the order they were added.
+    pub fn shared_sink<H: SharedMessageHandler<T> + Send + 'static>(mut self, sink: H) -> Self {
+        self.sinks.push(Box::new(sink));
+        self
+    }
+
+    /// Sets how failing sinks are retried (defaults to [`RetryPolicy::none`]).
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Sets the destination of messages a sink still fails on after all retries.
+    ///
+    /// Without one, such a failure stops the pipeline.
+    pub fn dead_letter<D: DeadLetterSink<T> + 'static>(mut self, dead_letter: D) -> Self {
+        self.dead_letter = Some(Box::new(dead_letter));
+        self
+    }
+
+    /// Attaches shared [`StreamStats`] that record parse errors, sink errors and
+    /// the latency of the sink chain.
+    pub fn stats(mut self, stats: Arc<StreamStats>) -> Self {
+        self.stats = Some(stats);
+        self
+    }
+
+    /// Builds the pipeline.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no source or no sink has been configured.
+    pub fn build(self) -> Result<Pipeline<T>> {
+        let source = self
+            .source
+            .with_context(|| format!("Pipeline '{}' has no source", self.name))?;
+        if self.sinks.is_empty() {
+            anyhow::bail!("Pipeline '{}' has no sinks", self.name);
+        }
+        Ok(Pipeline {
+            name: self.name,
+            source,
+            transforms: self.transforms,
+            sinks: self.sinks,
+            retry: self.retry,
+            dead_letter: self.dead_letter,
+            stats: self.stats,
+        })
+    }
+}
+
+/// A runnable source → transforms → sinks chain.
+///
+/// See the [module documentation](self) for an example.
+pub struct Pipeline<T> {
+    name: String,
+    source: Box<dyn Source<T>>,
+    transforms: Vec<Box<dyn Transform<T>>>,
+    sinks: Vec<Box<dyn SharedMessageHandler<T> + Send>>,
+    retry: RetryPolicy,
+    dead_letter: Option<Box<dyn DeadLetterSink<T>>>,
+    stats: Option<Arc<StreamStats>>,
+}
+
+impl<T> Pipeline<T>
+where
+    T: Send + Sync + 'static,
+{
+    /// Starts building a pipeline with the given name, used in logs and errors.
+    pub fn builder(name: &str) -> PipelineBuilder<T> {
+        PipelineBuilder {
+            name: name.to_string(),
+            source: None,
+            transforms: Vec::new(),
+            sinks: Vec::new(),
+            retry: RetryPolicy::none(),
+            dead_letter: None,
+            stats: None,
+        }
+    }
+
+    /// Returns the pipeline name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Runs the pipeline until the source is exhausted.
+    ///
+    /// Messages the source fails to parse are logged and counted but do not stop
+    /// the pipeline. A failing source stops the pipeline with an error. A sink that
+    /// still fails after the retries of the [`RetryPolicy`] stops the pipeline
+    /// with an error, unless a dead-letter destination is configured, in which
+    /// case the message is dead-lettered and the remaining sinks are still called.
+    pub async fn run(mut self) -> Result<PipelineReport> {
+        let mut report = PipelineReport::default();
+        self.source
+            .start()
+            .await
+            .with_context(|| format!("Failed to start source of pipeline '{}'", self.name))?;
+        log::info!("Pipeline '{}' started", self.name);
+
+        while let Some(result) = self.source.next().await? {
+            let message = match result {
+                Ok(message) => message,
+                Err(e) => {
+                    report.errors += 1;
+                    if let Some(stats) = &self.stats {
+                        stats.record_error();
+                    }
+                    log::warn!("Pipeline '{}' skipped a message: {:#}", self.name, e);
+                    continue;
+                }
+            };
+            report.received += 1;
+
+            let Some(message) = self
+                .transforms
+                .iter_mut()
+                .try_fold(message, |message, transform| transform.apply(message))
+            else {
+                report.dropped += 1;
+                continue;
+            };
+
+            let message = Arc::new(message);
+            let context = self
+                .source
+                .context()
+                .unwrap_or_else(|| IngestContext::new(UNKNOWN_EXCHANGE, &self.name));
+            let started_at = Instant::now();
+            let mut dead_lettered = false;
+            for (index, sink) in self.sinks.iter_mut().enumerate() {
+                let Err((attempts, e)) =
+                    deliver(sink.as_mut(), &message, &context, &self.retry).await
+                else {
+                    continue;
+                };
+                if let Some(stats) = &self.stats {
+                    stats.record_error();
+                }
+                let Some(dead_letter) = self.dead_letter.as_mut() else {
+                    return Err(e.context(format!("Sink of pipeline '{}' failed", self.name)));
+                };
+                log::warn!(
+                    "Sink {} of pipeline '{}' failed after {} attempts, dead-lettering the message: {:#}",
+                    index,
+                    self.name,
+                    attempts,
+                    e
+                );
+                dead_letter
+                    .write(DeadLetter {
+                        pipeline: self.name.clone(),
+                        sink: index,
+                        attempts,
+                        error: format!("{:#}", e),
+                        context: context.clone(),
+                        failed_at: Utc::now(),
+                        message: Arc::clone(&message),
+                    })
+                    .await
+                    .with_context(|| {
+                        format!("Failed to dead-letter a message of pipeline '{}'", self.name)
+                    })?;
+                dead_lettered = true;
+            }
+            if let Some(stats) = &self.stats {
+                stats.record_latency(started_at.elapsed());
+            }
+            if dead_lettered {
+                report.dead_lettered += 1;
+            } else {
+                report.delivered += 1;
+            }
+        }
+
+        log::info!("Pipeline '{}' finished: {:?}", self.name, report);
+        Ok(report)
+    }
+
+    /// Runs the pipeline on a new Tokio task.
+    pub fn spawn(self) -> JoinHandle<Result<PipelineReport>> {
+        tokio::spawn(self.run())
+    }
+}
+
+/// Calls a sink until it succeeds or the attempts of `retry` are used up.
+///
+/// Returns the number of attempts and the last error on failure.
+async fn deliver<T: Send + Sync>(
+    sink: &mut (dyn SharedMessageHandler<T> + Send),
+    message: &Arc<T>,
+    context: &IngestContext,
+    retry: &RetryPolicy,
+) -> std::result::Result<(), (u32, anyhow::Error)> {
+    let mut backoff = retry.initial_backoff;
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        match sink.handle_shared_with_context(message, context).await {
+            Ok(()) => return Ok(()),
+            Err(e) if attempts >= retry.max_attempts => return Err((attempts, e)),
+            Err(e) => {
+                log::debug!("Sink attempt {} failed, retrying: {:#}", attempts, e);
+                tokio::time::sleep(backoff).await;
+                backoff = backoff.saturating_mul(2);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct VecSource(VecDeque<Result<u64>>);
+
+    #[async_trait]
+    impl Source<u64> for VecSource {
+        async fn start(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn next(&mut self) -> Result<Option<Result<u64>>> {
+            Ok(self.0.pop_front())
+        }
+    }
+
+    struct CollectSink(Arc<std::sync::Mutex<Vec<u64>>>);
+
+    #[async_trait]
+    impl MessageHandler<u64> for CollectSink {
+        async fn handle_message(&mut self, message: &u64) -> Result<()> {
+            self.0.lock().unwrap().push(*message);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_applies_transforms_in_order() {
+        let collected = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let source =
+            VecSource(vec![Ok(1), Ok(2), Err(anyhow::anyhow!("bad")), Ok(3), Ok(4)].into());
+        let report = Pipeline::builder("test")
+            .source(source)
+            .filter(|n| n % 2 == 0)
+            .map(|n| n * 10)
+            .sink(CollectSink(Arc::clone(&collected)))
+            .build()
+            .unwrap()
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(*collected.lock().unwrap(), vec![20, 40]);
+        assert_eq!(
+            report,
+            PipelineReport {
+                received: 4,
+                dropped: 2,
+                delivered: 2,
+                dead_lettered: 0,
+                errors: 1,
+            }
+        );
+    }
+
+    /// An internal event type without serde support.
+    struct Enriched {
+        value: u64,
+        even: bool,
+    }
+
+    struct EnrichedSource(VecDeque<u64>);
+
+    #[async_trait]
+    impl Source<Enriched> for EnrichedSource {
+        async fn start(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn next(&mut self) -> Result<Option<Result<Enriched>>> {
+            Ok(self.0.pop_front().map(|value| {
+                Ok(Enriched {
+                    value,
+                    even: value % 2 == 0,
+                })
+            }))
+        }
+    }
+
+    struct EvenSink(Arc<std::sync::Mutex<Vec<u64>>>);
+
+    #[async_trait]
+    impl MessageHandler<Enriched> for EvenSink {
+        async fn handle_message(&mut self, message: &Enriched) -> Result<()> {
+            if message.even {
+                self.0.lock().unwrap().push(message.value);
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_delivers_non_serializable_messages() {
+        let collected = Arc::new(std::sync::Mutex::new(Vec::new()));
+        Pipeline::builder("enriched")
+            .source(EnrichedSource(vec![1, 2, 3, 4].into()))
+            .sink(EvenSink(Arc::clone(&collected)))
+            .build()
+            .unwrap()
+            .run()
+            .await
+            .unwrap();
+        assert_eq!(*collected.lock().unwrap(), vec![2, 4]);
+    }
+
+    struct ContextSink(Arc<std::sync::Mutex<Vec<IngestContext>>>);
+
+    #[async_trait]
+    impl MessageHandler<u64> for ContextSink {
+        async fn handle_message(&mut self, _message: &u64) -> Result<()> {
+            unreachable!("the pipeline always passes a context")
+        }
+
+        async fn handle_message_with_context(
+            &mut self,
+            _message: &u64,
+            context: &IngestContext,
+        ) -> Result<()> {
+            self.0.lock().unwrap().push(context.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sinks_receive_pipeline_context_without_source_context() {
+        let contexts = Arc::new(std::sync::Mutex::new(Vec::new()));
+        Pipeline::builder("numbers")
+            .source(VecSource(vec![Ok(1), Ok(2)].into()))
+            .sink(ContextSink(Arc::clone(&contexts)))
+            .build()
+            .unwrap()
+            .run()
+            .await
+            .unwrap();
+
+        let contexts = contexts.lock().unwrap();
+        assert_eq!(contexts.len(), 2);
+        assert!(contexts.iter().all(|context| context.stream == "numbers"
+            && context.exchange == UNKNOWN_EXCHANGE
+            && context.generation == 0));
+    }
+
+    #[tokio::test]
+    async fn test_shared_sinks_receive_the_same_allocation() {
+        use crate::data_source::websocket::ChannelHandler;
+
+        let collected = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let (first_tx, mut first_rx) = tokio::sync::mpsc::channel(4);
+        let (second_tx, mut second_rx) = tokio::sync::mpsc::channel(4);
+        Pipeline::builder("shared")
+            .source(VecSource(vec![Ok(1), Ok(2)].into()))
+            .shared_sink(ChannelHandler::new(first_tx))
+            .sink(CollectSink(Arc::clone(&collected)))
+            .shared_sink(ChannelHandler::new(second_tx))
+            .build()
+            .unwrap()
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(*collected.lock().unwrap(), vec![1, 2]);
+        for expected in [1, 2] {
+            let first = first_rx.recv().await.unwrap();
+            let second = second_rx.recv().await.unwrap();
+            assert_eq!(*first, expected);
+            assert!(Arc::ptr_eq(&first, &second));
+        }
+    }
+
+    /// Always fails on odd messages, and fails once on the first even message.
+    struct FlakySink {
+        failed_once: bool,
+    }
+
+    #[async_trait]
+    impl MessageHandler<u64> for FlakySink {
+        async fn handle_message(&mut self, message: &u64) -> Result<()> {
+            if message % 2 == 1 {
+                anyhow::bail!("flaky failure on {}", message);
+            }
+            if !self.failed_once {
+                self.failed_once = true;
+                anyhow::bail!("transient failure on {}", message);
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failing_sinks_are_retried_then_dead_lettered() {
+        use crate::ingest::dead_letter::ChannelDeadLetters;
+
+        let collected = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let (tx, mut rx) = tokio::sync::mpsc::channel(4);
+        let report = Pipeline::builder("flaky")
+            .source(VecSource(vec![Ok(1), Ok(2)].into()))
+            .sink(FlakySink { failed_once: false })
+            .sink(CollectSink(Arc::clone(&collected)))
+            .retry(RetryPolicy {
+                max_attempts: 3,
+                initial_backoff: std::time::Duration::ZERO,
+            })
+            .dead_letter(ChannelDeadLetters::new(tx))
+            .build()
+            .unwrap()
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(*collected.lock().unwrap(), vec![1, 2]);
+        assert_eq!(report.delivered, 1);
+        assert_eq!(report.dead_lettered, 1);
+        let letter = rx.recv().await.unwrap();
+        assert_eq!(*letter.message, 1);
+        assert_eq!(letter.sink, 0);
+        assert_eq!(letter.attempts, 3);
+        assert!(letter.error.contains("flaky failure on 1"));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_failing_sink_stops_pipeline_without_dead_letter() {
+        let result = Pipeline::builder("flaky")
+            .source(VecSource(vec![Ok(1)].into()))
+            .sink(FlakySink { failed_once: false })
+            .build()
+            .unwrap()
+            .run()
+            .await;
+        assert!(result.is_err());
+    }
+
+    fn kline(symbol: &str, is_final: bool) -> SerdableKlineData {
+        SerdableKlineData {
+            start_time: 0,
+            end_time: 59_999,
+            symbol: symbol.to_string(),
+            interval: "1m".to_string(),
+            first_trade_id: 1,
+            last_trade_id: 2,
+            open: "1.0".to_string(),
+            close: "1.0".to_string(),
+            high: "1.0".to_string(),
+            low: "1.0".to_string(),
+            volume: "1.0".to_string(),
+            trade_count: 2,
+            quote_volume: "1.0".to_string(),
+            event_time: None,
+            is_final,
+            exchange: None,
+        }
+    }
+
+    #[test]
+    fn test_built_in_transforms_chain() {
+        let mut chain: Vec<Box<dyn Transform<SerdableKlineData>>> = vec![
+            Box::new(FinalOnly),
+            Box::new(RenameSymbols::new().rename("XBTUSD", "BTCUSDT")),
+            Box::new(|mut kline: SerdableKlineData| {
+                kline.symbol = kline.symbol.to_lowercase();
+                Some(kline)
+            }),
+        ];
+        let mut apply = |kline| {
+            chain
+                .iter_mut()
+                .try_fold(kline, |kline, transform| transform.apply(kline))
+        };
+
+        assert!(apply(kline("XBTUSD", false)).is_none());
+        assert_eq!(apply(kline("XBTUSD", true)).unwrap().symbol, "btcusdt");
+        assert_eq!(apply(kline("ETHUSDT", true)).unwrap().symbol, "ethusdt");
+    }
+
+    #[test]
+    fn test_build_requires_source_and_sink() {
+        assert!(Pipeline::<u64>::builder("empty").build().is_err());
+        let no_sink = Pipeline::<u64>::builder("no-sink").source(VecSource(VecDeque::new()));
+        assert!(no_sink.build().is_err());
+    }
+}
@@ -0,0 +1,147 @@
+//! # Decimal Precision
+//!
+//! The exchange formats prices and quantities with varying precision, so the same
+//! value can arrive as "0.1" from one path and as "0.10000000" from another. Stored
+//! as is, such values compare equal numerically but not textually, which produces
+//! spurious diffs in reconciliation and replication. This module provides the
+//! [`DecimalScale`] of every symbol, derived from the tick and lot sizes of the
+//! exchangeInfo endpoint, so candles can be normalized before they are stored:
+//!
+//! - Live streams normalize with [`UpsertSink::with_decimal_scale`](crate::ingest::pipeline::UpsertSink::with_decimal_scale).
+//! - Backfills normalize by fetching through a [`ScaledSource`].
+//!
+//! The scales are fetched once and cached for [`LISTING_TTL`].
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use opentrade_core::data_source::exchange::Binance;
+//! use opentrade_core::ingest::precision::{ScaledSource, symbol_scales};
+//!
+//! # async fn example() -> anyhow::Result<()> {
+//! let source = ScaledSource::new(Binance, symbol_scales().await?);
+//! // Klines fetched from `source` are stored with the scale of their symbol.
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+use crate::data_source::exchange::MarketDataSource;
+use crate::data_source::rest::{extract_symbol_scales, get_exchange_info};
+use crate::data_source::websocket::StreamingClient;
+use crate::ingest::symbols::LISTING_TTL;
+use crate::models::{DecimalScale, KlineData, SerdableKlineData};
+
+/// The scales of the listed symbols and the time they were fetched.
+static SCALES: Mutex<Option<(Instant, HashMap<String, DecimalScale>)>> = Mutex::new(None);
+
+/// Returns the [`DecimalScale`] of every listed symbol, fetching them if the
+/// cached scales are missing or older than [`LISTING_TTL`].
+pub async fn symbol_scales() -> Result<HashMap<String, DecimalScale>> {
+    if let Some((fetched_at, scales)) = SCALES.lock().unwrap().as_ref()
+        && fetched_at.elapsed() < LISTING_TTL
+    {
+        return Ok(scales.clone());
+    }
+    let exchange_info = get_exchange_info()
+        .await
+        .context("Failed to fetch exchange info")?;
+    let scales: HashMap<String, DecimalScale> = extract_symbol_scales(&exchange_info)
+        .context("Failed to parse exchange info")?
+        .into_iter()
+        .collect();
+    *SCALES.lock().unwrap() = Some((Instant::now(), scales.clone()));
+    Ok(scales)
+}
+
+/// Returns the [`DecimalScale`] of a symbol.
+///
+/// # Errors
+///
+/// Returns an error if the exchange info cannot be fetched or the symbol is not listed.
+pub async fn symbol_scale(symbol: &str) -> Result<DecimalScale> {
+    symbol_scales()
+        .await?
+        .remove(symbol)
+        .with_context(|| format!("No decimal scale for unlisted symbol {}", symbol))
+}
+
+/// A [`MarketDataSource`] that normalizes fetched klines to the scale of their symbol.
+///
+/// Klines of symbols without a scale are returned unchanged. Live streams are
+/// passed through, so their candles are normalized by the sink that stores them.
+pub struct ScaledSource<S> {
+    inner: S,
+    scales: HashMap<String, DecimalScale>,
+}
+
+impl<S: MarketDataSource> ScaledSource<S> {
+    /// Creates a normalizing source.
+    ///
+    /// # Arguments
+    ///
+    /// * `inner` - The source the klines are fetched from.
+    /// * `scales` - The scales of the symbols, e.g. from [`symbol_scales`].
+    pub fn new(inner: S, scales: HashMap<String, DecimalScale>) -> Self {
+        Self { inner, scales }
+    }
+}
+
+#[async_trait]
+impl<S: MarketDataSource> MarketDataSource for ScaledSource<S> {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn normalize_symbol(&self, symbol: &str) -> String {
+        self.inner.normalize_symbol(symbol)
+    }
+
+    fn max_kline_limit(&self) -> u32 {
+        self.inner.max_kline_limit()
+    }
+
+    fn default_kline_limit(&self) -> u32 {
+        self.inner.default_kline_limit()
+    }
+
+    async fn fetch_klines(
+        &self,
+        symbol: &str,
+        interval: &str,
+        start_time: u64,
+        end_time: Option<u64>,
+        limit: Option<u32>,
+    ) -> Result<Vec<KlineData>> {
+        let klines = self
+            .inner
+            .fetch_klines(symbol, interval, start_time, end_time, limit)
+            .await?;
+        Ok(match self.scales.get(symbol) {
+            Some(scale) => klines
+                .into_iter()
+                .map(|kline| kline.with_decimal_scale(scale))
+                .collect(),
+            None => klines,
+        })
+    }
+
+    async fn stream_klines(
+        &self,
+        symbol: &str,
+        interval: &str,
+    ) -> Result<Box<dyn StreamingClient<SerdableKlineData>>> {
+        self.inner.stream_klines(symbol, interval).await
+    }
+
+    async fn server_time(&self) -> Result<DateTime<Utc>> {
+        self.inner.server_time().await
+    }
+}
@@ -0,0 +1,449 @@
+//! # Data-Quality Reports
+//!
+//! This module compiles a [`QualityReport`] of the stored data of a set of
+//! streams over a recent window (typically the last 24 hours): coverage, missing
+//! candles, ranges the exchange reported as empty, quarantined rows and how many
+//! candles were written. Reports render as Markdown or HTML for delivery by
+//! webhook or as a file.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use opentrade_core::ingest::quality::QualityReport;
+//! use opentrade_core::models::{DEFAULT_DATASET, DEFAULT_EXCHANGE};
+//! use chrono::{Duration, Utc};
+//! # use anyhow::Result;
+//!
+//! # async fn example(pool: sqlx::PgPool) -> Result<()> {
+//! let streams = vec![("BTCUSDT".to_string(), "1m".to_string())];
+//! let report = QualityReport::build(
+//!     &pool,
+//!     &streams,
+//!     DEFAULT_DATASET,
+//!     DEFAULT_EXCHANGE,
+//!     Utc::now(),
+//!     Duration::hours(24),
+//! )
+//! .await?;
+//! report.write("quality.html")?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+use crate::ingest::audit::{GapReport, gap_report, interval_duration};
+use crate::ingest::maintenance::MaintenanceCalendar;
+use crate::models::coverage::{Coverage, coverage, rows_written};
+use crate::models::exchange_gap::ExchangeGap;
+use crate::models::quarantine::{QuarantineFilter, QuarantinedRow};
+use crate::time;
+
+/// The number of most frequent quarantine reasons listed per stream.
+const TOP_REASONS: usize = 3;
+
+/// Data-quality findings for one symbol and interval.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StreamQuality {
+    /// The trading symbol.
+    pub symbol: String,
+    /// The Kline interval.
+    pub interval: String,
+    /// The coverage of all stored candles.
+    pub coverage: Coverage,
+    /// The missing closed candles in the window, or `None` for calendar intervals.
+    pub gaps: Option<GapReport>,
+    /// The number of ranges in the window the exchange reported as empty.
+    pub exchange_gaps: usize,
+    /// The number of rows quarantined in the window.
+    pub quarantined: usize,
+    /// The most frequent quarantine reasons in the window, with their counts.
+    pub quarantine_reasons: Vec<(String, usize)>,
+    /// The number of candles inserted or updated in the window.
+    pub rows_written: i64,
+}
+
+impl StreamQuality {
+    /// Returns the number of closed candles missing from the window.
+    pub fn missing(&self) -> i64 {
+        self.gaps.as_ref().map_or(0, |gaps| gaps.missing)
+    }
+
+    /// Returns true if the stream has no data, missing candles or quarantined rows.
+    pub fn has_issues(&self) -> bool {
+        self.coverage.is_empty() || self.missing() > 0 || self.quarantined > 0
+    }
+}
+
+/// A data-quality report over a window. See the [module documentation](self).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct QualityReport {
+    /// The dataset label.
+    pub dataset: String,
+    /// The exchange the streams are ingested from.
+    pub exchange: String,
+    /// The inclusive start of the window.
+    pub window_start: DateTime<Utc>,
+    /// The exclusive end of the window.
+    pub window_end: DateTime<Utc>,
+    /// The findings per stream, in the requested order.
+    pub streams: Vec<StreamQuality>,
+}
+
+impl QualityReport {
+    /// Compiles a report of the `window` ending at `window_end`.
+    ///
+    /// The gap count only considers closed candles: the window is cut at the start
+    /// of the candle that is still open at `window_end`.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `streams` - The `(symbol, interval)` pairs to report on.
+    /// * `dataset` - The dataset label.
+    /// * `exchange` - The exchange the streams are ingested from (e.g., "binance").
+    /// * `window_end` - The exclusive end of the window, usually now.
+    /// * `window` - The length of the window, usually 24 hours.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a query fails.
+    pub async fn build(
+        pool: &sqlx::PgPool,
+        streams: &[(String, String)],
+        dataset: &str,
+        exchange: &str,
+        window_end: DateTime<Utc>,
+        window: Duration,
+    ) -> Result<Self> {
+        let window_start = window_end - window;
+        let mut results = Vec::with_capacity(streams.len());
+        for (symbol, interval) in streams {
+            let gaps = match interval_duration(interval) {
+                Some(step) => {
+                    let closed_end = last_closed_end(window_end, step);
+                    Some(
+                        gap_report(
+                            pool,
+                            symbol,
+                            interval,
+                            window_start,
+                            closed_end,
+                            dataset,
+                            exchange,
+                        )
+                        .await?,
+                    )
+                }
+                None => None,
+            };
+            let exchange_gaps = ExchangeGap::list_range(
+                pool,
+                symbol,
+                interval,
+                dataset,
+                exchange,
+                window_start,
+                window_end,
+            )
+            .await?
+            .len();
+            let quarantined = QuarantinedRow::list(
+                pool,
+                &QuarantineFilter {
+                    symbol: Some(symbol.clone()),
+                    interval: Some(interval.clone()),
+                    dataset: Some(dataset.to_string()),
+                    since: Some(window_start),
+                    until: Some(window_end),
+                    include_reprocessed: true,
+                    ..Default::default()
+                },
+            )
+            .await?;
+            results.push(StreamQuality {
+                symbol: symbol.clone(),
+                interval: interval.clone(),
+                coverage: coverage(pool, symbol, interval, dataset, exchange).await?,
+                gaps,
+                exchange_gaps,
+                quarantined: quarantined.len(),
+                quarantine_reasons: top_reasons(&quarantined),
+                rows_written: rows_written(
+                    pool,
+                    symbol,
+                    interval,
+                    dataset,
+                    exchange,
+                    window_start,
+                    window_end,
+                )
+                .await?,
+            });
+        }
+        Ok(Self {
+            dataset: dataset.to_string(),
+            exchange: exchange.to_string(),
+            window_start,
+            window_end,
+            streams: results,
+        })
+    }
+
+    /// Returns the report without the candles starting within the maintenance
+    /// windows of an exchange, which are then not reported as missing.
+    ///
+    /// # Arguments
+    ///
+    /// * `calendar` - The known maintenance windows.
+    /// * `exchange` - The exchange the streams are ingested from (e.g., "binance").
+    pub fn excluding_maintenance(mut self, calendar: &MaintenanceCalendar, exchange: &str) -> Self {
+        let windows = calendar.ranges(exchange, self.window_start, self.window_end);
+        if windows.is_empty() {
+            return self;
+        }
+        for stream in &mut self.streams {
+            stream.gaps = stream.gaps.take().map(|gaps| gaps.excluding(&windows));
+        }
+        self
+    }
+
+    /// Returns the number of streams with issues.
+    pub fn issues(&self) -> usize {
+        self.streams.iter().filter(|stream| stream.has_issues()).count()
+    }
+
+    /// Renders the report as Markdown.
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# Data-quality report: {}", self.dataset);
+        let _ = writeln!(out);
+        let _ = writeln!(
+            out,
+            "Window {} to {}. {} of {} streams have issues.",
+            self.window_start.to_rfc3339(),
+            self.window_end.to_rfc3339(),
+            self.issues(),
+            self.streams.len()
+        );
+        let _ = writeln!(out);
+        let _ = writeln!(
+            out,
+            "| Stream | Status | Stored | Latest | Missing | Gaps | Exchange gaps | Quarantined | Written |"
+        );
+        let _ = writeln!(out, "|---|---|---|---|---|---|---|---|---|");
+        for stream in &self.streams {
+            let row = table_row(stream);
+            let _ = writeln!(out, "| {} |", row.join(" | "));
+        }
+        for stream in self.streams.iter().filter(|stream| stream.quarantined > 0) {
+            let _ = writeln!(out);
+            let _ = writeln!(out, "## Quarantine reasons: {} {}", stream.symbol, stream.interval);
+            let _ = writeln!(out);
+            for (reason, count) in &stream.quarantine_reasons {
+                let _ = writeln!(out, "- {} × {}", count, reason);
+            }
+        }
+        out
+    }
+
+    /// Renders the report as a standalone HTML page.
+    pub fn to_html(&self) -> String {
+        let mut out = String::new();
+        let title = format!("Data-quality report: {}", escape_html(&self.dataset));
+        let _ = writeln!(
+            out,
+            "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>{}</title></head>\n<body>",
+            title
+        );
+        let _ = writeln!(out, "<h1>{}</h1>", title);
+        let _ = writeln!(
+            out,
+            "<p>Window {} to {}. {} of {} streams have issues.</p>",
+            self.window_start.to_rfc3339(),
+            self.window_end.to_rfc3339(),
+            self.issues(),
+            self.streams.len()
+        );
+        let _ = writeln!(
+            out,
+            "<table border=\"1\">\n<tr><th>Stream</th><th>Status</th><th>Stored</th><th>Latest</th><th>Missing</th><th>Gaps</th><th>Exchange gaps</th><th>Quarantined</th><th>Written</th></tr>"
+        );
+        for stream in &self.streams {
+            let cells: Vec<String> = table_row(stream)
+                .iter()
+                .map(|cell| format!("<td>{}</td>", escape_html(cell)))
+                .collect();
+            let _ = writeln!(out, "<tr>{}</tr>", cells.concat());
+        }
+        let _ = writeln!(out, "</table>");
+        for stream in self.streams.iter().filter(|stream| stream.quarantined > 0) {
+            let _ = writeln!(
+                out,
+                "<h2>Quarantine reasons: {} {}</h2>\n<ul>",
+                escape_html(&stream.symbol),
+                escape_html(&stream.interval)
+            );
+            for (reason, count) in &stream.quarantine_reasons {
+                let _ = writeln!(out, "<li>{} &times; {}</li>", count, escape_html(reason));
+            }
+            let _ = writeln!(out, "</ul>");
+        }
+        let _ = writeln!(out, "</body>\n</html>");
+        out
+    }
+
+    /// Writes the report to `path`, as HTML for `.html`/`.htm` paths and as
+    /// Markdown otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let is_html = path.extension().is_some_and(|extension| {
+            extension.eq_ignore_ascii_case("html") || extension.eq_ignore_ascii_case("htm")
+        });
+        let contents = if is_html {
+            self.to_html()
+        } else {
+            self.to_markdown()
+        };
+        std::fs::write(path, contents)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// Returns the start of the candle of length `step` that is open at `now`, which
+/// is the exclusive end of the closed candles.
+fn last_closed_end(now: DateTime<Utc>, step: Duration) -> DateTime<Utc> {
+    time::align_down(now, step)
+}
+
+/// Counts quarantined rows by reason, most frequent first.
+fn top_reasons(rows: &[QuarantinedRow]) -> Vec<(String, usize)> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for row in rows {
+        *counts.entry(&row.reason).or_default() += 1;
+    }
+    let mut reasons: Vec<(String, usize)> = counts
+        .into_iter()
+        .map(|(reason, count)| (reason.to_string(), count))
+        .collect();
+    reasons.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    reasons.truncate(TOP_REASONS);
+    reasons
+}
+
+/// Formats the summary table cells of a stream.
+fn table_row(stream: &StreamQuality) -> Vec<String> {
+    vec![
+        format!("{} {}", stream.symbol, stream.interval),
+        if stream.has_issues() { "issues" } else { "ok" }.to_string(),
+        stream.coverage.rows.to_string(),
+        stream
+            .coverage
+            .last_start
+            .map_or_else(|| "-".to_string(), |start| start.to_rfc3339()),
+        stream
+            .gaps
+            .as_ref()
+            .map_or_else(|| "-".to_string(), |gaps| gaps.missing.to_string()),
+        stream
+            .gaps
+            .as_ref()
+            .map_or_else(|| "-".to_string(), |gaps| gaps.gaps.len().to_string()),
+        stream.exchange_gaps.to_string(),
+        stream.quarantined.to_string(),
+        stream.rows_written.to_string(),
+    ]
+}
+
+/// Escapes text for inclusion in HTML.
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(hours: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000 / 3600 * 3600 + hours * 3600, 0).unwrap()
+    }
+
+    fn report() -> QualityReport {
+        let stream = |symbol: &str, missing: i64, quarantined: usize| StreamQuality {
+            symbol: symbol.to_string(),
+            interval: "1h".to_string(),
+            coverage: Coverage {
+                symbol: symbol.to_string(),
+                interval: "1h".to_string(),
+                dataset: "default".to_string(),
+                exchange: "binance".to_string(),
+                first_start: Some(at(-100)),
+                last_start: Some(at(-1)),
+                rows: 100,
+                gaps: Some(0),
+            },
+            gaps: Some(GapReport {
+                symbol: symbol.to_string(),
+                interval: "1h".to_string(),
+                range_start: at(-24),
+                range_end: at(0),
+                expected: 24,
+                found: 24 - missing,
+                missing,
+                gaps: Vec::new(),
+            }),
+            exchange_gaps: 0,
+            quarantined,
+            quarantine_reasons: if quarantined > 0 {
+                vec![("high < low".to_string(), quarantined)]
+            } else {
+                Vec::new()
+            },
+            rows_written: 24,
+        };
+        QualityReport {
+            dataset: "default".to_string(),
+            exchange: "binance".to_string(),
+            window_start: at(-24),
+            window_end: at(0),
+            streams: vec![stream("BTCUSDT", 0, 0), stream("ETHUSDT", 2, 1)],
+        }
+    }
+
+    #[test]
+    fn test_last_closed_end_excludes_open_candle() {
+        let step = Duration::hours(1);
+        assert_eq!(last_closed_end(at(3) + Duration::minutes(20), step), at(3));
+        assert_eq!(last_closed_end(at(3), step), at(3));
+    }
+
+    #[test]
+    fn test_markdown_summarizes_streams_with_issues() {
+        let markdown = report().to_markdown();
+        assert!(markdown.contains("1 of 2 streams have issues"));
+        assert!(markdown.contains("| BTCUSDT 1h | ok | 100 |"));
+        assert!(markdown.contains("| ETHUSDT 1h | issues | 100 |"));
+        assert!(markdown.contains("## Quarantine reasons: ETHUSDT 1h"));
+        assert!(!markdown.contains("Quarantine reasons: BTCUSDT"));
+    }
+
+    #[test]
+    fn test_html_escapes_reasons() {
+        let html = report().to_html();
+        assert!(html.contains("<td>ETHUSDT 1h</td><td>issues</td>"));
+        assert!(html.contains("1 &times; high &lt; low"));
+    }
+}
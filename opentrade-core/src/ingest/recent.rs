@@ -0,0 +1,486 @@
+//! # Recent Candle Store
+//!
+//! This module provides [`RecentStore`], a memory-resident buffer of the candles
+//! of the last hours per series, for indicator computations that repeatedly read
+//! fresh data. The candles of a series are kept in a columnar layout
+//! ([`CandleColumns`]): one vector per field instead of one struct per candle, with
+//! prices and volumes as `f64`, so indicators iterate over contiguous numbers
+//! without loading rows or converting decimals.
+//!
+//! The store is filled by [`RecentSink`], which runs next to the sink that stores
+//! the streamed candles, and can be warmed from the database with
+//! [`RecentStore::warm`]. [`RecentStore::columns`] serves a range from memory if
+//! the buffer covers it and falls back to Postgres otherwise.
+//!
+//! A buffer only knows the candles it received, so candles missed while a stream
+//! was disconnected are missing from it until they are re-read from the database.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use std::sync::Arc;
+//!
+//! use chrono::{Duration, Utc};
+//! use opentrade_core::data_source::exchange::{Binance, MarketDataSource};
+//! use opentrade_core::ingest::pipeline::{Pipeline, StreamSource, UpsertSink};
+//! use opentrade_core::ingest::recent::{RecentSink, RecentStore};
+//! use opentrade_core::models::{DEFAULT_DATASET, DEFAULT_EXCHANGE};
+//! # use anyhow::Result;
+//!
+//! # async fn example(pool: sqlx::PgPool) -> Result<()> {
+//! let store = Arc::new(RecentStore::new(Duration::hours(6)));
+//! store
+//!     .warm(&pool, "BTCUSDT", "1m", DEFAULT_DATASET, DEFAULT_EXCHANGE)
+//!     .await?;
+//!
+//! let client = Binance.stream_klines("BTCUSDT", "1m").await?;
+//! let pipeline = Pipeline::builder("btcusdt-1m")
+//!     .source(StreamSource::new(client))
+//!     .sink(UpsertSink::new(pool.clone(), "websocket"))
+//!     .sink(RecentSink::new(store.clone()))
+//!     .build()?;
+//! tokio::spawn(pipeline.run());
+//!
+//! let now = Utc::now();
+//! let columns = store
+//!     .columns(
+//!         &pool,
+//!         "BTCUSDT",
+//!         "1m",
+//!         now - Duration::hours(1),
+//!         now,
+//!         DEFAULT_DATASET,
+//!         DEFAULT_EXCHANGE,
+//!     )
+//!     .await?;
+//! println!("Last close: {:?}", columns.close().last());
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+
+use crate::analytics::to_f64;
+use crate::data_source::websocket::MessageHandler;
+use crate::models::{DEFAULT_DATASET, KlineData, SerdableKlineData};
+
+/// The candles of a series in a columnar layout, ordered by start time.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CandleColumns {
+    start_time: Vec<DateTime<Utc>>,
+    open: Vec<f64>,
+    high: Vec<f64>,
+    low: Vec<f64>,
+    close: Vec<f64>,
+    volume: Vec<f64>,
+}
+
+impl CandleColumns {
+    /// Builds the columns of candles ordered by start time.
+    pub fn from_klines(klines: &[KlineData]) -> Self {
+        let mut columns = Self::default();
+        for kline in klines {
+            columns.push(kline);
+        }
+        columns
+    }
+
+    /// Adds a candle, replacing the candle with the same start time if there is one.
+    /// Candles are usually appended, so out-of-order candles are inserted at their
+    /// position by a binary search.
+    pub fn push(&mut self, kline: &KlineData) {
+        let index = match self.start_time.last() {
+            Some(last) if *last < kline.start_time => self.start_time.len(),
+            _ => match self.start_time.binary_search(&kline.start_time) {
+                Ok(index) => {
+                    self.open[index] = to_f64(&kline.open);
+                    self.high[index] = to_f64(&kline.high);
+                    self.low[index] = to_f64(&kline.low);
+                    self.close[index] = to_f64(&kline.close);
+                    self.volume[index] = to_f64(&kline.volume);
+                    return;
+                }
+                Err(index) => index,
+            },
+        };
+        self.start_time.insert(index, kline.start_time);
+        self.open.insert(index, to_f64(&kline.open));
+        self.high.insert(index, to_f64(&kline.high));
+        self.low.insert(index, to_f64(&kline.low));
+        self.close.insert(index, to_f64(&kline.close));
+        self.volume.insert(index, to_f64(&kline.volume));
+    }
+
+    /// Drops the candles starting before `cutoff`.
+    pub fn trim_before(&mut self, cutoff: DateTime<Utc>) {
+        let count = self.start_time.partition_point(|time| *time < cutoff);
+        if count > 0 {
+            self.start_time.drain(..count);
+            self.open.drain(..count);
+            self.high.drain(..count);
+            self.low.drain(..count);
+            self.close.drain(..count);
+            self.volume.drain(..count);
+        }
+    }
+
+    /// Returns a copy of the candles starting within `[start_time, end_time)`.
+    pub fn slice(&self, start_time: DateTime<Utc>, end_time: DateTime<Utc>) -> Self {
+        let from = self.start_time.partition_point(|time| *time < start_time);
+        let to = self
+            .start_time
+            .partition_point(|time| *time < end_time)
+            .max(from);
+        Self {
+            start_time: self.start_time[from..to].to_vec(),
+            open: self.open[from..to].to_vec(),
+            high: self.high[from..to].to_vec(),
+            low: self.low[from..to].to_vec(),
+            close: self.close[from..to].to_vec(),
+            volume: self.volume[from..to].to_vec(),
+        }
+    }
+
+    /// Returns the number of candles.
+    pub fn len(&self) -> usize {
+        self.start_time.len()
+    }
+
+    /// Returns true if there are no candles.
+    pub fn is_empty(&self) -> bool {
+        self.start_time.is_empty()
+    }
+
+    /// Returns the start times.
+    pub fn start_time(&self) -> &[DateTime<Utc>] {
+        &self.start_time
+    }
+
+    /// Returns the opening prices.
+    pub fn open(&self) -> &[f64] {
+        &self.open
+    }
+
+    /// Returns the highest prices.
+    pub fn high(&self) -> &[f64] {
+        &self.high
+    }
+
+    /// Returns the lowest prices.
+    pub fn low(&self) -> &[f64] {
+        &self.low
+    }
+
+    /// Returns the closing prices.
+    pub fn close(&self) -> &[f64] {
+        &self.close
+    }
+
+    /// Returns the base asset volumes.
+    pub fn volume(&self) -> &[f64] {
+        &self.volume
+    }
+}
+
+/// The buffered candles of a series and the time from which they are complete.
+#[derive(Debug)]
+struct Series {
+    columns: CandleColumns,
+    covered_from: DateTime<Utc>,
+}
+
+/// A memory-resident buffer of the candles of the last [`window`](Self::window)
+/// per symbol, interval, dataset and exchange.
+#[derive(Debug)]
+pub struct RecentStore {
+    window: Duration,
+    series: RwLock<HashMap<SeriesKey, Series>>,
+}
+
+impl RecentStore {
+    /// Creates an empty store.
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - How far back from the latest candle of a series candles are kept.
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            series: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Returns how far back from the latest candle of a series candles are kept.
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Loads the candles of the last window of a series from the database, so the
+    /// buffer covers the whole window before the stream has filled it.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `symbol` - The trading symbol.
+    /// * `interval` - The Kline interval.
+    /// * `dataset` - The dataset label.
+    /// * `exchange` - The exchange the candles were captured from (e.g., "binance").
+    pub async fn warm(
+        &self,
+        pool: &sqlx::PgPool,
+        symbol: &str,
+        interval: &str,
+        dataset: &str,
+        exchange: &str,
+    ) -> Result<()> {
+        let end_time = Utc::now();
+        let start_time = end_time - self.window;
+        let klines = KlineData::list_range(
+            pool, symbol, interval, start_time, end_time, dataset, exchange,
+        )
+        .await
+        .with_context(|| format!("Failed to load recent candles of {}", symbol))?;
+        let mut series = self.series.write().unwrap();
+        let entry = series
+            .entry(series_key(symbol, interval, dataset, exchange))
+            .or_insert_with(|| Series {
+                columns: CandleColumns::default(),
+                covered_from: start_time,
+            });
+        // Candles streamed while the range was loaded are newer than the loaded ones.
+        for kline in &klines {
+            if entry
+                .columns
+                .start_time
+                .binary_search(&kline.start_time)
+                .is_err()
+            {
+                entry.columns.push(kline);
+            }
+        }
+        entry.covered_from = entry.covered_from.min(start_time);
+        Ok(())
+    }
+
+    /// Adds a candle to the buffer of its series and drops the candles that fell
+    /// out of the window.
+    pub fn insert(&self, kline: &KlineData) {
+        let mut series = self.series.write().unwrap();
+        let entry = series
+            .entry(series_key(
+                &kline.symbol,
+                &kline.interval,
+                &kline.dataset,
+                &kline.exchange,
+            ))
+            .or_insert_with(|| Series {
+                columns: CandleColumns::default(),
+                covered_from: kline.start_time,
+            });
+        entry.columns.push(kline);
+        if let Some(latest) = entry.columns.start_time.last() {
+            let cutoff = *latest - self.window;
+            entry.columns.trim_before(cutoff);
+            entry.covered_from = entry.covered_from.max(cutoff);
+        }
+    }
+
+    /// Returns the buffered candles of a range, or `None` if the buffer of the
+    /// series does not cover the range.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbol` - The trading symbol.
+    /// * `interval` - The Kline interval.
+    /// * `start_time` - The inclusive lower bound for the start time.
+    /// * `end_time` - The exclusive upper bound for the start time.
+    /// * `dataset` - The dataset label.
+    /// * `exchange` - The exchange the candles were captured from (e.g., "binance").
+    pub fn get(
+        &self,
+        symbol: &str,
+        interval: &str,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        dataset: &str,
+        exchange: &str,
+    ) -> Option<CandleColumns> {
+        let series = self.series.read().unwrap();
+        let entry = series.get(&series_key(symbol, interval, dataset, exchange))?;
+        (start_time >= entry.covered_from).then(|| entry.columns.slice(start_time, end_time))
+    }
+
+    /// Returns the candles of a range from the buffer if it covers the range, and
+    /// from the database otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `symbol` - The trading symbol.
+    /// * `interval` - The Kline interval.
+    /// * `start_time` - The inclusive lower bound for the start time.
+    /// * `end_time` - The exclusive upper bound for the start time.
+    /// * `dataset` - The dataset label.
+    /// * `exchange` - The exchange the candles were captured from (e.g., "binance").
+    #[allow(clippy::too_many_arguments)]
+    pub async fn columns(
+        &self,
+        pool: &sqlx::PgPool,
+        symbol: &str,
+        interval: &str,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        dataset: &str,
+        exchange: &str,
+    ) -> Result<CandleColumns> {
+        if let Some(columns) = self.get(symbol, interval, start_time, end_time, dataset, exchange) {
+            return Ok(columns);
+        }
+        let klines = KlineData::list_range(
+            pool, symbol, interval, start_time, end_time, dataset, exchange,
+        )
+        .await
+        .with_context(|| format!("Failed to load candles of {}", symbol))?;
+        Ok(CandleColumns::from_klines(&klines))
+    }
+}
+
+/// The symbol, interval, dataset and exchange of a series in a [`RecentStore`].
+type SeriesKey = (String, String, String, String);
+
+/// Returns the key of a series in a [`RecentStore`].
+fn series_key(symbol: &str, interval: &str, dataset: &str, exchange: &str) -> SeriesKey {
+    (
+        symbol.to_string(),
+        interval.to_string(),
+        dataset.to_string(),
+        exchange.to_string(),
+    )
+}
+
+/// A sink that adds valid streamed candles to a [`RecentStore`].
+///
+/// The sink does not store candles in the database, so it runs next to a sink
+/// that does, such as [`UpsertSink`](crate::ingest::pipeline::UpsertSink).
+pub struct RecentSink {
+    store: Arc<RecentStore>,
+    dataset: String,
+}
+
+impl RecentSink {
+    /// Creates a sink filling `store`.
+    pub fn new(store: Arc<RecentStore>) -> Self {
+        Self {
+            store,
+            dataset: DEFAULT_DATASET.to_string(),
+        }
+    }
+
+    /// Sets the dataset the candles are buffered under (defaults to [`DEFAULT_DATASET`]).
+    pub fn with_dataset(mut self, dataset: &str) -> Self {
+        self.dataset = dataset.to_string();
+        self
+    }
+}
+
+#[async_trait]
+impl MessageHandler<SerdableKlineData> for RecentSink {
+    async fn handle_message(&mut self, message: &SerdableKlineData) -> Result<()> {
+        // Invalid messages are quarantined by the sink that stores them.
+        if let Ok(kline) = message.to_validated_kline_data() {
+            self.store.insert(&kline.with_dataset(&self.dataset));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::DEFAULT_EXCHANGE;
+    use sqlx::types::BigDecimal as Decimal;
+    use std::str::FromStr;
+
+    fn kline(minute: u64, close: &str) -> KlineData {
+        let price = Decimal::from_str(close).unwrap();
+        KlineData::new(
+            &(minute * 60_000),
+            &(minute * 60_000 + 59_999),
+            "BTCUSDT",
+            "1m",
+            1,
+            2,
+            price.clone(),
+            price.clone(),
+            price.clone(),
+            price,
+            Decimal::from(1),
+            Some(1),
+            None,
+        )
+    }
+
+    fn at(minute: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(minute * 60, 0).unwrap()
+    }
+
+    #[test]
+    fn test_push_orders_and_replaces_candles() {
+        let mut columns = CandleColumns::default();
+        columns.push(&kline(1, "10"));
+        columns.push(&kline(3, "30"));
+        columns.push(&kline(2, "20"));
+        columns.push(&kline(3, "31"));
+        assert_eq!(columns.start_time(), &[at(1), at(2), at(3)]);
+        assert_eq!(columns.close(), &[10.0, 20.0, 31.0]);
+    }
+
+    #[test]
+    fn test_insert_keeps_window() {
+        let store = RecentStore::new(Duration::minutes(2));
+        for minute in 0..5 {
+            store.insert(&kline(minute, "10"));
+        }
+        let columns = store
+            .get(
+                "BTCUSDT",
+                "1m",
+                at(2),
+                at(5),
+                DEFAULT_DATASET,
+                DEFAULT_EXCHANGE,
+            )
+            .unwrap();
+        assert_eq!(columns.start_time(), &[at(2), at(3), at(4)]);
+        // Candles before the window are no longer covered.
+        assert!(
+            store
+                .get(
+                    "BTCUSDT",
+                    "1m",
+                    at(1),
+                    at(5),
+                    DEFAULT_DATASET,
+                    DEFAULT_EXCHANGE
+                )
+                .is_none()
+        );
+        assert!(
+            store
+                .get(
+                    "ETHUSDT",
+                    "1m",
+                    at(2),
+                    at(5),
+                    DEFAULT_DATASET,
+                    DEFAULT_EXCHANGE
+                )
+                .is_none()
+        );
+    }
+}
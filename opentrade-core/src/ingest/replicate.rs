@@ -0,0 +1,204 @@
+//! # Replication
+//!
+//! This module copies stored Kline data from one PostgreSQL database to another,
+//! e.g. to migrate a dataset or to mirror it into another region.
+//!
+//! Rows are read from the source in keyset-paginated batches ordered by
+//! `(start_time, symbol, interval, dataset, exchange)` and upserted into the destination,
+//! so replication is safe to repeat and never duplicates rows. After every batch
+//! a [`ReplicationCheckpoint`] is reported, which can be persisted and passed back
+//! to [`replicate_from`] to resume an interrupted run.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use opentrade_core::ingest::replicate::{ReplicationFilter, replicate};
+//! use sqlx::PgPool;
+//!
+//! # async fn example(src: &PgPool, dst: &PgPool) -> Result<(), sqlx::Error> {
+//! let filter = ReplicationFilter {
+//!     symbol: Some("BTCUSDT".to_string()),
+//!     ..Default::default()
+//! };
+//! let report = replicate(src, dst, &filter).await?;
+//! println!("Replicated {} rows", report.rows);
+//! # Ok(())
+//! # }
+//! ```
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::{DEFAULT_EXCHANGE, KlineData};
+
+/// The number of rows copied per batch when no batch size is given.
+const DEFAULT_BATCH_SIZE: i64 = 1000;
+
+/// Criteria for selecting the rows to replicate. Unset fields match every row.
+#[derive(Debug, Clone, Default)]
+pub struct ReplicationFilter {
+    /// Only rows for this symbol.
+    pub symbol: Option<String>,
+    /// Only rows for this interval.
+    pub interval: Option<String>,
+    /// Only rows in this dataset.
+    pub dataset: Option<String>,
+    /// Only rows of this exchange.
+    pub exchange: Option<String>,
+    /// Only rows starting at or after this time.
+    pub start_time: Option<DateTime<Utc>>,
+    /// Only rows starting before this time.
+    pub end_time: Option<DateTime<Utc>>,
+    /// The number of rows copied per batch (defaults to 1000).
+    pub batch_size: Option<i64>,
+}
+
+/// The key of the last replicated row; replication resumes after it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplicationCheckpoint {
+    /// The start time of the last replicated row.
+    pub start_time: DateTime<Utc>,
+    /// The symbol of the last replicated row.
+    pub symbol: String,
+    /// The interval of the last replicated row.
+    pub interval: String,
+    /// The dataset of the last replicated row.
+    pub dataset: String,
+    /// The exchange of the last replicated row; checkpoints persisted before the
+    /// exchange was recorded resume after [`DEFAULT_EXCHANGE`].
+    #[serde(default = "default_exchange")]
+    pub exchange: String,
+}
+
+fn default_exchange() -> String {
+    DEFAULT_EXCHANGE.to_string()
+}
+
+impl From<&KlineData> for ReplicationCheckpoint {
+    fn from(kline: &KlineData) -> Self {
+        Self {
+            start_time: kline.start_time,
+            symbol: kline.symbol.clone(),
+            interval: kline.interval.clone(),
+            dataset: kline.dataset.clone(),
+            exchange: kline.exchange.clone(),
+        }
+    }
+}
+
+/// The outcome of a replication run.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ReplicationReport {
+    /// Number of rows upserted into the destination.
+    pub rows: usize,
+    /// The key of the last replicated row, if any row was replicated.
+    pub checkpoint: Option<ReplicationCheckpoint>,
+}
+
+/// Replicates every row matching `filter` from `src` to `dst`.
+///
+/// # Arguments
+///
+/// * `src` - The connection pool of the database to read from.
+/// * `dst` - The connection pool of the database to write to.
+/// * `filter` - Which rows to replicate.
+pub async fn replicate(
+    src: &sqlx::PgPool,
+    dst: &sqlx::PgPool,
+    filter: &ReplicationFilter,
+) -> Result<ReplicationReport, sqlx::Error> {
+    replicate_from(src, dst, filter, None, |_| {}).await
+}
+
+/// Replicates rows matching `filter` from `src` to `dst`, starting after `resume_from`.
+///
+/// `on_checkpoint` is called after every batch with the key of the last replicated
+/// row, so callers can persist progress.
+///
+/// # Arguments
+///
+/// * `src` - The connection pool of the database to read from.
+/// * `dst` - The connection pool of the database to write to.
+/// * `filter` - Which rows to replicate.
+/// * `resume_from` - The checkpoint of a previous run, if resuming.
+/// * `on_checkpoint` - Called with the checkpoint after every batch.
+pub async fn replicate_from(
+    src: &sqlx::PgPool,
+    dst: &sqlx::PgPool,
+    filter: &ReplicationFilter,
+    resume_from: Option<ReplicationCheckpoint>,
+    mut on_checkpoint: impl FnMut(&ReplicationCheckpoint),
+) -> Result<ReplicationReport, sqlx::Error> {
+    let batch_size = filter.batch_size.unwrap_or(DEFAULT_BATCH_SIZE);
+    let mut report = ReplicationReport {
+        rows: 0,
+        checkpoint: resume_from,
+    };
+
+    loop {
+        let batch = fetch_batch(src, filter, report.checkpoint.as_ref(), batch_size).await?;
+        let Some(last) = batch.last() else {
+            break;
+        };
+        let checkpoint = ReplicationCheckpoint::from(last);
+        for kline in &batch {
+            kline.upsert(dst).await?;
+        }
+        report.rows += batch.len();
+        on_checkpoint(&checkpoint);
+        log::info!(
+            "Replicated {} rows, last at {} {} {} ({})",
+            report.rows,
+            checkpoint.symbol,
+            checkpoint.interval,
+            checkpoint.start_time,
+            checkpoint.dataset
+        );
+        report.checkpoint = Some(checkpoint);
+        if (batch.len() as i64) < batch_size {
+            break;
+        }
+    }
+    Ok(report)
+}
+
+/// Reads the next batch of matching rows after `after` from the source database.
+pub(crate) async fn fetch_batch(
+    pool: &sqlx::PgPool,
+    filter: &ReplicationFilter,
+    after: Option<&ReplicationCheckpoint>,
+    batch_size: i64,
+) -> Result<Vec<KlineData>, sqlx::Error> {
+    let klines = sqlx::query_as!(
+        KlineData,
+        r#"
+        SELECT * FROM kline_data
+        WHERE ($1::text IS NULL OR symbol = $1)
+          AND ($2::text IS NULL OR interval = $2)
+          AND ($3::text IS NULL OR dataset = $3)
+          AND ($4::text IS NULL OR exchange = $4)
+          AND ($5::timestamptz IS NULL OR start_time >= $5)
+          AND ($6::timestamptz IS NULL OR start_time < $6)
+          AND ($7::timestamptz IS NULL
+               OR (start_time, symbol, interval, dataset, exchange)
+                  > ($7, $8::text, $9::text, $10::text, $11::text))
+        ORDER BY start_time, symbol, interval, dataset, exchange
+        LIMIT $12
+        "#,
+        filter.symbol,
+        filter.interval,
+        filter.dataset,
+        filter.exchange,
+        filter.start_time,
+        filter.end_time,
+        after.map(|checkpoint| checkpoint.start_time),
+        after.map(|checkpoint| checkpoint.symbol.as_str()),
+        after.map(|checkpoint| checkpoint.interval.as_str()),
+        after.map(|checkpoint| checkpoint.dataset.as_str()),
+        after.map(|checkpoint| checkpoint.exchange.as_str()),
+        batch_size
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(klines)
+}
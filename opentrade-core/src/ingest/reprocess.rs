@@ -0,0 +1,125 @@
+//! # Reprocessing
+//!
+//! This module closes the loop on parser and validation fixes: rows that were
+//! previously rejected into the quarantine table, or raw messages archived to
+//! disk, are re-parsed with the current parser and upserted into `kline_data`
+//! when they now pass validation.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use opentrade_core::ingest::reprocess::reprocess;
+//! use opentrade_core::models::quarantine::QuarantineFilter;
+//! use sqlx::PgPool;
+//!
+//! # async fn example(pool: &PgPool) -> Result<(), sqlx::Error> {
+//! let filter = QuarantineFilter {
+//!     source: Some("websocket".to_string()),
+//!     ..Default::default()
+//! };
+//! let report = reprocess(pool, &filter).await?;
+//! println!("{} of {} rows recovered", report.reprocessed, report.examined);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+use crate::models::quarantine::{QuarantineFilter, QuarantinedRow, parse_raw_kline};
+
+/// Counters describing a reprocessing run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReprocessReport {
+    /// Number of rows or messages examined.
+    pub examined: usize,
+    /// Number of rows or messages successfully parsed, validated and upserted.
+    pub reprocessed: usize,
+    /// Number of rows or messages that still fail to parse or validate.
+    pub still_failing: usize,
+}
+
+/// Reprocesses the quarantined rows matching `filter`.
+///
+/// Rows that now pass validation are upserted and marked as reprocessed; rows
+/// that still fail are left in the quarantine for further review.
+///
+/// # Arguments
+///
+/// * `pool` - The database connection pool.
+/// * `filter` - Which quarantined rows to reprocess.
+pub async fn reprocess(
+    pool: &sqlx::PgPool,
+    filter: &QuarantineFilter,
+) -> Result<ReprocessReport, sqlx::Error> {
+    let mut report = ReprocessReport::default();
+    for row in QuarantinedRow::list(pool, filter).await? {
+        report.examined += 1;
+        match row.reprocess(pool).await? {
+            Ok(_) => report.reprocessed += 1,
+            Err(reason) => {
+                log::debug!("Quarantined row {} still fails: {}", row.id, reason);
+                report.still_failing += 1;
+            }
+        }
+    }
+    log::info!(
+        "Reprocessed {} of {} quarantined rows, {} still failing",
+        report.reprocessed,
+        report.examined,
+        report.still_failing
+    );
+    Ok(report)
+}
+
+/// Reprocesses an archive of raw Kline messages stored as newline-delimited JSON.
+///
+/// Each line may be a raw WebSocket payload or a serialized Kline message.
+/// Messages that still fail are logged and counted, but not quarantined again.
+///
+/// # Arguments
+///
+/// * `pool` - The database connection pool.
+/// * `path` - The path of the archive file.
+/// * `dataset` - The dataset label the recovered klines are stored under.
+pub async fn reprocess_archive(
+    pool: &sqlx::PgPool,
+    path: impl AsRef<Path>,
+    dataset: &str,
+) -> Result<ReprocessReport> {
+    let path = path.as_ref();
+    let file = tokio::fs::File::open(path)
+        .await
+        .with_context(|| format!("Failed to open archive {}", path.display()))?;
+    let mut lines = BufReader::new(file).lines();
+
+    let mut report = ReprocessReport::default();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        report.examined += 1;
+        let kline = parse_raw_kline(&line)
+            .and_then(|message| message.to_validated_kline_data().map_err(|e| e.to_string()));
+        match kline {
+            Ok(kline) => {
+                kline.with_dataset(dataset).upsert(pool).await?;
+                report.reprocessed += 1;
+            }
+            Err(reason) => {
+                log::warn!("Archived message still fails: {}", reason);
+                report.still_failing += 1;
+            }
+        }
+    }
+    log::info!(
+        "Reprocessed {} of {} archived messages from {}, {} still failing",
+        report.reprocessed,
+        report.examined,
+        path.display(),
+        report.still_failing
+    );
+    Ok(report)
+}
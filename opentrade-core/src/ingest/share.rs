@@ -0,0 +1,366 @@
+//! # Shareable Datasets
+//!
+//! This module produces down-sampled and noise-perturbed copies of stored candles,
+//! so realistic datasets can be shared publicly or with vendors without handing
+//! out the exact data. Rows are selected with a
+//! [`ReplicationFilter`](super::replicate::ReplicationFilter), transformed by a
+//! [`ShareTransform`] and upserted into a target dataset, in the same or another
+//! database.
+//!
+//! ## Transforms
+//!
+//! - Sampling keeps a fraction of the candles, chosen per candle.
+//! - Price noise scales the open, high, low and close of a candle by the same
+//!   random factor, so the candle stays consistent (low ≤ open, close ≤ high).
+//! - Volume noise scales the volume by another random factor; the quote volume is
+//!   scaled by both.
+//! - Symbols can be renamed (e.g., "BTCUSDT" to "ASSET1").
+//! - Trade ids are always cleared, as they identify the exchange's trades.
+//!
+//! Random choices are derived from the seed and the key of each candle, so a copy
+//! is reproducible: running it again, or after more candles were stored, yields
+//! the same values for the same candles.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use opentrade_core::ingest::replicate::ReplicationFilter;
+//! use opentrade_core::ingest::share::{ShareTransform, share};
+//! use sqlx::PgPool;
+//!
+//! # async fn example(src: &PgPool, dst: &PgPool) -> anyhow::Result<()> {
+//! let filter = ReplicationFilter {
+//!     symbol: Some("BTCUSDT".to_string()),
+//!     interval: Some("1h".to_string()),
+//!     ..Default::default()
+//! };
+//! let transform = ShareTransform::new(42)
+//!     .with_sample_rate(0.5)
+//!     .with_price_noise(0.001)
+//!     .with_symbol_alias("BTCUSDT", "ASSET1");
+//! let report = share(src, dst, &filter, &transform, "public").await?;
+//! println!("Wrote {} of {} candles", report.written, report.read);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use anyhow::{Context, Result, bail};
+use sqlx::types::BigDecimal as Decimal;
+
+use crate::ingest::replicate::{ReplicationCheckpoint, ReplicationFilter, fetch_batch};
+use crate::models::KlineData;
+
+/// The number of rows read per batch when the filter sets no batch size.
+const DEFAULT_BATCH_SIZE: i64 = 1000;
+
+/// The number of decimal places of prices and volumes in `kline_data`.
+const DECIMAL_SCALE: i64 = 8;
+
+/// How stored candles are transformed into a shareable copy.
+///
+/// The default transform of [`new`](Self::new) keeps every candle unchanged except
+/// for its trade ids.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShareTransform {
+    seed: u64,
+    sample_rate: f64,
+    price_noise: f64,
+    volume_noise: f64,
+    symbol_aliases: HashMap<String, String>,
+}
+
+impl ShareTransform {
+    /// Creates a transform whose random choices are derived from `seed`.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            sample_rate: 1.0,
+            price_noise: 0.0,
+            volume_noise: 0.0,
+            symbol_aliases: HashMap::new(),
+        }
+    }
+
+    /// Keeps each candle with the given probability, in `(0, 1]`.
+    pub fn with_sample_rate(mut self, rate: f64) -> Self {
+        self.sample_rate = rate;
+        self
+    }
+
+    /// Scales the prices of each candle by a random factor in
+    /// `[1 - noise, 1 + noise]` (e.g., 0.001 for ±0.1%).
+    pub fn with_price_noise(mut self, noise: f64) -> Self {
+        self.price_noise = noise;
+        self
+    }
+
+    /// Scales the volume of each candle by a random factor in
+    /// `[1 - noise, 1 + noise]`.
+    pub fn with_volume_noise(mut self, noise: f64) -> Self {
+        self.volume_noise = noise;
+        self
+    }
+
+    /// Stores the candles of `symbol` under `alias`.
+    pub fn with_symbol_alias(mut self, symbol: &str, alias: &str) -> Self {
+        self.symbol_aliases
+            .insert(symbol.to_string(), alias.to_string());
+        self
+    }
+
+    /// Checks that the rates and noise levels are in range.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the sample rate is not in `(0, 1]` or a noise level is
+    /// not in `[0, 1)`.
+    pub fn validate(&self) -> Result<()> {
+        if !(self.sample_rate > 0.0 && self.sample_rate <= 1.0) {
+            bail!("Sample rate must be in (0, 1], got {}", self.sample_rate);
+        }
+        for (name, noise) in [("Price", self.price_noise), ("Volume", self.volume_noise)] {
+            if !(0.0..1.0).contains(&noise) {
+                bail!("{} noise must be in [0, 1), got {}", name, noise);
+            }
+        }
+        Ok(())
+    }
+
+    /// Transforms a candle, or returns `None` if sampling drops it.
+    ///
+    /// # Arguments
+    ///
+    /// * `kline` - The stored candle.
+    /// * `dataset` - The dataset the copy is stored under.
+    pub fn apply(&self, kline: &KlineData, dataset: &str) -> Result<Option<KlineData>> {
+        let mut random = CandleRandom::new(self.seed, kline);
+        if random.next_unit() >= self.sample_rate {
+            return Ok(None);
+        }
+        let price = factor(random.next_signed(), self.price_noise)?;
+        let volume = factor(random.next_signed(), self.volume_noise)?;
+        let scale = |value: &Decimal, factor: &Decimal| (value * factor).round(DECIMAL_SCALE);
+
+        let mut copy = kline.clone().with_dataset(dataset);
+        if let Some(alias) = self.symbol_aliases.get(&kline.symbol) {
+            copy.symbol = alias.clone();
+        }
+        copy.first_trade_id = 0;
+        copy.last_trade_id = 0;
+        copy.open = scale(&kline.open, &price);
+        copy.high = scale(&kline.high, &price);
+        copy.low = scale(&kline.low, &price);
+        copy.close = scale(&kline.close, &price);
+        copy.volume = scale(&kline.volume, &volume);
+        copy.quote_volume = kline
+            .quote_volume
+            .as_ref()
+            .map(|quote| scale(quote, &(&price * &volume)));
+        copy.created_at = None;
+        copy.update_at = None;
+        Ok(Some(copy))
+    }
+}
+
+/// Returns `1 + noise * r` as a decimal.
+fn factor(r: f64, noise: f64) -> Result<Decimal> {
+    let factor = format!("{:.12}", 1.0 + noise * r);
+    Decimal::from_str(&factor).with_context(|| format!("Invalid noise factor: {}", factor))
+}
+
+/// A random sequence derived from a seed and the key of a candle.
+struct CandleRandom {
+    state: u64,
+}
+
+impl CandleRandom {
+    fn new(seed: u64, kline: &KlineData) -> Self {
+        // FNV-1a over the key, which unlike `DefaultHasher` is stable across builds.
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325 ^ seed;
+        let start_time = kline.start_time.timestamp_millis().to_le_bytes();
+        for byte in kline
+            .symbol
+            .bytes()
+            .chain([0])
+            .chain(kline.interval.bytes())
+            .chain([0])
+            .chain(start_time)
+        {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+        Self { state: hash.max(1) }
+    }
+
+    /// Returns the next value of a xorshift generator.
+    fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    /// Returns a value in `[0, 1)`.
+    fn next_unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns a value in `[-1, 1)`.
+    fn next_signed(&mut self) -> f64 {
+        self.next_unit() * 2.0 - 1.0
+    }
+}
+
+/// The outcome of a [`share`] run.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ShareReport {
+    /// Number of candles read from the source.
+    pub read: usize,
+    /// Number of transformed candles written to the target.
+    pub written: usize,
+}
+
+/// Copies the candles matching `filter` from `src` into `dataset` in `dst`,
+/// transformed by `transform`.
+///
+/// # Arguments
+///
+/// * `src` - The connection pool of the database to read from.
+/// * `dst` - The connection pool of the database to write to, which may be the
+///   same as `src`.
+/// * `filter` - Which candles to copy.
+/// * `transform` - How candles are sampled and perturbed.
+/// * `dataset` - The dataset the copies are stored under.
+///
+/// # Errors
+///
+/// Returns an error if the transform is invalid or a query fails.
+pub async fn share(
+    src: &sqlx::PgPool,
+    dst: &sqlx::PgPool,
+    filter: &ReplicationFilter,
+    transform: &ShareTransform,
+    dataset: &str,
+) -> Result<ShareReport> {
+    transform.validate()?;
+    // Copies written into a dataset the filter reads would be read back and
+    // transformed again if both pools point to the same database.
+    if filter
+        .dataset
+        .as_deref()
+        .is_none_or(|source| source == dataset)
+    {
+        log::warn!(
+            "The filter also reads dataset {}; the target must be another database",
+            dataset
+        );
+    }
+
+    let batch_size = filter.batch_size.unwrap_or(DEFAULT_BATCH_SIZE);
+    let mut report = ShareReport::default();
+    let mut checkpoint: Option<ReplicationCheckpoint> = None;
+    loop {
+        let batch = fetch_batch(src, filter, checkpoint.as_ref(), batch_size).await?;
+        let Some(last) = batch.last() else {
+            break;
+        };
+        checkpoint = Some(ReplicationCheckpoint::from(last));
+        let mut copies = Vec::with_capacity(batch.len());
+        for kline in &batch {
+            copies.extend(transform.apply(kline, dataset)?);
+        }
+        KlineData::upsert_many(dst, &copies).await?;
+        report.read += batch.len();
+        report.written += copies.len();
+        log::info!(
+            "Shared {} of {} candles into dataset {}",
+            report.written,
+            report.read,
+            dataset
+        );
+        if (batch.len() as i64) < batch_size {
+            break;
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A minute boundary in November 2023, so that the candles pass validation.
+    const BASE_TIME: u64 = 1_699_999_200_000;
+
+    fn kline(minute: u64) -> KlineData {
+        let price = |value: &str| Decimal::from_str(value).unwrap();
+        KlineData::new(
+            &(BASE_TIME + minute * 60_000),
+            &(BASE_TIME + minute * 60_000 + 59_999),
+            "BTCUSDT",
+            "1m",
+            10,
+            20,
+            price("100.5"),
+            price("102"),
+            price("99"),
+            price("101"),
+            price("3"),
+            Some(11),
+            Some(price("301.5")),
+        )
+    }
+
+    #[test]
+    fn test_noise_keeps_candles_consistent_and_reproducible() {
+        let transform = ShareTransform::new(7)
+            .with_price_noise(0.05)
+            .with_volume_noise(0.2)
+            .with_symbol_alias("BTCUSDT", "ASSET1");
+        for minute in 0..50 {
+            let copy = transform.apply(&kline(minute), "public").unwrap().unwrap();
+            assert!(copy.validate().is_ok());
+            assert_eq!(copy.symbol, "ASSET1");
+            assert_eq!(copy.dataset, "public");
+            assert_eq!((copy.first_trade_id, copy.last_trade_id), (0, 0));
+            let again = transform.apply(&kline(minute), "public").unwrap().unwrap();
+            assert_eq!(copy.close, again.close);
+        }
+        let copy = transform.apply(&kline(0), "public").unwrap().unwrap();
+        assert_ne!(copy.close, kline(0).close);
+    }
+
+    #[test]
+    fn test_sampling_keeps_a_fraction() {
+        let transform = ShareTransform::new(7).with_sample_rate(0.25);
+        let kept = (0..1000)
+            .filter_map(|minute| transform.apply(&kline(minute), "public").unwrap())
+            .count();
+        assert!((150..350).contains(&kept), "kept {}", kept);
+        let unchanged = ShareTransform::new(7)
+            .apply(&kline(0), "public")
+            .unwrap()
+            .unwrap();
+        assert_eq!(unchanged.close, kline(0).close);
+    }
+
+    #[test]
+    fn test_validate_rejects_out_of_range_settings() {
+        assert!(
+            ShareTransform::new(1)
+                .with_sample_rate(0.0)
+                .validate()
+                .is_err()
+        );
+        assert!(
+            ShareTransform::new(1)
+                .with_price_noise(1.0)
+                .validate()
+                .is_err()
+        );
+        assert!(ShareTransform::new(1).validate().is_ok());
+    }
+}
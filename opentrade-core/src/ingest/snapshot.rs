@@ -0,0 +1,273 @@
+//! # Snapshots
+//!
+//! This module exports all stored candles of a symbol and exchange into a
+//! self-contained, gzip-compressed archive and restores such archives into a
+//! database, which makes it easy to share reproducible research datasets.
+//!
+//! ## Archive Format
+//!
+//! An archive is gzip-compressed newline-delimited JSON. The first line is the
+//! [`SnapshotMetadata`] (including the schema version of the exporting database),
+//! and every following line is one candle as a [`SerdableKlineData`].
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use opentrade_core::ingest::snapshot::{restore, snapshot};
+//! use sqlx::PgPool;
+//!
+//! # async fn example(prod: &PgPool, research: &PgPool) -> anyhow::Result<()> {
+//! snapshot(prod, "BTCUSDT", "default", "binance", "btcusdt.ndjson.gz").await?;
+//! let metadata = restore(research, "btcusdt.ndjson.gz").await?;
+//! println!("Restored {} candles of {}", metadata.rows, metadata.symbol);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Utc};
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use futures_util::TryStreamExt;
+use serde::{Deserialize, Serialize};
+
+use crate::models::schema::SCHEMA_VERSION;
+use crate::models::{DEFAULT_EXCHANGE, KlineData, SerdableKlineData};
+
+/// The version of the archive layout written by [`snapshot`].
+pub const SNAPSHOT_FORMAT_VERSION: u32 = 1;
+
+/// Describes the contents of a snapshot archive.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotMetadata {
+    /// The version of the archive layout.
+    pub format_version: u32,
+    /// The database schema version the snapshot was taken from.
+    pub schema_version: i64,
+    /// The trading symbol.
+    pub symbol: String,
+    /// The dataset the candles were read from and are restored into.
+    pub dataset: String,
+    /// The exchange the candles were captured from; archives written before the
+    /// exchange was recorded hold [`DEFAULT_EXCHANGE`] candles.
+    #[serde(default = "default_exchange")]
+    pub exchange: String,
+    /// The number of candles in the archive.
+    pub rows: usize,
+    /// The time the snapshot was taken.
+    pub created_at: DateTime<Utc>,
+}
+
+fn default_exchange() -> String {
+    DEFAULT_EXCHANGE.to_string()
+}
+
+/// Writes every stored candle of a symbol and exchange in a dataset to a
+/// compressed archive.
+///
+/// # Arguments
+///
+/// * `pool` - The database connection pool.
+/// * `symbol` - The trading symbol.
+/// * `dataset` - The dataset to export.
+/// * `exchange` - The exchange whose candles are exported (e.g., "binance").
+/// * `path` - The path of the archive to create.
+///
+/// # Returns
+///
+/// The metadata written to the archive.
+pub async fn snapshot(
+    pool: &sqlx::PgPool,
+    symbol: &str,
+    dataset: &str,
+    exchange: &str,
+    path: impl AsRef<Path>,
+) -> Result<SnapshotMetadata> {
+    let path = path.as_ref();
+    let mut body = Vec::new();
+    let mut rows = 0;
+    let mut klines = sqlx::query_as!(
+        KlineData,
+        r#"
+        SELECT * FROM kline_data
+        WHERE symbol = $1 AND dataset = $2 AND exchange = $3
+        ORDER BY interval, start_time
+        "#,
+        symbol,
+        dataset,
+        exchange
+    )
+    .fetch(pool);
+    while let Some(kline) = klines.try_next().await? {
+        serde_json::to_writer(&mut body, &SerdableKlineData::from(kline))?;
+        body.push(b'\n');
+        rows += 1;
+    }
+
+    let metadata = SnapshotMetadata {
+        format_version: SNAPSHOT_FORMAT_VERSION,
+        schema_version: SCHEMA_VERSION,
+        symbol: symbol.to_string(),
+        dataset: dataset.to_string(),
+        exchange: exchange.to_string(),
+        rows,
+        created_at: Utc::now(),
+    };
+    let file = File::create(path)
+        .with_context(|| format!("Failed to create snapshot {}", path.display()))?;
+    let mut encoder = GzEncoder::new(BufWriter::new(file), Compression::default());
+    serde_json::to_writer(&mut encoder, &metadata)?;
+    encoder.write_all(b"\n")?;
+    encoder.write_all(&body)?;
+    encoder.finish()?.flush()?;
+
+    log::info!(
+        "Wrote snapshot of {} candles for symbol {} to {}",
+        rows,
+        symbol,
+        path.display()
+    );
+    Ok(metadata)
+}
+
+/// Reads the metadata line of a snapshot archive without restoring it.
+///
+/// # Arguments
+///
+/// * `path` - The path of the archive.
+pub fn read_metadata(path: impl AsRef<Path>) -> Result<SnapshotMetadata> {
+    let mut lines = open_archive(path.as_ref())?;
+    parse_metadata(&mut lines)
+}
+
+/// Restores a snapshot archive into the database, upserting every candle into the
+/// dataset recorded in the archive.
+///
+/// # Arguments
+///
+/// * `pool` - The database connection pool.
+/// * `path` - The path of the archive.
+///
+/// # Returns
+///
+/// The metadata of the restored archive.
+///
+/// # Errors
+///
+/// Returns an error if the archive layout is not supported, the archive is
+/// truncated, or a candle fails to parse or validate.
+pub async fn restore(pool: &sqlx::PgPool, path: impl AsRef<Path>) -> Result<SnapshotMetadata> {
+    let path = path.as_ref();
+    let mut lines = open_archive(path)?;
+    let metadata = parse_metadata(&mut lines)?;
+    if metadata.schema_version != SCHEMA_VERSION {
+        log::warn!(
+            "Snapshot {} was taken at schema version {}, restoring into version {}",
+            path.display(),
+            metadata.schema_version,
+            SCHEMA_VERSION
+        );
+    }
+
+    let mut restored = 0;
+    for line in lines {
+        let line = line?;
+        if line.is_empty() {
+            continue;
+        }
+        let message: SerdableKlineData = serde_json::from_str(&line)?;
+        let kline = message
+            .to_validated_kline_data()
+            .with_context(|| format!("Invalid candle in snapshot: {}", line))?
+            .with_dataset(&metadata.dataset);
+        kline.upsert(pool).await?;
+        restored += 1;
+    }
+    if restored != metadata.rows {
+        bail!(
+            "Snapshot {} is truncated: expected {} candles, found {}",
+            path.display(),
+            metadata.rows,
+            restored
+        );
+    }
+
+    log::info!(
+        "Restored {} candles for symbol {} into dataset {}",
+        restored,
+        metadata.symbol,
+        metadata.dataset
+    );
+    Ok(metadata)
+}
+
+/// Opens a snapshot archive as decompressed lines.
+fn open_archive(path: &Path) -> Result<std::io::Lines<BufReader<GzDecoder<File>>>> {
+    let file =
+        File::open(path).with_context(|| format!("Failed to open snapshot {}", path.display()))?;
+    Ok(BufReader::new(GzDecoder::new(file)).lines())
+}
+
+/// Parses and checks the metadata line at the start of an archive.
+fn parse_metadata(
+    lines: &mut impl Iterator<Item = std::io::Result<String>>,
+) -> Result<SnapshotMetadata> {
+    let line = lines.next().context("Snapshot is empty")??;
+    let metadata: SnapshotMetadata =
+        serde_json::from_str(&line).context("Failed to parse snapshot metadata")?;
+    if metadata.format_version != SNAPSHOT_FORMAT_VERSION {
+        bail!(
+            "Unsupported snapshot format version {} (expected {})",
+            metadata.format_version,
+            SNAPSHOT_FORMAT_VERSION
+        );
+    }
+    Ok(metadata)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_archive(name: &str, metadata: &SnapshotMetadata) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(name);
+        let mut encoder = GzEncoder::new(File::create(&path).unwrap(), Compression::default());
+        serde_json::to_writer(&mut encoder, metadata).unwrap();
+        encoder.write_all(b"\n").unwrap();
+        encoder.finish().unwrap();
+        path
+    }
+
+    fn metadata() -> SnapshotMetadata {
+        SnapshotMetadata {
+            format_version: SNAPSHOT_FORMAT_VERSION,
+            schema_version: SCHEMA_VERSION,
+            symbol: "BTCUSDT".to_string(),
+            dataset: "research".to_string(),
+            exchange: "binance".to_string(),
+            rows: 0,
+            created_at: DateTime::from_timestamp_millis(1751897340000).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_read_metadata_roundtrip() {
+        let path = write_archive("opentrade-snapshot-roundtrip.ndjson.gz", &metadata());
+        assert_eq!(read_metadata(&path).unwrap(), metadata());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_read_metadata_rejects_unknown_format() {
+        let mut metadata = metadata();
+        metadata.format_version = SNAPSHOT_FORMAT_VERSION + 1;
+        let path = write_archive("opentrade-snapshot-unknown.ndjson.gz", &metadata);
+        assert!(read_metadata(&path).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,317 @@
+//! # Streaming Statistics
+//!
+//! This module provides a built-in [`StatsHandler`] that aggregates throughput and
+//! health metrics for a live Kline stream and periodically emits a summary through
+//! the `log` facade.
+//!
+//! ## Collected Metrics
+//!
+//! For every reporting window the following values are tracked:
+//! - Number of messages received
+//! - Number of unique symbols seen
+//! - Number of finalized (closed) candles
+//! - Number of errors (parse failures and handler failures)
+//! - 99th percentile latency of the handler chain
+//!
+//! Message counts are recorded by the handler itself, while errors and handler
+//! latency are recorded by the streaming client once a shared [`StreamStats`]
+//! is attached with [`KlineStreaming::attach_stats`].
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use opentrade_core::data_source::websocket::KlineStreaming;
+//! use opentrade_core::ingest::stats::StatsHandler;
+//! use binance_spot_connector_rust::market::klines::KlineInterval;
+//! use std::time::Duration;
+//! # use anyhow::Result;
+//!
+//! # async fn example() -> Result<()> {
+//! let mut stream = KlineStreaming::new("BTCUSDT", KlineInterval::Minutes1).await?;
+//!
+//! let stats_handler = StatsHandler::new(Duration::from_secs(60));
+//! stream.attach_stats(stats_handler.stats());
+//! stream.add_callback(stats_handler);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`KlineStreaming::attach_stats`]: crate::data_source::websocket::KlineStreaming::attach_stats
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::data_source::websocket::MessageHandler;
+use crate::models::SerdableKlineData;
+
+/// A point-in-time summary of a single statistics window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatsSummary {
+    /// Length of the window the summary covers.
+    pub window: Duration,
+    /// Number of messages received during the window.
+    pub messages: u64,
+    /// Number of distinct symbols seen during the window.
+    pub unique_symbols: usize,
+    /// Number of candles that were finalized during the window.
+    pub finalized_candles: u64,
+    /// Number of errors recorded during the window.
+    pub errors: u64,
+    /// 99th percentile handler latency, if any latency samples were recorded.
+    pub p99_handler_latency: Option<Duration>,
+}
+
+impl fmt::Display for StatsSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "window={:.1}s messages={} unique_symbols={} finalized_candles={} errors={} p99_handler_latency=",
+            self.window.as_secs_f64(),
+            self.messages,
+            self.unique_symbols,
+            self.finalized_candles,
+            self.errors,
+        )?;
+        match self.p99_handler_latency {
+            Some(latency) => write!(f, "{:?}", latency),
+            None => write!(f, "n/a"),
+        }
+    }
+}
+
+/// Mutable state of the current statistics window.
+struct StatsWindow {
+    started_at: Instant,
+    messages: u64,
+    symbols: HashSet<String>,
+    finalized_candles: u64,
+    errors: u64,
+    latencies: Vec<Duration>,
+    /// Start time of the most recent candle per `(symbol, interval)`, kept across windows.
+    open_candles: HashMap<(String, String), u64>,
+}
+
+impl StatsWindow {
+    fn new() -> Self {
+        Self {
+            started_at: Instant::now(),
+            messages: 0,
+            symbols: HashSet::new(),
+            finalized_candles: 0,
+            errors: 0,
+            latencies: Vec::new(),
+            open_candles: HashMap::new(),
+        }
+    }
+}
+
+/// Thread-safe statistics accumulator shared between a stream and its [`StatsHandler`].
+///
+/// A candle is counted as finalized when a message marks it as final, or when a
+/// message for a later candle of the same symbol and interval arrives, since the
+/// previous candle can no longer change.
+pub struct StreamStats {
+    window: Mutex<StatsWindow>,
+}
+
+impl Default for StreamStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StreamStats {
+    /// Creates an empty statistics accumulator with its window starting now.
+    pub fn new() -> Self {
+        Self {
+            window: Mutex::new(StatsWindow::new()),
+        }
+    }
+
+    /// Records a received Kline message.
+    pub fn record_message(&self, message: &SerdableKlineData) {
+        let mut window = self.window.lock().unwrap();
+        window.messages += 1;
+        if !window.symbols.contains(&message.symbol) {
+            window.symbols.insert(message.symbol.clone());
+        }
+        let key = (message.symbol.clone(), message.interval.clone());
+        if message.is_final {
+            window.open_candles.remove(&key);
+            window.finalized_candles += 1;
+            return;
+        }
+        match window.open_candles.insert(key, message.start_time) {
+            Some(previous) if previous < message.start_time => window.finalized_candles += 1,
+            _ => {}
+        }
+    }
+
+    /// Records a parse or handler error.
+    pub fn record_error(&self) {
+        self.window.lock().unwrap().errors += 1;
+    }
+
+    /// Records how long the handler chain took to process a single message.
+    pub fn record_latency(&self, latency: Duration) {
+        self.window.lock().unwrap().latencies.push(latency);
+    }
+
+    /// Returns the time elapsed since the current window started.
+    pub fn elapsed(&self) -> Duration {
+        self.window.lock().unwrap().started_at.elapsed()
+    }
+
+    /// Closes the current window, returning its summary and starting a new one.
+    ///
+    /// The per-candle tracking used for finalization detection is carried over
+    /// to the new window so candles spanning a window boundary are still counted.
+    pub fn take_summary(&self) -> StatsSummary {
+        let mut window = self.window.lock().unwrap();
+        let mut next = StatsWindow::new();
+        next.open_candles = std::mem::take(&mut window.open_candles);
+        let mut finished = std::mem::replace(&mut *window, next);
+
+        StatsSummary {
+            window: finished.started_at.elapsed(),
+            messages: finished.messages,
+            unique_symbols: finished.symbols.len(),
+            finalized_candles: finished.finalized_candles,
+            errors: finished.errors,
+            p99_handler_latency: percentile(&mut finished.latencies, 0.99),
+        }
+    }
+}
+
+/// Computes the nearest-rank percentile of the given samples.
+fn percentile(samples: &mut [Duration], quantile: f64) -> Option<Duration> {
+    if samples.is_empty() {
+        return None;
+    }
+    samples.sort_unstable();
+    let rank = (quantile * samples.len() as f64).ceil() as usize;
+    Some(samples[rank.clamp(1, samples.len()) - 1])
+}
+
+/// A message handler that collects stream statistics and logs a summary every period.
+///
+/// The summary is emitted at INFO level when a message arrives after the period has
+/// elapsed, so an idle stream produces no log output.
+pub struct StatsHandler {
+    stats: Arc<StreamStats>,
+    period: Duration,
+}
+
+impl StatsHandler {
+    /// Creates a new [`StatsHandler`] that reports once per `period`.
+    ///
+    /// # Arguments
+    ///
+    /// * `period` - The length of each reporting window (e.g., one minute).
+    pub fn new(period: Duration) -> Self {
+        Self {
+            stats: Arc::new(StreamStats::new()),
+            period,
+        }
+    }
+
+    /// Returns a handle to the shared statistics, to be attached to the stream so that
+    /// errors and handler latency are recorded as well.
+    pub fn stats(&self) -> Arc<StreamStats> {
+        Arc::clone(&self.stats)
+    }
+}
+
+#[async_trait]
+impl MessageHandler<SerdableKlineData> for StatsHandler {
+    async fn handle_message(&mut self, message: &SerdableKlineData) -> Result<()> {
+        self.stats.record_message(message);
+        if self.stats.elapsed() >= self.period {
+            log::info!("Stream stats: {}", self.stats.take_summary());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kline(symbol: &str, start_time: u64) -> SerdableKlineData {
+        SerdableKlineData {
+            start_time,
+            end_time: start_time + 59_999,
+            symbol: symbol.to_string(),
+            interval: "1m".to_string(),
+            first_trade_id: 1,
+            last_trade_id: 2,
+            open: "1.0".to_string(),
+            close: "1.0".to_string(),
+            high: "1.0".to_string(),
+            low: "1.0".to_string(),
+            volume: "1.0".to_string(),
+            trade_count: 2,
+            quote_volume: "1.0".to_string(),
+            event_time: None,
+            is_final: false,
+            exchange: None,
+        }
+    }
+
+    #[test]
+    fn test_summary_counts_messages_symbols_and_finalized() {
+        let stats = StreamStats::new();
+        stats.record_message(&kline("BTCUSDT", 0));
+        stats.record_message(&kline("BTCUSDT", 0));
+        stats.record_message(&kline("ETHUSDT", 0));
+        stats.record_message(&kline("BTCUSDT", 60_000));
+        stats.record_error();
+
+        let summary = stats.take_summary();
+        assert_eq!(summary.messages, 4);
+        assert_eq!(summary.unique_symbols, 2);
+        assert_eq!(summary.finalized_candles, 1);
+        assert_eq!(summary.errors, 1);
+        assert_eq!(summary.p99_handler_latency, None);
+    }
+
+    #[test]
+    fn test_final_messages_are_counted_once() {
+        let stats = StreamStats::new();
+        stats.record_message(&kline("BTCUSDT", 0));
+        stats.record_message(&SerdableKlineData {
+            is_final: true,
+            ..kline("BTCUSDT", 0)
+        });
+        stats.record_message(&kline("BTCUSDT", 60_000));
+
+        assert_eq!(stats.take_summary().finalized_candles, 1);
+    }
+
+    #[test]
+    fn test_take_summary_resets_window_but_keeps_open_candles() {
+        let stats = StreamStats::new();
+        stats.record_message(&kline("BTCUSDT", 0));
+        stats.take_summary();
+
+        stats.record_message(&kline("BTCUSDT", 60_000));
+        let summary = stats.take_summary();
+        assert_eq!(summary.messages, 1);
+        assert_eq!(summary.finalized_candles, 1);
+    }
+
+    #[test]
+    fn test_p99_latency() {
+        let stats = StreamStats::new();
+        for ms in 1..=100 {
+            stats.record_latency(Duration::from_millis(ms));
+        }
+        let summary = stats.take_summary();
+        assert_eq!(summary.p99_handler_latency, Some(Duration::from_millis(99)));
+    }
+}
@@ -0,0 +1,197 @@
+//! # Symbol Status Tracking
+//!
+//! This module polls the exchangeInfo endpoint and records trading status
+//! changes (e.g., `TRADING` → `BREAK`) in the `symbol_status_history` table.
+//! Symbols that are no longer listed at all are recorded as
+//! [`DELISTED`](crate::models::symbol_status::DELISTED).
+//!
+//! Backfill and streaming components use [`refresh_symbol_status`] to skip
+//! inactive symbols, and [`wait_until_inactive`] to stop a running stream once
+//! its symbol is delisted or halted. [`refresh_latest_statuses`] and
+//! [`wait_until_all_inactive`] do the same for a group of symbols with one
+//! exchangeInfo request per poll.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use opentrade_core::ingest::status::refresh_symbol_status;
+//! use sqlx::PgPool;
+//!
+//! # async fn example(pool: &PgPool) -> anyhow::Result<()> {
+//! let status = refresh_symbol_status(pool, "BTCUSDT").await?;
+//! if !status.is_active() {
+//!     println!("BTCUSDT is {}, skipping", status.status);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+
+use crate::data_source::rest::{extract_symbol_statuses, get_exchange_info};
+use crate::models::symbol_status::{DELISTED, SymbolStatus};
+
+/// Resolves the current status of each tracked symbol from an exchange listing.
+///
+/// Symbols missing from `listing` are reported as [`DELISTED`].
+pub fn resolve_statuses(listing: &[(String, String)], symbols: &[&str]) -> Vec<(String, String)> {
+    let listed: HashMap<&str, &str> = listing
+        .iter()
+        .map(|(symbol, status)| (symbol.as_str(), status.as_str()))
+        .collect();
+    symbols
+        .iter()
+        .map(|symbol| {
+            let status = listed.get(symbol).copied().unwrap_or(DELISTED);
+            (symbol.to_string(), status.to_string())
+        })
+        .collect()
+}
+
+/// Fetches the exchange listing and records the status of each symbol.
+///
+/// # Arguments
+///
+/// * `pool` - The database connection pool.
+/// * `symbols` - The symbols to track.
+///
+/// # Returns
+///
+/// The status changes that were recorded, if any.
+pub async fn refresh_symbol_statuses(
+    pool: &sqlx::PgPool,
+    symbols: &[&str],
+) -> Result<Vec<SymbolStatus>> {
+    let exchange_info = get_exchange_info()
+        .await
+        .context("Failed to fetch exchange info")?;
+    let listing =
+        extract_symbol_statuses(&exchange_info).context("Failed to parse exchange info")?;
+
+    let mut changes = Vec::new();
+    for (symbol, status) in resolve_statuses(&listing, symbols) {
+        if let Some(change) = SymbolStatus::record(pool, &symbol, &status).await? {
+            log::info!("Symbol {} status changed to {}", symbol, status);
+            changes.push(change);
+        }
+    }
+    Ok(changes)
+}
+
+/// Refreshes the status of a single symbol and returns its latest status.
+///
+/// # Arguments
+///
+/// * `pool` - The database connection pool.
+/// * `symbol` - The trading symbol.
+pub async fn refresh_symbol_status(pool: &sqlx::PgPool, symbol: &str) -> Result<SymbolStatus> {
+    refresh_symbol_statuses(pool, &[symbol]).await?;
+    SymbolStatus::latest(pool, symbol)
+        .await?
+        .with_context(|| format!("No status recorded for symbol {}", symbol))
+}
+
+/// Refreshes the statuses of many symbols with a single exchangeInfo request and
+/// returns their latest statuses, in the order of `symbols`.
+///
+/// # Arguments
+///
+/// * `pool` - The database connection pool.
+/// * `symbols` - The trading symbols.
+pub async fn refresh_latest_statuses(
+    pool: &sqlx::PgPool,
+    symbols: &[&str],
+) -> Result<Vec<SymbolStatus>> {
+    refresh_symbol_statuses(pool, symbols).await?;
+    let mut statuses = Vec::with_capacity(symbols.len());
+    for symbol in symbols {
+        let status = SymbolStatus::latest(pool, symbol)
+            .await?
+            .with_context(|| format!("No status recorded for symbol {}", symbol))?;
+        statuses.push(status);
+    }
+    Ok(statuses)
+}
+
+/// Polls the status of a symbol every `poll_interval` and returns once it is no
+/// longer active.
+///
+/// Failed polls are logged and retried on the next tick, so a flaky exchangeInfo
+/// endpoint never stops a healthy stream.
+///
+/// # Arguments
+///
+/// * `pool` - The database connection pool.
+/// * `symbol` - The trading symbol.
+/// * `poll_interval` - How often to poll the exchange.
+pub async fn wait_until_inactive(
+    pool: &sqlx::PgPool,
+    symbol: &str,
+    poll_interval: Duration,
+) -> SymbolStatus {
+    let mut ticker = tokio::time::interval(poll_interval);
+    loop {
+        ticker.tick().await;
+        match refresh_symbol_status(pool, symbol).await {
+            Ok(status) if !status.is_active() => return status,
+            Ok(_) => {}
+            Err(e) => log::warn!("Failed to refresh status of {}: {}", symbol, e),
+        }
+    }
+}
+
+/// Polls the statuses of many symbols every `poll_interval`, with one exchangeInfo
+/// request per poll, and returns their statuses once none of them is active.
+///
+/// Failed polls are logged and retried on the next tick, like in
+/// [`wait_until_inactive`].
+///
+/// # Arguments
+///
+/// * `pool` - The database connection pool.
+/// * `symbols` - The trading symbols.
+/// * `poll_interval` - How often to poll the exchange.
+pub async fn wait_until_all_inactive(
+    pool: &sqlx::PgPool,
+    symbols: &[&str],
+    poll_interval: Duration,
+) -> Vec<SymbolStatus> {
+    let mut ticker = tokio::time::interval(poll_interval);
+    loop {
+        ticker.tick().await;
+        match refresh_latest_statuses(pool, symbols).await {
+            Ok(statuses) if !statuses.iter().any(SymbolStatus::is_active) => return statuses,
+            Ok(_) => {}
+            Err(e) => log::warn!(
+                "Failed to refresh the status of {} symbols: {}",
+                symbols.len(),
+                e
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_statuses_marks_missing_symbols_delisted() {
+        let listing = vec![
+            ("BTCUSDT".to_string(), "TRADING".to_string()),
+            ("LUNAUSDT".to_string(), "BREAK".to_string()),
+        ];
+        let statuses = resolve_statuses(&listing, &["BTCUSDT", "LUNAUSDT", "FTTUSDT"]);
+        assert_eq!(
+            statuses,
+            vec![
+                ("BTCUSDT".to_string(), "TRADING".to_string()),
+                ("LUNAUSDT".to_string(), "BREAK".to_string()),
+                ("FTTUSDT".to_string(), DELISTED.to_string()),
+            ]
+        );
+    }
+}
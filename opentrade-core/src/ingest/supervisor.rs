@@ -0,0 +1,374 @@
+//! # Supervised Task Groups
+//!
+//! This module provides a [`Supervisor`] that runs long-lived pipeline components
+//! (streams, pipelines, pollers) as Tokio tasks, restarts components that fail or
+//! panic with exponential backoff, and shuts the whole group down when a component
+//! keeps failing.
+//!
+//! ## Restart Semantics
+//!
+//! - A component that returns `Ok(())` is considered finished and is not restarted.
+//! - A component that returns an error or panics is restarted after a backoff that
+//!   doubles on every consecutive failure, up to [`RestartPolicy::max_backoff`].
+//! - When more than [`RestartPolicy::max_restarts`] failures happen within
+//!   [`RestartPolicy::window`], the failure is escalated: every other component is
+//!   aborted and [`Supervisor::run`] returns an error.
+//!
+//! ## Lifecycle Events
+//!
+//! Every state change is logged and published as a [`SupervisorEvent`] on a
+//! broadcast channel obtained with [`Supervisor::subscribe`], which can be used to
+//! drive metrics or alerting.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use opentrade_core::data_source::websocket::KlineStreaming;
+//! use opentrade_core::ingest::supervisor::{RestartPolicy, Supervisor};
+//! use binance_spot_connector_rust::market::klines::KlineInterval;
+//! # use anyhow::Result;
+//!
+//! # async fn example() -> Result<()> {
+//! let mut supervisor = Supervisor::new(RestartPolicy::default());
+//! supervisor.add("btcusdt-1m", || async {
+//!     let mut stream = KlineStreaming::new("BTCUSDT", KlineInterval::Minutes1).await?;
+//!     stream.subscribe().await?;
+//!     stream.listen().await
+//! });
+//!
+//! supervisor.run_until(tokio::signal::ctrl_c()).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use futures_util::FutureExt;
+use futures_util::future::BoxFuture;
+use tokio::sync::broadcast;
+use tokio::task::{AbortHandle, JoinSet};
+use tokio::time::Instant;
+
+/// Controls how failed components are restarted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RestartPolicy {
+    /// Backoff before the first restart after a failure.
+    pub initial_backoff: Duration,
+    /// Upper bound for the exponentially growing backoff.
+    pub max_backoff: Duration,
+    /// Number of failures tolerated within `window` before escalating.
+    pub max_restarts: usize,
+    /// Sliding window used to count recent failures.
+    pub window: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            max_restarts: 5,
+            window: Duration::from_secs(600),
+        }
+    }
+}
+
+/// A lifecycle event emitted by the [`Supervisor`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SupervisorEvent {
+    /// A component was started; `attempt` is 1 for the first run.
+    Started { name: String, attempt: u32 },
+    /// A component returned successfully and will not be restarted.
+    Completed { name: String },
+    /// A component returned an error or panicked.
+    Failed { name: String, error: String },
+    /// A failed component will be restarted after `backoff`.
+    Restarting {
+        name: String,
+        attempt: u32,
+        backoff: Duration,
+    },
+    /// A component exceeded its restart budget; the group is shutting down.
+    Escalated { name: String },
+    /// All components were stopped.
+    ShutDown,
+}
+
+type ComponentFactory = Arc<dyn Fn() -> BoxFuture<'static, Result<()>> + Send + Sync>;
+
+struct Component {
+    name: String,
+    factory: ComponentFactory,
+}
+
+/// Runs a group of restartable components. See the [module documentation](self).
+pub struct Supervisor {
+    policy: RestartPolicy,
+    components: Vec<Component>,
+    events: broadcast::Sender<SupervisorEvent>,
+}
+
+impl Supervisor {
+    /// Creates an empty supervisor using `policy` for every component.
+    pub fn new(policy: RestartPolicy) -> Self {
+        let (events, _) = broadcast::channel(256);
+        Self {
+            policy,
+            components: Vec::new(),
+            events,
+        }
+    }
+
+    /// Adds a component.
+    ///
+    /// `factory` is called to create a fresh future every time the component is
+    /// (re)started, so it should construct its connections and state inside the
+    /// returned future.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - A name identifying the component in events and logs
+    /// * `factory` - A function producing the component's future
+    pub fn add<F, Fut>(&mut self, name: &str, factory: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.components.push(Component {
+            name: name.to_string(),
+            factory: Arc::new(move || factory().boxed()),
+        });
+    }
+
+    /// Subscribes to lifecycle events. Only events emitted after subscribing are received.
+    pub fn subscribe(&self) -> broadcast::Receiver<SupervisorEvent> {
+        self.events.subscribe()
+    }
+
+    /// Runs all components until every one of them completes or one escalates.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the component that exceeded its restart budget.
+    pub async fn run(self) -> Result<()> {
+        self.run_until(std::future::pending::<()>()).await
+    }
+
+    /// Runs all components until they complete, one escalates, or `shutdown` resolves.
+    ///
+    /// When `shutdown` resolves, all running components are aborted and `Ok(())`
+    /// is returned.
+    pub async fn run_until<S: Future>(self, shutdown: S) -> Result<()> {
+        let mut tasks = JoinSet::new();
+        for component in self.components {
+            tasks.spawn(supervise(
+                component,
+                self.policy.clone(),
+                self.events.clone(),
+            ));
+        }
+
+        let result = {
+            tokio::pin!(shutdown);
+            loop {
+                tokio::select! {
+                    _ = &mut shutdown => {
+                        log::info!("Supervisor received shutdown signal");
+                        break Ok(());
+                    }
+                    joined = tasks.join_next() => match joined {
+                        None => break Ok(()),
+                        Some(Ok(Ok(()))) => continue,
+                        Some(Ok(Err(e))) => break Err(e),
+                        Some(Err(e)) => break Err(anyhow::Error::new(e)),
+                    },
+                }
+            }
+        };
+
+        tasks.shutdown().await;
+        emit(&self.events, SupervisorEvent::ShutDown);
+        result
+    }
+}
+
+/// Runs a single component, restarting it according to `policy`.
+async fn supervise(
+    component: Component,
+    policy: RestartPolicy,
+    events: broadcast::Sender<SupervisorEvent>,
+) -> Result<()> {
+    let name = component.name;
+    let mut failures: VecDeque<Instant> = VecDeque::new();
+    let mut backoff = policy.initial_backoff;
+    let mut attempt = 0;
+
+    loop {
+        attempt += 1;
+        emit(
+            &events,
+            SupervisorEvent::Started {
+                name: name.clone(),
+                attempt,
+            },
+        );
+
+        // Run the component on its own task so that panics are caught, and make
+        // sure it is aborted if this supervising task is aborted on shutdown.
+        let task = tokio::spawn((component.factory)());
+        let _guard = AbortOnDrop(task.abort_handle());
+        let error = match task.await {
+            Ok(Ok(())) => {
+                emit(&events, SupervisorEvent::Completed { name: name.clone() });
+                return Ok(());
+            }
+            Ok(Err(e)) => format!("{:#}", e),
+            Err(e) => format!("task panicked or was cancelled: {}", e),
+        };
+        emit(
+            &events,
+            SupervisorEvent::Failed {
+                name: name.clone(),
+                error: error.clone(),
+            },
+        );
+
+        let now = Instant::now();
+        while failures
+            .front()
+            .is_some_and(|failed_at| now.duration_since(*failed_at) > policy.window)
+        {
+            failures.pop_front();
+        }
+        if failures.is_empty() {
+            backoff = policy.initial_backoff;
+        }
+        failures.push_back(now);
+
+        if failures.len() > policy.max_restarts {
+            emit(&events, SupervisorEvent::Escalated { name: name.clone() });
+            anyhow::bail!(
+                "Component '{}' failed {} times within {:?}, last error: {}",
+                name,
+                failures.len(),
+                policy.window,
+                error
+            );
+        }
+
+        emit(
+            &events,
+            SupervisorEvent::Restarting {
+                name: name.clone(),
+                attempt: attempt + 1,
+                backoff,
+            },
+        );
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(policy.max_backoff);
+    }
+}
+
+/// Aborts the wrapped task when dropped.
+struct AbortOnDrop(AbortHandle);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Logs an event and publishes it to subscribers.
+fn emit(events: &broadcast::Sender<SupervisorEvent>, event: SupervisorEvent) {
+    match &event {
+        SupervisorEvent::Failed { name, error } => {
+            log::error!("Component '{}' failed: {}", name, error)
+        }
+        SupervisorEvent::Escalated { name } => {
+            log::error!("Component '{}' exceeded its restart budget", name)
+        }
+        SupervisorEvent::Restarting { name, backoff, .. } => {
+            log::warn!("Restarting component '{}' in {:?}", name, backoff)
+        }
+        other => log::info!("Supervisor event: {:?}", other),
+    }
+    // Sending only fails when there are no subscribers, which is fine.
+    let _ = events.send(event);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn fast_policy(max_restarts: usize) -> RestartPolicy {
+        RestartPolicy {
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(4),
+            max_restarts,
+            window: Duration::from_secs(60),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_restarts_failed_component_until_success() {
+        let runs = Arc::new(AtomicU32::new(0));
+        let mut supervisor = Supervisor::new(fast_policy(5));
+        let counter = Arc::clone(&runs);
+        supervisor.add("flaky", move || {
+            let counter = Arc::clone(&counter);
+            async move {
+                if counter.fetch_add(1, Ordering::SeqCst) < 2 {
+                    anyhow::bail!("transient failure");
+                }
+                Ok(())
+            }
+        });
+        let mut events = supervisor.subscribe();
+
+        supervisor.run().await.unwrap();
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+
+        let mut received = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            received.push(event);
+        }
+        assert!(received.contains(&SupervisorEvent::Completed {
+            name: "flaky".to_string()
+        }));
+        assert_eq!(received.last(), Some(&SupervisorEvent::ShutDown));
+    }
+
+    #[tokio::test]
+    async fn test_escalates_after_repeated_failures() {
+        let mut supervisor = Supervisor::new(fast_policy(2));
+        supervisor.add("broken", || async { anyhow::bail!("always fails") });
+        supervisor.add("idle", std::future::pending::<Result<()>>);
+
+        let error = supervisor.run().await.unwrap_err();
+        assert!(error.to_string().contains("'broken' failed 3 times"));
+    }
+
+    #[tokio::test]
+    async fn test_restarts_panicking_component() {
+        let runs = Arc::new(AtomicU32::new(0));
+        let mut supervisor = Supervisor::new(fast_policy(5));
+        let counter = Arc::clone(&runs);
+        supervisor.add("panicky", move || {
+            let counter = Arc::clone(&counter);
+            async move {
+                if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+                    panic!("boom");
+                }
+                Ok(())
+            }
+        });
+
+        supervisor.run().await.unwrap();
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+    }
+}
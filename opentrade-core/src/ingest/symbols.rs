@@ -0,0 +1,239 @@
+//! # Symbol Validation
+//!
+//! This module checks a requested symbol and interval against the exchange
+//! listing before a backfill or stream is started, so that typos fail fast with
+//! suggestions instead of looping over empty API responses.
+//!
+//! The listing is fetched from the exchangeInfo endpoint once and cached for
+//! [`LISTING_TTL`], so validating many jobs in one process costs a single request.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use opentrade_core::ingest::symbols::validate_symbol;
+//!
+//! # async fn example() -> anyhow::Result<()> {
+//! // Fails with "unknown symbol BTCUSTD (did you mean BTCUSDT?)"
+//! validate_symbol("BTCUSTD", "1m").await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use binance_spot_connector_rust::market::klines::KlineInterval;
+
+use crate::data_source::rest::{extract_symbol_statuses, get_exchange_info};
+use crate::models::Interval;
+
+/// How long a fetched exchange listing is reused.
+pub const LISTING_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// The kline intervals supported by the exchange.
+pub const SUPPORTED_INTERVALS: &[&str] = &[
+    "1m", "3m", "5m", "15m", "30m", "1h", "2h", "4h", "6h", "8h", "12h", "1d", "3d", "1w", "1M",
+];
+
+/// The maximum number of suggestions reported for an unknown symbol.
+const MAX_SUGGESTIONS: usize = 3;
+
+/// The listed symbols and the time they were fetched.
+static LISTING: Mutex<Option<(Instant, Vec<String>)>> = Mutex::new(None);
+
+/// Errors reported by [`check_symbol`] and [`validate_symbol`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SymbolValidationError {
+    /// The symbol is not listed on the exchange.
+    UnknownSymbol {
+        symbol: String,
+        suggestions: Vec<String>,
+    },
+    /// The interval is not supported by the exchange.
+    UnsupportedInterval { interval: String },
+}
+
+impl fmt::Display for SymbolValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SymbolValidationError::UnknownSymbol {
+                symbol,
+                suggestions,
+            } if suggestions.is_empty() => write!(f, "unknown symbol {}", symbol),
+            SymbolValidationError::UnknownSymbol {
+                symbol,
+                suggestions,
+            } => write!(
+                f,
+                "unknown symbol {} (did you mean {}?)",
+                symbol,
+                suggestions.join(", ")
+            ),
+            SymbolValidationError::UnsupportedInterval { interval } => write!(
+                f,
+                "unsupported interval {} (supported: {})",
+                interval,
+                SUPPORTED_INTERVALS.join(", ")
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SymbolValidationError {}
+
+/// Parses a kline interval (e.g., "1m", "4h", "1M") into a [`KlineInterval`].
+///
+/// # Returns
+///
+/// The interval, or `None` if it is not one of [`SUPPORTED_INTERVALS`].
+pub fn parse_interval(interval: &str) -> Option<KlineInterval> {
+    interval.parse::<Interval>().ok()?.to_binance()
+}
+
+/// Checks a symbol and interval against the listed symbols.
+///
+/// # Arguments
+///
+/// * `listed` - The symbols listed on the exchange.
+/// * `symbol` - The requested trading symbol.
+/// * `interval` - The requested kline interval (e.g., "1m").
+pub fn check_symbol(
+    listed: &[String],
+    symbol: &str,
+    interval: &str,
+) -> Result<(), SymbolValidationError> {
+    if parse_interval(interval).is_none() {
+        return Err(SymbolValidationError::UnsupportedInterval {
+            interval: interval.to_string(),
+        });
+    }
+    if listed.iter().any(|listed| listed == symbol) {
+        return Ok(());
+    }
+    Err(SymbolValidationError::UnknownSymbol {
+        symbol: symbol.to_string(),
+        suggestions: suggest_symbols(listed, symbol),
+    })
+}
+
+/// Returns the listed symbols closest to `symbol` by edit distance, closest first.
+///
+/// Only symbols within a third of the symbol's length of edits are suggested.
+pub fn suggest_symbols(listed: &[String], symbol: &str) -> Vec<String> {
+    let symbol = symbol.to_uppercase();
+    let max_distance = (symbol.len() / 3).max(1);
+    let mut candidates: Vec<(usize, &String)> = listed
+        .iter()
+        .map(|listed| (edit_distance(&symbol, listed), listed))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .collect();
+    candidates.sort();
+    candidates
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, listed)| listed.clone())
+        .collect()
+}
+
+/// Computes the edit distance between two strings, counting insertions, deletions,
+/// substitutions and transpositions of adjacent characters as one edit each.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+/// Returns the symbols listed on the exchange, fetching them if the cached
+/// listing is missing or older than [`LISTING_TTL`].
+pub async fn listed_symbols() -> Result<Vec<String>> {
+    if let Some((fetched_at, symbols)) = LISTING.lock().unwrap().as_ref()
+        && fetched_at.elapsed() < LISTING_TTL
+    {
+        return Ok(symbols.clone());
+    }
+    let exchange_info = get_exchange_info()
+        .await
+        .context("Failed to fetch exchange info")?;
+    let symbols: Vec<String> = extract_symbol_statuses(&exchange_info)
+        .context("Failed to parse exchange info")?
+        .into_iter()
+        .map(|(symbol, _)| symbol)
+        .collect();
+    *LISTING.lock().unwrap() = Some((Instant::now(), symbols.clone()));
+    Ok(symbols)
+}
+
+/// Validates a symbol and interval against the cached exchange listing.
+///
+/// # Errors
+///
+/// Returns a [`SymbolValidationError`] if the symbol is not listed or the interval
+/// is not supported, or another error if the listing cannot be fetched.
+pub async fn validate_symbol(symbol: &str, interval: &str) -> Result<()> {
+    let listed = listed_symbols().await?;
+    check_symbol(&listed, symbol, interval)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listed() -> Vec<String> {
+        ["BTCUSDT", "ETHUSDT", "BTCUSDC", "SOLUSDT"]
+            .iter()
+            .map(|symbol| symbol.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_check_symbol_accepts_listed_symbol() {
+        assert_eq!(check_symbol(&listed(), "ETHUSDT", "1h"), Ok(()));
+    }
+
+    #[test]
+    fn test_check_symbol_suggests_closest_symbols() {
+        let error = check_symbol(&listed(), "BTCUSTD", "1m").unwrap_err();
+        let SymbolValidationError::UnknownSymbol { suggestions, .. } = &error else {
+            panic!("unexpected error: {}", error);
+        };
+        assert_eq!(suggestions.first().map(String::as_str), Some("BTCUSDT"));
+        assert!(!suggestions.contains(&"SOLUSDT".to_string()));
+    }
+
+    #[test]
+    fn test_parse_interval_roundtrips_supported_intervals() {
+        for interval in SUPPORTED_INTERVALS {
+            assert_eq!(parse_interval(interval).unwrap().to_string(), *interval);
+        }
+        assert!(parse_interval("1s").is_none());
+    }
+
+    #[test]
+    fn test_check_symbol_rejects_unsupported_interval() {
+        assert!(matches!(
+            check_symbol(&listed(), "BTCUSDT", "7m"),
+            Err(SymbolValidationError::UnsupportedInterval { .. })
+        ));
+    }
+}
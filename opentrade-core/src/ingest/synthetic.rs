@@ -0,0 +1,290 @@
+//! # Synthetic Kline Traffic
+//!
+//! This module provides [`SyntheticKlines`], a deterministic generator of Kline
+//! WebSocket messages for benchmarks and load tests. It produces the same
+//! [`Payload`] envelopes Binance sends, so generated traffic exercises the real
+//! parsing and conversion paths without a network connection.
+//!
+//! Messages are emitted round-robin across the configured symbols. Each candle is
+//! updated a configurable number of times, and only its last update is marked as
+//! final, mimicking a live stream. Prices follow a seeded random walk, so two
+//! generators with the same configuration produce identical traffic.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use opentrade_core::ingest::synthetic::SyntheticKlines;
+//!
+//! let mut generator = SyntheticKlines::new(&["BTCUSDT", "ETHUSDT"]).with_updates_per_candle(3);
+//!
+//! let json = generator.next_json();
+//! let kline = generator.next_kline();
+//! assert_eq!(kline.symbol, "ETHUSDT");
+//! # let _ = json;
+//! ```
+
+use crate::data_source::websocket::{KlineDetails, KlinePayloadData, Payload};
+use crate::models::{KlineData, SerdableKlineData};
+
+/// The interval of generated candles.
+const INTERVAL: &str = "1m";
+/// The length of a generated candle in milliseconds.
+const INTERVAL_MILLIS: u64 = 60_000;
+/// Open time of the first generated candle (2020-09-13T12:26:40Z).
+const DEFAULT_START_TIME: u64 = 1_600_000_000_000;
+
+/// The per-symbol state of a [`SyntheticKlines`] generator.
+struct SymbolState {
+    symbol: String,
+    start_time: u64,
+    update: u32,
+    /// The open price of the current candle, in hundredths.
+    open: i64,
+    /// The latest price of the current candle, in hundredths.
+    close: i64,
+    high: i64,
+    low: i64,
+    trade_count: u64,
+    next_trade_id: u64,
+}
+
+/// A deterministic generator of synthetic Kline stream messages.
+///
+/// See the [module documentation](self) for an example.
+pub struct SyntheticKlines {
+    symbols: Vec<SymbolState>,
+    updates_per_candle: u32,
+    next_symbol: usize,
+    seed: u64,
+}
+
+impl SyntheticKlines {
+    /// Creates a generator emitting 1-minute candles for `symbols`.
+    ///
+    /// Every message is a final candle until
+    /// [`with_updates_per_candle`](Self::with_updates_per_candle) is used.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `symbols` is empty.
+    pub fn new(symbols: &[&str]) -> Self {
+        assert!(!symbols.is_empty(), "SyntheticKlines needs at least one symbol");
+        let symbols = symbols
+            .iter()
+            .enumerate()
+            .map(|(i, symbol)| {
+                let price = 10_000 * (i as i64 + 1);
+                SymbolState {
+                    symbol: symbol.to_string(),
+                    start_time: DEFAULT_START_TIME,
+                    update: 0,
+                    open: price,
+                    close: price,
+                    high: price,
+                    low: price,
+                    trade_count: 0,
+                    next_trade_id: 1,
+                }
+            })
+            .collect();
+        Self {
+            symbols,
+            updates_per_candle: 1,
+            next_symbol: 0,
+            seed: 0x2545_f491_4f6c_dd1d,
+        }
+    }
+
+    /// Creates a generator for `count` symbols named `SYN0USDT`, `SYN1USDT`, ...
+    pub fn with_symbol_count(count: usize) -> Self {
+        let symbols: Vec<String> = (0..count).map(|i| format!("SYN{}USDT", i)).collect();
+        let symbols: Vec<&str> = symbols.iter().map(String::as_str).collect();
+        Self::new(&symbols)
+    }
+
+    /// Sets the open time of the first candle, in milliseconds since the Unix epoch.
+    pub fn with_start_time(mut self, start_time: u64) -> Self {
+        for state in &mut self.symbols {
+            state.start_time = start_time;
+        }
+        self
+    }
+
+    /// Sets how many messages are emitted per candle; only the last one is final.
+    pub fn with_updates_per_candle(mut self, updates: u32) -> Self {
+        self.updates_per_candle = updates.max(1);
+        self
+    }
+
+    /// Sets the seed of the price random walk.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        // xorshift has a fixed point at zero.
+        self.seed = seed.max(1);
+        self
+    }
+
+    /// Returns the number of symbols messages are generated for.
+    pub fn symbol_count(&self) -> usize {
+        self.symbols.len()
+    }
+
+    /// Returns the next message as a WebSocket [`Payload`].
+    pub fn next_payload(&mut self) -> Payload {
+        let step = self.random_step();
+        let updates_per_candle = self.updates_per_candle;
+        let next = (self.next_symbol + 1) % self.symbols.len();
+        let state = &mut self.symbols[self.next_symbol];
+        self.next_symbol = next;
+
+        state.close = (state.close + step).max(1);
+        state.high = state.high.max(state.close);
+        state.low = state.low.min(state.close);
+        state.trade_count += 1 + step.unsigned_abs() % 5;
+        state.update += 1;
+        let is_final = state.update >= updates_per_candle;
+        let end_time = state.start_time + INTERVAL_MILLIS - 1;
+        let first_trade_id = state.next_trade_id;
+
+        let volume = state.trade_count * 3;
+        let payload = Payload {
+            stream: format!("{}@kline_{}", state.symbol.to_lowercase(), INTERVAL),
+            data: KlinePayloadData {
+                event_type: "kline".to_string(),
+                event_time: if is_final {
+                    end_time + 1
+                } else {
+                    state.start_time + INTERVAL_MILLIS * state.update as u64
+                        / updates_per_candle as u64
+                },
+                symbol: state.symbol.clone(),
+                kline: KlineDetails {
+                    start_time: state.start_time,
+                    end_time,
+                    symbol: state.symbol.clone(),
+                    interval: INTERVAL.to_string(),
+                    first_trade_id,
+                    last_trade_id: first_trade_id + state.trade_count - 1,
+                    open: price(state.open),
+                    close: price(state.close),
+                    high: price(state.high),
+                    low: price(state.low),
+                    volume: format!("{}.000", volume),
+                    trade_count: state.trade_count,
+                    is_final,
+                    quote_volume: price(volume as i64 * state.close),
+                    taker_buy_base_volume: format!("{}.000", volume / 2),
+                    taker_buy_quote_volume: price(volume as i64 / 2 * state.close),
+                    ignore: "0".to_string(),
+                },
+            },
+        };
+
+        if is_final {
+            state.next_trade_id += state.trade_count;
+            state.start_time += INTERVAL_MILLIS;
+            state.update = 0;
+            state.open = state.close;
+            state.high = state.close;
+            state.low = state.close;
+            state.trade_count = 0;
+        }
+        payload
+    }
+
+    /// Returns the next message serialized as the JSON text of a WebSocket frame.
+    pub fn next_json(&mut self) -> String {
+        serde_json::to_string(&self.next_payload()).expect("Payload serialization cannot fail")
+    }
+
+    /// Returns the next message as [`SerdableKlineData`].
+    pub fn next_kline(&mut self) -> SerdableKlineData {
+        self.next_payload()
+            .to_serializable_kline_data()
+            .expect("Synthetic payloads are always valid")
+    }
+
+    /// Returns the next `count` final candles as [`KlineData`] rows, e.g. to
+    /// benchmark database writes. Intermediate updates are skipped.
+    pub fn final_klines(&mut self, count: usize) -> Vec<KlineData> {
+        let mut klines = Vec::with_capacity(count);
+        while klines.len() < count {
+            let payload = self.next_payload();
+            if payload.data.kline.is_final {
+                klines.push(
+                    payload
+                        .to_kline_data()
+                        .expect("Synthetic payloads are always valid"),
+                );
+            }
+        }
+        klines
+    }
+
+    /// Returns a price step in hundredths between -50 and 50 (xorshift64).
+    fn random_step(&mut self) -> i64 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 7;
+        self.seed ^= self.seed << 17;
+        (self.seed % 101) as i64 - 50
+    }
+}
+
+impl Iterator for SyntheticKlines {
+    type Item = Payload;
+
+    /// Returns the next payload; the generator never ends.
+    fn next(&mut self) -> Option<Payload> {
+        Some(self.next_payload())
+    }
+}
+
+/// Formats a price in hundredths as a decimal string.
+fn price(hundredths: i64) -> String {
+    format!("{}.{:02}", hundredths / 100, hundredths % 100)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_messages_round_robin_and_finalize_candles() {
+        let mut generator =
+            SyntheticKlines::new(&["BTCUSDT", "ETHUSDT"]).with_updates_per_candle(2);
+        let klines: Vec<SerdableKlineData> = (0..6).map(|_| generator.next_kline()).collect();
+
+        let symbols: Vec<&str> = klines.iter().map(|k| k.symbol.as_str()).collect();
+        assert_eq!(symbols, ["BTCUSDT", "ETHUSDT", "BTCUSDT", "ETHUSDT", "BTCUSDT", "ETHUSDT"]);
+        let finals: Vec<bool> = klines.iter().map(|k| k.is_final).collect();
+        assert_eq!(finals, [false, false, true, true, false, false]);
+        assert_eq!(klines[4].start_time, klines[0].start_time + INTERVAL_MILLIS);
+    }
+
+    #[test]
+    fn test_generated_messages_are_valid_and_deterministic() {
+        let mut first = SyntheticKlines::with_symbol_count(3).with_seed(7);
+        let mut second = SyntheticKlines::with_symbol_count(3).with_seed(7);
+        for _ in 0..500 {
+            let json = first.next_json();
+            assert_eq!(json, second.next_json());
+            let payload: Payload = serde_json::from_str(&json).unwrap();
+            payload
+                .to_serializable_kline_data()
+                .unwrap()
+                .to_validated_kline_data()
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_final_klines_are_consecutive() {
+        let klines = SyntheticKlines::new(&["BTCUSDT"])
+            .with_updates_per_candle(4)
+            .final_klines(3);
+        assert_eq!(klines.len(), 3);
+        assert_eq!(
+            klines[2].start_time - klines[0].start_time,
+            chrono::Duration::minutes(2)
+        );
+    }
+}
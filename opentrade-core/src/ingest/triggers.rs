@@ -0,0 +1,856 @@
+//! # Candle Triggers
+//!
+//! This module evaluates user-declared conditions over final candles and emits a
+//! [`TriggerEvent`] to the registered handlers whenever a condition fires. A
+//! [`TriggerSink`] is a pipeline sink, so the same triggers run over live streams
+//! and over stored candles replayed with a
+//! [`ReplaySource`](crate::ingest::pipeline::ReplaySource).
+//!
+//! ## Configuration
+//!
+//! Triggers are declared in a JSON file read by [`TriggerConfig::from_file`]:
+//!
+//! ```json
+//! {
+//!     "triggers": [
+//!         {
+//!             "name": "btc-breakout",
+//!             "symbol": "BTCUSDT",
+//!             "interval": "1h",
+//!             "condition": { "crosses_above": ["close", { "sma": { "field": "close", "periods": 50 } }] }
+//!         },
+//!         {
+//!             "name": "btc-volume-spike",
+//!             "symbol": "BTCUSDT",
+//!             "interval": "5m",
+//!             "condition": {
+//!                 "above": ["volume", { "times": 3, "of": { "sma": { "field": "volume", "periods": 288 } } }]
+//!             }
+//!         },
+//!         {
+//!             "name": "eth-drop",
+//!             "symbol": "ETHUSDT",
+//!             "interval": "1m",
+//!             "condition": { "below": [{ "roc": { "field": "close", "periods": 60 } }, -0.05] }
+//!         }
+//!     ]
+//! }
+//! ```
+//!
+//! A [`Condition`] compares two [`Operand`]s, each of which is one of:
+//! - A number, e.g. `-0.05`
+//! - A candle [`Field`]: `"open"`, `"high"`, `"low"`, `"close"` or `"volume"`
+//! - `{"sma": {"field": ..., "periods": n}}` - The simple moving average of the
+//!   last `n` candles, including the current one
+//! - `{"roc": {"field": ..., "periods": n}}` - The rate of change since the candle
+//!   `n` candles earlier, as a fraction (`0.05` is +5%)
+//! - `{"times": k, "of": operand}` - Another operand multiplied by `k`
+//!
+//! Periods are counted in candles of the trigger's interval, so "close crosses
+//! above the 1h SMA50" is a `1h` trigger and "5m volume above 3x the 24h average"
+//! is a `5m` trigger over 288 periods. `above` and `below` fire on every candle on
+//! which they hold; `crosses_above` and `crosses_below` fire only on the candle on
+//! which the comparison flips.
+//!
+//! Only final candles are evaluated. A trigger needs enough contiguous history for
+//! its operands before it can fire; a gap in the candles restarts the warm-up,
+//! and [`TriggerSink::warm_up`] preloads the history from the database.
+//!
+//! ## Backtesting
+//!
+//! [`backtest`] runs the triggers over stored candles and returns every event they
+//! would have emitted, so that a definition can be checked for how often and when
+//! it fires before it goes live.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use opentrade_core::data_source::exchange::{Binance, MarketDataSource};
+//! use opentrade_core::ingest::pipeline::{Pipeline, StreamSource};
+//! use opentrade_core::ingest::triggers::{LogTriggerHandler, TriggerConfig, TriggerSink};
+//! use opentrade_core::models::SerdableKlineData;
+//! # use anyhow::Result;
+//!
+//! # async fn example() -> Result<()> {
+//! let config = TriggerConfig::from_file("triggers.json")?;
+//! config.validate()?;
+//!
+//! let stream = Binance.stream_klines("BTCUSDT", "1h").await?;
+//! Pipeline::<SerdableKlineData>::builder("btcusdt-1h-triggers")
+//!     .source(StreamSource::new(stream))
+//!     .sink(TriggerSink::new(config.triggers).with_handler(LogTriggerHandler))
+//!     .build()?
+//!     .run()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::fs::{File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+
+use crate::analytics::to_f64;
+use crate::config::{ConfigError, StreamSpec, is_valid_symbol};
+use crate::data_source::websocket::MessageHandler;
+use crate::ingest::audit::interval_duration;
+use crate::ingest::pipeline::{Pipeline, ReplaySource};
+use crate::ingest::symbols::{SUPPORTED_INTERVALS, parse_interval};
+use crate::models::{KlineData, SerdableKlineData};
+
+/// A value of a candle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Field {
+    Open,
+    High,
+    Low,
+    Close,
+    Volume,
+}
+
+impl Field {
+    /// Returns the value of the field in a candle.
+    fn of(&self, bar: &Bar) -> f64 {
+        match self {
+            Field::Open => bar.open,
+            Field::High => bar.high,
+            Field::Low => bar.low,
+            Field::Close => bar.close,
+            Field::Volume => bar.volume,
+        }
+    }
+}
+
+/// An indicator computed over the history of a trigger's candles.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Indicator {
+    /// The simple moving average of the last `periods` candles.
+    Sma { field: Field, periods: usize },
+    /// The rate of change since the candle `periods` candles earlier, as a fraction.
+    Roc { field: Field, periods: usize },
+}
+
+/// One side of a [`Condition`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Operand {
+    /// A constant threshold.
+    Constant(f64),
+    /// A value of the current candle.
+    Field(Field),
+    /// An indicator ending at the current candle.
+    Indicator(Indicator),
+    /// Another operand multiplied by a factor.
+    Scaled { times: f64, of: Box<Operand> },
+}
+
+impl Operand {
+    /// Returns how many candles before the current one the operand reads.
+    pub fn lookback(&self) -> usize {
+        match self {
+            Operand::Constant(_) | Operand::Field(_) => 0,
+            Operand::Indicator(Indicator::Sma { periods, .. }) => periods.saturating_sub(1),
+            Operand::Indicator(Indicator::Roc { periods, .. }) => *periods,
+            Operand::Scaled { of, .. } => of.lookback(),
+        }
+    }
+
+    /// Returns the periods of the indicators of the operand.
+    fn periods(&self) -> Vec<usize> {
+        match self {
+            Operand::Constant(_) | Operand::Field(_) => Vec::new(),
+            Operand::Indicator(Indicator::Sma { periods, .. })
+            | Operand::Indicator(Indicator::Roc { periods, .. }) => vec![*periods],
+            Operand::Scaled { of, .. } => of.periods(),
+        }
+    }
+
+    /// Computes the operand at the candle `offset` candles before the newest one
+    /// of `bars`, or `None` without enough history.
+    fn value(&self, bars: &VecDeque<Bar>, offset: usize) -> Option<f64> {
+        // The exclusive end of the candles visible at the offset.
+        let end = bars.len().checked_sub(offset)?;
+        let current = bars.get(end.checked_sub(1)?)?;
+        match self {
+            Operand::Constant(value) => Some(*value),
+            Operand::Field(field) => Some(field.of(current)),
+            Operand::Indicator(Indicator::Sma { field, periods }) => {
+                if *periods == 0 || end < *periods {
+                    return None;
+                }
+                let sum: f64 = bars
+                    .range(end - periods..end)
+                    .map(|bar| field.of(bar))
+                    .sum();
+                Some(sum / *periods as f64)
+            }
+            Operand::Indicator(Indicator::Roc { field, periods }) => {
+                let base = field.of(bars.get(end.checked_sub(1 + periods)?)?);
+                (base != 0.0).then(|| field.of(current) / base - 1.0)
+            }
+            Operand::Scaled { times, of } => of.value(bars, offset).map(|value| value * times),
+        }
+    }
+}
+
+/// A comparison of two operands that fires a trigger.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Condition {
+    /// Fires on every candle on which the left operand is above the right one.
+    Above(Operand, Operand),
+    /// Fires on every candle on which the left operand is below the right one.
+    Below(Operand, Operand),
+    /// Fires when the left operand moves from at or below the right one to above it.
+    CrossesAbove(Operand, Operand),
+    /// Fires when the left operand moves from at or above the right one to below it.
+    CrossesBelow(Operand, Operand),
+}
+
+impl Condition {
+    /// Returns the left and right operands.
+    pub fn operands(&self) -> (&Operand, &Operand) {
+        match self {
+            Condition::Above(left, right)
+            | Condition::Below(left, right)
+            | Condition::CrossesAbove(left, right)
+            | Condition::CrossesBelow(left, right) => (left, right),
+        }
+    }
+
+    /// Returns how many candles before the current one the condition reads.
+    pub fn lookback(&self) -> usize {
+        let (left, right) = self.operands();
+        let crosses = matches!(
+            self,
+            Condition::CrossesAbove(..) | Condition::CrossesBelow(..)
+        );
+        left.lookback().max(right.lookback()) + usize::from(crosses)
+    }
+
+    /// Evaluates the condition at the newest candle of `bars`, returning the
+    /// values of the left and right operands if it fires.
+    fn evaluate(&self, bars: &VecDeque<Bar>) -> Option<(f64, f64)> {
+        let (left, right) = self.operands();
+        let current = (left.value(bars, 0)?, right.value(bars, 0)?);
+        let fires = match self {
+            Condition::Above(..) => current.0 > current.1,
+            Condition::Below(..) => current.0 < current.1,
+            Condition::CrossesAbove(..) | Condition::CrossesBelow(..) => {
+                let previous = (left.value(bars, 1)?, right.value(bars, 1)?);
+                if matches!(self, Condition::CrossesAbove(..)) {
+                    previous.0 <= previous.1 && current.0 > current.1
+                } else {
+                    previous.0 >= previous.1 && current.0 < current.1
+                }
+            }
+        };
+        fires.then_some(current)
+    }
+}
+
+/// A named condition over the candles of one symbol and interval.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TriggerSpec {
+    /// The name reported in the trigger's events.
+    pub name: String,
+    /// The trading symbol (e.g., "BTCUSDT").
+    pub symbol: String,
+    /// The kline interval the condition is evaluated on (e.g., "1h").
+    pub interval: String,
+    /// The condition that fires the trigger.
+    pub condition: Condition,
+}
+
+impl TriggerSpec {
+    /// Returns the kline stream the trigger is evaluated on.
+    pub fn stream(&self) -> StreamSpec {
+        StreamSpec::new(&self.symbol, &self.interval)
+    }
+}
+
+/// A set of triggers, as read from a config file.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TriggerConfig {
+    /// The declared triggers.
+    pub triggers: Vec<TriggerSpec>,
+}
+
+impl TriggerConfig {
+    /// Reads the triggers from a JSON file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the config file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read trigger config {}", path.display()))?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse trigger config {}", path.display()))
+    }
+
+    /// Returns the distinct kline streams the triggers are evaluated on, in the
+    /// order they are first declared.
+    pub fn streams(&self) -> Vec<StreamSpec> {
+        let mut streams: Vec<StreamSpec> = Vec::new();
+        for trigger in &self.triggers {
+            let stream = trigger.stream();
+            if !streams.contains(&stream) {
+                streams.push(stream);
+            }
+        }
+        streams
+    }
+
+    /// Returns the triggers evaluated on a stream.
+    pub fn triggers_for(&self, stream: &StreamSpec) -> Vec<TriggerSpec> {
+        self.triggers
+            .iter()
+            .filter(|trigger| &trigger.stream() == stream)
+            .cloned()
+            .collect()
+    }
+
+    /// Checks every trigger of the configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ConfigError`] listing an empty trigger list, duplicate names,
+    /// malformed symbols, unsupported intervals and indicators over zero periods.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = Vec::new();
+        if self.triggers.is_empty() {
+            problems.push("no triggers are configured".to_string());
+        }
+        for (i, trigger) in self.triggers.iter().enumerate() {
+            if self.triggers[..i]
+                .iter()
+                .any(|other| other.name == trigger.name)
+            {
+                problems.push(format!("trigger {}: duplicate name {:?}", i, trigger.name));
+            }
+            if !is_valid_symbol(&trigger.symbol) {
+                problems.push(format!(
+                    "trigger {}: malformed symbol {:?}: expected uppercase letters and digits",
+                    i, trigger.symbol
+                ));
+            }
+            if parse_interval(&trigger.interval).is_none() {
+                problems.push(format!(
+                    "trigger {}: unknown interval {:?} (supported: {})",
+                    i,
+                    trigger.interval,
+                    SUPPORTED_INTERVALS.join(", ")
+                ));
+            }
+            let (left, right) = trigger.condition.operands();
+            if left
+                .periods()
+                .into_iter()
+                .chain(right.periods())
+                .any(|p| p == 0)
+            {
+                problems.push(format!(
+                    "trigger {}: indicators need at least one period",
+                    i
+                ));
+            }
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError { problems })
+        }
+    }
+}
+
+/// A trigger that fired on a candle.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TriggerEvent {
+    /// The name of the trigger.
+    pub trigger: String,
+    /// The trading symbol.
+    pub symbol: String,
+    /// The kline interval.
+    pub interval: String,
+    /// The start time of the candle the trigger fired on.
+    pub start_time: DateTime<Utc>,
+    /// The close price of the candle.
+    pub close: f64,
+    /// The value of the left operand of the condition.
+    pub left: f64,
+    /// The value of the right operand of the condition.
+    pub right: f64,
+}
+
+/// The values of a final candle used by trigger conditions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Bar {
+    start_time: DateTime<Utc>,
+    open: f64,
+    high: f64,
+    low: f64,
+    close: f64,
+    volume: f64,
+}
+
+impl From<&KlineData> for Bar {
+    fn from(kline: &KlineData) -> Self {
+        Self {
+            start_time: kline.start_time,
+            open: to_f64(&kline.open),
+            high: to_f64(&kline.high),
+            low: to_f64(&kline.low),
+            close: to_f64(&kline.close),
+            volume: to_f64(&kline.volume),
+        }
+    }
+}
+
+/// A trigger together with the recent candles it is evaluated on.
+struct TriggerState {
+    spec: TriggerSpec,
+    step: Option<Duration>,
+    bars: VecDeque<Bar>,
+}
+
+impl TriggerState {
+    fn new(spec: TriggerSpec) -> Self {
+        Self {
+            step: interval_duration(&spec.interval),
+            spec,
+            bars: VecDeque::new(),
+        }
+    }
+
+    /// Appends a candle to the history, returning false for candles at or before
+    /// the newest one, which are ignored.
+    fn push(&mut self, bar: Bar) -> bool {
+        if let Some(last) = self.bars.back() {
+            if bar.start_time <= last.start_time {
+                return false;
+            }
+            if self
+                .step
+                .is_some_and(|step| bar.start_time != last.start_time + step)
+            {
+                self.bars.clear();
+            }
+        }
+        self.bars.push_back(bar);
+        while self.bars.len() > self.spec.condition.lookback() + 1 {
+            self.bars.pop_front();
+        }
+        true
+    }
+
+    /// Appends a candle and evaluates the trigger on it.
+    fn on_candle(&mut self, bar: Bar) -> Option<TriggerEvent> {
+        if !self.push(bar) {
+            return None;
+        }
+        let (left, right) = self.spec.condition.evaluate(&self.bars)?;
+        Some(TriggerEvent {
+            trigger: self.spec.name.clone(),
+            symbol: self.spec.symbol.clone(),
+            interval: self.spec.interval.clone(),
+            start_time: bar.start_time,
+            close: bar.close,
+            left,
+            right,
+        })
+    }
+}
+
+/// A sink that evaluates triggers on the final candles it receives and passes
+/// the resulting [`TriggerEvent`]s to its handlers, in registration order.
+///
+/// Candles of symbols and intervals without triggers are ignored, so one sink can
+/// serve triggers over several streams.
+pub struct TriggerSink {
+    triggers: Vec<TriggerState>,
+    handlers: Vec<Box<dyn MessageHandler<TriggerEvent> + Send>>,
+}
+
+impl TriggerSink {
+    /// Creates a sink evaluating the given triggers.
+    pub fn new(triggers: Vec<TriggerSpec>) -> Self {
+        Self {
+            triggers: triggers.into_iter().map(TriggerState::new).collect(),
+            handlers: Vec::new(),
+        }
+    }
+
+    /// Adds a handler for the events of the triggers.
+    pub fn with_handler<H: MessageHandler<TriggerEvent> + Send + 'static>(
+        mut self,
+        handler: H,
+    ) -> Self {
+        self.handlers.push(Box::new(handler));
+        self
+    }
+
+    /// Loads the stored candles each trigger needs before it can fire, so that
+    /// triggers with long lookbacks fire from the first live candle.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `dataset` - The dataset label.
+    /// * `exchange` - The exchange the candles were captured from (e.g., "binance").
+    pub async fn warm_up(
+        &mut self,
+        pool: &sqlx::PgPool,
+        dataset: &str,
+        exchange: &str,
+    ) -> Result<()> {
+        let now = Utc::now();
+        for trigger in &mut self.triggers {
+            let Some(step) = trigger.step else {
+                continue;
+            };
+            let periods = trigger.spec.condition.lookback() as i32 + 1;
+            let klines = KlineData::list_range(
+                pool,
+                &trigger.spec.symbol,
+                &trigger.spec.interval,
+                now - step * periods,
+                now,
+                dataset,
+                exchange,
+            )
+            .await?;
+            for kline in klines.iter().filter(|kline| kline.end_time < now) {
+                trigger.push(Bar::from(kline));
+            }
+        }
+        Ok(())
+    }
+
+    /// Evaluates every trigger of the candle's symbol and interval.
+    fn evaluate(&mut self, kline: &KlineData) -> Vec<TriggerEvent> {
+        let bar = Bar::from(kline);
+        self.triggers
+            .iter_mut()
+            .filter(|trigger| {
+                trigger.spec.symbol == kline.symbol && trigger.spec.interval == kline.interval
+            })
+            .filter_map(|trigger| trigger.on_candle(bar))
+            .collect()
+    }
+}
+
+#[async_trait]
+impl MessageHandler<SerdableKlineData> for TriggerSink {
+    async fn handle_message(&mut self, message: &SerdableKlineData) -> Result<()> {
+        if !message.is_final {
+            return Ok(());
+        }
+        let kline = match message.to_validated_kline_data() {
+            Ok(kline) => kline,
+            Err(reason) => {
+                log::warn!("Not evaluating triggers on invalid Kline data: {}", reason);
+                return Ok(());
+            }
+        };
+        for event in self.evaluate(&kline) {
+            for handler in &mut self.handlers {
+                handler.handle_message(&event).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A trigger event handler that logs every event as JSON.
+pub struct LogTriggerHandler;
+
+#[async_trait]
+impl MessageHandler<TriggerEvent> for LogTriggerHandler {
+    async fn handle_message(&mut self, message: &TriggerEvent) -> Result<()> {
+        log::info!("Trigger fired: {}", serde_json::to_string(message)?);
+        Ok(())
+    }
+}
+
+/// A trigger event handler that appends every event to a file as a line of JSON.
+///
+/// The file is created if it does not exist and opened on the first event.
+pub struct FileTriggerHandler {
+    path: PathBuf,
+    file: Option<File>,
+}
+
+impl FileTriggerHandler {
+    /// Creates a handler appending to `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            file: None,
+        }
+    }
+}
+
+#[async_trait]
+impl MessageHandler<TriggerEvent> for FileTriggerHandler {
+    async fn handle_message(&mut self, message: &TriggerEvent) -> Result<()> {
+        if self.file.is_none() {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+                .await
+                .with_context(|| format!("Failed to open trigger log {}", self.path.display()))?;
+            self.file = Some(file);
+        }
+        let mut line = serde_json::to_vec(message)?;
+        line.push(b'\n');
+        let file = self.file.as_mut().expect("file was opened above");
+        file.write_all(&line).await?;
+        file.flush().await?;
+        Ok(())
+    }
+}
+
+/// A trigger event handler that keeps the events in memory.
+struct EventCollector(Arc<Mutex<Vec<TriggerEvent>>>);
+
+#[async_trait]
+impl MessageHandler<TriggerEvent> for EventCollector {
+    async fn handle_message(&mut self, message: &TriggerEvent) -> Result<()> {
+        self.0.lock().expect("collector lock").push(message.clone());
+        Ok(())
+    }
+}
+
+/// Runs triggers over stored candles and returns every event they would have
+/// emitted, e.g. to check how often an alert definition fires before going live.
+///
+/// Each stream of the config is replayed with a [`ReplaySource`]. Replays start
+/// early enough for the triggers to warm up by `start`, and only events on
+/// candles starting within `[start, end)` are returned, ordered by candle start
+/// time and then by the order the triggers are declared in.
+///
+/// # Arguments
+///
+/// * `pool` - The database connection pool.
+/// * `config` - The triggers to run.
+/// * `start` - The inclusive start of the range.
+/// * `end` - The exclusive end of the range.
+/// * `dataset` - The dataset label of the replayed candles.
+/// * `exchange` - The exchange of the replayed candles (e.g., "binance").
+pub async fn backtest(
+    pool: &sqlx::PgPool,
+    config: &TriggerConfig,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    dataset: &str,
+    exchange: &str,
+) -> Result<Vec<TriggerEvent>> {
+    let mut events = Vec::new();
+    for stream in config.streams() {
+        let triggers = config.triggers_for(&stream);
+        let lookback = triggers
+            .iter()
+            .map(|trigger| trigger.condition.lookback())
+            .max()
+            .unwrap_or(0);
+        let replay_start = match interval_duration(&stream.interval) {
+            Some(step) => start - step * lookback as i32,
+            None => start,
+        };
+
+        let collected = Arc::new(Mutex::new(Vec::new()));
+        let source = ReplaySource::new(
+            pool.clone(),
+            &stream.symbol,
+            &stream.interval,
+            replay_start,
+            end,
+        )
+        .with_dataset(dataset)
+        .with_exchange(exchange);
+        Pipeline::<SerdableKlineData>::builder(&format!("{}-backtest", stream.name()))
+            .source(source)
+            .sink(TriggerSink::new(triggers).with_handler(EventCollector(collected.clone())))
+            .build()?
+            .run()
+            .await
+            .with_context(|| format!("Failed to backtest triggers on {}", stream.name()))?;
+        let collected = std::mem::take(&mut *collected.lock().expect("collector lock"));
+        events.extend(
+            collected
+                .into_iter()
+                .filter(|event| event.start_time >= start),
+        );
+    }
+    let order: Vec<&str> = config.triggers.iter().map(|t| t.name.as_str()).collect();
+    events.sort_by_key(|event| {
+        let position = order.iter().position(|name| *name == event.trigger);
+        (event.start_time, position)
+    });
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn bar(minute: i64, close: f64, volume: f64) -> Bar {
+        Bar {
+            start_time: Utc.timestamp_opt(minute * 60, 0).unwrap(),
+            open: close,
+            high: close,
+            low: close,
+            close,
+            volume,
+        }
+    }
+
+    fn spec(condition: Condition) -> TriggerSpec {
+        TriggerSpec {
+            name: "test".to_string(),
+            symbol: "BTCUSDT".to_string(),
+            interval: "1m".to_string(),
+            condition,
+        }
+    }
+
+    fn sma(field: Field, periods: usize) -> Operand {
+        Operand::Indicator(Indicator::Sma { field, periods })
+    }
+
+    #[test]
+    fn test_condition_parses_from_config() {
+        let condition: Condition = serde_json::from_str(
+            r#"{"above": ["volume", {"times": 3, "of": {"sma": {"field": "volume", "periods": 288}}}]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            condition,
+            Condition::Above(
+                Operand::Field(Field::Volume),
+                Operand::Scaled {
+                    times: 3.0,
+                    of: Box::new(sma(Field::Volume, 288)),
+                }
+            )
+        );
+        assert_eq!(condition.lookback(), 287);
+
+        let condition: Condition = serde_json::from_str(
+            r#"{"below": [{"roc": {"field": "close", "periods": 5}}, -0.05]}"#,
+        )
+        .unwrap();
+        assert_eq!(condition.lookback(), 5);
+    }
+
+    #[test]
+    fn test_crosses_above_fires_once_per_cross() {
+        let mut trigger = TriggerState::new(spec(Condition::CrossesAbove(
+            Operand::Field(Field::Close),
+            sma(Field::Close, 3),
+        )));
+        let closes = [10.0, 10.0, 10.0, 13.0, 14.0, 9.0, 15.0];
+        let fired: Vec<i64> = closes
+            .iter()
+            .enumerate()
+            .filter_map(|(i, close)| trigger.on_candle(bar(i as i64, *close, 1.0)))
+            .map(|event| event.start_time.timestamp() / 60)
+            .collect();
+        assert_eq!(fired, vec![3, 6]);
+    }
+
+    #[test]
+    fn test_threshold_on_scaled_average_and_gap_restarts_warm_up() {
+        let mut trigger = TriggerState::new(spec(Condition::Above(
+            Operand::Field(Field::Volume),
+            Operand::Scaled {
+                times: 3.0,
+                of: Box::new(sma(Field::Volume, 4)),
+            },
+        )));
+        for minute in 0..3 {
+            assert!(trigger.on_candle(bar(minute, 1.0, 1.0)).is_none());
+        }
+        let event = trigger.on_candle(bar(3, 1.0, 10.0)).unwrap();
+        assert_eq!(event.left, 10.0);
+        assert_eq!(event.right, 9.75);
+
+        // After a gap, the average needs four contiguous candles again.
+        assert!(trigger.on_candle(bar(10, 1.0, 100.0)).is_none());
+        assert!(trigger.on_candle(bar(11, 1.0, 1.0)).is_none());
+        // Candles at or before the newest one are ignored.
+        assert!(trigger.on_candle(bar(11, 1.0, 100.0)).is_none());
+    }
+
+    #[test]
+    fn test_validate_lists_all_problems() {
+        let mut config = TriggerConfig {
+            triggers: vec![
+                spec(Condition::Above(
+                    Operand::Field(Field::Close),
+                    Operand::Constant(1.0),
+                )),
+                spec(Condition::Below(
+                    Operand::Field(Field::Close),
+                    sma(Field::Close, 0),
+                )),
+            ],
+        };
+        config.triggers[1].symbol = "btc".to_string();
+        config.triggers[1].interval = "7m".to_string();
+        let error = config.validate().unwrap_err();
+        assert_eq!(error.problems.len(), 4);
+        assert!(error.problems[0].contains("duplicate"));
+        assert!(error.problems[1].contains("btc"));
+        assert!(error.problems[2].contains("7m"));
+        assert!(error.problems[3].contains("period"));
+        assert!(TriggerConfig::default().validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sink_evaluates_final_candles_of_matching_streams() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut sink = TriggerSink::new(vec![spec(Condition::Above(
+            Operand::Field(Field::Close),
+            Operand::Constant(100.0),
+        ))])
+        .with_handler(EventCollector(events.clone()));
+
+        let message = |symbol: &str, close: &str, is_final: bool| {
+            let mut message: SerdableKlineData = serde_json::from_str(&format!(
+                r#"{{"t":1699999200000,"T":1699999259999,"s":"{}","i":"1m","f":1,"L":2,"o":"{close}","c":"{close}","h":"{close}","l":"{close}","v":"1","n":2,"q":"1"}}"#,
+                symbol
+            ))
+            .unwrap();
+            message.is_final = is_final;
+            message
+        };
+        sink.handle_message(&message("BTCUSDT", "101", false))
+            .await
+            .unwrap();
+        sink.handle_message(&message("ETHUSDT", "101", true))
+            .await
+            .unwrap();
+        sink.handle_message(&message("BTCUSDT", "101", true))
+            .await
+            .unwrap();
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].symbol, "BTCUSDT");
+        assert_eq!(events[0].close, 101.0);
+    }
+}
@@ -0,0 +1,213 @@
+//! # Watchlists
+//!
+//! A watchlist is a named set of symbols, e.g. "majors", that commands accept with
+//! `--watchlist` in place of a long list of `--symbol` arguments. Watchlists come
+//! from two places:
+//!
+//! - The configuration, read by [`Watchlists::from_env`], for sets that are
+//!   deployed with the commands.
+//! - The `watchlists` table, managed with the `watchlist` binary or
+//!   [`WatchlistEntry`], for sets that change at runtime.
+//!
+//! A configured watchlist takes precedence over a stored one of the same name.
+//!
+//! ## Configuration
+//!
+//! - `WATCHLISTS_CONFIG` - Path of a JSON file that maps names to symbol lists
+//! - `WATCHLISTS` - JSON object of the same form, overriding the lists of the file
+//!
+//! ```json
+//! {
+//!     "majors": ["BTCUSDT", "ETHUSDT"],
+//!     "alts": ["SOLUSDT", "ADAUSDT", "XRPUSDT"]
+//! }
+//! ```
+//!
+//! ## Example
+//!
+//! ```rust
+//! use opentrade_core::ingest::watchlists::Watchlists;
+//!
+//! let watchlists = Watchlists::default()
+//!     .with_watchlist("majors", &["BTCUSDT".to_string(), "ETHUSDT".to_string()]);
+//! let symbols = watchlists
+//!     .merge(&["ethusdt".to_string(), "SOLUSDT".to_string()], &["majors".to_string()])
+//!     .unwrap();
+//! assert_eq!(symbols, vec!["ETHUSDT", "SOLUSDT", "BTCUSDT"]);
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+
+use crate::models::watchlist::WatchlistEntry;
+
+/// The configured watchlists. See the [module documentation](self).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Watchlists {
+    lists: BTreeMap<String, Vec<String>>,
+}
+
+impl Watchlists {
+    /// Creates the watchlists of a map from names to symbols.
+    pub fn new(lists: BTreeMap<String, Vec<String>>) -> Self {
+        Self { lists }
+    }
+
+    /// Reads the watchlists from a JSON file that maps names to symbol lists.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a valid map.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read watchlists {}", path.display()))?;
+        let lists = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse watchlists {}", path.display()))?;
+        Ok(Self::new(lists))
+    }
+
+    /// Reads the watchlists from the `WATCHLISTS_CONFIG` file and the
+    /// `WATCHLISTS` map. There are no configured watchlists if neither is set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read, or the file or `WATCHLISTS`
+    /// is not a valid map.
+    pub fn from_env() -> Result<Self> {
+        let mut watchlists = match std::env::var("WATCHLISTS_CONFIG") {
+            Ok(path) => Self::from_file(path)?,
+            Err(_) => Self::default(),
+        };
+        if let Ok(lists) = std::env::var("WATCHLISTS") {
+            let lists: BTreeMap<String, Vec<String>> =
+                serde_json::from_str(&lists).context("Failed to parse WATCHLISTS")?;
+            watchlists.lists.extend(lists);
+        }
+        Ok(watchlists)
+    }
+
+    /// Adds a watchlist, replacing a configured one of the same name.
+    pub fn with_watchlist(mut self, name: &str, symbols: &[String]) -> Self {
+        self.lists.insert(name.to_string(), symbols.to_vec());
+        self
+    }
+
+    /// Returns the symbols of a configured watchlist.
+    pub fn get(&self, name: &str) -> Option<&[String]> {
+        self.lists.get(name).map(Vec::as_slice)
+    }
+
+    /// Returns the configured watchlists by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &[String])> {
+        self.lists
+            .iter()
+            .map(|(name, symbols)| (name.as_str(), symbols.as_slice()))
+    }
+
+    /// Merges explicit symbols with the symbols of configured watchlists.
+    ///
+    /// Symbols are uppercased and deduplicated, keeping the order of their first
+    /// occurrence: the explicit symbols first, then the watchlists in the given
+    /// order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a watchlist is not configured or is empty.
+    pub fn merge(&self, symbols: &[String], names: &[String]) -> Result<Vec<String>> {
+        let mut lists = Vec::with_capacity(names.len());
+        for name in names {
+            match self.get(name) {
+                Some(list) => lists.push((name.as_str(), list.to_vec())),
+                None => bail!("Unknown watchlist {}", name),
+            }
+        }
+        merge_symbols(symbols, &lists)
+    }
+
+    /// Resolves the symbols of a command from its explicit symbols and
+    /// watchlists, looking up the watchlists that are not configured in the
+    /// `watchlists` table.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `symbols` - The explicit symbols, e.g. of `--symbol`.
+    /// * `names` - The watchlist names, e.g. of `--watchlist`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a watchlist is neither configured nor stored, or if it
+    /// is empty.
+    pub async fn resolve(
+        &self,
+        pool: &sqlx::PgPool,
+        symbols: &[String],
+        names: &[String],
+    ) -> Result<Vec<String>> {
+        let mut lists = Vec::with_capacity(names.len());
+        for name in names {
+            let list = match self.get(name) {
+                Some(list) => list.to_vec(),
+                None => WatchlistEntry::symbols(pool, name)
+                    .await
+                    .with_context(|| format!("Failed to load watchlist {}", name))?,
+            };
+            lists.push((name.as_str(), list));
+        }
+        merge_symbols(symbols, &lists)
+    }
+}
+
+/// Reads the configured watchlists from the environment and resolves the
+/// symbols of a command with [`Watchlists::resolve`].
+///
+/// # Errors
+///
+/// Returns an error if the configuration is invalid, or a watchlist is unknown
+/// or empty.
+pub async fn resolve_symbols(
+    pool: &sqlx::PgPool,
+    symbols: &[String],
+    names: &[String],
+) -> Result<Vec<String>> {
+    Watchlists::from_env()?.resolve(pool, symbols, names).await
+}
+
+fn merge_symbols(symbols: &[String], lists: &[(&str, Vec<String>)]) -> Result<Vec<String>> {
+    let mut merged: Vec<String> = Vec::new();
+    let mut push = |symbol: &str| {
+        let symbol = symbol.to_uppercase();
+        if !merged.contains(&symbol) {
+            merged.push(symbol);
+        }
+    };
+    symbols.iter().for_each(|symbol| push(symbol));
+    for (name, list) in lists {
+        if list.is_empty() {
+            bail!("Watchlist {} is empty or does not exist", name);
+        }
+        list.iter().for_each(|symbol| push(symbol));
+    }
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_deduplicates_and_rejects_unknown_lists() {
+        let json = r#"{"majors": ["BTCUSDT", "ETHUSDT"], "empty": []}"#;
+        let watchlists = Watchlists::new(serde_json::from_str(json).unwrap());
+        let names = |names: &[&str]| names.iter().map(ToString::to_string).collect::<Vec<_>>();
+
+        let symbols = watchlists.merge(&names(&["btcusdt"]), &names(&["majors"]));
+        assert_eq!(symbols.unwrap(), vec!["BTCUSDT", "ETHUSDT"]);
+        assert!(watchlists.merge(&[], &names(&["alts"])).is_err());
+        assert!(watchlists.merge(&[], &names(&["empty"])).is_err());
+        assert!(watchlists.merge(&[], &[]).unwrap().is_empty());
+    }
+}
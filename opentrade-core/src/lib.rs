@@ -17,6 +17,12 @@
 //! - [`models`] - Core data structures for market data (Klines, trades, etc.)
 //! - [`data_source`] - Data source implementations for REST and WebSocket APIs
 //! - [`ingest`] - Data ingestion pipelines for real-time and historical data processing
+//! - [`config`] - Configuration of the pipeline binaries
+//! - [`analytics`] - Research and risk metrics derived from stored data
+//! - [`error`] - The typed error of exchange requests, backfills and streams
+//! - [`time`] - Millisecond timestamp conversions and candle bucket alignment
+//! - `plot` - Candlestick chart rendering (requires the `plot` feature)
+//! - `arbitrary` - Proptest strategies for Kline types (requires the `proptest` feature)
 //!
 //! ## Quick Start
 //!
@@ -62,4 +68,12 @@
 
 pub mod models;
 pub mod data_source;
-pub mod ingest;
\ No newline at end of file
+pub mod ingest;
+pub mod config;
+pub mod analytics;
+pub mod error;
+pub mod time;
+#[cfg(feature = "plot")]
+pub mod plot;
+#[cfg(feature = "proptest")]
+pub mod arbitrary;
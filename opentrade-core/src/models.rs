@@ -2,7 +2,39 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use sqlx::types::BigDecimal as Decimal;
-use std::fmt::Debug;
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+use std::ops::Range;
+
+use metrics::StatementTimer;
+
+pub mod backfill_job;
+pub mod book_ticker;
+pub mod cache;
+pub mod coverage;
+pub mod event;
+pub mod exchange_gap;
+pub mod funding;
+pub mod futures_price;
+pub mod interval;
+pub mod kline_correction;
+pub mod liquidation;
+pub mod metrics;
+pub mod open_interest;
+pub mod option;
+pub mod order_book;
+pub mod quarantine;
+pub mod read_only;
+pub mod regime;
+pub mod risk;
+pub mod schema;
+pub mod source_usage;
+pub mod symbol_status;
+pub mod trace;
+pub mod trade;
+pub mod watchlist;
+
+pub use interval::{Interval, ParseIntervalError};
 
 /// A serializable representation of Kline (candlestick) data optimized for JSON serialization.
 ///
@@ -27,6 +59,12 @@ use std::fmt::Debug;
 /// - `v`: Volume of the base asset traded (as string)
 /// - `n`: Total number of trades during the interval
 /// - `q`: Volume of the quote asset traded (as string)
+/// - `E`: Time the exchange generated the event (Unix timestamp in milliseconds);
+///   absent for data that did not come from a live stream
+/// - `x`: Whether this Kline is closed (final) or still updating; defaults to
+///   `false` when absent
+/// - `X`: The exchange the Kline came from (see [`DEFAULT_EXCHANGE`]); absent in
+///   exchange payloads, where stream clients fill it in
 ///
 /// # Usage
 ///
@@ -53,9 +91,9 @@ pub struct SerdableKlineData {
     #[serde(rename = "i")]
     pub interval: String,
     #[serde(rename = "f")]
-    pub first_trade_id: i32,
+    pub first_trade_id: i64,
     #[serde(rename = "L")]
-    pub last_trade_id: i32,
+    pub last_trade_id: i64,
     #[serde(rename = "o")]
     pub open: String,
     #[serde(rename = "c")]
@@ -70,8 +108,123 @@ pub struct SerdableKlineData {
     pub trade_count: u64,
     #[serde(rename = "q")]
     pub quote_volume: String,
+    #[serde(rename = "E", default, skip_serializing_if = "Option::is_none")]
+    pub event_time: Option<u64>,
+    #[serde(rename = "x", default)]
+    pub is_final: bool,
+    #[serde(rename = "X", default, skip_serializing_if = "Option::is_none")]
+    pub exchange: Option<String>,
+}
+
+impl SerdableKlineData {
+    /// Converts the data into a [`KlineData`] and validates it, without panicking
+    /// on malformed input like the [`From`] conversion does.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`KlineValidationError`] if a timestamp or decimal string cannot be
+    /// parsed, or if the resulting Kline fails [`KlineData::validate`].
+    pub fn to_validated_kline_data(&self) -> Result<KlineData, KlineValidationError> {
+        fn timestamp(
+            field: &'static str,
+            value: u64,
+        ) -> Result<DateTime<Utc>, KlineValidationError> {
+            crate::time::from_unsigned_millis(value)
+                .map_err(|_| KlineValidationError::InvalidTimestamp { field, value })
+        }
+        fn decimal(field: &'static str, value: &str) -> Result<Decimal, KlineValidationError> {
+            value
+                .parse::<Decimal>()
+                .map_err(|_| KlineValidationError::InvalidDecimal {
+                    field,
+                    value: value.to_string(),
+                })
+        }
+
+        let kline = KlineData {
+            start_time: timestamp("start_time", self.start_time)?,
+            end_time: timestamp("end_time", self.end_time)?,
+            symbol: self.symbol.clone(),
+            interval: self.interval.clone(),
+            first_trade_id: self.first_trade_id,
+            last_trade_id: self.last_trade_id,
+            open: decimal("open", &self.open)?,
+            high: decimal("high", &self.high)?,
+            low: decimal("low", &self.low)?,
+            close: decimal("close", &self.close)?,
+            volume: decimal("volume", &self.volume)?,
+            trade_count: Some(self.trade_count as i32),
+            quote_volume: Some(decimal("quote_volume", &self.quote_volume)?),
+            created_at: None,
+            update_at: None,
+            dataset: DEFAULT_DATASET.to_string(),
+            source_kind: SOURCE_KIND_UNKNOWN.to_string(),
+            exchange: self
+                .exchange
+                .clone()
+                .unwrap_or_else(|| DEFAULT_EXCHANGE.to_string()),
+        };
+        kline.validate()?;
+        Ok(kline)
+    }
+}
+
+/// The reason a Kline was rejected by validation.
+///
+/// Rejected rows are typically written to the [`quarantine`] table together with
+/// their raw payload instead of being dropped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KlineValidationError {
+    /// A price or volume string could not be parsed as a decimal.
+    InvalidDecimal { field: &'static str, value: String },
+    /// A timestamp could not be converted into a valid date and time.
+    InvalidTimestamp { field: &'static str, value: u64 },
+    /// The start time is not before the end time.
+    InvertedTimeRange {
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    },
+    /// A timestamp lies before the first crypto exchanges or too far in the future.
+    TimestampOutOfRange {
+        field: &'static str,
+        value: DateTime<Utc>,
+    },
+    /// A price lies outside the candle's low-high range.
+    OhlcViolation { field: &'static str },
+    /// A volume or count is negative.
+    NegativeValue { field: &'static str },
+}
+
+impl fmt::Display for KlineValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KlineValidationError::InvalidDecimal { field, value } => {
+                write!(f, "invalid decimal in {}: {:?}", field, value)
+            }
+            KlineValidationError::InvalidTimestamp { field, value } => {
+                write!(f, "invalid timestamp in {}: {}", field, value)
+            }
+            KlineValidationError::InvertedTimeRange {
+                start_time,
+                end_time,
+            } => write!(
+                f,
+                "start time {} is not before end time {}",
+                start_time, end_time
+            ),
+            KlineValidationError::TimestampOutOfRange { field, value } => {
+                write!(f, "{} out of range: {}", field, value)
+            }
+            KlineValidationError::OhlcViolation { field } => {
+                write!(f, "{} is outside the low-high range", field)
+            }
+            KlineValidationError::NegativeValue { field } => write!(f, "{} is negative", field),
+        }
+    }
 }
 
+impl std::error::Error for KlineValidationError {}
+
 /// Converts a [`SerdableKlineData`] into a [`KlineData`] for database storage.
 ///
 /// This conversion transforms the string-based serializable format into a typed
@@ -81,7 +234,7 @@ pub struct SerdableKlineData {
 ///
 /// - Timestamp fields (u64) → DateTime<Utc> using millisecond precision
 /// - String price/volume fields → BigDecimal for precise financial calculations
-/// - Trade ID fields (u64) → i32 (database constraint)
+/// - Trade ID fields (i64) remain i64, stored as BIGINT
 /// - String fields remain as String
 /// - Sets created_at and update_at to None (will be populated by database)
 ///
@@ -110,6 +263,9 @@ pub struct SerdableKlineData {
 ///     volume: "10.5".to_string(),
 ///     trade_count: 100,
 ///     quote_volume: "525000.00".to_string(),
+///     event_time: Some(1640995260012),
+///     is_final: true,
+///     exchange: Some("binance".to_string()),
 /// };
 ///
 /// let kline_data: KlineData = serdable.into();
@@ -117,8 +273,8 @@ pub struct SerdableKlineData {
 impl From<SerdableKlineData> for KlineData {
     fn from(data: SerdableKlineData) -> Self {
         KlineData {
-            start_time: DateTime::from_timestamp_millis(data.start_time as i64).unwrap(),
-            end_time: DateTime::from_timestamp_millis(data.end_time as i64).unwrap(),
+            start_time: crate::time::from_unsigned_millis(data.start_time).unwrap(),
+            end_time: crate::time::from_unsigned_millis(data.end_time).unwrap(),
             symbol: data.symbol,
             interval: data.interval,
             first_trade_id: data.first_trade_id,
@@ -132,6 +288,11 @@ impl From<SerdableKlineData> for KlineData {
             quote_volume: Some(data.quote_volume.parse::<Decimal>().unwrap()),
             created_at: None,
             update_at: None,
+            dataset: DEFAULT_DATASET.to_string(),
+            source_kind: SOURCE_KIND_UNKNOWN.to_string(),
+            exchange: data
+                .exchange
+                .unwrap_or_else(|| DEFAULT_EXCHANGE.to_string()),
         }
     }
 }
@@ -146,8 +307,9 @@ impl From<SerdableKlineData> for KlineData {
 ///
 /// - DateTime<Utc> fields → u64 timestamps (milliseconds since Unix epoch)
 /// - BigDecimal price/volume fields → String representation
-/// - i32 trade ID fields → u64 (expanding type for compatibility)
+/// - Trade ID fields (i64) remain i64
 /// - Optional fields → Default values if None (0 for trade_count, empty string for quote_volume)
+/// - `event_time` is `None`, and `is_final` is set once the Kline interval has ended
 /// - String fields remain as String
 ///
 /// # Example
@@ -174,6 +336,9 @@ impl From<SerdableKlineData> for KlineData {
 ///     quote_volume: Some(BigDecimal::from_str("525000.00").unwrap()),
 ///     created_at: None,
 ///     update_at: None,
+///     dataset: "default".to_string(),
+///     source_kind: "stream".to_string(),
+///     exchange: "binance".to_string(),
 /// };
 ///
 /// let serdable: SerdableKlineData = kline_data.into();
@@ -181,8 +346,8 @@ impl From<SerdableKlineData> for KlineData {
 impl From<KlineData> for SerdableKlineData {
     fn from(data: KlineData) -> Self {
         SerdableKlineData {
-            start_time: data.start_time.timestamp_millis() as u64,
-            end_time: data.end_time.timestamp_millis() as u64,
+            start_time: crate::time::to_unsigned_millis(data.start_time),
+            end_time: crate::time::to_unsigned_millis(data.end_time),
             symbol: data.symbol,
             interval: data.interval,
             first_trade_id: data.first_trade_id,
@@ -194,11 +359,54 @@ impl From<KlineData> for SerdableKlineData {
             volume: data.volume.to_string(),
             trade_count: data.trade_count.unwrap_or(0) as u64,
             quote_volume: data.quote_volume.unwrap_or_default().to_string(),
+            event_time: None,
+            is_final: data.end_time < Utc::now(),
+            exchange: Some(data.exchange),
         }
     }
 }
 
+/// The dataset label assigned to rows that were not ingested with an explicit one.
+pub const DEFAULT_DATASET: &str = "default";
+
+/// The exchange recorded for rows that were not ingested with an explicit one,
+/// including rows stored before the exchange was recorded, which all came from
+/// Binance.
+pub const DEFAULT_EXCHANGE: &str = "binance";
+
+/// The source kind of candles written by live stream pipelines.
+pub const SOURCE_KIND_STREAM: &str = "stream";
+
+/// The source kind of candles written by REST backfills.
+pub const SOURCE_KIND_BACKFILL: &str = "backfill";
+
+/// The source kind of candles derived from stored candles of a shorter interval
+/// (see [`crate::ingest::aggregate`]).
+pub const SOURCE_KIND_AGGREGATE: &str = "aggregate";
+
+/// The source kind of candles whose ingestion path is not known, including rows
+/// stored before provenance was recorded.
+pub const SOURCE_KIND_UNKNOWN: &str = "unknown";
+
+/// The number of decimal places prices and quantities of a symbol are stored with.
+///
+/// The exchange formats the same value with varying precision (e.g., "0.1" and
+/// "0.10000000"). Storing every value of a symbol with a fixed scale, derived from
+/// its tick and lot sizes, keeps equal values equal in comparisons and dedup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecimalScale {
+    /// The scale of prices (open, high, low and close), from the tick size.
+    pub price: i64,
+    /// The scale of base asset quantities (volume), from the lot step size.
+    pub quantity: i64,
+    /// The scale of quote asset quantities (quote volume).
+    pub quote: i64,
+}
+
 /// Represents a single Kline (candlestick) data point for a specific symbol and interval.
+///
+/// Rows are scoped to a dataset label, so a single database can host multiple
+/// isolated datasets (e.g., "prod" and "research") side by side.
 #[derive(FromRow, Debug, Clone)]
 pub struct KlineData {
     /// The start time of the Kline interval.
@@ -210,9 +418,9 @@ pub struct KlineData {
     /// The interval of the Kline data (e.g., "1m", "1h").
     pub interval: String,
     /// The ID of the first trade in this Kline interval.
-    pub first_trade_id: i32,
+    pub first_trade_id: i64,
     /// The ID of the last trade in this Kline interval.
-    pub last_trade_id: i32,
+    pub last_trade_id: i64,
     /// The opening price for the interval.
     pub open: Decimal,
     /// The highest price reached during the interval.
@@ -231,6 +439,14 @@ pub struct KlineData {
     pub created_at: Option<DateTime<Utc>>,
     /// The timestamp when this record was last updated in the database.
     pub update_at: Option<DateTime<Utc>>,
+    /// The dataset this record belongs to (see [`DEFAULT_DATASET`]).
+    pub dataset: String,
+    /// The ingestion path that last wrote this record: [`SOURCE_KIND_STREAM`],
+    /// [`SOURCE_KIND_BACKFILL`], [`SOURCE_KIND_AGGREGATE`] or [`SOURCE_KIND_UNKNOWN`].
+    pub source_kind: String,
+    /// The exchange the record was ingested from (see [`DEFAULT_EXCHANGE`]). It is
+    /// part of the key, so a dataset can hold the same symbol of several exchanges.
+    pub exchange: String,
 }
 
 impl KlineData {
@@ -257,8 +473,8 @@ impl KlineData {
         end_time: &u64,
         symbol: &str,
         interval: &str,
-        first_trade_id: i32,
-        last_trade_id: i32,
+        first_trade_id: i64,
+        last_trade_id: i64,
         open: Decimal,
         high: Decimal,
         low: Decimal,
@@ -268,8 +484,8 @@ impl KlineData {
         quote_volume: Option<Decimal>,
     ) -> Self {
         KlineData {
-            start_time: DateTime::from_timestamp_millis(*start_time as i64).unwrap(),
-            end_time: DateTime::from_timestamp_millis(*end_time as i64).unwrap(),
+            start_time: crate::time::from_unsigned_millis(*start_time).unwrap(),
+            end_time: crate::time::from_unsigned_millis(*end_time).unwrap(),
             symbol: symbol.to_string(),
             interval: interval.to_string(),
             first_trade_id,
@@ -283,23 +499,137 @@ impl KlineData {
             quote_volume,
             created_at: None,
             update_at: None,
+            dataset: DEFAULT_DATASET.to_string(),
+            source_kind: SOURCE_KIND_UNKNOWN.to_string(),
+            exchange: DEFAULT_EXCHANGE.to_string(),
         }
     }
 
+    /// Assigns the record to a dataset.
+    ///
+    /// # Arguments
+    ///
+    /// * `dataset` - The dataset label (e.g., "prod", "research").
+    pub fn with_dataset(mut self, dataset: &str) -> Self {
+        self.dataset = dataset.to_string();
+        self
+    }
+
+    /// Records the ingestion path that produced the record.
+    ///
+    /// # Arguments
+    ///
+    /// * `source_kind` - [`SOURCE_KIND_STREAM`], [`SOURCE_KIND_BACKFILL`],
+    ///   [`SOURCE_KIND_AGGREGATE`] or [`SOURCE_KIND_UNKNOWN`].
+    pub fn with_source_kind(mut self, source_kind: &str) -> Self {
+        self.source_kind = source_kind.to_string();
+        self
+    }
+
+    /// Records the exchange the record was ingested from.
+    ///
+    /// # Arguments
+    ///
+    /// * `exchange` - The exchange name, as reported by
+    ///   [`MarketDataSource::name`](crate::data_source::exchange::MarketDataSource::name)
+    ///   (e.g., "bybit").
+    pub fn with_exchange(mut self, exchange: &str) -> Self {
+        self.exchange = exchange.to_string();
+        self
+    }
+
+    /// Sets the scale of the prices and quantities, padding them with zeros or
+    /// truncating digits below the tick and lot sizes.
+    ///
+    /// # Arguments
+    ///
+    /// * `scale` - The decimal scale of the symbol.
+    pub fn with_decimal_scale(mut self, scale: &DecimalScale) -> Self {
+        self.open = self.open.with_scale(scale.price);
+        self.high = self.high.with_scale(scale.price);
+        self.low = self.low.with_scale(scale.price);
+        self.close = self.close.with_scale(scale.price);
+        self.volume = self.volume.with_scale(scale.quantity);
+        self.quote_volume = self
+            .quote_volume
+            .map(|quote_volume| quote_volume.with_scale(scale.quote));
+        self
+    }
+
+    /// Checks the Kline for internal consistency.
+    ///
+    /// The following rules are enforced:
+    /// - The start time is before the end time
+    /// - Timestamps are not before 2009-01-03 and not more than a day in the future
+    /// - `low <= open, close <= high`
+    /// - Volumes and the trade count are not negative
+    ///
+    /// # Errors
+    ///
+    /// Returns the first violated rule as a [`KlineValidationError`].
+    pub fn validate(&self) -> Result<(), KlineValidationError> {
+        if self.start_time >= self.end_time {
+            return Err(KlineValidationError::InvertedTimeRange {
+                start_time: self.start_time,
+                end_time: self.end_time,
+            });
+        }
+        let earliest = DateTime::parse_from_rfc3339("2009-01-03T00:00:00Z")
+            .expect("valid RFC 3339 timestamp")
+            .with_timezone(&Utc);
+        let latest = Utc::now() + chrono::Duration::days(1);
+        for (field, value) in [("start_time", self.start_time), ("end_time", self.end_time)] {
+            if value < earliest || value > latest {
+                return Err(KlineValidationError::TimestampOutOfRange { field, value });
+            }
+        }
+
+        if self.low > self.high {
+            return Err(KlineValidationError::OhlcViolation { field: "low" });
+        }
+        for (field, price) in [("open", &self.open), ("close", &self.close)] {
+            if *price < self.low || *price > self.high {
+                return Err(KlineValidationError::OhlcViolation { field });
+            }
+        }
+
+        let zero = Decimal::from(0);
+        if self.volume < zero {
+            return Err(KlineValidationError::NegativeValue { field: "volume" });
+        }
+        if self
+            .quote_volume
+            .as_ref()
+            .is_some_and(|volume| *volume < zero)
+        {
+            return Err(KlineValidationError::NegativeValue {
+                field: "quote_volume",
+            });
+        }
+        if self.trade_count.is_some_and(|count| count < 0) {
+            return Err(KlineValidationError::NegativeValue {
+                field: "trade_count",
+            });
+        }
+        Ok(())
+    }
+
     /// Inserts a new `KlineData` record into the database.
     ///
     /// # Arguments
     ///
     /// * `pool` - The database connection pool.
     pub async fn add(&self, pool: &sqlx::PgPool) -> Result<Self, sqlx::Error> {
+        let _timer = StatementTimer::start("kline_data.add");
         let kline = sqlx::query_as!(
             KlineData,
             r#"
             INSERT INTO kline_data (
                 start_time, end_time, symbol, interval, first_trade_id, last_trade_id,
-                open, high, low, close, volume, trade_count, quote_volume
+                open, high, low, close, volume, trade_count, quote_volume, dataset, source_kind,
+                exchange
             )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
             RETURNING *
             "#,
             self.start_time,
@@ -314,7 +644,10 @@ impl KlineData {
             self.close,
             self.volume,
             self.trade_count,
-            self.quote_volume
+            self.quote_volume,
+            self.dataset,
+            self.source_kind,
+            self.exchange
         )
         .fetch_one(pool)
         .await?;
@@ -330,35 +663,384 @@ impl KlineData {
     /// * `end_time` - The end time of the Kline interval.
     /// * `symbol` - The trading symbol.
     /// * `interval` - The Kline interval.
+    /// * `dataset` - The dataset label.
+    /// * `exchange` - The exchange the candles were captured from (e.g., "binance").
     pub async fn get(
         pool: &sqlx::PgPool,
         start_time: DateTime<Utc>,
         end_time: DateTime<Utc>,
         symbol: &str,
         interval: &str,
+        dataset: &str,
+        exchange: &str,
     ) -> Result<Option<Self>, sqlx::Error> {
+        let _timer = StatementTimer::start("kline_data.get");
         let kline = sqlx::query_as!(
             KlineData,
             r#"
             SELECT * FROM kline_data
             WHERE start_time > $1 AND end_time <= $2 AND symbol = $3 AND interval = $4
+              AND dataset = $5 AND exchange = $6
             "#,
             start_time,
             end_time,
             symbol,
-            interval
+            interval,
+            dataset,
+            exchange
         )
         .fetch_optional(pool)
         .await?;
         Ok(kline)
     }
 
+    /// Retrieves all `KlineData` records whose start time falls within a range,
+    /// ordered by start time.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `symbol` - The trading symbol.
+    /// * `interval` - The Kline interval.
+    /// * `start_time` - The inclusive lower bound for the start time.
+    /// * `end_time` - The exclusive upper bound for the start time.
+    /// * `dataset` - The dataset label.
+    /// * `exchange` - The exchange the candles were captured from (e.g., "binance").
+    pub async fn list_range(
+        pool: &sqlx::PgPool,
+        symbol: &str,
+        interval: &str,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        dataset: &str,
+        exchange: &str,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        let _timer = StatementTimer::start("kline_data.list_range");
+        let klines = sqlx::query_as!(
+            KlineData,
+            r#"
+            SELECT * FROM kline_data
+            WHERE symbol = $1 AND interval = $2 AND start_time >= $3 AND start_time < $4
+              AND dataset = $5 AND exchange = $6
+            ORDER BY start_time
+            "#,
+            symbol,
+            interval,
+            start_time,
+            end_time,
+            dataset,
+            exchange
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(klines)
+    }
+
+    /// Deletes the `KlineData` records starting within a range.
+    ///
+    /// The deleted rows are kept in `kline_data_history` by the revision trigger.
+    /// Pass a transaction to delete and rewrite a range atomically, as
+    /// [`rewrite_range`](crate::ingest::backfill::klines::rewrite_range) does.
+    ///
+    /// # Arguments
+    ///
+    /// * `executor` - The database connection pool, or a connection or transaction.
+    /// * `symbol` - The trading symbol.
+    /// * `interval` - The Kline interval.
+    /// * `range` - The range of start times to delete.
+    /// * `dataset` - The dataset label.
+    /// * `exchange` - The exchange the candles were captured from (e.g., "binance").
+    ///
+    /// # Returns
+    ///
+    /// The number of deleted records.
+    pub async fn delete_range<'e, E>(
+        executor: E,
+        symbol: &str,
+        interval: &str,
+        range: Range<DateTime<Utc>>,
+        dataset: &str,
+        exchange: &str,
+    ) -> Result<u64, sqlx::Error>
+    where
+        E: sqlx::PgExecutor<'e>,
+    {
+        let _timer = StatementTimer::start("kline_data.delete_range");
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM kline_data
+            WHERE symbol = $1 AND interval = $2 AND start_time >= $3 AND start_time < $4
+              AND dataset = $5 AND exchange = $6
+            "#,
+            symbol,
+            interval,
+            range.start,
+            range.end,
+            dataset,
+            exchange
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Retrieves the `KlineData` records within a range that were written by one
+    /// ingestion path, ordered by start time.
+    ///
+    /// Comparing the candles of a range by source kind helps to diagnose
+    /// discrepancies between live-captured and backfilled values.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `symbol` - The trading symbol.
+    /// * `interval` - The Kline interval.
+    /// * `start_time` - The inclusive lower bound for the start time.
+    /// * `end_time` - The exclusive upper bound for the start time.
+    /// * `dataset` - The dataset label.
+    /// * `exchange` - The exchange the candles were captured from (e.g., "binance").
+    /// * `source_kind` - The source kind, e.g. [`SOURCE_KIND_BACKFILL`].
+    #[allow(clippy::too_many_arguments)]
+    pub async fn list_range_by_source_kind(
+        pool: &sqlx::PgPool,
+        symbol: &str,
+        interval: &str,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        dataset: &str,
+        exchange: &str,
+        source_kind: &str,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        let _timer = StatementTimer::start("kline_data.list_range_by_source_kind");
+        let klines = sqlx::query_as!(
+            KlineData,
+            r#"
+            SELECT * FROM kline_data
+            WHERE symbol = $1 AND interval = $2 AND start_time >= $3 AND start_time < $4
+              AND dataset = $5 AND source_kind = $6 AND exchange = $7
+            ORDER BY start_time
+            "#,
+            symbol,
+            interval,
+            start_time,
+            end_time,
+            dataset,
+            source_kind,
+            exchange
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(klines)
+    }
+
+    /// Counts the candles stored within a range per source kind, ordered by
+    /// source kind.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `symbol` - The trading symbol.
+    /// * `interval` - The Kline interval.
+    /// * `start_time` - The inclusive lower bound for the start time.
+    /// * `end_time` - The exclusive upper bound for the start time.
+    /// * `dataset` - The dataset label.
+    /// * `exchange` - The exchange the candles were captured from (e.g., "binance").
+    ///
+    /// # Returns
+    ///
+    /// The `(source_kind, count)` pairs of the source kinds present in the range.
+    pub async fn count_by_source_kind(
+        pool: &sqlx::PgPool,
+        symbol: &str,
+        interval: &str,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        dataset: &str,
+        exchange: &str,
+    ) -> Result<Vec<(String, i64)>, sqlx::Error> {
+        let _timer = StatementTimer::start("kline_data.count_by_source_kind");
+        let rows = sqlx::query!(
+            r#"
+            SELECT source_kind, COUNT(*) AS "count!" FROM kline_data
+            WHERE symbol = $1 AND interval = $2 AND start_time >= $3 AND start_time < $4
+              AND dataset = $5 AND exchange = $6
+            GROUP BY source_kind
+            ORDER BY source_kind
+            "#,
+            symbol,
+            interval,
+            start_time,
+            end_time,
+            dataset,
+            exchange
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.source_kind, row.count))
+            .collect())
+    }
+
+    /// Retrieves the start times of the candles stored within a range, ordered by
+    /// start time.
+    ///
+    /// This is a lighter alternative to [`list_range`](Self::list_range) for
+    /// completeness checks, which do not need the candle values.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `symbol` - The trading symbol.
+    /// * `interval` - The Kline interval.
+    /// * `start_time` - The inclusive lower bound for the start time.
+    /// * `end_time` - The exclusive upper bound for the start time.
+    /// * `dataset` - The dataset label.
+    /// * `exchange` - The exchange the candles were captured from (e.g., "binance").
+    pub async fn list_start_times(
+        pool: &sqlx::PgPool,
+        symbol: &str,
+        interval: &str,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        dataset: &str,
+        exchange: &str,
+    ) -> Result<Vec<DateTime<Utc>>, sqlx::Error> {
+        let _timer = StatementTimer::start("kline_data.list_start_times");
+        let start_times = sqlx::query_scalar!(
+            r#"
+            SELECT start_time FROM kline_data
+            WHERE symbol = $1 AND interval = $2 AND start_time >= $3 AND start_time < $4
+              AND dataset = $5 AND exchange = $6
+            ORDER BY start_time
+            "#,
+            symbol,
+            interval,
+            start_time,
+            end_time,
+            dataset,
+            exchange
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(start_times)
+    }
+
+    /// Retrieves the start time of the latest stored candle, or `None` if no
+    /// candles are stored.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `symbol` - The trading symbol.
+    /// * `interval` - The Kline interval.
+    /// * `dataset` - The dataset label.
+    /// * `exchange` - The exchange the candles were captured from (e.g., "binance").
+    pub async fn latest_start_time(
+        pool: &sqlx::PgPool,
+        symbol: &str,
+        interval: &str,
+        dataset: &str,
+        exchange: &str,
+    ) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+        let _timer = StatementTimer::start("kline_data.latest_start_time");
+        sqlx::query_scalar!(
+            r#"
+            SELECT MAX(start_time) FROM kline_data
+            WHERE symbol = $1 AND interval = $2 AND dataset = $3 AND exchange = $4
+            "#,
+            symbol,
+            interval,
+            dataset,
+            exchange
+        )
+        .fetch_one(pool)
+        .await
+    }
+
+    /// Retrieves a candle as it was stored at a given wall-clock time.
+    ///
+    /// Previous versions of updated or deleted candles are kept in the
+    /// `kline_data_history` table, so this returns the values that were known at
+    /// `as_of_ts` rather than the latest ones. This avoids look-ahead bias in
+    /// backtests that replay data as it arrived. Updates that leave the values of a
+    /// candle unchanged are not revisions and are not recorded.
+    ///
+    /// Returns `None` if the candle had not been stored yet at `as_of_ts`.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `symbol` - The trading symbol.
+    /// * `interval` - The Kline interval.
+    /// * `start_time` - The start time of the candle.
+    /// * `as_of_ts` - The wall-clock time at which to read the candle.
+    /// * `dataset` - The dataset label.
+    /// * `exchange` - The exchange the candles were captured from (e.g., "binance").
+    pub async fn as_of(
+        pool: &sqlx::PgPool,
+        symbol: &str,
+        interval: &str,
+        start_time: DateTime<Utc>,
+        as_of_ts: DateTime<Utc>,
+        dataset: &str,
+        exchange: &str,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        let _timer = StatementTimer::start("kline_data.as_of");
+        let current = sqlx::query_as!(
+            KlineData,
+            r#"
+            SELECT * FROM kline_data
+            WHERE symbol = $1 AND interval = $2 AND start_time = $3 AND dataset = $4
+              AND exchange = $6
+              AND COALESCE(update_at, created_at) <= $5
+            "#,
+            symbol,
+            interval,
+            start_time,
+            dataset,
+            as_of_ts,
+            exchange
+        )
+        .fetch_optional(pool)
+        .await?;
+        if current.is_some() {
+            return Ok(current);
+        }
+
+        let revision = sqlx::query_as!(
+            KlineData,
+            r#"
+            SELECT
+                start_time, end_time, symbol, interval, first_trade_id, last_trade_id,
+                open, high, low, close, volume, trade_count, quote_volume, created_at,
+                valid_from AS "update_at?", dataset, source_kind, exchange
+            FROM kline_data_history
+            WHERE symbol = $1 AND interval = $2 AND start_time = $3 AND dataset = $4
+              AND exchange = $6
+              AND valid_from <= $5 AND valid_to > $5
+            ORDER BY valid_from DESC
+            LIMIT 1
+            "#,
+            symbol,
+            interval,
+            start_time,
+            dataset,
+            as_of_ts,
+            exchange
+        )
+        .fetch_optional(pool)
+        .await?;
+        Ok(revision)
+    }
+
     /// Updates an existing `KlineData` record in the database.
     ///
     /// # Arguments
     ///
     /// * `pool` - The database connection pool.
     pub async fn update(&self, pool: &sqlx::PgPool) -> Result<Self, sqlx::Error> {
+        let _timer = StatementTimer::start("kline_data.update");
         let kline = sqlx::query_as!(
             KlineData,
             r#"
@@ -374,8 +1056,10 @@ impl KlineData {
                 volume = $8,
                 trade_count = $9,
                 quote_volume = $10,
+                source_kind = $15,
                 update_at = NOW()
-            WHERE start_time = $11 AND symbol = $12 AND interval = $13
+            WHERE start_time = $11 AND symbol = $12 AND interval = $13 AND dataset = $14
+              AND exchange = $16
             RETURNING *
             "#,
             self.end_time,
@@ -390,7 +1074,10 @@ impl KlineData {
             self.quote_volume,
             self.start_time,
             self.symbol,
-            self.interval
+            self.interval,
+            self.dataset,
+            self.source_kind,
+            self.exchange
         )
         .fetch_one(pool)
         .await?;
@@ -399,22 +1086,28 @@ impl KlineData {
 
     /// Inserts a new `KlineData` record or updates an existing one if a conflict occurs.
     ///
-    /// A conflict is determined by the unique constraint on `(start_time, symbol, interval)`.
+    /// A conflict is determined by the unique constraint on
+    /// `(start_time, symbol, interval, dataset, exchange)`.
     ///
     /// # Arguments
     ///
-    /// * `pool` - The database connection pool.
-    pub async fn upsert(&self, pool: &sqlx::PgPool) -> Result<Self, sqlx::Error> {
+    /// * `executor` - The database connection pool, or a connection or transaction.
+    pub async fn upsert<'e, E>(&self, executor: E) -> Result<Self, sqlx::Error>
+    where
+        E: sqlx::PgExecutor<'e>,
+    {
+        let _timer = StatementTimer::start("kline_data.upsert");
         // Upsert by using on conflict clause
         let kline = sqlx::query_as!(
             KlineData,
             r#"
             INSERT INTO kline_data (
                 start_time, end_time, symbol, interval, first_trade_id, last_trade_id,
-                open, high, low, close, volume, trade_count, quote_volume
+                open, high, low, close, volume, trade_count, quote_volume, dataset, source_kind,
+                exchange
             )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
-            ON CONFLICT (start_time, symbol, interval) DO UPDATE
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
+            ON CONFLICT (start_time, symbol, interval, dataset, exchange) DO UPDATE
             SET
                 end_time = EXCLUDED.end_time,
                 first_trade_id = EXCLUDED.first_trade_id,
@@ -426,6 +1119,7 @@ impl KlineData {
                 volume = EXCLUDED.volume,
                 trade_count = EXCLUDED.trade_count,
                 quote_volume = EXCLUDED.quote_volume,
+                source_kind = EXCLUDED.source_kind,
                 update_at = NOW()
             RETURNING *
             "#,
@@ -441,10 +1135,378 @@ impl KlineData {
             self.close,
             self.volume,
             self.trade_count,
-            self.quote_volume
+            self.quote_volume,
+            self.dataset,
+            self.source_kind,
+            self.exchange
         )
-        .fetch_one(pool)
+        .fetch_one(executor)
+        .await?;
+        Ok(kline)
+    }
+
+    /// Upserts a batch of `KlineData` records in a single transaction.
+    ///
+    /// All rows are written on one connection, so the upsert statement is prepared
+    /// once and reused for every row, and the batch is committed atomically.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `klines` - The records to upsert.
+    ///
+    /// # Returns
+    ///
+    /// The number of upserted records.
+    pub async fn upsert_batch(pool: &sqlx::PgPool, klines: &[Self]) -> Result<usize, sqlx::Error> {
+        let _timer = StatementTimer::start("kline_data.upsert_batch");
+        let mut tx = trace::begin(pool).await?;
+        for kline in klines {
+            kline.upsert(&mut *tx).await?;
+        }
+        tx.commit().await?;
+        Ok(klines.len())
+    }
+
+    /// Upserts many `KlineData` records with a single multi-row statement.
+    ///
+    /// Unlike [`upsert_batch`](Self::upsert_batch), which still sends one statement
+    /// per row, the records are passed as arrays and unnested by PostgreSQL, so a
+    /// batch of any size is written in one round trip. Conflicts are resolved like
+    /// [`upsert`](Self::upsert). When the batch contains the same candle more than
+    /// once, only its last occurrence is written.
+    ///
+    /// # Arguments
+    ///
+    /// * `executor` - The database connection pool, or a connection or transaction.
+    /// * `klines` - The records to upsert.
+    ///
+    /// # Returns
+    ///
+    /// The number of inserted or updated records.
+    pub async fn upsert_many<'e, E>(executor: E, klines: &[Self]) -> Result<usize, sqlx::Error>
+    where
+        E: sqlx::PgExecutor<'e>,
+    {
+        let _timer = StatementTimer::start("kline_data.upsert_many");
+        if klines.is_empty() {
+            return Ok(0);
+        }
+        let rows = last_occurrences(klines);
+        let trade_counts: Vec<Option<i32>> = rows.iter().map(|k| k.trade_count).collect();
+        let quote_volumes: Vec<Option<Decimal>> =
+            rows.iter().map(|k| k.quote_volume.clone()).collect();
+
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO kline_data (
+                start_time, end_time, symbol, interval, first_trade_id, last_trade_id,
+                open, high, low, close, volume, trade_count, quote_volume, dataset, source_kind,
+                exchange
+            )
+            SELECT * FROM UNNEST(
+                $1::timestamptz[], $2::timestamptz[], $3::varchar[], $4::varchar[],
+                $5::int8[], $6::int8[], $7::numeric[], $8::numeric[], $9::numeric[],
+                $10::numeric[], $11::numeric[], $12::int4[], $13::numeric[], $14::varchar[],
+                $15::varchar[], $16::varchar[]
+            )
+            ON CONFLICT (start_time, symbol, interval, dataset, exchange) DO UPDATE
+            SET
+                end_time = EXCLUDED.end_time,
+                first_trade_id = EXCLUDED.first_trade_id,
+                last_trade_id = EXCLUDED.last_trade_id,
+                open = EXCLUDED.open,
+                high = EXCLUDED.high,
+                low = EXCLUDED.low,
+                close = EXCLUDED.close,
+                volume = EXCLUDED.volume,
+                trade_count = EXCLUDED.trade_count,
+                quote_volume = EXCLUDED.quote_volume,
+                source_kind = EXCLUDED.source_kind,
+                update_at = NOW()
+            "#,
+            &rows.iter().map(|k| k.start_time).collect::<Vec<_>>(),
+            &rows.iter().map(|k| k.end_time).collect::<Vec<_>>(),
+            &rows.iter().map(|k| k.symbol.clone()).collect::<Vec<_>>(),
+            &rows.iter().map(|k| k.interval.clone()).collect::<Vec<_>>(),
+            &rows.iter().map(|k| k.first_trade_id).collect::<Vec<_>>(),
+            &rows.iter().map(|k| k.last_trade_id).collect::<Vec<_>>(),
+            &rows.iter().map(|k| k.open.clone()).collect::<Vec<_>>(),
+            &rows.iter().map(|k| k.high.clone()).collect::<Vec<_>>(),
+            &rows.iter().map(|k| k.low.clone()).collect::<Vec<_>>(),
+            &rows.iter().map(|k| k.close.clone()).collect::<Vec<_>>(),
+            &rows.iter().map(|k| k.volume.clone()).collect::<Vec<_>>(),
+            // The arrays of nullable columns hold NULLs, which the inferred
+            // parameter types do not allow.
+            &trade_counts as &[Option<i32>],
+            &quote_volumes as &[Option<Decimal>],
+            &rows.iter().map(|k| k.dataset.clone()).collect::<Vec<_>>(),
+            &rows
+                .iter()
+                .map(|k| k.source_kind.clone())
+                .collect::<Vec<_>>(),
+            &rows.iter().map(|k| k.exchange.clone()).collect::<Vec<_>>()
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() as usize)
+    }
+
+    /// Upserts a replayed `KlineData` record without rewriting newer or identical data.
+    ///
+    /// Unlike [`upsert`](Self::upsert), an existing candle is only updated when the
+    /// replayed record covers more trades (a higher `last_trade_id`). Replaying the
+    /// same messages twice, or an older update of a candle after a newer one, leaves
+    /// the stored candle and its revision history untouched, so replays from a log
+    /// of messages are idempotent.
+    ///
+    /// # Arguments
+    ///
+    /// * `executor` - The database connection pool or connection.
+    ///
+    /// # Returns
+    ///
+    /// The stored record if it was inserted or updated, or `None` if it was skipped.
+    pub async fn upsert_replayed<'e, E>(&self, executor: E) -> Result<Option<Self>, sqlx::Error>
+    where
+        E: sqlx::PgExecutor<'e>,
+    {
+        let _timer = StatementTimer::start("kline_data.upsert_replayed");
+        let kline = sqlx::query_as!(
+            KlineData,
+            r#"
+            INSERT INTO kline_data (
+                start_time, end_time, symbol, interval, first_trade_id, last_trade_id,
+                open, high, low, close, volume, trade_count, quote_volume, dataset, source_kind,
+                exchange
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
+            ON CONFLICT (start_time, symbol, interval, dataset, exchange) DO UPDATE
+            SET
+                end_time = EXCLUDED.end_time,
+                first_trade_id = EXCLUDED.first_trade_id,
+                last_trade_id = EXCLUDED.last_trade_id,
+                open = EXCLUDED.open,
+                high = EXCLUDED.high,
+                low = EXCLUDED.low,
+                close = EXCLUDED.close,
+                volume = EXCLUDED.volume,
+                trade_count = EXCLUDED.trade_count,
+                quote_volume = EXCLUDED.quote_volume,
+                source_kind = EXCLUDED.source_kind,
+                update_at = NOW()
+            WHERE kline_data.last_trade_id < EXCLUDED.last_trade_id
+            RETURNING *
+            "#,
+            self.start_time,
+            self.end_time,
+            self.symbol,
+            self.interval,
+            self.first_trade_id,
+            self.last_trade_id,
+            self.open,
+            self.high,
+            self.low,
+            self.close,
+            self.volume,
+            self.trade_count,
+            self.quote_volume,
+            self.dataset,
+            self.source_kind,
+            self.exchange
+        )
+        .fetch_optional(executor)
         .await?;
         Ok(kline)
     }
 }
+
+/// Returns the last occurrence of every candle in `klines`, in their original order.
+///
+/// A single statement cannot update the same row twice, so
+/// [`KlineData::upsert_many`] keeps only the latest copy of a candle.
+fn last_occurrences(klines: &[KlineData]) -> Vec<&KlineData> {
+    let mut positions = HashMap::with_capacity(klines.len());
+    for (position, kline) in klines.iter().enumerate() {
+        let key = (
+            kline.start_time,
+            &kline.symbol,
+            &kline.interval,
+            &kline.dataset,
+            &kline.exchange,
+        );
+        positions.insert(key, position);
+    }
+    let mut positions: Vec<usize> = positions.into_values().collect();
+    positions.sort_unstable();
+    positions
+        .into_iter()
+        .map(|position| &klines[position])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn serdable() -> SerdableKlineData {
+        SerdableKlineData {
+            start_time: 1751897340000,
+            end_time: 1751897399999,
+            symbol: "BTCUSDT".to_string(),
+            interval: "1m".to_string(),
+            first_trade_id: 1,
+            last_trade_id: 2,
+            open: "108521.04".to_string(),
+            close: "108473.03".to_string(),
+            high: "108521.04".to_string(),
+            low: "108473.02".to_string(),
+            volume: "5.21".to_string(),
+            trade_count: 1831,
+            quote_volume: "565334.99".to_string(),
+            event_time: Some(1751897378015),
+            is_final: false,
+            exchange: None,
+        }
+    }
+
+    #[test]
+    fn test_validated_kline_data_success() {
+        let kline = serdable().to_validated_kline_data().unwrap();
+        assert_eq!(kline.symbol, "BTCUSDT");
+        assert_eq!(kline.trade_count, Some(1831));
+    }
+
+    #[test]
+    fn test_serdable_event_time_and_is_final_roundtrip() {
+        let json = serde_json::to_string(&serdable()).unwrap();
+        let message: SerdableKlineData = serde_json::from_str(&json).unwrap();
+        assert_eq!(message.event_time, Some(1751897378015));
+        assert!(!message.is_final);
+
+        let legacy = r#"{"t":1751897340000,"T":1751897399999,"s":"BTCUSDT","i":"1m","f":1,"L":2,"o":"1","c":"1","h":"1","l":"1","v":"1","n":1,"q":"1"}"#;
+        let message: SerdableKlineData = serde_json::from_str(legacy).unwrap();
+        assert_eq!(message.event_time, None);
+        assert!(!message.is_final);
+    }
+
+    #[test]
+    fn test_validated_kline_data_invalid_decimal() {
+        let mut message = serdable();
+        message.close = "not-a-decimal".to_string();
+        assert_eq!(
+            message.to_validated_kline_data().unwrap_err(),
+            KlineValidationError::InvalidDecimal {
+                field: "close",
+                value: "not-a-decimal".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_validate_ohlc_invariants() {
+        let mut message = serdable();
+        message.close = "200000".to_string();
+        assert_eq!(
+            message.to_validated_kline_data().unwrap_err(),
+            KlineValidationError::OhlcViolation { field: "close" }
+        );
+    }
+
+    #[test]
+    fn test_validate_inverted_time_range() {
+        let mut message = serdable();
+        message.end_time = message.start_time;
+        assert!(matches!(
+            message.to_validated_kline_data().unwrap_err(),
+            KlineValidationError::InvertedTimeRange { .. }
+        ));
+    }
+
+    #[test]
+    fn test_source_kind_defaults_to_unknown() {
+        let kline = serdable().to_validated_kline_data().unwrap();
+        assert_eq!(kline.source_kind, SOURCE_KIND_UNKNOWN);
+        let kline = kline.with_source_kind(SOURCE_KIND_BACKFILL);
+        assert_eq!(kline.source_kind, SOURCE_KIND_BACKFILL);
+    }
+
+    #[test]
+    fn test_exchange_defaults_to_binance() {
+        let kline = KlineData::from(serdable());
+        assert_eq!(kline.exchange, DEFAULT_EXCHANGE);
+        let kline = kline.with_exchange("bybit");
+        assert_eq!(kline.exchange, "bybit");
+    }
+
+    #[test]
+    fn test_trade_ids_beyond_i32() {
+        let message = SerdableKlineData {
+            first_trade_id: 5_000_000_000,
+            last_trade_id: 5_000_000_123,
+            ..serdable()
+        };
+        let kline = message.to_validated_kline_data().unwrap();
+        assert_eq!(kline.first_trade_id, 5_000_000_000);
+        assert_eq!(SerdableKlineData::from(kline).last_trade_id, 5_000_000_123);
+    }
+
+    #[test]
+    fn test_serdable_exchange_roundtrip() {
+        let message = SerdableKlineData::from(serdable().to_validated_kline_data().unwrap());
+        let json = serde_json::to_string(&SerdableKlineData {
+            exchange: Some("okx".to_string()),
+            ..message
+        })
+        .unwrap();
+        assert!(json.contains(r#""X":"okx""#));
+        let message: SerdableKlineData = serde_json::from_str(&json).unwrap();
+        assert_eq!(message.to_validated_kline_data().unwrap().exchange, "okx");
+        let json = serde_json::to_string(&serdable()).unwrap();
+        assert!(!json.contains(r#""X""#));
+    }
+
+    #[test]
+    fn test_with_decimal_scale_normalizes_precision() {
+        let scale = DecimalScale {
+            price: 2,
+            quantity: 5,
+            quote: 8,
+        };
+        let mut kline = KlineData::from(serdable());
+        kline.open = "0.1".parse().unwrap();
+        kline.close = "0.10000000".parse().unwrap();
+        kline.volume = "1.234567".parse().unwrap();
+        let kline = kline.with_decimal_scale(&scale);
+        assert_eq!(kline.open.to_string(), "0.10");
+        assert_eq!(kline.close.to_string(), "0.10");
+        assert_eq!(kline.open, kline.close);
+        assert_eq!(kline.volume.to_string(), "1.23456");
+        assert_eq!(kline.quote_volume.unwrap().as_bigint_and_exponent().1, 8);
+    }
+
+    #[test]
+    fn test_last_occurrences_keeps_latest_duplicate() {
+        let first = KlineData::from(serdable());
+        let mut update = first.clone();
+        update.last_trade_id = 3;
+        let mut next = first.clone();
+        next.start_time += chrono::Duration::minutes(1);
+        let other_dataset = first.clone().with_dataset("research");
+        let other_exchange = first.clone().with_exchange("bybit");
+
+        let klines = vec![
+            first,
+            next.clone(),
+            update.clone(),
+            other_dataset.clone(),
+            other_exchange,
+        ];
+        let rows = last_occurrences(&klines);
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows[0].start_time, next.start_time);
+        assert_eq!(rows[1].last_trade_id, 3);
+        assert_eq!(rows[2].dataset, "research");
+        assert_eq!(rows[3].exchange, "bybit");
+    }
+}
@@ -0,0 +1,278 @@
+use chrono::{DateTime, Duration, Utc};
+use sqlx::FromRow;
+
+use crate::models::metrics::StatementTimer;
+
+/// The status of a job that is running, or was running when its process died.
+pub const RUNNING: &str = "running";
+
+/// The status of a job that reached its end time.
+pub const COMPLETED: &str = "completed";
+
+/// The status of a job whose last batch failed.
+pub const FAILED: &str = "failed";
+
+/// A tracked kline backfill and its progress, stored in `backfill_jobs`.
+#[derive(FromRow, Debug, Clone, PartialEq, Eq)]
+pub struct BackfillJob {
+    /// The unique identifier of the job.
+    pub id: i64,
+    /// The trading symbol (e.g., "BTCUSDT").
+    pub symbol: String,
+    /// The Kline interval (e.g., "1m").
+    pub interval: String,
+    /// The dataset the klines are stored under.
+    pub dataset: String,
+    /// The inclusive start of the backfilled range.
+    pub start_time: DateTime<Utc>,
+    /// The exclusive end of the backfilled range, or `None` to backfill up to now.
+    pub end_time: Option<DateTime<Utc>>,
+    /// The close time of the last stored batch, if any batch was stored.
+    pub last_end_time: Option<DateTime<Utc>>,
+    /// The number of klines backfilled so far.
+    pub row_count: i64,
+    /// [`RUNNING`], [`COMPLETED`] or [`FAILED`].
+    pub status: String,
+    /// The error of the last failed batch.
+    pub error: Option<String>,
+    /// The timestamp when the job was created.
+    pub created_at: DateTime<Utc>,
+    /// The timestamp of the last progress or status update.
+    pub updated_at: DateTime<Utc>,
+    /// Whether the job walks its range from the end back to the start.
+    pub newest_first: bool,
+    /// The open time of the earliest stored batch of a newest-first job, if any
+    /// batch was stored.
+    pub first_start_time: Option<DateTime<Utc>>,
+}
+
+impl BackfillJob {
+    /// Returns the time the next batch of the job starts at, or for a
+    /// [`newest_first`](Self::newest_first) job, the exclusive end of the next batch.
+    pub fn resume_time(&self) -> DateTime<Utc> {
+        if self.newest_first {
+            return self
+                .first_start_time
+                .or(self.end_time)
+                .unwrap_or(self.created_at);
+        }
+        match self.last_end_time {
+            Some(last_end_time) => last_end_time + Duration::milliseconds(1),
+            None => self.start_time,
+        }
+    }
+
+    /// Returns true if the job reached its end time.
+    pub fn is_completed(&self) -> bool {
+        self.status == COMPLETED
+    }
+
+    /// Creates a running job.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `symbol` - The trading symbol.
+    /// * `interval` - The Kline interval.
+    /// * `dataset` - The dataset label.
+    /// * `start_time` - The inclusive start of the range.
+    /// * `end_time` - The exclusive end of the range, or `None` to backfill up to now.
+    /// * `newest_first` - Whether the range is walked from the end back to the start,
+    ///   which requires an end time.
+    pub async fn create(
+        pool: &sqlx::PgPool,
+        symbol: &str,
+        interval: &str,
+        dataset: &str,
+        start_time: DateTime<Utc>,
+        end_time: Option<DateTime<Utc>>,
+        newest_first: bool,
+    ) -> Result<Self, sqlx::Error> {
+        let job = sqlx::query_as!(
+            BackfillJob,
+            r#"
+            INSERT INTO backfill_jobs (symbol, interval, dataset, start_time, end_time, newest_first)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING *
+            "#,
+            symbol,
+            interval,
+            dataset,
+            start_time,
+            end_time,
+            newest_first
+        )
+        .fetch_one(pool)
+        .await?;
+        Ok(job)
+    }
+
+    /// Retrieves a job by its identifier.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `id` - The job identifier.
+    pub async fn get(pool: &sqlx::PgPool, id: i64) -> Result<Option<Self>, sqlx::Error> {
+        let job = sqlx::query_as!(
+            BackfillJob,
+            r#"
+            SELECT * FROM backfill_jobs WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_optional(pool)
+        .await?;
+        Ok(job)
+    }
+
+    /// Lists the jobs that did not complete, oldest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    pub async fn list_unfinished(pool: &sqlx::PgPool) -> Result<Vec<Self>, sqlx::Error> {
+        let jobs = sqlx::query_as!(
+            BackfillJob,
+            r#"
+            SELECT * FROM backfill_jobs WHERE status <> $1 ORDER BY id
+            "#,
+            COMPLETED
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(jobs)
+    }
+
+    /// Records a stored batch and marks the job as running.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `last_end_time` - The close time of the last kline of the batch.
+    /// * `row_count` - The total number of klines backfilled by the job.
+    pub async fn record_progress(
+        &mut self,
+        pool: &sqlx::PgPool,
+        last_end_time: DateTime<Utc>,
+        row_count: i64,
+    ) -> Result<(), sqlx::Error> {
+        let _timer = StatementTimer::start("backfill_jobs.record_progress");
+        *self = sqlx::query_as!(
+            BackfillJob,
+            r#"
+            UPDATE backfill_jobs
+            SET last_end_time = $2, row_count = $3, status = $4, error = NULL, updated_at = NOW()
+            WHERE id = $1
+            RETURNING *
+            "#,
+            self.id,
+            last_end_time,
+            row_count,
+            RUNNING
+        )
+        .fetch_one(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Records a stored batch of a newest-first job and marks the job as running.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `first_start_time` - The open time of the first kline of the batch.
+    /// * `row_count` - The total number of klines backfilled by the job.
+    pub async fn record_reverse_progress(
+        &mut self,
+        pool: &sqlx::PgPool,
+        first_start_time: DateTime<Utc>,
+        row_count: i64,
+    ) -> Result<(), sqlx::Error> {
+        let _timer = StatementTimer::start("backfill_jobs.record_reverse_progress");
+        *self = sqlx::query_as!(
+            BackfillJob,
+            r#"
+            UPDATE backfill_jobs
+            SET first_start_time = $2, row_count = $3, status = $4, error = NULL, updated_at = NOW()
+            WHERE id = $1
+            RETURNING *
+            "#,
+            self.id,
+            first_start_time,
+            row_count,
+            RUNNING
+        )
+        .fetch_one(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Sets the status of the job.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `status` - [`RUNNING`], [`COMPLETED`] or [`FAILED`].
+    /// * `error` - The error of a failed batch.
+    pub async fn set_status(
+        &mut self,
+        pool: &sqlx::PgPool,
+        status: &str,
+        error: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        *self = sqlx::query_as!(
+            BackfillJob,
+            r#"
+            UPDATE backfill_jobs
+            SET status = $2, error = $3, updated_at = NOW()
+            WHERE id = $1
+            RETURNING *
+            "#,
+            self.id,
+            status,
+            error
+        )
+        .fetch_one(pool)
+        .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_resume_time_follows_last_batch() {
+        let start_time = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let mut job = BackfillJob {
+            id: 1,
+            symbol: "BTCUSDT".to_string(),
+            interval: "1m".to_string(),
+            dataset: "default".to_string(),
+            start_time,
+            end_time: None,
+            last_end_time: None,
+            row_count: 0,
+            status: RUNNING.to_string(),
+            error: None,
+            created_at: start_time,
+            updated_at: start_time,
+            newest_first: false,
+            first_start_time: None,
+        };
+        assert_eq!(job.resume_time(), start_time);
+
+        job.last_end_time = Some(start_time + Duration::minutes(1) - Duration::milliseconds(1));
+        assert_eq!(job.resume_time(), start_time + Duration::minutes(1));
+
+        let end_time = start_time + Duration::days(1);
+        job.newest_first = true;
+        job.end_time = Some(end_time);
+        assert_eq!(job.resume_time(), end_time);
+        job.first_start_time = Some(end_time - Duration::hours(1));
+        assert_eq!(job.resume_time(), end_time - Duration::hours(1));
+    }
+}
@@ -0,0 +1,200 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use sqlx::types::BigDecimal as Decimal;
+
+use crate::models::DEFAULT_DATASET;
+use crate::models::metrics::StatementTimer;
+
+/// A best bid and ask update, as sent by the `<symbol>@bookTicker` streams.
+///
+/// Numeric fields are strings to preserve precision, like [`SerdableKlineData`](super::SerdableKlineData).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SerdableBookTickerData {
+    /// The order book update id.
+    #[serde(rename = "u")]
+    pub update_id: u64,
+    /// The trading symbol (e.g., "BTCUSDT").
+    #[serde(rename = "s")]
+    pub symbol: String,
+    /// The best bid price.
+    #[serde(rename = "b")]
+    pub bid_price: String,
+    /// The quantity at the best bid.
+    #[serde(rename = "B")]
+    pub bid_quantity: String,
+    /// The best ask price.
+    #[serde(rename = "a")]
+    pub ask_price: String,
+    /// The quantity at the best ask.
+    #[serde(rename = "A")]
+    pub ask_quantity: String,
+}
+
+impl SerdableBookTickerData {
+    /// Converts the update into a row of `book_tickers` under the [`DEFAULT_DATASET`].
+    ///
+    /// # Arguments
+    ///
+    /// * `received_at` - The time the update was received, as the stream carries no
+    ///   event time.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a decimal string cannot be parsed or the update id is out
+    /// of range.
+    pub fn to_book_ticker_data(
+        &self,
+        received_at: DateTime<Utc>,
+    ) -> anyhow::Result<BookTickerData> {
+        fn decimal(value: &str) -> anyhow::Result<Decimal> {
+            value
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid decimal: {}", value))
+        }
+
+        Ok(BookTickerData {
+            symbol: self.symbol.clone(),
+            dataset: DEFAULT_DATASET.to_string(),
+            update_id: i64::try_from(self.update_id)
+                .map_err(|_| anyhow::anyhow!("Invalid update id: {}", self.update_id))?,
+            received_at,
+            bid_price: decimal(&self.bid_price)?,
+            bid_quantity: decimal(&self.bid_quantity)?,
+            ask_price: decimal(&self.ask_price)?,
+            ask_quantity: decimal(&self.ask_quantity)?,
+        })
+    }
+}
+
+/// A best bid and ask quote of a symbol, stored in `book_tickers`.
+#[derive(FromRow, Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BookTickerData {
+    /// The trading symbol.
+    pub symbol: String,
+    /// The dataset label.
+    pub dataset: String,
+    /// The order book update id, which orders the quotes of a symbol.
+    pub update_id: i64,
+    /// The time the quote was received.
+    pub received_at: DateTime<Utc>,
+    /// The best bid price.
+    pub bid_price: Decimal,
+    /// The quantity at the best bid.
+    pub bid_quantity: Decimal,
+    /// The best ask price.
+    pub ask_price: Decimal,
+    /// The quantity at the best ask.
+    pub ask_quantity: Decimal,
+}
+
+impl BookTickerData {
+    /// Assigns the quote to a dataset.
+    pub fn with_dataset(mut self, dataset: &str) -> Self {
+        self.dataset = dataset.to_string();
+        self
+    }
+
+    /// Returns the absolute spread between the best ask and the best bid.
+    pub fn spread(&self) -> Decimal {
+        &self.ask_price - &self.bid_price
+    }
+
+    /// Returns the spread relative to the mid price, in basis points, or `None` if
+    /// the mid price is not positive.
+    pub fn spread_bps(&self) -> Option<Decimal> {
+        let mid = (&self.ask_price + &self.bid_price) / Decimal::from(2);
+        (mid > Decimal::from(0)).then(|| self.spread() * Decimal::from(10_000) / mid)
+    }
+
+    /// Stores the quote, replacing a previous quote with the same update id.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    pub async fn upsert(&self, pool: &sqlx::PgPool) -> Result<(), sqlx::Error> {
+        let _timer = StatementTimer::start("book_tickers.upsert");
+        sqlx::query!(
+            r#"
+            INSERT INTO book_tickers (
+                symbol, dataset, update_id, received_at,
+                bid_price, bid_quantity, ask_price, ask_quantity
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (symbol, dataset, update_id) DO UPDATE
+            SET
+                received_at = EXCLUDED.received_at,
+                bid_price = EXCLUDED.bid_price,
+                bid_quantity = EXCLUDED.bid_quantity,
+                ask_price = EXCLUDED.ask_price,
+                ask_quantity = EXCLUDED.ask_quantity
+            "#,
+            self.symbol,
+            self.dataset,
+            self.update_id,
+            self.received_at,
+            self.bid_price,
+            self.bid_quantity,
+            self.ask_price,
+            self.ask_quantity
+        )
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Lists the quotes of a symbol received in `[start_time, end_time)`, oldest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `symbol` - The trading symbol.
+    /// * `dataset` - The dataset label.
+    /// * `start_time` - The inclusive start of the range.
+    /// * `end_time` - The exclusive end of the range.
+    pub async fn list_range(
+        pool: &sqlx::PgPool,
+        symbol: &str,
+        dataset: &str,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        let _timer = StatementTimer::start("book_tickers.list_range");
+        let quotes = sqlx::query_as!(
+            BookTickerData,
+            r#"
+            SELECT * FROM book_tickers
+            WHERE symbol = $1 AND dataset = $2
+              AND received_at >= $3 AND received_at < $4
+            ORDER BY update_id
+            "#,
+            symbol,
+            dataset,
+            start_time,
+            end_time
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(quotes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_book_ticker() {
+        let json = r#"{"u":400900217,"s":"BNBUSDT","b":"25.35190000","B":"31.21000000","a":"25.36520000","A":"40.66000000"}"#;
+        let update: SerdableBookTickerData = serde_json::from_str(json).unwrap();
+        let quote = update
+            .to_book_ticker_data(Utc::now())
+            .unwrap()
+            .with_dataset("research");
+        assert_eq!(quote.update_id, 400900217);
+        assert_eq!(quote.dataset, "research");
+        assert_eq!(quote.spread(), "0.0133".parse::<Decimal>().unwrap());
+        let bps = quote.spread_bps().unwrap();
+        assert!(bps > Decimal::from(5) && bps < Decimal::from(6));
+    }
+}
@@ -0,0 +1,337 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+
+use super::KlineData;
+use crate::time;
+
+/// The default number of cached ranges.
+pub const DEFAULT_CACHE_CAPACITY: usize = 1024;
+
+/// The default width of the buckets requested ranges are widened to.
+pub const DEFAULT_CACHE_BUCKET: chrono::Duration = chrono::Duration::hours(1);
+
+/// The key of a cached range: the series and the range widened to whole buckets.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct RangeKey {
+    symbol: String,
+    interval: String,
+    dataset: String,
+    exchange: String,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+}
+
+impl RangeKey {
+    /// Returns true if the key belongs to the series and its range overlaps `range`.
+    fn overlaps(
+        &self,
+        symbol: &str,
+        interval: &str,
+        dataset: &str,
+        exchange: &str,
+        range: &Range<DateTime<Utc>>,
+    ) -> bool {
+        self.symbol == symbol
+            && self.interval == interval
+            && self.dataset == dataset
+            && self.exchange == exchange
+            && self.start < range.end
+            && range.start < self.end
+    }
+}
+
+/// A cached range, the time it was fetched and the tick it was last used at.
+#[derive(Debug)]
+struct CacheEntry {
+    klines: Arc<Vec<KlineData>>,
+    fetched_at: Instant,
+    used: u64,
+}
+
+/// The cached ranges, the use counter the least recently used range is
+/// determined by and the number of invalidations so far.
+#[derive(Debug, Default)]
+struct CacheState {
+    entries: HashMap<RangeKey, CacheEntry>,
+    tick: u64,
+    generation: u64,
+}
+
+impl CacheState {
+    /// Returns the klines of a cached range that is not older than `ttl`.
+    fn get(&mut self, key: &RangeKey, ttl: Option<Duration>) -> Option<Arc<Vec<KlineData>>> {
+        self.tick += 1;
+        let entry = self.entries.get_mut(key)?;
+        if ttl.is_some_and(|ttl| entry.fetched_at.elapsed() >= ttl) {
+            self.entries.remove(key);
+            return None;
+        }
+        entry.used = self.tick;
+        Some(entry.klines.clone())
+    }
+
+    /// Caches a range, evicting the least recently used range if the cache is full.
+    fn insert(&mut self, key: RangeKey, klines: Arc<Vec<KlineData>>, capacity: usize) {
+        self.tick += 1;
+        if !self.entries.contains_key(&key) && self.entries.len() >= capacity {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.entries.insert(
+            key,
+            CacheEntry {
+                klines,
+                fetched_at: Instant::now(),
+                used: self.tick,
+            },
+        );
+    }
+}
+
+/// An in-process LRU cache of [`KlineData::list_range`] results, for services
+/// that answer many overlapping range queries, e.g. dashboards.
+///
+/// Requested ranges are widened to whole buckets (see [`with_bucket`](Self::with_bucket)),
+/// so queries over slightly different ranges share one cached range, and the
+/// candles are filtered to the requested range on the way out.
+///
+/// Writers in the same process keep the cache consistent by invalidating the
+/// candles they store, e.g. through
+/// [`UpsertSink::with_cache`](crate::ingest::pipeline::UpsertSink::with_cache).
+/// Writes of other processes are not seen, so services reading data that other
+/// processes ingest should bound the staleness with [`with_ttl`](Self::with_ttl).
+#[derive(Debug)]
+pub struct KlineCache {
+    capacity: usize,
+    bucket: chrono::Duration,
+    ttl: Option<Duration>,
+    state: Mutex<CacheState>,
+}
+
+impl Default for KlineCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CACHE_CAPACITY)
+    }
+}
+
+impl KlineCache {
+    /// Creates a cache.
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - The maximum number of cached ranges.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            bucket: DEFAULT_CACHE_BUCKET,
+            ttl: None,
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    /// Sets the width of the buckets requested ranges are widened to (defaults to
+    /// [`DEFAULT_CACHE_BUCKET`]).
+    pub fn with_bucket(mut self, bucket: chrono::Duration) -> Self {
+        self.bucket = bucket;
+        self
+    }
+
+    /// Sets how long a cached range is served before it is fetched again. By
+    /// default cached ranges only expire when they are invalidated or evicted.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Retrieves the candles of a range, see [`KlineData::list_range`]. The bucketed
+    /// range is served from the cache if it is cached, and fetched and cached otherwise.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn list_range(
+        &self,
+        pool: &sqlx::PgPool,
+        symbol: &str,
+        interval: &str,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        dataset: &str,
+        exchange: &str,
+    ) -> Result<Vec<KlineData>, sqlx::Error> {
+        let key = self.key(symbol, interval, dataset, exchange, start_time, end_time);
+        let (cached, generation) = {
+            let mut state = self.state.lock().unwrap();
+            (state.get(&key, self.ttl), state.generation)
+        };
+        let klines = match cached {
+            Some(klines) => klines,
+            None => {
+                let klines = Arc::new(
+                    KlineData::list_range(
+                        pool, symbol, interval, key.start, key.end, dataset, exchange,
+                    )
+                    .await?,
+                );
+                // A write invalidated while the range was fetched may be missing from it.
+                let mut state = self.state.lock().unwrap();
+                if state.generation == generation {
+                    state.insert(key, klines.clone(), self.capacity);
+                }
+                klines
+            }
+        };
+        Ok(klines
+            .iter()
+            .filter(|kline| kline.start_time >= start_time && kline.start_time < end_time)
+            .cloned()
+            .collect())
+    }
+
+    /// Drops the cached ranges of a series that overlap a range of start times.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbol` - The trading symbol.
+    /// * `interval` - The Kline interval.
+    /// * `dataset` - The dataset label.
+    /// * `exchange` - The exchange the candles were captured from.
+    /// * `range` - The range of start times that was written.
+    pub fn invalidate(
+        &self,
+        symbol: &str,
+        interval: &str,
+        dataset: &str,
+        exchange: &str,
+        range: Range<DateTime<Utc>>,
+    ) {
+        let mut state = self.state.lock().unwrap();
+        state.generation += 1;
+        state
+            .entries
+            .retain(|key, _| !key.overlaps(symbol, interval, dataset, exchange, &range));
+    }
+
+    /// Drops the cached ranges containing a written candle.
+    pub fn invalidate_kline(&self, kline: &KlineData) {
+        self.invalidate(
+            &kline.symbol,
+            &kline.interval,
+            &kline.dataset,
+            &kline.exchange,
+            kline.start_time..kline.start_time + chrono::Duration::milliseconds(1),
+        );
+    }
+
+    /// Drops all cached ranges.
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.generation += 1;
+        state.entries.clear();
+    }
+
+    /// Returns the number of cached ranges.
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().entries.len()
+    }
+
+    /// Returns true if no range is cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the key of a range widened to whole buckets.
+    fn key(
+        &self,
+        symbol: &str,
+        interval: &str,
+        dataset: &str,
+        exchange: &str,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> RangeKey {
+        RangeKey {
+            symbol: symbol.to_string(),
+            interval: interval.to_string(),
+            dataset: dataset.to_string(),
+            exchange: exchange.to_string(),
+            start: time::align_down(start_time, self.bucket),
+            end: time::align_up(end_time, self.bucket),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn key(cache: &KlineCache, symbol: &str, start_hour: u32, end_hour: u32) -> RangeKey {
+        cache.key(
+            symbol,
+            "1m",
+            "live",
+            "binance",
+            Utc.with_ymd_and_hms(2024, 1, 1, start_hour, 30, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 1, 1, end_hour, 30, 0).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_key_widens_range_to_buckets() {
+        let cache = KlineCache::new(4);
+        let key = key(&cache, "BTCUSDT", 1, 3);
+        assert_eq!(
+            key.start,
+            Utc.with_ymd_and_hms(2024, 1, 1, 1, 0, 0).unwrap()
+        );
+        assert_eq!(key.end, Utc.with_ymd_and_hms(2024, 1, 1, 4, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn test_insert_evicts_least_recently_used() {
+        let cache = KlineCache::new(2);
+        let mut state = cache.state.lock().unwrap();
+        let (a, b, c) = (
+            key(&cache, "BTCUSDT", 1, 2),
+            key(&cache, "ETHUSDT", 1, 2),
+            key(&cache, "BNBUSDT", 1, 2),
+        );
+        state.insert(a.clone(), Arc::default(), 2);
+        state.insert(b.clone(), Arc::default(), 2);
+        assert!(state.get(&a, None).is_some());
+        state.insert(c.clone(), Arc::default(), 2);
+        assert!(state.get(&a, None).is_some());
+        assert!(state.get(&b, None).is_none());
+        assert!(state.get(&c, None).is_some());
+    }
+
+    #[test]
+    fn test_invalidate_drops_overlapping_ranges() {
+        let cache = KlineCache::new(4);
+        {
+            let mut state = cache.state.lock().unwrap();
+            state.insert(key(&cache, "BTCUSDT", 1, 2), Arc::default(), 4);
+            state.insert(key(&cache, "BTCUSDT", 5, 6), Arc::default(), 4);
+            state.insert(key(&cache, "ETHUSDT", 1, 2), Arc::default(), 4);
+        }
+        let written = Utc.with_ymd_and_hms(2024, 1, 1, 2, 15, 0).unwrap();
+        cache.invalidate(
+            "BTCUSDT",
+            "1m",
+            "live",
+            "binance",
+            written..written + chrono::Duration::minutes(1),
+        );
+        assert_eq!(cache.len(), 2);
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+}
@@ -0,0 +1,127 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::ingest::audit::interval_duration;
+
+/// Summary of the candles stored for a symbol and interval.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Coverage {
+    /// The trading symbol.
+    pub symbol: String,
+    /// The Kline interval.
+    pub interval: String,
+    /// The dataset label.
+    pub dataset: String,
+    /// The exchange the candles were captured from.
+    pub exchange: String,
+    /// The start time of the earliest stored candle, if any.
+    pub first_start: Option<DateTime<Utc>>,
+    /// The start time of the latest stored candle, if any.
+    pub last_start: Option<DateTime<Utc>>,
+    /// The number of stored candles.
+    pub rows: i64,
+    /// The number of gaps between the earliest and latest candle, or `None` for
+    /// calendar intervals (`1w`, `1M`) whose length varies.
+    pub gaps: Option<i64>,
+}
+
+impl Coverage {
+    /// Returns true if no candles are stored.
+    pub fn is_empty(&self) -> bool {
+        self.rows == 0
+    }
+}
+
+/// Retrieves the earliest and latest stored candle, the row count, and the gap
+/// count for a symbol and interval in a single query.
+///
+/// A gap is a run of one or more missing candles between two stored ones.
+///
+/// # Arguments
+///
+/// * `pool` - The database connection pool.
+/// * `symbol` - The trading symbol.
+/// * `interval` - The Kline interval.
+/// * `dataset` - The dataset label.
+/// * `exchange` - The exchange the candles were captured from (e.g., "binance").
+pub async fn coverage(
+    pool: &sqlx::PgPool,
+    symbol: &str,
+    interval: &str,
+    dataset: &str,
+    exchange: &str,
+) -> Result<Coverage, sqlx::Error> {
+    let step = interval_duration(interval);
+    let row = sqlx::query!(
+        r#"
+        SELECT
+            MIN(start_time) AS first_start,
+            MAX(start_time) AS last_start,
+            COUNT(*) AS "rows!",
+            COUNT(*) FILTER (WHERE start_time - previous > $5) AS "gaps!"
+        FROM (
+            SELECT start_time, LAG(start_time) OVER (ORDER BY start_time) AS previous
+            FROM kline_data
+            WHERE symbol = $1 AND interval = $2 AND dataset = $3 AND exchange = $4
+        ) candles
+        "#,
+        symbol,
+        interval,
+        dataset,
+        exchange,
+        step as Option<chrono::Duration>
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(Coverage {
+        symbol: symbol.to_string(),
+        interval: interval.to_string(),
+        dataset: dataset.to_string(),
+        exchange: exchange.to_string(),
+        first_start: row.first_start,
+        last_start: row.last_start,
+        rows: row.rows,
+        gaps: step.map(|_| row.gaps),
+    })
+}
+
+/// Counts the candles of a symbol and interval that were inserted or last updated
+/// within `[since, until)`, as a measure of ingestion activity.
+///
+/// # Arguments
+///
+/// * `pool` - The database connection pool.
+/// * `symbol` - The trading symbol.
+/// * `interval` - The Kline interval.
+/// * `dataset` - The dataset label.
+/// * `exchange` - The exchange the candles were captured from (e.g., "binance").
+/// * `since` - The inclusive start of the window.
+/// * `until` - The exclusive end of the window.
+pub async fn rows_written(
+    pool: &sqlx::PgPool,
+    symbol: &str,
+    interval: &str,
+    dataset: &str,
+    exchange: &str,
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+) -> Result<i64, sqlx::Error> {
+    let written = sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) AS "written!"
+        FROM kline_data
+        WHERE symbol = $1 AND interval = $2 AND dataset = $3 AND exchange = $4
+          AND COALESCE(update_at, created_at) >= $5
+          AND COALESCE(update_at, created_at) < $6
+        "#,
+        symbol,
+        interval,
+        dataset,
+        exchange,
+        since,
+        until
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(written)
+}
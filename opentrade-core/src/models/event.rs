@@ -0,0 +1,547 @@
+//! # Normalized Events
+//!
+//! This module defines the exchange-agnostic [`NormalizedEvent`] envelope that
+//! sinks emit to downstream consumers, so that consumers are not coupled to the
+//! field names of a particular exchange (e.g., Binance's single-letter keys).
+//!
+//! The JSON form of the events is described by the JSON Schema in
+//! [`EVENT_JSON_SCHEMA`] (`opentrade-core/schemas/event.v1.schema.json`). With the
+//! `protobuf` feature, events can also be encoded as the more compact protobuf
+//! messages of `opentrade-core/schemas/event.v1.proto` (see [`EventFormat`]).
+//!
+//! ## Envelope
+//!
+//! Every event carries the [`EVENT_SCHEMA_VERSION`] it was written with, where it
+//! came from (exchange and stream), when it was received, and a payload tagged by
+//! `type`. Timestamps are milliseconds since the Unix epoch, and prices and
+//! quantities are decimal strings so that no precision is lost.
+//!
+//! ## Decimal Format
+//!
+//! Consumers that compute with prices directly, e.g. dashboards or dataframes,
+//! can have the JSON form carry prices and quantities as numbers instead, by
+//! encoding with [`DecimalFormat::Float`]. Numbers are read as 64-bit floats by
+//! most JSON parsers, which hold 15 to 17 significant digits: prices of large
+//! quote volumes or of small-cap tokens may be rounded, and trailing zeros, which
+//! show the tick size, are dropped. Float events are therefore meant for display
+//! and analysis only. Consumers that store, reconcile or replay events should
+//! keep the default [`DecimalFormat::String`]. The schema accepts both forms, and
+//! [`NormalizedEvent`] parses both back into decimal strings. Protobuf events
+//! always carry strings.
+//!
+//! ## Schema Evolution
+//!
+//! - Adding an optional field or a new payload `type` is a compatible change and
+//!   keeps the schema version. Trade and order book payloads will be added this way.
+//! - Removing or renaming a field, changing its type or meaning, or making an
+//!   optional field required is a breaking change and bumps the schema version.
+//! - Consumers must ignore unknown fields and skip events with an unknown `type`
+//!   or a `schema_version` newer than they support.
+//! - Protobuf field numbers are never reused; new fields and payloads get new ones.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use opentrade_core::data_source::websocket::IngestContext;
+//! use opentrade_core::models::SerdableKlineData;
+//! use opentrade_core::models::event::{EVENT_SCHEMA_VERSION, NormalizedEvent};
+//!
+//! # fn example(kline: &SerdableKlineData) -> serde_json::Result<()> {
+//! let context = IngestContext::new("binance", "btcusdt@kline_1m");
+//! let event = NormalizedEvent::from_kline(kline, &context);
+//! assert_eq!(event.schema_version, EVENT_SCHEMA_VERSION);
+//! println!("{}", serde_json::to_string(&event)?);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::Result;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+use crate::data_source::websocket::IngestContext;
+use crate::models::SerdableKlineData;
+
+/// The version of the normalized event schema written by this build.
+pub const EVENT_SCHEMA_VERSION: u32 = 1;
+
+/// The JSON Schema of version [`EVENT_SCHEMA_VERSION`] of the normalized events.
+pub const EVENT_JSON_SCHEMA: &str = include_str!("../../schemas/event.v1.schema.json");
+
+/// A normalized market data event. See the [module documentation](self).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct NormalizedEvent {
+    /// The schema version the event was written with.
+    pub schema_version: u32,
+    /// The exchange the data came from (e.g., "binance").
+    pub exchange: String,
+    /// The stream the data came from (e.g., "btcusdt@kline_1m").
+    pub stream: String,
+    /// When the data was received, in milliseconds since the Unix epoch.
+    pub received_at: i64,
+    /// The event data.
+    #[serde(flatten)]
+    pub payload: EventPayload,
+}
+
+/// The data of a [`NormalizedEvent`], tagged by `type`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum EventPayload {
+    /// A candle update.
+    Kline(NormalizedKline),
+}
+
+/// A candle in the normalized event schema.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct NormalizedKline {
+    /// The trading symbol (e.g., "BTCUSDT").
+    pub symbol: String,
+    /// The candle interval (e.g., "1m").
+    pub interval: String,
+    /// The start of the candle, in milliseconds since the Unix epoch.
+    pub open_time: u64,
+    /// The end of the candle, in milliseconds since the Unix epoch.
+    pub close_time: u64,
+    /// The opening price.
+    #[serde(deserialize_with = "decimal_string")]
+    pub open: String,
+    /// The highest price.
+    #[serde(deserialize_with = "decimal_string")]
+    pub high: String,
+    /// The lowest price.
+    #[serde(deserialize_with = "decimal_string")]
+    pub low: String,
+    /// The closing (or latest) price.
+    #[serde(deserialize_with = "decimal_string")]
+    pub close: String,
+    /// The traded volume in the base asset.
+    #[serde(deserialize_with = "decimal_string")]
+    pub volume: String,
+    /// The traded volume in the quote asset.
+    #[serde(deserialize_with = "decimal_string")]
+    pub quote_volume: String,
+    /// The number of trades.
+    pub trade_count: u64,
+    /// The id of the first trade in the candle.
+    pub first_trade_id: i64,
+    /// The id of the last trade in the candle.
+    pub last_trade_id: i64,
+    /// Whether the candle is closed.
+    pub is_final: bool,
+    /// When the exchange emitted the update, in milliseconds since the Unix epoch,
+    /// if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_time: Option<u64>,
+}
+
+impl NormalizedKline {
+    /// The fields holding prices and quantities, see [`DecimalFormat`].
+    pub const DECIMAL_FIELDS: [&'static str; 6] =
+        ["open", "high", "low", "close", "volume", "quote_volume"];
+}
+
+/// Deserializes a decimal given as a string or, in events encoded with
+/// [`DecimalFormat::Float`], as a number.
+fn decimal_string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Decimal {
+        String(String),
+        Number(serde_json::Number),
+    }
+    Ok(match Decimal::deserialize(deserializer)? {
+        Decimal::String(value) => value,
+        Decimal::Number(value) => value.to_string(),
+    })
+}
+
+/// How prices and quantities are represented in the JSON form of events. See
+/// [Decimal Format](self#decimal-format) for the precision tradeoffs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DecimalFormat {
+    /// Decimal strings, exactly as received (e.g., `"0.10000000"`).
+    #[default]
+    String,
+    /// JSON numbers, rounded to the nearest 64-bit float (e.g., `0.1`).
+    Float,
+}
+
+impl DecimalFormat {
+    /// Returns the JSON representation of a decimal string. Decimals that have no
+    /// finite float representation are kept as strings.
+    pub fn to_json(self, decimal: &str) -> Value {
+        match self {
+            DecimalFormat::String => Value::String(decimal.to_string()),
+            DecimalFormat::Float => decimal
+                .parse::<f64>()
+                .ok()
+                .and_then(serde_json::Number::from_f64)
+                .map_or_else(|| Value::String(decimal.to_string()), Value::Number),
+        }
+    }
+}
+
+impl fmt::Display for DecimalFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecimalFormat::String => write!(f, "string"),
+            DecimalFormat::Float => write!(f, "float"),
+        }
+    }
+}
+
+impl FromStr for DecimalFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "string" => Ok(DecimalFormat::String),
+            "float" => Ok(DecimalFormat::Float),
+            other => Err(format!(
+                "unknown decimal format '{}', expected 'string' or 'float'",
+                other
+            )),
+        }
+    }
+}
+
+/// The wire format sinks encode [`NormalizedEvent`]s in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EventFormat {
+    /// One JSON object per line.
+    #[default]
+    Json,
+    /// Length-delimited [`proto::Event`] messages.
+    #[cfg(feature = "protobuf")]
+    Protobuf,
+}
+
+impl NormalizedEvent {
+    /// Encodes the event in `format`, including its delimiter (a newline for JSON,
+    /// a varint length prefix for protobuf), ready to be appended to a stream.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the event cannot be serialized.
+    pub fn encode(&self, format: EventFormat) -> Result<Vec<u8>> {
+        self.encode_with(format, DecimalFormat::default())
+    }
+
+    /// Encodes the event like [`encode`](Self::encode), with prices and quantities
+    /// of the JSON form in `decimals`. Protobuf events always carry strings.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the event cannot be serialized.
+    pub fn encode_with(&self, format: EventFormat, decimals: DecimalFormat) -> Result<Vec<u8>> {
+        match format {
+            EventFormat::Json => {
+                let mut bytes = match decimals {
+                    DecimalFormat::String => serde_json::to_vec(self)?,
+                    DecimalFormat::Float => serde_json::to_vec(&self.to_json(decimals)?)?,
+                };
+                bytes.push(b'\n');
+                Ok(bytes)
+            }
+            #[cfg(feature = "protobuf")]
+            EventFormat::Protobuf => {
+                Ok(prost::Message::encode_length_delimited_to_vec(&proto::Event::from(self)))
+            }
+        }
+    }
+
+    /// Returns the JSON form of the event with prices and quantities in `decimals`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the event cannot be serialized.
+    pub fn to_json(&self, decimals: DecimalFormat) -> Result<Value> {
+        let mut json = serde_json::to_value(self)?;
+        let fields = match &self.payload {
+            EventPayload::Kline(_) => NormalizedKline::DECIMAL_FIELDS,
+        };
+        if let Some(object) = json.as_object_mut() {
+            for field in fields {
+                if let Some(Value::String(decimal)) = object.get(field) {
+                    let value = decimals.to_json(decimal);
+                    object.insert(field.to_string(), value);
+                }
+            }
+        }
+        Ok(json)
+    }
+
+    /// Creates an event for a candle received in `context`.
+    pub fn from_kline(kline: &SerdableKlineData, context: &IngestContext) -> Self {
+        Self {
+            schema_version: EVENT_SCHEMA_VERSION,
+            exchange: context.exchange.clone(),
+            stream: context.stream.clone(),
+            received_at: context.received_at.timestamp_millis(),
+            payload: EventPayload::Kline(NormalizedKline::from(kline)),
+        }
+    }
+}
+
+impl From<&SerdableKlineData> for NormalizedKline {
+    fn from(kline: &SerdableKlineData) -> Self {
+        Self {
+            symbol: kline.symbol.clone(),
+            interval: kline.interval.clone(),
+            open_time: kline.start_time,
+            close_time: kline.end_time,
+            open: kline.open.clone(),
+            high: kline.high.clone(),
+            low: kline.low.clone(),
+            close: kline.close.clone(),
+            volume: kline.volume.clone(),
+            quote_volume: kline.quote_volume.clone(),
+            trade_count: kline.trade_count,
+            first_trade_id: kline.first_trade_id,
+            last_trade_id: kline.last_trade_id,
+            is_final: kline.is_final,
+            event_time: kline.event_time,
+        }
+    }
+}
+
+/// Protobuf messages of `opentrade-core/schemas/event.v1.proto`.
+#[cfg(feature = "protobuf")]
+pub mod proto {
+    use super::{EventPayload, NormalizedEvent, NormalizedKline};
+
+    /// A normalized event.
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Event {
+        #[prost(uint32, tag = "1")]
+        pub schema_version: u32,
+        #[prost(string, tag = "2")]
+        pub exchange: String,
+        #[prost(string, tag = "3")]
+        pub stream: String,
+        #[prost(int64, tag = "4")]
+        pub received_at: i64,
+        #[prost(oneof = "event::Payload", tags = "5")]
+        pub payload: Option<event::Payload>,
+    }
+
+    /// Nested types of [`Event`].
+    pub mod event {
+        /// The data of an [`Event`](super::Event).
+        #[derive(Clone, PartialEq, prost::Oneof)]
+        pub enum Payload {
+            #[prost(message, tag = "5")]
+            Kline(super::Kline),
+        }
+    }
+
+    /// A candle.
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Kline {
+        #[prost(string, tag = "1")]
+        pub symbol: String,
+        #[prost(string, tag = "2")]
+        pub interval: String,
+        #[prost(uint64, tag = "3")]
+        pub open_time: u64,
+        #[prost(uint64, tag = "4")]
+        pub close_time: u64,
+        #[prost(string, tag = "5")]
+        pub open: String,
+        #[prost(string, tag = "6")]
+        pub high: String,
+        #[prost(string, tag = "7")]
+        pub low: String,
+        #[prost(string, tag = "8")]
+        pub close: String,
+        #[prost(string, tag = "9")]
+        pub volume: String,
+        #[prost(string, tag = "10")]
+        pub quote_volume: String,
+        #[prost(uint64, tag = "11")]
+        pub trade_count: u64,
+        #[prost(int64, tag = "12")]
+        pub first_trade_id: i64,
+        #[prost(int64, tag = "13")]
+        pub last_trade_id: i64,
+        #[prost(bool, tag = "14")]
+        pub is_final: bool,
+        #[prost(uint64, optional, tag = "15")]
+        pub event_time: Option<u64>,
+    }
+
+    impl From<&NormalizedEvent> for Event {
+        fn from(event: &NormalizedEvent) -> Self {
+            let payload = match &event.payload {
+                EventPayload::Kline(kline) => event::Payload::Kline(Kline {
+                    symbol: kline.symbol.clone(),
+                    interval: kline.interval.clone(),
+                    open_time: kline.open_time,
+                    close_time: kline.close_time,
+                    open: kline.open.clone(),
+                    high: kline.high.clone(),
+                    low: kline.low.clone(),
+                    close: kline.close.clone(),
+                    volume: kline.volume.clone(),
+                    quote_volume: kline.quote_volume.clone(),
+                    trade_count: kline.trade_count,
+                    first_trade_id: kline.first_trade_id,
+                    last_trade_id: kline.last_trade_id,
+                    is_final: kline.is_final,
+                    event_time: kline.event_time,
+                }),
+            };
+            Self {
+                schema_version: event.schema_version,
+                exchange: event.exchange.clone(),
+                stream: event.stream.clone(),
+                received_at: event.received_at,
+                payload: Some(payload),
+            }
+        }
+    }
+
+    impl TryFrom<Event> for NormalizedEvent {
+        type Error = anyhow::Error;
+
+        /// Fails on events without a payload, e.g. events with a payload type
+        /// added after this build, which consumers should skip.
+        fn try_from(event: Event) -> anyhow::Result<Self> {
+            let payload = match event.payload {
+                Some(event::Payload::Kline(kline)) => EventPayload::Kline(NormalizedKline {
+                    symbol: kline.symbol,
+                    interval: kline.interval,
+                    open_time: kline.open_time,
+                    close_time: kline.close_time,
+                    open: kline.open,
+                    high: kline.high,
+                    low: kline.low,
+                    close: kline.close,
+                    volume: kline.volume,
+                    quote_volume: kline.quote_volume,
+                    trade_count: kline.trade_count,
+                    first_trade_id: kline.first_trade_id,
+                    last_trade_id: kline.last_trade_id,
+                    is_final: kline.is_final,
+                    event_time: kline.event_time,
+                }),
+                None => anyhow::bail!("Event has no payload of a known type"),
+            };
+            Ok(Self {
+                schema_version: event.schema_version,
+                exchange: event.exchange,
+                stream: event.stream,
+                received_at: event.received_at,
+                payload,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kline() -> SerdableKlineData {
+        SerdableKlineData {
+            start_time: 1_640_995_200_000,
+            end_time: 1_640_995_259_999,
+            symbol: "BTCUSDT".to_string(),
+            interval: "1m".to_string(),
+            first_trade_id: 100,
+            last_trade_id: 200,
+            open: "46000.00".to_string(),
+            close: "46100.00".to_string(),
+            high: "46200.00".to_string(),
+            low: "45900.00".to_string(),
+            volume: "12.5".to_string(),
+            trade_count: 101,
+            quote_volume: "576250.0".to_string(),
+            event_time: Some(1_640_995_230_000),
+            is_final: false,
+            exchange: None,
+        }
+    }
+
+    fn event() -> NormalizedEvent {
+        NormalizedEvent::from_kline(&kline(), &IngestContext::new("binance", "btcusdt@kline_1m"))
+    }
+
+    #[test]
+    fn test_kline_event_round_trips() {
+        let event = event();
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["type"], "kline");
+        assert_eq!(json["schema_version"], EVENT_SCHEMA_VERSION);
+        assert_eq!(json["open_time"], 1_640_995_200_000u64);
+        assert_eq!(json["close"], "46100.00");
+
+        let parsed: NormalizedEvent = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, event);
+    }
+
+    #[test]
+    fn test_unknown_fields_are_ignored() {
+        let mut json = serde_json::to_value(event()).unwrap();
+        json["added_in_a_later_release"] = serde_json::json!(true);
+        assert_eq!(serde_json::from_value::<NormalizedEvent>(json).unwrap(), event());
+    }
+
+    #[test]
+    fn test_json_encoding_is_newline_delimited() {
+        let bytes = event().encode(EventFormat::Json).unwrap();
+        assert_eq!(bytes.last(), Some(&b'\n'));
+        let parsed: NormalizedEvent = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(parsed, event());
+    }
+
+    #[test]
+    fn test_float_decimals_round_trip_as_numbers() {
+        let bytes = event()
+            .encode_with(EventFormat::Json, DecimalFormat::Float)
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(json["close"], 46100.0);
+        assert_eq!(json["symbol"], "BTCUSDT");
+
+        let parsed: NormalizedEvent = serde_json::from_value(json).unwrap();
+        let EventPayload::Kline(kline) = parsed.payload;
+        assert_eq!(kline.close, "46100.0");
+        assert_eq!(kline.volume, "12.5");
+        assert_eq!("float".parse(), Ok(DecimalFormat::Float));
+    }
+
+    #[cfg(feature = "protobuf")]
+    #[test]
+    fn test_protobuf_encoding_round_trips_and_is_smaller() {
+        use prost::Message;
+
+        let bytes = event().encode(EventFormat::Protobuf).unwrap();
+        let decoded = proto::Event::decode_length_delimited(bytes.as_slice()).unwrap();
+        assert_eq!(NormalizedEvent::try_from(decoded).unwrap(), event());
+        assert!(bytes.len() < event().encode(EventFormat::Json).unwrap().len());
+    }
+
+    #[test]
+    fn test_json_schema_lists_serialized_fields() {
+        let schema: serde_json::Value = serde_json::from_str(EVENT_JSON_SCHEMA).unwrap();
+        assert_eq!(
+            schema["properties"]["schema_version"]["const"],
+            EVENT_SCHEMA_VERSION
+        );
+        let kline_properties = &schema["$defs"]["kline"]["properties"];
+        let json = serde_json::to_value(event()).unwrap();
+        for field in json.as_object().unwrap().keys() {
+            assert!(
+                schema["properties"].get(field).is_some() || kline_properties.get(field).is_some(),
+                "field {} is missing from the JSON schema",
+                field
+            );
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+
+/// A range for which the exchange returned no candles, stored in `exchange_gaps`.
+///
+/// Exchange-side gaps (pre-listing periods, maintenance windows) are expected
+/// and cannot be repaired by re-fetching, unlike gaps caused by ingestion failures.
+#[derive(FromRow, Debug, Clone, PartialEq, Eq)]
+pub struct ExchangeGap {
+    /// The trading symbol.
+    pub symbol: String,
+    /// The Kline interval.
+    pub interval: String,
+    /// The dataset label.
+    pub dataset: String,
+    /// The exchange that returned no candles.
+    pub exchange: String,
+    /// The inclusive start of the empty range.
+    pub start_time: DateTime<Utc>,
+    /// The exclusive end of the empty range.
+    pub end_time: DateTime<Utc>,
+    /// The timestamp when the gap was recorded.
+    pub recorded_at: DateTime<Utc>,
+}
+
+impl ExchangeGap {
+    /// Records an empty range reported by the exchange.
+    ///
+    /// If a gap starting at the same time was already recorded, it is extended to
+    /// the later end time.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `symbol` - The trading symbol.
+    /// * `interval` - The Kline interval.
+    /// * `dataset` - The dataset label.
+    /// * `exchange` - The exchange the candles are fetched from (e.g., "binance").
+    /// * `start_time` - The inclusive start of the empty range.
+    /// * `end_time` - The exclusive end of the empty range.
+    pub async fn record(
+        pool: &sqlx::PgPool,
+        symbol: &str,
+        interval: &str,
+        dataset: &str,
+        exchange: &str,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<Self, sqlx::Error> {
+        let gap = sqlx::query_as!(
+            ExchangeGap,
+            r#"
+            INSERT INTO exchange_gaps (symbol, interval, dataset, exchange, start_time, end_time)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (symbol, interval, dataset, exchange, start_time) DO UPDATE
+            SET
+                end_time = GREATEST(exchange_gaps.end_time, EXCLUDED.end_time),
+                recorded_at = NOW()
+            RETURNING *
+            "#,
+            symbol,
+            interval,
+            dataset,
+            exchange,
+            start_time,
+            end_time
+        )
+        .fetch_one(pool)
+        .await?;
+        Ok(gap)
+    }
+
+    /// Lists the recorded gaps overlapping `[start_time, end_time)`, oldest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `symbol` - The trading symbol.
+    /// * `interval` - The Kline interval.
+    /// * `dataset` - The dataset label.
+    /// * `exchange` - The exchange the candles are fetched from (e.g., "binance").
+    /// * `start_time` - The inclusive start of the range.
+    /// * `end_time` - The exclusive end of the range.
+    pub async fn list_range(
+        pool: &sqlx::PgPool,
+        symbol: &str,
+        interval: &str,
+        dataset: &str,
+        exchange: &str,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        let gaps = sqlx::query_as!(
+            ExchangeGap,
+            r#"
+            SELECT * FROM exchange_gaps
+            WHERE symbol = $1 AND interval = $2 AND dataset = $3 AND exchange = $4
+              AND start_time < $6 AND end_time > $5
+            ORDER BY start_time
+            "#,
+            symbol,
+            interval,
+            dataset,
+            exchange,
+            start_time,
+            end_time
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(gaps)
+    }
+}
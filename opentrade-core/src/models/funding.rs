@@ -0,0 +1,278 @@
+use std::ops::Range;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use sqlx::types::BigDecimal as Decimal;
+
+use crate::models::DEFAULT_DATASET;
+use crate::models::metrics::StatementTimer;
+use crate::models::trace;
+
+/// A funding rate as returned by the `/fapi/v1/fundingRate` endpoint of Binance
+/// USDⓈ-M futures.
+///
+/// Numeric fields are strings to preserve precision, like [`SerdableKlineData`](super::SerdableKlineData).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SerdableFundingRate {
+    /// The perpetual contract symbol (e.g., "BTCUSDT").
+    pub symbol: String,
+    /// The funding time in milliseconds since the UNIX epoch.
+    #[serde(rename = "fundingTime")]
+    pub funding_time: i64,
+    /// The funding rate, as a fraction of the position notional.
+    #[serde(rename = "fundingRate")]
+    pub funding_rate: String,
+    /// The mark price the funding was settled at, empty for old settlements.
+    #[serde(rename = "markPrice", default)]
+    pub mark_price: String,
+}
+
+impl SerdableFundingRate {
+    /// Converts the funding rate into a row of `funding_rates` under the
+    /// [`DEFAULT_DATASET`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the funding time or a decimal string is invalid.
+    pub fn to_funding_rate(&self) -> anyhow::Result<FundingRate> {
+        let funding_time = crate::time::from_millis(self.funding_time)?;
+        let funding_rate = self
+            .funding_rate
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid funding rate: {}", self.funding_rate))?;
+        let mark_price = match self.mark_price.as_str() {
+            "" => None,
+            value => Some(
+                value
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("Invalid mark price: {}", value))?,
+            ),
+        };
+        Ok(FundingRate {
+            symbol: self.symbol.clone(),
+            dataset: DEFAULT_DATASET.to_string(),
+            funding_time,
+            funding_rate,
+            mark_price,
+        })
+    }
+}
+
+/// A funding rate settled on a perpetual futures contract, stored in `funding_rates`.
+///
+/// A positive rate is paid by long positions to short positions, as a fraction of
+/// the position notional, at the funding time.
+#[derive(FromRow, Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FundingRate {
+    /// The perpetual contract symbol (e.g., "BTCUSDT").
+    pub symbol: String,
+    /// The dataset label.
+    pub dataset: String,
+    /// The time the funding was settled.
+    pub funding_time: DateTime<Utc>,
+    /// The funding rate, as a fraction of the position notional.
+    pub funding_rate: Decimal,
+    /// The mark price the funding was settled at, if known.
+    pub mark_price: Option<Decimal>,
+}
+
+impl FundingRate {
+    /// Assigns the funding rate to a dataset.
+    pub fn with_dataset(mut self, dataset: &str) -> Self {
+        self.dataset = dataset.to_string();
+        self
+    }
+
+    /// Stores the funding rate, replacing a previous value for the same time.
+    ///
+    /// # Arguments
+    ///
+    /// * `executor` - The database connection pool, or a connection or transaction.
+    pub async fn upsert<'e, E>(&self, executor: E) -> Result<(), sqlx::Error>
+    where
+        E: sqlx::PgExecutor<'e>,
+    {
+        let _timer = StatementTimer::start("funding_rates.upsert");
+        sqlx::query!(
+            r#"
+            INSERT INTO funding_rates (symbol, dataset, funding_time, funding_rate, mark_price)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (symbol, dataset, funding_time) DO UPDATE
+            SET funding_rate = EXCLUDED.funding_rate, mark_price = EXCLUDED.mark_price
+            "#,
+            self.symbol,
+            self.dataset,
+            self.funding_time,
+            self.funding_rate,
+            self.mark_price
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Stores many funding rates in one transaction.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `rates` - The funding rates to store.
+    ///
+    /// # Returns
+    ///
+    /// The number of stored funding rates.
+    pub async fn upsert_batch(pool: &sqlx::PgPool, rates: &[Self]) -> Result<usize, sqlx::Error> {
+        let _timer = StatementTimer::start("funding_rates.upsert_batch");
+        let mut tx = trace::begin(pool).await?;
+        for rate in rates {
+            rate.upsert(&mut *tx).await?;
+        }
+        tx.commit().await?;
+        Ok(rates.len())
+    }
+
+    /// Retrieves the funding rate of a contract settled at a given time.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `symbol` - The perpetual contract symbol.
+    /// * `dataset` - The dataset label.
+    /// * `funding_time` - The time the funding was settled.
+    pub async fn get(
+        pool: &sqlx::PgPool,
+        symbol: &str,
+        dataset: &str,
+        funding_time: DateTime<Utc>,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        let _timer = StatementTimer::start("funding_rates.get");
+        let rate = sqlx::query_as!(
+            FundingRate,
+            r#"
+            SELECT * FROM funding_rates
+            WHERE symbol = $1 AND dataset = $2 AND funding_time = $3
+            "#,
+            symbol,
+            dataset,
+            funding_time
+        )
+        .fetch_optional(pool)
+        .await?;
+        Ok(rate)
+    }
+
+    /// Retrieves the time of the latest stored funding rate of a contract, or
+    /// `None` if no rates are stored.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `symbol` - The perpetual contract symbol.
+    /// * `dataset` - The dataset label.
+    pub async fn latest_funding_time(
+        pool: &sqlx::PgPool,
+        symbol: &str,
+        dataset: &str,
+    ) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+        let _timer = StatementTimer::start("funding_rates.latest_funding_time");
+        sqlx::query_scalar!(
+            r#"
+            SELECT MAX(funding_time) FROM funding_rates
+            WHERE symbol = $1 AND dataset = $2
+            "#,
+            symbol,
+            dataset
+        )
+        .fetch_one(pool)
+        .await
+    }
+
+    /// Deletes the funding rates of a contract settled in a range.
+    ///
+    /// # Arguments
+    ///
+    /// * `executor` - The database connection pool, or a connection or transaction.
+    /// * `symbol` - The perpetual contract symbol.
+    /// * `dataset` - The dataset label.
+    /// * `range` - The range of funding times to delete.
+    ///
+    /// # Returns
+    ///
+    /// The number of deleted funding rates.
+    pub async fn delete_range<'e, E>(
+        executor: E,
+        symbol: &str,
+        dataset: &str,
+        range: Range<DateTime<Utc>>,
+    ) -> Result<u64, sqlx::Error>
+    where
+        E: sqlx::PgExecutor<'e>,
+    {
+        let _timer = StatementTimer::start("funding_rates.delete_range");
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM funding_rates
+            WHERE symbol = $1 AND dataset = $2 AND funding_time >= $3 AND funding_time < $4
+            "#,
+            symbol,
+            dataset,
+            range.start,
+            range.end
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Lists the funding rates settled in `[start_time, end_time)`, oldest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `symbol` - The perpetual contract symbol.
+    /// * `dataset` - The dataset label.
+    /// * `start_time` - The inclusive start of the range.
+    /// * `end_time` - The exclusive end of the range.
+    pub async fn list_range(
+        pool: &sqlx::PgPool,
+        symbol: &str,
+        dataset: &str,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        let _timer = StatementTimer::start("funding_rates.list_range");
+        let rates = sqlx::query_as!(
+            FundingRate,
+            r#"
+            SELECT * FROM funding_rates
+            WHERE symbol = $1 AND dataset = $2 AND funding_time >= $3 AND funding_time < $4
+            ORDER BY funding_time
+            "#,
+            symbol,
+            dataset,
+            start_time,
+            end_time
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(rates)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_funding_rate() {
+        let json = r#"[{"symbol":"BTCUSDT","fundingTime":1698768000000,"fundingRate":"0.00010000","markPrice":"34287.54619963"},{"symbol":"BTCUSDT","fundingTime":1570608000000,"fundingRate":"-0.00030000","markPrice":""}]"#;
+        let rates: Vec<SerdableFundingRate> = serde_json::from_str(json).unwrap();
+        let rate = rates[0].to_funding_rate().unwrap().with_dataset("research");
+        assert_eq!(rate.dataset, "research");
+        assert_eq!(rate.funding_time.timestamp_millis(), 1698768000000);
+        assert_eq!(rate.funding_rate, "0.0001".parse::<Decimal>().unwrap());
+        assert!(rate.mark_price.is_some());
+        assert_eq!(rates[1].to_funding_rate().unwrap().mark_price, None);
+    }
+}
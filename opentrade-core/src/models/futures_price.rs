@@ -0,0 +1,267 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use sqlx::types::BigDecimal as Decimal;
+
+use crate::models::DEFAULT_DATASET;
+use crate::models::metrics::StatementTimer;
+
+/// The `kind` of rows holding mark prices.
+pub const MARK_PRICE: &str = "mark";
+
+/// The `kind` of rows holding index prices.
+pub const INDEX_PRICE: &str = "index";
+
+/// A mark price update of a USDⓈ-M futures contract, as sent by the exchange.
+///
+/// Numeric fields are strings to preserve precision, like [`SerdableKlineData`](super::SerdableKlineData).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MarkPriceUpdate {
+    /// The time the exchange generated the event (Unix timestamp in milliseconds).
+    #[serde(rename = "E")]
+    pub event_time: u64,
+    /// The contract symbol (e.g., "BTCUSDT").
+    #[serde(rename = "s")]
+    pub symbol: String,
+    /// The mark price.
+    #[serde(rename = "p")]
+    pub mark_price: String,
+    /// The index price.
+    #[serde(rename = "i")]
+    pub index_price: String,
+    /// The estimated settle price, only useful in the last hour before settlement.
+    #[serde(rename = "P")]
+    pub estimated_settle_price: String,
+    /// The current funding rate.
+    #[serde(rename = "r")]
+    pub funding_rate: String,
+    /// The next funding time (Unix timestamp in milliseconds).
+    #[serde(rename = "T")]
+    pub next_funding_time: u64,
+}
+
+/// An index price update of a futures pair, as sent by the exchange.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct IndexPriceUpdate {
+    /// The time the exchange generated the event (Unix timestamp in milliseconds).
+    #[serde(rename = "E")]
+    pub event_time: u64,
+    /// The index pair (e.g., "BTCUSD").
+    #[serde(rename = "i")]
+    pub pair: String,
+    /// The index price.
+    #[serde(rename = "p")]
+    pub index_price: String,
+}
+
+/// A futures price update, tagged by the event type field `e` of the exchange payload.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "e")]
+pub enum PriceUpdate {
+    /// A `markPriceUpdate` event.
+    #[serde(rename = "markPriceUpdate")]
+    MarkPrice(MarkPriceUpdate),
+    /// An `indexPriceUpdate` event.
+    #[serde(rename = "indexPriceUpdate")]
+    IndexPrice(IndexPriceUpdate),
+}
+
+impl PriceUpdate {
+    /// Returns the contract symbol or index pair of the update.
+    pub fn symbol(&self) -> &str {
+        match self {
+            PriceUpdate::MarkPrice(update) => &update.symbol,
+            PriceUpdate::IndexPrice(update) => &update.pair,
+        }
+    }
+
+    /// Converts the update into a row of `futures_prices` under the [`DEFAULT_DATASET`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a timestamp or decimal string cannot be parsed.
+    pub fn to_futures_price(&self) -> anyhow::Result<FuturesPrice> {
+        fn timestamp(value: u64) -> anyhow::Result<DateTime<Utc>> {
+            i64::try_from(value)
+                .ok()
+                .and_then(DateTime::from_timestamp_millis)
+                .ok_or_else(|| anyhow::anyhow!("Invalid timestamp: {}", value))
+        }
+        fn decimal(value: &str) -> anyhow::Result<Decimal> {
+            value
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid decimal: {}", value))
+        }
+
+        let price = match self {
+            PriceUpdate::MarkPrice(update) => FuturesPrice {
+                symbol: update.symbol.clone(),
+                dataset: DEFAULT_DATASET.to_string(),
+                kind: MARK_PRICE.to_string(),
+                event_time: timestamp(update.event_time)?,
+                price: decimal(&update.mark_price)?,
+                index_price: Some(decimal(&update.index_price)?),
+                estimated_settle_price: Some(decimal(&update.estimated_settle_price)?),
+                funding_rate: Some(decimal(&update.funding_rate)?),
+                next_funding_time: Some(timestamp(update.next_funding_time)?),
+            },
+            PriceUpdate::IndexPrice(update) => FuturesPrice {
+                symbol: update.pair.clone(),
+                dataset: DEFAULT_DATASET.to_string(),
+                kind: INDEX_PRICE.to_string(),
+                event_time: timestamp(update.event_time)?,
+                price: decimal(&update.index_price)?,
+                index_price: None,
+                estimated_settle_price: None,
+                funding_rate: None,
+                next_funding_time: None,
+            },
+        };
+        Ok(price)
+    }
+}
+
+/// A mark or index price of a futures contract, stored in `futures_prices`.
+#[derive(FromRow, Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FuturesPrice {
+    /// The contract symbol or index pair.
+    pub symbol: String,
+    /// The dataset label.
+    pub dataset: String,
+    /// [`MARK_PRICE`] or [`INDEX_PRICE`].
+    pub kind: String,
+    /// The time the exchange generated the price.
+    pub event_time: DateTime<Utc>,
+    /// The mark or index price.
+    pub price: Decimal,
+    /// The index price reported with a mark price.
+    pub index_price: Option<Decimal>,
+    /// The estimated settle price reported with a mark price.
+    pub estimated_settle_price: Option<Decimal>,
+    /// The funding rate reported with a mark price.
+    pub funding_rate: Option<Decimal>,
+    /// The next funding time reported with a mark price.
+    pub next_funding_time: Option<DateTime<Utc>>,
+}
+
+impl FuturesPrice {
+    /// Assigns the price to a dataset.
+    pub fn with_dataset(mut self, dataset: &str) -> Self {
+        self.dataset = dataset.to_string();
+        self
+    }
+
+    /// Stores the price, replacing a previous value for the same event time.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    pub async fn upsert(&self, pool: &sqlx::PgPool) -> Result<(), sqlx::Error> {
+        let _timer = StatementTimer::start("futures_prices.upsert");
+        sqlx::query!(
+            r#"
+            INSERT INTO futures_prices (
+                symbol, dataset, kind, event_time, price, index_price,
+                estimated_settle_price, funding_rate, next_funding_time
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            ON CONFLICT (symbol, dataset, kind, event_time) DO UPDATE
+            SET
+                price = EXCLUDED.price,
+                index_price = EXCLUDED.index_price,
+                estimated_settle_price = EXCLUDED.estimated_settle_price,
+                funding_rate = EXCLUDED.funding_rate,
+                next_funding_time = EXCLUDED.next_funding_time
+            "#,
+            self.symbol,
+            self.dataset,
+            self.kind,
+            self.event_time,
+            self.price,
+            self.index_price,
+            self.estimated_settle_price,
+            self.funding_rate,
+            self.next_funding_time
+        )
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Lists the prices of one kind generated in `[start_time, end_time)`, oldest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `symbol` - The contract symbol or index pair.
+    /// * `kind` - [`MARK_PRICE`] or [`INDEX_PRICE`].
+    /// * `dataset` - The dataset label.
+    /// * `start_time` - The inclusive start of the range.
+    /// * `end_time` - The exclusive end of the range.
+    pub async fn list_range(
+        pool: &sqlx::PgPool,
+        symbol: &str,
+        kind: &str,
+        dataset: &str,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        let _timer = StatementTimer::start("futures_prices.list_range");
+        let prices = sqlx::query_as!(
+            FuturesPrice,
+            r#"
+            SELECT * FROM futures_prices
+            WHERE symbol = $1 AND kind = $2 AND dataset = $3
+              AND event_time >= $4 AND event_time < $5
+            ORDER BY event_time
+            "#,
+            symbol,
+            kind,
+            dataset,
+            start_time,
+            end_time
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(prices)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mark_price_update() {
+        let json = r#"{"e":"markPriceUpdate","E":1562305380000,"s":"BTCUSDT","p":"11794.15000000","i":"11784.62659091","P":"11784.25641265","r":"0.00038167","T":1562306400000}"#;
+        let update: PriceUpdate = serde_json::from_str(json).unwrap();
+        assert_eq!(update.symbol(), "BTCUSDT");
+
+        let price = update.to_futures_price().unwrap();
+        assert_eq!(price.kind, MARK_PRICE);
+        assert_eq!(price.price, "11794.15".parse::<Decimal>().unwrap());
+        assert_eq!(price.funding_rate, Some("0.00038167".parse().unwrap()));
+        assert_eq!(
+            price.next_funding_time,
+            DateTime::from_timestamp_millis(1562306400000)
+        );
+    }
+
+    #[test]
+    fn test_parse_index_price_update() {
+        let json = r#"{"e":"indexPriceUpdate","E":1591261236000,"i":"BTCUSD","p":"9636.57860000"}"#;
+        let update: PriceUpdate = serde_json::from_str(json).unwrap();
+        let price = update.to_futures_price().unwrap().with_dataset("research");
+        assert_eq!(price.kind, INDEX_PRICE);
+        assert_eq!(price.symbol, "BTCUSD");
+        assert_eq!(price.dataset, "research");
+        assert_eq!(price.index_price, None);
+    }
+
+    #[test]
+    fn test_invalid_price_is_an_error() {
+        let json = r#"{"e":"indexPriceUpdate","E":1591261236000,"i":"BTCUSD","p":"n/a"}"#;
+        let update: PriceUpdate = serde_json::from_str(json).unwrap();
+        assert!(update.to_futures_price().is_err());
+    }
+}
@@ -0,0 +1,278 @@
+use std::fmt;
+use std::str::FromStr;
+
+use binance_spot_connector_rust::market::klines::KlineInterval;
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate, Utc};
+
+/// A Kline interval, labeled throughout the crate and in the database as "1m",
+/// "4h", "1w", "1M" and so on.
+///
+/// Intervals of up to 3 days open at multiples of their length since the Unix
+/// epoch. Weekly candles open on Mondays and monthly candles on the first day of
+/// the month, both at 00:00 UTC, like those of Binance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Interval {
+    Seconds1,
+    Minutes1,
+    Minutes3,
+    Minutes5,
+    Minutes15,
+    Minutes30,
+    Hours1,
+    Hours2,
+    Hours4,
+    Hours6,
+    Hours8,
+    Hours12,
+    Days1,
+    Days3,
+    Weeks1,
+    Months1,
+}
+
+/// The error returned when parsing an unknown interval label.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseIntervalError {
+    /// The label that failed to parse.
+    pub interval: String,
+}
+
+impl fmt::Display for ParseIntervalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let supported: Vec<&str> = Interval::ALL.iter().map(Interval::as_str).collect();
+        write!(
+            f,
+            "unsupported interval {} (supported: {})",
+            self.interval,
+            supported.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for ParseIntervalError {}
+
+impl Interval {
+    /// Every interval, shortest first.
+    pub const ALL: [Interval; 16] = [
+        Interval::Seconds1,
+        Interval::Minutes1,
+        Interval::Minutes3,
+        Interval::Minutes5,
+        Interval::Minutes15,
+        Interval::Minutes30,
+        Interval::Hours1,
+        Interval::Hours2,
+        Interval::Hours4,
+        Interval::Hours6,
+        Interval::Hours8,
+        Interval::Hours12,
+        Interval::Days1,
+        Interval::Days3,
+        Interval::Weeks1,
+        Interval::Months1,
+    ];
+
+    /// Returns the label of the interval (e.g., "1m").
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Interval::Seconds1 => "1s",
+            Interval::Minutes1 => "1m",
+            Interval::Minutes3 => "3m",
+            Interval::Minutes5 => "5m",
+            Interval::Minutes15 => "15m",
+            Interval::Minutes30 => "30m",
+            Interval::Hours1 => "1h",
+            Interval::Hours2 => "2h",
+            Interval::Hours4 => "4h",
+            Interval::Hours6 => "6h",
+            Interval::Hours8 => "8h",
+            Interval::Hours12 => "12h",
+            Interval::Days1 => "1d",
+            Interval::Days3 => "3d",
+            Interval::Weeks1 => "1w",
+            Interval::Months1 => "1M",
+        }
+    }
+
+    /// Returns the length of the interval, or `None` for months, whose length
+    /// varies.
+    pub fn duration(&self) -> Option<Duration> {
+        let duration = match self {
+            Interval::Seconds1 => Duration::seconds(1),
+            Interval::Minutes1 => Duration::minutes(1),
+            Interval::Minutes3 => Duration::minutes(3),
+            Interval::Minutes5 => Duration::minutes(5),
+            Interval::Minutes15 => Duration::minutes(15),
+            Interval::Minutes30 => Duration::minutes(30),
+            Interval::Hours1 => Duration::hours(1),
+            Interval::Hours2 => Duration::hours(2),
+            Interval::Hours4 => Duration::hours(4),
+            Interval::Hours6 => Duration::hours(6),
+            Interval::Hours8 => Duration::hours(8),
+            Interval::Hours12 => Duration::hours(12),
+            Interval::Days1 => Duration::days(1),
+            Interval::Days3 => Duration::days(3),
+            Interval::Weeks1 => Duration::weeks(1),
+            Interval::Months1 => return None,
+        };
+        Some(duration)
+    }
+
+    /// Returns `true` for the calendar intervals, `1w` and `1M`, whose candles do
+    /// not open at multiples of their length since the Unix epoch.
+    pub fn is_calendar(&self) -> bool {
+        matches!(self, Interval::Weeks1 | Interval::Months1)
+    }
+
+    /// Returns the start of the candle containing `time`.
+    pub fn start_of(&self, time: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            Interval::Weeks1 => {
+                let days = i64::from(time.weekday().num_days_from_monday());
+                midnight(time.date_naive()) - Duration::days(days)
+            }
+            Interval::Months1 => midnight(time.date_naive().with_day(1).unwrap_or_default()),
+            _ => crate::time::align_down(time, self.duration().unwrap_or_default()),
+        }
+    }
+
+    /// Returns the start of the candle after the one opening at `start`.
+    pub fn next_start(&self, start: DateTime<Utc>) -> DateTime<Utc> {
+        match self.duration() {
+            Some(step) => start + step,
+            None => start.checked_add_months(Months::new(1)).unwrap_or(start),
+        }
+    }
+
+    /// Returns the end time of the candle opening at `start`, one millisecond
+    /// before the next candle opens.
+    pub fn end_of(&self, start: DateTime<Utc>) -> DateTime<Utc> {
+        self.next_start(start) - Duration::milliseconds(1)
+    }
+
+    /// Returns the Binance interval, or `None` for `1s`, which the Binance
+    /// connector does not support.
+    pub fn to_binance(&self) -> Option<KlineInterval> {
+        let interval = match self {
+            Interval::Seconds1 => return None,
+            Interval::Minutes1 => KlineInterval::Minutes1,
+            Interval::Minutes3 => KlineInterval::Minutes3,
+            Interval::Minutes5 => KlineInterval::Minutes5,
+            Interval::Minutes15 => KlineInterval::Minutes15,
+            Interval::Minutes30 => KlineInterval::Minutes30,
+            Interval::Hours1 => KlineInterval::Hours1,
+            Interval::Hours2 => KlineInterval::Hours2,
+            Interval::Hours4 => KlineInterval::Hours4,
+            Interval::Hours6 => KlineInterval::Hours6,
+            Interval::Hours8 => KlineInterval::Hours8,
+            Interval::Hours12 => KlineInterval::Hours12,
+            Interval::Days1 => KlineInterval::Days1,
+            Interval::Days3 => KlineInterval::Days3,
+            Interval::Weeks1 => KlineInterval::Weeks1,
+            Interval::Months1 => KlineInterval::Months1,
+        };
+        Some(interval)
+    }
+}
+
+/// Returns 00:00 UTC of a date.
+fn midnight(date: NaiveDate) -> DateTime<Utc> {
+    date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc()
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Interval {
+    type Err = ParseIntervalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Interval::ALL
+            .into_iter()
+            .find(|interval| interval.as_str() == s)
+            .ok_or_else(|| ParseIntervalError {
+                interval: s.to_string(),
+            })
+    }
+}
+
+impl From<KlineInterval> for Interval {
+    fn from(interval: KlineInterval) -> Self {
+        match interval {
+            KlineInterval::Minutes1 => Interval::Minutes1,
+            KlineInterval::Minutes3 => Interval::Minutes3,
+            KlineInterval::Minutes5 => Interval::Minutes5,
+            KlineInterval::Minutes15 => Interval::Minutes15,
+            KlineInterval::Minutes30 => Interval::Minutes30,
+            KlineInterval::Hours1 => Interval::Hours1,
+            KlineInterval::Hours2 => Interval::Hours2,
+            KlineInterval::Hours4 => Interval::Hours4,
+            KlineInterval::Hours6 => Interval::Hours6,
+            KlineInterval::Hours8 => Interval::Hours8,
+            KlineInterval::Hours12 => Interval::Hours12,
+            KlineInterval::Days1 => Interval::Days1,
+            KlineInterval::Days3 => Interval::Days3,
+            KlineInterval::Weeks1 => Interval::Weeks1,
+            KlineInterval::Months1 => Interval::Months1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(value: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(value)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_labels_roundtrip() {
+        for interval in Interval::ALL {
+            assert_eq!(interval.to_string().parse::<Interval>(), Ok(interval));
+            if let Some(binance) = interval.to_binance() {
+                assert_eq!(binance.to_string(), interval.as_str());
+                assert_eq!(Interval::from(binance), interval);
+            }
+        }
+        assert!("1y".parse::<Interval>().is_err());
+        assert!("1H".parse::<Interval>().is_err());
+    }
+
+    #[test]
+    fn test_calendar_intervals() {
+        // 2024-01-10 is a Wednesday.
+        let now = time("2024-01-10T15:30:00Z");
+        let week = Interval::Weeks1.start_of(now);
+        assert_eq!(week, time("2024-01-08T00:00:00Z"));
+        assert_eq!(
+            Interval::Weeks1.next_start(week),
+            time("2024-01-15T00:00:00Z")
+        );
+
+        let month = Interval::Months1.start_of(time("2024-02-29T23:59:59Z"));
+        assert_eq!(month, time("2024-02-01T00:00:00Z"));
+        assert_eq!(
+            Interval::Months1.end_of(month),
+            time("2024-02-29T23:59:59.999Z")
+        );
+        assert!(Interval::Months1.duration().is_none());
+    }
+
+    #[test]
+    fn test_fixed_intervals_align_to_the_epoch() {
+        let now = time("2024-01-10T15:30:00Z");
+        assert_eq!(Interval::Hours4.start_of(now), time("2024-01-10T12:00:00Z"));
+        assert_eq!(Interval::Days3.start_of(now), time("2024-01-09T00:00:00Z"));
+        assert_eq!(
+            Interval::Minutes15.end_of(time("2024-01-10T15:30:00Z")),
+            time("2024-01-10T15:44:59.999Z")
+        );
+        assert!(!Interval::Days3.is_calendar());
+    }
+}
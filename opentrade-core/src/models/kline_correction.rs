@@ -0,0 +1,140 @@
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+use sqlx::types::BigDecimal as Decimal;
+
+use crate::models::KlineData;
+use crate::models::metrics::StatementTimer;
+
+/// A streamed candle that was overwritten with its final values from REST,
+/// stored in `kline_corrections`.
+///
+/// The `streamed_*` values are the ones stored before the correction, or `None`
+/// if the candle was not stored at all.
+#[derive(FromRow, Debug, Clone, PartialEq, Eq)]
+pub struct KlineCorrection {
+    /// The unique identifier of the correction.
+    pub id: i64,
+    /// The trading symbol.
+    pub symbol: String,
+    /// The Kline interval.
+    pub interval: String,
+    /// The dataset label.
+    pub dataset: String,
+    /// The start time of the corrected candle.
+    pub start_time: DateTime<Utc>,
+    /// The stored opening price before the correction.
+    pub streamed_open: Option<Decimal>,
+    /// The stored highest price before the correction.
+    pub streamed_high: Option<Decimal>,
+    /// The stored lowest price before the correction.
+    pub streamed_low: Option<Decimal>,
+    /// The stored closing price before the correction.
+    pub streamed_close: Option<Decimal>,
+    /// The stored volume before the correction.
+    pub streamed_volume: Option<Decimal>,
+    /// The stored ID of the last trade before the correction.
+    pub streamed_last_trade_id: Option<i64>,
+    /// The final opening price.
+    pub open: Decimal,
+    /// The final highest price.
+    pub high: Decimal,
+    /// The final lowest price.
+    pub low: Decimal,
+    /// The final closing price.
+    pub close: Decimal,
+    /// The final volume.
+    pub volume: Decimal,
+    /// The final ID of the last trade.
+    pub last_trade_id: i64,
+    /// The timestamp when the correction was made.
+    pub corrected_at: DateTime<Utc>,
+}
+
+impl KlineCorrection {
+    /// Records the correction of a candle.
+    ///
+    /// # Arguments
+    ///
+    /// * `executor` - The database connection pool, or a connection or transaction.
+    /// * `streamed` - The stored candle before the correction, if it was stored.
+    /// * `corrected` - The candle with its final values.
+    pub async fn record<'e, E>(
+        executor: E,
+        streamed: Option<&KlineData>,
+        corrected: &KlineData,
+    ) -> Result<Self, sqlx::Error>
+    where
+        E: sqlx::PgExecutor<'e>,
+    {
+        let _timer = StatementTimer::start("kline_corrections.record");
+        let correction = sqlx::query_as!(
+            KlineCorrection,
+            r#"
+            INSERT INTO kline_corrections (
+                symbol, interval, dataset, start_time,
+                streamed_open, streamed_high, streamed_low, streamed_close, streamed_volume,
+                streamed_last_trade_id, open, high, low, close, volume, last_trade_id
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
+            RETURNING *
+            "#,
+            corrected.symbol,
+            corrected.interval,
+            corrected.dataset,
+            corrected.start_time,
+            streamed.map(|kline| kline.open.clone()),
+            streamed.map(|kline| kline.high.clone()),
+            streamed.map(|kline| kline.low.clone()),
+            streamed.map(|kline| kline.close.clone()),
+            streamed.map(|kline| kline.volume.clone()),
+            streamed.map(|kline| kline.last_trade_id),
+            corrected.open,
+            corrected.high,
+            corrected.low,
+            corrected.close,
+            corrected.volume,
+            corrected.last_trade_id
+        )
+        .fetch_one(executor)
+        .await?;
+        Ok(correction)
+    }
+
+    /// Lists the corrections of candles starting within `[start_time, end_time)`,
+    /// ordered by start time.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `symbol` - The trading symbol.
+    /// * `interval` - The Kline interval.
+    /// * `dataset` - The dataset label.
+    /// * `start_time` - The inclusive start of the range.
+    /// * `end_time` - The exclusive end of the range.
+    pub async fn list_range(
+        pool: &sqlx::PgPool,
+        symbol: &str,
+        interval: &str,
+        dataset: &str,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        let corrections = sqlx::query_as!(
+            KlineCorrection,
+            r#"
+            SELECT * FROM kline_corrections
+            WHERE symbol = $1 AND interval = $2 AND dataset = $3
+              AND start_time >= $4 AND start_time < $5
+            ORDER BY start_time, id
+            "#,
+            symbol,
+            interval,
+            dataset,
+            start_time,
+            end_time
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(corrections)
+    }
+}
@@ -0,0 +1,268 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use sqlx::types::BigDecimal as Decimal;
+
+use crate::models::DEFAULT_DATASET;
+use crate::models::metrics::StatementTimer;
+
+/// The liquidation order of a `forceOrder` event, as sent by the exchange.
+///
+/// Numeric fields are strings to preserve precision, like [`SerdableKlineData`](super::SerdableKlineData).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SerdableLiquidationOrder {
+    /// The contract symbol (e.g., "BTCUSDT").
+    #[serde(rename = "s")]
+    pub symbol: String,
+    /// The side of the order: "SELL" liquidates a long position, "BUY" a short one.
+    #[serde(rename = "S")]
+    pub side: String,
+    /// The order type (e.g., "LIMIT").
+    #[serde(rename = "o")]
+    pub order_type: String,
+    /// The time in force (e.g., "IOC").
+    #[serde(rename = "f")]
+    pub time_in_force: String,
+    /// The original quantity.
+    #[serde(rename = "q")]
+    pub quantity: String,
+    /// The order price.
+    #[serde(rename = "p")]
+    pub price: String,
+    /// The average fill price.
+    #[serde(rename = "ap")]
+    pub average_price: String,
+    /// The order status (e.g., "FILLED").
+    #[serde(rename = "X")]
+    pub status: String,
+    /// The quantity of the last fill.
+    #[serde(rename = "l")]
+    pub last_filled_quantity: String,
+    /// The accumulated filled quantity.
+    #[serde(rename = "z")]
+    pub filled_quantity: String,
+    /// The trade time (Unix timestamp in milliseconds).
+    #[serde(rename = "T")]
+    pub trade_time: u64,
+}
+
+/// A `forceOrder` event of the Binance USDⓈ-M futures streams.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SerdableLiquidationEvent {
+    /// The time the exchange generated the event (Unix timestamp in milliseconds).
+    #[serde(rename = "E")]
+    pub event_time: u64,
+    /// The liquidation order.
+    #[serde(rename = "o")]
+    pub order: SerdableLiquidationOrder,
+}
+
+impl SerdableLiquidationEvent {
+    /// Converts the event into a row of `liquidations` under the [`DEFAULT_DATASET`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a timestamp or decimal string cannot be parsed.
+    pub fn to_liquidation_event(&self) -> anyhow::Result<LiquidationEvent> {
+        fn timestamp(value: u64) -> anyhow::Result<DateTime<Utc>> {
+            i64::try_from(value)
+                .ok()
+                .and_then(DateTime::from_timestamp_millis)
+                .ok_or_else(|| anyhow::anyhow!("Invalid timestamp: {}", value))
+        }
+        fn decimal(value: &str) -> anyhow::Result<Decimal> {
+            value
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Invalid decimal: {}", value))
+        }
+
+        let order = &self.order;
+        Ok(LiquidationEvent {
+            symbol: order.symbol.clone(),
+            dataset: DEFAULT_DATASET.to_string(),
+            trade_time: timestamp(order.trade_time)?,
+            side: order.side.clone(),
+            order_type: order.order_type.clone(),
+            time_in_force: order.time_in_force.clone(),
+            status: order.status.clone(),
+            price: decimal(&order.price)?,
+            average_price: decimal(&order.average_price)?,
+            quantity: decimal(&order.quantity)?,
+            last_filled_quantity: decimal(&order.last_filled_quantity)?,
+            filled_quantity: decimal(&order.filled_quantity)?,
+            event_time: timestamp(self.event_time)?,
+        })
+    }
+}
+
+/// A liquidation order of a futures contract, stored in `liquidations`.
+#[derive(FromRow, Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LiquidationEvent {
+    /// The contract symbol.
+    pub symbol: String,
+    /// The dataset label.
+    pub dataset: String,
+    /// The time of the last fill of the order.
+    pub trade_time: DateTime<Utc>,
+    /// "SELL" for liquidated longs, "BUY" for liquidated shorts.
+    pub side: String,
+    /// The order type.
+    pub order_type: String,
+    /// The time in force.
+    pub time_in_force: String,
+    /// The order status.
+    pub status: String,
+    /// The order price.
+    pub price: Decimal,
+    /// The average fill price.
+    pub average_price: Decimal,
+    /// The original quantity.
+    pub quantity: Decimal,
+    /// The quantity of the last fill.
+    pub last_filled_quantity: Decimal,
+    /// The accumulated filled quantity.
+    pub filled_quantity: Decimal,
+    /// The time the exchange generated the event.
+    pub event_time: DateTime<Utc>,
+}
+
+impl LiquidationEvent {
+    /// Assigns the event to a dataset.
+    pub fn with_dataset(mut self, dataset: &str) -> Self {
+        self.dataset = dataset.to_string();
+        self
+    }
+
+    /// Returns the filled notional of the order in the quote asset.
+    pub fn notional(&self) -> Decimal {
+        &self.average_price * &self.filled_quantity
+    }
+
+    /// Stores the event. An event that is already stored, e.g. received again
+    /// after a reconnect, is ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the event was not stored yet.
+    pub async fn insert(&self, pool: &sqlx::PgPool) -> Result<bool, sqlx::Error> {
+        let _timer = StatementTimer::start("liquidations.insert");
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO liquidations (
+                symbol, dataset, trade_time, side, order_type, time_in_force, status,
+                price, average_price, quantity, last_filled_quantity, filled_quantity,
+                event_time
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+            ON CONFLICT (symbol, dataset, trade_time, side, price, filled_quantity) DO NOTHING
+            "#,
+            self.symbol,
+            self.dataset,
+            self.trade_time,
+            self.side,
+            self.order_type,
+            self.time_in_force,
+            self.status,
+            self.price,
+            self.average_price,
+            self.quantity,
+            self.last_filled_quantity,
+            self.filled_quantity,
+            self.event_time
+        )
+        .execute(pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Lists the liquidations of a contract traded in `[start_time, end_time)`,
+    /// oldest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `symbol` - The contract symbol.
+    /// * `dataset` - The dataset label.
+    /// * `start_time` - The inclusive start of the range.
+    /// * `end_time` - The exclusive end of the range.
+    pub async fn list_range(
+        pool: &sqlx::PgPool,
+        symbol: &str,
+        dataset: &str,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        let _timer = StatementTimer::start("liquidations.list_range");
+        let events = sqlx::query_as!(
+            LiquidationEvent,
+            r#"
+            SELECT * FROM liquidations
+            WHERE symbol = $1 AND dataset = $2
+              AND trade_time >= $3 AND trade_time < $4
+            ORDER BY trade_time
+            "#,
+            symbol,
+            dataset,
+            start_time,
+            end_time
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(events)
+    }
+
+    /// Lists the liquidations of all contracts traded in `[start_time, end_time)`,
+    /// oldest first, to follow cascades across the market.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `dataset` - The dataset label.
+    /// * `start_time` - The inclusive start of the range.
+    /// * `end_time` - The exclusive end of the range.
+    pub async fn list_all_range(
+        pool: &sqlx::PgPool,
+        dataset: &str,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        let _timer = StatementTimer::start("liquidations.list_all_range");
+        let events = sqlx::query_as!(
+            LiquidationEvent,
+            r#"
+            SELECT * FROM liquidations
+            WHERE dataset = $1 AND trade_time >= $2 AND trade_time < $3
+            ORDER BY trade_time, symbol
+            "#,
+            dataset,
+            start_time,
+            end_time
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_liquidation_event() {
+        let json = r#"{"e":"forceOrder","E":1568014460893,"o":{"s":"BTCUSDT","S":"SELL","o":"LIMIT","f":"IOC","q":"0.014","p":"9910","ap":"9910","X":"FILLED","l":"0.014","z":"0.014","T":1568014460893}}"#;
+        let event: SerdableLiquidationEvent = serde_json::from_str(json).unwrap();
+        let liquidation = event
+            .to_liquidation_event()
+            .unwrap()
+            .with_dataset("research");
+        assert_eq!(liquidation.side, "SELL");
+        assert_eq!(liquidation.dataset, "research");
+        assert_eq!(liquidation.trade_time.timestamp_millis(), 1568014460893);
+        assert_eq!(liquidation.notional(), "138.74".parse::<Decimal>().unwrap());
+    }
+}
@@ -0,0 +1,106 @@
+//! Per-statement latency metrics for the database operations of the models.
+//!
+//! Every instrumented operation records how long its statement took under a
+//! stable name such as `kline_data.upsert`. The collected [`StatementStats`] can be
+//! read with [`snapshot`] and logged or exported periodically.
+//!
+//! sqlx prepares each `query!` statement once per connection and reuses it from
+//! the connection's statement cache afterwards, so batched writes that run on a
+//! single connection (see [`KlineData::upsert_batch`](super::KlineData::upsert_batch))
+//! only pay for parsing and planning once per batch.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The collected statistics, keyed by statement name.
+static STATS: Mutex<BTreeMap<&'static str, StatementStats>> = Mutex::new(BTreeMap::new());
+
+/// Latency statistics of one statement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatementStats {
+    /// The name of the statement, e.g. `kline_data.upsert`.
+    pub statement: &'static str,
+    /// The number of executions.
+    pub calls: u64,
+    /// The total time spent executing the statement.
+    pub total: Duration,
+    /// The longest single execution.
+    pub max: Duration,
+}
+
+impl StatementStats {
+    /// Returns the mean latency, or zero if the statement was never executed.
+    pub fn mean(&self) -> Duration {
+        if self.calls == 0 {
+            return Duration::ZERO;
+        }
+        self.total / self.calls as u32
+    }
+}
+
+/// Records one execution of a statement.
+pub fn record(statement: &'static str, elapsed: Duration) {
+    let mut stats = STATS.lock().unwrap();
+    let entry = stats.entry(statement).or_insert(StatementStats {
+        statement,
+        calls: 0,
+        total: Duration::ZERO,
+        max: Duration::ZERO,
+    });
+    entry.calls += 1;
+    entry.total += elapsed;
+    entry.max = entry.max.max(elapsed);
+}
+
+/// Returns the statistics of every statement executed so far, ordered by name.
+pub fn snapshot() -> Vec<StatementStats> {
+    STATS.lock().unwrap().values().copied().collect()
+}
+
+/// Clears all collected statistics.
+pub fn reset() {
+    STATS.lock().unwrap().clear();
+}
+
+/// Records the latency of a statement when dropped, whether it succeeded or failed.
+pub(crate) struct StatementTimer {
+    /// The name of the statement.
+    statement: &'static str,
+    /// The time the statement started.
+    started_at: Instant,
+}
+
+impl StatementTimer {
+    /// Starts timing a statement.
+    pub(crate) fn start(statement: &'static str) -> Self {
+        Self {
+            statement,
+            started_at: Instant::now(),
+        }
+    }
+}
+
+impl Drop for StatementTimer {
+    fn drop(&mut self) {
+        record(self.statement, self.started_at.elapsed());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_aggregates_calls() {
+        record("test.statement", Duration::from_millis(10));
+        record("test.statement", Duration::from_millis(30));
+        let stats = snapshot()
+            .into_iter()
+            .find(|stats| stats.statement == "test.statement")
+            .unwrap();
+        assert_eq!(stats.calls, 2);
+        assert_eq!(stats.max, Duration::from_millis(30));
+        assert_eq!(stats.mean(), Duration::from_millis(20));
+    }
+}
@@ -0,0 +1,247 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use sqlx::types::BigDecimal as Decimal;
+
+use crate::models::DEFAULT_DATASET;
+use crate::models::metrics::StatementTimer;
+use crate::models::trace;
+
+/// The current open interest as returned by the `/fapi/v1/openInterest` endpoint
+/// of Binance USDⓈ-M futures.
+///
+/// Numeric fields are strings to preserve precision, like [`SerdableKlineData`](super::SerdableKlineData).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SerdableOpenInterest {
+    /// The contract symbol (e.g., "BTCUSDT").
+    pub symbol: String,
+    /// The open interest, in contracts or base asset.
+    #[serde(rename = "openInterest")]
+    pub open_interest: String,
+    /// The time of the value in milliseconds since the UNIX epoch.
+    pub time: i64,
+}
+
+impl SerdableOpenInterest {
+    /// Converts the open interest into a row of `open_interest` under the
+    /// [`DEFAULT_DATASET`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the time or the open interest is invalid.
+    pub fn to_open_interest(&self) -> anyhow::Result<OpenInterestData> {
+        Ok(OpenInterestData {
+            symbol: self.symbol.clone(),
+            dataset: DEFAULT_DATASET.to_string(),
+            timestamp: parse_timestamp(self.time)?,
+            open_interest: parse_decimal("open interest", &self.open_interest)?,
+            open_interest_value: None,
+        })
+    }
+}
+
+/// A value of the open interest history as returned by the
+/// `/futures/data/openInterestHist` endpoint of Binance USDⓈ-M futures.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SerdableOpenInterestHist {
+    /// The contract symbol (e.g., "BTCUSDT").
+    pub symbol: String,
+    /// The open interest, in contracts or base asset.
+    #[serde(rename = "sumOpenInterest")]
+    pub sum_open_interest: String,
+    /// The notional of the open interest, in the quote asset.
+    #[serde(rename = "sumOpenInterestValue")]
+    pub sum_open_interest_value: String,
+    /// The start of the period in milliseconds since the UNIX epoch.
+    pub timestamp: i64,
+}
+
+impl SerdableOpenInterestHist {
+    /// Converts the value into a row of `open_interest` under the
+    /// [`DEFAULT_DATASET`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the timestamp or a decimal string is invalid.
+    pub fn to_open_interest(&self) -> anyhow::Result<OpenInterestData> {
+        Ok(OpenInterestData {
+            symbol: self.symbol.clone(),
+            dataset: DEFAULT_DATASET.to_string(),
+            timestamp: parse_timestamp(self.timestamp)?,
+            open_interest: parse_decimal("open interest", &self.sum_open_interest)?,
+            open_interest_value: Some(parse_decimal(
+                "open interest value",
+                &self.sum_open_interest_value,
+            )?),
+        })
+    }
+}
+
+fn parse_timestamp(millis: i64) -> anyhow::Result<DateTime<Utc>> {
+    Ok(crate::time::from_millis(millis)?)
+}
+
+fn parse_decimal(field: &str, value: &str) -> anyhow::Result<Decimal> {
+    value
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid {}: {}", field, value))
+}
+
+/// The open interest of a futures contract at a point in time, stored in
+/// `open_interest`.
+#[derive(FromRow, Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OpenInterestData {
+    /// The contract symbol (e.g., "BTCUSDT").
+    pub symbol: String,
+    /// The dataset label.
+    pub dataset: String,
+    /// The time of the value.
+    pub timestamp: DateTime<Utc>,
+    /// The open interest, in contracts or base asset.
+    pub open_interest: Decimal,
+    /// The notional of the open interest in the quote asset, if known.
+    pub open_interest_value: Option<Decimal>,
+}
+
+impl OpenInterestData {
+    /// Assigns the value to a dataset.
+    pub fn with_dataset(mut self, dataset: &str) -> Self {
+        self.dataset = dataset.to_string();
+        self
+    }
+
+    /// Stores the value, replacing a previous value for the same time. A known
+    /// notional is kept if the new value has none.
+    ///
+    /// # Arguments
+    ///
+    /// * `executor` - The database connection pool, or a connection or transaction.
+    pub async fn upsert<'e, E>(&self, executor: E) -> Result<(), sqlx::Error>
+    where
+        E: sqlx::PgExecutor<'e>,
+    {
+        let _timer = StatementTimer::start("open_interest.upsert");
+        sqlx::query!(
+            r#"
+            INSERT INTO open_interest (symbol, dataset, timestamp, open_interest, open_interest_value)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (symbol, dataset, timestamp) DO UPDATE
+            SET
+                open_interest = EXCLUDED.open_interest,
+                open_interest_value = COALESCE(
+                    EXCLUDED.open_interest_value, open_interest.open_interest_value
+                )
+            "#,
+            self.symbol,
+            self.dataset,
+            self.timestamp,
+            self.open_interest,
+            self.open_interest_value
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Stores many values in one transaction.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `values` - The values to store.
+    ///
+    /// # Returns
+    ///
+    /// The number of stored values.
+    pub async fn upsert_batch(pool: &sqlx::PgPool, values: &[Self]) -> Result<usize, sqlx::Error> {
+        let _timer = StatementTimer::start("open_interest.upsert_batch");
+        let mut tx = trace::begin(pool).await?;
+        for value in values {
+            value.upsert(&mut *tx).await?;
+        }
+        tx.commit().await?;
+        Ok(values.len())
+    }
+
+    /// Lists the values of a contract in `[start_time, end_time)`, oldest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `symbol` - The contract symbol.
+    /// * `dataset` - The dataset label.
+    /// * `start_time` - The inclusive start of the range.
+    /// * `end_time` - The exclusive end of the range.
+    pub async fn list_range(
+        pool: &sqlx::PgPool,
+        symbol: &str,
+        dataset: &str,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        let _timer = StatementTimer::start("open_interest.list_range");
+        let values = sqlx::query_as!(
+            OpenInterestData,
+            r#"
+            SELECT * FROM open_interest
+            WHERE symbol = $1 AND dataset = $2 AND timestamp >= $3 AND timestamp < $4
+            ORDER BY timestamp
+            "#,
+            symbol,
+            dataset,
+            start_time,
+            end_time
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(values)
+    }
+
+    /// Retrieves the time of the latest stored value of a contract, or `None` if
+    /// no values are stored.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `symbol` - The contract symbol.
+    /// * `dataset` - The dataset label.
+    pub async fn latest_timestamp(
+        pool: &sqlx::PgPool,
+        symbol: &str,
+        dataset: &str,
+    ) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+        let _timer = StatementTimer::start("open_interest.latest_timestamp");
+        sqlx::query_scalar!(
+            r#"
+            SELECT MAX(timestamp) FROM open_interest
+            WHERE symbol = $1 AND dataset = $2
+            "#,
+            symbol,
+            dataset
+        )
+        .fetch_one(pool)
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_open_interest() {
+        let json = r#"{"openInterest":"10659.509","symbol":"BTCUSDT","time":1589437530011}"#;
+        let current: SerdableOpenInterest = serde_json::from_str(json).unwrap();
+        let value = current.to_open_interest().unwrap().with_dataset("research");
+        assert_eq!(value.dataset, "research");
+        assert_eq!(value.timestamp.timestamp_millis(), 1589437530011);
+        assert_eq!(value.open_interest, "10659.509".parse::<Decimal>().unwrap());
+        assert_eq!(value.open_interest_value, None);
+
+        let json = r#"[{"symbol":"BTCUSDT","sumOpenInterest":"20403.63700000","sumOpenInterestValue":"150570784.07809979","timestamp":1583127900000}]"#;
+        let history: Vec<SerdableOpenInterestHist> = serde_json::from_str(json).unwrap();
+        let value = history[0].to_open_interest().unwrap();
+        assert_eq!(value.timestamp.timestamp_millis(), 1583127900000);
+        assert!(value.open_interest_value.is_some());
+    }
+}
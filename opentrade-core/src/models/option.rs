@@ -0,0 +1,207 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use sqlx::types::BigDecimal as Decimal;
+use std::fmt;
+
+use crate::models::metrics::StatementTimer;
+
+/// Whether an option is a call or a put.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum OptionKind {
+    Call,
+    Put,
+}
+
+impl OptionKind {
+    /// Returns the label stored in `option_marks.option_type` ("C" or "P").
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OptionKind::Call => "C",
+            OptionKind::Put => "P",
+        }
+    }
+
+    /// Parses a stored label.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "C" => Some(OptionKind::Call),
+            "P" => Some(OptionKind::Put),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for OptionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The mark price, implied volatilities and greeks of an options contract at one
+/// point in time, stored in `option_marks`.
+///
+/// Implied volatilities are annualized fractions (0.5 is 50%). Values the exchange
+/// did not quote, e.g. the bid IV of a contract without bids, are `None`.
+#[derive(FromRow, Debug, Clone, PartialEq, Serialize)]
+pub struct OptionMark {
+    /// The contract symbol (e.g., "BTC-250926-100000-C").
+    pub symbol: String,
+    /// The dataset label.
+    pub dataset: String,
+    /// The time of the snapshot the mark belongs to.
+    pub observed_at: DateTime<Utc>,
+    /// The underlying asset (e.g., "BTC").
+    pub underlying: String,
+    /// The expiry of the contract.
+    pub expiry: DateTime<Utc>,
+    /// The strike price.
+    pub strike: Decimal,
+    /// [`OptionKind::Call`] or [`OptionKind::Put`], as returned by [`OptionKind::as_str`].
+    pub option_type: String,
+    /// The mark price.
+    pub mark_price: Decimal,
+    /// The implied volatility of the best bid.
+    pub bid_iv: Option<f64>,
+    /// The implied volatility of the best ask.
+    pub ask_iv: Option<f64>,
+    /// The implied volatility of the mark price.
+    pub mark_iv: Option<f64>,
+    /// The delta.
+    pub delta: Option<f64>,
+    /// The gamma.
+    pub gamma: Option<f64>,
+    /// The theta.
+    pub theta: Option<f64>,
+    /// The vega.
+    pub vega: Option<f64>,
+}
+
+impl OptionMark {
+    /// Returns whether the contract is a call or a put.
+    pub fn kind(&self) -> Option<OptionKind> {
+        OptionKind::parse(&self.option_type)
+    }
+
+    /// Stores the mark, replacing a previous value of the same snapshot.
+    ///
+    /// # Arguments
+    ///
+    /// * `executor` - The database connection pool or connection.
+    pub async fn upsert<'e, E>(&self, executor: E) -> Result<(), sqlx::Error>
+    where
+        E: sqlx::PgExecutor<'e>,
+    {
+        sqlx::query!(
+            r#"
+            INSERT INTO option_marks (
+                symbol, dataset, observed_at, underlying, expiry, strike, option_type,
+                mark_price, bid_iv, ask_iv, mark_iv, delta, gamma, theta, vega
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+            ON CONFLICT (symbol, dataset, observed_at) DO UPDATE
+            SET
+                mark_price = EXCLUDED.mark_price,
+                bid_iv = EXCLUDED.bid_iv,
+                ask_iv = EXCLUDED.ask_iv,
+                mark_iv = EXCLUDED.mark_iv,
+                delta = EXCLUDED.delta,
+                gamma = EXCLUDED.gamma,
+                theta = EXCLUDED.theta,
+                vega = EXCLUDED.vega
+            "#,
+            self.symbol,
+            self.dataset,
+            self.observed_at,
+            self.underlying,
+            self.expiry,
+            self.strike,
+            self.option_type,
+            self.mark_price,
+            self.bid_iv,
+            self.ask_iv,
+            self.mark_iv,
+            self.delta,
+            self.gamma,
+            self.theta,
+            self.vega
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Stores many marks in one transaction.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `marks` - The marks to store.
+    ///
+    /// # Returns
+    ///
+    /// The number of stored marks.
+    pub async fn upsert_batch(pool: &sqlx::PgPool, marks: &[Self]) -> Result<usize, sqlx::Error> {
+        let _timer = StatementTimer::start("option_marks.upsert_batch");
+        let mut tx = pool.begin().await?;
+        for mark in marks {
+            mark.upsert(&mut *tx).await?;
+        }
+        tx.commit().await?;
+        Ok(marks.len())
+    }
+
+    /// Lists the marks of the latest snapshot of an underlying taken at or before
+    /// `as_of`, sorted by expiry, strike and option type.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `underlying` - The underlying asset (e.g., "BTC").
+    /// * `dataset` - The dataset label.
+    /// * `as_of` - The latest snapshot time to consider.
+    pub async fn list_surface(
+        pool: &sqlx::PgPool,
+        underlying: &str,
+        dataset: &str,
+        as_of: DateTime<Utc>,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        let _timer = StatementTimer::start("option_marks.list_surface");
+        let marks = sqlx::query_as!(
+            OptionMark,
+            r#"
+            SELECT * FROM option_marks
+            WHERE underlying = $1 AND dataset = $2
+              AND observed_at = (
+                  SELECT MAX(observed_at) FROM option_marks
+                  WHERE underlying = $1 AND dataset = $2 AND observed_at <= $3
+              )
+            ORDER BY expiry, strike, option_type
+            "#,
+            underlying,
+            dataset,
+            as_of
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(marks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_option_kind_labels() {
+        assert_eq!(
+            OptionKind::parse(OptionKind::Call.as_str()),
+            Some(OptionKind::Call)
+        );
+        assert_eq!(
+            OptionKind::parse(OptionKind::Put.as_str()),
+            Some(OptionKind::Put)
+        );
+        assert_eq!(OptionKind::parse("X"), None);
+    }
+}
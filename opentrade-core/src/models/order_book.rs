@@ -0,0 +1,436 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use sqlx::types::BigDecimal as Decimal;
+
+use crate::models::DEFAULT_DATASET;
+use crate::models::metrics::StatementTimer;
+
+/// A price level as sent by the exchange: the price and the quantity, as strings
+/// to preserve precision.
+pub type RawLevel = [String; 2];
+
+/// A diff of an order book, as sent by the `<symbol>@depth` streams.
+///
+/// Each diff carries the absolute quantities of the levels that changed; a
+/// quantity of zero removes the level.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DepthUpdate {
+    /// The time the exchange generated the event (Unix timestamp in milliseconds).
+    #[serde(rename = "E")]
+    pub event_time: u64,
+    /// The trading symbol (e.g., "BTCUSDT").
+    #[serde(rename = "s")]
+    pub symbol: String,
+    /// The id of the first update in the diff.
+    #[serde(rename = "U")]
+    pub first_update_id: u64,
+    /// The id of the last update in the diff.
+    #[serde(rename = "u")]
+    pub final_update_id: u64,
+    /// The changed bid levels.
+    #[serde(rename = "b")]
+    pub bids: Vec<RawLevel>,
+    /// The changed ask levels.
+    #[serde(rename = "a")]
+    pub asks: Vec<RawLevel>,
+}
+
+/// An order book snapshot, as returned by the `/api/v3/depth` endpoint.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DepthSnapshot {
+    /// The id of the last update included in the snapshot.
+    #[serde(rename = "lastUpdateId")]
+    pub last_update_id: u64,
+    /// The bid levels, best first.
+    pub bids: Vec<RawLevel>,
+    /// The ask levels, best first.
+    pub asks: Vec<RawLevel>,
+}
+
+/// Errors applying a [`DepthUpdate`] to an [`OrderBook`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DepthSyncError {
+    /// The update does not follow the last applied update, so diffs were missed
+    /// and the book has to be rebuilt from a new snapshot.
+    Gap { expected: u64, first_update_id: u64 },
+    /// A price or quantity is not a valid decimal.
+    InvalidLevel(String),
+}
+
+impl fmt::Display for DepthSyncError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DepthSyncError::Gap {
+                expected,
+                first_update_id,
+            } => write!(
+                f,
+                "depth update starts at {} but {} was expected",
+                first_update_id, expected
+            ),
+            DepthSyncError::InvalidLevel(level) => write!(f, "invalid price level: {}", level),
+        }
+    }
+}
+
+impl std::error::Error for DepthSyncError {}
+
+/// A local order book maintained from a [`DepthSnapshot`] and the [`DepthUpdate`]s
+/// following it.
+///
+/// [`apply`](Self::apply) implements the synchronization rules of Binance: diffs
+/// up to the last update id of the book are dropped, the first applied diff must
+/// contain the update following the snapshot, and every later diff must start
+/// right after the previous one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderBook {
+    symbol: String,
+    last_update_id: u64,
+    /// True once a diff was applied on top of the snapshot.
+    streaming: bool,
+    bids: BTreeMap<Decimal, Decimal>,
+    asks: BTreeMap<Decimal, Decimal>,
+}
+
+impl OrderBook {
+    /// Builds a book from a REST snapshot.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DepthSyncError::InvalidLevel`] if a level cannot be parsed.
+    pub fn from_snapshot(symbol: &str, snapshot: &DepthSnapshot) -> Result<Self, DepthSyncError> {
+        let mut book = Self {
+            symbol: symbol.to_string(),
+            last_update_id: snapshot.last_update_id,
+            streaming: false,
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+        };
+        apply_levels(&mut book.bids, &snapshot.bids)?;
+        apply_levels(&mut book.asks, &snapshot.asks)?;
+        Ok(book)
+    }
+
+    /// Applies a diff to the book.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(true)` if the diff was applied, `Ok(false)` if it is already contained in
+    /// the book, or [`DepthSyncError::Gap`] if diffs between the book and the diff
+    /// are missing. The book is left unchanged on errors.
+    pub fn apply(&mut self, update: &DepthUpdate) -> Result<bool, DepthSyncError> {
+        if update.final_update_id <= self.last_update_id {
+            return Ok(false);
+        }
+        let expected = self.last_update_id + 1;
+        let follows = if self.streaming {
+            update.first_update_id == expected
+        } else {
+            update.first_update_id <= expected
+        };
+        if !follows {
+            return Err(DepthSyncError::Gap {
+                expected,
+                first_update_id: update.first_update_id,
+            });
+        }
+        let bids = parse_levels(&update.bids)?;
+        let asks = parse_levels(&update.asks)?;
+        set_levels(&mut self.bids, bids);
+        set_levels(&mut self.asks, asks);
+        self.last_update_id = update.final_update_id;
+        self.streaming = true;
+        Ok(true)
+    }
+
+    /// Returns the trading symbol.
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    /// Returns the id of the last update contained in the book.
+    pub fn last_update_id(&self) -> u64 {
+        self.last_update_id
+    }
+
+    /// Returns the bid levels as `(price, quantity)`, best (highest) first.
+    pub fn bids(&self) -> impl Iterator<Item = (&Decimal, &Decimal)> {
+        self.bids.iter().rev()
+    }
+
+    /// Returns the ask levels as `(price, quantity)`, best (lowest) first.
+    pub fn asks(&self) -> impl Iterator<Item = (&Decimal, &Decimal)> {
+        self.asks.iter()
+    }
+
+    /// Returns the best bid as `(price, quantity)`.
+    pub fn best_bid(&self) -> Option<(&Decimal, &Decimal)> {
+        self.bids.last_key_value()
+    }
+
+    /// Returns the best ask as `(price, quantity)`.
+    pub fn best_ask(&self) -> Option<(&Decimal, &Decimal)> {
+        self.asks.first_key_value()
+    }
+
+    /// Returns the top `depth` levels of each side as a row of `order_book_snapshots`
+    /// under the [`DEFAULT_DATASET`].
+    ///
+    /// # Arguments
+    ///
+    /// * `depth` - The number of levels per side to keep.
+    /// * `snapshot_time` - The time the snapshot is recorded at.
+    pub fn to_snapshot(&self, depth: usize, snapshot_time: DateTime<Utc>) -> OrderBookSnapshot {
+        let (bid_prices, bid_quantities) = self
+            .bids()
+            .take(depth)
+            .map(|(price, quantity)| (price.clone(), quantity.clone()))
+            .unzip();
+        let (ask_prices, ask_quantities) = self
+            .asks()
+            .take(depth)
+            .map(|(price, quantity)| (price.clone(), quantity.clone()))
+            .unzip();
+        OrderBookSnapshot {
+            symbol: self.symbol.clone(),
+            dataset: DEFAULT_DATASET.to_string(),
+            snapshot_time,
+            last_update_id: self.last_update_id as i64,
+            bid_prices,
+            bid_quantities,
+            ask_prices,
+            ask_quantities,
+        }
+    }
+}
+
+/// Parses the levels of a diff or snapshot.
+fn parse_levels(levels: &[RawLevel]) -> Result<Vec<(Decimal, Decimal)>, DepthSyncError> {
+    levels
+        .iter()
+        .map(|[price, quantity]| {
+            let invalid = || DepthSyncError::InvalidLevel(format!("{} @ {}", quantity, price));
+            Ok((
+                price.parse().map_err(|_| invalid())?,
+                quantity.parse().map_err(|_| invalid())?,
+            ))
+        })
+        .collect()
+}
+
+/// Sets the quantities of levels of one side, removing levels with a zero quantity.
+fn set_levels(side: &mut BTreeMap<Decimal, Decimal>, levels: Vec<(Decimal, Decimal)>) {
+    for (price, quantity) in levels {
+        if quantity == Decimal::from(0) {
+            side.remove(&price);
+        } else {
+            side.insert(price, quantity);
+        }
+    }
+}
+
+/// Parses and sets the levels of one side.
+fn apply_levels(
+    side: &mut BTreeMap<Decimal, Decimal>,
+    levels: &[RawLevel],
+) -> Result<(), DepthSyncError> {
+    set_levels(side, parse_levels(levels)?);
+    Ok(())
+}
+
+/// The top levels of an order book at a point in time, stored in
+/// `order_book_snapshots`.
+#[derive(FromRow, Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OrderBookSnapshot {
+    /// The trading symbol.
+    pub symbol: String,
+    /// The dataset label.
+    pub dataset: String,
+    /// The time the snapshot was recorded.
+    pub snapshot_time: DateTime<Utc>,
+    /// The id of the last update contained in the book.
+    pub last_update_id: i64,
+    /// The bid prices, best first.
+    pub bid_prices: Vec<Decimal>,
+    /// The quantities of the bid levels.
+    pub bid_quantities: Vec<Decimal>,
+    /// The ask prices, best first.
+    pub ask_prices: Vec<Decimal>,
+    /// The quantities of the ask levels.
+    pub ask_quantities: Vec<Decimal>,
+}
+
+impl OrderBookSnapshot {
+    /// Assigns the snapshot to a dataset.
+    pub fn with_dataset(mut self, dataset: &str) -> Self {
+        self.dataset = dataset.to_string();
+        self
+    }
+
+    /// Stores the snapshot, replacing a previous snapshot recorded at the same time.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    pub async fn upsert(&self, pool: &sqlx::PgPool) -> Result<(), sqlx::Error> {
+        let _timer = StatementTimer::start("order_book_snapshots.upsert");
+        sqlx::query!(
+            r#"
+            INSERT INTO order_book_snapshots (
+                symbol, dataset, snapshot_time, last_update_id,
+                bid_prices, bid_quantities, ask_prices, ask_quantities
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (symbol, dataset, snapshot_time) DO UPDATE
+            SET
+                last_update_id = EXCLUDED.last_update_id,
+                bid_prices = EXCLUDED.bid_prices,
+                bid_quantities = EXCLUDED.bid_quantities,
+                ask_prices = EXCLUDED.ask_prices,
+                ask_quantities = EXCLUDED.ask_quantities
+            "#,
+            self.symbol,
+            self.dataset,
+            self.snapshot_time,
+            self.last_update_id,
+            &self.bid_prices,
+            &self.bid_quantities,
+            &self.ask_prices,
+            &self.ask_quantities
+        )
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Retrieves the latest snapshot of a symbol recorded at or before `as_of`.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `symbol` - The trading symbol.
+    /// * `dataset` - The dataset label.
+    /// * `as_of` - The latest snapshot time to consider.
+    pub async fn latest(
+        pool: &sqlx::PgPool,
+        symbol: &str,
+        dataset: &str,
+        as_of: DateTime<Utc>,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        let _timer = StatementTimer::start("order_book_snapshots.latest");
+        let snapshot = sqlx::query_as!(
+            OrderBookSnapshot,
+            r#"
+            SELECT * FROM order_book_snapshots
+            WHERE symbol = $1 AND dataset = $2 AND snapshot_time <= $3
+            ORDER BY snapshot_time DESC
+            LIMIT 1
+            "#,
+            symbol,
+            dataset,
+            as_of
+        )
+        .fetch_optional(pool)
+        .await?;
+        Ok(snapshot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(price: &str, quantity: &str) -> RawLevel {
+        [price.to_string(), quantity.to_string()]
+    }
+
+    fn update(first: u64, last: u64, bids: Vec<RawLevel>, asks: Vec<RawLevel>) -> DepthUpdate {
+        DepthUpdate {
+            event_time: 1_700_000_000_000,
+            symbol: "BTCUSDT".to_string(),
+            first_update_id: first,
+            final_update_id: last,
+            bids,
+            asks,
+        }
+    }
+
+    fn book() -> OrderBook {
+        let snapshot = DepthSnapshot {
+            last_update_id: 100,
+            bids: vec![level("99.0", "1"), level("98.0", "2")],
+            asks: vec![level("101.0", "1"), level("102.0", "3")],
+        };
+        OrderBook::from_snapshot("BTCUSDT", &snapshot).unwrap()
+    }
+
+    #[test]
+    fn test_parse_depth_update() {
+        let json = r#"{"e":"depthUpdate","E":1672515782136,"s":"BNBBTC","U":157,"u":160,"b":[["0.0024","10"]],"a":[["0.0026","100"]]}"#;
+        let update: DepthUpdate = serde_json::from_str(json).unwrap();
+        assert_eq!(update.first_update_id, 157);
+        assert_eq!(update.final_update_id, 160);
+        assert_eq!(update.bids, vec![level("0.0024", "10")]);
+    }
+
+    #[test]
+    fn test_apply_follows_sync_rules() {
+        let mut book = book();
+        // Diffs contained in the snapshot are dropped.
+        assert_eq!(book.apply(&update(90, 100, vec![], vec![])), Ok(false));
+        // The first diff may start before the snapshot, but must contain its successor.
+        assert_eq!(
+            book.apply(&update(95, 105, vec![level("99.0", "0")], vec![])),
+            Ok(true)
+        );
+        assert_eq!(book.last_update_id(), 105);
+        assert_eq!(
+            book.best_bid(),
+            Some((&"98.0".parse().unwrap(), &"2".parse().unwrap()))
+        );
+        // Later diffs must start right after the previous one.
+        assert_eq!(
+            book.apply(&update(107, 110, vec![], vec![])),
+            Err(DepthSyncError::Gap {
+                expected: 106,
+                first_update_id: 107
+            })
+        );
+        assert_eq!(
+            book.apply(&update(106, 110, vec![], vec![level("100.5", "4")])),
+            Ok(true)
+        );
+        assert_eq!(
+            book.best_ask(),
+            Some((&"100.5".parse().unwrap(), &"4".parse().unwrap()))
+        );
+    }
+
+    #[test]
+    fn test_first_diff_after_snapshot_gap_is_rejected() {
+        let mut book = book();
+        assert!(matches!(
+            book.apply(&update(105, 110, vec![], vec![])),
+            Err(DepthSyncError::Gap { expected: 101, .. })
+        ));
+    }
+
+    #[test]
+    fn test_to_snapshot_keeps_top_levels() {
+        let snapshot = book().to_snapshot(1, Utc::now());
+        assert_eq!(snapshot.last_update_id, 100);
+        assert_eq!(
+            snapshot.bid_prices,
+            vec!["99.0".parse::<Decimal>().unwrap()]
+        );
+        assert_eq!(
+            snapshot.ask_prices,
+            vec!["101.0".parse::<Decimal>().unwrap()]
+        );
+        assert_eq!(snapshot.ask_quantities, vec![Decimal::from(1)]);
+    }
+}
@@ -0,0 +1,279 @@
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+
+use crate::data_source::websocket::Payload;
+use crate::models::{KlineData, SerdableKlineData};
+
+/// Criteria for selecting quarantined rows. Unset fields match every row.
+#[derive(Debug, Clone, Default)]
+pub struct QuarantineFilter {
+    /// Only rows from this source (e.g., "websocket", "rest").
+    pub source: Option<String>,
+    /// Only rows for this symbol.
+    pub symbol: Option<String>,
+    /// Only rows for this interval.
+    pub interval: Option<String>,
+    /// Only rows for this dataset.
+    pub dataset: Option<String>,
+    /// Only rows quarantined at or after this time.
+    pub since: Option<DateTime<Utc>>,
+    /// Only rows quarantined before this time.
+    pub until: Option<DateTime<Utc>>,
+    /// Also include rows that were already reprocessed.
+    pub include_reprocessed: bool,
+    /// The maximum number of rows to return.
+    pub limit: Option<i64>,
+}
+
+/// A row that failed validation, stored verbatim in the `quarantine` table.
+///
+/// Rows are quarantined instead of dropped so that they can be reviewed and
+/// reprocessed once the parser or the upstream data has been fixed.
+#[derive(FromRow, Debug, Clone)]
+pub struct QuarantinedRow {
+    /// The unique identifier of the quarantined row.
+    pub id: i64,
+    /// Where the row came from (e.g., "websocket", "rest").
+    pub source: String,
+    /// The trading symbol, if it could be determined.
+    pub symbol: Option<String>,
+    /// The Kline interval, if it could be determined.
+    pub interval: Option<String>,
+    /// The raw payload as received.
+    pub raw_payload: String,
+    /// Why the row was rejected.
+    pub reason: String,
+    /// The timestamp when the row was quarantined.
+    pub created_at: DateTime<Utc>,
+    /// The timestamp when the row was successfully reprocessed, if it was.
+    pub reprocessed_at: Option<DateTime<Utc>>,
+    /// The dataset the row was ingested into.
+    pub dataset: String,
+}
+
+impl QuarantinedRow {
+    /// Writes a rejected row to the quarantine table.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `source` - Where the row came from (e.g., "websocket", "rest").
+    /// * `symbol` - The trading symbol, if known.
+    /// * `interval` - The Kline interval, if known.
+    /// * `raw_payload` - The raw payload as received.
+    /// * `reason` - Why the row was rejected.
+    /// * `dataset` - The dataset the row was ingested into.
+    pub async fn add(
+        pool: &sqlx::PgPool,
+        source: &str,
+        symbol: Option<&str>,
+        interval: Option<&str>,
+        raw_payload: &str,
+        reason: &str,
+        dataset: &str,
+    ) -> Result<Self, sqlx::Error> {
+        let row = sqlx::query_as!(
+            QuarantinedRow,
+            r#"
+            INSERT INTO quarantine (source, symbol, interval, raw_payload, reason, dataset)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING *
+            "#,
+            source,
+            symbol,
+            interval,
+            raw_payload,
+            reason,
+            dataset
+        )
+        .fetch_one(pool)
+        .await?;
+        Ok(row)
+    }
+
+    /// Quarantines a Kline message that failed validation, storing it as JSON.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `source` - Where the message came from (e.g., "websocket", "rest").
+    /// * `message` - The rejected message.
+    /// * `reason` - Why the message was rejected.
+    /// * `dataset` - The dataset the message was ingested into.
+    pub async fn add_kline(
+        pool: &sqlx::PgPool,
+        source: &str,
+        message: &SerdableKlineData,
+        reason: &str,
+        dataset: &str,
+    ) -> Result<Self, sqlx::Error> {
+        let raw_payload =
+            serde_json::to_string(message).map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
+        Self::add(
+            pool,
+            source,
+            Some(&message.symbol),
+            Some(&message.interval),
+            &raw_payload,
+            reason,
+            dataset,
+        )
+        .await
+    }
+
+    /// Retrieves a quarantined row by id.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `id` - The id of the quarantined row.
+    pub async fn get(pool: &sqlx::PgPool, id: i64) -> Result<Option<Self>, sqlx::Error> {
+        let row = sqlx::query_as!(
+            QuarantinedRow,
+            r#"
+            SELECT * FROM quarantine WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_optional(pool)
+        .await?;
+        Ok(row)
+    }
+
+    /// Lists quarantined rows that have not been reprocessed yet, oldest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `limit` - The maximum number of rows to return.
+    pub async fn list_pending(pool: &sqlx::PgPool, limit: i64) -> Result<Vec<Self>, sqlx::Error> {
+        let rows = sqlx::query_as!(
+            QuarantinedRow,
+            r#"
+            SELECT * FROM quarantine
+            WHERE reprocessed_at IS NULL
+            ORDER BY created_at, id
+            LIMIT $1
+            "#,
+            limit
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(rows)
+    }
+
+    /// Lists quarantined rows matching `filter`, oldest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `filter` - The selection criteria.
+    pub async fn list(
+        pool: &sqlx::PgPool,
+        filter: &QuarantineFilter,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        let rows = sqlx::query_as!(
+            QuarantinedRow,
+            r#"
+            SELECT * FROM quarantine
+            WHERE ($1::text IS NULL OR source = $1)
+              AND ($2::text IS NULL OR symbol = $2)
+              AND ($3::text IS NULL OR interval = $3)
+              AND ($4::text IS NULL OR dataset = $4)
+              AND ($5::timestamptz IS NULL OR created_at >= $5)
+              AND ($6::timestamptz IS NULL OR created_at < $6)
+              AND ($7 OR reprocessed_at IS NULL)
+            ORDER BY created_at, id
+            LIMIT $8
+            "#,
+            filter.source,
+            filter.symbol,
+            filter.interval,
+            filter.dataset,
+            filter.since,
+            filter.until,
+            filter.include_reprocessed,
+            filter.limit
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(rows)
+    }
+
+    /// Marks the row as successfully reprocessed.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    pub async fn mark_reprocessed(&self, pool: &sqlx::PgPool) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE quarantine SET reprocessed_at = NOW() WHERE id = $1
+            "#,
+            self.id
+        )
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Permanently deletes the row, e.g. after review concluded it is garbage.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    pub async fn discard(&self, pool: &sqlx::PgPool) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            DELETE FROM quarantine WHERE id = $1
+            "#,
+            self.id
+        )
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Re-parses the raw payload as a Kline message, validates it, and upserts it
+    /// into the row's dataset.
+    ///
+    /// The payload may either be a serialized [`SerdableKlineData`] or a raw
+    /// WebSocket [`Payload`] envelope. On success the row is marked as reprocessed and the stored Kline is returned.
+    /// If the payload still fails to parse or validate, `Ok(Err(reason))` is
+    /// returned and the row is left in the quarantine.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    pub async fn reprocess(
+        &self,
+        pool: &sqlx::PgPool,
+    ) -> Result<Result<KlineData, String>, sqlx::Error> {
+        let message = match parse_raw_kline(&self.raw_payload) {
+            Ok(message) => message,
+            Err(e) => return Ok(Err(e)),
+        };
+        let kline = match message.to_validated_kline_data() {
+            Ok(kline) => kline.with_dataset(&self.dataset),
+            Err(e) => return Ok(Err(e.to_string())),
+        };
+        let stored = kline.upsert(pool).await?;
+        self.mark_reprocessed(pool).await?;
+        Ok(Ok(stored))
+    }
+}
+
+/// Parses a raw Kline payload, accepting both serialized [`SerdableKlineData`] and
+/// WebSocket [`Payload`] envelopes.
+pub fn parse_raw_kline(raw_payload: &str) -> Result<SerdableKlineData, String> {
+    if let Ok(message) = serde_json::from_str::<SerdableKlineData>(raw_payload) {
+        return Ok(message);
+    }
+    serde_json::from_str::<Payload>(raw_payload)
+        .map_err(|e| e.to_string())
+        .and_then(|payload| {
+            payload
+                .to_serializable_kline_data()
+                .map_err(|e| e.to_string())
+        })
+}
@@ -0,0 +1,121 @@
+use std::str::FromStr;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+
+use super::KlineData;
+use super::cache::KlineCache;
+use super::coverage::{Coverage, coverage};
+
+/// The SQLSTATE Postgres reports for a write attempted in a read-only transaction.
+const READ_ONLY_SQL_TRANSACTION: &str = "25006";
+
+/// A connection pool for analytics services that refuses write operations.
+///
+/// Every connection is opened with `default_transaction_read_only` enabled, so
+/// Postgres rejects writes even if the underlying pool is passed to a write
+/// operation such as [`KlineData::upsert`]. Combined with a database role that
+/// only has `SELECT` privileges, this keeps read-only services from modifying
+/// stored data.
+///
+/// The common queries are available as methods. Other read operations take the
+/// underlying pool from [`pool`](Self::pool).
+#[derive(Debug, Clone)]
+pub struct ReadOnlyPool {
+    /// The underlying read-only pool.
+    pool: PgPool,
+    /// The cache range queries are read through, if any.
+    cache: Option<Arc<KlineCache>>,
+}
+
+impl ReadOnlyPool {
+    /// Connects to the database with read-only sessions.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The PostgreSQL connection string.
+    pub async fn connect(url: &str) -> Result<Self, sqlx::Error> {
+        let options =
+            PgConnectOptions::from_str(url)?.options([("default_transaction_read_only", "on")]);
+        let pool = PgPoolOptions::new().connect_with(options).await?;
+        Ok(Self { pool, cache: None })
+    }
+
+    /// Reads [`list_klines`](Self::list_klines) through a cache, e.g. to absorb
+    /// the load of dashboards querying the same ranges.
+    pub fn with_cache(mut self, cache: Arc<KlineCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Returns the underlying pool, e.g. to pass it to read operations without a
+    /// method here. Writes through it are rejected by the database.
+    pub fn pool(&self) -> &PgPool {
+        &self.pool
+    }
+
+    /// Retrieves the candles of a range, see [`KlineData::list_range`], through the
+    /// cache if one is set.
+    pub async fn list_klines(
+        &self,
+        symbol: &str,
+        interval: &str,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        dataset: &str,
+        exchange: &str,
+    ) -> Result<Vec<KlineData>, sqlx::Error> {
+        match &self.cache {
+            Some(cache) => {
+                cache
+                    .list_range(
+                        &self.pool, symbol, interval, start_time, end_time, dataset, exchange,
+                    )
+                    .await
+            }
+            None => {
+                KlineData::list_range(
+                    &self.pool, symbol, interval, start_time, end_time, dataset, exchange,
+                )
+                .await
+            }
+        }
+    }
+
+    /// Retrieves a candle as it was stored at a given time, see [`KlineData::as_of`].
+    pub async fn kline_as_of(
+        &self,
+        symbol: &str,
+        interval: &str,
+        start_time: DateTime<Utc>,
+        as_of_ts: DateTime<Utc>,
+        dataset: &str,
+        exchange: &str,
+    ) -> Result<Option<KlineData>, sqlx::Error> {
+        KlineData::as_of(
+            &self.pool, symbol, interval, start_time, as_of_ts, dataset, exchange,
+        )
+        .await
+    }
+
+    /// Summarizes the stored candles, see [`coverage`].
+    pub async fn coverage(
+        &self,
+        symbol: &str,
+        interval: &str,
+        dataset: &str,
+        exchange: &str,
+    ) -> Result<Coverage, sqlx::Error> {
+        coverage(&self.pool, symbol, interval, dataset, exchange).await
+    }
+}
+
+/// Returns true if `error` is Postgres refusing a write in a read-only session.
+pub fn is_read_only_violation(error: &sqlx::Error) -> bool {
+    error
+        .as_database_error()
+        .and_then(|e| e.code())
+        .is_some_and(|code| code == READ_ONLY_SQL_TRANSACTION)
+}
@@ -0,0 +1,87 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::models::metrics::StatementTimer;
+
+/// The regime labels of a candle, stored in `candle_regimes`.
+///
+/// The labels are stored as text (`trend`/`range` and `low`/`normal`/`high`) next
+/// to the indicator values they were derived from, so they can be filtered on and
+/// re-bucketed in SQL.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CandleRegime {
+    /// The trading symbol.
+    pub symbol: String,
+    /// The Kline interval.
+    pub interval: String,
+    /// The dataset label.
+    pub dataset: String,
+    /// The start time of the tagged candle.
+    pub start_time: DateTime<Utc>,
+    /// Whether the market was trending or ranging.
+    pub trend: String,
+    /// The realized-volatility bucket.
+    pub volatility: String,
+    /// The Average Directional Index at the candle.
+    pub adx: f64,
+    /// The annualized realized volatility at the candle.
+    pub realized_volatility: f64,
+}
+
+impl CandleRegime {
+    /// Stores the labels of a candle, replacing previous labels.
+    ///
+    /// # Arguments
+    ///
+    /// * `executor` - The database connection pool or connection.
+    pub async fn upsert<'e, E>(&self, executor: E) -> Result<(), sqlx::Error>
+    where
+        E: sqlx::PgExecutor<'e>,
+    {
+        sqlx::query!(
+            r#"
+            INSERT INTO candle_regimes
+                (symbol, interval, dataset, start_time, trend, volatility, adx, realized_volatility)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (symbol, interval, dataset, start_time) DO UPDATE
+            SET
+                trend = EXCLUDED.trend,
+                volatility = EXCLUDED.volatility,
+                adx = EXCLUDED.adx,
+                realized_volatility = EXCLUDED.realized_volatility,
+                computed_at = NOW()
+            "#,
+            self.symbol,
+            self.interval,
+            self.dataset,
+            self.start_time,
+            self.trend,
+            self.volatility,
+            self.adx,
+            self.realized_volatility
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// Stores the labels of many candles in one transaction.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `regimes` - The labels to store.
+    ///
+    /// # Returns
+    ///
+    /// The number of stored labels.
+    pub async fn upsert_batch(pool: &sqlx::PgPool, regimes: &[Self]) -> Result<usize, sqlx::Error> {
+        let _timer = StatementTimer::start("candle_regimes.upsert_batch");
+        let mut tx = pool.begin().await?;
+        for regime in regimes {
+            regime.upsert(&mut *tx).await?;
+        }
+        tx.commit().await?;
+        Ok(regimes.len())
+    }
+}
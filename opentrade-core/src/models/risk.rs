@@ -0,0 +1,101 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::models::metrics::StatementTimer;
+
+/// An annualized rolling volatility, stored in `volatility_metrics`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VolatilityMetric {
+    /// The trading symbol.
+    pub symbol: String,
+    /// The Kline interval of the returns.
+    pub interval: String,
+    /// The dataset label.
+    pub dataset: String,
+    /// The number of returns in the window.
+    pub window_size: i32,
+    /// The exclusive end of the window.
+    pub as_of: DateTime<Utc>,
+    /// The annualized standard deviation of the log returns.
+    pub volatility: f64,
+}
+
+impl VolatilityMetric {
+    /// Stores the metric, replacing a previous value for the same window.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    pub async fn upsert(&self, pool: &sqlx::PgPool) -> Result<(), sqlx::Error> {
+        let _timer = StatementTimer::start("volatility_metrics.upsert");
+        sqlx::query!(
+            r#"
+            INSERT INTO volatility_metrics (symbol, interval, dataset, window_size, as_of, volatility)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (symbol, interval, dataset, window_size, as_of) DO UPDATE
+            SET volatility = EXCLUDED.volatility, computed_at = NOW()
+            "#,
+            self.symbol,
+            self.interval,
+            self.dataset,
+            self.window_size,
+            self.as_of,
+            self.volatility
+        )
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+}
+
+/// The return correlation of a pair of symbols, stored in `correlation_metrics`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CorrelationMetric {
+    /// The symbol of the pair that sorts first.
+    pub symbol_a: String,
+    /// The symbol of the pair that sorts last.
+    pub symbol_b: String,
+    /// The Kline interval of the returns.
+    pub interval: String,
+    /// The dataset label.
+    pub dataset: String,
+    /// The number of returns in the window.
+    pub window_size: i32,
+    /// The exclusive end of the window.
+    pub as_of: DateTime<Utc>,
+    /// The Pearson correlation of the log returns.
+    pub correlation: f64,
+    /// The number of returns both symbols had in the window.
+    pub samples: i32,
+}
+
+impl CorrelationMetric {
+    /// Stores the metric, replacing a previous value for the same pair and window.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    pub async fn upsert(&self, pool: &sqlx::PgPool) -> Result<(), sqlx::Error> {
+        let _timer = StatementTimer::start("correlation_metrics.upsert");
+        sqlx::query!(
+            r#"
+            INSERT INTO correlation_metrics
+                (symbol_a, symbol_b, interval, dataset, window_size, as_of, correlation, samples)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (symbol_a, symbol_b, interval, dataset, window_size, as_of) DO UPDATE
+            SET correlation = EXCLUDED.correlation, samples = EXCLUDED.samples, computed_at = NOW()
+            "#,
+            self.symbol_a,
+            self.symbol_b,
+            self.interval,
+            self.dataset,
+            self.window_size,
+            self.as_of,
+            self.correlation,
+            self.samples
+        )
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,120 @@
+use std::fmt;
+
+/// The database schema version this build of `opentrade-core` expects.
+///
+/// This is the version of the latest migration in `migrations/` that changes the
+/// schema. Such migrations insert their version into the `schema_version` table,
+/// and this constant must be bumped alongside them.
+pub const SCHEMA_VERSION: i64 = 20250806090000;
+
+/// The command hinted at when the database schema is behind the code.
+const MIGRATE_HINT: &str = "run `sqlx migrate run` to apply the pending migrations";
+
+/// Errors reported by [`check_schema_version`].
+#[derive(Debug)]
+pub enum SchemaVersionError {
+    /// The database has no `schema_version` table, or it is empty.
+    Missing,
+    /// The database schema is older than the code expects.
+    Outdated { database: i64, expected: i64 },
+    /// The database schema is newer than the code supports.
+    TooNew { database: i64, expected: i64 },
+    /// The schema version could not be read.
+    Database(sqlx::Error),
+}
+
+impl fmt::Display for SchemaVersionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaVersionError::Missing => write!(
+                f,
+                "database has no schema version (expected {}); {}",
+                SCHEMA_VERSION, MIGRATE_HINT
+            ),
+            SchemaVersionError::Outdated { database, expected } => write!(
+                f,
+                "database schema version {} is older than the expected version {}; {}",
+                database, expected, MIGRATE_HINT
+            ),
+            SchemaVersionError::TooNew { database, expected } => write!(
+                f,
+                "database schema version {} is newer than the supported version {}; upgrade this binary",
+                database, expected
+            ),
+            SchemaVersionError::Database(e) => write!(f, "failed to read schema version: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SchemaVersionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SchemaVersionError::Database(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Compares a database schema version against the version the code expects.
+pub fn check_compatibility(database: i64, expected: i64) -> Result<(), SchemaVersionError> {
+    match database.cmp(&expected) {
+        std::cmp::Ordering::Less => Err(SchemaVersionError::Outdated { database, expected }),
+        std::cmp::Ordering::Greater => Err(SchemaVersionError::TooNew { database, expected }),
+        std::cmp::Ordering::Equal => Ok(()),
+    }
+}
+
+/// Reads the latest schema version recorded in the database.
+///
+/// Returns `Ok(None)` if the `schema_version` table does not exist or is empty.
+///
+/// # Arguments
+///
+/// * `pool` - The database connection pool.
+pub async fn database_schema_version(pool: &sqlx::PgPool) -> Result<Option<i64>, sqlx::Error> {
+    let result = sqlx::query_scalar!(
+        r#"
+        SELECT MAX(version) FROM schema_version
+        "#
+    )
+    .fetch_one(pool)
+    .await;
+    match result {
+        Ok(version) => Ok(version),
+        Err(sqlx::Error::Database(e)) if e.code().as_deref() == Some("42P01") => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Checks that the database schema matches [`SCHEMA_VERSION`].
+///
+/// Components call this at startup so that a mismatch between code and schema
+/// fails fast with a clear message instead of surfacing as query errors later.
+///
+/// # Arguments
+///
+/// * `pool` - The database connection pool.
+pub async fn check_schema_version(pool: &sqlx::PgPool) -> Result<(), SchemaVersionError> {
+    let database = database_schema_version(pool)
+        .await
+        .map_err(SchemaVersionError::Database)?
+        .ok_or(SchemaVersionError::Missing)?;
+    check_compatibility(database, SCHEMA_VERSION)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_compatibility() {
+        assert!(check_compatibility(SCHEMA_VERSION, SCHEMA_VERSION).is_ok());
+
+        let outdated = check_compatibility(1, SCHEMA_VERSION).unwrap_err();
+        assert!(matches!(outdated, SchemaVersionError::Outdated { .. }));
+        assert!(outdated.to_string().contains("sqlx migrate run"));
+
+        let too_new = check_compatibility(SCHEMA_VERSION + 1, SCHEMA_VERSION).unwrap_err();
+        assert!(matches!(too_new, SchemaVersionError::TooNew { .. }));
+    }
+}
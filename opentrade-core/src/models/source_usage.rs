@@ -0,0 +1,91 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+
+use crate::models::metrics::StatementTimer;
+
+/// The requests sent to and rows downloaded from a data source with one API key
+/// on one day (UTC), stored in `source_usage`.
+#[derive(FromRow, Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SourceUsage {
+    /// The data source, as returned by
+    /// [`MarketDataSource::name`](crate::data_source::exchange::MarketDataSource::name).
+    pub source: String,
+    /// The label of the API key (e.g., "default").
+    pub api_key: String,
+    /// The day the usage was counted on.
+    pub day: NaiveDate,
+    /// The number of requests sent.
+    pub requests: i64,
+    /// The number of rows downloaded.
+    pub rows: i64,
+    /// The time of the last recorded request.
+    pub updated_at: DateTime<Utc>,
+}
+
+impl SourceUsage {
+    /// Adds requests and rows to the usage of a day and returns the new totals,
+    /// including those recorded by other processes.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `source` - The data source.
+    /// * `api_key` - The label of the API key.
+    /// * `day` - The day to count the usage on.
+    /// * `requests` - The number of requests to add.
+    /// * `rows` - The number of rows to add.
+    pub async fn record(
+        pool: &sqlx::PgPool,
+        source: &str,
+        api_key: &str,
+        day: NaiveDate,
+        requests: i64,
+        rows: i64,
+    ) -> Result<Self, sqlx::Error> {
+        let _timer = StatementTimer::start("source_usage.record");
+        let usage = sqlx::query_as!(
+            SourceUsage,
+            r#"
+            INSERT INTO source_usage (source, api_key, day, requests, rows)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (source, api_key, day) DO UPDATE
+            SET
+                requests = source_usage.requests + EXCLUDED.requests,
+                rows = source_usage.rows + EXCLUDED.rows,
+                updated_at = NOW()
+            RETURNING *
+            "#,
+            source,
+            api_key,
+            day,
+            requests,
+            rows
+        )
+        .fetch_one(pool)
+        .await?;
+        Ok(usage)
+    }
+
+    /// Lists the usage of every source and API key on a day.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `day` - The day.
+    pub async fn list_day(pool: &sqlx::PgPool, day: NaiveDate) -> Result<Vec<Self>, sqlx::Error> {
+        let _timer = StatementTimer::start("source_usage.list_day");
+        let usage = sqlx::query_as!(
+            SourceUsage,
+            r#"
+            SELECT * FROM source_usage
+            WHERE day = $1
+            ORDER BY source, api_key
+            "#,
+            day
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(usage)
+    }
+}
@@ -0,0 +1,115 @@
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+
+/// The status recorded for symbols that are no longer listed by the exchange.
+pub const DELISTED: &str = "DELISTED";
+
+/// The status Binance reports for symbols whose trading has been halted,
+/// including pairs that have been delisted but are still returned by exchangeInfo.
+pub const BREAK: &str = "BREAK";
+
+/// Returns false for statuses under which a symbol does not produce new data.
+pub fn is_active_status(status: &str) -> bool {
+    status != DELISTED && status != BREAK
+}
+
+/// A change in the trading status of a symbol, stored in `symbol_status_history`.
+#[derive(FromRow, Debug, Clone, PartialEq, Eq)]
+pub struct SymbolStatus {
+    /// The unique identifier of the status change.
+    pub id: i64,
+    /// The trading symbol (e.g., "BTCUSDT").
+    pub symbol: String,
+    /// The exchange status (e.g., "TRADING", "BREAK") or [`DELISTED`].
+    pub status: String,
+    /// The timestamp when the status was first observed.
+    pub observed_at: DateTime<Utc>,
+}
+
+impl SymbolStatus {
+    /// Returns false if the symbol is delisted or halted ([`DELISTED`] or [`BREAK`])
+    /// and will not produce new data.
+    pub fn is_active(&self) -> bool {
+        is_active_status(&self.status)
+    }
+
+    /// Records the observed status of a symbol if it differs from the latest one.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `symbol` - The trading symbol.
+    /// * `status` - The observed status.
+    ///
+    /// # Returns
+    ///
+    /// The new history row if the status changed, or `None` if it is unchanged.
+    pub async fn record(
+        pool: &sqlx::PgPool,
+        symbol: &str,
+        status: &str,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        if Self::latest(pool, symbol)
+            .await?
+            .is_some_and(|latest| latest.status == status)
+        {
+            return Ok(None);
+        }
+        let row = sqlx::query_as!(
+            SymbolStatus,
+            r#"
+            INSERT INTO symbol_status_history (symbol, status)
+            VALUES ($1, $2)
+            RETURNING *
+            "#,
+            symbol,
+            status
+        )
+        .fetch_one(pool)
+        .await?;
+        Ok(Some(row))
+    }
+
+    /// Retrieves the latest known status of a symbol.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `symbol` - The trading symbol.
+    pub async fn latest(pool: &sqlx::PgPool, symbol: &str) -> Result<Option<Self>, sqlx::Error> {
+        let row = sqlx::query_as!(
+            SymbolStatus,
+            r#"
+            SELECT * FROM symbol_status_history
+            WHERE symbol = $1
+            ORDER BY observed_at DESC, id DESC
+            LIMIT 1
+            "#,
+            symbol
+        )
+        .fetch_optional(pool)
+        .await?;
+        Ok(row)
+    }
+
+    /// Lists all status changes of a symbol, oldest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `symbol` - The trading symbol.
+    pub async fn history(pool: &sqlx::PgPool, symbol: &str) -> Result<Vec<Self>, sqlx::Error> {
+        let rows = sqlx::query_as!(
+            SymbolStatus,
+            r#"
+            SELECT * FROM symbol_status_history
+            WHERE symbol = $1
+            ORDER BY observed_at, id
+            "#,
+            symbol
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(rows)
+    }
+}
@@ -0,0 +1,142 @@
+//! Job ids that tie database writes back to the backfill batch or streaming
+//! session that issued them.
+//!
+//! A [`JobId`] is generated per unit of work and made current for everything the
+//! work awaits with [`scope`]. Writes that go through [`begin`] then tag their
+//! transaction: the tagging statement carries the id as a SQL comment, and the id
+//! becomes the transaction's `application_name`.
+//!
+//! `pg_stat_statements` ignores comments when grouping statements, so its entries
+//! keep the text (and comment) of the first execution only. To attribute a slow
+//! write to a job, log slow statements with `log_min_duration_statement` and `%a`
+//! in `log_line_prefix`, or look the job up in `pg_stat_activity.application_name`
+//! while it runs.
+
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use chrono::Utc;
+use sqlx::{PgPool, Postgres, Transaction};
+
+use crate::models::metrics::StatementTimer;
+
+/// The number of job ids generated by this process.
+static GENERATED: AtomicU64 = AtomicU64::new(0);
+
+/// The maximum length of an `application_name` (`NAMEDATALEN - 1`).
+const MAX_APPLICATION_NAME_LEN: usize = 63;
+
+tokio::task_local! {
+    static CURRENT_JOB: JobId;
+}
+
+/// The id of a backfill batch or streaming session, e.g.
+/// `backfill-20250728T090000-4711-3`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct JobId(String);
+
+impl JobId {
+    /// Generates an id for a job of `kind` (e.g., "backfill", "stream"), unique
+    /// across processes of the same host.
+    pub fn new(kind: &str) -> Self {
+        Self(format!(
+            "{}-{}-{}-{}",
+            kind,
+            Utc::now().format("%Y%m%dT%H%M%S"),
+            std::process::id(),
+            GENERATED.fetch_add(1, Ordering::Relaxed)
+        ))
+    }
+
+    /// Returns the id as a string.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns the id as a SQL comment, in the `key='value'` form of sqlcommenter.
+    pub fn sql_comment(&self) -> String {
+        let id = self.0.replace('\'', "").replace("*/", "");
+        format!("/* opentrade_job='{}' */", id)
+    }
+
+    /// Returns the id prefixed with the application, truncated to the length
+    /// PostgreSQL keeps of an `application_name`.
+    fn application_name(&self) -> String {
+        let mut name = format!("opentrade {}", self.0);
+        if name.len() > MAX_APPLICATION_NAME_LEN {
+            let mut end = MAX_APPLICATION_NAME_LEN;
+            while !name.is_char_boundary(end) {
+                end -= 1;
+            }
+            name.truncate(end);
+        }
+        name
+    }
+}
+
+impl fmt::Display for JobId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Runs `future` with `job` as the current job.
+///
+/// The job is current for everything the future awaits on its task, but not for
+/// tasks it spawns.
+pub async fn scope<F: Future>(job: JobId, future: F) -> F::Output {
+    CURRENT_JOB.scope(job, future).await
+}
+
+/// Returns the current job, if any.
+pub fn current() -> Option<JobId> {
+    CURRENT_JOB.try_with(JobId::clone).ok()
+}
+
+/// Begins a transaction and tags it with the current job, if any.
+///
+/// # Arguments
+///
+/// * `pool` - The database connection pool.
+pub async fn begin(pool: &PgPool) -> Result<Transaction<'static, Postgres>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    if let Some(job) = current() {
+        let _timer = StatementTimer::start("trace.tag");
+        // The comment varies per job, so the statement cannot be checked at compile time.
+        let sql = format!(
+            "{} SELECT set_config('application_name', $1, true)",
+            job.sql_comment()
+        );
+        sqlx::query(&sql)
+            .bind(job.application_name())
+            .execute(&mut *tx)
+            .await?;
+    }
+    Ok(tx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_scope_sets_the_current_job() {
+        let job = JobId::new("backfill");
+        assert!(job.as_str().starts_with("backfill-"));
+        assert_ne!(job, JobId::new("backfill"));
+        assert_eq!(current(), None);
+        let inner = scope(job.clone(), async { current() }).await;
+        assert_eq!(inner, Some(job));
+        assert_eq!(current(), None);
+    }
+
+    #[test]
+    fn test_sql_comment_and_application_name() {
+        let job = JobId("stream-x'*/".to_string());
+        assert_eq!(job.sql_comment(), "/* opentrade_job='stream-x' */");
+        let job = JobId("stream-".repeat(20));
+        assert_eq!(job.application_name().len(), MAX_APPLICATION_NAME_LEN);
+        assert!(job.application_name().starts_with("opentrade stream-"));
+    }
+}
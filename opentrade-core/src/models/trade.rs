@@ -0,0 +1,41 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::types::BigDecimal as Decimal;
+
+/// The taker side of a trade that bought from the order book.
+pub const TRADE_SIDE_BUY: &str = "BUY";
+
+/// The taker side of a trade that sold to the order book.
+pub const TRADE_SIDE_SELL: &str = "SELL";
+
+/// A single trade of a symbol, mapped from the trade messages of an exchange.
+///
+/// Trades are not stored; streams aggregate them into [`KlineData`](super::KlineData)
+/// (see [`coinbase`](crate::data_source::coinbase)).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TradeData {
+    /// The trading symbol (e.g., "BTCUSD").
+    pub symbol: String,
+    /// The exchange's trade id, increasing per symbol.
+    pub trade_id: i64,
+    /// The execution price.
+    pub price: Decimal,
+    /// The traded quantity of the base asset.
+    pub quantity: Decimal,
+    /// The taker side: [`TRADE_SIDE_BUY`] or [`TRADE_SIDE_SELL`].
+    pub side: String,
+    /// The execution time.
+    pub trade_time: DateTime<Utc>,
+}
+
+impl TradeData {
+    /// Returns the traded quantity of the quote asset.
+    pub fn quote_quantity(&self) -> Decimal {
+        &self.price * &self.quantity
+    }
+
+    /// Returns `true` if the taker bought from the order book.
+    pub fn is_buy(&self) -> bool {
+        self.side == TRADE_SIDE_BUY
+    }
+}
@@ -0,0 +1,144 @@
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+
+use crate::models::metrics::StatementTimer;
+use crate::models::trace;
+
+/// A symbol of a named watchlist, stored in `watchlists`.
+#[derive(FromRow, Debug, Clone, PartialEq, Eq)]
+pub struct WatchlistEntry {
+    /// The name of the watchlist (e.g., "majors").
+    pub name: String,
+    /// The trading symbol (e.g., "BTCUSDT").
+    pub symbol: String,
+    /// The time the symbol was added to the watchlist.
+    pub added_at: DateTime<Utc>,
+}
+
+impl WatchlistEntry {
+    /// Adds symbols to a watchlist, creating it if needed. Symbols already on the
+    /// watchlist are kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `name` - The name of the watchlist.
+    /// * `symbols` - The symbols to add.
+    ///
+    /// # Returns
+    ///
+    /// The number of symbols that were not on the watchlist yet.
+    pub async fn add(
+        pool: &sqlx::PgPool,
+        name: &str,
+        symbols: &[String],
+    ) -> Result<u64, sqlx::Error> {
+        let _timer = StatementTimer::start("watchlists.add");
+        let mut tx = trace::begin(pool).await?;
+        let mut added = 0;
+        for symbol in symbols {
+            added += sqlx::query!(
+                r#"
+                INSERT INTO watchlists (name, symbol)
+                VALUES ($1, $2)
+                ON CONFLICT (name, symbol) DO NOTHING
+                "#,
+                name,
+                symbol
+            )
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        }
+        tx.commit().await?;
+        Ok(added)
+    }
+
+    /// Removes symbols from a watchlist.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `name` - The name of the watchlist.
+    /// * `symbols` - The symbols to remove.
+    ///
+    /// # Returns
+    ///
+    /// The number of removed symbols.
+    pub async fn remove(
+        pool: &sqlx::PgPool,
+        name: &str,
+        symbols: &[String],
+    ) -> Result<u64, sqlx::Error> {
+        let _timer = StatementTimer::start("watchlists.remove");
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM watchlists
+            WHERE name = $1 AND symbol = ANY($2)
+            "#,
+            name,
+            symbols
+        )
+        .execute(pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Deletes a watchlist with all its symbols.
+    ///
+    /// # Returns
+    ///
+    /// The number of removed symbols.
+    pub async fn delete(pool: &sqlx::PgPool, name: &str) -> Result<u64, sqlx::Error> {
+        let _timer = StatementTimer::start("watchlists.delete");
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM watchlists
+            WHERE name = $1
+            "#,
+            name
+        )
+        .execute(pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Lists the symbols of a watchlist in alphabetical order. The list is empty
+    /// if the watchlist does not exist.
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - The database connection pool.
+    /// * `name` - The name of the watchlist.
+    pub async fn symbols(pool: &sqlx::PgPool, name: &str) -> Result<Vec<String>, sqlx::Error> {
+        let _timer = StatementTimer::start("watchlists.symbols");
+        sqlx::query_scalar!(
+            r#"
+            SELECT symbol FROM watchlists
+            WHERE name = $1
+            ORDER BY symbol
+            "#,
+            name
+        )
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Lists the stored watchlists with their number of symbols, by name.
+    pub async fn names(pool: &sqlx::PgPool) -> Result<Vec<(String, i64)>, sqlx::Error> {
+        let _timer = StatementTimer::start("watchlists.names");
+        let rows = sqlx::query!(
+            r#"
+            SELECT name, COUNT(*) AS "symbols!" FROM watchlists
+            GROUP BY name
+            ORDER BY name
+            "#
+        )
+        .fetch_all(pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.name, row.symbols))
+            .collect())
+    }
+}
@@ -0,0 +1,192 @@
+//! # Candlestick Charts
+//!
+//! This module renders stored Kline data as a candlestick chart with a volume
+//! panel below it, e.g. to attach to alerts or quick reports. It is only available
+//! with the `plot` feature.
+//!
+//! The image format is chosen from the file extension: `.svg` files are written as
+//! SVG and every other extension as a bitmap (PNG for `.png`).
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use opentrade_core::models::{DEFAULT_DATASET, DEFAULT_EXCHANGE, KlineData};
+//! use opentrade_core::plot::{ChartOptions, render_chart};
+//! use chrono::{Duration, Utc};
+//! # use anyhow::Result;
+//!
+//! # async fn example(pool: sqlx::PgPool) -> Result<()> {
+//! let end = Utc::now();
+//! let start = end - Duration::days(7);
+//! let klines = KlineData::list_range(
+//!     &pool,
+//!     "BTCUSDT",
+//!     "1h",
+//!     start,
+//!     end,
+//!     DEFAULT_DATASET,
+//!     DEFAULT_EXCHANGE,
+//! )
+//! .await?;
+//! render_chart(&klines, "btcusdt-1h.png", &ChartOptions::default())?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use sqlx::types::BigDecimal as Decimal;
+
+use crate::models::KlineData;
+
+/// Size and labeling of a rendered chart.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChartOptions {
+    /// The image width in pixels.
+    pub width: u32,
+    /// The image height in pixels.
+    pub height: u32,
+    /// The chart caption. Defaults to the symbol and interval of the candles.
+    pub title: Option<String>,
+}
+
+impl Default for ChartOptions {
+    fn default() -> Self {
+        Self {
+            width: 1200,
+            height: 800,
+            title: None,
+        }
+    }
+}
+
+/// Renders candles, in start-time order, to an image at `path`.
+///
+/// # Errors
+///
+/// Returns an error if `klines` is empty or the image cannot be drawn or written.
+pub fn render_chart(
+    klines: &[KlineData],
+    path: impl AsRef<Path>,
+    options: &ChartOptions,
+) -> Result<()> {
+    let path = path.as_ref();
+    let first = klines.first().context("No candles to plot")?;
+    let title = options
+        .title
+        .clone()
+        .unwrap_or_else(|| format!("{} {}", first.symbol, first.interval));
+    let size = (options.width, options.height);
+    let is_svg = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("svg"));
+    let result = if is_svg {
+        draw(SVGBackend::new(path, size).into_drawing_area(), klines, &title)
+    } else {
+        draw(BitMapBackend::new(path, size).into_drawing_area(), klines, &title)
+    };
+    result.with_context(|| format!("Failed to render chart to {}", path.display()))
+}
+
+/// Converts a decimal to a plot coordinate.
+fn to_f64(value: &Decimal) -> f64 {
+    value.to_string().parse().unwrap_or(0.0)
+}
+
+/// Draws the price panel on the top three quarters and the volume panel below.
+fn draw<DB>(root: DrawingArea<DB, Shift>, klines: &[KlineData], title: &str) -> Result<()>
+where
+    DB: DrawingBackend,
+    DB::ErrorType: 'static,
+{
+    root.fill(&WHITE)?;
+    let (_, height) = root.dim_in_pixel();
+    let (price_area, volume_area) = root.split_vertically(height * 3 / 4);
+
+    let start: DateTime<Utc> = klines[0].start_time;
+    let end: DateTime<Utc> = klines[klines.len() - 1].end_time;
+    let low = klines
+        .iter()
+        .map(|kline| to_f64(&kline.low))
+        .fold(f64::INFINITY, f64::min);
+    let high = klines
+        .iter()
+        .map(|kline| to_f64(&kline.high))
+        .fold(f64::NEG_INFINITY, f64::max);
+    let padding = ((high - low) * 0.05).max(f64::EPSILON);
+    let max_volume = klines
+        .iter()
+        .map(|kline| to_f64(&kline.volume))
+        .fold(0.0, f64::max)
+        .max(f64::EPSILON);
+
+    let (width, _) = price_area.dim_in_pixel();
+    let candle_width = (width as f64 * 0.8 / klines.len() as f64).clamp(1.0, 20.0) as u32;
+
+    let mut prices = ChartBuilder::on(&price_area)
+        .caption(title, ("sans-serif", 24))
+        .margin(10)
+        .x_label_area_size(0)
+        .y_label_area_size(70)
+        .build_cartesian_2d(start..end, (low - padding)..(high + padding))?;
+    prices.configure_mesh().y_desc("Price").draw()?;
+    prices.draw_series(klines.iter().map(|kline| {
+        CandleStick::new(
+            kline.start_time,
+            to_f64(&kline.open),
+            to_f64(&kline.high),
+            to_f64(&kline.low),
+            to_f64(&kline.close),
+            GREEN.filled(),
+            RED.filled(),
+            candle_width,
+        )
+    }))?;
+
+    let mut volumes = ChartBuilder::on(&volume_area)
+        .margin(10)
+        .x_label_area_size(30)
+        .y_label_area_size(70)
+        .build_cartesian_2d(start..end, 0.0..max_volume)?;
+    volumes.configure_mesh().y_desc("Volume").draw()?;
+    volumes.draw_series(klines.iter().map(|kline| {
+        let color = if kline.close >= kline.open { GREEN } else { RED };
+        Rectangle::new(
+            [(kline.start_time, 0.0), (kline.end_time, to_f64(&kline.volume))],
+            color.mix(0.6).filled(),
+        )
+    }))?;
+
+    root.present()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingest::synthetic::SyntheticKlines;
+
+    #[test]
+    fn test_renders_svg_with_title() {
+        let klines = SyntheticKlines::new(&["BTCUSDT"]).final_klines(30);
+        let path =
+            std::env::temp_dir().join(format!("opentrade-chart-{}.svg", std::process::id()));
+
+        render_chart(&klines, &path, &ChartOptions::default()).unwrap();
+
+        let svg = std::fs::read_to_string(&path).unwrap();
+        assert!(svg.contains("<svg"));
+        assert!(svg.contains("BTCUSDT 1m"));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_empty_range_is_an_error() {
+        let path = std::env::temp_dir().join("opentrade-empty-chart.svg");
+        assert!(render_chart(&[], &path, &ChartOptions::default()).is_err());
+    }
+}
@@ -0,0 +1,159 @@
+//! # Time Utilities
+//!
+//! This module converts between milliseconds since the Unix epoch, the unit of
+//! exchange APIs and [`SerdableKlineData`](crate::models::SerdableKlineData), and
+//! [`DateTime<Utc>`], the type stored in the database. It also aligns times to
+//! candle buckets and iterates over the bucket starts of a range.
+//!
+//! Buckets of length `step` start at multiples of `step` since the epoch, which
+//! matches the candles of every fixed-size interval. Weekly and monthly candles
+//! open on calendar boundaries instead; see [`Interval`](crate::models::Interval).
+//!
+//! ## Example
+//!
+//! ```rust
+//! use chrono::Duration;
+//! use opentrade_core::time::{align_down, buckets, from_millis};
+//!
+//! # fn example() -> opentrade_core::error::Result<()> {
+//! let time = from_millis(1_640_995_230_000)?; // 2022-01-01 00:00:30 UTC
+//! let start = align_down(time, Duration::minutes(1));
+//! assert_eq!(start.timestamp_millis(), 1_640_995_200_000);
+//! assert_eq!(buckets(start, time, Duration::seconds(10)).count(), 3);
+//! # Ok(())
+//! # }
+//! ```
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::error::{Error, Result};
+
+/// Converts milliseconds since the epoch to a timestamp.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidTimestamp`] if the time is outside the range of
+/// [`DateTime<Utc>`].
+pub fn from_millis(millis: i64) -> Result<DateTime<Utc>> {
+    DateTime::from_timestamp_millis(millis).ok_or(Error::InvalidTimestamp(millis))
+}
+
+/// Converts unsigned milliseconds since the epoch, as used by the Binance API,
+/// to a timestamp.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidTimestamp`] if the time is outside the range of
+/// [`DateTime<Utc>`].
+pub fn from_unsigned_millis(millis: u64) -> Result<DateTime<Utc>> {
+    i64::try_from(millis)
+        .ok()
+        .and_then(DateTime::from_timestamp_millis)
+        .ok_or(Error::InvalidTimestamp(millis as i64))
+}
+
+/// Converts a timestamp to unsigned milliseconds since the epoch, clamping times
+/// before the epoch to 0.
+pub fn to_unsigned_millis(time: DateTime<Utc>) -> u64 {
+    time.timestamp_millis().max(0) as u64
+}
+
+/// Returns the start of the bucket of length `step` containing `time`.
+pub fn align_down(time: DateTime<Utc>, step: Duration) -> DateTime<Utc> {
+    let step = step.num_milliseconds().max(1);
+    let millis = time.timestamp_millis();
+    DateTime::from_timestamp_millis(millis - millis.rem_euclid(step)).unwrap_or(time)
+}
+
+/// Returns the first start of a bucket of length `step` at or after `time`.
+pub fn align_up(time: DateTime<Utc>, step: Duration) -> DateTime<Utc> {
+    let step = step.num_milliseconds().max(1);
+    let millis = time.timestamp_millis();
+    DateTime::from_timestamp_millis(millis + (step - millis.rem_euclid(step)) % step)
+        .unwrap_or(time)
+}
+
+/// Returns the end time of a candle of length `step` opening at `start`, one
+/// millisecond before the next candle opens, like the `T` field of a kline.
+pub fn candle_end(start: DateTime<Utc>, step: Duration) -> DateTime<Utc> {
+    start + step - Duration::milliseconds(1)
+}
+
+/// Returns an iterator over the starts of the buckets of length `step` in
+/// `[start, end)`, beginning with the first aligned time at or after `start`.
+pub fn buckets(start: DateTime<Utc>, end: DateTime<Utc>, step: Duration) -> Buckets {
+    let step = step.max(Duration::milliseconds(1));
+    Buckets {
+        next: align_up(start, step),
+        end,
+        step,
+    }
+}
+
+/// An iterator over the bucket starts of a range, created by [`buckets`].
+#[derive(Debug, Clone)]
+pub struct Buckets {
+    next: DateTime<Utc>,
+    end: DateTime<Utc>,
+    step: Duration,
+}
+
+impl Iterator for Buckets {
+    type Item = DateTime<Utc>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next >= self.end {
+            return None;
+        }
+        let start = self.next;
+        self.next = start.checked_add_signed(self.step).unwrap_or(self.end);
+        Some(start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_millis_conversions() {
+        let time = from_millis(1_640_995_200_000).unwrap();
+        assert_eq!(from_unsigned_millis(1_640_995_200_000).unwrap(), time);
+        assert_eq!(to_unsigned_millis(time), 1_640_995_200_000);
+        assert_eq!(to_unsigned_millis(from_millis(-1).unwrap()), 0);
+        assert!(matches!(
+            from_millis(i64::MAX),
+            Err(Error::InvalidTimestamp(i64::MAX))
+        ));
+        assert!(from_unsigned_millis(u64::MAX).is_err());
+    }
+
+    #[test]
+    fn test_alignment() {
+        let step = Duration::minutes(15);
+        let time = from_millis(1_640_995_200_000 + 61_000).unwrap();
+        let start = from_millis(1_640_995_200_000).unwrap();
+        assert_eq!(align_down(time, step), start);
+        assert_eq!(align_up(time, step), start + step);
+        assert_eq!(align_down(start, step), start);
+        assert_eq!(align_up(start, step), start);
+        assert_eq!(
+            candle_end(start, step).timestamp_millis(),
+            1_640_996_099_999
+        );
+
+        // Times before the epoch round toward negative infinity.
+        let before = from_millis(-1).unwrap();
+        assert_eq!(align_down(before, step).timestamp_millis(), -900_000);
+    }
+
+    #[test]
+    fn test_buckets() {
+        let step = Duration::hours(1);
+        let start = from_millis(1_640_995_200_000).unwrap();
+        let starts: Vec<_> =
+            buckets(start + Duration::minutes(1), start + step * 3, step).collect();
+        assert_eq!(starts, vec![start + step, start + step * 2]);
+        assert_eq!(buckets(start, start, step).count(), 0);
+    }
+}
@@ -0,0 +1,165 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use env_logger::Builder;
+use opentrade_core::config::KlineStreamingConfig;
+use opentrade_core::ingest::maintenance::MaintenanceCalendar;
+use opentrade_core::ingest::quality::QualityReport;
+use opentrade_core::models::DEFAULT_DATASET;
+use opentrade_core::models::read_only::ReadOnlyPool;
+use opentrade_core::models::schema::check_schema_version;
+
+/// Command line arguments for the data-quality report binary.
+///
+/// This binary compiles a report of the coverage, missing candles, exchange-side
+/// gaps, quarantined rows and ingestion activity of the configured streams over
+/// the last `--window-hours`, and delivers it as Markdown to a webhook and/or
+/// writes it to files (`.html` files as HTML, others as Markdown). The streams and
+/// database are read from the environment like `streaming_klines` (see
+/// [`opentrade_core::config`]).
+///
+/// Candles within the maintenance windows of the `--exchange` configured in
+/// `MAINTENANCE_CALENDAR` or `MAINTENANCE_WINDOWS` (see
+/// [`opentrade_core::ingest::maintenance`]) are not reported as missing. The
+/// calendar is read again for every report.
+///
+/// Run it from cron, or pass `--repeat` to produce a report every window.
+///
+/// # Examples
+///
+/// ```bash
+/// # Write the daily report of the configured streams
+/// cargo run --bin quality_report -- -o quality.html -o quality.md
+///
+/// # Post a report to a chat webhook every day
+/// cargo run --bin quality_report -- --webhook https://hooks.example.com/T000/B000 --repeat
+/// ```
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct QualityReportArgs {
+    /// Write the report to this file. Can be repeated.
+    #[arg(short = 'o', long = "output")]
+    outputs: Vec<String>,
+
+    /// POST the report as JSON `{"text": "<markdown>"}` to this URL.
+    #[arg(long)]
+    webhook: Option<String>,
+
+    /// The length of the reported window, in hours.
+    #[arg(long, default_value_t = 24)]
+    window_hours: u32,
+
+    /// Keep running and produce a report at the end of every window.
+    #[arg(long)]
+    repeat: bool,
+
+    /// The dataset to report on (e.g., "prod", "research").
+    #[arg(long, default_value = DEFAULT_DATASET)]
+    dataset: String,
+
+    /// The exchange the streams are ingested from. Only its candles are reported
+    /// on, and its maintenance windows are not reported as gaps.
+    #[arg(long, default_value = "binance")]
+    exchange: String,
+
+    /// The profile of the config file to apply (e.g., "dev", "staging", "prod").
+    #[arg(long)]
+    profile: Option<String>,
+}
+
+/// Posts the Markdown report to a webhook.
+async fn post_webhook(client: &reqwest::Client, url: &str, report: &QualityReport) -> Result<()> {
+    client
+        .post(url)
+        .json(&serde_json::json!({ "text": report.to_markdown() }))
+        .send()
+        .await
+        .context("Failed to send the report to the webhook")?
+        .error_for_status()
+        .context("The webhook rejected the report")?;
+    Ok(())
+}
+
+/// Compiles one report and delivers it to every configured destination.
+async fn run_once(
+    args: &QualityReportArgs,
+    config: &KlineStreamingConfig,
+    pool: &ReadOnlyPool,
+    client: &reqwest::Client,
+) -> Result<()> {
+    let streams: Vec<(String, String)> = config
+        .streams
+        .iter()
+        .map(|stream| (stream.symbol.clone(), stream.interval.clone()))
+        .collect();
+    let report = QualityReport::build(
+        pool.pool(),
+        &streams,
+        &args.dataset,
+        &args.exchange,
+        chrono::Utc::now(),
+        chrono::Duration::hours(i64::from(args.window_hours)),
+    )
+    .await?
+    .excluding_maintenance(&MaintenanceCalendar::from_env()?, &args.exchange);
+    log::info!(
+        "Data-quality report: {} of {} streams have issues",
+        report.issues(),
+        report.streams.len()
+    );
+
+    for output in &args.outputs {
+        report.write(output)?;
+        log::info!("Wrote report to {}", output);
+    }
+    if let Some(url) = &args.webhook {
+        post_webhook(client, url, &report).await?;
+        log::info!("Sent report to the webhook");
+    }
+    if args.outputs.is_empty() && args.webhook.is_none() {
+        println!("{}", report.to_markdown());
+    }
+    Ok(())
+}
+
+/// Main entry point for the data-quality report binary.
+#[tokio::main]
+async fn main() {
+    Builder::from_default_env()
+        .filter(None, log::LevelFilter::Info)
+        .init();
+    let args = QualityReportArgs::parse();
+    let config = match KlineStreamingConfig::from_env(args.profile.as_deref()) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{:#}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let pool = ReadOnlyPool::connect(&config.database_url)
+        .await
+        .expect("Failed to connect to the database");
+    if let Err(e) = check_schema_version(pool.pool()).await {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+
+    let client = reqwest::Client::new();
+    let window = Duration::from_secs(u64::from(args.window_hours.max(1)) * 3600);
+    loop {
+        let result = run_once(&args, &config, &pool, &client).await;
+        if !args.repeat {
+            if let Err(e) = result {
+                eprintln!("{:#}", e);
+                std::process::exit(1);
+            }
+            break;
+        }
+        if let Err(e) = result {
+            log::error!("Failed to produce the data-quality report: {:#}", e);
+        }
+        tokio::time::sleep(window).await;
+    }
+}
@@ -8,7 +8,37 @@
 //! This binary serves as a placeholder for the main pipeline orchestration.
 //! For specific functionality, use the dedicated binaries:
 //! - `backfill_klines`: For historical kline data backfilling
+//! - `backfill_symbols`: For backfilling many symbols concurrently under a shared rate limit
+//! - `backfill_uniswap`: For building candles of Uniswap v3 pools from their swaps
+//! - `backfill_futures`: For backfilling Binance USDⓈ-M futures klines and funding rates
+//! - `backfill_coinbase`: For backfilling and streaming Coinbase Advanced Trade candles
+//! - `backfill_bybit`: For backfilling and streaming Bybit spot and perpetual candles
+//! - `backfill_okx`: For backfilling and streaming OKX spot and perpetual swap candles
+//! - `quota`: For showing the daily usage and remaining quota of each data source
+//! - `resume_backfill`: For resuming tracked backfill jobs after a crash or failure
 //! - `streaming_klines`: For real-time kline data streaming
+//! - `gaps`: For auditing stored kline data and exporting gap reports
+//! - `export_klines`: For exporting filtered candles and columns as CSV or JSON lines
+//! - `coverage`: For summarizing the stored kline data of each symbol
+//! - `replicate`: For copying stored kline data between databases
+//! - `share_dataset`: For sampled, noise-perturbed copies of stored data for sharing
+//! - `bench_upsert`: For comparing per-row and batched kline upserts
+//! - `loadtest`: For measuring sustained pipeline throughput under synthetic traffic
+//! - `restore_klines`: For idempotently replaying logged kline messages into storage
+//! - `chart`: For rendering stored candles as a PNG/SVG chart (requires the `plot` feature)
+//! - `quality_report`: For daily data-quality reports delivered to files or a webhook
+//! - `risk_metrics`: For historical volatility and return correlations across symbols
+//! - `tag_regimes`: For labeling stored candles with trend and volatility regimes
+//! - `streaming_futures_prices`: For streaming futures mark and index prices into storage
+//! - `streaming_order_books`: For maintaining local order books and storing their snapshots
+//! - `streaming_book_tickers`: For streaming best bid and ask quotes into storage
+//! - `streaming_liquidations`: For recording the liquidation orders of futures contracts
+//! - `snapshot_options`: For storing options marks, implied volatilities and greeks
+//! - `poll_open_interest`: For polling and backfilling the open interest of futures contracts
+//! - `watch_triggers`: For emitting events when configured conditions hold on live candles
+//! - `backtest_triggers`: For listing the historical firings of trigger definitions
+//! - `aggregate_klines`: For deriving and storing higher-timeframe candles from 1m candles
+//! - `watchlist`: For managing the named symbol sets that commands accept with `--watchlist`
 
 /// Main entry point for the opentrade-pipeline application.
 ///